@@ -0,0 +1,25 @@
+//! `cargo bench` entry point. The crate is a binary with no library to link
+//! a harness against, so the backend is compiled in here directly and timed
+//! in-process on generated files (see `backend::bench`), each operation run
+//! several times for its min, median and mean.
+//!
+//! Row counts come from `CSVIT_BENCH_ROWS`, comma separated; by default
+//! 1M and 10M rows. `CSVIT_BENCH_SAMPLES` sets the runs per operation.
+
+// Only the bench module is used; the rest of the backend comes along with it
+#[allow(dead_code, unused_imports)]
+#[path = "../src/backend/mod.rs"]
+mod backend;
+
+fn main() {
+    let sizes = std::env::var("CSVIT_BENCH_ROWS").unwrap_or_else(|_| "1000000,10000000".to_string());
+    let samples = std::env::var("CSVIT_BENCH_SAMPLES").ok().and_then(|s| s.parse().ok()).unwrap_or(5);
+    for rows in sizes.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let rows: usize = rows.parse().expect("CSVIT_BENCH_ROWS holds row counts");
+        println!("{} rows, {} sample(s) each", rows, samples);
+        let results = backend::bench::run_in_temp_dir(rows, samples).expect("benchmark failed");
+        for result in results {
+            println!("  {}", result);
+        }
+    }
+}
@@ -0,0 +1,537 @@
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::ops::Range;
+use std::path::Path;
+
+use super::jobs::Progress;
+use super::sort::compare_values;
+
+/// A summary of a column's values within a group
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Aggregate {
+    /// Rows in the group
+    Count,
+    /// Rows with a non-empty value
+    CountNonEmpty,
+    Sum,
+    Mean,
+    Min,
+    Max,
+}
+
+impl Aggregate {
+    pub const ALL: [Aggregate; 6] = [
+        Aggregate::Count,
+        Aggregate::CountNonEmpty,
+        Aggregate::Sum,
+        Aggregate::Mean,
+        Aggregate::Min,
+        Aggregate::Max,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Aggregate::Count => "count",
+            Aggregate::CountNonEmpty => "count non-empty",
+            Aggregate::Sum => "sum",
+            Aggregate::Mean => "mean",
+            Aggregate::Min => "min",
+            Aggregate::Max => "max",
+        }
+    }
+}
+
+/// Running totals for one column of one group; values are added one at a
+/// time, so any number of rows fits in constant memory
+#[derive(Clone, Debug, Default)]
+pub struct Accumulator {
+    count: usize,
+    non_empty: usize,
+    numeric: usize,
+    sum: f64,
+    min: Option<f64>,
+    max: Option<f64>,
+}
+
+impl Accumulator {
+    pub fn add(&mut self, value: &str) {
+        self.count += 1;
+        let value = value.trim();
+        if value.is_empty() {
+            return;
+        }
+        self.non_empty += 1;
+        if let Ok(x) = value.parse::<f64>()
+            && x.is_finite()
+        {
+            self.add_number(x);
+        }
+    }
+
+    /// Count a number that's already parsed, as a non-empty value
+    fn add_number(&mut self, x: f64) {
+        self.numeric += 1;
+        self.sum += x;
+        self.min = Some(self.min.map_or(x, |m| m.min(x)));
+        self.max = Some(self.max.map_or(x, |m| m.max(x)));
+    }
+
+    /// The aggregate's value, None when there are no numbers to summarize
+    pub fn value(&self, aggregate: Aggregate) -> Option<f64> {
+        match aggregate {
+            Aggregate::Count => Some(self.count as f64),
+            Aggregate::CountNonEmpty => Some(self.non_empty as f64),
+            Aggregate::Sum => (self.numeric > 0).then_some(self.sum),
+            Aggregate::Mean => (self.numeric > 0).then(|| self.sum / self.numeric as f64),
+            Aggregate::Min => self.min,
+            Aggregate::Max => self.max,
+        }
+    }
+}
+
+/// Quick figures for a block of selected cells: the `Aggregate`s plus how
+/// many different non-empty values the block holds
+#[derive(Clone, Debug, Default)]
+pub struct SelectionStats {
+    pub totals: Accumulator,
+    pub distinct: usize,
+}
+
+pub fn selection_stats<'a>(values: impl IntoIterator<Item = &'a str>) -> SelectionStats {
+    let mut counter = SelectionCounter::default();
+    for value in values {
+        counter.add(value);
+    }
+    counter.finish()
+}
+
+/// Builds `SelectionStats` one value at a time, so a large selection never
+/// has to be collected first
+#[derive(Default)]
+pub struct SelectionCounter {
+    totals: Accumulator,
+    seen: HashSet<String>,
+}
+
+impl SelectionCounter {
+    pub fn add(&mut self, value: &str) {
+        self.totals.add(value);
+        let value = value.trim();
+        if !value.is_empty() && !self.seen.contains(value) {
+            self.seen.insert(value.to_string());
+        }
+    }
+
+    pub fn finish(self) -> SelectionStats {
+        SelectionStats { totals: self.totals, distinct: self.seen.len() }
+    }
+}
+
+/// A number as a cell: whole numbers without a fraction, others as short as they round-trip
+pub fn format_number(x: f64) -> String {
+    if x.fract() == 0.0 && x.abs() < 1e15 {
+        format!("{}", x as i64)
+    } else {
+        x.to_string()
+    }
+}
+
+/// Group rows by the values of `keys` and summarize columns per group
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GroupBySpec {
+    pub keys: Vec<usize>,
+    /// (column, aggregate)
+    pub aggregates: Vec<(usize, Aggregate)>,
+}
+
+/// Groups kept by `group_by`; rows that would start another are skipped
+pub const MAX_GROUPS: usize = 100_000;
+
+/// One row per group: the key values, then each aggregate
+#[derive(Clone, Debug, Default)]
+pub struct GroupSummary {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    /// Rows left out because there were already `MAX_GROUPS` groups
+    pub skipped_rows: usize,
+}
+
+impl GroupSummary {
+    pub fn write_csv(&self, path: &Path) -> Result<()> {
+        let mut writer = csv::Writer::from_path(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        writer.write_record(&self.headers)?;
+        for row in &self.rows {
+            writer.write_record(row)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Stream `rows` once, keeping one set of accumulators per group. Groups
+/// come out in the order they first appear, up to `MAX_GROUPS` of them.
+pub fn group_by(
+    spec: &GroupBySpec,
+    headers: &[String],
+    rows: Range<usize>,
+    row_at: impl Fn(usize) -> Vec<String>,
+    progress: &Progress,
+) -> GroupSummary {
+    let header = |c: usize| headers.get(c).cloned().unwrap_or_else(|| format!("Column {}", c + 1));
+    let mut summary = GroupSummary {
+        headers: spec.keys.iter().map(|&c| header(c))
+            .chain(spec.aggregates.iter().map(|&(c, aggregate)| match aggregate {
+                Aggregate::Count => "count".to_string(),
+                _ => format!("{} {}", aggregate.name(), header(c)),
+            }))
+            .collect(),
+        rows: Vec::new(),
+        skipped_rows: 0,
+    };
+
+    progress.set_total(rows.len());
+    let start = rows.start;
+    let mut groups: HashMap<Vec<String>, usize> = HashMap::new();
+    let mut accumulators: Vec<(Vec<String>, Vec<Accumulator>)> = Vec::new();
+    for row in rows {
+        if (row - start).is_multiple_of(10_000) {
+            if progress.is_cancelled() {
+                break;
+            }
+            progress.set_done(row - start);
+        }
+        let values = row_at(row);
+        let cell = |c: usize| values.get(c).map_or("", |v| v.trim());
+        let key: Vec<String> = spec.keys.iter().map(|&c| cell(c).to_string()).collect();
+        let index = match groups.get(&key) {
+            Some(&index) => index,
+            None if accumulators.len() >= MAX_GROUPS => {
+                summary.skipped_rows += 1;
+                continue;
+            }
+            None => {
+                groups.insert(key.clone(), accumulators.len());
+                accumulators.push((key, vec![Accumulator::default(); spec.aggregates.len()]));
+                accumulators.len() - 1
+            }
+        };
+        for (acc, &(c, _)) in accumulators[index].1.iter_mut().zip(&spec.aggregates) {
+            acc.add(cell(c));
+        }
+    }
+
+    summary.rows = accumulators
+        .into_iter()
+        .map(|(key, accs)| {
+            key.into_iter()
+                .chain(accs.iter().zip(&spec.aggregates).map(|(acc, &(_, aggregate))| {
+                    acc.value(aggregate).map(format_number).unwrap_or_default()
+                }))
+                .collect()
+        })
+        .collect();
+    summary
+}
+
+/// Span of time that points are resampled into
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimeBucket {
+    Minute,
+    Hour,
+    Day,
+}
+
+impl TimeBucket {
+    pub const ALL: [TimeBucket; 3] = [TimeBucket::Minute, TimeBucket::Hour, TimeBucket::Day];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimeBucket::Minute => "per minute",
+            TimeBucket::Hour => "per hour",
+            TimeBucket::Day => "per day",
+        }
+    }
+
+    pub fn seconds(&self) -> i64 {
+        match self {
+            TimeBucket::Minute => 60,
+            TimeBucket::Hour => 3_600,
+            TimeBucket::Day => 86_400,
+        }
+    }
+}
+
+/// Summarize `points` (x in Unix seconds) per `bucket`: one point per bucket
+/// holding any, at the bucket's start, in time order
+pub fn resample(points: &[[f64; 2]], bucket: TimeBucket, aggregate: Aggregate) -> Vec<[f64; 2]> {
+    let mut resampler = Resampler::new(bucket);
+    for &[x, y] in points {
+        resampler.add(x, y);
+    }
+    resampler.finish(aggregate)
+}
+
+/// Builds `resample`'s summary one point at a time, so a whole column can be
+/// resampled without holding its points
+pub struct Resampler {
+    size: i64,
+    buckets: BTreeMap<i64, Accumulator>,
+}
+
+impl Resampler {
+    pub fn new(bucket: TimeBucket) -> Self {
+        Self { size: bucket.seconds(), buckets: BTreeMap::new() }
+    }
+
+    pub fn add(&mut self, x: f64, y: f64) {
+        let acc = self.buckets.entry((x as i64).div_euclid(self.size) * self.size).or_default();
+        acc.count += 1;
+        acc.non_empty += 1;
+        acc.add_number(y);
+    }
+
+    pub fn finish(self, aggregate: Aggregate) -> Vec<[f64; 2]> {
+        self.buckets.into_iter()
+            .filter_map(|(start, acc)| acc.value(aggregate).map(|y| [start as f64, y]))
+            .collect()
+    }
+}
+
+/// A calculation over a moving window of rows, for smoothing time series
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rolling {
+    Mean,
+    Sum,
+    Min,
+    Max,
+    /// Running total from the first row; the window size doesn't apply
+    CumulativeSum,
+}
+
+impl Rolling {
+    pub const ALL: [Rolling; 5] = [Rolling::Mean, Rolling::Sum, Rolling::Min, Rolling::Max, Rolling::CumulativeSum];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rolling::Mean => "rolling mean",
+            Rolling::Sum => "rolling sum",
+            Rolling::Min => "rolling min",
+            Rolling::Max => "rolling max",
+            Rolling::CumulativeSum => "cumulative sum",
+        }
+    }
+}
+
+/// `function` over `values`, taken in the order of `order` (row order when
+/// None) and returned in row order. A row's window is it and the `window - 1`
+/// rows before it; rows before the first full window are left empty.
+/// Non-numeric values take up their place in the window but are skipped,
+/// and give an empty result themselves. Each row costs O(1) on average
+/// whatever the window size: sums are kept running and min/max come from a
+/// deque of candidates.
+pub fn rolling_column(values: &[String], order: Option<&[String]>, function: Rolling, window: usize, progress: &Progress) -> Vec<String> {
+    let window = window.max(1);
+    let mut sequence: Vec<usize> = (0..values.len()).collect();
+    if let Some(order) = order {
+        // Stable, so ties keep row order
+        sequence.sort_by(|&a, &b| compare_values(order.get(a).map_or("", String::as_str), order.get(b).map_or("", String::as_str)));
+    }
+
+    progress.set_total(sequence.len());
+    let mut out = vec![String::new(); values.len()];
+    let numbers: Vec<Option<f64>> = sequence.iter()
+        .map(|&row| values[row].trim().parse::<f64>().ok().filter(|x| x.is_finite()))
+        .collect();
+    // Sum and count of the numbers in the window
+    let (mut sum, mut count) = (0.0, 0usize);
+    // Positions in `numbers` whose values could still be the window's min
+    // (or max), oldest first; their values only ever get worse toward the back
+    let mut candidates: VecDeque<usize> = VecDeque::new();
+    let better = |a: f64, b: f64| if function == Rolling::Min { a <= b } else { a >= b };
+    for (seen, &row) in sequence.iter().enumerate() {
+        if seen.is_multiple_of(10_000) {
+            if progress.is_cancelled() {
+                break;
+            }
+            progress.set_done(seen);
+        }
+        let x = numbers[seen];
+        match function {
+            Rolling::CumulativeSum => {
+                if let Some(x) = x {
+                    sum += x;
+                    out[row] = format_number(sum);
+                }
+                continue;
+            }
+            Rolling::Mean | Rolling::Sum => {
+                if let Some(x) = x {
+                    sum += x;
+                    count += 1;
+                }
+                if let Some(leaving) = seen.checked_sub(window).and_then(|i| numbers[i]) {
+                    sum -= leaving;
+                    count -= 1;
+                }
+                if count == 0 {
+                    // Start again from exactly zero so rounding doesn't pile up
+                    sum = 0.0;
+                }
+            }
+            Rolling::Min | Rolling::Max => {
+                if let Some(x) = x {
+                    while candidates.back().is_some_and(|&i| numbers[i].is_some_and(|y| better(x, y))) {
+                        candidates.pop_back();
+                    }
+                    candidates.push_back(seen);
+                }
+                while candidates.front().is_some_and(|&i| i + window <= seen) {
+                    candidates.pop_front();
+                }
+            }
+        }
+        if x.is_none() || seen + 1 < window {
+            continue;
+        }
+        let result = match function {
+            Rolling::Mean => sum / count as f64,
+            Rolling::Sum => sum,
+            Rolling::Min | Rolling::Max => candidates.front().and_then(|&i| numbers[i]).unwrap_or_default(),
+            Rolling::CumulativeSum => unreachable!(),
+        };
+        out[row] = format_number(result);
+    }
+    out
+}
+
+/// How a derived column compares two numeric columns, a and b
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Combine {
+    Difference,
+    Ratio,
+    /// Change from b to a, in percent of b
+    PercentChange,
+}
+
+impl Combine {
+    pub const ALL: [Combine; 3] = [Combine::Difference, Combine::Ratio, Combine::PercentChange];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Combine::Difference => "difference (a − b)",
+            Combine::Ratio => "ratio (a ÷ b)",
+            Combine::PercentChange => "% change from b to a",
+        }
+    }
+
+    /// Header of the new column, from the headers of a and b
+    pub fn header(&self, a: &str, b: &str) -> String {
+        match self {
+            Combine::Difference => format!("{} - {}", a, b),
+            Combine::Ratio => format!("{} / {}", a, b),
+            Combine::PercentChange => format!("{} vs {} %", a, b),
+        }
+    }
+}
+
+/// `combine` of each row's pair of values. A row where either value isn't a
+/// number gets an empty result, as does dividing by zero.
+pub fn combine_columns(a: &[String], b: &[String], combine: Combine) -> Vec<String> {
+    let number = |v: Option<&String>| v.and_then(|v| v.trim().parse::<f64>().ok()).filter(|x| x.is_finite());
+    (0..a.len().max(b.len()))
+        .map(|row| {
+            let (Some(x), Some(y)) = (number(a.get(row)), number(b.get(row))) else {
+                return String::new();
+            };
+            let result = match combine {
+                Combine::Difference => x - y,
+                Combine::Ratio => x / y,
+                Combine::PercentChange => (x - y) / y * 100.0,
+            };
+            if result.is_finite() { format_number(result) } else { String::new() }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_by() {
+        let headers = ["region", "product", "sales"].map(String::from);
+        let rows = [["north", "a", "10"], ["south", "a", "5"], ["north", "b", "2.5"], ["north", "a", ""], ["south", "b", "x"]];
+        let row_at = |r: usize| rows[r].iter().map(|v| v.to_string()).collect();
+        let spec = GroupBySpec {
+            keys: vec![0],
+            aggregates: vec![(2, Aggregate::Count), (2, Aggregate::Sum), (2, Aggregate::Mean), (2, Aggregate::Max)],
+        };
+        let summary = group_by(&spec, &headers, 0..rows.len(), row_at, &Progress::default());
+
+        assert_eq!(summary.headers, ["region", "count", "sum sales", "mean sales", "max sales"]);
+        assert_eq!(summary.rows, [["north", "3", "12.5", "6.25", "10"], ["south", "2", "5", "5", "5"]]);
+        assert_eq!(summary.skipped_rows, 0);
+    }
+
+    #[test]
+    fn test_group_by_stops_adding_groups_at_the_limit() {
+        let spec = GroupBySpec { keys: vec![0], aggregates: vec![(0, Aggregate::Count)] };
+        let row_at = |r: usize| vec![(r % (MAX_GROUPS + 2)).to_string()];
+        let summary = group_by(&spec, &["id".to_string()], 0..MAX_GROUPS + 5, row_at, &Progress::default());
+
+        assert_eq!(summary.rows.len(), MAX_GROUPS);
+        // Rows MAX_GROUPS and MAX_GROUPS + 1 are new keys; the last three repeat 0, 1 and 2
+        assert_eq!(summary.skipped_rows, 2);
+        assert_eq!(summary.rows[0], ["0", "2"]);
+    }
+
+    #[test]
+    fn test_selection_stats() {
+        let stats = selection_stats(["4", " 4", "", "x", "-1.5"]);
+        assert_eq!(stats.totals.value(Aggregate::Count), Some(5.0));
+        assert_eq!(stats.totals.value(Aggregate::CountNonEmpty), Some(4.0));
+        assert_eq!(stats.totals.value(Aggregate::Sum), Some(6.5));
+        assert_eq!(stats.totals.value(Aggregate::Min), Some(-1.5));
+        assert_eq!(stats.distinct, 3);
+        assert_eq!(selection_stats(["a"]).totals.value(Aggregate::Mean), None);
+    }
+
+    #[test]
+    fn test_resample() {
+        // 08:00:10, 08:00:50, 08:01:30 and the next day
+        let points = [[28_810.0, 1.0], [28_850.0, 3.0], [28_890.0, 10.0], [115_200.0, 5.0]];
+        assert_eq!(resample(&points, TimeBucket::Minute, Aggregate::Mean), [[28_800.0, 2.0], [28_860.0, 10.0], [115_200.0, 5.0]]);
+        assert_eq!(resample(&points, TimeBucket::Day, Aggregate::Sum), [[0.0, 14.0], [86_400.0, 5.0]]);
+        assert_eq!(resample(&points, TimeBucket::Hour, Aggregate::Count), [[28_800.0, 3.0], [115_200.0, 1.0]]);
+    }
+
+    #[test]
+    fn test_rolling_column() {
+        let values = ["1", "2", "3", "x", "5"].map(String::from);
+        let progress = Progress::default();
+        assert_eq!(rolling_column(&values, None, Rolling::Mean, 2, &progress), ["", "1.5", "2.5", "", "5"]);
+        assert_eq!(rolling_column(&values, None, Rolling::Max, 3, &progress), ["", "", "3", "", "5"]);
+        assert_eq!(rolling_column(&values, None, Rolling::CumulativeSum, 1, &progress), ["1", "3", "6", "", "11"]);
+
+        // Min over a window that slides past earlier, smaller values
+        let values = ["4", "1", "3", "5", "2", "6"].map(String::from);
+        assert_eq!(rolling_column(&values, None, Rolling::Min, 3, &progress), ["", "", "1", "1", "2", "2"]);
+        assert_eq!(rolling_column(&values, None, Rolling::Max, 2, &progress), ["", "4", "3", "5", "5", "6"]);
+        assert_eq!(rolling_column(&values, None, Rolling::Sum, 3, &progress), ["", "", "8", "9", "10", "13"]);
+
+        // Ordered by date, the result still lines up with the original rows
+        let dates = ["2024-01-03", "2024-01-01", "2024-01-02"].map(String::from);
+        let values = ["30", "10", "20"].map(String::from);
+        assert_eq!(rolling_column(&values, Some(&dates), Rolling::Sum, 2, &progress), ["50", "", "30"]);
+    }
+
+    #[test]
+    fn test_combine_columns() {
+        let a = ["12", " 5 ", "", "n/a", "3"].map(String::from);
+        let b = ["10", "0", "1", "2", "4"].map(String::from);
+        assert_eq!(combine_columns(&a, &b, Combine::Difference), ["2", "5", "", "", "-1"]);
+        assert_eq!(combine_columns(&a, &b, Combine::Ratio), ["1.2", "", "", "", "0.75"]);
+        assert_eq!(combine_columns(&a, &b, Combine::PercentChange), ["20", "", "", "", "-25"]);
+        assert_eq!(Combine::Ratio.header("revenue", "cost"), "revenue / cost");
+    }
+}
@@ -1,5 +1,14 @@
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use super::jobs::Progress;
 
 /// Inferred data type for a column
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -25,14 +34,107 @@ impl InferredType {
             InferredType::Mixed => "Mixed",
         }
     }
+
+    /// Compact marker shown next to column headers
+    pub fn badge(&self) -> &'static str {
+        match self {
+            InferredType::Integer => "123",
+            InferredType::Float => "1.0",
+            InferredType::Boolean => "T",
+            InferredType::Date => "📅",
+            InferredType::Text => "abc",
+            InferredType::Empty => "∅",
+            InferredType::Mixed => "?",
+        }
+    }
+
+    /// Whether a single value of type `value` counts towards this column type
+    fn accepts(&self, value: &InferredType) -> bool {
+        match self {
+            InferredType::Float => matches!(value, InferredType::Integer | InferredType::Float),
+            other => other == value,
+        }
+    }
+}
+
+/// What a text column's values mean, beyond their storage type
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum SemanticType {
+    Email,
+    Url,
+    Uuid,
+    Ipv4,
+    Ipv6,
+    Phone,
+}
+
+static EMAIL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}$").unwrap());
+static URL: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(?i)(https?|ftp)://[^\s/?#]+\.[^\s/?#]+([/?#]\S*)?$").unwrap());
+static UUID: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?i)\{?[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}\}?$").unwrap()
+});
+/// Digits with at least one separator or a leading +, so plain integers don't count
+static PHONE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(\+\d[\d ().-]{6,18}\d|\(?\d{2,4}\)?[ .-]\d[\d ().-]{4,14}\d)$").unwrap());
+
+impl SemanticType {
+    pub const ALL: [SemanticType; 6] = [
+        SemanticType::Email,
+        SemanticType::Url,
+        SemanticType::Uuid,
+        SemanticType::Ipv4,
+        SemanticType::Ipv6,
+        SemanticType::Phone,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SemanticType::Email => "Email",
+            SemanticType::Url => "URL",
+            SemanticType::Uuid => "UUID",
+            SemanticType::Ipv4 => "IPv4",
+            SemanticType::Ipv6 => "IPv6",
+            SemanticType::Phone => "Phone",
+        }
+    }
+
+    /// Whether a (trimmed) value is valid for this type
+    pub fn matches(&self, val: &str) -> bool {
+        match self {
+            SemanticType::Email => EMAIL.is_match(val),
+            SemanticType::Url => URL.is_match(val),
+            SemanticType::Uuid => UUID.is_match(val),
+            SemanticType::Ipv4 => val.parse::<std::net::Ipv4Addr>().is_ok(),
+            SemanticType::Ipv6 => val.parse::<std::net::Ipv6Addr>().is_ok(),
+            SemanticType::Phone => PHONE.is_match(val),
+        }
+    }
+
+    /// The type most (over 80%) of the values have, if any
+    fn detect(values: &[&str]) -> Option<SemanticType> {
+        if values.is_empty() {
+            return None;
+        }
+        Self::ALL.into_iter().find(|t| {
+            let matching = values.iter().filter(|v| t.matches(v)).count();
+            matching as f64 / values.len() as f64 > 0.8
+        })
+    }
 }
 
 /// Profile/statistics for a single column
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize)]
 pub struct ColumnProfile {
     pub column_index: usize,
     pub header: String,
     pub data_type: Option<InferredType>,
+    /// Email, URL, etc. when most non-null values are one
+    pub semantic_type: Option<SemanticType>,
+    /// Non-null values in the profiled rows that aren't a valid `semantic_type`
+    pub semantic_invalid: usize,
+    /// Share of non-null values matching `data_type`, in [0, 1]
+    pub type_confidence: f64,
     pub total_count: usize,
     pub null_count: usize,
     pub unique_count: usize,
@@ -41,9 +143,13 @@ pub struct ColumnProfile {
     pub max: Option<f64>,
     pub sum: Option<f64>,
     pub mean: Option<f64>,
+    pub median: Option<f64>,
     pub std_dev: Option<f64>,
     // Categorical stats (top 5 values)
     pub top_values: Vec<(String, usize)>,
+    /// Display length in characters (longest line) that 95% of values fit in
+    pub length_p95: usize,
+    pub max_length: usize,
 }
 
 impl ColumnProfile {
@@ -56,6 +162,138 @@ impl ColumnProfile {
     }
 }
 
+/// Values in a column that aren't valid for its semantic type
+#[derive(Clone, Debug)]
+pub struct InvalidValues {
+    pub column_index: usize,
+    pub semantic_type: SemanticType,
+    /// (row, value), up to `MAX_LISTED_INVALID`
+    pub values: Vec<(usize, String)>,
+    pub count: usize,
+}
+
+/// Invalid values listed individually; the rest are only counted
+const MAX_LISTED_INVALID: usize = 1_000;
+
+/// Every distinct value of a column with its number of occurrences
+#[derive(Clone, Debug, Default)]
+pub struct DistinctValues {
+    pub column_index: usize,
+    /// Most frequent first, ties in value order
+    pub values: Vec<(String, usize)>,
+    /// More than `MAX_DISTINCT_VALUES` distinct values: later new values
+    /// weren't counted, so `values` covers only the first ones seen
+    pub too_many: bool,
+    pub rows_scanned: usize,
+}
+
+/// Distinct values kept before a column counts as having too many to list
+pub const MAX_DISTINCT_VALUES: usize = 100_000;
+
+impl DistinctValues {
+    pub fn write_csv(&self, header: &str, path: &Path) -> Result<()> {
+        let mut writer = csv::Writer::from_path(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        writer.write_record([header, "count"])?;
+        for (value, count) in &self.values {
+            writer.write_record([value.as_str(), &count.to_string()])?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Points in a header sparkline
+pub const SPARKLINE_POINTS: usize = 40;
+
+/// Rows read per sparkline point, evenly spread over its share of the file
+const SPARKLINE_SAMPLES_PER_POINT: usize = 5;
+
+/// Numbers read from a spread of rows, one list per column, aligned by row;
+/// None where a row's value isn't a number
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ColumnSample {
+    pub columns: Vec<usize>,
+    pub values: Vec<Vec<Option<f64>>>,
+}
+
+impl ColumnSample {
+    /// Rows where both columns (by position in `columns`) have a number
+    pub fn pairs(&self, a: usize, b: usize) -> Vec<[f64; 2]> {
+        self.values[a].iter()
+            .zip(&self.values[b])
+            .filter_map(|(x, y)| Some([(*x)?, (*y)?]))
+            .collect()
+    }
+
+    /// Pearson correlation of two columns over the rows where both have a number
+    pub fn correlation(&self, a: usize, b: usize) -> Option<f64> {
+        let pairs = self.pairs(a, b);
+        if pairs.len() < 2 {
+            return None;
+        }
+        let n = pairs.len() as f64;
+        let (mean_x, mean_y) = (pairs.iter().map(|p| p[0]).sum::<f64>() / n, pairs.iter().map(|p| p[1]).sum::<f64>() / n);
+        let (mut cov, mut var_x, mut var_y) = (0.0, 0.0, 0.0);
+        for [x, y] in pairs {
+            cov += (x - mean_x) * (y - mean_y);
+            var_x += (x - mean_x).powi(2);
+            var_y += (y - mean_y).powi(2);
+        }
+        (var_x > 0.0 && var_y > 0.0).then(|| cov / (var_x * var_y).sqrt())
+    }
+}
+
+/// How many values fall in each of a run of equal-width bins
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Histogram {
+    /// Lower edge of the first bin
+    pub start: f64,
+    pub width: f64,
+    pub counts: Vec<usize>,
+}
+
+impl Histogram {
+    /// Middle of bin `i`
+    pub fn center(&self, i: usize) -> f64 {
+        self.start + self.width * (i as f64 + 0.5)
+    }
+}
+
+/// Values that occur more than once within a column
+#[derive(Clone, Debug, Default)]
+pub struct DuplicateReport {
+    pub column_index: usize,
+    /// Each repeated value with its number of occurrences, most frequent first
+    pub values: Vec<(String, usize)>,
+    /// Rows holding a repeat, i.e. every occurrence after the first
+    pub repeat_rows: Vec<usize>,
+    duplicated: HashSet<String>,
+}
+
+impl DuplicateReport {
+    pub fn is_duplicate(&self, value: &str) -> bool {
+        self.duplicated.contains(value.trim())
+    }
+}
+
+/// Pairs of columns are only tried among this many leading columns,
+/// since every pair keeps its own set of seen values
+const MAX_KEY_PAIR_COLUMNS: usize = 8;
+
+/// Value hashes held at once while looking for keys, a couple of hundred MB
+pub const MAX_KEY_HASHES: usize = 12_000_000;
+
+/// Columns and column pairs that could serve as a primary key
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct KeyCandidates {
+    pub keys: Vec<Vec<usize>>,
+    /// Pairs were given up on to stay under `MAX_KEY_HASHES`
+    pub pairs_skipped: bool,
+    /// Rows checked. When even single columns went over `MAX_KEY_HASHES`
+    /// this falls short of the table, and the keys are only unique that far.
+    pub rows_checked: usize,
+}
+
 /// Analyzer that profiles CSV columns
 pub struct ColumnAnalyzer;
 
@@ -75,6 +313,7 @@ impl ColumnAnalyzer {
 
         if values.is_empty() {
             profile.data_type = Some(InferredType::Empty);
+            profile.type_confidence = 1.0;
             return profile;
         }
 
@@ -84,7 +323,7 @@ impl ColumnAnalyzer {
 
         for val in values {
             let trimmed = val.trim();
-            if trimmed.is_empty() || trimmed.eq_ignore_ascii_case("null") || trimmed.eq_ignore_ascii_case("na") || trimmed.eq_ignore_ascii_case("n/a") {
+            if Self::is_null_like(trimmed) {
                 profile.null_count += 1;
             } else {
                 non_null_values.push(trimmed);
@@ -94,14 +333,37 @@ impl ColumnAnalyzer {
 
         profile.unique_count = value_counts.len();
 
+        // Lengths as displayed, for sizing columns without letting outliers decide
+        let mut lengths: Vec<usize> = values
+            .iter()
+            .map(|v| v.lines().map(|line| line.chars().count()).max().unwrap_or(0))
+            .collect();
+        lengths.sort_unstable();
+        profile.length_p95 = lengths[(lengths.len() - 1) * 95 / 100];
+        profile.max_length = lengths[lengths.len() - 1];
+
         // Top values
         let mut top: Vec<(String, usize)> = value_counts.into_iter().collect();
-        top.sort_by(|a, b| b.1.cmp(&a.1));
+        top.sort_by_key(|v| std::cmp::Reverse(v.1));
         profile.top_values = top.into_iter().take(5).collect();
 
         // Infer type and compute stats
         let (inferred_type, numeric_values) = Self::infer_type(&non_null_values);
+        profile.type_confidence = if non_null_values.is_empty() {
+            1.0
+        } else {
+            let matching = non_null_values.iter()
+                .filter(|v| inferred_type.accepts(&Self::infer_value_type(v)))
+                .count();
+            matching as f64 / non_null_values.len() as f64
+        };
         profile.data_type = Some(inferred_type.clone());
+        if matches!(inferred_type, InferredType::Text | InferredType::Mixed) {
+            profile.semantic_type = SemanticType::detect(&non_null_values);
+            if let Some(semantic) = profile.semantic_type {
+                profile.semantic_invalid = non_null_values.iter().filter(|v| !semantic.matches(v)).count();
+            }
+        }
 
         // Compute numeric stats if applicable
         if !numeric_values.is_empty() {
@@ -114,16 +376,330 @@ impl ColumnAnalyzer {
                 .sum::<f64>() / count;
             let std_dev = variance.sqrt();
 
+            let mut sorted = numeric_values.clone();
+            sorted.sort_by(f64::total_cmp);
+            let middle = sorted.len() / 2;
+            let median = if sorted.len().is_multiple_of(2) { (sorted[middle - 1] + sorted[middle]) / 2.0 } else { sorted[middle] };
+
             profile.min = numeric_values.iter().cloned().reduce(f64::min);
             profile.max = numeric_values.iter().cloned().reduce(f64::max);
             profile.sum = Some(sum);
             profile.mean = Some(mean);
+            profile.median = Some(median);
             profile.std_dev = Some(std_dev);
         }
 
         profile
     }
 
+    /// Scan `total` rows of a column for repeated values. Empty and null-like
+    /// values are not considered duplicates of each other.
+    pub fn find_duplicates(
+        col_index: usize,
+        total: usize,
+        value_at: impl Fn(usize) -> String,
+        progress: &Progress,
+    ) -> DuplicateReport {
+        progress.set_total(total);
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        let mut repeat_rows = Vec::new();
+        for row in 0..total {
+            if row % 10_000 == 0 {
+                if progress.is_cancelled() {
+                    break;
+                }
+                progress.set_done(row);
+            }
+            let value = value_at(row);
+            let value = value.trim();
+            if Self::is_null_like(value) {
+                continue;
+            }
+            let count = counts.entry(value.to_string()).or_insert(0);
+            *count += 1;
+            if *count > 1 {
+                repeat_rows.push(row);
+            }
+        }
+
+        let mut values: Vec<(String, usize)> = counts.into_iter().filter(|(_, n)| *n > 1).collect();
+        values.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        DuplicateReport {
+            column_index: col_index,
+            duplicated: values.iter().map(|(v, _)| v.clone()).collect(),
+            values,
+            repeat_rows,
+        }
+    }
+
+    /// Count each distinct (trimmed) value in `rows` of a column, streaming,
+    /// up to `MAX_DISTINCT_VALUES` of them
+    pub fn distinct_values(
+        col_index: usize,
+        rows: Range<usize>,
+        value_at: impl Fn(usize) -> String,
+        progress: &Progress,
+    ) -> DistinctValues {
+        progress.set_total(rows.len());
+        let start = rows.start;
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        let mut result = DistinctValues { column_index: col_index, ..Default::default() };
+        for row in rows {
+            if (row - start).is_multiple_of(10_000) {
+                if progress.is_cancelled() {
+                    break;
+                }
+                progress.set_done(row - start);
+            }
+            result.rows_scanned += 1;
+            let value = value_at(row);
+            let value = value.trim();
+            if let Some(count) = counts.get_mut(value) {
+                *count += 1;
+            } else if counts.len() < MAX_DISTINCT_VALUES {
+                counts.insert(value.to_string(), 1);
+            } else {
+                result.too_many = true;
+            }
+        }
+        result.values = counts.into_iter().collect();
+        result.values.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        result
+    }
+
+    /// Spread `values` over `bins` equal bins from the lowest to the highest.
+    /// When they're all the same they land in the middle of a unit-wide range.
+    pub fn histogram(values: &[f64], bins: usize) -> Histogram {
+        let bins = bins.max(1);
+        let (min, max) = values.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &x| (lo.min(x), hi.max(x)));
+        if min > max {
+            return Histogram { start: 0.0, width: 1.0 / bins as f64, counts: vec![0; bins] };
+        }
+        let (start, span) = if max > min { (min, max - min) } else { (min - 0.5, 1.0) };
+        let width = span / bins as f64;
+        let mut counts = vec![0; bins];
+        for &x in values {
+            counts[(((x - start) / width) as usize).min(bins - 1)] += 1;
+        }
+        Histogram { start, width, counts }
+    }
+
+    /// Up to `samples` rows spread evenly over `rows`, read once for all of
+    /// `columns`
+    pub fn sample_columns(
+        columns: &[usize],
+        rows: Range<usize>,
+        samples: usize,
+        row_at: impl Fn(usize) -> Vec<String>,
+        progress: &Progress,
+    ) -> ColumnSample {
+        let samples = samples.min(rows.len());
+        progress.set_total(samples);
+        let mut sample = ColumnSample { columns: columns.to_vec(), values: vec![Vec::with_capacity(samples); columns.len()] };
+        for i in 0..samples {
+            if i.is_multiple_of(1_000) {
+                if progress.is_cancelled() {
+                    break;
+                }
+                progress.set_done(i);
+            }
+            let values = row_at(rows.start + i * rows.len() / samples);
+            for (out, &col) in sample.values.iter_mut().zip(columns) {
+                out.push(values.get(col).and_then(|v| v.trim().parse::<f64>().ok()).filter(|x| x.is_finite()));
+            }
+        }
+        sample
+    }
+
+    /// A downsampled trend of each of `columns` over row order: `rows` is
+    /// split into `SPARKLINE_POINTS` equal parts and each point is the mean
+    /// of the numbers among a few rows read from its part (None when there
+    /// are none). Rows are read once for all columns.
+    pub fn sparklines(
+        columns: &[usize],
+        rows: Range<usize>,
+        row_at: impl Fn(usize) -> Vec<String>,
+        progress: &Progress,
+    ) -> Vec<Vec<Option<f64>>> {
+        let points = SPARKLINE_POINTS.min(rows.len());
+        let samples = (points * SPARKLINE_SAMPLES_PER_POINT).min(rows.len());
+        progress.set_total(samples);
+        let mut sums = vec![vec![(0.0, 0usize); points]; columns.len()];
+        for i in 0..samples {
+            if progress.is_cancelled() {
+                break;
+            }
+            let row = rows.start + i * rows.len() / samples;
+            let point = i * points / samples;
+            let values = row_at(row);
+            for (sum, &col) in sums.iter_mut().zip(columns) {
+                if let Some(x) = values.get(col).and_then(|v| v.trim().parse::<f64>().ok()).filter(|x| x.is_finite()) {
+                    sum[point].0 += x;
+                    sum[point].1 += 1;
+                }
+            }
+            progress.set_done(i + 1);
+        }
+        sums.into_iter()
+            .map(|sum| sum.into_iter().map(|(total, n)| (n > 0).then(|| total / n as f64)).collect())
+            .collect()
+    }
+
+    /// Scan `rows` of a column for non-null values that aren't a valid `semantic_type`
+    pub fn find_invalid(
+        col_index: usize,
+        semantic_type: SemanticType,
+        rows: Range<usize>,
+        value_at: impl Fn(usize) -> String,
+        progress: &Progress,
+    ) -> InvalidValues {
+        progress.set_total(rows.len());
+        let start = rows.start;
+        let mut report = InvalidValues { column_index: col_index, semantic_type, values: Vec::new(), count: 0 };
+        for row in rows {
+            if (row - start).is_multiple_of(10_000) {
+                if progress.is_cancelled() {
+                    break;
+                }
+                progress.set_done(row - start);
+            }
+            let value = value_at(row);
+            let value = value.trim();
+            if Self::is_null_like(value) || semantic_type.matches(value) {
+                continue;
+            }
+            report.count += 1;
+            if report.values.len() < MAX_LISTED_INVALID {
+                report.values.push((row, value.to_string()));
+            }
+        }
+        report
+    }
+
+    /// Find columns, and pairs of columns, whose values are non-null and
+    /// unique across `rows`. Pairs are only reported when neither column is
+    /// a key on its own. Values are compared by hash, so a (very unlikely)
+    /// collision can only hide a candidate, never invent one. Past
+    /// `MAX_KEY_HASHES` held, pairs are dropped first, then the scan stops.
+    pub fn find_key_candidates(
+        num_cols: usize,
+        rows: Range<usize>,
+        row_at: impl Fn(usize) -> Vec<String>,
+        progress: &Progress,
+    ) -> KeyCandidates {
+        fn hash_of(value: impl Hash) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            value.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        // A set is dropped (None) as soon as its column(s) fail
+        type Seen = Option<HashSet<u64>>;
+        let mut singles: Vec<Seen> = (0..num_cols).map(|_| Some(HashSet::new())).collect();
+        let pair_cols = num_cols.min(MAX_KEY_PAIR_COLUMNS);
+        let mut pairs: Vec<((usize, usize), Seen)> = (0..pair_cols)
+            .flat_map(|a| (a + 1..pair_cols).map(move |b| ((a, b), Some(HashSet::new()))))
+            .collect();
+        let mut result = KeyCandidates::default();
+        // Hashes in all the sets still kept
+        let mut held = 0;
+        let check = |seen: &mut Seen, hash: Option<u64>, held: &mut usize| {
+            let unique = match (seen.as_mut(), hash) {
+                (Some(set), Some(h)) => set.insert(h),
+                _ => false,
+            };
+            if unique {
+                *held += 1;
+            } else if let Some(set) = seen.take() {
+                *held -= set.len();
+            }
+        };
+
+        progress.set_total(rows.len());
+        let start = rows.start;
+        for row in rows {
+            if (row - start).is_multiple_of(10_000) {
+                if progress.is_cancelled() {
+                    return KeyCandidates::default();
+                }
+                progress.set_done(row - start);
+            }
+            let values = row_at(row);
+            let hashes: Vec<Option<u64>> = (0..num_cols)
+                .map(|c| values.get(c).filter(|v| !Self::is_null_like(v)).map(|v| hash_of(v.trim())))
+                .collect();
+
+            for (seen, hash) in singles.iter_mut().zip(&hashes) {
+                check(seen, *hash, &mut held);
+            }
+            for ((a, b), seen) in pairs.iter_mut() {
+                let hash = hashes[*a].zip(hashes[*b]).map(hash_of);
+                check(seen, hash, &mut held);
+            }
+            result.rows_checked += 1;
+
+            if held > MAX_KEY_HASHES && !pairs.is_empty() {
+                held -= pairs.iter().filter_map(|(_, seen)| seen.as_ref()).map(HashSet::len).sum::<usize>();
+                pairs.clear();
+                result.pairs_skipped = true;
+            }
+            if held > MAX_KEY_HASHES {
+                break;
+            }
+        }
+
+        let is_key: Vec<bool> = singles.iter().map(|s| s.is_some()).collect();
+        result.keys = (0..num_cols).filter(|&c| is_key[c]).map(|c| vec![c]).collect();
+        result.keys.extend(
+            pairs.iter()
+                .filter(|((a, b), seen)| seen.is_some() && !is_key[*a] && !is_key[*b])
+                .map(|((a, b), _)| vec![*a, *b]),
+        );
+        result
+    }
+
+    /// Whether a value counts as missing: empty, or a null marker like "null", "NA" or "N/A"
+    pub fn is_null_like(val: &str) -> bool {
+        let val = val.trim();
+        val.is_empty()
+            || val.eq_ignore_ascii_case("null")
+            || val.eq_ignore_ascii_case("na")
+            || val.eq_ignore_ascii_case("n/a")
+    }
+
+    /// Infer the type of a single (trimmed, non-null) value
+    pub fn infer_value_type(val: &str) -> InferredType {
+        if val.is_empty() {
+            return InferredType::Empty;
+        }
+
+        // Try integer
+        if val.parse::<i64>().is_ok() {
+            return InferredType::Integer;
+        }
+
+        // Try float
+        if val.parse::<f64>().is_ok() {
+            return InferredType::Float;
+        }
+
+        // Try boolean
+        let lower = val.to_lowercase();
+        if lower == "true" || lower == "false" || lower == "yes" || lower == "no" {
+            return InferredType::Boolean;
+        }
+
+        // Try date patterns (simple check)
+        if val.contains('-') || val.contains('/') {
+            let parts: Vec<&str> = val.split(['-', '/']).collect();
+            if parts.len() == 3 && parts.iter().all(|p| p.parse::<u32>().is_ok()) {
+                return InferredType::Date;
+            }
+        }
+
+        InferredType::Text
+    }
+
     /// Infer the type of a column based on its values
     fn infer_type(values: &[&str]) -> (InferredType, Vec<f64>) {
         if values.is_empty() {
@@ -138,42 +714,16 @@ impl ColumnAnalyzer {
         let mut numeric_values = Vec::new();
 
         for val in values {
-            // Try integer
-            if val.parse::<i64>().is_ok() {
-                int_count += 1;
-                if let Ok(n) = val.parse::<f64>() {
-                    numeric_values.push(n);
-                }
-                continue;
+            match Self::infer_value_type(val) {
+                InferredType::Integer => int_count += 1,
+                InferredType::Float => float_count += 1,
+                InferredType::Boolean => bool_count += 1,
+                InferredType::Date => date_count += 1,
+                _ => text_count += 1,
             }
-
-            // Try float
-            if val.parse::<f64>().is_ok() {
-                float_count += 1;
-                if let Ok(n) = val.parse::<f64>() {
-                    numeric_values.push(n);
-                }
-                continue;
+            if let Ok(n) = val.parse::<f64>() {
+                numeric_values.push(n);
             }
-
-            // Try boolean
-            let lower = val.to_lowercase();
-            if lower == "true" || lower == "false" || lower == "yes" || lower == "no" || lower == "1" || lower == "0" {
-                bool_count += 1;
-                continue;
-            }
-
-            // Try date patterns (simple check)
-            if val.contains('-') || val.contains('/') {
-                let parts: Vec<&str> = val.split(|c| c == '-' || c == '/').collect();
-                if parts.len() == 3 && parts.iter().all(|p| p.parse::<u32>().is_ok()) {
-                    date_count += 1;
-                    continue;
-                }
-            }
-
-            // Otherwise text
-            text_count += 1;
         }
 
         let total = values.len();
@@ -215,6 +765,126 @@ mod tests {
         assert_eq!(profile.min, Some(1.0));
         assert_eq!(profile.max, Some(5.0));
         assert_eq!(profile.mean, Some(3.0));
+        assert_eq!(profile.median, Some(3.0));
+        assert_eq!(profile.type_confidence, 1.0);
+    }
+
+    #[test]
+    fn test_type_confidence() {
+        let values: Vec<String> = vec!["1", "2.5", "3", "4", "5", "x"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let profile = ColumnAnalyzer::analyze_column("Mostly numbers", 0, &values);
+
+        assert_eq!(profile.data_type, Some(InferredType::Float));
+        assert!((profile.type_confidence - 5.0 / 6.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_length_percentile() {
+        let mut values: Vec<String> = (0..99).map(|i| "x".repeat(i % 10 + 1)).collect();
+        values.push(format!("{}\nshort", "y".repeat(5000)));
+        let profile = ColumnAnalyzer::analyze_column("Text", 0, &values);
+
+        assert_eq!(profile.length_p95, 10);
+        assert_eq!(profile.max_length, 5000);
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let values = ["a", "b", "a", "", "", "c", "a", "b"];
+        let report = ColumnAnalyzer::find_duplicates(0, values.len(), |r| values[r].to_string(), &Progress::default());
+
+        assert_eq!(report.values, vec![("a".to_string(), 3), ("b".to_string(), 2)]);
+        assert_eq!(report.repeat_rows, vec![2, 6, 7]);
+        assert!(report.is_duplicate("a"));
+        assert!(!report.is_duplicate("c"));
+        assert!(!report.is_duplicate(""));
+    }
+
+    #[test]
+    fn test_distinct_values() {
+        let values = ["b", "a ", "", "b", "c", "a"];
+        let distinct = ColumnAnalyzer::distinct_values(0, 0..values.len(), |r| values[r].to_string(), &Progress::default());
+
+        let expected = [("a", 2), ("b", 2), ("", 1), ("c", 1)].map(|(v, n)| (v.to_string(), n));
+        assert_eq!(distinct.values, expected);
+        assert!(!distinct.too_many);
+        assert_eq!(distinct.rows_scanned, 6);
+    }
+
+    #[test]
+    fn test_sample_columns() {
+        // y = 2x, z = -x, w is text
+        let row_at = |r: usize| vec![r.to_string(), (2 * r).to_string(), format!("-{}", r), "w".to_string()];
+        let sample = ColumnAnalyzer::sample_columns(&[0, 1, 2, 3], 0..100, 10, row_at, &Progress::default());
+        assert_eq!(sample.values[0], (0..10).map(|i| Some(i as f64 * 10.0)).collect::<Vec<_>>());
+        assert_eq!(sample.correlation(0, 1), Some(1.0));
+        assert_eq!(sample.correlation(0, 2), Some(-1.0));
+        assert_eq!(sample.correlation(0, 3), None);
+        assert_eq!(sample.pairs(1, 2)[1], [20.0, -10.0]);
+    }
+
+    #[test]
+    fn test_histogram() {
+        let histogram = ColumnAnalyzer::histogram(&[0.0, 1.0, 2.5, 9.0, 10.0], 4);
+        assert_eq!(histogram.counts, [2, 1, 0, 2]);
+        assert_eq!(histogram.width, 2.5);
+        assert_eq!(histogram.center(1), 3.75);
+
+        let same = ColumnAnalyzer::histogram(&[7.0, 7.0], 3);
+        assert_eq!(same.counts, [0, 2, 0]);
+        assert_eq!(same.center(1), 7.0);
+    }
+
+    #[test]
+    fn test_sparklines() {
+        // 400 rows: column 0 climbs steadily, column 1 is text
+        let row_at = |r: usize| vec![r.to_string(), "x".to_string()];
+        let lines = ColumnAnalyzer::sparklines(&[0, 1], 0..400, row_at, &Progress::default());
+        assert_eq!(lines[0].len(), SPARKLINE_POINTS);
+        assert_eq!(lines[0][0], Some(4.0));
+        assert!(lines[0].windows(2).all(|w| w[0] < w[1]));
+        assert!(lines[1].iter().all(Option::is_none));
+
+        let short = ColumnAnalyzer::sparklines(&[0], 0..3, row_at, &Progress::default());
+        assert_eq!(short[0], [Some(0.0), Some(1.0), Some(2.0)]);
+    }
+
+    #[test]
+    fn test_key_candidates() {
+        // id is unique; (city, year) is unique together; note has a null
+        let rows = [
+            ["1", "Oslo", "2020", "x"],
+            ["2", "Oslo", "2021", ""],
+            ["3", "Rome", "2020", "y"],
+        ];
+        let row_at = |r: usize| rows[r].iter().map(|v| v.to_string()).collect();
+        let keys = ColumnAnalyzer::find_key_candidates(4, 0..rows.len(), row_at, &Progress::default());
+        assert_eq!(keys.keys, vec![vec![0], vec![1, 2]]);
+        assert_eq!(keys.rows_checked, 3);
+        assert!(!keys.pairs_skipped);
+    }
+
+    #[test]
+    fn test_semantic_types() {
+        let values: Vec<String> = ["ann@example.com", "bob@mail.co.uk", "n/a", "cat@x.io", "dan@y.org", "eve@z.net", "not-an-email"]
+            .map(String::from)
+            .to_vec();
+        let profile = ColumnAnalyzer::analyze_column("Email", 0, &values);
+        assert_eq!(profile.semantic_type, Some(SemanticType::Email));
+        assert_eq!(profile.semantic_invalid, 1);
+
+        assert!(SemanticType::Url.matches("https://example.com/a?b=1"));
+        assert!(SemanticType::Uuid.matches("123e4567-e89b-12d3-a456-426614174000"));
+        assert!(SemanticType::Ipv4.matches("192.168.0.1") && !SemanticType::Ipv4.matches("300.1.1.1"));
+        assert!(SemanticType::Ipv6.matches("::1"));
+        assert!(SemanticType::Phone.matches("+44 20 7946 0958") && !SemanticType::Phone.matches("12345"));
+
+        let invalid = ColumnAnalyzer::find_invalid(0, SemanticType::Email, 1..values.len(), |r| values[r].clone(), &Progress::default());
+        assert_eq!(invalid.count, 1);
+        assert_eq!(invalid.values, [(6, "not-an-email".to_string())]);
     }
 
     #[test]
@@ -0,0 +1,131 @@
+use sha2::{Digest, Sha256};
+use std::ops::Range;
+
+use super::editor::CellEdit;
+use super::jobs::Progress;
+
+const FIRST_NAMES: &[&str] = &[
+    "Alex", "Blake", "Casey", "Dana", "Eli", "Frankie", "Gray", "Harper", "Indra", "Jules",
+    "Kai", "Lee", "Morgan", "Noor", "Oakley", "Parker", "Quinn", "Riley", "Sage", "Tatum",
+];
+const LAST_NAMES: &[&str] = &[
+    "Adams", "Baker", "Chen", "Diaz", "Evans", "Fischer", "Garcia", "Hughes", "Ito", "Jensen",
+    "Khan", "Lopez", "Moreau", "Novak", "Okafor", "Patel", "Rossi", "Silva", "Tanaka", "Weber",
+];
+
+/// A masking transform for a sensitive column
+#[derive(Clone, Debug, PartialEq)]
+pub enum MaskTransform {
+    /// Hex SHA-256 of salt + value; equal inputs stay equal, so joins still work
+    Hash { salt: String },
+    /// Replace all but the last `keep` characters with '*'
+    MaskKeepLast(usize),
+    FakeName,
+    FakeEmail,
+}
+
+impl MaskTransform {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MaskTransform::Hash { .. } => "SHA-256 Hash",
+            MaskTransform::MaskKeepLast(_) => "Partial Mask",
+            MaskTransform::FakeName => "Fake Name",
+            MaskTransform::FakeEmail => "Fake Email",
+        }
+    }
+
+    /// Masked form of `value`. Empty cells stay empty, and fakes are chosen
+    /// from the value's hash so the same input always gets the same fake.
+    pub fn apply(&self, value: &str) -> String {
+        if value.trim().is_empty() {
+            return value.to_string();
+        }
+        match self {
+            MaskTransform::Hash { salt } => {
+                let mut hasher = Sha256::new();
+                hasher.update(salt.as_bytes());
+                hasher.update(value.as_bytes());
+                hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect()
+            }
+            MaskTransform::MaskKeepLast(keep) => {
+                let len = value.chars().count();
+                value
+                    .chars()
+                    .enumerate()
+                    .map(|(i, c)| if i + keep < len && !c.is_whitespace() { '*' } else { c })
+                    .collect()
+            }
+            MaskTransform::FakeName => {
+                let (first, last) = fake_name(value);
+                format!("{} {}", first, last)
+            }
+            MaskTransform::FakeEmail => {
+                let (first, last) = fake_name(value);
+                let n = seed(value) % 1000;
+                format!("{}.{}{}@example.com", first.to_lowercase(), last.to_lowercase(), n)
+            }
+        }
+    }
+}
+
+fn seed(value: &str) -> usize {
+    let digest = Sha256::digest(value.as_bytes());
+    usize::from_le_bytes(digest[..8].try_into().unwrap_or_default())
+}
+
+fn fake_name(value: &str) -> (&'static str, &'static str) {
+    let seed = seed(value);
+    (FIRST_NAMES[seed % FIRST_NAMES.len()], LAST_NAMES[(seed / FIRST_NAMES.len()) % LAST_NAMES.len()])
+}
+
+/// Edits masking every value of a column
+pub fn mask_edits(
+    col: usize,
+    rows: Range<usize>,
+    value_at: impl Fn(usize) -> String,
+    transform: &MaskTransform,
+    progress: &Progress,
+) -> Vec<CellEdit> {
+    progress.set_total(rows.len());
+    let start = rows.start;
+    let mut edits = Vec::new();
+    for row in rows {
+        if (row - start).is_multiple_of(10_000) {
+            if progress.is_cancelled() {
+                return Vec::new();
+            }
+            progress.set_done(row - start);
+        }
+        let value = value_at(row);
+        let masked = transform.apply(&value);
+        if masked != value {
+            edits.push((row, col, value, masked));
+        }
+    }
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask_transforms() {
+        let hash = MaskTransform::Hash { salt: "s".to_string() };
+        assert_eq!(hash.apply("abc"), hash.apply("abc"));
+        assert_ne!(hash.apply("abc"), MaskTransform::Hash { salt: "t".to_string() }.apply("abc"));
+        assert_eq!(hash.apply("abc").len(), 64);
+
+        assert_eq!(MaskTransform::MaskKeepLast(4).apply("4111 1111 1111 1234"), "**** **** **** 1234");
+        assert_eq!(MaskTransform::MaskKeepLast(4).apply("12"), "12");
+
+        let email = MaskTransform::FakeEmail.apply("jane@corp.com");
+        assert!(email.ends_with("@example.com"));
+        assert_eq!(email, MaskTransform::FakeEmail.apply("jane@corp.com"));
+        assert_eq!(MaskTransform::FakeName.apply(""), "");
+
+        let column = ["name", "Jane", ""];
+        let edits = mask_edits(0, 1..3, |r| column[r].to_string(), &MaskTransform::FakeName, &Progress::default());
+        assert_eq!(edits.len(), 1);
+    }
+}
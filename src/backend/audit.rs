@@ -0,0 +1,307 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::path::Path;
+
+use super::editor::EditCommand;
+
+/// How a change came about
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub enum AuditAction {
+    Edit,
+    Undo,
+    Redo,
+}
+
+impl AuditAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AuditAction::Edit => "Edit",
+            AuditAction::Undo => "Undo",
+            AuditAction::Redo => "Redo",
+        }
+    }
+}
+
+/// One recorded change. Rows and columns are 1-based as shown in the grid,
+/// and every entry from the same undo step shares its `step`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AuditEntry {
+    pub time: String,
+    pub step: u64,
+    pub action: AuditAction,
+    pub change: &'static str,
+    pub row: Option<usize>,
+    pub column: Option<usize>,
+    pub old_value: String,
+    pub new_value: String,
+}
+
+/// A value a cell held, and when it got it (None for its value before any edit)
+#[derive(Clone, Debug, PartialEq)]
+pub struct CellVersion {
+    pub time: Option<String>,
+    pub value: String,
+}
+
+/// Entries kept before the oldest are dropped
+pub const MAX_AUDIT_ENTRIES: usize = 100_000;
+
+/// Every cell and structural edit made this session, in order, for
+/// documenting changes. Undo and redo are recorded too rather than
+/// rewriting history. Past `MAX_AUDIT_ENTRIES` the oldest entries are
+/// dropped, so a long session can't grow the log without bound.
+#[derive(Clone, Debug, Default)]
+pub struct AuditLog {
+    entries: Vec<AuditEntry>,
+    steps: u64,
+    dropped: usize,
+}
+
+impl AuditLog {
+    /// Record a command as one step. With `header_row`, row 0 is the header
+    /// line (as in a file-backed document) rather than the first data row.
+    /// An undone command is recorded with its values swapped, so old and new
+    /// are always what the cell held before and after.
+    pub fn record(&mut self, action: AuditAction, cmd: &EditCommand, header_row: bool) {
+        self.steps += 1;
+        let time = chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, false);
+        let undo = action == AuditAction::Undo;
+        let mut add = |change, row: Option<usize>, column: Option<usize>, old_value, new_value| {
+            let (old_value, new_value) = if undo { (new_value, old_value) } else { (old_value, new_value) };
+            self.entries.push(AuditEntry {
+                time: time.clone(),
+                step: self.steps,
+                action,
+                change,
+                row: row.map(|r| if header_row { r } else { r + 1 }),
+                column: column.map(|c| c + 1),
+                old_value,
+                new_value,
+            });
+        };
+        flatten(cmd, header_row, &mut add);
+        if self.entries.len() > MAX_AUDIT_ENTRIES {
+            // Drop a tenth at a time so trimming stays cheap
+            let excess = self.entries.len() - MAX_AUDIT_ENTRIES + MAX_AUDIT_ENTRIES / 10;
+            let excess = excess.min(self.entries.len());
+            self.entries.drain(..excess);
+            self.dropped += excess;
+        }
+    }
+
+    pub fn entries(&self) -> &[AuditEntry] {
+        &self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Oldest entries dropped to stay under `MAX_AUDIT_ENTRIES`
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.dropped = 0;
+    }
+
+    /// The values a cell has held this session, oldest first, starting with
+    /// its value before the first edit. `row` and `column` are 1-based like
+    /// the entries; inserted and deleted rows and columns are followed back
+    /// so an edit made before the cell moved is still found. Empty if the
+    /// cell was never set, as far back as the log reaches.
+    pub fn cell_history(&self, row: usize, column: usize) -> Vec<CellVersion> {
+        let (mut row, mut column) = (row, column);
+        let mut versions = Vec::new();
+        let mut earliest = None;
+        let mut end = self.entries.len();
+        'steps: while end > 0 {
+            let step = self.entries[end - 1].step;
+            let start = self.entries[..end].iter().rposition(|e| e.step != step).map_or(0, |i| i + 1);
+            let entries = &self.entries[start..end];
+            end = start;
+            // Entries of an undone step were applied last to first, so walk back over them first to last
+            let undo = entries[0].action == AuditAction::Undo;
+            let ordered: Box<dyn Iterator<Item = &AuditEntry>> =
+                if undo { Box::new(entries.iter()) } else { Box::new(entries.iter().rev()) };
+            for entry in ordered {
+                let inserted = (entry.change == "Insert row") != undo;
+                let inserted_column = (entry.change == "Insert column") != undo;
+                match entry.change {
+                    "Set cell" if entry.row == Some(row) && entry.column == Some(column) => {
+                        versions.push(CellVersion { time: Some(entry.time.clone()), value: entry.new_value.clone() });
+                        earliest = Some(entry.old_value.clone());
+                    }
+                    "Insert row" | "Delete row" => {
+                        let at = entry.row.unwrap_or_default();
+                        if inserted {
+                            if at == row {
+                                break 'steps;
+                            }
+                            if at < row {
+                                row -= 1;
+                            }
+                        } else if at <= row {
+                            row += 1;
+                        }
+                    }
+                    "Insert column" | "Delete column" => {
+                        let at = entry.column.unwrap_or_default();
+                        if inserted_column {
+                            if at == column {
+                                break 'steps;
+                            }
+                            if at < column {
+                                column -= 1;
+                            }
+                        } else if at <= column {
+                            column += 1;
+                        }
+                    }
+                    "Replace table" => break 'steps,
+                    _ => {}
+                }
+            }
+        }
+        if let Some(value) = earliest {
+            versions.push(CellVersion { time: None, value });
+        }
+        versions.reverse();
+        versions
+    }
+
+    pub fn write_csv(&self, path: &Path) -> Result<()> {
+        let mut writer = csv::Writer::from_path(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        for entry in &self.entries {
+            writer.serialize(entry)?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn write_json(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(&self.entries)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+type AddEntry<'a> = dyn FnMut(&'static str, Option<usize>, Option<usize>, String, String) + 'a;
+
+/// Split a command into entries: (change, row, column, old value, new value)
+fn flatten(cmd: &EditCommand, header_row: bool, add: &mut AddEntry) {
+    match cmd {
+        EditCommand::SetCell { row: 0, col, old_value, new_value } if header_row => {
+            add("Rename column", None, Some(*col), old_value.clone(), new_value.clone())
+        }
+        EditCommand::SetCell { row, col, old_value, new_value } => {
+            add("Set cell", Some(*row), Some(*col), old_value.clone(), new_value.clone())
+        }
+        EditCommand::SetHeader { col, old_value, new_value } => {
+            add("Rename column", None, Some(*col), old_value.clone(), new_value.clone())
+        }
+        EditCommand::InsertRow { at, data } => add("Insert row", Some(*at), None, String::new(), row_text(data)),
+        EditCommand::DeleteRow { at, data } => add("Delete row", Some(*at), None, row_text(data), String::new()),
+        EditCommand::InsertColumn { at, header } => add("Insert column", None, Some(*at), String::new(), header.clone()),
+        EditCommand::DeleteColumn { at, header, .. } => add("Delete column", None, Some(*at), header.clone(), String::new()),
+        EditCommand::Batch(cmds) => cmds.iter().for_each(|cmd| flatten(cmd, header_row, add)),
+        EditCommand::ReplaceTable { old_headers, old_rows, new_headers, new_rows } => add(
+            "Replace table",
+            None,
+            None,
+            table_size(old_headers, old_rows),
+            table_size(new_headers, new_rows),
+        ),
+    }
+}
+
+/// A whole row as one value, unambiguous whatever the cells contain
+fn row_text(data: &[String]) -> String {
+    serde_json::to_string(data).unwrap_or_default()
+}
+
+fn table_size(headers: &[String], rows: &[Vec<String>]) -> String {
+    format!("{} rows × {} columns", rows.len(), headers.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_audit_log_records_and_exports() -> Result<()> {
+        let mut log = AuditLog::default();
+        let batch = EditCommand::Batch(vec![
+            EditCommand::SetCell { row: 0, col: 1, old_value: "a".to_string(), new_value: "b".to_string() },
+            EditCommand::InsertRow { at: 2, data: vec!["x".to_string(), "y,z".to_string()] },
+        ]);
+        log.record(AuditAction::Edit, &batch, false);
+        log.record(AuditAction::Undo, &batch, false);
+        let rename = EditCommand::SetCell { row: 0, col: 0, old_value: "id".to_string(), new_value: "key".to_string() };
+        log.record(AuditAction::Edit, &rename, true);
+
+        let entries = log.entries();
+        assert_eq!(entries.len(), 5);
+        assert_eq!((entries[0].step, entries[1].step, entries[2].step), (1, 1, 2));
+        assert_eq!((entries[0].row, entries[0].column), (Some(1), Some(2)));
+        assert_eq!(entries[1].new_value, r#"["x","y,z"]"#);
+        assert_eq!(entries[2].action, AuditAction::Undo);
+        assert_eq!((entries[2].old_value.as_str(), entries[2].new_value.as_str()), ("b", "a"));
+        assert_eq!((entries[4].change, entries[4].row, entries[4].column), ("Rename column", None, Some(1)));
+
+        let dir = TempDir::new()?;
+        let csv_path = dir.path().join("audit.csv");
+        log.write_csv(&csv_path)?;
+        let text = std::fs::read_to_string(&csv_path)?;
+        let mut lines = text.lines();
+        assert_eq!(lines.next(), Some("time,step,action,change,row,column,old_value,new_value"));
+        assert!(lines.next().unwrap().ends_with(",1,Edit,Set cell,1,2,a,b"));
+        assert_eq!(lines.count(), 4);
+
+        let json_path = dir.path().join("audit.json");
+        log.write_json(&json_path)?;
+        let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&json_path)?)?;
+        assert_eq!(json.as_array().map(Vec::len), Some(5));
+        Ok(())
+    }
+
+    #[test]
+    fn test_cell_history_follows_moved_rows() {
+        let set = |row, old: &str, new: &str| EditCommand::SetCell {
+            row,
+            col: 0,
+            old_value: old.to_string(),
+            new_value: new.to_string(),
+        };
+        let mut log = AuditLog::default();
+        log.record(AuditAction::Edit, &set(3, "a", "b"), false);
+        log.record(AuditAction::Edit, &set(3, "b", "c"), false);
+        log.record(AuditAction::Undo, &set(3, "b", "c"), false);
+        // A row inserted above moves the cell from row 4 to row 5
+        log.record(AuditAction::Edit, &EditCommand::InsertRow { at: 0, data: Vec::new() }, false);
+        log.record(AuditAction::Edit, &set(4, "b", "d"), false);
+        log.record(AuditAction::Edit, &set(0, "", "other"), false);
+
+        let values: Vec<String> = log.cell_history(5, 1).into_iter().map(|v| v.value).collect();
+        assert_eq!(values, ["a", "b", "c", "b", "d"]);
+        assert_eq!(log.cell_history(5, 1)[0].time, None);
+        assert!(log.cell_history(4, 1).is_empty());
+        // The inserted row has no history from before it existed
+        assert_eq!(log.cell_history(1, 1).len(), 2);
+    }
+
+    #[test]
+    fn test_audit_log_drops_the_oldest_entries() {
+        let mut log = AuditLog::default();
+        for i in 0..MAX_AUDIT_ENTRIES + 1 {
+            log.record(AuditAction::Edit, &EditCommand::SetCell { row: i, col: 0, old_value: String::new(), new_value: "x".to_string() }, false);
+        }
+        assert!(log.entries().len() <= MAX_AUDIT_ENTRIES);
+        assert_eq!(log.entries().len() + log.dropped(), MAX_AUDIT_ENTRIES + 1);
+        assert_eq!(log.entries().last().and_then(|e| e.row), Some(MAX_AUDIT_ENTRIES + 1));
+        log.clear();
+        assert_eq!(log.dropped(), 0);
+    }
+}
@@ -0,0 +1,196 @@
+use anyhow::{Context, Result};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use super::analysis::ColumnAnalyzer;
+use super::editor::DeltaBuffer;
+use super::export::export_to_json;
+use super::jobs::Progress;
+use super::loader::CsvLoader;
+use super::parser::CsvParser;
+use super::query::Query;
+use super::sort::{sort_loader, SortOrder};
+
+const CATEGORIES: &[&str] = &["books", "garden", "toys", "tools", "music", "food", "sports", "\"home, office\""];
+
+/// How long one backend operation took over `items` rows, once per sample
+#[derive(Debug, Clone)]
+pub struct BenchResult {
+    pub name: &'static str,
+    pub samples: Vec<Duration>,
+    pub items: usize,
+}
+
+impl BenchResult {
+    pub fn min(&self) -> Duration {
+        self.samples.iter().min().copied().unwrap_or_default()
+    }
+
+    pub fn median(&self) -> Duration {
+        let mut sorted = self.samples.clone();
+        sorted.sort();
+        sorted.get(sorted.len() / 2).copied().unwrap_or_default()
+    }
+
+    pub fn mean(&self) -> Duration {
+        let count = u32::try_from(self.samples.len()).unwrap_or(u32::MAX).max(1);
+        self.samples.iter().sum::<Duration>() / count
+    }
+
+    /// Throughput at the median time
+    pub fn rows_per_second(&self) -> f64 {
+        self.items as f64 / self.median().as_secs_f64().max(1e-9)
+    }
+}
+
+impl fmt::Display for BenchResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        write!(
+            f,
+            "{:<18} min {:>9.1} ms  median {:>9.1} ms  mean {:>9.1} ms  {:>13.0} rows/s",
+            self.name,
+            ms(self.min()),
+            ms(self.median()),
+            ms(self.mean()),
+            self.rows_per_second()
+        )
+    }
+}
+
+/// Write a CSV of `rows` records with integer, text, decimal, date and quoted
+/// categorical columns. Values are derived from the row number, so runs are comparable.
+pub fn generate_fixture(path: &Path, rows: usize) -> Result<()> {
+    let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut out = BufWriter::new(file);
+    writeln!(out, "id,name,price,date,category")?;
+    for i in 0..rows {
+        writeln!(
+            out,
+            "{},item {},{}.{:02},2024-{:02}-{:02},{}",
+            i,
+            i.wrapping_mul(2_654_435_761) % 100_000,
+            i % 1000,
+            i % 100,
+            i % 12 + 1,
+            i % 28 + 1,
+            CATEGORIES[i % CATEGORIES.len()],
+        )?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Run `f` `samples` times (at least once), keeping the last value
+fn time<T>(name: &'static str, items: usize, samples: usize, mut f: impl FnMut() -> Result<T>) -> Result<(BenchResult, T)> {
+    let mut timings = Vec::with_capacity(samples);
+    loop {
+        let start = Instant::now();
+        let value = f()?;
+        timings.push(start.elapsed());
+        if timings.len() >= samples {
+            return Ok((BenchResult { name, samples: timings, items }, value));
+        }
+    }
+}
+
+/// Time indexing, parsing, profiling, JSON export, a filtering query and an
+/// external sort on a generated file of `rows` records, `samples` times each,
+/// using `dir` for the fixture and outputs
+pub fn run_benchmarks(rows: usize, samples: usize, dir: &Path) -> Result<Vec<BenchResult>> {
+    let input = dir.join("bench.csv");
+    let output = dir.join("bench.json");
+    let mut results = Vec::new();
+
+    // Written once; it's the input for everything else
+    let (result, ()) = time("generate fixture", rows, 1, || generate_fixture(&input, rows))?;
+    results.push(result);
+
+    let (result, loader) = time("build_index", rows, samples, || CsvLoader::new(&input))?;
+    results.push(result);
+    let total = loader.total_records();
+    let line = |i: usize| String::from_utf8_lossy(&loader.get_record_line(i).unwrap_or_default()).into_owned();
+
+    let (result, prices) = time("parse_line", total, samples, || {
+        let mut prices = Vec::with_capacity(total);
+        for i in 1..total {
+            let fields = CsvParser::parse_line(&line(i))?;
+            prices.push(fields.get(2).cloned().unwrap_or_default());
+        }
+        Ok(prices)
+    })?;
+    results.push(result);
+
+    let (result, _) = time("analyze_column", prices.len(), samples, || Ok(ColumnAnalyzer::analyze_column("price", 2, &prices)))?;
+    results.push(result);
+
+    let (result, ()) = time("export_to_json", rows, samples, || {
+        export_to_json(&input.to_string_lossy(), &output.to_string_lossy())
+    })?;
+    results.push(result);
+
+    // Rare matches, so the filter scans the whole file
+    let headers = CsvParser::parse_line(&line(0))?;
+    let query = Query::parse("SELECT id, name WHERE price > 999.9 AND category = 'books'")?;
+    let (result, _) = time("query search", total, samples, || {
+        query.run(&headers, total.saturating_sub(1), |r| CsvParser::parse_line(&line(r + 1)).unwrap_or_default())
+    })?;
+    results.push(result);
+
+    // Small runs so the timing includes spilling to disk and the merge
+    let sorted = dir.join("bench_sorted.csv");
+    let (result, ()) = time("external sort", total, samples, || {
+        sort_loader(&loader, &DeltaBuffer::new(), 2, SortOrder::Descending, &sorted, 4 * 1024 * 1024, &Progress::default())
+    })?;
+    results.push(result);
+
+    drop(loader);
+    let _ = std::fs::remove_file(&output);
+    let _ = std::fs::remove_file(&sorted);
+    Ok(results)
+}
+
+/// `run_benchmarks` in a fresh temporary directory, removed afterwards
+pub fn run_in_temp_dir(rows: usize, samples: usize) -> Result<Vec<BenchResult>> {
+    let dir = std::env::temp_dir().join(format!("csvit-bench-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let results = run_benchmarks(rows, samples, &dir);
+    let _ = std::fs::remove_dir_all(&dir);
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_sample_statistics() {
+        let ms = Duration::from_millis;
+        let result = BenchResult { name: "x", samples: vec![ms(30), ms(10), ms(20), ms(60)], items: 100 };
+        assert_eq!(result.min(), ms(10));
+        assert_eq!(result.median(), ms(30));
+        assert_eq!(result.mean(), ms(30));
+        assert_eq!(result.rows_per_second().round(), 3333.0);
+    }
+
+    #[test]
+    fn test_benchmarks_run_on_small_fixture() -> Result<()> {
+        let dir = TempDir::new()?;
+        let results = run_benchmarks(500, 3, dir.path())?;
+        let names: Vec<&str> = results.iter().map(|r| r.name).collect();
+        assert_eq!(names, ["generate fixture", "build_index", "parse_line", "analyze_column", "export_to_json", "query search", "external sort"]);
+        assert_eq!(results[1].items, 500);
+        assert_eq!(results[0].samples.len(), 1);
+        assert!(results[1..].iter().all(|r| r.samples.len() == 3 && r.min() <= r.median()));
+
+        let loader = CsvLoader::new(&dir.path().join("bench.csv"))?;
+        assert_eq!(loader.total_records(), 501);
+        let last = String::from_utf8_lossy(&loader.get_record_line(500).unwrap_or_default()).into_owned();
+        assert_eq!(CsvParser::parse_line(&last)?.len(), 5);
+        Ok(())
+    }
+}
@@ -0,0 +1,200 @@
+/// Serialize rows as spreadsheet-style TSV.
+/// Fields containing tabs, newlines or quotes are quoted the way Excel/Sheets do.
+pub fn to_tsv(rows: &[Vec<String>]) -> String {
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .map(|cell| {
+                    if cell.contains('\t') || cell.contains('\n') || cell.contains('"') {
+                        format!("\"{}\"", cell.replace('"', "\"\""))
+                    } else {
+                        cell.clone()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\t")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Serialize a single row as a CSV line (no trailing newline)
+pub fn to_csv_row(row: &[String]) -> String {
+    row.iter()
+        .map(|cell| {
+            if cell.contains(',') || cell.contains('"') || cell.contains('\n') {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell.clone()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Parse spreadsheet-style TSV (as produced by [`to_tsv`], Excel or Sheets).
+/// A single trailing newline does not produce an extra empty row.
+pub fn parse_tsv(text: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut at_field_start = true;
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if at_field_start => {
+                in_quotes = true;
+                at_field_start = false;
+            }
+            '"' if in_quotes => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    in_quotes = false;
+                }
+            }
+            '\t' if !in_quotes => {
+                row.push(std::mem::take(&mut field));
+                at_field_start = true;
+            }
+            '\r' if !in_quotes && chars.peek() == Some(&'\n') => {}
+            '\n' if !in_quotes => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+                at_field_start = true;
+            }
+            _ => {
+                field.push(c);
+                at_field_start = false;
+            }
+        }
+    }
+
+    if !at_field_start || !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows
+}
+
+/// How clipboard rows are placed by Paste Special
+#[derive(PartialEq, Clone, Copy, Debug, Default)]
+pub enum PasteMode {
+    /// Overwrite cells starting at the selection
+    #[default]
+    Values,
+    /// Swap rows and columns, then overwrite from the selection
+    Transposed,
+    /// Insert the rows below the selection
+    NewRows,
+    /// Insert the rows below the selection, taking the first as a header
+    /// and matching its columns to the document's
+    MatchedRows,
+    /// Insert each pasted column after the selection
+    NewColumns,
+}
+
+impl PasteMode {
+    pub fn all() -> &'static [PasteMode] {
+        &[PasteMode::Values, PasteMode::Transposed, PasteMode::NewRows, PasteMode::MatchedRows, PasteMode::NewColumns]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PasteMode::Values => "Values into range",
+            PasteMode::Transposed => "Transposed",
+            PasteMode::NewRows => "As new rows",
+            PasteMode::MatchedRows => "As new rows, matching columns by header",
+            PasteMode::NewColumns => "As new columns",
+        }
+    }
+}
+
+/// Swap rows and columns, padding ragged rows with empty cells
+pub fn transpose(rows: &[Vec<String>]) -> Vec<Vec<String>> {
+    let width = rows.iter().map(|r| r.len()).max().unwrap_or(0);
+    (0..width)
+        .map(|c| rows.iter().map(|r| r.get(c).cloned().unwrap_or_default()).collect())
+        .collect()
+}
+
+/// Serialize rows as an HTML table for rich paste into spreadsheets.
+/// The first row is written as header cells.
+pub fn to_html_table(rows: &[Vec<String>]) -> String {
+    let escape = |s: &str| {
+        s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;").replace('\n', "<br>")
+    };
+    let mut html = String::from("<table>\n");
+    for (i, row) in rows.iter().enumerate() {
+        let tag = if i == 0 { "th" } else { "td" };
+        html.push_str("<tr>");
+        for cell in row {
+            html.push_str(&format!("<{tag}>{}</{tag}>", escape(cell)));
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>");
+    html
+}
+
+/// Kept for the life of the app: on Linux the clipboard contents are lost
+/// when the handle that set them is dropped
+static SYSTEM_CLIPBOARD: std::sync::Mutex<Option<arboard::Clipboard>> = std::sync::Mutex::new(None);
+
+/// Put HTML on the system clipboard with a plain-text alternative
+pub fn write_system_clipboard_html(html: &str, text: &str) -> anyhow::Result<()> {
+    let mut guard = SYSTEM_CLIPBOARD.lock().unwrap_or_else(|e| e.into_inner());
+    if guard.is_none() {
+        *guard = Some(arboard::Clipboard::new()?);
+    }
+    let clipboard = guard.as_mut().expect("clipboard was just created");
+    clipboard.set_html(html, Some(text))?;
+    Ok(())
+}
+
+/// Read text from the system clipboard, if available
+pub fn read_system_clipboard() -> Option<String> {
+    arboard::Clipboard::new().ok()?.get_text().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tsv_round_trip() {
+        let rows = vec![
+            vec!["a".to_string(), "b\tc".to_string()],
+            vec!["line\nbreak".to_string(), "say \"hi\"".to_string()],
+        ];
+        let text = to_tsv(&rows);
+        assert_eq!(parse_tsv(&text), rows);
+    }
+
+    #[test]
+    fn test_html_table_escapes_cells() {
+        let rows = vec![
+            vec!["name".to_string(), "note".to_string()],
+            vec!["<b>".to_string(), "a & b\nc".to_string()],
+        ];
+        assert_eq!(
+            to_html_table(&rows),
+            "<table>\n<tr><th>name</th><th>note</th></tr>\n<tr><td>&lt;b&gt;</td><td>a &amp; b<br>c</td></tr>\n</table>"
+        );
+    }
+
+    #[test]
+    fn test_transpose_ragged() {
+        let rows = vec![vec!["a".to_string(), "b".to_string()], vec!["c".to_string()]];
+        assert_eq!(transpose(&rows), vec![vec!["a", "c"], vec!["b", ""]]);
+    }
+
+    #[test]
+    fn test_parse_trailing_newline() {
+        let rows = parse_tsv("1\t2\r\n3\t4\r\n");
+        assert_eq!(rows, vec![vec!["1", "2"], vec!["3", "4"]]);
+    }
+}
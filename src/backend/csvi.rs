@@ -1,12 +1,13 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::{Read, Write};
 use std::path::Path;
 use zip::write::SimpleFileOptions;
 use zip::{CompressionMethod, ZipArchive, ZipWriter};
 
-use super::formatting::FormatMap;
+use super::formatting::{ColumnFormat, FormatMap, RowColorRule};
 
 /// Metadata stored in the .csvi archive
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
@@ -17,6 +18,18 @@ pub struct CsviMetadata {
     pub column_widths: Vec<f32>,
     #[serde(default)]
     pub view_settings: ViewSettings,
+    /// Display formats keyed by column index
+    #[serde(default)]
+    pub column_formats: BTreeMap<usize, ColumnFormat>,
+    /// Whole-row colors, checked in order
+    #[serde(default)]
+    pub row_colors: Vec<RowColorRule>,
+    /// Lines the source file had above its header and below its table,
+    /// written back around the table when it's saved as CSV again
+    #[serde(default)]
+    pub preamble: String,
+    #[serde(default)]
+    pub footer: String,
 }
 
 /// View settings to restore editor state
@@ -35,6 +48,10 @@ impl CsviMetadata {
             column_names: Vec::new(),
             column_widths: Vec::new(),
             view_settings: ViewSettings::default(),
+            column_formats: BTreeMap::new(),
+            row_colors: Vec::new(),
+            preamble: String::new(),
+            footer: String::new(),
         }
     }
 }
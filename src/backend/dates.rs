@@ -0,0 +1,186 @@
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime};
+use std::collections::HashMap;
+use std::ops::Range;
+
+use super::editor::CellEdit;
+use super::jobs::Progress;
+
+/// ISO 8601 calendar date, the default target
+pub const ISO_DATE: &str = "%Y-%m-%d";
+
+/// Source formats tried when detecting a column's dates, with who writes them
+pub const DATE_FORMATS: &[(&str, &str)] = &[
+    ("%Y-%m-%d", "Year-Month-Day (ISO 8601)"),
+    ("%Y/%m/%d", "Year/Month/Day (Japan, China)"),
+    ("%d/%m/%Y", "Day/Month/Year (UK, Europe)"),
+    ("%m/%d/%Y", "Month/Day/Year (US)"),
+    ("%d.%m.%Y", "Day.Month.Year (Germany)"),
+    ("%d-%m-%Y", "Day-Month-Year (Netherlands)"),
+    ("%m-%d-%Y", "Month-Day-Year (US)"),
+    ("%d/%m/%y", "Day/Month/2-digit year"),
+    ("%m/%d/%y", "Month/Day/2-digit year"),
+    ("%y/%m/%d", "2-digit year/Month/Day"),
+    ("%d %b %Y", "Day Mon Year (12 Mar 2024)"),
+    ("%b %d, %Y", "Mon Day, Year (Mar 12, 2024)"),
+    ("%d %B %Y", "Day Month Year (12 March 2024)"),
+    ("%B %d, %Y", "Month Day, Year (March 12, 2024)"),
+    ("%Y%m%d", "YYYYMMDD"),
+];
+
+/// Target formats offered for rewriting
+pub const TARGET_FORMATS: &[(&str, &str)] = &[
+    (ISO_DATE, "ISO 8601 (2024-03-12)"),
+    ("%d/%m/%Y", "Day/Month/Year (12/03/2024)"),
+    ("%m/%d/%Y", "Month/Day/Year (03/12/2024)"),
+    ("%d.%m.%Y", "Day.Month.Year (12.03.2024)"),
+    ("%d %B %Y", "Day Month Year (12 March 2024)"),
+    ("%B %-d, %Y", "Month Day, Year (March 12, 2024)"),
+];
+
+/// A date in `format`. Four-digit year fields must have four digits, or
+/// 03/04/05 would also read as the year 5.
+pub fn parse_date(value: &str, format: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value.trim(), format)
+        .ok()
+        .filter(|date| !format.contains("%Y") || date.year() >= 1000)
+}
+
+/// Timestamp layouts tried by `parse_datetime`, before falling back to dates
+const DATETIME_FORMATS: &[&str] = &[
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M",
+    "%Y/%m/%d %H:%M:%S",
+    "%d/%m/%Y %H:%M:%S",
+];
+
+/// A timestamp such as `2024-03-12T08:30:00Z` or `2024-03-12 08:30`, or an
+/// ISO date taken as midnight. Offsets are converted to UTC.
+pub fn parse_datetime(value: &str) -> Option<NaiveDateTime> {
+    let value = value.trim();
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Some(datetime.naive_utc());
+    }
+    DATETIME_FORMATS.iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(value, f).ok())
+        .or_else(|| parse_date(value, ISO_DATE).and_then(|d| d.and_hms_opt(0, 0, 0)))
+}
+
+/// `date` written in `format`, or None if the pattern isn't valid strftime
+pub fn format_date(date: NaiveDate, format: &str) -> Option<String> {
+    use std::fmt::Write;
+    let mut out = String::new();
+    write!(out, "{}", date.format(format)).ok()?;
+    Some(out)
+}
+
+/// Each known format with how many of `samples` it parses, best first;
+/// formats that parse none are left out
+pub fn detect_formats(samples: &[String]) -> Vec<(&'static str, usize)> {
+    let mut counts: Vec<(&'static str, usize)> = DATE_FORMATS
+        .iter()
+        .map(|&(format, _)| (format, samples.iter().filter(|v| parse_date(v, format).is_some()).count()))
+        .filter(|&(_, n)| n > 0)
+        .collect();
+    // Stable, so ties keep the list's order (ISO first)
+    counts.sort_by_key(|&(_, n)| std::cmp::Reverse(n));
+    counts
+}
+
+/// The different dates a value could mean under the known formats. More
+/// than one means the value is ambiguous, like 03/04/05.
+pub fn interpretations(value: &str) -> Vec<(&'static str, NaiveDate)> {
+    let mut found: Vec<(&'static str, NaiveDate)> = Vec::new();
+    for &(format, _) in DATE_FORMATS {
+        if let Some(date) = parse_date(value, format)
+            && !found.iter().any(|&(_, d)| d == date)
+        {
+            found.push((format, date));
+        }
+    }
+    found
+}
+
+/// Rewrites dates from one format to another. Values listed in `overrides`
+/// (settled by hand because they were ambiguous) map straight to their
+/// output; values that don't parse are left as they are.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DateReformat {
+    pub source: String,
+    pub target: String,
+    pub overrides: HashMap<String, String>,
+}
+
+impl DateReformat {
+    pub fn apply(&self, value: &str) -> Option<String> {
+        if let Some(output) = self.overrides.get(value) {
+            return Some(output.clone());
+        }
+        parse_date(value, &self.source).and_then(|date| format_date(date, &self.target))
+    }
+}
+
+/// Edits rewriting every parseable date in a column
+pub fn reformat_edits(
+    col: usize,
+    rows: Range<usize>,
+    value_at: impl Fn(usize) -> String,
+    reformat: &DateReformat,
+    progress: &Progress,
+) -> Vec<CellEdit> {
+    progress.set_total(rows.len());
+    let start = rows.start;
+    let mut edits = Vec::new();
+    for row in rows {
+        if (row - start).is_multiple_of(10_000) {
+            if progress.is_cancelled() {
+                return Vec::new();
+            }
+            progress.set_done(row - start);
+        }
+        let value = value_at(row);
+        if let Some(output) = reformat.apply(&value)
+            && output != value
+        {
+            edits.push((row, col, value, output));
+        }
+    }
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_and_reformat_dates() {
+        let samples: Vec<String> = ["13/04/2024", "01/02/2024", "28/12/2023", ""].map(String::from).to_vec();
+        assert_eq!(detect_formats(&samples)[0], ("%d/%m/%Y", 3));
+
+        assert_eq!(interpretations("03/04/05").len(), 3);
+        assert_eq!(interpretations("13/04/2024").len(), 1);
+        // Same date either way, so not ambiguous
+        assert_eq!(interpretations("05/05/2024").len(), 1);
+
+        let mut reformat = DateReformat { source: "%d/%m/%Y".to_string(), target: ISO_DATE.to_string(), overrides: HashMap::new() };
+        assert_eq!(reformat.apply("13/04/2024").as_deref(), Some("2024-04-13"));
+        assert_eq!(format_date(NaiveDate::default(), "%Q"), None);
+        reformat.overrides.insert("01/02/2024".to_string(), "2024-01-02".to_string());
+        let column = ["date", "13/04/2024", "01/02/2024", "soon", "", "2024-01-01"];
+        let edits = reformat_edits(0, 1..column.len(), |r| column[r].to_string(), &reformat, &Progress::default());
+        let outputs: Vec<_> = edits.iter().map(|e| e.3.as_str()).collect();
+        assert_eq!(outputs, ["2024-04-13", "2024-01-02"]);
+    }
+
+    #[test]
+    fn test_parse_datetime() {
+        let at = |s: &str| parse_datetime(s).map(|t| t.and_utc().timestamp());
+        assert_eq!(at("2024-03-12T08:30:00Z"), Some(1_710_232_200));
+        assert_eq!(at("2024-03-12T10:30:00+02:00"), Some(1_710_232_200));
+        assert_eq!(at("2024-03-12 08:30:00.250"), Some(1_710_232_200));
+        assert_eq!(at("2024-03-12 08:30"), Some(1_710_232_200));
+        assert_eq!(at("2024-03-12"), Some(1_710_201_600));
+        assert_eq!(at("8:30"), None);
+    }
+}
@@ -0,0 +1,520 @@
+//! One interface over the two ways a document is held: parsed into an
+//! editable grid, or indexed on disk with edits kept alongside
+use std::ops::Range;
+use std::sync::Arc;
+
+use super::audit::AuditLog;
+use super::editor::{CellEdit, EditBuffer};
+use super::grid::EditableGrid;
+use super::loader::CsvLoader;
+use super::paged_reader::PagedReader;
+use super::parser::CsvParser;
+use super::problems::ScannedRow;
+
+/// Reads the value of a column at a row
+pub type ColumnReader = Box<dyn Fn(usize) -> String + Send>;
+/// Reads all values of a row
+pub type RowReader = Box<dyn Fn(usize) -> Vec<String> + Send>;
+/// Reads a row for the problems scan, with what went wrong reading it
+pub type ScanReader = Box<dyn Fn(usize) -> ScannedRow + Send>;
+
+/// Rows and cells of a document, whatever holds them. Row numbers are the
+/// backing store's own: an indexed file keeps its header as row 0.
+pub trait CsvDocument {
+    /// Rows, including the header row when the file keeps one
+    fn row_count(&self) -> usize;
+
+    fn column_count(&self) -> usize;
+
+    /// First row holding data rather than the header
+    fn first_data_row(&self) -> usize;
+
+    /// Whether every row is held in memory, so the table can change shape
+    fn in_memory(&self) -> bool;
+
+    /// Whether there are changes that haven't been saved
+    fn is_modified(&self) -> bool;
+
+    /// Bumped whenever rows are inserted, deleted or reordered
+    fn row_generation(&self) -> u64;
+
+    /// Column names, one per column
+    fn headers(&self) -> Vec<String>;
+
+    /// Current value of a cell, including any pending edit
+    fn cell(&self, row: usize, col: usize) -> String;
+
+    /// Values of `columns` at a row, in that order
+    fn row_projected(&self, row: usize, columns: &[usize]) -> Vec<String> {
+        columns.iter().map(|&c| self.cell(row, c)).collect()
+    }
+
+    /// Every value of a row
+    fn row(&self, row: usize) -> Vec<String> {
+        (0..self.column_count()).map(|c| self.cell(row, c)).collect()
+    }
+
+    /// Rows in `range`, clipped to the document
+    fn rows(&self, range: Range<usize>) -> Box<dyn Iterator<Item = Vec<String>> + '_> {
+        let range = range.start..range.end.min(self.row_count());
+        Box::new(range.map(move |r| self.row(r)))
+    }
+
+    /// Whether a cell was changed this session
+    fn is_edited(&self, row: usize, col: usize) -> bool;
+
+    /// Rows with a change this session
+    fn edited_rows(&self) -> Vec<usize>;
+
+    /// Bumped whenever a column's values change
+    fn column_generation(&self, col: usize) -> u64;
+
+    /// Every edit made this session
+    fn audit(&self) -> &AuditLog;
+
+    fn audit_mut(&mut self) -> &mut AuditLog;
+
+    /// Revert the last change; false when there is nothing to undo
+    fn undo(&mut self) -> bool;
+
+    /// Reapply the last undone change; false when there is nothing to redo
+    fn redo(&mut self) -> bool;
+
+    /// Undo and redo steps available
+    fn history_len(&self) -> (usize, usize);
+
+    fn set_cell(&mut self, row: usize, col: usize, value: String);
+
+    /// Set several cells as a single undo step
+    fn set_cells(&mut self, edits: Vec<CellEdit>);
+
+    fn insert_rows(&mut self, at: usize, rows: Vec<Vec<String>>) -> Result<(), String>;
+
+    fn insert_columns(&mut self, at: usize, columns: Vec<(String, Vec<String>)>) -> Result<(), String>;
+
+    fn delete_row(&mut self, row: usize) -> Result<(), String>;
+
+    fn delete_column(&mut self, col: usize) -> Result<(), String>;
+
+    /// Remove up to `count` rows from `at` as one undo step, returning them
+    fn delete_rows(&mut self, at: usize, count: usize) -> Result<Vec<Vec<String>>, String>;
+
+    /// Swap in a whole new table as one undo step
+    fn replace_table(&mut self, headers: Vec<String>, rows: Vec<Vec<String>>) -> Result<(), String>;
+
+    /// Turn rows into columns, taking the new headers from the first column
+    /// or numbering them
+    fn transpose(&mut self, first_column_headers: bool) -> Result<(), String>;
+
+    /// Row count and a reader for whole rows that can be moved to a
+    /// background thread
+    fn row_reader(&self) -> (usize, RowReader);
+
+    /// Row count and a reader for one column's values that can be moved to
+    /// a background thread
+    fn column_reader(&self, col: usize) -> (usize, ColumnReader);
+
+    /// Row count and a reader for the problems scan; only a file on disk can
+    /// have rows that don't parse
+    fn scan_reader(&self) -> (usize, ScanReader) {
+        let (total, row_at) = self.row_reader();
+        (total, Box::new(move |r| ScannedRow { fields: row_at(r), ..Default::default() }))
+    }
+}
+
+impl CsvDocument for EditableGrid {
+    fn row_count(&self) -> usize {
+        self.num_rows()
+    }
+
+    fn column_count(&self) -> usize {
+        self.num_cols()
+    }
+
+    fn first_data_row(&self) -> usize {
+        0
+    }
+
+    fn in_memory(&self) -> bool {
+        true
+    }
+
+    fn is_modified(&self) -> bool {
+        EditableGrid::is_modified(self)
+    }
+
+    fn row_generation(&self) -> u64 {
+        EditableGrid::row_generation(self)
+    }
+
+    fn headers(&self) -> Vec<String> {
+        self.headers.clone()
+    }
+
+    fn cell(&self, row: usize, col: usize) -> String {
+        self.get_cell(row, col).cloned().unwrap_or_default()
+    }
+
+    fn is_edited(&self, row: usize, col: usize) -> bool {
+        self.is_cell_edited(row, col)
+    }
+
+    fn edited_rows(&self) -> Vec<usize> {
+        EditableGrid::edited_rows(self)
+    }
+
+    fn column_generation(&self, col: usize) -> u64 {
+        EditableGrid::column_generation(self, col)
+    }
+
+    fn audit(&self) -> &AuditLog {
+        EditableGrid::audit(self)
+    }
+
+    fn audit_mut(&mut self) -> &mut AuditLog {
+        EditableGrid::audit_mut(self)
+    }
+
+    fn undo(&mut self) -> bool {
+        EditableGrid::undo(self)
+    }
+
+    fn redo(&mut self) -> bool {
+        EditableGrid::redo(self)
+    }
+
+    fn history_len(&self) -> (usize, usize) {
+        (self.undo_count(), self.redo_count())
+    }
+
+    fn set_cell(&mut self, row: usize, col: usize, value: String) {
+        EditableGrid::set_cell(self, row, col, value);
+    }
+
+    fn set_cells(&mut self, edits: Vec<CellEdit>) {
+        EditableGrid::set_cells(self, edits.into_iter().map(|(r, c, _, new)| (r, c, new)));
+    }
+
+    fn insert_rows(&mut self, at: usize, rows: Vec<Vec<String>>) -> Result<(), String> {
+        EditableGrid::insert_rows(self, at, rows);
+        Ok(())
+    }
+
+    fn insert_columns(&mut self, at: usize, columns: Vec<(String, Vec<String>)>) -> Result<(), String> {
+        EditableGrid::insert_columns(self, at, columns);
+        Ok(())
+    }
+
+    fn delete_row(&mut self, row: usize) -> Result<(), String> {
+        EditableGrid::delete_row(self, row);
+        Ok(())
+    }
+
+    fn delete_column(&mut self, col: usize) -> Result<(), String> {
+        EditableGrid::delete_column(self, col);
+        Ok(())
+    }
+
+    fn delete_rows(&mut self, at: usize, count: usize) -> Result<Vec<Vec<String>>, String> {
+        Ok(EditableGrid::delete_rows(self, at, count))
+    }
+
+    fn replace_table(&mut self, headers: Vec<String>, rows: Vec<Vec<String>>) -> Result<(), String> {
+        EditableGrid::replace_table(self, headers, rows);
+        Ok(())
+    }
+
+    fn transpose(&mut self, first_column_headers: bool) -> Result<(), String> {
+        EditableGrid::transpose(self, first_column_headers);
+        Ok(())
+    }
+
+    fn row_reader(&self) -> (usize, RowReader) {
+        let rows = self.rows.clone();
+        (rows.len(), Box::new(move |r| rows.get(r).cloned().unwrap_or_default()))
+    }
+
+    fn column_reader(&self, col: usize) -> (usize, ColumnReader) {
+        let values: Vec<String> = (0..self.num_rows()).map(|r| self.cell(r, col)).collect();
+        (values.len(), Box::new(move |r| values.get(r).cloned().unwrap_or_default()))
+    }
+}
+
+/// A file read through its row index, with edits held in a delta buffer
+/// until it's saved. Rows and columns can't be added or removed.
+pub struct IndexedFileDocument {
+    pub loader: Arc<CsvLoader>,
+    pub reader: PagedReader,
+    pub edits: EditBuffer,
+    pub num_columns: usize,
+}
+
+impl IndexedFileDocument {
+    pub fn new(loader: Arc<CsvLoader>) -> Self {
+        Self {
+            reader: PagedReader::new(loader.clone()),
+            edits: EditBuffer::new(),
+            num_columns: loader.num_columns(),
+            loader,
+        }
+    }
+
+    /// Hand over the loader, leaving an empty one until `set_loader`, so it
+    /// can be rebuilt without copying its index when nothing else holds it
+    pub fn take_loader(&mut self) -> Arc<CsvLoader> {
+        let empty = Arc::new(CsvLoader::empty(0, 0));
+        self.reader = PagedReader::new(empty.clone());
+        std::mem::replace(&mut self.loader, empty)
+    }
+
+    /// Switch to a fresh mapping of the file, keeping pending edits
+    pub fn set_loader(&mut self, loader: Arc<CsvLoader>) {
+        self.reader = PagedReader::new(loader.clone());
+        self.num_columns = self.num_columns.max(loader.num_columns());
+        self.loader = loader;
+    }
+
+    /// Raw text of a row as it is in the file
+    fn line(&self, row: usize) -> String {
+        self.reader.get_rows(row, 1).ok().and_then(|v| v.into_iter().next()).unwrap_or_default()
+    }
+
+    /// Fields of a row as they are in the file, without edits
+    pub fn file_row(&self, row: usize) -> Vec<String> {
+        CsvParser::parse_line(&self.line(row)).unwrap_or_default()
+    }
+}
+
+fn structural<T>(what: &str) -> Result<T, String> {
+    Err(format!("{} requires an editable grid", what))
+}
+
+impl CsvDocument for IndexedFileDocument {
+    fn row_count(&self) -> usize {
+        self.loader.total_records()
+    }
+
+    fn column_count(&self) -> usize {
+        self.num_columns
+    }
+
+    fn first_data_row(&self) -> usize {
+        1
+    }
+
+    fn in_memory(&self) -> bool {
+        false
+    }
+
+    fn is_modified(&self) -> bool {
+        self.edits.edits().next().is_some()
+    }
+
+    /// Rows of a file never move
+    fn row_generation(&self) -> u64 {
+        0
+    }
+
+    fn headers(&self) -> Vec<String> {
+        let first = self.file_row(0);
+        (0..self.num_columns)
+            .map(|c| {
+                self.edits.get_edit(0, c).or_else(|| first.get(c)).cloned()
+                    .unwrap_or_else(|| format!("Column {}", c + 1))
+            })
+            .collect()
+    }
+
+    fn cell(&self, row: usize, col: usize) -> String {
+        match self.edits.get_edit(row, col) {
+            Some(edit) => edit.clone(),
+            None => self.file_row(row).get(col).cloned().unwrap_or_default(),
+        }
+    }
+
+    fn row(&self, row: usize) -> Vec<String> {
+        let mut fields = self.file_row(row);
+        fields.resize(self.num_columns, String::new());
+        for (col, value) in self.edits.row_edits(row) {
+            if let Some(field) = fields.get_mut(col) {
+                *field = value.clone();
+            }
+        }
+        fields
+    }
+
+    fn row_projected(&self, row: usize, columns: &[usize]) -> Vec<String> {
+        let mut fields = CsvParser::parse_line_projected(&self.line(row), columns).unwrap_or_default();
+        fields.resize(columns.len(), String::new());
+        for (field, &col) in fields.iter_mut().zip(columns) {
+            if let Some(edit) = self.edits.get_edit(row, col) {
+                *field = edit.clone();
+            }
+        }
+        fields
+    }
+
+    fn is_edited(&self, row: usize, col: usize) -> bool {
+        self.edits.get_edit(row, col).is_some()
+    }
+
+    fn edited_rows(&self) -> Vec<usize> {
+        self.edits.edits().map(|(row, _, _)| row).collect()
+    }
+
+    fn column_generation(&self, col: usize) -> u64 {
+        self.edits.column_generation(col)
+    }
+
+    fn audit(&self) -> &AuditLog {
+        self.edits.audit()
+    }
+
+    fn audit_mut(&mut self) -> &mut AuditLog {
+        self.edits.audit_mut()
+    }
+
+    fn undo(&mut self) -> bool {
+        self.edits.undo().is_some()
+    }
+
+    fn redo(&mut self) -> bool {
+        self.edits.redo().is_some()
+    }
+
+    fn history_len(&self) -> (usize, usize) {
+        (self.edits.undo_count(), self.edits.redo_count())
+    }
+
+    fn set_cell(&mut self, row: usize, col: usize, value: String) {
+        let old_value = self.cell(row, col);
+        self.edits.add_edit(row, col, old_value, value);
+    }
+
+    fn set_cells(&mut self, edits: Vec<CellEdit>) {
+        self.edits.add_edits(edits);
+    }
+
+    fn insert_rows(&mut self, _at: usize, _rows: Vec<Vec<String>>) -> Result<(), String> {
+        structural("Inserting rows")
+    }
+
+    fn insert_columns(&mut self, _at: usize, _columns: Vec<(String, Vec<String>)>) -> Result<(), String> {
+        structural("Inserting columns")
+    }
+
+    fn delete_row(&mut self, _row: usize) -> Result<(), String> {
+        structural("Deleting rows")
+    }
+
+    fn delete_column(&mut self, _col: usize) -> Result<(), String> {
+        structural("Deleting columns")
+    }
+
+    fn delete_rows(&mut self, _at: usize, _count: usize) -> Result<Vec<Vec<String>>, String> {
+        structural("Deleting rows")
+    }
+
+    fn replace_table(&mut self, _headers: Vec<String>, _rows: Vec<Vec<String>>) -> Result<(), String> {
+        structural("Replacing the table")
+    }
+
+    fn transpose(&mut self, _first_column_headers: bool) -> Result<(), String> {
+        structural("Transposing")
+    }
+
+    fn row_reader(&self) -> (usize, RowReader) {
+        let num_cols = self.num_columns;
+        let loader = self.loader.clone();
+        let edits = self.edits.clone();
+        (loader.total_records(), Box::new(move |r| {
+            let mut fields = loader.get_record_line(r)
+                .and_then(|line| CsvParser::parse_line(&String::from_utf8_lossy(&line)).ok())
+                .unwrap_or_default();
+            fields.resize(num_cols.max(fields.len()), String::new());
+            for (col, value) in edits.row_edits(r) {
+                if col < fields.len() {
+                    fields[col] = value.clone();
+                }
+            }
+            fields
+        }))
+    }
+
+    fn column_reader(&self, col: usize) -> (usize, ColumnReader) {
+        let loader = self.loader.clone();
+        let edits: std::collections::HashMap<usize, String> = self.edits.column_edits(col).into_iter().collect();
+        (loader.total_records(), Box::new(move |r| {
+            if let Some(edit) = edits.get(&r) {
+                return edit.clone();
+            }
+            loader.get_record_line(r)
+                .and_then(|line| CsvParser::parse_line(&String::from_utf8_lossy(&line)).ok())
+                .and_then(|fields| fields.get(col).cloned())
+                .unwrap_or_default()
+        }))
+    }
+
+    fn scan_reader(&self) -> (usize, ScanReader) {
+        let loader = self.loader.clone();
+        let edits = self.edits.clone();
+        (loader.total_records(), Box::new(move |r| {
+            let line = loader.get_record_line(r).unwrap_or_default();
+            let invalid_utf8 = std::str::from_utf8(&line).is_err();
+            match CsvParser::parse_line(&String::from_utf8_lossy(&line)) {
+                Ok(mut fields) => {
+                    for (col, value) in edits.row_edits(r) {
+                        if col < fields.len() {
+                            fields[col] = value.clone();
+                        }
+                    }
+                    ScannedRow { fields, invalid_utf8, parse_error: None }
+                }
+                Err(e) => ScannedRow { fields: Vec::new(), invalid_utf8, parse_error: Some(format!("{:#}", e)) },
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn grid_and_file_read_the_same() -> anyhow::Result<()> {
+        let mut file = NamedTempFile::new()?;
+        write!(file, "a,b\n1,2\n3,4\n")?;
+        let mut indexed = IndexedFileDocument::new(Arc::new(CsvLoader::new(file.path())?));
+        let mut grid = EditableGrid::from_csv("a,b\n1,2\n3,4\n");
+
+        indexed.set_cell(2, 1, "x".to_string());
+        grid.set_cell(1, 1, "x".to_string());
+        for doc in [&indexed as &dyn CsvDocument, &grid] {
+            assert_eq!(doc.headers(), ["a", "b"]);
+            let first = doc.first_data_row();
+            assert_eq!(doc.row_count() - first, 2);
+            let rows: Vec<Vec<String>> = doc.rows(first..usize::MAX).collect();
+            assert_eq!(rows, [vec!["1", "2"], vec!["3", "x"]]);
+            assert_eq!(doc.row_projected(first + 1, &[1, 0]), ["x", "3"]);
+            assert!(doc.is_edited(first + 1, 1));
+            assert_eq!(doc.edited_rows(), [first + 1]);
+            let (total, value_at) = doc.column_reader(1);
+            assert_eq!(value_at(total - 1), "x");
+        }
+
+        indexed.set_cell(0, 0, "renamed".to_string());
+        assert_eq!(indexed.headers(), ["renamed", "b"]);
+        assert!(indexed.undo());
+        assert_eq!(indexed.headers(), ["a", "b"]);
+        assert_eq!(indexed.history_len(), (1, 1));
+
+        assert!(indexed.delete_row(1).is_err());
+        assert!(indexed.transpose(false).is_err());
+        assert!(CsvDocument::delete_row(&mut grid, 0).is_ok());
+        assert_eq!(grid.row_count(), 1);
+        assert!(grid.is_modified() && indexed.is_modified());
+        Ok(())
+    }
+}
@@ -1,6 +1,8 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use serde::{Deserialize, Serialize};
 
+use super::audit::{AuditAction, AuditLog};
+
 /// Represents an edit command that can be undone/redone
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum EditCommand {
@@ -38,9 +40,49 @@ pub enum EditCommand {
         old_value: String,
         new_value: String,
     },
+    /// Several commands applied (and undone) as one step, in order
+    Batch(Vec<EditCommand>),
+    /// Replace the whole table, e.g. when transposing (stores both sides for undo)
+    ReplaceTable {
+        old_headers: Vec<String>,
+        old_rows: Vec<Vec<String>>,
+        new_headers: Vec<String>,
+        new_rows: Vec<Vec<String>>,
+    },
 }
 
 impl EditCommand {
+    /// The single column this command touches, or None for structural changes
+    pub fn affected_column(&self) -> Option<usize> {
+        match self {
+            EditCommand::SetCell { col, .. } | EditCommand::SetHeader { col, .. } => Some(*col),
+            EditCommand::InsertRow { .. }
+            | EditCommand::DeleteRow { .. }
+            | EditCommand::InsertColumn { .. }
+            | EditCommand::DeleteColumn { .. }
+            | EditCommand::Batch(_)
+            | EditCommand::ReplaceTable { .. } => None,
+        }
+    }
+
+    /// Whether this command (or any command in a batch) sets the given cell
+    pub fn touches_cell(&self, row: usize, col: usize) -> bool {
+        match self {
+            EditCommand::SetCell { row: r, col: c, .. } => *r == row && *c == col,
+            EditCommand::Batch(cmds) => cmds.iter().any(|cmd| cmd.touches_cell(row, col)),
+            _ => false,
+        }
+    }
+
+    /// Rows of every cell this command (or any command in a batch) sets
+    pub fn set_rows(&self, rows: &mut Vec<usize>) {
+        match self {
+            EditCommand::SetCell { row, .. } => rows.push(*row),
+            EditCommand::Batch(cmds) => cmds.iter().for_each(|cmd| cmd.set_rows(rows)),
+            _ => {}
+        }
+    }
+
     /// Create the inverse command for undo
     pub fn inverse(&self) -> Self {
         match self.clone() {
@@ -62,10 +104,69 @@ impl EditCommand {
             EditCommand::SetHeader { col, old_value, new_value } => {
                 EditCommand::SetHeader { col, old_value: new_value, new_value: old_value }
             }
+            EditCommand::Batch(cmds) => {
+                EditCommand::Batch(cmds.iter().rev().map(|cmd| cmd.inverse()).collect())
+            }
+            EditCommand::ReplaceTable { old_headers, old_rows, new_headers, new_rows } => {
+                EditCommand::ReplaceTable {
+                    old_headers: new_headers,
+                    old_rows: new_rows,
+                    new_headers: old_headers,
+                    new_rows: old_rows,
+                }
+            }
         }
     }
 }
 
+/// Monotonic per-column change counters, used to invalidate derived data
+/// (like column profiles) only for the columns an edit actually touched
+#[derive(Default, Clone, Debug)]
+pub struct EditGenerations {
+    counter: u64,
+    structural: u64,
+    rows: u64,
+    columns: HashMap<usize, u64>,
+}
+
+impl EditGenerations {
+    /// Record that a command was applied, undone or redone
+    pub fn bump(&mut self, cmd: &EditCommand) {
+        if let EditCommand::Batch(cmds) = cmd {
+            cmds.iter().for_each(|cmd| self.bump(cmd));
+            return;
+        }
+        self.counter += 1;
+        match cmd.affected_column() {
+            Some(col) => {
+                self.columns.insert(col, self.counter);
+            }
+            None => self.structural = self.counter,
+        }
+        if matches!(cmd, EditCommand::InsertRow { .. } | EditCommand::DeleteRow { .. } | EditCommand::ReplaceTable { .. }) {
+            self.rows = self.counter;
+        }
+    }
+
+    /// Mark every column as changed
+    pub fn invalidate_all(&mut self) {
+        self.counter += 1;
+        self.structural = self.counter;
+        self.rows = self.counter;
+    }
+
+    /// Generation of the row order; changes whenever rows may have been
+    /// added, removed or moved, so anything holding row numbers is stale
+    pub fn rows(&self) -> u64 {
+        self.rows
+    }
+
+    /// Generation of a column; changes whenever its contents may have changed
+    pub fn column(&self, col: usize) -> u64 {
+        self.columns.get(&col).copied().unwrap_or(0).max(self.structural)
+    }
+}
+
 /// Delta buffer that tracks edits with full undo/redo support
 #[derive(Default, Clone, Debug)]
 pub struct DeltaBuffer {
@@ -79,6 +180,13 @@ pub struct DeltaBuffer {
     dirty: bool,
     /// Maximum undo history size
     max_history: usize,
+    /// Whether commands have fallen off the bottom of the undo stack since
+    /// it was last cleared, so a cell's earlier values may no longer be in it
+    history_trimmed: bool,
+    /// Per-column change tracking
+    generations: EditGenerations,
+    /// Every change made, kept even when the history is cleared
+    audit: AuditLog,
 }
 
 impl DeltaBuffer {
@@ -89,6 +197,9 @@ impl DeltaBuffer {
             redo_stack: Vec::new(),
             dirty: false,
             max_history: 100,
+            history_trimmed: false,
+            generations: EditGenerations::default(),
+            audit: AuditLog::default(),
         }
     }
 
@@ -96,6 +207,8 @@ impl DeltaBuffer {
     pub fn execute(&mut self, cmd: EditCommand) {
         // Apply the command to our edit map
         self.apply_command(&cmd);
+        self.generations.bump(&cmd);
+        self.audit.record(AuditAction::Edit, &cmd, true);
         
         // Add to undo stack
         self.undo_stack.push(cmd);
@@ -106,6 +219,7 @@ impl DeltaBuffer {
         // Trim history if needed
         if self.undo_stack.len() > self.max_history {
             self.undo_stack.remove(0);
+            self.history_trimmed = true;
         }
         
         self.dirty = true;
@@ -114,8 +228,9 @@ impl DeltaBuffer {
     /// Undo the last command
     pub fn undo(&mut self) -> Option<EditCommand> {
         if let Some(cmd) = self.undo_stack.pop() {
-            let inverse = cmd.inverse();
-            self.apply_command(&inverse);
+            self.revert_command(&cmd, &[]);
+            self.generations.bump(&cmd);
+            self.audit.record(AuditAction::Undo, &cmd, true);
             self.redo_stack.push(cmd.clone());
             self.dirty = !self.undo_stack.is_empty();
             Some(cmd)
@@ -128,6 +243,8 @@ impl DeltaBuffer {
     pub fn redo(&mut self) -> Option<EditCommand> {
         if let Some(cmd) = self.redo_stack.pop() {
             self.apply_command(&cmd);
+            self.generations.bump(&cmd);
+            self.audit.record(AuditAction::Redo, &cmd, true);
             self.undo_stack.push(cmd.clone());
             self.dirty = true;
             Some(cmd)
@@ -136,23 +253,58 @@ impl DeltaBuffer {
         }
     }
 
+    /// Undo a command that has already been popped off the undo stack.
+    /// `earlier` holds commands from the same batch that ran before it.
+    fn revert_command(&mut self, cmd: &EditCommand, earlier: &[EditCommand]) {
+        match cmd {
+            EditCommand::SetCell { row, col, old_value, .. } => self.restore_cell(*row, *col, old_value, earlier),
+            EditCommand::Batch(cmds) => {
+                for (i, sub) in cmds.iter().enumerate().rev() {
+                    self.revert_command(sub, &cmds[..i]);
+                }
+            }
+            _ => self.apply_command(&cmd.inverse()),
+        }
+    }
+
+    /// Restore a cell's edit to whatever the remaining history says it was,
+    /// dropping it entirely if no earlier command touched the cell. Once the
+    /// history has been trimmed that can't be told, so the command's own
+    /// `old_value` is kept as the edit instead.
+    fn restore_cell(&mut self, row: usize, col: usize, old_value: &str, earlier: &[EditCommand]) {
+        let previous = last_cell_value(earlier, row, col)
+            .or_else(|| last_cell_value(&self.undo_stack, row, col))
+            .cloned()
+            .or_else(|| self.history_trimmed.then(|| old_value.to_string()));
+        match previous {
+            Some(value) => {
+                self.edits.insert((row, col), value);
+            }
+            None => {
+                self.edits.remove(&(row, col));
+            }
+        }
+    }
+
     /// Apply a command to the edit map
     fn apply_command(&mut self, cmd: &EditCommand) {
         match cmd {
             EditCommand::SetCell { row, col, new_value, .. } => {
-                if new_value.is_empty() {
-                    self.edits.remove(&(*row, *col));
-                } else {
-                    self.edits.insert((*row, *col), new_value.clone());
-                }
+                self.edits.insert((*row, *col), new_value.clone());
             }
             EditCommand::SetHeader { .. } => {
                 // Headers are handled at the grid level
             }
+            EditCommand::Batch(cmds) => {
+                for cmd in cmds {
+                    self.apply_command(cmd);
+                }
+            }
             EditCommand::InsertRow { .. } |
             EditCommand::DeleteRow { .. } |
             EditCommand::InsertColumn { .. } |
-            EditCommand::DeleteColumn { .. } => {
+            EditCommand::DeleteColumn { .. } |
+            EditCommand::ReplaceTable { .. } => {
                 // Row/column operations are handled at the grid level
                 // The DeltaBuffer just tracks the command history
             }
@@ -165,11 +317,57 @@ impl DeltaBuffer {
         self.execute(cmd);
     }
 
+    /// Set several cells as a single undoable step: (row, col, old_value, new_value)
+    pub fn add_edits(&mut self, edits: impl IntoIterator<Item = (usize, usize, String, String)>) {
+        let cmds: Vec<EditCommand> = edits
+            .into_iter()
+            .map(|(row, col, old_value, new_value)| EditCommand::SetCell { row, col, old_value, new_value })
+            .collect();
+        if !cmds.is_empty() {
+            self.execute(EditCommand::Batch(cmds));
+        }
+    }
+
     /// Get an edit for a specific cell
     pub fn get_edit(&self, row: usize, col: usize) -> Option<&String> {
         self.edits.get(&(row, col))
     }
 
+    /// All pending edits in a row as (col, value), in column order
+    pub fn row_edits(&self, row: usize) -> impl Iterator<Item = (usize, &String)> {
+        self.edits
+            .range((row, 0)..=(row, usize::MAX))
+            .map(|(&(_, col), value)| (col, value))
+    }
+
+    /// Every pending edit as (row, col, value), in row order
+    pub fn edits(&self) -> impl Iterator<Item = (usize, usize, &String)> {
+        self.edits.iter().map(|(&(row, col), value)| (row, col, value))
+    }
+
+    /// All pending edits in a column as (row, value)
+    pub fn column_edits(&self, col: usize) -> Vec<(usize, String)> {
+        self.edits
+            .iter()
+            .filter(|((_, c), _)| *c == col)
+            .map(|(&(row, _), value)| (row, value.clone()))
+            .collect()
+    }
+
+    /// Generation counter for a column's edits
+    pub fn column_generation(&self, col: usize) -> u64 {
+        self.generations.column(col)
+    }
+
+    /// Changes made this session; row 0 is the header line
+    pub fn audit(&self) -> &AuditLog {
+        &self.audit
+    }
+
+    pub fn audit_mut(&mut self) -> &mut AuditLog {
+        &mut self.audit
+    }
+
     /// Check if there are changes that can be undone
     pub fn can_undo(&self) -> bool {
         !self.undo_stack.is_empty()
@@ -200,18 +398,32 @@ impl DeltaBuffer {
         self.dirty = false;
     }
 
-    /// Clear all edits and history
+    /// Clear all edits and history. The audit log is kept.
     pub fn clear(&mut self) {
         self.edits.clear();
         self.undo_stack.clear();
+        self.history_trimmed = false;
         self.redo_stack.clear();
         self.dirty = false;
+        self.generations.invalidate_all();
     }
 }
 
+/// The value the most recent SetCell for a cell in `cmds` left behind
+fn last_cell_value(cmds: &[EditCommand], row: usize, col: usize) -> Option<&String> {
+    cmds.iter().rev().find_map(|cmd| match cmd {
+        EditCommand::SetCell { row: r, col: c, new_value, .. } if *r == row && *c == col => Some(new_value),
+        EditCommand::Batch(inner) => last_cell_value(inner, row, col),
+        _ => None,
+    })
+}
+
 // Keep backwards compatibility with old EditBuffer name
 pub type EditBuffer = DeltaBuffer;
 
+/// A cell rewrite computed off the UI thread: (row, col, old value, new value)
+pub type CellEdit = (usize, usize, String, String);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -239,6 +451,71 @@ mod tests {
         assert!(!buffer.can_redo());
     }
 
+    #[test]
+    fn test_cleared_cell_and_repeated_undo() {
+        let mut buffer = DeltaBuffer::new();
+
+        // Clearing a cell is an edit like any other, not a return to the file's value
+        buffer.add_edit(0, 0, "file".to_string(), "".to_string());
+        assert_eq!(buffer.get_edit(0, 0), Some(&String::new()));
+
+        // Undo steps back through the cell's own history, then drops the edit
+        buffer.add_edit(0, 0, "".to_string(), "second".to_string());
+        buffer.undo();
+        assert_eq!(buffer.get_edit(0, 0), Some(&String::new()));
+        buffer.undo();
+        assert_eq!(buffer.get_edit(0, 0), None);
+        assert!(!buffer.is_dirty());
+    }
+
+    #[test]
+    fn test_undo_past_trimmed_history_uses_old_value() {
+        let mut buffer = DeltaBuffer::new();
+        buffer.add_edit(0, 0, "file".to_string(), "first".to_string());
+        for i in 0..buffer.max_history {
+            buffer.add_edit(1, 0, String::new(), i.to_string());
+        }
+        buffer.add_edit(0, 0, "first".to_string(), "second".to_string());
+
+        // The edit that set "first" has been trimmed away, but undo still restores it
+        buffer.undo();
+        assert_eq!(buffer.get_edit(0, 0), Some(&"first".to_string()));
+    }
+
+    #[test]
+    fn test_column_generations() {
+        let mut buffer = DeltaBuffer::new();
+        let before_a = buffer.column_generation(0);
+        let before_b = buffer.column_generation(1);
+
+        buffer.add_edit(3, 0, "x".to_string(), "y".to_string());
+        assert_ne!(buffer.column_generation(0), before_a);
+        assert_eq!(buffer.column_generation(1), before_b);
+
+        let after_edit = buffer.column_generation(0);
+        buffer.undo();
+        assert_ne!(buffer.column_generation(0), after_edit);
+    }
+
+    #[test]
+    fn test_batch_undo_is_one_step() {
+        let mut buffer = DeltaBuffer::new();
+        buffer.add_edit(0, 0, "a".to_string(), "first".to_string());
+        buffer.add_edits(vec![
+            (0, 0, "first".to_string(), "second".to_string()),
+            (0, 1, "b".to_string(), "pasted".to_string()),
+        ]);
+        assert_eq!(buffer.undo_count(), 2);
+
+        buffer.undo();
+        assert_eq!(buffer.get_edit(0, 0), Some(&"first".to_string()));
+        assert_eq!(buffer.get_edit(0, 1), None);
+
+        buffer.redo();
+        assert_eq!(buffer.get_edit(0, 0), Some(&"second".to_string()));
+        assert_eq!(buffer.get_edit(0, 1), Some(&"pasted".to_string()));
+    }
+
     #[test]
     fn test_new_edit_clears_redo() {
         let mut buffer = DeltaBuffer::new();
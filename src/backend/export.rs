@@ -1,7 +1,304 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
 use std::fs::File;
+use serde_json::{Map, Value};
 use std::io::{BufReader, BufWriter, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 
+use super::analysis::{ColumnAnalyzer, InferredType};
+use super::formatting::FormatMap;
+use super::jobs::Progress;
+use super::loader::CsvLoader;
+use super::parser::CsvParser;
+use super::writer::{temp_path_for, write_via_temp, CsvWriteOptions, DeferredNewline};
+use super::xlsx::XlsxWriter;
+
+/// File formats the Export dialog can write
+#[derive(Clone, Copy, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub enum ExportFormat {
+    #[default]
+    Json,
+    JsonLines,
+    Csv,
+    Xlsx,
+}
+
+impl ExportFormat {
+    pub fn all() -> &'static [ExportFormat] {
+        &[ExportFormat::Json, ExportFormat::JsonLines, ExportFormat::Csv, ExportFormat::Xlsx]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "JSON",
+            ExportFormat::JsonLines => "JSON Lines",
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Xlsx => "Excel",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::JsonLines => "jsonl",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Xlsx => "xlsx",
+        }
+    }
+}
+
+/// One output column: the source column it comes from and the header it's written under
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExportColumn {
+    pub source: usize,
+    pub name: String,
+}
+
+/// Everything the Export dialog decides, shared by every format
+#[derive(Clone, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    /// Output columns in order
+    pub columns: Vec<ExportColumn>,
+    /// Dialect for CSV output
+    pub csv: CsvWriteOptions,
+    /// Split JSON output into parts of about this many bytes
+    #[serde(default)]
+    pub max_part_bytes: Option<u64>,
+}
+
+/// Default destination for a preset: next to the input, with the format's
+/// extension and a suffix so a CSV export never lands on the input itself
+pub const DEFAULT_DESTINATION: &str = "{dir}/{stem}-export.{ext}";
+
+/// Saved export settings that can be run again on any file with the same columns
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExportPreset {
+    pub name: String,
+    /// Format, dialect and chunking; column indices are re-resolved from `source_headers`
+    pub options: ExportOptions,
+    /// Input header of each output column, matched by name when the preset runs
+    pub source_headers: Vec<String>,
+    /// Output path with `{dir}`, `{stem}`, `{date}` and `{ext}` filled in from the input
+    pub destination: String,
+}
+
+impl ExportPreset {
+    /// Options for a file with these headers; fails if a preset column is missing
+    pub fn resolve(&self, headers: &[String]) -> Result<ExportOptions> {
+        let mut options = self.options.clone();
+        for (column, header) in options.columns.iter_mut().zip(&self.source_headers) {
+            column.source = headers
+                .iter()
+                .position(|h| h == header)
+                .with_context(|| format!("Column \"{}\" not found", header))?;
+        }
+        Ok(options)
+    }
+
+    /// Where the export of `input` goes
+    pub fn destination_for(&self, input: &Path, date: chrono::NaiveDate) -> PathBuf {
+        let dir = input.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let stem = input.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "export".to_string());
+        PathBuf::from(
+            self.destination
+                .replace("{dir}", &dir.to_string_lossy())
+                .replace("{stem}", &stem)
+                .replace("{date}", &date.format("%Y-%m-%d").to_string())
+                .replace("{ext}", self.options.format.extension()),
+        )
+    }
+}
+
+/// Run a preset on a CSV file from disk, as `csvit export --preset` does
+pub fn run_preset(preset: &ExportPreset, input: &Path, progress: &Progress) -> Result<Vec<PathBuf>> {
+    let loader = CsvLoader::open_table(input)?;
+    let row_at = |r: usize| {
+        loader.get_record_line(r)
+            .and_then(|line| CsvParser::parse_line(&String::from_utf8_lossy(&line)).ok())
+            .unwrap_or_default()
+    };
+    let options = preset.resolve(&row_at(0))?;
+    let path = preset.destination_for(input, chrono::Local::now().date_naive());
+    ensure_not_input(input, &path)?;
+    if let Some(dir) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let total = loader.total_records();
+    export_rows(&path, &options, 1.min(total)..total, row_at, &[], &FormatMap::new(), progress)
+}
+
+/// Refuse a destination that is the file being read: the export would
+/// replace its own input (and, while it's mapped, pull the rows out from under it)
+pub fn ensure_not_input(input: &Path, output: &Path) -> Result<()> {
+    if let (Ok(input), Ok(output)) = (std::fs::canonicalize(input), std::fs::canonicalize(output))
+        && input == output
+    {
+        anyhow::bail!("{} is the file being exported; choose another destination", output.display());
+    }
+    Ok(())
+}
+
+/// Write the data rows in `rows` (read through `row_at`) to `path`, keeping
+/// and renaming only `options.columns`. `types` holds each source column's
+/// profiled type, where known, for typed JSON and Excel values. `formats`
+/// styles Excel cells, keyed by data row (0 is `rows.start`) and source column.
+/// Returns the files written: just `path`, unless JSON was split into parts.
+/// Each file is written under a temporary name and renamed into place.
+pub fn export_rows(
+    path: &Path,
+    options: &ExportOptions,
+    rows: Range<usize>,
+    row_at: impl Fn(usize) -> Vec<String>,
+    types: &[Option<InferredType>],
+    formats: &FormatMap,
+    progress: &Progress,
+) -> Result<Vec<PathBuf>> {
+    let headers: Vec<String> = options.columns.iter().map(|c| c.name.clone()).collect();
+    let types: Vec<Option<InferredType>> = options.columns.iter()
+        .map(|c| types.get(c.source).cloned().flatten())
+        .collect();
+    let each_row = |write: &mut dyn FnMut(usize, Vec<String>) -> Result<()>| -> Result<()> {
+        progress.set_total(rows.len());
+        for (done, row) in rows.clone().enumerate() {
+            if done % 10_000 == 0 {
+                if progress.is_cancelled() {
+                    anyhow::bail!("Export cancelled");
+                }
+                progress.set_done(done);
+            }
+            let values = row_at(row);
+            write(done, options.columns.iter().map(|c| values.get(c.source).cloned().unwrap_or_default()).collect())?;
+        }
+        Ok(())
+    };
+
+    match options.format {
+        ExportFormat::Json | ExportFormat::JsonLines => {
+            let mut parts = JsonParts::new(path, options.format, options.max_part_bytes);
+            each_row(&mut |_, values| {
+                let row = serde_json::to_vec(&row_to_json(&headers, &values, &types))?;
+                parts.write(&row)
+            })?;
+            parts.finish()
+        }
+        ExportFormat::Csv => {
+            write_via_temp(path, |tmp_path| {
+                let file = File::create(tmp_path).with_context(|| format!("Failed to create {}", path.display()))?;
+                let mut deferred = DeferredNewline::new(BufWriter::new(file));
+                {
+                    let mut writer = options.csv.writer(&mut deferred);
+                    options.csv.write_record(&mut writer, &headers)?;
+                    each_row(&mut |_, values| Ok(options.csv.write_record(&mut writer, &values)?))?;
+                    writer.flush()?;
+                }
+                deferred.finish(options.csv.trailing_newline).context("Failed to flush export file")
+            })?;
+            Ok(vec![path.to_path_buf()])
+        }
+        ExportFormat::Xlsx => {
+            write_via_temp(path, |tmp_path| {
+                let mut writer = XlsxWriter::create(tmp_path)?;
+                writer.write_header(&headers)?;
+                each_row(&mut |done, values| {
+                    writer.write_row(&values, &types, |col| formats.get(done, options.columns[col].source))
+                })?;
+                writer.finish()
+            })?;
+            Ok(vec![path.to_path_buf()])
+        }
+    }
+}
+
+/// JSON output, as one array per file or one object per line, optionally
+/// started over in a new `{stem}-part-0001.{ext}` file once a part passes
+/// `max_bytes` so no single file grows too big to open. Parts are written
+/// under temporary names and only renamed into place by `finish`.
+struct JsonParts<'a> {
+    path: &'a Path,
+    format: ExportFormat,
+    max_bytes: Option<u64>,
+    current: Option<(BufWriter<File>, u64)>,
+    written: Vec<PathBuf>,
+}
+
+impl Drop for JsonParts<'_> {
+    /// Clean up the temporary parts of an export that failed or was cancelled
+    fn drop(&mut self) {
+        self.current = None;
+        for path in &self.written {
+            let _ = std::fs::remove_file(temp_path_for(path));
+        }
+    }
+}
+
+impl<'a> JsonParts<'a> {
+    fn new(path: &'a Path, format: ExportFormat, max_bytes: Option<u64>) -> Self {
+        Self { path, format, max_bytes, current: None, written: Vec::new() }
+    }
+
+    fn write(&mut self, row: &[u8]) -> Result<()> {
+        let full = match (&self.current, self.max_bytes) {
+            (Some((_, bytes)), Some(max)) => *bytes > 0 && bytes + row.len() as u64 > max,
+            _ => false,
+        };
+        if full || self.current.is_none() {
+            self.close_part()?;
+            self.open_part()?;
+        }
+        let (out, bytes) = self.current.as_mut().expect("a part was just opened");
+        let separator: &[u8] = match self.format {
+            ExportFormat::JsonLines => b"",
+            _ if *bytes == 0 => b"[",
+            _ => b",",
+        };
+        out.write_all(separator)?;
+        out.write_all(row)?;
+        if self.format == ExportFormat::JsonLines {
+            out.write_all(b"\n")?;
+        }
+        *bytes += (separator.len() + row.len() + 1) as u64;
+        Ok(())
+    }
+
+    fn open_part(&mut self) -> Result<()> {
+        let path = match self.max_bytes {
+            Some(_) => {
+                let stem = self.path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+                self.path.with_file_name(format!("{}-part-{:04}.{}", stem, self.written.len() + 1, self.format.extension()))
+            }
+            None => self.path.to_path_buf(),
+        };
+        let file = File::create(temp_path_for(&path)).with_context(|| format!("Failed to create {}", path.display()))?;
+        self.current = Some((BufWriter::new(file), 0));
+        self.written.push(path);
+        Ok(())
+    }
+
+    fn close_part(&mut self) -> Result<()> {
+        if let Some((mut out, bytes)) = self.current.take() {
+            if self.format == ExportFormat::Json {
+                out.write_all(if bytes == 0 { b"[]" } else { b"]" })?;
+            }
+            out.flush().context("Failed to flush export file")?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Vec<PathBuf>> {
+        // Even no rows at all makes one (empty) file
+        if self.written.is_empty() {
+            self.open_part()?;
+        }
+        self.close_part()?;
+        let written = std::mem::take(&mut self.written);
+        for path in &written {
+            std::fs::rename(temp_path_for(path), path).with_context(|| format!("Failed to move {} into place", path.display()))?;
+        }
+        Ok(written)
+    }
+}
 
 pub fn export_to_json(input_path: &str, output_path: &str) -> Result<()> {
     let input = File::open(input_path)?;
@@ -37,3 +334,198 @@ pub fn export_to_json(input_path: &str, output_path: &str) -> Result<()> {
 
     Ok(())
 }
+
+/// A cell as a JSON value: numbers and booleans when the column's type (or, if
+/// unknown, the value itself) says so, null for empty cells, otherwise a string
+pub fn json_value(value: &str, data_type: Option<&InferredType>) -> Value {
+    let trimmed = value.trim();
+    if ColumnAnalyzer::is_null_like(trimmed) {
+        return Value::Null;
+    }
+    let inferred;
+    let data_type = match data_type {
+        Some(t) => t,
+        None => {
+            inferred = ColumnAnalyzer::infer_value_type(trimmed);
+            &inferred
+        }
+    };
+    // Leading zeros (zip codes, IDs) would be lost as numbers
+    let leading_zero = trimmed.len() > 1 && trimmed.trim_start_matches('-').starts_with('0') && !trimmed.contains('.');
+    match data_type {
+        InferredType::Integer | InferredType::Float if !leading_zero => {
+            if let Ok(n) = trimmed.parse::<i64>() {
+                return Value::from(n);
+            }
+            if let Some(n) = trimmed.parse::<f64>().ok().and_then(serde_json::Number::from_f64) {
+                return Value::Number(n);
+            }
+        }
+        InferredType::Boolean => match trimmed.to_lowercase().as_str() {
+            "true" | "yes" => return Value::Bool(true),
+            "false" | "no" => return Value::Bool(false),
+            _ => {}
+        },
+        _ => {}
+    }
+    Value::String(value.to_string())
+}
+
+/// A row as a JSON object keyed by header. Repeated header names get a " (2)"
+/// suffix so no field is overwritten.
+pub fn row_to_json(headers: &[String], values: &[String], types: &[Option<InferredType>]) -> Map<String, Value> {
+    let mut map = Map::new();
+    for (i, value) in values.iter().enumerate() {
+        let header = headers.get(i).cloned().unwrap_or_else(|| format!("Column {}", i + 1));
+        let mut key = header.clone();
+        let mut n = 2;
+        while map.contains_key(&key) {
+            key = format!("{} ({})", header, n);
+            n += 1;
+        }
+        map.insert(key, json_value(value, types.get(i).and_then(Option::as_ref)));
+    }
+    map
+}
+
+/// A small table held in memory, e.g. the series behind a chart, as JSON
+/// (an array of objects) when `path` ends in .json and CSV otherwise
+pub fn write_table(headers: &[String], rows: &[Vec<String>], path: &Path) -> Result<()> {
+    let is_json = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("json"));
+    write_via_temp(path, |tmp_path| {
+        if !is_json {
+            let mut writer = csv::Writer::from_path(tmp_path).with_context(|| format!("Failed to create {}", path.display()))?;
+            writer.write_record(headers)?;
+            for row in rows {
+                writer.write_record(row)?;
+            }
+            writer.flush()?;
+            return Ok(());
+        }
+        let objects: Vec<Value> = rows.iter().map(|row| Value::Object(row_to_json(headers, row, &[]))).collect();
+        let file = File::create(tmp_path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, &objects)?;
+        writer.flush()?;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_row_to_json_types() {
+        let headers = ["id", "zip", "price", "active", "id"].map(String::from);
+        let values = ["7", "01234", "9.5", "yes", ""].map(String::from);
+        let types = [None, None, Some(InferredType::Float), Some(InferredType::Boolean), None];
+        let json = Value::Object(row_to_json(&headers, &values, &types));
+
+        assert_eq!(json, serde_json::json!({"id": 7, "zip": "01234", "price": 9.5, "active": true, "id (2)": null}));
+        assert_eq!(json_value("12", Some(&InferredType::Text)), Value::String("12".to_string()));
+    }
+
+    #[test]
+    fn test_export_renames_and_reorders_columns() -> Result<()> {
+        let rows = [["id", "name", "qty"], ["1", "pear", "3"], ["2", "fig", ""]];
+        let row_at = |r: usize| rows[r].iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        let mut options = ExportOptions {
+            columns: vec![
+                ExportColumn { source: 2, name: "count".to_string() },
+                ExportColumn { source: 1, name: "fruit".to_string() },
+            ],
+            ..Default::default()
+        };
+        let dir = tempfile::TempDir::new()?;
+        let path = dir.path().join("out");
+
+        export_rows(&path, &options, 1..3, row_at, &[], &FormatMap::new(), &Progress::default())?;
+        let json: Value = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+        assert_eq!(json, serde_json::json!([{"count": 3, "fruit": "pear"}, {"count": null, "fruit": "fig"}]));
+
+        options.format = ExportFormat::Csv;
+        export_rows(&path, &options, 1..3, row_at, &[], &FormatMap::new(), &Progress::default())?;
+        assert_eq!(std::fs::read_to_string(&path)?, "count,fruit\n3,pear\n,fig\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_parts_and_lines() -> Result<()> {
+        let row_at = |r: usize| vec![r.to_string()];
+        let mut options = ExportOptions {
+            format: ExportFormat::JsonLines,
+            columns: vec![ExportColumn { source: 0, name: "n".to_string() }],
+            ..Default::default()
+        };
+        let dir = tempfile::TempDir::new()?;
+        let path = dir.path().join("rows.jsonl");
+        export_rows(&path, &options, 0..3, row_at, &[], &FormatMap::new(), &Progress::default())?;
+        assert_eq!(std::fs::read_to_string(&path)?, "{\"n\":0}\n{\"n\":1}\n{\"n\":2}\n");
+
+        // Each row is 7 bytes with its separator, so two fit under 16
+        options.format = ExportFormat::Json;
+        options.max_part_bytes = Some(16);
+        let parts = export_rows(&dir.path().join("rows.json"), &options, 0..5, row_at, &[], &FormatMap::new(), &Progress::default())?;
+        let names: Vec<_> = parts.iter().map(|p| p.file_name().unwrap().to_string_lossy().to_string()).collect();
+        assert_eq!(names, ["rows-part-0001.json", "rows-part-0002.json", "rows-part-0003.json"]);
+        assert_eq!(std::fs::read_to_string(&parts[0])?, "[{\"n\":0},{\"n\":1}]");
+        assert_eq!(std::fs::read_to_string(&parts[2])?, "[{\"n\":4}]");
+        Ok(())
+    }
+
+    #[test]
+    fn test_preset_resolves_columns_by_name() -> Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let input = dir.path().join("sales.csv");
+        std::fs::write(&input, "region,total,id\nnorth,10,1\nsouth,7,2\n")?;
+        let preset = ExportPreset {
+            name: "monthly".to_string(),
+            options: ExportOptions {
+                format: ExportFormat::Csv,
+                columns: vec![
+                    ExportColumn { source: 0, name: "ID".to_string() },
+                    ExportColumn { source: 1, name: "Total".to_string() },
+                ],
+                ..Default::default()
+            },
+            source_headers: vec!["id".to_string(), "total".to_string()],
+            destination: "{dir}/out/{stem}-{date}.{ext}".to_string(),
+        };
+        let date = chrono::NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        assert_eq!(preset.destination_for(&input, date), dir.path().join("out/sales-2024-03-01.csv"));
+
+        let written = run_preset(&preset, &input, &Progress::default())?;
+        assert_eq!(std::fs::read_to_string(&written[0])?, "ID,Total\n1,10\n2,7\n");
+        assert!(preset.resolve(&["id".to_string()]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_preset_never_overwrites_its_input() -> Result<()> {
+        let dir = tempfile::TempDir::new()?;
+        let input = dir.path().join("sales.csv");
+        std::fs::write(&input, "id,total\n1,10\n")?;
+        let mut preset = ExportPreset {
+            name: "copy".to_string(),
+            options: ExportOptions {
+                format: ExportFormat::Csv,
+                columns: vec![ExportColumn { source: 0, name: "id".to_string() }],
+                ..Default::default()
+            },
+            source_headers: vec!["id".to_string()],
+            destination: DEFAULT_DESTINATION.to_string(),
+        };
+        let written = run_preset(&preset, &input, &Progress::default())?;
+        assert_eq!(written, [dir.path().join("sales-export.csv")]);
+
+        preset.destination = "{dir}/{stem}.{ext}".to_string();
+        assert!(run_preset(&preset, &input, &Progress::default()).is_err());
+        assert_eq!(std::fs::read_to_string(&input)?, "id,total\n1,10\n");
+        let leftovers: Vec<_> = std::fs::read_dir(dir.path())?.filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().ends_with(".csvit-tmp"))
+            .collect();
+        assert!(leftovers.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,58 @@
+use regex::Regex;
+
+/// Headers for the columns a pattern's capture groups become: a named group
+/// keeps its name, others are numbered after the source column
+pub fn group_headers(regex: &Regex, source_header: &str) -> Vec<String> {
+    regex
+        .capture_names()
+        .enumerate()
+        .skip(1)
+        .map(|(i, name)| name.map_or_else(|| format!("{}_{}", source_header, i), str::to_string))
+        .collect()
+}
+
+/// The capture groups of the first match in `value`, or None without a match.
+/// Groups that took no part in the match are empty.
+pub fn extract_groups(regex: &Regex, value: &str) -> Option<Vec<String>> {
+    let captures = regex.captures(value)?;
+    Some(
+        captures
+            .iter()
+            .skip(1)
+            .map(|group| group.map_or_else(String::new, |m| m.as_str().to_string()))
+            .collect(),
+    )
+}
+
+/// One column of values per capture group; rows without a match get empty cells
+pub fn extract_columns<'a>(regex: &Regex, values: impl IntoIterator<Item = &'a str>) -> Vec<Vec<String>> {
+    let groups = regex.captures_len() - 1;
+    let mut columns = vec![Vec::new(); groups];
+    for value in values {
+        let found = extract_groups(regex, value).unwrap_or_else(|| vec![String::new(); groups]);
+        for (column, cell) in columns.iter_mut().zip(found) {
+            column.push(cell);
+        }
+    }
+    columns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_capture_groups() {
+        let regex = Regex::new(r"^([^@]+)@(?P<domain>[\w.]+)$").unwrap();
+        assert_eq!(group_headers(&regex, "email"), ["email_1", "domain"]);
+        assert_eq!(extract_groups(&regex, "ann@example.com"), Some(vec!["ann".to_string(), "example.com".to_string()]));
+        assert_eq!(extract_groups(&regex, "not an email"), None);
+
+        let columns = extract_columns(&regex, ["a@x.org", "nope", "b@y.net"]);
+        assert_eq!(columns, [["a", "", "b"], ["x.org", "", "y.net"]]);
+
+        // An optional group that didn't match leaves its cell empty
+        let optional = Regex::new(r"(\d+)(px)?").unwrap();
+        assert_eq!(extract_groups(&optional, "12"), Some(vec!["12".to_string(), String::new()]));
+    }
+}
@@ -0,0 +1,258 @@
+use anyhow::{Context, Result};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use super::jobs::Progress;
+
+/// Bytes inspected for encoding and line endings
+const SAMPLE_BYTES: u64 = 64 * 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+    Cr,
+    Mixed,
+    /// A single line, or an empty file
+    None,
+}
+
+impl LineEnding {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "LF (Unix)",
+            LineEnding::CrLf => "CRLF (Windows)",
+            LineEnding::Cr => "CR (classic Mac)",
+            LineEnding::Mixed => "Mixed",
+            LineEnding::None => "None",
+        }
+    }
+
+    /// Short form for badges
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "LF",
+            LineEnding::CrLf => "CRLF",
+            LineEnding::Cr => "CR",
+            LineEnding::Mixed => "Mixed",
+            LineEnding::None => "None",
+        }
+    }
+
+    /// Count line breaks by kind and name the style
+    pub fn detect(sample: &[u8]) -> Self {
+        match Self::count(sample) {
+            (0, 0, 0) => LineEnding::None,
+            (_, 0, 0) => LineEnding::Lf,
+            (0, _, 0) => LineEnding::CrLf,
+            (0, 0, _) => LineEnding::Cr,
+            _ => LineEnding::Mixed,
+        }
+    }
+
+    /// The most common kind of line break, even when others occur too
+    pub fn dominant(sample: &[u8]) -> Self {
+        let (lf, crlf, cr) = Self::count(sample);
+        if lf + crlf + cr == 0 {
+            LineEnding::None
+        } else if lf >= crlf && lf >= cr {
+            LineEnding::Lf
+        } else if crlf >= cr {
+            LineEnding::CrLf
+        } else {
+            LineEnding::Cr
+        }
+    }
+
+    /// How one line (or record) ends; None for the last line without a break
+    pub fn of_line(line: &[u8]) -> Self {
+        match line {
+            [.., b'\r', b'\n'] => LineEnding::CrLf,
+            [.., b'\n'] => LineEnding::Lf,
+            [.., b'\r'] => LineEnding::Cr,
+            _ => LineEnding::None,
+        }
+    }
+
+    /// Line feeds, CR LF pairs and lone carriage returns in `sample`
+    fn count(sample: &[u8]) -> (usize, usize, usize) {
+        let (mut lf, mut crlf, mut cr) = (0, 0, 0);
+        let mut i = 0;
+        while i < sample.len() {
+            match sample[i] {
+                b'\r' if sample.get(i + 1) == Some(&b'\n') => {
+                    crlf += 1;
+                    i += 1;
+                }
+                b'\r' => cr += 1,
+                b'\n' => lf += 1,
+                _ => {}
+            }
+            i += 1;
+        }
+        (lf, crlf, cr)
+    }
+}
+
+/// `bytes` as a classic hex dump: each line is the file offset (counting
+/// from `base`), 16 bytes in hex, and the printable ASCII ones as text
+pub fn hex_dump(bytes: &[u8], base: u64) -> String {
+    use std::fmt::Write;
+    let mut out = String::new();
+    for (i, chunk) in bytes.chunks(16).enumerate() {
+        let _ = write!(out, "{:08x}  ", base + i as u64 * 16);
+        for j in 0..16 {
+            match chunk.get(j) {
+                Some(b) => {
+                    let _ = write!(out, "{:02x} ", b);
+                }
+                None => out.push_str("   "),
+            }
+            if j == 7 {
+                out.push(' ');
+            }
+        }
+        out.push_str(" |");
+        out.extend(chunk.iter().map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' }));
+        out.push_str("|\n");
+    }
+    out
+}
+
+/// Name the encoding from a byte-order mark, or by checking the sample is valid UTF-8
+pub fn detect_encoding(sample: &[u8]) -> &'static str {
+    if sample.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        return "UTF-8 with BOM";
+    }
+    if sample.starts_with(&[0xFF, 0xFE]) {
+        return "UTF-16 LE";
+    }
+    if sample.starts_with(&[0xFE, 0xFF]) {
+        return "UTF-16 BE";
+    }
+    if sample.is_ascii() {
+        return "ASCII";
+    }
+    match std::str::from_utf8(sample) {
+        Ok(_) => "UTF-8",
+        // The sample may end part-way through a multi-byte character
+        Err(e) if e.error_len().is_none() => "UTF-8",
+        Err(_) => "Not UTF-8 (likely Latin-1 / Windows-1252)",
+    }
+}
+
+/// Human-readable size with decimal units, e.g. "4.2 MB"
+pub fn format_size(bytes: u64) -> String {
+    let size = bytes as f64;
+    if size >= 1e9 {
+        format!("{:.1} GB", size / 1e9)
+    } else if size >= 1e6 {
+        format!("{:.1} MB", size / 1e6)
+    } else if size >= 1e3 {
+        format!("{:.1} KB", size / 1e3)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+/// Size and modification time, to notice when a file changes under an open document
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FileStamp {
+    pub len: u64,
+    pub modified: Option<std::time::SystemTime>,
+}
+
+impl FileStamp {
+    /// None when the file can't be read (e.g. it was deleted)
+    pub fn read(path: &Path) -> Option<Self> {
+        let meta = std::fs::metadata(path).ok()?;
+        Some(Self { len: meta.len(), modified: meta.modified().ok() })
+    }
+}
+
+/// What the File Info dialog shows about a file on disk
+#[derive(Clone, Debug)]
+pub struct FileInfo {
+    pub size: u64,
+    pub modified: Option<std::time::SystemTime>,
+    pub encoding: &'static str,
+    pub line_ending: LineEnding,
+}
+
+impl FileInfo {
+    pub fn read(path: &Path) -> Result<Self> {
+        let meta = std::fs::metadata(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut sample = Vec::new();
+        File::open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?
+            .take(SAMPLE_BYTES)
+            .read_to_end(&mut sample)?;
+        Ok(Self {
+            size: meta.len(),
+            modified: meta.modified().ok(),
+            encoding: detect_encoding(&sample),
+            line_ending: LineEnding::detect(&sample),
+        })
+    }
+}
+
+/// Hex SHA-256 of a file's contents, streamed so large files don't need to fit in memory
+pub fn sha256_file(path: &Path, progress: &Progress) -> Result<String> {
+    let mut file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    progress.set_total(file.metadata().map(|m| m.len() as usize).unwrap_or(0));
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1024 * 1024];
+    loop {
+        if progress.is_cancelled() {
+            anyhow::bail!("Checksum cancelled");
+        }
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        progress.inc(n);
+    }
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_file_info_and_checksum() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("data.csv");
+        std::fs::write(&path, "abc")?;
+
+        assert_eq!(
+            sha256_file(&path, &Progress::default())?,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        let stamp = FileStamp::read(&path);
+        assert_eq!(stamp.map(|s| s.len), Some(3));
+        std::fs::write(&path, "abcd")?;
+        assert_ne!(FileStamp::read(&path), stamp);
+        std::fs::write(&path, "abc")?;
+
+        let info = FileInfo::read(&path)?;
+        assert_eq!((info.size, info.encoding, info.line_ending), (3, "ASCII", LineEnding::None));
+
+        assert_eq!(LineEnding::detect(b"a\r\nb\r\n"), LineEnding::CrLf);
+        assert_eq!(LineEnding::detect(b"a\nb\r\n"), LineEnding::Mixed);
+        assert_eq!(LineEnding::dominant(b"a\r\nb\nc\r\n"), LineEnding::CrLf);
+        assert_eq!(LineEnding::of_line(b"a,b\r\n"), LineEnding::CrLf);
+        assert_eq!(LineEnding::of_line(b"a,b"), LineEnding::None);
+        assert_eq!(detect_encoding("é".as_bytes()), "UTF-8");
+        assert_eq!(detect_encoding(&[b'a', 0xE9, b'b']), "Not UTF-8 (likely Latin-1 / Windows-1252)");
+        assert_eq!(
+            hex_dump(b"a,\"b\"\r\n", 0x20),
+            "00000020  61 2c 22 62 22 0d 0a                              |a,\"b\"..|\n"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::HashSet;
+use std::ops::Range;
+
+use super::jobs::Progress;
+
+/// What a cell has to be for its row to pass
+#[derive(Clone, Debug, PartialEq)]
+pub enum FilterTest {
+    Equals(String),
+    NotEquals(String),
+    /// Case-insensitive substring match
+    Contains(String),
+    /// Any of these values, e.g. picked in the distinct-values browser
+    OneOf(HashSet<String>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FilterCondition {
+    pub column: usize,
+    pub test: FilterTest,
+}
+
+impl FilterCondition {
+    pub fn matches(&self, values: &[String]) -> bool {
+        let cell = values.get(self.column).map_or("", |v| v.trim());
+        match self.test {
+            FilterTest::Equals(ref value) => cell == value.trim(),
+            FilterTest::NotEquals(ref value) => cell != value.trim(),
+            FilterTest::Contains(ref value) => cell.to_lowercase().contains(&value.to_lowercase()),
+            FilterTest::OneOf(ref values) => values.contains(cell),
+        }
+    }
+
+    /// The test in a few words, e.g. `= "Oslo"` or `is one of 3 values`
+    pub fn describe(&self) -> String {
+        let quoted = |value: &str| match value.char_indices().nth(30) {
+            Some((end, _)) => format!("\"{}…\"", &value[..end]),
+            None => format!("\"{}\"", value),
+        };
+        match self.test {
+            FilterTest::Equals(ref value) => format!("= {}", quoted(value)),
+            FilterTest::NotEquals(ref value) => format!("≠ {}", quoted(value)),
+            FilterTest::Contains(ref value) => format!("contains {}", quoted(value)),
+            FilterTest::OneOf(ref values) if values.len() == 1 => format!("= {}", quoted(values.iter().next().unwrap())),
+            FilterTest::OneOf(ref values) => format!("is one of {} values", values.len()),
+        }
+    }
+}
+
+/// Rows shown in the table: those meeting every condition
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RowFilter {
+    pub conditions: Vec<FilterCondition>,
+    /// Only these rows, e.g. points brushed in the graph view
+    pub rows: Option<HashSet<usize>>,
+}
+
+impl RowFilter {
+    pub fn is_empty(&self) -> bool {
+        self.conditions.is_empty() && self.rows.is_none()
+    }
+
+    /// Add a condition, replacing an identical one
+    pub fn add(&mut self, condition: FilterCondition) {
+        self.conditions.retain(|c| *c != condition);
+        self.conditions.push(condition);
+    }
+
+    pub fn matches(&self, values: &[String]) -> bool {
+        self.conditions.iter().all(|c| c.matches(values))
+    }
+
+    /// The rows in `rows` that pass, in order
+    pub fn matching_rows(&self, rows: Range<usize>, row_at: impl Fn(usize) -> Vec<String>, progress: &Progress) -> Vec<usize> {
+        progress.set_total(rows.len());
+        let start = rows.start;
+        let mut matching = Vec::new();
+        for row in rows {
+            if (row - start).is_multiple_of(10_000) {
+                if progress.is_cancelled() {
+                    break;
+                }
+                progress.set_done(row - start);
+            }
+            if self.rows.as_ref().is_some_and(|rows| !rows.contains(&row)) {
+                continue;
+            }
+            if self.matches(&row_at(row)) {
+                matching.push(row);
+            }
+        }
+        matching
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_conditions() {
+        let rows = [["Oslo", "NO"], ["oslo kommune", "NO"], ["Rome", "IT"], ["Bergen", "NO"]];
+        let row_at = |r: usize| rows[r].iter().map(|v| v.to_string()).collect::<Vec<_>>();
+
+        let mut filter = RowFilter::default();
+        filter.add(FilterCondition { column: 1, test: FilterTest::Equals("NO".to_string()) });
+        assert_eq!(filter.matching_rows(0..rows.len(), row_at, &Progress::default()), [0, 1, 3]);
+
+        filter.add(FilterCondition { column: 0, test: FilterTest::Contains("OSLO".to_string()) });
+        filter.add(FilterCondition { column: 0, test: FilterTest::Contains("OSLO".to_string()) });
+        assert_eq!(filter.conditions.len(), 2);
+        assert_eq!(filter.matching_rows(0..rows.len(), row_at, &Progress::default()), [0, 1]);
+
+        filter.add(FilterCondition { column: 0, test: FilterTest::NotEquals("Oslo".to_string()) });
+        assert_eq!(filter.matching_rows(0..rows.len(), row_at, &Progress::default()), [1]);
+
+        let one_of = FilterCondition { column: 0, test: FilterTest::OneOf(["Rome", "Bergen"].map(String::from).into()) };
+        let mut filter = RowFilter { conditions: vec![one_of], ..Default::default() };
+        assert_eq!(filter.matching_rows(0..rows.len(), row_at, &Progress::default()), [2, 3]);
+
+        filter.rows = Some([0, 3].into());
+        assert_eq!(filter.matching_rows(0..rows.len(), row_at, &Progress::default()), [3]);
+    }
+}
@@ -1,6 +1,9 @@
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use super::scripting::RowExpression;
+
 /// Cell formatting information
 #[derive(Clone, Debug, Serialize, Deserialize, Default)]
 pub struct CellFormat {
@@ -119,3 +122,207 @@ impl CellFormat {
         }
     }
 }
+
+/// Colors a whole row where an expression holds, e.g. `status == "failed"`.
+/// Rules are checked while rows are drawn, in order; the first one a row meets wins.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct RowColorRule {
+    pub expression: String,
+    /// RGBA, like `CellFormat`
+    pub color: [u8; 4],
+    #[serde(default = "rule_enabled")]
+    pub enabled: bool,
+}
+
+fn rule_enabled() -> bool {
+    true
+}
+
+impl Default for RowColorRule {
+    fn default() -> Self {
+        Self { expression: String::new(), color: [230, 80, 70, 70], enabled: true }
+    }
+}
+
+/// Enabled row color rules, compiled against the document's headers
+#[derive(Default)]
+pub struct RowColors {
+    rules: Vec<([u8; 4], RowExpression)>,
+}
+
+impl RowColors {
+    pub fn compile(rules: &[RowColorRule], headers: &[String]) -> Result<Self> {
+        let rules = rules.iter()
+            .filter(|rule| rule.enabled && !rule.expression.trim().is_empty())
+            .map(|rule| {
+                let expression = RowExpression::compile(&rule.expression, headers)
+                    .with_context(|| format!("Invalid row color rule \"{}\"", rule.expression))?;
+                Ok((rule.color, expression))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Color of the first rule `values` meets; a rule that fails to evaluate doesn't match
+    pub fn color(&self, values: &[String]) -> Option<[u8; 4]> {
+        self.rules.iter()
+            .find(|(_, expression)| expression.eval_bool(values) == Ok(true))
+            .map(|(color, _)| *color)
+    }
+}
+
+/// Input layouts recognised when reformatting dates
+const DATE_INPUTS: &[&str] = &["%Y-%m-%d", "%Y/%m/%d", "%d/%m/%Y", "%d.%m.%Y"];
+const DATETIME_INPUTS: &[&str] = &["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"];
+
+/// Per-column display format, applied when rendering; the stored values are never changed
+#[derive(Clone, Debug, Serialize, Deserialize, Default, PartialEq)]
+pub struct ColumnFormat {
+    /// Fixed number of decimal places; None keeps the value's own precision
+    pub decimals: Option<usize>,
+    pub thousands_separator: bool,
+    /// Show 0.25 as 25%
+    pub percent: bool,
+    /// Symbol placed before numbers, e.g. "$" or "€"
+    pub currency: Option<String>,
+    /// strftime-style pattern for date values, e.g. "%d %b %Y"
+    pub date_format: Option<String>,
+}
+
+impl ColumnFormat {
+    pub fn is_plain(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Display text for `value`; values that aren't numbers or dates come back unchanged
+    pub fn apply(&self, value: &str) -> String {
+        let trimmed = value.trim();
+        if let Some(ref pattern) = self.date_format
+            && let Some(date) = format_date(trimmed, pattern)
+        {
+            return date;
+        }
+        self.format_number(trimmed).unwrap_or_else(|| value.to_string())
+    }
+
+    fn format_number(&self, value: &str) -> Option<String> {
+        if self.decimals.is_none() && !self.thousands_separator && !self.percent && self.currency.is_none() {
+            return None;
+        }
+        let number: f64 = value.parse().ok()?;
+        if !number.is_finite() {
+            return None;
+        }
+        let text = match (self.decimals, self.percent) {
+            (Some(places), true) => format!("{:.*}", places, number * 100.0),
+            (Some(places), false) => format!("{:.*}", places, number),
+            // Round away float noise like 12.299999999999999
+            (None, true) => {
+                let text = format!("{:.6}", number * 100.0);
+                text.trim_end_matches('0').trim_end_matches('.').to_string()
+            }
+            (None, false) => value.trim_start_matches('+').to_string(),
+        };
+
+        let (sign, digits) = match text.strip_prefix('-') {
+            Some(rest) => ("-", rest),
+            None => ("", text.as_str()),
+        };
+        let (int, frac) = match digits.split_once('.') {
+            Some((int, frac)) => (int, Some(frac)),
+            None => (digits, None),
+        };
+        // Exponent forms like 1e5 are left ungrouped
+        let int = if self.thousands_separator && int.bytes().all(|b| b.is_ascii_digit()) {
+            group_thousands(int)
+        } else {
+            int.to_string()
+        };
+
+        let mut out = format!("{}{}{}", sign, self.currency.as_deref().unwrap_or(""), int);
+        if let Some(frac) = frac {
+            out.push('.');
+            out.push_str(frac);
+        }
+        if self.percent {
+            out.push('%');
+        }
+        Some(out)
+    }
+}
+
+fn group_thousands(digits: &str) -> String {
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn format_date(value: &str, pattern: &str) -> Option<String> {
+    use chrono::{NaiveDate, NaiveDateTime};
+    use std::fmt::Write;
+
+    let parsed = DATETIME_INPUTS.iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(value, f).ok())
+        .or_else(|| {
+            DATE_INPUTS.iter()
+                .find_map(|f| NaiveDate::parse_from_str(value, f).ok())
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        })?;
+    // An invalid pattern makes formatting fail rather than panic
+    let mut out = String::new();
+    write!(out, "{}", parsed.format(pattern)).ok()?;
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_column_format_display() {
+        let money = ColumnFormat {
+            decimals: Some(2),
+            thousands_separator: true,
+            currency: Some("$".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(money.apply("-1234567.5"), "-$1,234,567.50");
+        assert_eq!(money.apply("n/a"), "n/a");
+
+        let percent = ColumnFormat { percent: true, ..Default::default() };
+        assert_eq!(percent.apply("0.123"), "12.3%");
+
+        let date = ColumnFormat { date_format: Some("%d %b %Y".to_string()), ..Default::default() };
+        assert_eq!(date.apply("2024-03-07"), "07 Mar 2024");
+        assert_eq!(date.apply("42"), "42");
+        assert!(ColumnFormat::default().is_plain());
+    }
+
+    #[test]
+    fn test_row_colors() -> Result<()> {
+        let headers = vec!["id".to_string(), "status".to_string()];
+        let row = |status: &str| vec!["1".to_string(), status.to_string()];
+        let rules = [
+            RowColorRule { expression: r#"status == "failed""#.to_string(), color: [255, 0, 0, 80], enabled: true },
+            RowColorRule { expression: "id > 0".to_string(), color: [0, 0, 255, 80], enabled: false },
+            RowColorRule { expression: r#"status == "" || status == ()"#.to_string(), color: [128, 128, 128, 80], enabled: true },
+        ];
+        let colors = RowColors::compile(&rules, &headers)?;
+        assert_eq!(colors.color(&row("failed")), Some([255, 0, 0, 80]));
+        assert_eq!(colors.color(&row("")), Some([128, 128, 128, 80]));
+        assert_eq!(colors.color(&row("ok")), None);
+
+        let broken = [RowColorRule { expression: "status ==".to_string(), ..Default::default() }];
+        assert!(RowColors::compile(&broken, &headers).is_err());
+        Ok(())
+    }
+}
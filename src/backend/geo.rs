@@ -0,0 +1,177 @@
+use std::ops::Range;
+
+use super::jobs::Progress;
+
+/// Points kept for the map preview; further valid rows are only counted
+const MAX_MAP_POINTS: usize = 50_000;
+/// Problem rows listed individually; the rest are only counted
+const MAX_LISTED_ISSUES: usize = 1_000;
+
+const LATITUDE_NAMES: &[&str] = &["lat", "latitude", "lat_deg", "y_lat"];
+const LONGITUDE_NAMES: &[&str] = &["lon", "lng", "long", "longitude", "lon_deg", "x_lon"];
+
+/// A latitude column and a longitude column, found by their headers or,
+/// failing that, by their values (the first numeric column pair whose
+/// samples fit -90..90 and -180..180 with at least one longitude outside ±90).
+pub fn detect_lat_lon(headers: &[String], samples: &[Vec<String>]) -> Option<(usize, usize)> {
+    let named = |names: &[&str]| {
+        headers.iter().position(|h| {
+            let h = h.trim().to_lowercase();
+            names.iter().any(|n| h == *n || h.ends_with(&format!("_{}", n)))
+        })
+    };
+    if let (Some(lat), Some(lon)) = (named(LATITUDE_NAMES), named(LONGITUDE_NAMES)) {
+        return Some((lat, lon));
+    }
+
+    let ranges: Vec<Option<(f64, f64)>> = (0..headers.len())
+        .map(|c| {
+            let mut values = samples.iter().filter_map(|row| row.get(c)).filter(|v| !v.trim().is_empty()).peekable();
+            values.peek()?;
+            values.try_fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| {
+                let x: f64 = v.trim().parse().ok()?;
+                Some((lo.min(x), hi.max(x)))
+            })
+        })
+        .collect();
+    let fits = |c: usize, limit: f64| ranges[c].is_some_and(|(lo, hi)| lo >= -limit && hi <= limit);
+    let wide = |c: usize| ranges[c].is_some_and(|(lo, hi)| lo < -90.0 || hi > 90.0);
+    (0..headers.len().saturating_sub(1))
+        .find_map(|c| {
+            let (a, b) = (c, c + 1);
+            if fits(a, 90.0) && fits(b, 180.0) && wide(b) {
+                Some((a, b))
+            } else if fits(b, 90.0) && fits(a, 180.0) && wide(a) {
+                Some((b, a))
+            } else {
+                None
+            }
+        })
+}
+
+/// What's wrong with a row's coordinates
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GeoIssue {
+    NotANumber,
+    LatitudeOutOfRange,
+    LongitudeOutOfRange,
+    /// Latitude is out of range but the pair is valid the other way round
+    Swapped,
+    /// Exactly 0,0, usually a placeholder rather than a real place
+    NullIsland,
+    /// One of the pair is missing
+    Incomplete,
+}
+
+impl GeoIssue {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GeoIssue::NotANumber => "Not a number",
+            GeoIssue::LatitudeOutOfRange => "Latitude outside -90..90",
+            GeoIssue::LongitudeOutOfRange => "Longitude outside -180..180",
+            GeoIssue::Swapped => "Latitude and longitude look swapped",
+            GeoIssue::NullIsland => "0, 0 (likely a placeholder)",
+            GeoIssue::Incomplete => "Only one of the pair is set",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct GeoReport {
+    /// Valid points as [longitude, latitude], for plotting
+    pub points: Vec<[f64; 2]>,
+    pub valid: usize,
+    /// Rows with neither value set
+    pub empty: usize,
+    /// (row, issue), up to `MAX_LISTED_ISSUES`
+    pub issues: Vec<(usize, GeoIssue)>,
+    pub issue_count: usize,
+}
+
+/// Check one pair of cells; Ok(None) when both are empty
+pub fn check_pair(lat: &str, lon: &str) -> Result<Option<(f64, f64)>, GeoIssue> {
+    let (lat, lon) = (lat.trim(), lon.trim());
+    match (lat.is_empty(), lon.is_empty()) {
+        (true, true) => return Ok(None),
+        (true, false) | (false, true) => return Err(GeoIssue::Incomplete),
+        _ => {}
+    }
+    let (Ok(lat), Ok(lon)) = (lat.parse::<f64>(), lon.parse::<f64>()) else {
+        return Err(GeoIssue::NotANumber);
+    };
+    if !lat.is_finite() || !lon.is_finite() {
+        return Err(GeoIssue::NotANumber);
+    }
+    if lat.abs() > 90.0 {
+        return Err(if lon.abs() <= 90.0 && lat.abs() <= 180.0 { GeoIssue::Swapped } else { GeoIssue::LatitudeOutOfRange });
+    }
+    if lon.abs() > 180.0 {
+        return Err(GeoIssue::LongitudeOutOfRange);
+    }
+    if lat == 0.0 && lon == 0.0 {
+        return Err(GeoIssue::NullIsland);
+    }
+    Ok(Some((lat, lon)))
+}
+
+/// Validate every row's coordinates and gather points for the map
+pub fn check_coordinates(
+    rows: Range<usize>,
+    pair_at: impl Fn(usize) -> (String, String),
+    progress: &Progress,
+) -> GeoReport {
+    progress.set_total(rows.len());
+    let start = rows.start;
+    let mut report = GeoReport::default();
+    for row in rows {
+        if (row - start).is_multiple_of(10_000) {
+            if progress.is_cancelled() {
+                break;
+            }
+            progress.set_done(row - start);
+        }
+        let (lat, lon) = pair_at(row);
+        match check_pair(&lat, &lon) {
+            Ok(Some((lat, lon))) => {
+                report.valid += 1;
+                if report.points.len() < MAX_MAP_POINTS {
+                    report.points.push([lon, lat]);
+                }
+            }
+            Ok(None) => report.empty += 1,
+            Err(issue) => {
+                report.issue_count += 1;
+                if report.issues.len() < MAX_LISTED_ISSUES {
+                    report.issues.push((row, issue));
+                }
+            }
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_and_check_coordinates() {
+        let headers = ["city", "Latitude", "Longitude"].map(String::from);
+        assert_eq!(detect_lat_lon(&headers, &[]), Some((1, 2)));
+
+        // Unnamed columns are recognised by their ranges
+        let headers = ["name", "a", "b"].map(String::from);
+        let samples = [["x", "-122.4", "37.8"], ["y", "2.35", "48.9"]].map(|r| r.map(String::from).to_vec());
+        assert_eq!(detect_lat_lon(&headers, &samples), Some((2, 1)));
+
+        let rows = [("51.5", "-0.12"), ("", ""), ("120", "45"), ("0", "0"), ("12", ""), ("abc", "1"), ("10", "190")];
+        let report = check_coordinates(0..rows.len(), |r| (rows[r].0.to_string(), rows[r].1.to_string()), &Progress::default());
+        assert_eq!((report.valid, report.empty, report.issue_count), (1, 1, 5));
+        assert_eq!(report.points, [[-0.12, 51.5]]);
+        let issues: Vec<_> = report.issues.iter().map(|&(_, issue)| issue).collect();
+        assert_eq!(
+            issues,
+            [GeoIssue::Swapped, GeoIssue::NullIsland, GeoIssue::Incomplete, GeoIssue::NotANumber, GeoIssue::LongitudeOutOfRange]
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use anyhow::{bail, Context, Result};
+use std::io::Read;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use super::jobs::Progress;
+use super::snapshots::read_records;
+
+/// Where a file stands in its git repository
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileState {
+    Unmodified,
+    /// Changed in the working tree since the last commit
+    Modified,
+    /// Changes staged for the next commit, none since
+    Staged,
+    /// New in the index, not yet in any commit
+    Added,
+    Untracked,
+    Ignored,
+}
+
+impl FileState {
+    pub fn name(self) -> &'static str {
+        match self {
+            FileState::Unmodified => "unmodified",
+            FileState::Modified => "modified",
+            FileState::Staged => "staged",
+            FileState::Added => "added",
+            FileState::Untracked => "untracked",
+            FileState::Ignored => "ignored",
+        }
+    }
+
+    /// Whether HEAD has a version of the file to compare with
+    pub fn in_head(self) -> bool {
+        matches!(self, FileState::Unmodified | FileState::Modified | FileState::Staged)
+    }
+
+    /// Read the two-letter code of `git status --porcelain` for one file;
+    /// no line at all means the file is unmodified
+    pub fn from_porcelain(line: &str) -> Self {
+        let mut code = line.chars();
+        let (x, y) = (code.next().unwrap_or(' '), code.next().unwrap_or(' '));
+        match (x, y) {
+            ('?', '?') => FileState::Untracked,
+            ('!', '!') => FileState::Ignored,
+            ('A', _) => FileState::Added,
+            (_, ' ') if x != ' ' => FileState::Staged,
+            (' ', ' ') => FileState::Unmodified,
+            _ => FileState::Modified,
+        }
+    }
+}
+
+/// A file's branch and state, for the status bar
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GitStatus {
+    pub branch: String,
+    pub state: FileState,
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .context("Failed to run git")?;
+    if !output.status.success() {
+        bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Split a file path into the folder git runs in and the name it's given
+fn locate(path: &Path) -> Option<(&Path, &std::ffi::OsStr)> {
+    let name = path.file_name()?;
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    Some((dir, name))
+}
+
+/// Status of the file at `path`, or None when it isn't inside a git
+/// repository or git isn't installed
+pub fn file_status(path: &Path) -> Option<GitStatus> {
+    let (dir, name) = locate(path)?;
+    let name = name.to_str()?;
+    let porcelain = git(dir, &["status", "--porcelain=v1", "--ignored", "--", name]).ok()?;
+    let state = porcelain.lines().next().map_or(FileState::Unmodified, FileState::from_porcelain);
+    // A repository without commits has no HEAD to name
+    let branch = git(dir, &["rev-parse", "--abbrev-ref", "HEAD"])
+        .map(|b| b.trim().to_string())
+        .unwrap_or_else(|_| "(no commits)".to_string());
+    Some(GitStatus { branch, state })
+}
+
+/// Stream the file's records as of the last commit, header first, to
+/// `visit`, parsed as `git show` writes them out rather than held as text first
+pub fn read_head(path: &Path, progress: &Progress, visit: &mut dyn FnMut(Vec<String>)) -> Result<()> {
+    let (dir, name) = locate(path).context("Not a file path")?;
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["show", &format!("HEAD:./{}", name.to_string_lossy())])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to run git")?;
+    let stdout = child.stdout.take().context("git gave no output")?;
+    // Drained alongside stdout, so git never blocks on a full stderr pipe
+    let mut stderr = child.stderr.take().context("git gave no error output")?;
+    let errors = std::thread::spawn(move || {
+        let mut text = String::new();
+        let _ = stderr.read_to_string(&mut text);
+        text
+    });
+    let read = read_records(stdout, progress, visit);
+    if read.is_err() {
+        // Cancelled or unreadable; git may still be writing
+        let _ = child.kill();
+    }
+    let status = child.wait().context("Failed to run git")?;
+    let errors = errors.join().unwrap_or_default();
+    if read.is_ok() && !status.success() {
+        match errors.trim() {
+            "" => bail!("git show failed ({})", status),
+            errors => bail!("git show failed ({}): {}", status, errors),
+        }
+    }
+    read
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::rebase::RowMatch;
+    use crate::backend::snapshots::{collect_table, diff_snapshot};
+
+    #[test]
+    fn test_porcelain_codes() {
+        let states: Vec<FileState> = ["?? a.csv", "!! a.csv", "A  a.csv", "M  a.csv", " M a.csv", "MM a.csv", ""]
+            .iter()
+            .map(|line| FileState::from_porcelain(line))
+            .collect();
+        assert_eq!(states, [
+            FileState::Untracked,
+            FileState::Ignored,
+            FileState::Added,
+            FileState::Staged,
+            FileState::Modified,
+            FileState::Modified,
+            FileState::Unmodified,
+        ]);
+    }
+
+    #[test]
+    fn test_read_head_against_a_repository() -> Result<()> {
+        // Skipped where git isn't installed
+        if Command::new("git").arg("--version").output().is_err() {
+            return Ok(());
+        }
+        let dir = tempfile::TempDir::new()?;
+        let path = dir.path().join("people.csv");
+        std::fs::write(&path, "id,name\n1,ann\n2,bob\n")?;
+        let run = |args: &[&str]| git(dir.path(), args);
+        run(&["init", "-q"])?;
+        run(&["add", "people.csv"])?;
+        run(&["-c", "user.name=t", "-c", "user.email=t@example.com", "commit", "-q", "-m", "people"])?;
+        std::fs::write(&path, "id,name\n0,zed\n1,ann\n2,Bob\n")?;
+
+        let (headers, rows) = collect_table(|visit| read_head(&path, &Progress::default(), visit))?;
+        assert_eq!(headers, ["id", "name"]);
+        assert_eq!(rows, [["1", "ann"], ["2", "bob"]]);
+
+        let now: Vec<Vec<String>> = [["0", "zed"], ["1", "ann"], ["2", "Bob"]].iter().map(|r| r.map(String::from).to_vec()).collect();
+        let diff = diff_snapshot(|visit| read_head(&path, &Progress::default(), visit), &headers, now.into_iter(), RowMatch::Key(0), &Progress::default())?;
+        assert_eq!(diff.cells, [(2, 1, "bob".to_string(), "Bob".to_string())]);
+        assert_eq!((diff.rows_added, diff.rows_removed), (1, 0));
+
+        // A file HEAD doesn't have is an error, not an empty table
+        let missing = read_head(&dir.path().join("missing.csv"), &Progress::default(), &mut |_| {});
+        assert!(missing.is_err_and(|e| e.to_string().contains("exit status")));
+        Ok(())
+    }
+}
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
-use crate::backend::editor::EditCommand;
+use crate::backend::audit::{AuditAction, AuditLog};
+use crate::backend::editor::{EditCommand, EditGenerations};
+use crate::backend::writer::CsvWriteOptions;
 
 /// An in-memory editable grid for CSV data with undo/redo support
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -10,6 +12,10 @@ pub struct EditableGrid {
     undo_stack: Vec<EditCommand>,
     #[serde(skip)]
     redo_stack: Vec<EditCommand>,
+    #[serde(skip)]
+    generations: EditGenerations,
+    #[serde(skip)]
+    audit: AuditLog,
     modified: bool,
 }
 
@@ -25,6 +31,8 @@ impl EditableGrid {
             rows: row_data,
             undo_stack: Vec::new(),
             redo_stack: Vec::new(),
+            generations: EditGenerations::default(),
+            audit: AuditLog::default(),
             modified: false,
         }
     }
@@ -35,11 +43,11 @@ impl EditableGrid {
         
         let headers = lines
             .next()
-            .map(|h| Self::parse_csv_row(h))
+            .map(Self::parse_csv_row)
             .unwrap_or_default();
         
         let rows: Vec<Vec<String>> = lines
-            .map(|line| Self::parse_csv_row(line))
+            .map(Self::parse_csv_row)
             .collect();
         
         Self {
@@ -47,6 +55,27 @@ impl EditableGrid {
             rows,
             undo_stack: Vec::new(),
             redo_stack: Vec::new(),
+            generations: EditGenerations::default(),
+            audit: AuditLog::default(),
+            modified: false,
+        }
+    }
+
+    /// Create from already-split rows. Short rows are padded to the header width.
+    pub fn from_rows(headers: Vec<String>, mut rows: Vec<Vec<String>>) -> Self {
+        let width = headers.len();
+        for row in &mut rows {
+            if row.len() < width {
+                row.resize(width, String::new());
+            }
+        }
+        Self {
+            headers,
+            rows,
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            generations: EditGenerations::default(),
+            audit: AuditLog::default(),
             modified: false,
         }
     }
@@ -84,34 +113,16 @@ impl EditableGrid {
         fields
     }
 
-    /// Convert to CSV text
-    pub fn to_csv(&self) -> String {
-        let mut output = String::new();
-        
-        // Headers
-        output.push_str(&self.row_to_csv(&self.headers));
-        output.push('\n');
-        
-        // Data rows
-        for row in &self.rows {
-            output.push_str(&self.row_to_csv(row));
-            output.push('\n');
-        }
-        
-        output
+    /// Convert to CSV text in the given dialect
+    pub fn to_csv(&self, options: &CsvWriteOptions) -> anyhow::Result<String> {
+        self.to_csv_between(options, b"", b"")
     }
 
-    fn row_to_csv(&self, row: &[String]) -> String {
-        row.iter()
-            .map(|cell| {
-                if cell.contains(',') || cell.contains('"') || cell.contains('\n') {
-                    format!("\"{}\"", cell.replace('"', "\"\""))
-                } else {
-                    cell.clone()
-                }
-            })
-            .collect::<Vec<_>>()
-            .join(",")
+    /// The table as CSV between lines kept above and below it, e.g. the
+    /// preamble and footer of the file it was converted from
+    pub fn to_csv_between(&self, options: &CsvWriteOptions, preamble: &[u8], footer: &[u8]) -> anyhow::Result<String> {
+        let rows = std::iter::once(&self.headers).chain(&self.rows).map(Vec::as_slice);
+        options.encode_rows_between(preamble, rows, footer)
     }
 
     // ---- Editing operations ----
@@ -129,13 +140,11 @@ impl EditableGrid {
     }
 
     pub fn set_cell(&mut self, row: usize, col: usize, value: String) {
-        if let Some(r) = self.rows.get_mut(row) {
-            if let Some(cell) = r.get_mut(col) {
-                let old_value = std::mem::replace(cell, value.clone());
-                let cmd = EditCommand::SetCell { row, col, old_value, new_value: value };
-                self.push_undo(cmd);
-                self.modified = true;
-            }
+        if let Some(cell) = self.rows.get_mut(row).and_then(|r| r.get_mut(col)) {
+            let old_value = std::mem::replace(cell, value.clone());
+            let cmd = EditCommand::SetCell { row, col, old_value, new_value: value };
+            self.push_undo(cmd);
+            self.modified = true;
         }
     }
 
@@ -169,6 +178,75 @@ impl EditableGrid {
         self.modified = true;
     }
 
+    /// Insert a row with the given values at `at` (clamped to the end),
+    /// padding or truncating it to the grid's column count
+    pub fn insert_row(&mut self, at: usize, mut data: Vec<String>) {
+        data.resize(self.num_cols(), String::new());
+        let at = at.min(self.rows.len());
+        self.rows.insert(at, data.clone());
+        let cmd = EditCommand::InsertRow { at, data };
+        self.push_undo(cmd);
+        self.modified = true;
+    }
+
+    /// Insert several rows at `at` as a single undoable step
+    pub fn insert_rows(&mut self, at: usize, rows: Vec<Vec<String>>) {
+        let at = at.min(self.rows.len());
+        let cmds: Vec<EditCommand> = rows
+            .into_iter()
+            .enumerate()
+            .map(|(i, mut data)| {
+                data.resize(self.num_cols(), String::new());
+                self.rows.insert(at + i, data.clone());
+                EditCommand::InsertRow { at: at + i, data }
+            })
+            .collect();
+        self.push_batch(cmds);
+    }
+
+    /// Set several cells as a single undoable step. Rows are appended as
+    /// needed to fit the values; cells past the last column are dropped.
+    pub fn set_cells(&mut self, cells: impl IntoIterator<Item = (usize, usize, String)>) {
+        let mut cmds = Vec::new();
+        for (row, col, value) in cells {
+            if col >= self.num_cols() {
+                continue;
+            }
+            self.append_empty_rows(row + 1, &mut cmds);
+            let cell = &mut self.rows[row][col];
+            let old_value = std::mem::replace(cell, value.clone());
+            cmds.push(EditCommand::SetCell { row, col, old_value, new_value: value });
+        }
+        self.push_batch(cmds);
+    }
+
+    /// Insert columns at `at` filled from the top, as a single undoable step
+    pub fn insert_columns(&mut self, at: usize, columns: Vec<(String, Vec<String>)>) {
+        let at = at.min(self.num_cols());
+        let mut cmds = Vec::new();
+        for (i, (header, values)) in columns.into_iter().enumerate() {
+            let col = at + i;
+            let cmd = EditCommand::InsertColumn { at: col, header };
+            self.apply_command(&cmd);
+            cmds.push(cmd);
+            for (row, value) in values.into_iter().enumerate() {
+                self.append_empty_rows(row + 1, &mut cmds);
+                let old_value = std::mem::replace(&mut self.rows[row][col], value.clone());
+                cmds.push(EditCommand::SetCell { row, col, old_value, new_value: value });
+            }
+        }
+        self.push_batch(cmds);
+    }
+
+    /// Grow the grid to at least `len` rows, recording the inserts
+    fn append_empty_rows(&mut self, len: usize, cmds: &mut Vec<EditCommand>) {
+        while self.rows.len() < len {
+            let data = vec![String::new(); self.num_cols()];
+            self.rows.push(data.clone());
+            cmds.push(EditCommand::InsertRow { at: self.rows.len() - 1, data });
+        }
+    }
+
     pub fn delete_row(&mut self, row: usize) {
         if row < self.rows.len() {
             let data = self.rows.remove(row);
@@ -178,6 +256,16 @@ impl EditableGrid {
         }
     }
 
+    /// Delete up to `count` rows from `at` as a single undoable step,
+    /// returning the rows removed
+    pub fn delete_rows(&mut self, at: usize, count: usize) -> Vec<Vec<String>> {
+        let end = at.saturating_add(count).min(self.rows.len());
+        let removed: Vec<Vec<String>> = self.rows.drain(at.min(end)..end).collect();
+        let cmds = removed.iter().map(|data| EditCommand::DeleteRow { at, data: data.clone() }).collect();
+        self.push_batch(cmds);
+        removed
+    }
+
     pub fn add_column(&mut self, after_col: Option<usize>) {
         let insert_pos = after_col.map(|c| c + 1).unwrap_or(self.num_cols());
         let header = format!("Column {}", self.num_cols() + 1);
@@ -208,6 +296,73 @@ impl EditableGrid {
         }
     }
 
+    /// Swap rows and columns as a single undoable step. The headers become
+    /// the first column; with `first_column_as_headers` the first column
+    /// (topped by its header) becomes the new headers.
+    pub fn transpose(&mut self, first_column_as_headers: bool) {
+        let mut columns: Vec<Vec<String>> = self.headers
+            .iter()
+            .enumerate()
+            .map(|(col, header)| {
+                std::iter::once(header.clone())
+                    .chain(self.rows.iter().map(|row| row.get(col).cloned().unwrap_or_default()))
+                    .collect()
+            })
+            .collect();
+
+        let new_headers = if first_column_as_headers && !columns.is_empty() {
+            columns.remove(0)
+        } else {
+            (0..self.rows.len() + 1).map(|i| format!("Column {}", i + 1)).collect()
+        };
+
+        self.replace_table(new_headers, columns);
+    }
+
+    /// Replace the whole table as one undoable step
+    pub fn replace_table(&mut self, headers: Vec<String>, rows: Vec<Vec<String>>) {
+        let cmd = EditCommand::ReplaceTable {
+            old_headers: std::mem::replace(&mut self.headers, headers.clone()),
+            old_rows: std::mem::replace(&mut self.rows, rows.clone()),
+            new_headers: headers,
+            new_rows: rows,
+        };
+        self.push_undo(cmd);
+        self.modified = true;
+    }
+
+    /// Rows with a cell set during this session, unsorted and possibly repeated
+    pub fn edited_rows(&self) -> Vec<usize> {
+        let mut rows = Vec::new();
+        self.undo_stack.iter().for_each(|cmd| cmd.set_rows(&mut rows));
+        rows
+    }
+
+    /// Whether a cell has been set during this session (according to the undo history)
+    pub fn is_cell_edited(&self, row: usize, col: usize) -> bool {
+        self.undo_stack.iter().any(|cmd| cmd.touches_cell(row, col))
+    }
+
+    /// Generation counter for a column, bumped whenever it may have changed
+    pub fn column_generation(&self, col: usize) -> u64 {
+        self.generations.column(col)
+    }
+
+    /// Generation counter for the row order, bumped whenever rows are
+    /// inserted, deleted or reordered
+    pub fn row_generation(&self) -> u64 {
+        self.generations.rows()
+    }
+
+    /// Changes made this session
+    pub fn audit(&self) -> &AuditLog {
+        &self.audit
+    }
+
+    pub fn audit_mut(&mut self) -> &mut AuditLog {
+        &mut self.audit
+    }
+
     pub fn is_modified(&self) -> bool {
         self.modified
     }
@@ -219,6 +374,8 @@ impl EditableGrid {
     // ---- Undo/Redo Support ----
 
     fn push_undo(&mut self, cmd: EditCommand) {
+        self.generations.bump(&cmd);
+        self.audit.record(AuditAction::Edit, &cmd, false);
         self.undo_stack.push(cmd);
         self.redo_stack.clear(); // New action clears redo
         
@@ -228,9 +385,19 @@ impl EditableGrid {
         }
     }
 
+    /// Record already-applied commands as one undo step
+    fn push_batch(&mut self, cmds: Vec<EditCommand>) {
+        if !cmds.is_empty() {
+            self.push_undo(EditCommand::Batch(cmds));
+            self.modified = true;
+        }
+    }
+
     pub fn undo(&mut self) -> bool {
         if let Some(cmd) = self.undo_stack.pop() {
             self.apply_inverse(&cmd);
+            self.generations.bump(&cmd);
+            self.audit.record(AuditAction::Undo, &cmd, false);
             self.redo_stack.push(cmd);
             true
         } else {
@@ -241,6 +408,8 @@ impl EditableGrid {
     pub fn redo(&mut self) -> bool {
         if let Some(cmd) = self.redo_stack.pop() {
             self.apply_command(&cmd);
+            self.generations.bump(&cmd);
+            self.audit.record(AuditAction::Redo, &cmd, false);
             self.undo_stack.push(cmd);
             true
         } else {
@@ -251,10 +420,8 @@ impl EditableGrid {
     fn apply_command(&mut self, cmd: &EditCommand) {
         match cmd {
             EditCommand::SetCell { row, col, new_value, .. } => {
-                if let Some(r) = self.rows.get_mut(*row) {
-                    if let Some(cell) = r.get_mut(*col) {
-                        *cell = new_value.clone();
-                    }
+                if let Some(cell) = self.rows.get_mut(*row).and_then(|r| r.get_mut(*col)) {
+                    *cell = new_value.clone();
                 }
             }
             EditCommand::SetHeader { col, new_value, .. } => {
@@ -290,16 +457,23 @@ impl EditableGrid {
                     }
                 }
             }
+            EditCommand::Batch(cmds) => {
+                for cmd in cmds {
+                    self.apply_command(cmd);
+                }
+            }
+            EditCommand::ReplaceTable { new_headers, new_rows, .. } => {
+                self.headers = new_headers.clone();
+                self.rows = new_rows.clone();
+            }
         }
     }
 
     fn apply_inverse(&mut self, cmd: &EditCommand) {
         match cmd {
             EditCommand::SetCell { row, col, old_value, .. } => {
-                if let Some(r) = self.rows.get_mut(*row) {
-                    if let Some(cell) = r.get_mut(*col) {
-                        *cell = old_value.clone();
-                    }
+                if let Some(cell) = self.rows.get_mut(*row).and_then(|r| r.get_mut(*col)) {
+                    *cell = old_value.clone();
                 }
             }
             EditCommand::SetHeader { col, old_value, .. } => {
@@ -336,6 +510,15 @@ impl EditableGrid {
                     }
                 }
             }
+            EditCommand::Batch(cmds) => {
+                for cmd in cmds.iter().rev() {
+                    self.apply_inverse(cmd);
+                }
+            }
+            EditCommand::ReplaceTable { old_headers, old_rows, .. } => {
+                self.headers = old_headers.clone();
+                self.rows = old_rows.clone();
+            }
         }
     }
 
@@ -361,3 +544,56 @@ impl Default for EditableGrid {
         Self::new(3, 10)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transpose_and_undo() {
+        let mut grid = EditableGrid::from_csv("id,a,b\nx,1,2\ny,3,4\n");
+        grid.transpose(true);
+        assert_eq!(grid.headers, vec!["id", "x", "y"]);
+        assert_eq!(grid.rows, vec![vec!["a", "1", "3"], vec!["b", "2", "4"]]);
+
+        grid.undo();
+        assert_eq!(grid.headers, vec!["id", "a", "b"]);
+        assert_eq!(grid.num_rows(), 2);
+
+        grid.transpose(false);
+        assert_eq!(grid.headers, vec!["Column 1", "Column 2", "Column 3"]);
+        assert_eq!(grid.rows[0], vec!["id", "x", "y"]);
+
+        grid.set_cell(2, 1, "5".to_string());
+        grid.set_cells([(0, 0, "z".to_string()), (2, 2, "6".to_string())]);
+        assert_eq!(grid.edited_rows(), vec![2, 0, 2]);
+    }
+
+    #[test]
+    fn test_delete_rows_is_one_undo_step() {
+        let mut grid = EditableGrid::from_csv("id\n1\n2\n3\n4\n");
+        let removed = grid.delete_rows(1, 5);
+        assert_eq!(removed, vec![vec!["2"], vec!["3"], vec!["4"]]);
+        assert_eq!(grid.rows, vec![vec!["1"]]);
+
+        assert!(grid.undo());
+        assert_eq!(grid.num_rows(), 4);
+        assert_eq!(grid.rows[3], vec!["4"]);
+        assert!(!grid.undo());
+    }
+
+    #[test]
+    fn test_row_generation_follows_row_changes() {
+        let mut grid = EditableGrid::from_csv("id,a\n1,x\n2,y\n");
+        let before = grid.row_generation();
+        grid.set_cell(0, 1, "z".to_string());
+        grid.insert_columns(2, vec![("b".to_string(), vec!["".to_string(); 2])]);
+        assert_eq!(grid.row_generation(), before);
+
+        grid.delete_rows(0, 1);
+        let deleted = grid.row_generation();
+        assert_ne!(deleted, before);
+        grid.undo();
+        assert_ne!(grid.row_generation(), deleted);
+    }
+}
@@ -0,0 +1,110 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+
+use super::jobs::Progress;
+
+/// Where handoff files are written
+pub fn handoff_dir() -> PathBuf {
+    std::env::temp_dir().join("csvit-handoff")
+}
+
+/// Write rows to a CSV file for another tool to pick up. `header` is written
+/// first when given; `columns` keeps only those source columns, in order.
+pub fn write_handoff_csv(
+    path: &Path,
+    header: Option<Vec<String>>,
+    total: usize,
+    row_at: impl Fn(usize) -> Vec<String>,
+    columns: Option<&[usize]>,
+    progress: &Progress,
+) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).context("Failed to create handoff directory")?;
+    }
+    let mut writer = csv::WriterBuilder::new()
+        .flexible(true)
+        .from_path(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    let project = |values: Vec<String>| -> Vec<String> {
+        match columns {
+            Some(cols) => cols.iter().map(|&c| values.get(c).cloned().unwrap_or_default()).collect(),
+            None => values,
+        }
+    };
+
+    if let Some(header) = header {
+        writer.write_record(project(header))?;
+    }
+    progress.set_total(total);
+    for row in 0..total {
+        if row % 10_000 == 0 {
+            if progress.is_cancelled() {
+                anyhow::bail!("Export cancelled");
+            }
+            progress.set_done(row);
+        }
+        writer.write_record(project(row_at(row)))?;
+    }
+    writer.flush().context("Failed to flush handoff file")?;
+    Ok(())
+}
+
+/// A ready-to-run Python snippet that loads `csv_path` into a DataFrame
+pub fn pandas_snippet(csv_path: &Path) -> String {
+    // A JSON string literal is also a valid Python string literal
+    let literal = serde_json::to_string(&csv_path.to_string_lossy()).unwrap_or_default();
+    format!(
+        "import pandas as pd\n\ndf = pd.read_csv({})\nprint(df.shape)\ndf.head()\n",
+        literal
+    )
+}
+
+/// Write a one-cell Jupyter notebook containing `code` next to the data file
+pub fn write_notebook(path: &Path, code: &str) -> Result<()> {
+    let source: Vec<String> = code.split_inclusive('\n').map(String::from).collect();
+    let notebook = serde_json::json!({
+        "cells": [{
+            "cell_type": "code",
+            "execution_count": null,
+            "metadata": {},
+            "outputs": [],
+            "source": source,
+        }],
+        "metadata": {
+            "kernelspec": { "display_name": "Python 3", "language": "python", "name": "python3" }
+        },
+        "nbformat": 4,
+        "nbformat_minor": 5,
+    });
+    std::fs::write(path, serde_json::to_string_pretty(&notebook)?).context("Failed to write notebook")
+}
+
+/// Open a notebook with the `jupyter` found on PATH
+pub fn launch_jupyter(notebook: &Path) -> Result<()> {
+    std::process::Command::new("jupyter")
+        .arg("notebook")
+        .arg(notebook)
+        .spawn()
+        .context("Could not start jupyter (is it installed and on PATH?)")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_handoff_csv_projects_columns() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("out.csv");
+        let rows = [["1", "a,b", "x"], ["2", "c", "y"]];
+        let header = Some(vec!["id".to_string(), "text".to_string(), "z".to_string()]);
+        let row_at = |r: usize| rows[r].iter().map(|v| v.to_string()).collect();
+        write_handoff_csv(&path, header, rows.len(), row_at, Some(&[1, 0]), &Progress::default())?;
+
+        assert_eq!(std::fs::read_to_string(&path)?, "text,id\n\"a,b\",1\nc,2\n");
+        assert!(pandas_snippet(&path).contains(&format!("pd.read_csv(\"{}\"", path.display())));
+        Ok(())
+    }
+}
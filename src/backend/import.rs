@@ -0,0 +1,443 @@
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+
+use super::grid::EditableGrid;
+use super::normalize::{fingerprint, levenshtein};
+
+/// Build a grid from a simple XML record file, e.g.
+/// `<items><item id="1"><name>a</name></item>…</items>`.
+/// Single wrapper elements are skipped; the most common child element is
+/// taken as the record, and its attributes and child elements become columns.
+pub fn import_xml_records(text: &str) -> Result<EditableGrid> {
+    let doc = roxmltree::Document::parse(text).context("Invalid XML")?;
+    let mut parent = doc.root_element();
+    loop {
+        let mut children = parent.children().filter(|n| n.is_element());
+        match (children.next(), children.next()) {
+            (Some(only), None) if only.children().any(|n| n.is_element()) => parent = only,
+            _ => break,
+        }
+    }
+
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for child in parent.children().filter(|n| n.is_element()) {
+        *counts.entry(child.tag_name().name()).or_default() += 1;
+    }
+    let Some(record_tag) = counts.into_iter().max_by_key(|(_, n)| *n).map(|(tag, _)| tag) else {
+        bail!("No records found under <{}>", parent.tag_name().name());
+    };
+
+    let mut headers: Vec<String> = Vec::new();
+    let mut records: Vec<Vec<(usize, String)>> = Vec::new();
+    for record in parent.children().filter(|n| n.is_element() && n.tag_name().name() == record_tag) {
+        let mut fields = Vec::new();
+        for attr in record.attributes() {
+            fields.push((column_index(&mut headers, attr.name()), attr.value().to_string()));
+        }
+        for field in record.children().filter(|n| n.is_element()) {
+            let value: String = field.descendants().filter(|n| n.is_text()).filter_map(|n| n.text()).collect();
+            fields.push((column_index(&mut headers, field.tag_name().name()), value.trim().to_string()));
+        }
+        if fields.is_empty()
+            && let Some(text) = record.text().map(str::trim).filter(|t| !t.is_empty())
+        {
+            fields.push((column_index(&mut headers, record_tag), text.to_string()));
+        }
+        records.push(fields);
+    }
+
+    let rows = records
+        .into_iter()
+        .map(|fields| {
+            let mut row = vec![String::new(); headers.len()];
+            for (col, value) in fields {
+                row[col] = value;
+            }
+            row
+        })
+        .collect();
+    Ok(EditableGrid::from_rows(headers, rows))
+}
+
+/// Index of the column called `name`, adding it if it is new
+fn column_index(headers: &mut Vec<String>, name: &str) -> usize {
+    match headers.iter().position(|h| h == name) {
+        Some(i) => i,
+        None => {
+            headers.push(name.to_string());
+            headers.len() - 1
+        }
+    }
+}
+
+/// A `<table>` found in an HTML document
+#[derive(Debug, Clone, Default)]
+pub struct HtmlTable {
+    pub caption: Option<String>,
+    /// Taken from the first row when it is made of `<th>` cells
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl HtmlTable {
+    pub fn width(&self) -> usize {
+        self.rows.iter().map(Vec::len).chain(std::iter::once(self.headers.len())).max().unwrap_or(0)
+    }
+
+    pub fn into_grid(self) -> EditableGrid {
+        let width = self.width();
+        let mut headers = self.headers;
+        for i in headers.len()..width {
+            headers.push(format!("Column {}", i + 1));
+        }
+        EditableGrid::from_rows(headers, self.rows)
+    }
+}
+
+#[derive(Default)]
+struct TableBuilder {
+    start: usize,
+    table: HtmlTable,
+    in_caption: bool,
+    row: Option<Vec<(String, bool)>>,
+    cell: Option<(String, bool)>,
+    header_rows_seen: bool,
+}
+
+impl TableBuilder {
+    fn finish_cell(&mut self) {
+        if let Some((text, is_th)) = self.cell.take() {
+            self.row.get_or_insert_with(Vec::new).push((collapse_whitespace(&text), is_th));
+        }
+    }
+
+    fn finish_row(&mut self) {
+        self.finish_cell();
+        let Some(cells) = self.row.take() else {
+            return;
+        };
+        if cells.is_empty() {
+            return;
+        }
+        let all_th = cells.iter().all(|(_, th)| *th);
+        let values = cells.into_iter().map(|(text, _)| text).collect();
+        if all_th && !self.header_rows_seen && self.table.rows.is_empty() {
+            self.table.headers = values;
+        } else {
+            self.table.rows.push(values);
+        }
+        self.header_rows_seen = true;
+    }
+
+    fn text(&mut self, text: &str) {
+        if let Some((ref mut cell, _)) = self.cell {
+            cell.push_str(text);
+        } else if self.in_caption {
+            self.table.caption.get_or_insert_with(String::new).push_str(text);
+        }
+    }
+}
+
+/// Find every `<table>` in an HTML document, in document order.
+/// This is a tolerant tag scanner rather than a full HTML parser: it
+/// understands rows, header/data cells and captions, and ignores spans.
+pub fn find_html_tables(html: &str) -> Vec<HtmlTable> {
+    let mut stack: Vec<TableBuilder> = Vec::new();
+    let mut done: Vec<(usize, HtmlTable)> = Vec::new();
+    let mut rest = html;
+    let mut opened = 0;
+
+    while let Some(lt) = rest.find('<') {
+        if let Some(builder) = stack.last_mut() {
+            builder.text(&decode_entities(&rest[..lt]));
+        }
+        rest = &rest[lt..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map(|end| &comment[end + 3..]).unwrap_or("");
+            continue;
+        }
+        let Some(gt) = rest.find('>') else {
+            break;
+        };
+        let tag = &rest[1..gt];
+        rest = &rest[gt + 1..];
+
+        let closing = tag.starts_with('/');
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+
+        // Skip over content that is never cell text
+        if !closing && (name == "script" || name == "style") {
+            let end = format!("</{}", name);
+            let lower = rest.to_ascii_lowercase();
+            rest = lower.find(&end).map(|i| &rest[i..]).unwrap_or("");
+            continue;
+        }
+
+        match (name.as_str(), closing) {
+            ("table", false) => {
+                stack.push(TableBuilder { start: opened, ..Default::default() });
+                opened += 1;
+            }
+            ("table", true) => {
+                if let Some(mut builder) = stack.pop() {
+                    builder.finish_row();
+                    done.push((builder.start, builder.table));
+                }
+            }
+            _ => {
+                let Some(builder) = stack.last_mut() else {
+                    continue;
+                };
+                match (name.as_str(), closing) {
+                    ("caption", closing) => builder.in_caption = !closing,
+                    ("tr", false) => {
+                        builder.finish_row();
+                        builder.row = Some(Vec::new());
+                    }
+                    ("tr", true) => builder.finish_row(),
+                    ("td" | "th", false) => {
+                        builder.finish_cell();
+                        builder.cell = Some((String::new(), name == "th"));
+                    }
+                    ("td" | "th", true) => builder.finish_cell(),
+                    ("br", _) => builder.text(" "),
+                    _ => {}
+                }
+            }
+        }
+    }
+    while let Some(mut builder) = stack.pop() {
+        builder.finish_row();
+        done.push((builder.start, builder.table));
+    }
+
+    done.sort_by_key(|(start, _)| *start);
+    done.into_iter()
+        .map(|(_, mut table)| {
+            table.caption = table.caption.map(|c| collapse_whitespace(&c)).filter(|c| !c.is_empty());
+            table
+        })
+        .filter(|table| !table.rows.is_empty() || !table.headers.is_empty())
+        .collect()
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest.find(';').filter(|&end| end <= 10).and_then(|end| {
+            let entity = &rest[1..end];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .map(|hex| u32::from_str_radix(hex, 16).ok())
+                    .unwrap_or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                    .and_then(char::from_u32),
+            };
+            c.map(|c| (c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Cut a line at character positions. `boundaries` must be ascending;
+/// fields are trimmed, and missing fields come back empty.
+pub fn split_fixed_width(line: &str, boundaries: &[usize]) -> Vec<String> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut starts = vec![0];
+    starts.extend_from_slice(boundaries);
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, &start)| {
+            let end = starts.get(i + 1).copied().unwrap_or(chars.len()).min(chars.len());
+            chars.get(start.min(end)..end).map(|s| s.iter().collect::<String>()).unwrap_or_default().trim().to_string()
+        })
+        .collect()
+}
+
+/// Suggest boundaries where a column of blanks (across every line) is
+/// followed by text
+pub fn guess_fixed_width_boundaries(lines: &[&str]) -> Vec<usize> {
+    let rows: Vec<Vec<char>> = lines.iter().filter(|l| !l.trim().is_empty()).map(|l| l.chars().collect()).collect();
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let blank = |p: usize| rows.iter().all(|r| r.get(p).is_none_or(|c| c.is_whitespace()));
+    (1..width).filter(|&p| blank(p - 1) && !blank(p)).collect()
+}
+
+/// Build a grid from fixed-width text. With `header`, the first line is
+/// skipped and only used for names left empty in `names`.
+pub fn import_fixed_width(text: &str, boundaries: &[usize], names: &[String], header: bool) -> EditableGrid {
+    let mut lines = text.lines().filter(|l| !l.trim().is_empty());
+    let header_fields = if header { lines.next().map(|l| split_fixed_width(l, boundaries)) } else { None };
+    let headers = (0..=boundaries.len())
+        .map(|i| {
+            let name = names.get(i).map(|n| n.trim()).unwrap_or("");
+            let from_header = header_fields.as_ref().and_then(|h| h.get(i)).filter(|h| !h.is_empty());
+            match (name.is_empty(), from_header) {
+                (false, _) => name.to_string(),
+                (true, Some(h)) => h.clone(),
+                (true, None) => format!("Column {}", i + 1),
+            }
+        })
+        .collect();
+    let rows = lines.map(|l| split_fixed_width(l, boundaries)).collect();
+    EditableGrid::from_rows(headers, rows)
+}
+
+/// Header and data rows of a CSV file, for importing into an open document
+pub fn read_csv_table(path: &std::path::Path) -> Result<(Vec<String>, Vec<Vec<String>>)> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_path(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        rows.push(record?.iter().map(str::to_string).collect::<Vec<_>>());
+    }
+    if rows.is_empty() {
+        bail!("{} is empty", path.display());
+    }
+    let headers = rows.remove(0);
+    Ok((headers, rows))
+}
+
+/// For each target column, the source column to fill it from. Names that
+/// match once case and punctuation are ignored pair up first, then each
+/// remaining target takes the closest unused source name, if it's within a
+/// third of the name's length in edits. None leaves the column empty.
+pub fn suggest_column_mapping(target: &[String], source: &[String]) -> Vec<Option<usize>> {
+    let target_keys: Vec<String> = target.iter().map(|name| fingerprint(name)).collect();
+    let source_keys: Vec<String> = source.iter().map(|name| fingerprint(name)).collect();
+    let mut mapping = vec![None; target.len()];
+    let mut used = vec![false; source.len()];
+    for (t, key) in target_keys.iter().enumerate() {
+        if let Some(s) = (0..source.len()).find(|&s| !used[s] && !key.is_empty() && source_keys[s] == *key) {
+            mapping[t] = Some(s);
+            used[s] = true;
+        }
+    }
+    for (t, key) in target_keys.iter().enumerate() {
+        if mapping[t].is_some() || key.is_empty() {
+            continue;
+        }
+        let closest = (0..source.len())
+            .filter(|&s| !used[s] && !source_keys[s].is_empty())
+            .map(|s| (s, levenshtein(key, &source_keys[s])))
+            .filter(|&(s, distance)| distance * 3 <= key.chars().count().max(source_keys[s].chars().count()))
+            .min_by_key(|&(_, distance)| distance);
+        if let Some((s, _)) = closest {
+            mapping[t] = Some(s);
+            used[s] = true;
+        }
+    }
+    mapping
+}
+
+/// Rows laid out in the target's column order, per `mapping`
+pub fn map_rows(mapping: &[Option<usize>], rows: &[Vec<String>]) -> Vec<Vec<String>> {
+    rows.iter()
+        .map(|row| {
+            mapping.iter()
+                .map(|source| source.and_then(|s| row.get(s).cloned()).unwrap_or_default())
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xml_records_become_columns() -> Result<()> {
+        let xml = r#"<export><items>
+            <item id="1"><name>Apple</name><price>1.5</price></item>
+            <item id="2"><name>Pear &amp; Co</name><stock>4</stock></item>
+        </items></export>"#;
+        let grid = import_xml_records(xml)?;
+        assert_eq!(grid.headers, vec!["id", "name", "price", "stock"]);
+        assert_eq!(grid.rows, vec![vec!["1", "Apple", "1.5", ""], vec!["2", "Pear & Co", "", "4"]]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_html_tables_in_document_order() {
+        let html = r#"<html><body>
+            <table><caption> Prices </caption>
+              <tr><th>Item</th><th>Cost</th></tr>
+              <tr><td>Tea<br>green</td><td>&#36;3</td></tr>
+              <tr><td>Cake<td>5
+            </table>
+            <!-- <table><tr><td>hidden</td></tr></table> -->
+            <TABLE><TR><TD>x</TD></TR></TABLE>
+        </body></html>"#;
+        let tables = find_html_tables(html);
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[0].caption.as_deref(), Some("Prices"));
+        assert_eq!(tables[0].headers, vec!["Item", "Cost"]);
+        assert_eq!(tables[0].rows, vec![vec!["Tea green", "$3"], vec!["Cake", "5"]]);
+
+        let grid = tables[1].clone().into_grid();
+        assert_eq!(grid.headers, vec!["Column 1"]);
+        assert_eq!(grid.rows, vec![vec!["x"]]);
+    }
+
+    #[test]
+    fn test_fixed_width_guess_and_split() {
+        let text = "ID  NAME      QTY\n1   apple       3\n22  kiwi fruit 10\n";
+        let lines: Vec<&str> = text.lines().collect();
+        let boundaries = guess_fixed_width_boundaries(&lines);
+        assert_eq!(boundaries, vec![4]);
+
+        let grid = import_fixed_width(text, &[4, 14], &[String::new(), "Fruit".to_string()], true);
+        assert_eq!(grid.headers, vec!["ID", "Fruit", "QTY"]);
+        assert_eq!(grid.rows, vec![vec!["1", "apple", "3"], vec!["22", "kiwi fruit", "10"]]);
+    }
+
+    #[test]
+    fn test_column_mapping_by_name() {
+        let names = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let target = names(&["id", "First Name", "email", "phone"]);
+        let source = names(&["E-Mail", "first_name", "ID", "notes"]);
+        let mapping = suggest_column_mapping(&target, &source);
+        assert_eq!(mapping, vec![Some(2), Some(1), Some(0), None]);
+
+        let rows = vec![names(&["a@x", "Ann", "7", "hi"])];
+        assert_eq!(map_rows(&mapping, &rows), vec![names(&["7", "Ann", "a@x", ""])]);
+    }
+}
@@ -0,0 +1,82 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+
+/// Progress shared between a background job and the UI
+#[derive(Default)]
+pub struct Progress {
+    done: AtomicUsize,
+    total: AtomicUsize,
+    cancelled: AtomicBool,
+}
+
+impl Progress {
+    pub fn set_total(&self, total: usize) {
+        self.total.store(total, Ordering::Relaxed);
+    }
+
+    pub fn set_done(&self, done: usize) {
+        self.done.store(done, Ordering::Relaxed);
+    }
+
+    pub fn inc(&self, n: usize) {
+        self.done.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Fraction complete in [0, 1], or None if the total is unknown
+    pub fn fraction(&self) -> Option<f32> {
+        let total = self.total.load(Ordering::Relaxed);
+        if total == 0 {
+            None
+        } else {
+            Some((self.done.load(Ordering::Relaxed) as f32 / total as f32).min(1.0))
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// A unit of work running on its own thread, polled from the UI each frame
+pub struct Job<T> {
+    pub label: String,
+    progress: Arc<Progress>,
+    rx: Receiver<T>,
+}
+
+impl<T: Send + 'static> Job<T> {
+    pub fn spawn(label: impl Into<String>, work: impl FnOnce(&Progress) -> T + Send + 'static) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let progress = Arc::new(Progress::default());
+        let worker_progress = progress.clone();
+        std::thread::spawn(move || {
+            let _ = tx.send(work(&worker_progress));
+        });
+        Self {
+            label: label.into(),
+            progress,
+            rx,
+        }
+    }
+}
+
+impl<T> Job<T> {
+    /// Take the result if the job has finished.
+    /// A worker that panicked surfaces as an error rather than running forever.
+    pub fn poll(&self) -> Option<Result<T, String>> {
+        match self.rx.try_recv() {
+            Ok(result) => Some(Ok(result)),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(format!("{} stopped unexpectedly", self.label))),
+        }
+    }
+
+    pub fn progress(&self) -> &Progress {
+        &self.progress
+    }
+}
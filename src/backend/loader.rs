@@ -1,111 +1,483 @@
-use anyhow::{Context, Result};
-use memmap2::Mmap;
-use std::fs::File;
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
 use std::path::Path;
 use std::sync::Arc;
 
+use super::jobs::Progress;
+use super::source::{open_source, CsvSource};
+
+/// Size of each block sampled for the prefix checksum
+const CHECKSUM_BLOCK: u64 = 4096;
+/// Blocks sampled across the indexed bytes for the prefix checksum
+const CHECKSUM_BLOCKS: u64 = 32;
+/// Bytes scanned at a time while indexing
+const INDEX_CHUNK: u64 = 1024 * 1024;
+/// Records looked at when guessing whether the file starts with a preamble
+const PREAMBLE_SAMPLE: usize = 50;
+/// Longest preamble guessed; more odd lines than this is a ragged file, not notes
+const MAX_PREAMBLE_LINES: usize = 20;
+/// Longest record still checked for being a blank separator line
+const MAX_BLANK_RECORD: u64 = 256;
+/// Field separators told apart when counting fields
+const DELIMITERS: [u8; 4] = [b',', b';', b'\t', b'|'];
+const QUOTE: u8 = b'"';
+
 pub struct CsvLoader {
-    mmap: Option<Arc<Mmap>>,
+    source: Option<Arc<dyn CsvSource>>,
     /// Start byte offset of each record
     record_offsets: Vec<u64>,
     /// Total number of records (rows)
     total_records: usize,
     /// Number of columns (for empty mode)
     num_columns_override: Option<usize>,
+    /// Checksum of the indexed bytes, to tell an append from a rewrite
+    prefix_checksum: [u8; 32],
+    /// Lines before the header that aren't part of the table, and where they end
+    preamble_lines: usize,
+    preamble_end: u64,
+    /// Trailing records kept out of the table, e.g. a TOTAL row, and where they start
+    footer_rows: usize,
+    footer_start: u64,
+    /// Field separator the first records agree on; see `detect_delimiter`
+    delimiter: u8,
+}
+
+/// One of several tables in a file, separated from the others by blank lines
+#[derive(Clone, Debug, PartialEq)]
+pub struct TableSpan {
+    /// Records above the table and below it, as `with_preamble` and
+    /// `with_footer` take them to leave only this table
+    pub preamble: usize,
+    pub footer: usize,
+    /// Data rows, not counting the header
+    pub rows: usize,
+    /// The header line as it reads in the file
+    pub header: String,
+}
+
+/// Records appended to a file since it was indexed, found by `CsvLoader::scan_append`
+pub struct Append {
+    source: Arc<dyn CsvSource>,
+    /// Starts of the records after the last one indexed before
+    offsets: Vec<u64>,
+    checksum: [u8; 32],
+}
+
+/// What `CsvLoader::reindex` had to do
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Reindex {
+    Unchanged,
+    /// The file grew by this many records; only the new bytes were scanned
+    Appended(usize),
+    /// Earlier bytes changed (or the file shrank), so everything was scanned again
+    Full,
 }
 
 impl CsvLoader {
     /// Create an empty CSV loader for new file creation
     pub fn empty(cols: usize, rows: usize) -> Self {
         Self {
-            mmap: None,
+            source: None,
             record_offsets: (0..rows).map(|i| i as u64).collect(),
             total_records: rows,
             num_columns_override: Some(cols),
+            prefix_checksum: [0; 32],
+            preamble_lines: 0,
+            preamble_end: 0,
+            footer_rows: 0,
+            footer_start: 0,
+            delimiter: b',',
         }
     }
     pub fn new(path: &Path) -> Result<Self> {
-        let file = File::open(path).with_context(|| format!("Failed to open file: {:?}", path))?;
-        let mmap = unsafe { Mmap::map(&file).context("Failed to memory map file")? };
-        let mmap = Arc::new(mmap);
+        Ok(Self::from_source(open_source(path)?))
+    }
 
-        let offsets = Self::build_index(&mmap)?;
+    /// Open `path` with any notes above the real header set aside (see
+    /// `sniff_preamble`), as the editor and the command line both do
+    pub fn open_table(path: &Path) -> Result<Self> {
+        let loader = Self::new(path)?;
+        let preamble = loader.sniff_preamble();
+        Ok(loader.with_preamble(preamble))
+    }
 
-        Ok(Self {
-            record_offsets: offsets.clone(),
+    /// Index a file opened any way; see `source::open_source` for how it's picked
+    pub fn from_source(source: Arc<dyn CsvSource>) -> Self {
+        let offsets = Self::build_index(source.as_ref());
+        Self::from_parts(source, offsets)
+    }
+
+    fn from_parts(source: Arc<dyn CsvSource>, offsets: Vec<u64>) -> Self {
+        let mut loader = Self {
             total_records: offsets.len(),
-            mmap: Some(mmap),
+            record_offsets: offsets,
+            prefix_checksum: prefix_checksum(source.as_ref(), source.len()),
+            source: Some(source),
             num_columns_override: None,
-        })
+            preamble_lines: 0,
+            preamble_end: 0,
+            footer_rows: 0,
+            footer_start: 0,
+            delimiter: b',',
+        };
+        loader.delimiter = detect_delimiter(&loader.sample_lines());
+        loader
+    }
+
+    /// Set the first `lines` records aside, e.g. an instrument's notes above
+    /// the real header. Record 0 becomes the line after them; the preamble
+    /// itself is kept for `preamble` and written back first on save.
+    pub fn with_preamble(mut self, lines: usize) -> Self {
+        let lines = lines.min(self.record_offsets.len());
+        self.preamble_end = self.record_offsets.get(lines).copied().unwrap_or(self.byte_len());
+        self.record_offsets.drain(..lines);
+        self.total_records = self.record_offsets.len();
+        self.preamble_lines += lines;
+        self
+    }
+
+    /// Set the last `rows` records aside, e.g. a "TOTAL,…" summary line, so
+    /// sorting and analysis never see them. They're kept for `footer` and
+    /// written back after the rows on save.
+    pub fn with_footer(mut self, rows: usize) -> Self {
+        let rows = rows.min(self.record_offsets.len());
+        if rows == 0 {
+            return self;
+        }
+        let keep = self.record_offsets.len() - rows;
+        self.footer_start = self.record_offsets[keep];
+        self.record_offsets.truncate(keep);
+        self.total_records = keep;
+        self.footer_rows += rows;
+        self
+    }
+
+    /// Leading records that look like notes rather than rows; see `detect_preamble`
+    pub fn sniff_preamble(&self) -> usize {
+        detect_preamble(&self.sample_lines(), self.delimiter)
+    }
+
+    /// The first records, which the delimiter and preamble are guessed from
+    fn sample_lines(&self) -> Vec<Cow<'_, [u8]>> {
+        (0..self.total_records.min(PREAMBLE_SAMPLE))
+            .filter_map(|i| self.get_record_line(i))
+            .collect()
+    }
+
+    /// Field separator the file appears to use, for counting its columns
+    pub fn delimiter(&self) -> u8 {
+        self.delimiter
+    }
+
+    pub fn preamble_lines(&self) -> usize {
+        self.preamble_lines
+    }
+
+    /// Raw bytes of the lines set aside by `with_preamble`, if any
+    pub fn preamble(&self) -> Option<Cow<'_, [u8]>> {
+        let source = self.source.as_ref()?;
+        (self.preamble_end > 0).then(|| source.read(0, self.preamble_end))
+    }
+
+    /// At most the first `limit` bytes of the preamble, for showing it
+    pub fn preamble_head(&self, limit: usize) -> Option<Cow<'_, [u8]>> {
+        let source = self.source.as_ref()?;
+        (self.preamble_end > 0).then(|| source.read(0, self.preamble_end.min(limit as u64)))
+    }
+
+    pub fn footer_rows(&self) -> usize {
+        self.footer_rows
+    }
+
+    /// Raw bytes of the records set aside by `with_footer`, if any
+    pub fn footer(&self) -> Option<Cow<'_, [u8]>> {
+        let source = self.source.as_ref()?;
+        (self.footer_rows > 0).then(|| source.read(self.footer_start, source.len()))
+    }
+
+    /// At most the first `limit` bytes of the footer, for showing it
+    pub fn footer_head(&self, limit: usize) -> Option<Cow<'_, [u8]>> {
+        let source = self.source.as_ref()?;
+        let end = source.len().min(self.footer_start + limit as u64);
+        (self.footer_rows > 0).then(|| source.read(self.footer_start, end))
+    }
+
+    /// Where the table's bytes stop: the footer's start, or the end of the file
+    fn records_end(&self, source: &dyn CsvSource) -> u64 {
+        if self.footer_rows > 0 { self.footer_start } else { source.len() }
+    }
+
+    /// Tables separated by blank lines (or lines of bare separators, as
+    /// spreadsheets export them). A blank run only starts a new table when the
+    /// line after it is a different width from the current table's header, so
+    /// empty rows inside one table don't split it. A file holding one table
+    /// gives one span; a cancelled scan gives none.
+    pub fn find_tables(&self, progress: &Progress) -> Vec<TableSpan> {
+        progress.set_total(self.total_records);
+        let mut tables = Vec::new();
+        // First record and width of the table being read, and the end of its last non-blank run
+        let mut current: Option<(usize, usize)> = None;
+        let mut end = 0;
+        let mut push = |first: usize, end: usize| {
+            let header = self.get_record_line(first)
+                .map(|line| String::from_utf8_lossy(&line).trim_end().to_string())
+                .unwrap_or_default();
+            tables.push(TableSpan {
+                preamble: self.preamble_lines + first,
+                footer: self.footer_rows + self.total_records - end,
+                rows: end - first - 1,
+                header,
+            });
+        };
+        let mut i = 0;
+        while i < self.total_records {
+            if i % 10_000 == 0 {
+                if progress.is_cancelled() {
+                    return Vec::new();
+                }
+                progress.set_done(i);
+            }
+            if self.is_blank_record(i) {
+                i += 1;
+                continue;
+            }
+            let width = self.get_record_line(i).map(|line| field_count(&line, self.delimiter, QUOTE)).unwrap_or(0);
+            match current {
+                Some((first, table_width)) if end < i && width != table_width => {
+                    push(first, end);
+                    current = Some((i, width));
+                }
+                None => current = Some((i, width)),
+                _ => {}
+            }
+            i += 1;
+            end = i;
+        }
+        if let Some((first, _)) = current {
+            push(first, end);
+        }
+        tables
+    }
+
+    fn is_blank_record(&self, index: usize) -> bool {
+        let Some(source) = self.source.as_ref() else {
+            return false;
+        };
+        let start = self.record_offsets[index];
+        let end = self.record_offsets.get(index + 1).copied().unwrap_or_else(|| self.records_end(source.as_ref()));
+        // Long records have content somewhere; only short ones are read
+        end - start <= MAX_BLANK_RECORD
+            && self.get_record_line(index)
+                .is_some_and(|line| line.iter().all(|&b| b.is_ascii_whitespace() || b == self.delimiter || matches!(b, b',' | b';')))
+    }
+
+    /// Open `path` again after it changed on disk. When the file only grew and
+    /// the bytes indexed before still match (by a sampled checksum), the
+    /// existing offsets are kept and only the new tail is scanned.
+    pub fn reindex(&self, path: &Path) -> Result<(Self, Reindex)> {
+        if let Some((loader, added)) = self.extend(path)? {
+            let kind = if loader.byte_len() == self.byte_len() { Reindex::Unchanged } else { Reindex::Appended(added) };
+            return Ok((loader, kind));
+        }
+        let loader = Self::new(path)?.with_preamble(self.preamble_lines).with_footer(self.footer_rows);
+        Ok((loader, Reindex::Full))
+    }
+
+    /// The incremental half of `reindex`: None when the indexed bytes no
+    /// longer match, otherwise the new loader and how many records were added
+    pub fn extend(&self, path: &Path) -> Result<Option<(Self, usize)>> {
+        let Some(append) = self.scan_append(path)? else {
+            return Ok(None);
+        };
+        let added = append.offsets.len();
+        let mut offsets = self.record_offsets.clone();
+        offsets.extend_from_slice(&append.offsets);
+        Ok(Some((self.appended(offsets, append), added)))
+    }
+
+    /// Scan only what was appended to `path` since it was indexed, leaving
+    /// this loader alone so it can run in the background. None when the
+    /// indexed bytes no longer match.
+    pub fn scan_append(&self, path: &Path) -> Result<Option<Append>> {
+        let source = open_source(path)?;
+        let old_len = self.byte_len();
+        let prefix_intact = !self.record_offsets.is_empty()
+            && source.len() >= old_len
+            && prefix_checksum(source.as_ref(), old_len) == self.prefix_checksum;
+        // Rows appended below a footer belong above it, so a file with one is always read afresh
+        if !prefix_intact || self.footer_rows > 0 {
+            return Ok(None);
+        }
+
+        // The last record may have been cut short (or missing its newline), so rescan from its start
+        let mut offsets = Vec::new();
+        if source.len() > old_len {
+            let last_start = *self.record_offsets.last().expect("checked non-empty");
+            Self::index_from(source.as_ref(), last_start, &mut offsets);
+        }
+        let checksum = prefix_checksum(source.as_ref(), source.len());
+        Ok(Some(Append { source, offsets, checksum }))
+    }
+
+    /// Take on the records found by `scan_append`, returning the new loader
+    /// and how many records were added. The index is moved over rather than
+    /// copied when nothing else holds `loader` any more.
+    pub fn with_append(loader: Arc<Self>, append: Append) -> (Self, usize) {
+        let added = append.offsets.len();
+        match Arc::try_unwrap(loader) {
+            Ok(mut loader) => {
+                let mut offsets = std::mem::take(&mut loader.record_offsets);
+                offsets.extend_from_slice(&append.offsets);
+                (loader.appended(offsets, append), added)
+            }
+            Err(shared) => {
+                let mut offsets = shared.record_offsets.clone();
+                offsets.extend_from_slice(&append.offsets);
+                (shared.appended(offsets, append), added)
+            }
+        }
+    }
+
+    /// A loader over the grown file, keeping this one's preamble
+    fn appended(&self, offsets: Vec<u64>, append: Append) -> Self {
+        Self {
+            total_records: offsets.len(),
+            record_offsets: offsets,
+            prefix_checksum: append.checksum,
+            source: Some(append.source),
+            num_columns_override: None,
+            preamble_lines: self.preamble_lines,
+            preamble_end: self.preamble_end,
+            footer_rows: 0,
+            footer_start: 0,
+            delimiter: self.delimiter,
+        }
     }
 
     /// Scans the file to find the start of every record, respecting quotes.
-    fn build_index(data: &[u8]) -> Result<Vec<u64>> {
+    fn build_index(source: &dyn CsvSource) -> Vec<u64> {
         let mut offsets = Vec::new();
-        if data.is_empty() {
-            return Ok(offsets);
+        if source.is_empty() {
+            return offsets;
         }
 
         // The first record always starts at 0
         offsets.push(0);
+        Self::index_from(source, 0, &mut offsets);
+        offsets
+    }
 
+    /// Push the start of every record after the one starting at `start`
+    fn index_from(source: &dyn CsvSource, start: u64, offsets: &mut Vec<u64>) {
         let mut in_quote = false;
-        let mut i = 0;
-        let len = data.len();
-
-        while i < len {
-            let b = data[i];
-            
-            match b {
-                b'"' => {
-                    in_quote = !in_quote;
-                }
-                b'\n' => {
-                    if !in_quote {
-                        // Found a record separator
-                        if i + 1 < len {
-                            offsets.push((i + 1) as u64);
-                        }
+        let len = source.len();
+        let mut chunk_start = start;
+
+        while chunk_start < len {
+            let chunk = source.read(chunk_start, chunk_start + INDEX_CHUNK);
+            if chunk.is_empty() {
+                break;
+            }
+            for (i, &b) in chunk.iter().enumerate() {
+                let pos = chunk_start + i as u64;
+                match b {
+                    b'"' => {
+                        in_quote = !in_quote;
                     }
+                    // Found a record separator
+                    b'\n' if !in_quote && pos + 1 < len => {
+                        offsets.push(pos + 1);
+                    }
+                    b'\r' => {
+                        // Handle CRLF: If \r\n, we wait for the \n.
+                        // If just \r (classic Mac), we treat as newline if not in quote?
+                        // Modern CSV usually expects \n or \r\n. 
+                        // We'll ignore \r for the purpose of triggering a line break, 
+                        // relying on the following \n. 
+                        // Edge case: Old Mac files (\r only). 
+                        // Let's assume standard \n or \r\n for now.
+                    }
+                    _ => {}
                 }
-                b'\r' => {
-                    // Handle CRLF: If \r\n, we wait for the \n.
-                    // If just \r (classic Mac), we treat as newline if not in quote?
-                    // Modern CSV usually expects \n or \r\n. 
-                    // We'll ignore \r for the purpose of triggering a line break, 
-                    // relying on the following \n. 
-                    // Edge case: Old Mac files (\r only). 
-                    // Let's assume standard \n or \r\n for now.
-                }
-                _ => {}
             }
-            i += 1;
+            chunk_start += chunk.len() as u64;
         }
-
-        Ok(offsets)
     }
 
-    pub fn get_record_line(&self, index: usize) -> Option<&[u8]> {
-        let mmap = self.mmap.as_ref()?;
+    /// Raw bytes of one record, borrowed from a mapped file or read on demand
+    pub fn get_record_line(&self, index: usize) -> Option<Cow<'_, [u8]>> {
+        let source = self.source.as_ref()?;
         
         if index >= self.record_offsets.len() {
             return None;
         }
 
-        let start = self.record_offsets[index] as usize;
+        let start = self.record_offsets[index];
         let end = if index + 1 < self.record_offsets.len() {
-            self.record_offsets[index + 1] as usize
+            self.record_offsets[index + 1]
         } else {
-            mmap.len()
+            self.records_end(source.as_ref())
         };
 
-        if start >= mmap.len() || start >= end {
+        if start >= source.len() || start >= end {
             return None;
         }
 
-        Some(&mmap[start..end])
+        Some(source.read(start, end))
     }
     
+    /// The raw bytes of records `lines`, exactly as in the file: separators,
+    /// quoting and any invalid UTF-8 included
+    pub fn get_record_range(&self, lines: std::ops::Range<usize>) -> Option<Cow<'_, [u8]>> {
+        let source = self.source.as_ref()?;
+        let start = *self.record_offsets.get(lines.start)?;
+        let end = self.record_offsets.get(lines.end).copied().unwrap_or_else(|| self.records_end(source.as_ref()));
+        (start < end).then(|| source.read(start, end))
+    }
+
+    /// Byte offset in the file where record `index` starts
+    pub fn record_offset(&self, index: usize) -> Option<u64> {
+        self.source.as_ref()?;
+        self.record_offsets.get(index).copied()
+    }
+
+    /// The record that byte `offset` of the file belongs to
+    pub fn record_at_offset(&self, offset: u64) -> Option<usize> {
+        let source = self.source.as_ref()?;
+        if offset >= self.records_end(source.as_ref()) || offset < self.preamble_end || self.record_offsets.is_empty() {
+            return None;
+        }
+        Some(self.record_offsets.partition_point(|&start| start <= offset).saturating_sub(1))
+    }
+
+    /// Size of the file in bytes, as of when it was opened
+    pub fn byte_len(&self) -> u64 {
+        self.source.as_ref().map_or(0, |source| source.len())
+    }
+
+    /// Whether lines end in `\r\n`, judged by the first one in the file
+    pub fn uses_crlf(&self) -> bool {
+        let Some(source) = self.source.as_ref() else {
+            return false;
+        };
+        let head = source.read(0, source.len().min(64 * 1024));
+        head.iter().position(|&b| b == b'\n').is_some_and(|i| i > 0 && head[i - 1] == b'\r')
+    }
+
+    /// Whether the file's last line ends with a newline (an empty file counts)
+    pub fn ends_with_newline(&self) -> bool {
+        self.source.as_ref().is_none_or(|source| {
+            let len = source.len();
+            len == 0 || *source.read(len - 1, len) == *b"\n"
+        })
+    }
+
+    /// How the file is being read, or None for a new unsaved document
+    pub fn source_kind(&self) -> Option<&'static str> {
+        self.source.as_ref().map(|source| source.kind())
+    }
+
     pub fn total_records(&self) -> usize {
         self.total_records
     }
@@ -115,67 +487,83 @@ impl CsvLoader {
             return cols;
         }
         
-        if let Some(line) = self.get_record_line(0) {
-            let mut count = 1;
-            let mut in_quote = false;
-            for &b in line {
-                match b {
-                    b'"' => in_quote = !in_quote,
-                    b',' => if !in_quote { count += 1 },
-                    _ => {}
-                }
-            }
-            count
-        } else {
-            0
+        self.get_record_line(0).map_or(0, |line| field_count(&line, self.delimiter, QUOTE))
+    }
+}
+
+/// Fields in a raw record, counting delimiters outside quotes
+fn field_count(line: &[u8], delimiter: u8, quote: u8) -> usize {
+    let mut count = 1;
+    let mut in_quote = false;
+    for &b in line {
+        if b == quote {
+            in_quote = !in_quote;
+        } else if b == delimiter && !in_quote {
+            count += 1;
         }
     }
+    count
+}
 
-    pub fn estimate_column_widths(&self) -> Vec<f32> {
-        let num_cols = self.num_columns();
-        if num_cols == 0 {
-            return Vec::new();
+/// Which of `DELIMITERS` splits the most of `lines` into the same number of
+/// fields (more than one). Unlike sniffing from the first line, notes above
+/// the header don't throw it off. A comma when nothing splits the lines.
+fn detect_delimiter(lines: &[impl AsRef<[u8]>]) -> u8 {
+    let mut best = (b',', 0, 0);
+    for delimiter in DELIMITERS {
+        let mut tally: std::collections::HashMap<usize, usize> = std::collections::HashMap::new();
+        for line in lines {
+            *tally.entry(field_count(line.as_ref(), delimiter, QUOTE)).or_default() += 1;
         }
+        let Some((width, agreeing)) = tally.into_iter().filter(|&(width, _)| width > 1).max_by_key(|&(width, n)| (n, width)) else {
+            continue;
+        };
+        if (agreeing, width) > (best.1, best.2) {
+            best = (delimiter, agreeing, width);
+        }
+    }
+    best.0
+}
 
-        let mut max_lens = vec![10; num_cols]; // Start with min width of 10 chars
-        
-        // Scan first 100 lines
-        let records_to_scan = std::cmp::min(self.total_records(), 100);
-        
-        for i in 0..records_to_scan {
-            if let Some(line) = self.get_record_line(i) {
-                // Quick parse
-                let mut col_idx = 0;
-                let mut in_quote = false;
-                let mut current_len = 0;
-                
-                for &b in line {
-                    match b {
-                        b'"' => in_quote = !in_quote,
-                        b',' => {
-                            if !in_quote {
-                                if col_idx < num_cols {
-                                    max_lens[col_idx] = std::cmp::max(max_lens[col_idx], current_len);
-                                }
-                                col_idx += 1;
-                                current_len = 0;
-                            } else {
-                                current_len += 1;
-                            }
-                        }
-                        _ => current_len += 1,
-                    }
-                }
-                // Last column
-                if col_idx < num_cols {
-                     max_lens[col_idx] = std::cmp::max(max_lens[col_idx], current_len);
-                }
-            }
+/// How many of `lines` come before the table. The table's width is the
+/// field count most lines share; the preamble is the lines before the first
+/// one that wide, as long as they're all at most half as wide. Tables of one
+/// column, or where most lines from there on aren't that wide, aren't given
+/// a preamble.
+fn detect_preamble(lines: &[impl AsRef<[u8]>], delimiter: u8) -> usize {
+    let counts: Vec<usize> = lines.iter().map(|line| field_count(line.as_ref(), delimiter, QUOTE)).collect();
+    let mut tally: std::collections::HashMap<usize, usize> = std::collections::HashMap::new();
+    for &count in &counts {
+        *tally.entry(count).or_default() += 1;
+    }
+    let Some((width, _)) = tally.into_iter().max_by_key(|&(width, n)| (n, width)) else {
+        return 0;
+    };
+    let start = counts.iter().position(|&count| count == width).unwrap_or(0);
+    let table = &counts[start..];
+    let lines_that_wide = table.iter().filter(|&&count| count == width).count();
+    // A header a field short of its rows (say, rows with a trailing comma) isn't notes
+    let narrow = counts[..start].iter().all(|&count| count * 2 <= width);
+    if width < 2 || lines_that_wide * 2 <= table.len() || start > MAX_PREAMBLE_LINES || !narrow {
+        return 0;
+    }
+    start
+}
+
+/// SHA-256 of the first `len` bytes when that's small, or of evenly spaced
+/// blocks (always including the first and last) when it isn't
+fn prefix_checksum(source: &dyn CsvSource, len: u64) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(len.to_le_bytes());
+    if len <= CHECKSUM_BLOCK * CHECKSUM_BLOCKS {
+        hasher.update(source.read(0, len));
+    } else {
+        let step = (len - CHECKSUM_BLOCK) / (CHECKSUM_BLOCKS - 1);
+        for i in 0..CHECKSUM_BLOCKS {
+            hasher.update(source.read(i * step, i * step + CHECKSUM_BLOCK));
         }
-        
-        // Convert chars to approx pixels (average char width ~8px + padding)
-        max_lens.into_iter().map(|len| (len as f32 * 8.0).max(50.0).min(400.0)).collect()
     }
+    hasher.finalize().into()
 }
 
 #[cfg(test)]
@@ -193,13 +581,23 @@ mod tests {
         assert_eq!(loader.total_records(), 3);
         
         // Line 0: "a,b,c\n"
-        let line0 = std::str::from_utf8(loader.get_record_line(0).unwrap())?;
+        let line0 = loader.get_record_line(0).unwrap();
+        let line0 = std::str::from_utf8(&line0)?;
         assert_eq!(line0, "a,b,c\n");
 
         // Line 2: "4,5,6" (no newline at EOF)
-        let line2 = std::str::from_utf8(loader.get_record_line(2).unwrap())?;
+        let line2 = loader.get_record_line(2).unwrap();
+        let line2 = std::str::from_utf8(&line2)?;
         assert_eq!(line2, "4,5,6");
 
+        assert_eq!(loader.record_offset(2), Some(12));
+        assert_eq!(loader.get_record_range(1..3).as_deref(), Some(&b"1,2,3\n4,5,6"[..]));
+        assert_eq!(loader.get_record_range(0..1).as_deref(), Some(&b"a,b,c\n"[..]));
+        assert_eq!(loader.record_at_offset(0), Some(0));
+        assert_eq!(loader.record_at_offset(11), Some(1));
+        assert_eq!(loader.record_at_offset(12), Some(2));
+        assert_eq!(loader.record_at_offset(17), None);
+
         Ok(())
     }
 
@@ -212,13 +610,120 @@ mod tests {
         assert_eq!(loader.total_records(), 2);
         
         // Line 0: "a,b,\"c\nd\"\n"
-        let line0 = std::str::from_utf8(loader.get_record_line(0).unwrap())?;
+        let line0 = loader.get_record_line(0).unwrap();
+        let line0 = std::str::from_utf8(&line0)?;
         assert_eq!(line0, "a,b,\"c\nd\"\n");
 
         // Line 1: "1,2,3"
-        let line1 = std::str::from_utf8(loader.get_record_line(1).unwrap())?;
+        let line1 = loader.get_record_line(1).unwrap();
+        let line1 = std::str::from_utf8(&line1)?;
         assert_eq!(line1, "1,2,3");
 
         Ok(())
     }
+
+    #[test]
+    fn test_preamble() -> Result<()> {
+        let mut file = NamedTempFile::new()?;
+        write!(file, "Instrument: XR-7\nRun 42, started 09:00\n\nt,a,b,c\n0,1,2,3\n1,3,4,5\n")?;
+        let loader = CsvLoader::new(file.path())?;
+        assert_eq!(loader.sniff_preamble(), 3);
+
+        let loader = loader.with_preamble(3);
+        assert_eq!(loader.total_records(), 3);
+        assert_eq!(loader.num_columns(), 4);
+        assert_eq!(loader.get_record_line(0).as_deref(), Some(&b"t,a,b,c\n"[..]));
+        assert_eq!(loader.preamble().as_deref(), Some(&b"Instrument: XR-7\nRun 42, started 09:00\n\n"[..]));
+        assert_eq!(loader.record_at_offset(3), None);
+
+        // A trailing total is set aside the same way
+        let loader = loader.with_footer(1);
+        assert_eq!(loader.total_records(), 2);
+        assert_eq!(loader.footer_rows(), 1);
+        assert_eq!(loader.get_record_line(1).as_deref(), Some(&b"0,1,2,3\n"[..]));
+        assert_eq!(loader.footer().as_deref(), Some(&b"1,3,4,5\n"[..]));
+        assert_eq!(loader.record_at_offset(loader.byte_len() - 1), None);
+
+        // Only a clear majority width counts; a ragged row isn't a preamble
+        assert_eq!(detect_preamble(&["a,b,c", "1,2", "3,4,5", "6,7,8"], b','), 0);
+        assert_eq!(detect_preamble(&["a,b,c", "1,2,3,", "4,5,6,"], b','), 0);
+        assert_eq!(detect_preamble(&["notes", "a", "b"], b','), 0);
+
+        // Counted with the file's own delimiter, so commas in the notes don't matter
+        let lines = ["Run 42, started 09:00, by lab 3", "t;a;b;c", "0;1,5;2;3", "1;3;4;5"];
+        assert_eq!(detect_delimiter(&lines), b';');
+        assert_eq!(detect_preamble(&lines, b';'), 1);
+        assert_eq!(field_count(b"a\t'b\tc'\td", b'\t', b'\''), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_tables() -> Result<()> {
+        let mut file = NamedTempFile::new()?;
+        write!(file, "a,b\n1,2\n3,4\n\n,,\nx,y,z\n7,8,9\n")?;
+        let loader = CsvLoader::new(file.path())?;
+        let tables = loader.find_tables(&Progress::default());
+        assert_eq!(tables, vec![
+            TableSpan { preamble: 0, footer: 4, rows: 2, header: "a,b".to_string() },
+            TableSpan { preamble: 5, footer: 0, rows: 1, header: "x,y,z".to_string() },
+        ]);
+
+        // The chosen table alone is the document; the rest is kept around it
+        let first = CsvLoader::new(file.path())?.with_footer(4);
+        assert_eq!(first.total_records(), 3);
+        assert_eq!(first.footer().as_deref(), Some(&b"\n,,\nx,y,z\n7,8,9\n"[..]));
+        let second = loader.with_preamble(5).with_footer(0);
+        assert_eq!(second.num_columns(), 3);
+        assert_eq!(second.total_records(), 2);
+
+        // Empty rows inside one table, and at its end, don't split it
+        let mut file = NamedTempFile::new()?;
+        write!(file, "a,b\n1,2\n\n,\n3,4\n\n")?;
+        let tables = CsvLoader::new(file.path())?.find_tables(&Progress::default());
+        assert_eq!(tables, vec![TableSpan { preamble: 0, footer: 1, rows: 4, header: "a,b".to_string() }]);
+
+        // Widths are counted with the file's delimiter, commas in values and all
+        let mut file = NamedTempFile::new()?;
+        write!(file, "a\tb\n1,5\t2\n\nx\ty\tz\n7\t8\t9,5\n")?;
+        let loader = CsvLoader::new(file.path())?;
+        assert_eq!(loader.delimiter(), b'\t');
+        assert_eq!(loader.num_columns(), 2);
+        let tables = loader.find_tables(&Progress::default());
+        assert_eq!(tables, vec![
+            TableSpan { preamble: 0, footer: 3, rows: 1, header: "a\tb".to_string() },
+            TableSpan { preamble: 3, footer: 0, rows: 1, header: "x\ty\tz".to_string() },
+        ]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_reindex_after_append() -> Result<()> {
+        let mut file = NamedTempFile::new()?;
+        write!(file, "a,b\n1,2")?;
+        let loader = CsvLoader::new(file.path())?;
+
+        // The unterminated last record is rescanned along with the new rows
+        write!(file, "3\n4,\"5\n6\"\n")?;
+        file.flush()?;
+        let (appended, kind) = loader.reindex(file.path())?;
+        assert_eq!(kind, Reindex::Appended(1));
+        assert_eq!(appended.total_records(), 3);
+        assert_eq!(appended.get_record_line(1).as_deref(), Some(&b"1,23\n"[..]));
+        assert_eq!(appended.get_record_line(2).as_deref(), Some(&b"4,\"5\n6\"\n"[..]));
+        assert_eq!(appended.reindex(file.path())?.1, Reindex::Unchanged);
+
+        // Scanned in the background and taken on without copying the index
+        writeln!(file, "9,10")?;
+        file.flush()?;
+        let append = appended.scan_append(file.path())?.expect("only appended to");
+        let (grown, added) = CsvLoader::with_append(Arc::new(appended), append);
+        assert_eq!((added, grown.total_records()), (1, 4));
+        assert_eq!(grown.get_record_line(3).as_deref(), Some(&b"9,10\n"[..]));
+        let appended = grown;
+
+        std::fs::write(file.path(), "x,y\n1,23\n4,\"5\n6\"\n7,8\n")?;
+        let (rewritten, kind) = appended.reindex(file.path())?;
+        assert_eq!((kind, rewritten.total_records()), (Reindex::Full, 4));
+        Ok(())
+    }
 }
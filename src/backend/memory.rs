@@ -0,0 +1,106 @@
+use super::file_info::format_size;
+
+/// Pointer, length and capacity of each String or Vec
+const HEADER_BYTES: u64 = 24;
+
+/// Rough bytes needed to hold a table in memory as rows of Strings
+/// (as the editable grid or an in-memory sort does), given its size on disk
+pub fn table_estimate(file_bytes: u64, rows: usize, cols: usize) -> u64 {
+    file_bytes + rows as u64 * HEADER_BYTES * (cols as u64 + 1)
+}
+
+/// Rough bytes needed to hold every value of one column
+pub fn column_estimate(file_bytes: u64, rows: usize, cols: usize) -> u64 {
+    file_bytes / cols.max(1) as u64 + rows as u64 * HEADER_BYTES
+}
+
+/// An operation expected to need more memory than it should use
+#[derive(Clone, Debug, PartialEq)]
+pub struct MemoryWarning {
+    pub needed: u64,
+    pub budget: u64,
+    /// What the OS reports as free, when it's lower than the budget
+    pub available: Option<u64>,
+}
+
+impl MemoryWarning {
+    pub fn message(&self) -> String {
+        match self.available {
+            Some(available) => format!(
+                "This needs about {} of memory, but only {} is available.",
+                format_size(self.needed),
+                format_size(available)
+            ),
+            None => format!(
+                "This needs about {} of memory, over the {} budget set in Settings.",
+                format_size(self.needed),
+                format_size(self.budget)
+            ),
+        }
+    }
+}
+
+/// Err when `needed` bytes is over the budget, or over what the system has free
+pub fn check(needed: u64, budget_mb: u64) -> Result<(), MemoryWarning> {
+    check_against(needed, budget_mb * 1024 * 1024, available_memory())
+}
+
+fn check_against(needed: u64, budget: u64, available: Option<u64>) -> Result<(), MemoryWarning> {
+    let available = available.filter(|&a| a < budget);
+    if needed <= available.unwrap_or(budget) {
+        return Ok(());
+    }
+    Err(MemoryWarning { needed, budget, available })
+}
+
+/// Memory the OS could give us without swapping, where it exposes it cheaply
+pub fn available_memory() -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+        let line = meminfo.lines().find(|l| l.starts_with("MemAvailable:"))?;
+        let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+        Some(kb * 1024)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+/// Resident memory of this process in bytes, where the OS exposes it cheaply
+pub fn resident_memory() -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        // Second field of statm is resident pages
+        let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+        let pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+        Some(pages * 4096)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_check() {
+        const MB: u64 = 1024 * 1024;
+        // 100 MB file of 1M rows x 10 columns
+        let needed = table_estimate(100 * MB, 1_000_000, 10);
+        assert!(needed > 300 * MB && needed < 400 * MB);
+        assert!(column_estimate(100 * MB, 1_000_000, 10) < needed / 10);
+
+        assert!(check_against(needed, 512 * MB, None).is_ok());
+        let warning = check_against(needed, 256 * MB, None).unwrap_err();
+        assert!(warning.message().contains("budget"));
+        // Low free memory tightens the limit below the budget
+        let warning = check_against(needed, 512 * MB, Some(200 * MB)).unwrap_err();
+        assert_eq!(warning.available, Some(200 * MB));
+        assert!(check_against(needed, 512 * MB, Some(4096 * MB)).is_ok());
+    }
+}
@@ -1,10 +1,47 @@
 pub mod loader;
 pub mod paged_reader;
 pub mod parser;
+pub mod pipeline;
+pub mod print;
+pub mod problems;
+pub mod preview;
 pub mod editor;
 pub mod export;
+pub mod extract;
+pub mod file_info;
+pub mod filter;
 pub mod settings;
 pub mod formatting;
+pub mod geo;
+pub mod git;
 pub mod csvi;
+pub mod dates;
+pub mod document;
 pub mod grid;
+pub mod aggregate;
 pub mod analysis;
+pub mod anonymize;
+pub mod audit;
+pub mod bench;
+pub mod clipboard;
+pub mod jobs;
+pub mod memory;
+pub mod handoff;
+pub mod import;
+pub mod normalize;
+pub mod perf;
+pub mod profile_cache;
+pub mod query;
+pub mod rebase;
+pub mod schema;
+pub mod scripting;
+pub mod server;
+pub mod sort;
+pub mod snapshots;
+pub mod source;
+pub mod split;
+pub mod templates;
+pub mod units;
+pub mod workspace;
+pub mod writer;
+pub mod xlsx;
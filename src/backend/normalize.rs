@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use super::editor::CellEdit;
+use super::jobs::Progress;
+
+/// Above this many distinct keys, only exact fingerprint matches are clustered;
+/// the pairwise edit-distance pass is quadratic
+const MAX_FUZZY_KEYS: usize = 3000;
+
+/// Spellings of what is probably the same value, with how often each occurs
+#[derive(Clone, Debug)]
+pub struct ValueCluster {
+    /// (value, count), most frequent first
+    pub values: Vec<(String, usize)>,
+    /// Value the others are mapped to; the most frequent spelling by default
+    pub canonical: String,
+}
+
+impl ValueCluster {
+    pub fn total(&self) -> usize {
+        self.values.iter().map(|(_, n)| n).sum()
+    }
+}
+
+/// Count the distinct non-empty values of a column
+pub fn count_values(rows: Range<usize>, value_at: impl Fn(usize) -> String, progress: &Progress) -> Vec<(String, usize)> {
+    progress.set_total(rows.len());
+    let start = rows.start;
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for row in rows {
+        if (row - start).is_multiple_of(10_000) {
+            if progress.is_cancelled() {
+                break;
+            }
+            progress.set_done(row - start);
+        }
+        let value = value_at(row);
+        if !value.trim().is_empty() {
+            *counts.entry(value).or_insert(0) += 1;
+        }
+    }
+    counts.into_iter().collect()
+}
+
+/// Group values whose normalized forms match, are within `max_distance` edits
+/// of each other, or where one is the initials of the other ("NY" / "new york").
+/// Only groups of two or more spellings are returned, largest first.
+pub fn cluster_values(counts: &[(String, usize)], max_distance: usize) -> Vec<ValueCluster> {
+    // Values sharing a fingerprint start out in the same group
+    let mut by_key: HashMap<String, Vec<usize>> = HashMap::new();
+    for (i, (value, _)) in counts.iter().enumerate() {
+        by_key.entry(fingerprint(value)).or_default().push(i);
+    }
+    let keys: Vec<String> = by_key.keys().cloned().collect();
+    let mut parent: Vec<usize> = (0..keys.len()).collect();
+
+    if keys.len() <= MAX_FUZZY_KEYS {
+        let initials: Vec<String> = keys.iter().map(|k| initials(k)).collect();
+        for a in 0..keys.len() {
+            for b in (a + 1)..keys.len() {
+                let (ka, kb) = (&keys[a], &keys[b]);
+                let acronym = (initials[a].len() > 1 && initials[a] == *kb) || (initials[b].len() > 1 && initials[b] == *ka);
+                // Short keys like "ca" / "co" differ by one edit but aren't typos of each other
+                let close = max_distance > 0
+                    && ka.chars().count().min(kb.chars().count()) > max_distance * 3
+                    && levenshtein(ka, kb) <= max_distance;
+                if acronym || close {
+                    let (ra, rb) = (find(&mut parent, a), find(&mut parent, b));
+                    parent[ra] = rb;
+                }
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (k, key) in keys.iter().enumerate() {
+        let root = find(&mut parent, k);
+        groups.entry(root).or_default().extend(&by_key[key]);
+    }
+
+    let mut clusters: Vec<ValueCluster> = groups
+        .into_values()
+        .filter(|members| members.len() > 1)
+        .map(|members| {
+            let mut values: Vec<(String, usize)> = members.into_iter().map(|i| counts[i].clone()).collect();
+            values.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+            ValueCluster { canonical: values[0].0.clone(), values }
+        })
+        .collect();
+    clusters.sort_by(|a, b| b.total().cmp(&a.total()).then_with(|| a.canonical.cmp(&b.canonical)));
+    clusters
+}
+
+/// Edits (row, col, old, new) that replace every mapped value in a column
+pub fn normalization_edits(
+    col: usize,
+    rows: Range<usize>,
+    value_at: impl Fn(usize) -> String,
+    mapping: &HashMap<String, String>,
+    progress: &Progress,
+) -> Vec<CellEdit> {
+    progress.set_total(rows.len());
+    let start = rows.start;
+    let mut edits = Vec::new();
+    for row in rows {
+        if (row - start).is_multiple_of(10_000) {
+            progress.set_done(row - start);
+        }
+        let value = value_at(row);
+        if let Some(new) = mapping.get(&value)
+            && *new != value
+        {
+            edits.push((row, col, value, new.clone()));
+        }
+    }
+    edits
+}
+
+/// Lowercase words with punctuation removed and whitespace collapsed
+pub fn fingerprint(value: &str) -> String {
+    let cleaned: String = value
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c.is_whitespace() { c } else { ' ' })
+        .collect();
+    // "N.Y." leaves single letters behind, which belong together
+    let words: Vec<&str> = cleaned.split_whitespace().collect();
+    if words.len() > 1 && words.iter().all(|w| w.chars().count() == 1) {
+        words.concat()
+    } else {
+        words.join(" ")
+    }
+}
+
+fn initials(key: &str) -> String {
+    let words: Vec<&str> = key.split_whitespace().collect();
+    if words.len() < 2 {
+        return String::new();
+    }
+    words.iter().filter_map(|w| w.chars().next()).collect()
+}
+
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = (above + 1).min(row[j] + 1).min(diagonal + usize::from(ca != *cb));
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cluster_near_duplicates() {
+        let counts: Vec<(String, usize)> = [("NY", 5), ("N.Y.", 2), ("new york", 1), ("California", 4), ("Califronia", 1), ("CA", 3), ("CO", 2)]
+            .iter()
+            .map(|(v, n)| (v.to_string(), *n))
+            .collect();
+        let clusters = cluster_values(&counts, 2);
+
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[0].canonical, "NY");
+        assert_eq!(clusters[0].values.len(), 3);
+        assert_eq!(clusters[1].canonical, "California");
+        assert_eq!(clusters[1].values.len(), 2);
+
+        let mapping: HashMap<String, String> = [("N.Y.".to_string(), "NY".to_string())].into();
+        let column = ["state", "N.Y.", "NY", "N.Y."];
+        let edits = normalization_edits(0, 1..4, |r| column[r].to_string(), &mapping, &Progress::default());
+        assert_eq!(edits.iter().map(|e| e.0).collect::<Vec<_>>(), [1, 3]);
+    }
+}
@@ -1,12 +1,15 @@
 use anyhow::Result;
 use std::cmp::min;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use super::loader::CsvLoader;
 
 pub struct PagedReader {
     loader: Arc<CsvLoader>,
     page_size: usize,
+    // Rows handed out since the performance overlay last asked
+    rows_read: AtomicUsize,
 }
 
 impl PagedReader {
@@ -14,6 +17,7 @@ impl PagedReader {
         Self {
             loader,
             page_size: 100,
+            rows_read: AtomicUsize::new(0),
         }
     }
 
@@ -21,6 +25,7 @@ impl PagedReader {
         Self {
             loader: Arc::new(CsvLoader::empty(0, 0)),
             page_size: 100,
+            rows_read: AtomicUsize::new(0),
         }
     }
 
@@ -40,13 +45,19 @@ impl PagedReader {
                 // We do a lossy utf8 conversion here for display purposes.
                 // In a real editor we might want to keep bytes if encoding is weird,
                 // but for now String is fine.
-                let line = String::from_utf8_lossy(bytes).into_owned();
+                let line = String::from_utf8_lossy(&bytes).into_owned();
                 rows.push(line);
             } else {
                 break;
             }
         }
 
+        self.rows_read.fetch_add(rows.len(), Ordering::Relaxed);
         Ok(rows)
     }
+
+    /// Rows read since the last call
+    pub fn take_rows_read(&self) -> usize {
+        self.rows_read.swap(0, Ordering::Relaxed)
+    }
 }
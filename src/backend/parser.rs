@@ -22,6 +22,22 @@ impl CsvParser {
              Ok(vec![])
         }
     }
+
+    /// Parses only the fields at `cols` (in that order), so unselected columns
+    /// are never turned into strings. Missing fields come back empty.
+    pub fn parse_line_projected(line: &str, cols: &[usize]) -> Result<Vec<String>> {
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(line.as_bytes());
+
+        let mut record = ByteRecord::new();
+        reader.read_byte_record(&mut record)?;
+        Ok(cols
+            .iter()
+            .map(|&c| record.get(c).map(|f| String::from_utf8_lossy(f).into_owned()).unwrap_or_default())
+            .collect())
+    }
 }
 
 #[cfg(test)]
@@ -41,4 +57,10 @@ mod tests {
         let fields = CsvParser::parse_line(line).unwrap();
         assert_eq!(fields, vec!["a", "b,c", "d"]);
     }
+
+    #[test]
+    fn test_parse_projected() {
+        let fields = CsvParser::parse_line_projected("a,\"b,c\",d", &[2, 0, 5]).unwrap();
+        assert_eq!(fields, vec!["d", "a", ""]);
+    }
 }
@@ -0,0 +1,54 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Frames kept for the rolling average
+const FRAME_WINDOW: usize = 120;
+
+/// Recent frame times for the performance overlay
+#[derive(Default)]
+pub struct FrameStats {
+    times: VecDeque<Duration>,
+}
+
+impl FrameStats {
+    pub fn record(&mut self, time: Duration) {
+        if self.times.len() == FRAME_WINDOW {
+            self.times.pop_front();
+        }
+        self.times.push_back(time);
+    }
+
+    pub fn last(&self) -> Duration {
+        self.times.back().copied().unwrap_or_default()
+    }
+
+    pub fn average(&self) -> Duration {
+        if self.times.is_empty() {
+            return Duration::ZERO;
+        }
+        self.times.iter().sum::<Duration>() / self.times.len() as u32
+    }
+
+    /// Slowest frame in the window, which is what a stutter looks like
+    pub fn max(&self) -> Duration {
+        self.times.iter().max().copied().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_stats_window() {
+        let mut stats = FrameStats::default();
+        assert_eq!(stats.average(), Duration::ZERO);
+        for ms in 1..=FRAME_WINDOW as u64 + 2 {
+            stats.record(Duration::from_millis(ms));
+        }
+        // The first two frames fell out of the window
+        assert_eq!(stats.last(), Duration::from_millis(FRAME_WINDOW as u64 + 2));
+        assert_eq!(stats.max(), stats.last());
+        assert_eq!(stats.average(), Duration::from_micros((3 + FRAME_WINDOW as u64 + 2) * 1000 / 2));
+    }
+}
@@ -0,0 +1,329 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use super::export::{ensure_not_input, export_rows, ExportColumn, ExportPreset};
+use super::formatting::FormatMap;
+use super::jobs::Progress;
+use super::loader::CsvLoader;
+use super::parser::CsvParser;
+use super::query::Query;
+use super::sort::{sort_indices, SortOrder, DEFAULT_RUN_BYTES};
+
+/// Rows shown when previewing a pipeline
+pub const PREVIEW_ROWS: usize = 50;
+/// Source rows a preview reads, so it stays instant on big files
+const PREVIEW_SOURCE_ROWS: usize = 5_000;
+/// Default destination of a new pipeline's export, beside the input but never on it
+pub const DEFAULT_DESTINATION: &str = "{dir}/{stem}-pipeline.{ext}";
+
+/// A value rewrite applied to every cell of a column
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ColumnTransform {
+    Trim,
+    Uppercase,
+    Lowercase,
+    Replace { find: String, replace: String },
+}
+
+impl ColumnTransform {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColumnTransform::Trim => "Trim",
+            ColumnTransform::Uppercase => "Uppercase",
+            ColumnTransform::Lowercase => "Lowercase",
+            ColumnTransform::Replace { .. } => "Replace",
+        }
+    }
+
+    pub fn apply(&self, value: &str) -> String {
+        match self {
+            ColumnTransform::Trim => value.trim().to_string(),
+            ColumnTransform::Uppercase => value.to_uppercase(),
+            ColumnTransform::Lowercase => value.to_lowercase(),
+            ColumnTransform::Replace { find, replace } if !find.is_empty() => value.replace(find.as_str(), replace),
+            ColumnTransform::Replace { .. } => value.to_string(),
+        }
+    }
+}
+
+/// One step of a pipeline. Columns are named by header so a saved pipeline
+/// runs on any file with the same columns.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum PipelineStep {
+    /// Keep rows matching a WHERE condition, e.g. `status = 'open' AND total > 10`
+    Filter { condition: String },
+    Transform { column: String, transform: ColumnTransform },
+    Sort { column: String, order: SortOrder },
+}
+
+impl PipelineStep {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PipelineStep::Filter { .. } => "Filter",
+            PipelineStep::Transform { .. } => "Transform",
+            PipelineStep::Sort { .. } => "Sort",
+        }
+    }
+}
+
+/// Steps run in order on the data rows, then the result is exported.
+/// Saved as JSON so a cleanup session can be re-run on next month's file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Pipeline {
+    pub steps: Vec<PipelineStep>,
+    /// Format and destination; no columns means every column under its own header
+    pub export: ExportPreset,
+}
+
+impl Pipeline {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&text).with_context(|| format!("{} is not a pipeline file", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+type RowMatcher = Box<dyn Fn(&[String]) -> bool>;
+
+/// A step with its columns resolved against the input's headers
+enum Stage {
+    Filter(RowMatcher),
+    Transform(usize, ColumnTransform),
+    Sort(usize, SortOrder),
+}
+
+fn compile(steps: &[PipelineStep], headers: &[String]) -> Result<Vec<Stage>> {
+    let find = |name: &str| {
+        headers
+            .iter()
+            .position(|h| h.trim().eq_ignore_ascii_case(name.trim()))
+            .with_context(|| format!("Column \"{}\" not found", name))
+    };
+    steps
+        .iter()
+        .map(|step| {
+            Ok(match step {
+                PipelineStep::Filter { condition } => {
+                    let query = Query::parse(&format!("SELECT * WHERE {}", condition))
+                        .with_context(|| format!("Bad filter \"{}\"", condition))?;
+                    Stage::Filter(Box::new(query.matcher(headers)?))
+                }
+                PipelineStep::Transform { column, transform } => Stage::Transform(find(column)?, transform.clone()),
+                PipelineStep::Sort { column, order } => Stage::Sort(find(column)?, *order),
+            })
+        })
+        .collect()
+}
+
+/// The row after the filters and transforms among `stages`, or None if a filter drops it
+fn eval(stages: &[Stage], mut values: Vec<String>) -> Option<Vec<String>> {
+    for stage in stages {
+        match stage {
+            Stage::Filter(matches) => {
+                if !matches(&values) {
+                    return None;
+                }
+            }
+            Stage::Transform(col, transform) => {
+                if let Some(value) = values.get_mut(*col) {
+                    *value = transform.apply(value);
+                }
+            }
+            Stage::Sort(..) => {}
+        }
+    }
+    Some(values)
+}
+
+/// Source rows that come out of the pipeline, in output order. Only the
+/// indices are kept between steps; values are re-read and re-transformed.
+fn run_stages(
+    stages: &[Stage],
+    rows: Range<usize>,
+    row_at: &impl Fn(usize) -> Vec<String>,
+    progress: &Progress,
+) -> Result<Vec<usize>> {
+    let mut indices: Vec<usize> = rows.collect();
+    let mut done = 0;
+    for (i, stage) in stages.iter().enumerate() {
+        match stage {
+            Stage::Filter(_) => {
+                progress.set_total(indices.len());
+                indices.retain(|&r| {
+                    done += 1;
+                    if done % 10_000 == 0 {
+                        progress.set_done(done);
+                    }
+                    !progress.is_cancelled() && eval(&stages[..=i], row_at(r)).is_some()
+                });
+                done = 0;
+            }
+            Stage::Sort(col, order) => {
+                let keys = indices.iter().filter_map(|&r| {
+                    let values = eval(&stages[..i], row_at(r))?;
+                    Some((r, values.get(*col).cloned().unwrap_or_default()))
+                });
+                indices = sort_indices(keys, *order, DEFAULT_RUN_BYTES, &std::env::temp_dir(), progress)?
+                    .collect::<Result<_>>()?;
+            }
+            Stage::Transform(..) => {}
+        }
+        if progress.is_cancelled() {
+            anyhow::bail!("Pipeline cancelled");
+        }
+    }
+    Ok(indices)
+}
+
+/// Run the steps over the data rows in `rows` and export the result to `path`
+pub fn run_pipeline(
+    pipeline: &Pipeline,
+    headers: &[String],
+    rows: Range<usize>,
+    row_at: impl Fn(usize) -> Vec<String>,
+    path: &Path,
+    progress: &Progress,
+) -> Result<Vec<PathBuf>> {
+    let stages = compile(&pipeline.steps, headers)?;
+    let indices = run_stages(&stages, rows, &row_at, progress)?;
+    let mut export = pipeline.export.clone();
+    if export.options.columns.is_empty() {
+        export.options.columns = headers.iter().enumerate().map(|(c, h)| ExportColumn { source: c, name: h.clone() }).collect();
+        export.source_headers = headers.to_vec();
+    }
+    let options = export.resolve(headers)?;
+    let value_at = |i: usize| eval(&stages, row_at(indices[i])).unwrap_or_default();
+    export_rows(path, &options, 0..indices.len(), value_at, &[], &FormatMap::new(), progress)
+}
+
+/// The first `PREVIEW_ROWS` output rows, from the first rows of the input
+pub fn preview_pipeline(
+    steps: &[PipelineStep],
+    headers: &[String],
+    rows: Range<usize>,
+    row_at: impl Fn(usize) -> Vec<String>,
+) -> Result<Vec<Vec<String>>> {
+    let stages = compile(steps, headers)?;
+    let rows = rows.start..rows.end.min(rows.start + PREVIEW_SOURCE_ROWS);
+    let indices = run_stages(&stages, rows, &row_at, &Progress::default())?;
+    Ok(indices
+        .into_iter()
+        .take(PREVIEW_ROWS)
+        .filter_map(|r| eval(&stages, row_at(r)))
+        .collect())
+}
+
+/// Run a pipeline file on a CSV file from disk, as `csvit pipeline` does
+pub fn run_pipeline_file(pipeline: &Pipeline, input: &Path, progress: &Progress) -> Result<Vec<PathBuf>> {
+    let loader = CsvLoader::open_table(input)?;
+    let row_at = |r: usize| {
+        loader.get_record_line(r)
+            .and_then(|line| CsvParser::parse_line(&String::from_utf8_lossy(&line)).ok())
+            .unwrap_or_default()
+    };
+    let headers = row_at(0);
+    let path = pipeline.export.destination_for(input, chrono::Local::now().date_naive());
+    ensure_not_input(input, &path)?;
+    if let Some(dir) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    let total = loader.total_records();
+    run_pipeline(pipeline, &headers, 1.min(total)..total, row_at, &path, progress)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::export::{ExportFormat, ExportOptions};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_pipeline_filter_transform_sort_export() -> Result<()> {
+        let dir = TempDir::new()?;
+        let input = dir.path().join("orders.csv");
+        std::fs::write(&input, "name,status,total\n bob ,open,5\nann,closed,9\n cy,open,12\ndee,open,1\n")?;
+        let pipeline = Pipeline {
+            steps: vec![
+                PipelineStep::Filter { condition: "status = 'open' AND total > 2".to_string() },
+                PipelineStep::Transform { column: "name".to_string(), transform: ColumnTransform::Trim },
+                PipelineStep::Transform { column: "name".to_string(), transform: ColumnTransform::Uppercase },
+                PipelineStep::Sort { column: "total".to_string(), order: SortOrder::Descending },
+            ],
+            export: ExportPreset {
+                name: String::new(),
+                options: ExportOptions { format: ExportFormat::Csv, ..Default::default() },
+                source_headers: Vec::new(),
+                destination: "{dir}/{stem}-clean.{ext}".to_string(),
+            },
+        };
+
+        // Saved and loaded as JSON, then run on the file
+        let saved = dir.path().join("cleanup.json");
+        pipeline.save(&saved)?;
+        let loaded = Pipeline::load(&saved)?;
+        assert_eq!(loaded, pipeline);
+        let written = run_pipeline_file(&loaded, &input, &Progress::default())?;
+        assert_eq!(written, [dir.path().join("orders-clean.csv")]);
+        assert_eq!(std::fs::read_to_string(&written[0])?, "name,status,total\nCY,open,12\nBOB,open,5\n");
+
+        let headers = ["name", "status", "total"].map(String::from);
+        let rows = [[" bob ", "open", "5"], ["ann", "closed", "9"]];
+        let preview = preview_pipeline(&pipeline.steps, &headers, 0..2, |r| rows[r].map(String::from).to_vec())?;
+        assert_eq!(preview, [["BOB", "open", "5"]]);
+
+        let bad = [PipelineStep::Sort { column: "missing".to_string(), order: SortOrder::Ascending }];
+        assert!(preview_pipeline(&bad, &headers, 0..2, |r| rows[r].map(String::from).to_vec()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_default_pipeline_leaves_input_alone() -> Result<()> {
+        let dir = TempDir::new()?;
+        let input = dir.path().join("orders.csv");
+        std::fs::write(&input, "name,total\nann,9\nbob,5\n")?;
+        let mut pipeline = Pipeline {
+            steps: Vec::new(),
+            export: ExportPreset {
+                name: String::new(),
+                options: ExportOptions { format: ExportFormat::Csv, ..Default::default() },
+                source_headers: Vec::new(),
+                destination: DEFAULT_DESTINATION.to_string(),
+            },
+        };
+        let written = run_pipeline_file(&pipeline, &input, &Progress::default())?;
+        assert_eq!(written, [dir.path().join("orders-pipeline.csv")]);
+        assert_eq!(std::fs::read_to_string(&written[0])?, "name,total\nann,9\nbob,5\n");
+
+        // A destination that resolves to the input is refused
+        pipeline.export.destination = "{dir}/{stem}.{ext}".to_string();
+        assert!(run_pipeline_file(&pipeline, &input, &Progress::default()).is_err());
+        assert_eq!(std::fs::read_to_string(&input)?, "name,total\nann,9\nbob,5\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_pipeline_file_skips_notes_above_the_header() -> Result<()> {
+        let dir = TempDir::new()?;
+        let input = dir.path().join("orders.csv");
+        std::fs::write(&input, "Exported by Shop v2\nname,total\nann,9\nbob,5\n")?;
+        let pipeline = Pipeline {
+            steps: Vec::new(),
+            export: ExportPreset {
+                name: String::new(),
+                options: ExportOptions { format: ExportFormat::Csv, ..Default::default() },
+                source_headers: Vec::new(),
+                destination: DEFAULT_DESTINATION.to_string(),
+            },
+        };
+        let written = run_pipeline_file(&pipeline, &input, &Progress::default())?;
+        assert_eq!(std::fs::read_to_string(&written[0])?, "name,total\nann,9\nbob,5\n");
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use super::csvi::{is_csvi_file, load_csvi};
+
+/// Rows shown in a file preview, including the header line
+pub const PREVIEW_ROWS: usize = 10;
+/// Only the start of the file is read, so previews stay cheap for huge files
+const SAMPLE_BYTES: u64 = 64 * 1024;
+
+/// A quick look at the start of a file, without indexing it
+#[derive(Clone, Debug)]
+pub struct FilePreview {
+    pub size: u64,
+    pub delimiter: u8,
+    pub columns: usize,
+    pub rows: Vec<Vec<String>>,
+}
+
+pub fn preview_file(path: &Path) -> Result<FilePreview> {
+    let size = std::fs::metadata(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?
+        .len();
+    let sample = if is_csvi_file(path) {
+        let (csv, _) = load_csvi(path)?;
+        csv.chars().take(SAMPLE_BYTES as usize).collect()
+    } else {
+        let mut bytes = Vec::new();
+        File::open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?
+            .take(SAMPLE_BYTES)
+            .read_to_end(&mut bytes)?;
+        String::from_utf8_lossy(&bytes).into_owned()
+    };
+
+    let delimiter = sniff_delimiter(&sample);
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(delimiter)
+        .from_reader(sample.as_bytes());
+    let rows: Vec<Vec<String>> = reader
+        .records()
+        .take(PREVIEW_ROWS)
+        .filter_map(|r| r.ok())
+        .map(|r| r.iter().map(str::to_string).collect())
+        .collect();
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+
+    Ok(FilePreview { size, delimiter, columns, rows })
+}
+
+/// Pick the delimiter that splits the first lines into the same, largest number of fields
+pub fn sniff_delimiter(sample: &str) -> u8 {
+    let lines: Vec<&str> = sample.lines().filter(|l| !l.is_empty()).take(PREVIEW_ROWS).collect();
+    let mut best = (b',', 0, 0);
+    for delimiter in [b',', b';', b'\t', b'|'] {
+        let counts: Vec<usize> = lines.iter().map(|l| count_unquoted(l, delimiter)).collect();
+        let Some(&first) = counts.first() else { break };
+        if first == 0 {
+            continue;
+        }
+        let consistent = counts.iter().filter(|&&c| c == first).count();
+        if (consistent, first) > (best.1, best.2) {
+            best = (delimiter, consistent, first);
+        }
+    }
+    best.0
+}
+
+fn count_unquoted(line: &str, delimiter: u8) -> usize {
+    let mut in_quote = false;
+    line.bytes()
+        .filter(|&b| {
+            if b == b'"' {
+                in_quote = !in_quote;
+            }
+            b == delimiter && !in_quote
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_preview_detects_delimiter() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("data.csv");
+        let mut text = String::from("id;name;note\n");
+        for i in 0..20 {
+            text.push_str(&format!("{};\"Smith, J\";x\n", i));
+        }
+        std::fs::write(&path, &text)?;
+
+        let preview = preview_file(&path)?;
+        assert_eq!(preview.delimiter, b';');
+        assert_eq!(preview.columns, 3);
+        assert_eq!(preview.rows.len(), PREVIEW_ROWS);
+        assert_eq!(preview.rows[1][1], "Smith, J");
+        assert_eq!(preview.size, text.len() as u64);
+        Ok(())
+    }
+}
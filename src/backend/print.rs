@@ -0,0 +1,144 @@
+use anyhow::{Context, Result};
+use printpdf::{BuiltinFont, IndirectFontRef, Line, Mm, PdfDocument, PdfLayerReference, Point};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use super::jobs::Progress;
+
+// A4 landscape, in millimetres
+const PAGE_WIDTH: f32 = 297.0;
+const PAGE_HEIGHT: f32 = 210.0;
+const MARGIN: f32 = 12.0;
+const FONT_SIZE: f32 = 8.0;
+const ROW_HEIGHT: f32 = 4.5;
+/// Rough average glyph width of Helvetica, as a fraction of the font size
+const CHAR_WIDTH_EM: f32 = 0.5;
+const PT_TO_MM: f32 = 0.3528;
+
+/// What to print: a title, the column headers and their relative widths
+pub struct PrintLayout {
+    pub title: String,
+    pub headers: Vec<String>,
+    /// Relative widths (e.g. on-screen pixels); scaled to fit the page
+    pub widths: Vec<f32>,
+}
+
+/// Lay out `total` rows as a paginated table and write it to a PDF.
+/// Headers repeat on every page. Returns the number of pages written.
+pub fn print_pdf(
+    path: &Path,
+    layout: &PrintLayout,
+    total: usize,
+    row_at: impl Fn(usize) -> Vec<String>,
+    progress: &Progress,
+) -> Result<usize> {
+    let usable_width = PAGE_WIDTH - 2.0 * MARGIN;
+    let sum: f32 = layout.widths.iter().sum::<f32>().max(1.0);
+    let widths: Vec<f32> = layout.widths.iter().map(|w| w / sum * usable_width).collect();
+
+    // Leave room for the title and header above the rows
+    let rows_per_page = (((PAGE_HEIGHT - 2.0 * MARGIN) / ROW_HEIGHT) as usize).saturating_sub(3).max(1);
+    let pages = total.div_ceil(rows_per_page).max(1);
+
+    let (doc, first_page, first_layer) = PdfDocument::new(&layout.title, Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Table");
+    let font = doc.add_builtin_font(BuiltinFont::Helvetica).map_err(|e| anyhow::anyhow!("{}", e))?;
+    let bold = doc.add_builtin_font(BuiltinFont::HelveticaBold).map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    progress.set_total(total);
+    for page in 0..pages {
+        if progress.is_cancelled() {
+            anyhow::bail!("Printing cancelled");
+        }
+        let layer = if page == 0 {
+            doc.get_page(first_page).get_layer(first_layer)
+        } else {
+            let (p, l) = doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "Table");
+            doc.get_page(p).get_layer(l)
+        };
+
+        let mut y = PAGE_HEIGHT - MARGIN - ROW_HEIGHT;
+        layer.use_text(&layout.title, FONT_SIZE + 2.0, Mm(MARGIN), Mm(y), &bold);
+        y -= ROW_HEIGHT * 1.5;
+        draw_row(&layer, &bold, &layout.headers, &widths, y);
+        rule(&layer, y - 1.2);
+
+        let start = page * rows_per_page;
+        for r in start..(start + rows_per_page).min(total) {
+            y -= ROW_HEIGHT;
+            draw_row(&layer, &font, &row_at(r), &widths, y);
+        }
+        progress.set_done((start + rows_per_page).min(total));
+
+        let footer = format!("Page {} of {}", page + 1, pages);
+        let footer_x = PAGE_WIDTH - MARGIN - text_width(&footer, FONT_SIZE);
+        layer.use_text(footer, FONT_SIZE, Mm(footer_x), Mm(MARGIN / 2.0), &font);
+    }
+
+    let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    doc.save(&mut BufWriter::new(file)).map_err(|e| anyhow::anyhow!("Failed to write PDF: {}", e))?;
+    Ok(pages)
+}
+
+fn draw_row(layer: &PdfLayerReference, font: &IndirectFontRef, values: &[String], widths: &[f32], y: f32) {
+    let mut x = MARGIN;
+    for (i, width) in widths.iter().enumerate() {
+        let value = values.get(i).map(String::as_str).unwrap_or("");
+        let text = fit_text(value, width - 1.5);
+        if !text.is_empty() {
+            layer.use_text(text, FONT_SIZE, Mm(x), Mm(y), font);
+        }
+        x += width;
+    }
+}
+
+fn rule(layer: &PdfLayerReference, y: f32) {
+    layer.set_outline_thickness(0.3);
+    layer.add_line(Line {
+        points: vec![
+            (Point::new(Mm(MARGIN), Mm(y)), false),
+            (Point::new(Mm(PAGE_WIDTH - MARGIN), Mm(y)), false),
+        ],
+        is_closed: false,
+    });
+}
+
+fn text_width(text: &str, size: f32) -> f32 {
+    text.chars().count() as f32 * size * CHAR_WIDTH_EM * PT_TO_MM
+}
+
+/// Cut text to fit `width` millimetres, marking the cut with "..."
+fn fit_text(text: &str, width: f32) -> String {
+    let text = text.replace(['\n', '\r', '\t'], " ");
+    let max_chars = (width / (FONT_SIZE * CHAR_WIDTH_EM * PT_TO_MM)).max(0.0) as usize;
+    if text.chars().count() <= max_chars {
+        text
+    } else if max_chars > 3 {
+        text.chars().take(max_chars - 3).chain("...".chars()).collect()
+    } else {
+        text.chars().take(max_chars).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_print_paginates() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("out.pdf");
+        let layout = PrintLayout {
+            title: "data.csv".to_string(),
+            headers: vec!["id".to_string(), "name".to_string()],
+            widths: vec![50.0, 150.0],
+        };
+        let pages = print_pdf(&path, &layout, 100, |r| vec![r.to_string(), format!("row {}", r)], &Progress::default())?;
+
+        assert_eq!(pages, 3);
+        assert!(std::fs::read(&path)?.starts_with(b"%PDF"));
+        assert_eq!(fit_text("abcdefghij", 10.0), "abcd...");
+        Ok(())
+    }
+}
@@ -0,0 +1,235 @@
+use std::ops::Range;
+
+use super::analysis::{ColumnAnalyzer, InferredType};
+use super::jobs::Progress;
+use super::schema::SchemaIssue;
+
+/// Problems listed individually per category; the rest are only counted
+const MAX_LISTED_PER_CATEGORY: usize = 1_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Info,
+}
+
+impl Severity {
+    pub const ALL: [Severity; 3] = [Severity::Error, Severity::Warning, Severity::Info];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Severity::Error => "Errors",
+            Severity::Warning => "Warnings",
+            Severity::Info => "Info",
+        }
+    }
+
+    pub fn icon(&self) -> &'static str {
+        match self {
+            Severity::Error => "⛔",
+            Severity::Warning => "⚠",
+            Severity::Info => "ℹ",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProblemCategory {
+    /// A record that doesn't parse as CSV
+    Parse,
+    /// Bytes that aren't valid UTF-8
+    Encoding,
+    /// A record with more or fewer fields than the header
+    RaggedRow,
+    /// A schema constraint or row rule that isn't met
+    Validation,
+    /// A value that doesn't fit its column's inferred type
+    TypeCoercion,
+}
+
+impl ProblemCategory {
+    pub const ALL: [ProblemCategory; 5] = [
+        ProblemCategory::Parse,
+        ProblemCategory::Encoding,
+        ProblemCategory::RaggedRow,
+        ProblemCategory::Validation,
+        ProblemCategory::TypeCoercion,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProblemCategory::Parse => "Parse errors",
+            ProblemCategory::Encoding => "Encoding",
+            ProblemCategory::RaggedRow => "Ragged rows",
+            ProblemCategory::Validation => "Validation",
+            ProblemCategory::TypeCoercion => "Type coercion",
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match self {
+            ProblemCategory::Parse | ProblemCategory::Validation => Severity::Error,
+            ProblemCategory::Encoding | ProblemCategory::RaggedRow => Severity::Warning,
+            ProblemCategory::TypeCoercion => Severity::Info,
+        }
+    }
+
+    fn index(&self) -> usize {
+        Self::ALL.iter().position(|c| c == self).unwrap_or(0)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Problem {
+    pub category: ProblemCategory,
+    pub row: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl Problem {
+    pub fn severity(&self) -> Severity {
+        self.category.severity()
+    }
+}
+
+/// Schema validation results as problems
+impl From<&SchemaIssue> for Problem {
+    fn from(issue: &SchemaIssue) -> Self {
+        Problem { category: ProblemCategory::Validation, row: issue.row, column: issue.column, message: issue.message.clone() }
+    }
+}
+
+/// Problems found by a scan, listed up to a cap per category and counted in full
+#[derive(Clone, Debug, Default)]
+pub struct Problems {
+    pub list: Vec<Problem>,
+    counts: [usize; ProblemCategory::ALL.len()],
+}
+
+impl Problems {
+    pub fn push(&mut self, problem: Problem) {
+        let count = &mut self.counts[problem.category.index()];
+        *count += 1;
+        if *count <= MAX_LISTED_PER_CATEGORY {
+            self.list.push(problem);
+        }
+    }
+
+    pub fn count(&self, category: ProblemCategory) -> usize {
+        self.counts[category.index()]
+    }
+
+    pub fn total(&self) -> usize {
+        self.counts.iter().sum()
+    }
+}
+
+/// A record as the scan sees it: its fields as parsed, and what went wrong reading it
+#[derive(Clone, Debug, Default)]
+pub struct ScannedRow {
+    pub fields: Vec<String>,
+    pub invalid_utf8: bool,
+    pub parse_error: Option<String>,
+}
+
+/// Check `rows` for parse errors, invalid UTF-8, a field count other than
+/// `num_cols`, and values that don't fit their column's type in `types`
+/// (None where a column's type isn't known). Rows are ordered as scanned.
+pub fn scan(
+    rows: Range<usize>,
+    num_cols: usize,
+    types: &[Option<InferredType>],
+    row_at: impl Fn(usize) -> ScannedRow,
+    progress: &Progress,
+) -> Problems {
+    progress.set_total(rows.len());
+    let start = rows.start;
+    let mut problems = Problems::default();
+    for row in rows {
+        if (row - start).is_multiple_of(10_000) {
+            if progress.is_cancelled() {
+                break;
+            }
+            progress.set_done(row - start);
+        }
+        let scanned = row_at(row);
+        if scanned.invalid_utf8 {
+            problems.push(Problem {
+                category: ProblemCategory::Encoding,
+                row: Some(row),
+                column: None,
+                message: "Contains bytes that aren't valid UTF-8".to_string(),
+            });
+        }
+        if let Some(message) = scanned.parse_error {
+            problems.push(Problem { category: ProblemCategory::Parse, row: Some(row), column: None, message });
+            continue;
+        }
+        if scanned.fields.len() != num_cols {
+            problems.push(Problem {
+                category: ProblemCategory::RaggedRow,
+                row: Some(row),
+                column: Some(scanned.fields.len().min(num_cols).saturating_sub(1)),
+                message: format!("{} field(s), the header has {}", scanned.fields.len(), num_cols),
+            });
+        }
+        for (col, value) in scanned.fields.iter().enumerate() {
+            let Some(Some(expected)) = types.get(col) else {
+                continue;
+            };
+            if let Some(message) = coercion_problem(expected, value) {
+                problems.push(Problem { category: ProblemCategory::TypeCoercion, row: Some(row), column: Some(col), message });
+            }
+        }
+    }
+    problems
+}
+
+/// Why `value` won't convert to a column of type `expected`, for the typed columns
+fn coercion_problem(expected: &InferredType, value: &str) -> Option<String> {
+    let value = value.trim();
+    if ColumnAnalyzer::is_null_like(value)
+        || !matches!(expected, InferredType::Integer | InferredType::Float | InferredType::Boolean | InferredType::Date)
+    {
+        return None;
+    }
+    let actual = ColumnAnalyzer::infer_value_type(value);
+    let fits = actual == *expected || (*expected == InferredType::Float && actual == InferredType::Integer);
+    (!fits).then(|| format!("\"{}\" is {}, the column is {}", value, actual.name(), expected.name()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_finds_each_category() {
+        let row = |fields: &[&str]| ScannedRow { fields: fields.iter().map(|f| f.to_string()).collect(), ..Default::default() };
+        let rows = [
+            row(&["1", "a"]),
+            row(&["2"]),
+            ScannedRow { invalid_utf8: true, ..row(&["3", "b"]) },
+            ScannedRow { parse_error: Some("unterminated quote".to_string()), ..Default::default() },
+            row(&["x", "c"]),
+            row(&["NA", "d"]),
+        ];
+        let types = [Some(InferredType::Integer), None];
+        let problems = scan(0..rows.len(), 2, &types, |r| rows[r].clone(), &Progress::default());
+
+        let found: Vec<(ProblemCategory, Option<usize>)> = problems.list.iter().map(|p| (p.category, p.row)).collect();
+        assert_eq!(
+            found,
+            [
+                (ProblemCategory::RaggedRow, Some(1)),
+                (ProblemCategory::Encoding, Some(2)),
+                (ProblemCategory::Parse, Some(3)),
+                (ProblemCategory::TypeCoercion, Some(4)),
+            ]
+        );
+        assert_eq!(problems.count(ProblemCategory::Parse), 1);
+        assert_eq!(problems.total(), 4);
+        assert_eq!(problems.list[3].severity(), Severity::Info);
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use super::analysis::ColumnProfile;
+use super::jobs::Job;
+
+/// Cheap identity for a file's contents: path, size and modification time
+pub fn file_key(path: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    if let Ok(meta) = std::fs::metadata(path) {
+        meta.len().hash(&mut hasher);
+        if let Ok(modified) = meta.modified() {
+            modified.hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+/// Column profiles cached per (file, column, edit generation), computed in the background
+#[derive(Default)]
+pub struct ProfileCache {
+    file_key: u64,
+    entries: HashMap<usize, (u64, ColumnProfile)>,
+    pending: HashMap<usize, (u64, Job<ColumnProfile>)>,
+    hits: u64,
+    misses: u64,
+}
+
+impl ProfileCache {
+    pub fn new(file_key: u64) -> Self {
+        Self {
+            file_key,
+            ..Default::default()
+        }
+    }
+
+    /// Switch to a different file, dropping everything cached for the old one
+    pub fn set_file_key(&mut self, file_key: u64) {
+        if file_key != self.file_key {
+            *self = Self::new(file_key);
+        }
+    }
+
+    /// The cached profile, if it was computed for this generation of the column
+    pub fn get(&self, col: usize, generation: u64) -> Option<&ColumnProfile> {
+        self.entries
+            .get(&col)
+            .filter(|(cached_gen, _)| *cached_gen == generation)
+            .map(|(_, profile)| profile)
+    }
+
+    /// Whether a fresh profile is already cached or being computed
+    pub fn is_current(&self, col: usize, generation: u64) -> bool {
+        self.get(col, generation).is_some() || self.is_pending(col, generation)
+    }
+
+    pub fn is_pending(&self, col: usize, generation: u64) -> bool {
+        self.pending.get(&col).is_some_and(|(g, _)| *g == generation)
+    }
+
+    /// Start profiling a column in the background unless a current result exists
+    pub fn request(
+        &mut self,
+        col: usize,
+        generation: u64,
+        compute: impl FnOnce() -> ColumnProfile + Send + 'static,
+    ) {
+        if self.is_current(col, generation) {
+            self.hits += 1;
+            return;
+        }
+        self.misses += 1;
+        let job = Job::spawn(format!("Profiling column {}", col + 1), move |_| compute());
+        self.pending.insert(col, (generation, job));
+    }
+
+    /// Collect finished jobs. Returns true while work is still outstanding.
+    pub fn poll(&mut self) -> bool {
+        let mut finished = Vec::new();
+        for (col, (generation, job)) in &self.pending {
+            if let Some(result) = job.poll() {
+                if let Ok(profile) = result {
+                    self.entries.insert(*col, (*generation, profile));
+                }
+                finished.push(*col);
+            }
+        }
+        for col in finished {
+            self.pending.remove(&col);
+        }
+        !self.pending.is_empty()
+    }
+
+    /// Forget a column's profile so the next request recomputes it
+    pub fn invalidate(&mut self, col: usize) {
+        self.entries.remove(&col);
+        self.pending.remove(&col);
+    }
+
+    pub fn pending_count(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Requests answered from the cache or an in-flight job, and those that started one
+    pub fn hit_stats(&self) -> (u64, u64) {
+        (self.hits, self.misses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wait(cache: &mut ProfileCache) {
+        while cache.poll() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_profiles_are_keyed_by_generation() {
+        let mut cache = ProfileCache::new(1);
+        cache.request(2, 7, || ColumnProfile { column_index: 2, total_count: 3, ..Default::default() });
+        wait(&mut cache);
+        cache.request(2, 7, ColumnProfile::default);
+        assert_eq!(cache.hit_stats(), (1, 1));
+
+        assert_eq!(cache.get(2, 7).map(|p| p.total_count), Some(3));
+        // An edit to the column bumps its generation, so the entry is stale
+        assert!(cache.get(2, 8).is_none());
+        assert!(!cache.is_current(2, 8));
+
+        cache.invalidate(2);
+        assert!(cache.get(2, 7).is_none());
+
+        cache.set_file_key(2);
+        assert!(cache.get(2, 7).is_none());
+    }
+}
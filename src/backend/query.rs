@@ -0,0 +1,374 @@
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+
+use super::jobs::Progress;
+use super::sort::{sort_indices, SortOrder, DEFAULT_RUN_BYTES};
+
+/// Rows returned by a query without a LIMIT
+pub const MAX_QUERY_ROWS: usize = 10_000;
+
+/// A small read-only subset of SQL:
+/// `SELECT * | col, ... [FROM name] [WHERE cond [AND cond ...]] [ORDER BY col [ASC | DESC]] [LIMIT n [OFFSET m]]`
+/// where `cond` is `col (= | != | <> | < | <= | > | >= | LIKE) literal`.
+/// Columns are matched to headers case-insensitively; quote them with `"…"`
+/// if they contain spaces. Values compare numerically when both sides are numbers.
+/// ORDER BY spills to disk when the matching rows are too many to sort in memory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    columns: Option<Vec<String>>,
+    conditions: Vec<Condition>,
+    order_by: Option<(String, SortOrder)>,
+    limit: Option<usize>,
+    offset: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Condition {
+    column: String,
+    op: Op,
+    value: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Like,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Str(String),
+    Symbol(String),
+}
+
+/// Result of running a query: the selected headers and matching rows
+pub struct QueryResult {
+    pub headers: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+fn tokenize(sql: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = sql.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '\'' || c == '"' {
+            chars.next();
+            let mut text = String::new();
+            loop {
+                match chars.next() {
+                    Some(q) if q == c => {
+                        // A doubled quote is an escaped quote
+                        if chars.peek() == Some(&c) {
+                            chars.next();
+                            text.push(c);
+                        } else {
+                            break;
+                        }
+                    }
+                    Some(ch) => text.push(ch),
+                    None => bail!("Unterminated quote"),
+                }
+            }
+            tokens.push(if c == '\'' { Token::Str(text) } else { Token::Quoted(text) });
+        } else if c.is_alphanumeric() || c == '_' || c == '.' || c == '-' {
+            let mut word = String::new();
+            while let Some(&ch) = chars.peek() {
+                if ch.is_alphanumeric() || ch == '_' || ch == '.' || (ch == '-' && word.is_empty()) {
+                    word.push(ch);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            tokens.push(Token::Word(word));
+        } else {
+            chars.next();
+            let mut symbol = c.to_string();
+            if matches!(c, '<' | '>' | '!') && let Some(&next) = chars.peek()
+                && (next == '=' || (c == '<' && next == '>'))
+            {
+                symbol.push(next);
+                chars.next();
+            }
+            tokens.push(Token::Symbol(symbol));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.tokens.get(self.pos), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword))
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let found = self.peek_keyword(keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn symbol(&mut self, symbol: &str) -> bool {
+        let found = matches!(self.tokens.get(self.pos), Some(Token::Symbol(s)) if s == symbol);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn identifier(&mut self) -> Result<String> {
+        match self.next() {
+            Some(Token::Word(w)) | Some(Token::Quoted(w)) => Ok(w),
+            other => bail!("Expected a column name, found {:?}", other),
+        }
+    }
+
+    fn number(&mut self) -> Result<usize> {
+        match self.next() {
+            Some(Token::Word(w)) => w.parse().with_context(|| format!("Expected a number, found {}", w)),
+            other => bail!("Expected a number, found {:?}", other),
+        }
+    }
+}
+
+impl Query {
+    pub fn parse(sql: &str) -> Result<Self> {
+        let mut p = Parser { tokens: tokenize(sql)?, pos: 0 };
+        if !p.keyword("select") {
+            bail!("Only SELECT queries are supported");
+        }
+
+        let columns = if p.symbol("*") {
+            None
+        } else {
+            let mut cols = vec![p.identifier()?];
+            while p.symbol(",") {
+                cols.push(p.identifier()?);
+            }
+            Some(cols)
+        };
+
+        if p.keyword("from") {
+            // There is only one table; its name is not checked
+            p.identifier()?;
+        }
+
+        let mut conditions = Vec::new();
+        if p.keyword("where") {
+            loop {
+                let column = p.identifier()?;
+                let op = if p.keyword("like") {
+                    Op::Like
+                } else {
+                    match p.next() {
+                        Some(Token::Symbol(s)) => match s.as_str() {
+                            "=" => Op::Eq,
+                            "!=" | "<>" => Op::Ne,
+                            "<" => Op::Lt,
+                            "<=" => Op::Le,
+                            ">" => Op::Gt,
+                            ">=" => Op::Ge,
+                            other => bail!("Unknown operator {}", other),
+                        },
+                        other => bail!("Expected an operator, found {:?}", other),
+                    }
+                };
+                let value = match p.next() {
+                    Some(Token::Str(v)) | Some(Token::Word(v)) => v,
+                    other => bail!("Expected a value, found {:?}", other),
+                };
+                conditions.push(Condition { column, op, value });
+                if !p.keyword("and") {
+                    break;
+                }
+            }
+        }
+
+        let mut order_by = None;
+        if p.keyword("order") {
+            if !p.keyword("by") {
+                bail!("Expected BY after ORDER");
+            }
+            let column = p.identifier()?;
+            let order = if p.keyword("desc") {
+                SortOrder::Descending
+            } else {
+                p.keyword("asc");
+                SortOrder::Ascending
+            };
+            order_by = Some((column, order));
+        }
+
+        let mut limit = None;
+        let mut offset = 0;
+        if p.keyword("limit") {
+            limit = Some(p.number()?);
+            if p.keyword("offset") {
+                offset = p.number()?;
+            }
+        }
+        p.symbol(";");
+        if let Some(token) = p.tokens.get(p.pos) {
+            bail!("Unexpected {:?}", token);
+        }
+        Ok(Self { columns, conditions, order_by, limit, offset })
+    }
+
+    /// The WHERE clause as a test on a row's values, with columns resolved against `headers`
+    pub fn matcher(&self, headers: &[String]) -> Result<impl Fn(&[String]) -> bool + use<>> {
+        let conditions: Vec<(usize, Op, String, Option<Regex>)> = self.conditions
+            .iter()
+            .map(|c| {
+                let col = headers
+                    .iter()
+                    .position(|h| h.trim().eq_ignore_ascii_case(&c.column))
+                    .with_context(|| format!("Unknown column \"{}\"", c.column))?;
+                let pattern = (c.op == Op::Like).then(|| like_to_regex(&c.value)).transpose()?;
+                Ok((col, c.op, c.value.clone(), pattern))
+            })
+            .collect::<Result<_>>()?;
+        Ok(move |values: &[String]| {
+            conditions.iter().all(|(col, op, value, pattern)| {
+                let cell = values.get(*col).map(String::as_str).unwrap_or("");
+                match pattern {
+                    Some(re) => re.is_match(cell),
+                    None => compare(cell, *op, value),
+                }
+            })
+        })
+    }
+
+    /// Run against `total` rows. Stops after LIMIT (or `MAX_QUERY_ROWS`) matches,
+    /// unless ORDER BY needs every match first.
+    pub fn run(&self, headers: &[String], total: usize, row_at: impl Fn(usize) -> Vec<String>) -> Result<QueryResult> {
+        let find = |name: &str| {
+            headers
+                .iter()
+                .position(|h| h.trim().eq_ignore_ascii_case(name))
+                .with_context(|| format!("Unknown column \"{}\"", name))
+        };
+        let selected: Vec<usize> = match self.columns {
+            Some(ref cols) => cols.iter().map(|c| find(c)).collect::<Result<_>>()?,
+            None => (0..headers.len()).collect(),
+        };
+        let matches = self.matcher(headers)?;
+        let order_by = self.order_by.as_ref().map(|(name, order)| Ok::<_, anyhow::Error>((find(name)?, *order))).transpose()?;
+        let project = |values: Vec<String>| selected.iter().map(|&c| values.get(c).cloned().unwrap_or_default()).collect();
+
+        let limit = self.limit.unwrap_or(MAX_QUERY_ROWS).min(MAX_QUERY_ROWS);
+        let mut rows = Vec::new();
+        if let Some((col, order)) = order_by {
+            let keys = (0..total).filter_map(|r| {
+                let values = row_at(r);
+                matches(&values).then(|| (r, values.get(col).cloned().unwrap_or_default()))
+            });
+            let sorted = sort_indices(keys, order, DEFAULT_RUN_BYTES, &std::env::temp_dir(), &Progress::default())?;
+            for r in sorted.skip(self.offset).take(limit) {
+                rows.push(project(row_at(r?)));
+            }
+        } else {
+            let mut skipped = 0;
+            for r in 0..total {
+                if rows.len() >= limit {
+                    break;
+                }
+                let values = row_at(r);
+                if !matches(&values) {
+                    continue;
+                }
+                if skipped < self.offset {
+                    skipped += 1;
+                    continue;
+                }
+                rows.push(project(values));
+            }
+        }
+        Ok(QueryResult {
+            headers: selected.iter().map(|&c| headers[c].clone()).collect(),
+            rows,
+        })
+    }
+}
+
+fn compare(cell: &str, op: Op, value: &str) -> bool {
+    let ordering = match (cell.trim().parse::<f64>(), value.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.partial_cmp(&b),
+        _ => Some(cell.cmp(value)),
+    };
+    let Some(ordering) = ordering else {
+        return false;
+    };
+    match op {
+        Op::Eq => ordering.is_eq(),
+        Op::Ne => ordering.is_ne(),
+        Op::Lt => ordering.is_lt(),
+        Op::Le => ordering.is_le(),
+        Op::Gt => ordering.is_gt(),
+        Op::Ge => ordering.is_ge(),
+        Op::Like => false,
+    }
+}
+
+/// Translate a LIKE pattern (`%` any run, `_` one character) to an anchored regex
+fn like_to_regex(pattern: &str) -> Result<Regex> {
+    let mut re = String::from("(?i)^");
+    for c in pattern.chars() {
+        match c {
+            '%' => re.push_str(".*"),
+            '_' => re.push('.'),
+            other => re.push_str(&regex::escape(&other.to_string())),
+        }
+    }
+    re.push('$');
+    Regex::new(&re).context("Invalid LIKE pattern")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_where_limit() -> Result<()> {
+        let headers: Vec<String> = ["id", "name", "Unit Price"].iter().map(|s| s.to_string()).collect();
+        let data = [["1", "apple", "10"], ["2", "banana", "9.5"], ["3", "avocado", "30"], ["4", "apricot", "5"]];
+        let row_at = |r: usize| data[r].iter().map(|v| v.to_string()).collect();
+
+        let query = Query::parse(r#"SELECT name, "Unit Price" FROM t WHERE name LIKE 'a%' AND "unit price" >= 9 LIMIT 5"#)?;
+        let result = query.run(&headers, data.len(), row_at)?;
+        assert_eq!(result.headers, vec!["name", "Unit Price"]);
+        assert_eq!(result.rows, vec![vec!["apple", "10"], vec!["avocado", "30"]]);
+
+        let result = Query::parse("select id from data where name <> 'apple' limit 1 offset 1")?.run(&headers, data.len(), row_at)?;
+        assert_eq!(result.rows, vec![vec!["3"]]);
+
+        let result = Query::parse(r#"SELECT name FROM t WHERE id > 1 ORDER BY "unit price" DESC LIMIT 2"#)?.run(&headers, data.len(), row_at)?;
+        assert_eq!(result.rows, vec![vec!["avocado"], vec!["banana"]]);
+        let result = Query::parse("SELECT id ORDER BY name LIMIT 1 OFFSET 1")?.run(&headers, data.len(), row_at)?;
+        assert_eq!(result.rows, vec![vec!["4"]]);
+
+        assert!(Query::parse("DELETE FROM t").is_err());
+        assert!(Query::parse("SELECT missing FROM t")?.run(&headers, data.len(), row_at).is_err());
+        Ok(())
+    }
+}
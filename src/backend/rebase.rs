@@ -0,0 +1,195 @@
+use std::collections::{HashMap, HashSet};
+
+use super::editor::CellEdit;
+use super::jobs::Progress;
+
+/// How edited rows are found again in a file that was rewritten on disk
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RowMatch {
+    /// The row whose every value equals the row as it was before editing
+    #[default]
+    Content,
+    /// The row with the same value in this column
+    Key(usize),
+}
+
+impl RowMatch {
+    /// What two rows must share to count as the same row
+    pub fn key_of(self, values: &[String]) -> Option<String> {
+        match self {
+            RowMatch::Content => serde_json::to_string(values).ok(),
+            RowMatch::Key(col) => values.get(col).cloned(),
+        }
+    }
+}
+
+/// An unsaved edit that couldn't be carried over to the new file contents
+#[derive(Clone, Debug, PartialEq)]
+pub struct EditConflict {
+    /// Row the edit was made in, before the reload
+    pub row: usize,
+    /// Where that row is now, if it was found
+    pub new_row: Option<usize>,
+    pub col: usize,
+    /// Value before editing, if the row was recorded
+    pub original: Option<String>,
+    /// Value in the file now, if the row was found
+    pub disk: Option<String>,
+    pub mine: String,
+    pub reason: &'static str,
+}
+
+/// Edits moved onto the new rows, and those that didn't fit
+#[derive(Debug, Default)]
+pub struct Rebase {
+    /// (new row, col, value on disk, my value)
+    pub edits: Vec<CellEdit>,
+    pub conflicts: Vec<EditConflict>,
+}
+
+/// Re-apply `edits` (row, col, value) to the file as it is now. `bases` holds
+/// each edited row as it was before editing, and `total`/`row_at` read the
+/// new file. An edit carries over when its row is found and the cell wasn't
+/// changed on disk; otherwise it becomes a conflict. Row 0 is the header and
+/// always matches row 0. Cancelling stops the search, and the edits whose
+/// rows weren't found yet become conflicts rather than being lost.
+pub fn rebase_edits(
+    edits: impl IntoIterator<Item = (usize, usize, String)>,
+    bases: &HashMap<usize, Vec<String>>,
+    matching: RowMatch,
+    total: usize,
+    row_at: impl Fn(usize) -> Vec<String>,
+    progress: &Progress,
+) -> Rebase {
+    let mut by_row: Vec<(usize, Vec<(usize, String)>)> = Vec::new();
+    for (row, col, value) in edits {
+        match by_row.last_mut() {
+            Some((r, cells)) if *r == row => cells.push((col, value)),
+            _ => by_row.push((row, vec![(col, value)])),
+        }
+    }
+
+    let key_of = |values: &[String]| matching.key_of(values);
+    // Only rows that could match an edited row are remembered while scanning
+    let wanted: HashSet<String> = by_row
+        .iter()
+        .filter(|(row, _)| *row > 0)
+        .filter_map(|(row, _)| bases.get(row).and_then(|base| key_of(base)))
+        .collect();
+    let mut candidates: HashMap<String, Vec<usize>> = HashMap::new();
+    let mut cancelled = false;
+    if !wanted.is_empty() {
+        progress.set_total(total);
+        for r in 1..total {
+            if r % 10_000 == 0 {
+                if progress.is_cancelled() {
+                    cancelled = true;
+                    break;
+                }
+                progress.set_done(r);
+            }
+            if let Some(key) = key_of(&row_at(r)).filter(|key| wanted.contains(key)) {
+                candidates.entry(key).or_default().push(r);
+            }
+        }
+    }
+
+    let mut rebase = Rebase::default();
+    let mut taken = HashSet::new();
+    for (row, cells) in by_row {
+        let base = bases.get(&row);
+        // Of several matches, the one nearest its old position
+        let new_row = if row == 0 {
+            (total > 0).then_some(0)
+        } else if cancelled {
+            None
+        } else {
+            base.and_then(|base| key_of(base))
+                .and_then(|key| candidates.get(&key))
+                .and_then(|rows| rows.iter().filter(|r| !taken.contains(*r)).min_by_key(|r| r.abs_diff(row)).copied())
+        };
+        let reason = match (base, new_row) {
+            (None, _) if row > 0 => Some("Original row wasn't recorded"),
+            (_, None) if cancelled => Some("Cancelled before the row was found"),
+            (_, None) => Some("Row is no longer in the file"),
+            _ => None,
+        };
+        let current = new_row.map(&row_at).unwrap_or_default();
+        if let Some(r) = new_row {
+            taken.insert(r);
+        }
+        for (col, mine) in cells {
+            let original = base.and_then(|base| base.get(col)).cloned();
+            let disk = new_row.map(|_| current.get(col).cloned().unwrap_or_default());
+            let reason = reason.or_else(|| (disk != original && disk.as_ref() != Some(&mine)).then_some("Changed on disk"));
+            match (reason, new_row) {
+                (None, Some(r)) => {
+                    let disk = disk.unwrap_or_default();
+                    if disk != mine {
+                        rebase.edits.push((r, col, disk, mine));
+                    }
+                }
+                (reason, _) => rebase.conflicts.push(EditConflict {
+                    row,
+                    new_row,
+                    col,
+                    original,
+                    disk,
+                    mine,
+                    reason: reason.unwrap_or("Row is no longer in the file"),
+                }),
+            }
+        }
+    }
+    rebase
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(text: &str) -> Vec<Vec<String>> {
+        text.lines().map(|line| line.split(',').map(String::from).collect()).collect()
+    }
+
+    #[test]
+    fn test_rebase_follows_moved_rows_and_reports_conflicts() {
+        let old = rows("id,name,qty\n1,ann,5\n2,bob,7\n3,cy,9");
+        // Rows reordered and one removed; bob's qty changed on disk
+        let new = rows("id,name,qty\n0,zed,1\n2,bob,8\n1,ann,5");
+        let bases: HashMap<usize, Vec<String>> = [1, 2, 3].into_iter().map(|r| (r, old[r].clone())).collect();
+        let edits = || {
+            vec![
+                (1, 1, "Ann".to_string()),
+                (2, 1, "Bob".to_string()),
+                (2, 2, "70".to_string()),
+                (3, 2, "10".to_string()),
+            ]
+        };
+
+        // By content, bob's row no longer matches at all
+        let by_content = rebase_edits(edits(), &bases, RowMatch::Content, new.len(), |r| new[r].clone(), &Progress::default());
+        assert_eq!(by_content.edits, [(3, 1, "ann".to_string(), "Ann".to_string())]);
+        assert_eq!(by_content.conflicts.len(), 3);
+        assert!(by_content.conflicts.iter().all(|c| c.reason == "Row is no longer in the file"));
+
+        // By id, bob's name carries over and only the qty he changed on disk conflicts
+        let by_key = rebase_edits(edits(), &bases, RowMatch::Key(0), new.len(), |r| new[r].clone(), &Progress::default());
+        assert_eq!(
+            by_key.edits,
+            [(3, 1, "ann".to_string(), "Ann".to_string()), (2, 1, "bob".to_string(), "Bob".to_string())]
+        );
+        let reasons: Vec<_> = by_key.conflicts.iter().map(|c| (c.row, c.new_row, c.reason)).collect();
+        assert_eq!(reasons, [(2, Some(2), "Changed on disk"), (3, None, "Row is no longer in the file")]);
+        assert_eq!(by_key.conflicts[0].disk.as_deref(), Some("8"));
+
+        // Cancelled partway through a long file, every edit waits as a conflict
+        let long = |r: usize| if r == 0 { new[0].clone() } else { vec![r.to_string(), "x".to_string(), "0".to_string()] };
+        let progress = Progress::default();
+        progress.cancel();
+        let cancelled = rebase_edits(edits(), &bases, RowMatch::Key(0), 50_000, long, &progress);
+        assert!(cancelled.edits.is_empty());
+        assert_eq!(cancelled.conflicts.len(), 4);
+        assert!(cancelled.conflicts.iter().all(|c| c.reason == "Cancelled before the row was found"));
+    }
+}
@@ -0,0 +1,350 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+use super::analysis::{ColumnAnalyzer, ColumnProfile, InferredType};
+use super::jobs::Progress;
+use super::scripting::RowExpression;
+
+/// Stop collecting issues past this many; the rest is summarized
+const MAX_ISSUES: usize = 1000;
+
+/// A Frictionless Table Schema (https://specs.frictionlessdata.io/table-schema/).
+/// Only the parts CSVit checks are modelled; unknown keys are ignored.
+/// `rowRules` is a CSVit extension for checks that span several columns.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TableSchema {
+    pub fields: Vec<SchemaField>,
+    #[serde(rename = "primaryKey", default, skip_serializing_if = "Option::is_none")]
+    pub primary_key: Option<Vec<String>>,
+    #[serde(rename = "rowRules", default, skip_serializing_if = "Vec::is_empty")]
+    pub row_rules: Vec<RowRule>,
+}
+
+/// A row-level assertion, e.g. `end_date >= start_date`; see `RowExpression`
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RowRule {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    pub expression: String,
+}
+
+impl RowRule {
+    /// The name, or the expression itself when unnamed
+    pub fn label(&self) -> &str {
+        if self.name.trim().is_empty() { &self.expression } else { &self.name }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SchemaField {
+    pub name: String,
+    #[serde(rename = "type", default = "default_field_type")]
+    pub field_type: String,
+    #[serde(default, skip_serializing_if = "Constraints::is_empty")]
+    pub constraints: Constraints,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Constraints {
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub required: bool,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub unique: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minimum: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maximum: Option<f64>,
+    #[serde(rename = "minLength", default, skip_serializing_if = "Option::is_none")]
+    pub min_length: Option<usize>,
+    #[serde(rename = "maxLength", default, skip_serializing_if = "Option::is_none")]
+    pub max_length: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    #[serde(rename = "enum", default, skip_serializing_if = "Option::is_none")]
+    pub allowed: Option<Vec<String>>,
+}
+
+impl Constraints {
+    fn is_empty(&self) -> bool {
+        !self.required
+            && !self.unique
+            && self.minimum.is_none()
+            && self.maximum.is_none()
+            && self.min_length.is_none()
+            && self.max_length.is_none()
+            && self.pattern.is_none()
+            && self.allowed.is_none()
+    }
+}
+
+fn default_field_type() -> String {
+    "any".to_string()
+}
+
+/// One problem found while validating. `row` is None for header problems.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SchemaIssue {
+    pub row: Option<usize>,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl TableSchema {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).context("Failed to read schema file")?;
+        serde_json::from_str(&text).context("Invalid table schema JSON")
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json).context("Failed to write schema file")
+    }
+
+    /// Build a schema from inferred column profiles
+    pub fn from_profiles(profiles: &[ColumnProfile]) -> Self {
+        let fields = profiles
+            .iter()
+            .map(|p| {
+                let field_type = match p.data_type {
+                    Some(InferredType::Integer) => "integer",
+                    Some(InferredType::Float) => "number",
+                    Some(InferredType::Boolean) => "boolean",
+                    Some(InferredType::Date) => "date",
+                    Some(InferredType::Text) => "string",
+                    _ => "any",
+                };
+                let numeric = matches!(field_type, "integer" | "number");
+                SchemaField {
+                    name: p.header.clone(),
+                    field_type: field_type.to_string(),
+                    constraints: Constraints {
+                        required: p.total_count > 0 && p.null_count == 0,
+                        unique: p.total_count > 0 && p.null_count == 0 && p.unique_count == p.total_count,
+                        minimum: p.min.filter(|_| numeric),
+                        maximum: p.max.filter(|_| numeric),
+                        ..Default::default()
+                    },
+                }
+            })
+            .collect();
+        Self { fields, primary_key: None, row_rules: Vec::new() }
+    }
+
+    /// Check the header row and `total` data rows. Fields are matched to
+    /// columns by position; names are compared against `headers`.
+    pub fn validate(
+        &self,
+        headers: &[String],
+        total: usize,
+        row_at: impl Fn(usize) -> Vec<String>,
+        progress: &Progress,
+    ) -> Result<Vec<SchemaIssue>> {
+        let mut issues = Vec::new();
+        for (col, field) in self.fields.iter().enumerate() {
+            match headers.get(col) {
+                Some(name) if name.trim() == field.name => {}
+                Some(name) => issues.push(SchemaIssue {
+                    row: None,
+                    column: Some(col),
+                    message: format!("Header is \"{}\", schema expects \"{}\"", name, field.name),
+                }),
+                None => issues.push(SchemaIssue {
+                    row: None,
+                    column: None,
+                    message: format!("Missing column \"{}\"", field.name),
+                }),
+            }
+        }
+        // A schema of only row rules says nothing about the columns
+        if !self.fields.is_empty() && headers.len() > self.fields.len() {
+            issues.push(SchemaIssue {
+                row: None,
+                column: Some(self.fields.len()),
+                message: format!("{} column(s) not described by the schema", headers.len() - self.fields.len()),
+            });
+        }
+
+        let patterns: Vec<Option<Regex>> = self.fields
+            .iter()
+            .map(|f| f.constraints.pattern.as_deref().map(|p| Regex::new(&format!("^(?:{})$", p))).transpose())
+            .collect::<Result<_, _>>()
+            .context("Invalid pattern in schema")?;
+        let rules: Vec<(&RowRule, RowExpression)> = self.row_rules
+            .iter()
+            .map(|rule| {
+                let expression = RowExpression::compile(&rule.expression, headers)
+                    .with_context(|| format!("Invalid row rule \"{}\"", rule.label()))?;
+                Ok((rule, expression))
+            })
+            .collect::<Result<_>>()?;
+        let mut seen: Vec<Option<HashSet<String>>> = self.fields
+            .iter()
+            .map(|f| f.constraints.unique.then(HashSet::new))
+            .collect();
+
+        progress.set_total(total);
+        let mut truncated = 0;
+        for row in 0..total {
+            if row % 10_000 == 0 {
+                if progress.is_cancelled() {
+                    anyhow::bail!("Validation cancelled");
+                }
+                progress.set_done(row);
+            }
+            let values = row_at(row);
+            for (col, field) in self.fields.iter().enumerate() {
+                let value = values.get(col).map(|v| v.trim()).unwrap_or("");
+                if let Some(message) = field.check(value, patterns[col].as_ref(), seen[col].as_mut()) {
+                    if issues.len() < MAX_ISSUES {
+                        issues.push(SchemaIssue { row: Some(row), column: Some(col), message });
+                    } else {
+                        truncated += 1;
+                    }
+                }
+            }
+            for (rule, expression) in &rules {
+                let message = match expression.eval_bool(&values) {
+                    Ok(true) => continue,
+                    Ok(false) => format!("Breaks rule: {}", rule.label()),
+                    Err(e) => format!("Rule \"{}\" could not be checked: {}", rule.label(), e),
+                };
+                if issues.len() < MAX_ISSUES {
+                    issues.push(SchemaIssue { row: Some(row), column: None, message });
+                } else {
+                    truncated += 1;
+                }
+            }
+        }
+        if truncated > 0 {
+            issues.push(SchemaIssue {
+                row: None,
+                column: None,
+                message: format!("… and {} more issue(s)", truncated),
+            });
+        }
+        Ok(issues)
+    }
+}
+
+impl SchemaField {
+    /// Check a single value against the field's type and constraints.
+    /// Uniqueness needs the whole column, so it isn't checked here.
+    pub fn check_value(&self, value: &str) -> Option<String> {
+        let pattern = match self.constraints.pattern.as_deref().map(|p| Regex::new(&format!("^(?:{})$", p))) {
+            Some(Ok(re)) => Some(re),
+            Some(Err(_)) => return Some("Invalid pattern in schema".to_string()),
+            None => None,
+        };
+        self.check(value.trim(), pattern.as_ref(), None)
+    }
+
+    /// The first constraint a value breaks, if any
+    fn check(&self, value: &str, pattern: Option<&Regex>, seen: Option<&mut HashSet<String>>) -> Option<String> {
+        let c = &self.constraints;
+        if value.is_empty() {
+            return c.required.then(|| "Required value is missing".to_string());
+        }
+
+        let type_ok = match self.field_type.as_str() {
+            "integer" => value.parse::<i64>().is_ok(),
+            "number" => value.parse::<f64>().is_ok(),
+            "boolean" => ColumnAnalyzer::infer_value_type(value) == InferredType::Boolean,
+            "date" => ColumnAnalyzer::infer_value_type(value) == InferredType::Date,
+            _ => true,
+        };
+        if !type_ok {
+            return Some(format!("\"{}\" is not a valid {}", value, self.field_type));
+        }
+
+        if let Ok(n) = value.parse::<f64>() {
+            if let Some(min) = c.minimum
+                && n < min
+            {
+                return Some(format!("{} is below the minimum {}", value, min));
+            }
+            if let Some(max) = c.maximum
+                && n > max
+            {
+                return Some(format!("{} is above the maximum {}", value, max));
+            }
+        }
+        let len = value.chars().count();
+        if c.min_length.is_some_and(|min| len < min) || c.max_length.is_some_and(|max| len > max) {
+            return Some(format!("Length {} is out of range", len));
+        }
+        if let Some(re) = pattern
+            && !re.is_match(value)
+        {
+            return Some(format!("\"{}\" does not match the pattern", value));
+        }
+        if let Some(ref allowed) = c.allowed
+            && !allowed.iter().any(|a| a == value)
+        {
+            return Some(format!("\"{}\" is not an allowed value", value));
+        }
+        if let Some(seen) = seen
+            && !seen.insert(value.to_string())
+        {
+            return Some(format!("\"{}\" is not unique", value));
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_types_and_constraints() -> Result<()> {
+        let schema: TableSchema = serde_json::from_str(r#"{
+            "fields": [
+                {"name": "id", "type": "integer", "constraints": {"required": true, "unique": true}},
+                {"name": "code", "type": "string", "constraints": {"pattern": "[A-Z]{2}"}}
+            ]
+        }"#)?;
+        let rows = [["1", "AB"], ["x", "AB"], ["1", "abc"]];
+        let headers = vec!["id".to_string(), "kode".to_string()];
+        let row_at = |r: usize| rows[r].iter().map(|v| v.to_string()).collect();
+        let issues = schema.validate(&headers, rows.len(), row_at, &Progress::default())?;
+
+        let located: Vec<(Option<usize>, Option<usize>)> = issues.iter().map(|i| (i.row, i.column)).collect();
+        assert_eq!(located, vec![(None, Some(1)), (Some(1), Some(0)), (Some(2), Some(0)), (Some(2), Some(1))]);
+
+        assert!(schema.fields[1].check_value("CD").is_none());
+        assert!(schema.fields[1].check_value("cd").is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_row_rules() -> Result<()> {
+        let schema: TableSchema = serde_json::from_str(r#"{
+            "fields": [],
+            "rowRules": [{"name": "Ends after start", "expression": "end >= start"}, {"expression": "total == price * qty"}]
+        }"#)?;
+        let headers = ["start", "end", "price", "qty", "total"].map(String::from);
+        let rows = [["2024-01-01", "2024-01-02", "2", "3", "6"], ["2024-02-01", "2024-01-02", "2", "3", "7"]];
+        let row_at = |r: usize| rows[r].iter().map(|v| v.to_string()).collect();
+        let issues = schema.validate(&headers, rows.len(), row_at, &Progress::default())?;
+
+        let messages: Vec<&str> = issues.iter().map(|i| i.message.as_str()).collect();
+        assert_eq!(messages, ["Breaks rule: Ends after start", "Breaks rule: total == price * qty"]);
+        assert!(issues.iter().all(|i| i.row == Some(1) && i.column.is_none()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_generate_from_profiles() {
+        let values: Vec<String> = vec!["1", "2", "3"].into_iter().map(String::from).collect();
+        let profile = ColumnAnalyzer::analyze_column("id", 0, &values);
+        let schema = TableSchema::from_profiles(&[profile]);
+
+        let json = serde_json::to_value(&schema).unwrap();
+        assert_eq!(json["fields"][0]["type"], "integer");
+        assert_eq!(json["fields"][0]["constraints"]["unique"], true);
+        assert_eq!(json["fields"][0]["constraints"]["maximum"], 3.0);
+    }
+}
@@ -0,0 +1,365 @@
+use anyhow::Result;
+use rhai::{Array, Dynamic, Engine, EvalAltResult, FnPtr, Map, NativeCallContext, Scope, AST};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+/// Stop runaway scripts (e.g. an accidental infinite loop) after this many operations
+const MAX_SCRIPT_OPERATIONS: u64 = 500_000_000;
+/// A row expression is evaluated for every row, so it gets a much smaller budget
+const MAX_EXPRESSION_OPERATIONS: u64 = 100_000;
+
+/// Reads all values of a row from a file-backed document
+pub type RowSource = Box<dyn Fn(usize) -> Vec<String> + Send>;
+
+/// The document a script runs against
+pub enum ScriptInput {
+    /// An in-memory grid: every operation is available
+    Table { headers: Vec<String>, rows: Vec<Vec<String>> },
+    /// A file-backed document: cells can be read and set, but not restructured
+    File { total: usize, num_cols: usize, row_at: RowSource },
+}
+
+/// What a finished script changed, ready to apply as one undoable step
+pub enum ScriptChanges {
+    None,
+    /// The whole table after the script ran (in-memory grids)
+    Table { headers: Vec<String>, rows: Vec<Vec<String>> },
+    /// Cells that were set: (row, col, old_value, new_value)
+    Cells(Vec<(usize, usize, String, String)>),
+}
+
+pub struct ScriptOutcome {
+    pub changes: ScriptChanges,
+    /// Lines written with `print`
+    pub output: Vec<String>,
+}
+
+/// Short reference shown in the script console
+pub const SCRIPT_HELP: &str = "rows(), cols(), get(r, c), set(r, c, v), header(c)\n\
+    grid only: set_header(c, name), add_column(name), delete_row(r), filter(|row| bool)\n\
+    Indices start at 0. print(x) writes to the output.";
+
+struct ScriptDoc {
+    input: ScriptInput,
+    /// Cells set on a file-backed document, with the value they replaced
+    cell_edits: BTreeMap<(usize, usize), (String, String)>,
+    modified: bool,
+}
+
+type ScriptResult<T> = Result<T, Box<EvalAltResult>>;
+
+impl ScriptDoc {
+    fn rows(&self) -> usize {
+        match self.input {
+            ScriptInput::Table { ref rows, .. } => rows.len(),
+            ScriptInput::File { total, .. } => total,
+        }
+    }
+
+    fn cols(&self) -> usize {
+        match self.input {
+            ScriptInput::Table { ref headers, .. } => headers.len(),
+            ScriptInput::File { num_cols, .. } => num_cols,
+        }
+    }
+
+    fn index(&self, r: i64, c: i64) -> ScriptResult<(usize, usize)> {
+        let (rows, cols) = (self.rows() as i64, self.cols() as i64);
+        if r < 0 || r >= rows || c < 0 || c >= cols {
+            return Err(format!("Cell ({}, {}) is outside the table ({} × {})", r, c, rows, cols).into());
+        }
+        Ok((r as usize, c as usize))
+    }
+
+    fn get(&self, r: usize, c: usize) -> String {
+        match self.input {
+            ScriptInput::Table { ref rows, .. } => rows[r].get(c).cloned().unwrap_or_default(),
+            ScriptInput::File { ref row_at, .. } => match self.cell_edits.get(&(r, c)) {
+                Some((_, new)) => new.clone(),
+                None => row_at(r).get(c).cloned().unwrap_or_default(),
+            },
+        }
+    }
+
+    fn row(&self, r: usize) -> Vec<String> {
+        (0..self.cols()).map(|c| self.get(r, c)).collect()
+    }
+
+    fn set(&mut self, r: usize, c: usize, value: String) {
+        self.modified = true;
+        let old = self.get(r, c);
+        match self.input {
+            ScriptInput::Table { ref mut rows, .. } => {
+                let row = &mut rows[r];
+                if row.len() <= c {
+                    row.resize(c + 1, String::new());
+                }
+                row[c] = value;
+            }
+            ScriptInput::File { .. } => {
+                let original = self.cell_edits.get(&(r, c)).map(|(o, _)| o.clone()).unwrap_or(old);
+                self.cell_edits.insert((r, c), (original, value));
+            }
+        }
+    }
+
+    fn table_mut(&mut self, op: &str) -> ScriptResult<(&mut Vec<String>, &mut Vec<Vec<String>>)> {
+        match self.input {
+            ScriptInput::Table { ref mut headers, ref mut rows } => {
+                self.modified = true;
+                Ok((headers, rows))
+            }
+            ScriptInput::File { .. } => Err(format!("{} requires an in-memory grid", op).into()),
+        }
+    }
+
+    fn into_changes(self) -> ScriptChanges {
+        if !self.modified {
+            return ScriptChanges::None;
+        }
+        match self.input {
+            ScriptInput::Table { headers, rows } => ScriptChanges::Table { headers, rows },
+            ScriptInput::File { .. } => ScriptChanges::Cells(
+                self.cell_edits
+                    .into_iter()
+                    .filter(|(_, (old, new))| old != new)
+                    .map(|((r, c), (old, new))| (r, c, old, new))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Run a Rhai script against a document. Nothing is applied to the
+/// document itself; the caller applies the returned changes.
+pub fn run_script(script: &str, input: ScriptInput) -> Result<ScriptOutcome> {
+    let doc = Arc::new(Mutex::new(ScriptDoc { input, cell_edits: BTreeMap::new(), modified: false }));
+    let output = Arc::new(Mutex::new(Vec::new()));
+    let mut engine = Engine::new();
+
+    let sink = output.clone();
+    engine.on_print(move |s| sink.lock().unwrap().push(s.to_string()));
+    engine.set_max_operations(MAX_SCRIPT_OPERATIONS);
+
+    let d = doc.clone();
+    engine.register_fn("rows", move || d.lock().unwrap().rows() as i64);
+    let d = doc.clone();
+    engine.register_fn("cols", move || d.lock().unwrap().cols() as i64);
+    let d = doc.clone();
+    engine.register_fn("get", move |r: i64, c: i64| -> ScriptResult<String> {
+        let doc = d.lock().unwrap();
+        let (r, c) = doc.index(r, c)?;
+        Ok(doc.get(r, c))
+    });
+    let d = doc.clone();
+    engine.register_fn("set", move |r: i64, c: i64, value: Dynamic| -> ScriptResult<()> {
+        let mut doc = d.lock().unwrap();
+        let (r, c) = doc.index(r, c)?;
+        doc.set(r, c, value.to_string());
+        Ok(())
+    });
+    let d = doc.clone();
+    engine.register_fn("header", move |c: i64| -> ScriptResult<String> {
+        let doc = d.lock().unwrap();
+        match doc.input {
+            ScriptInput::Table { ref headers, .. } => headers
+                .get(c as usize)
+                .cloned()
+                .ok_or_else(|| format!("Column {} is outside the table", c).into()),
+            ScriptInput::File { .. } => Ok(format!("Column {}", c + 1)),
+        }
+    });
+    let d = doc.clone();
+    engine.register_fn("set_header", move |c: i64, name: &str| -> ScriptResult<()> {
+        let mut doc = d.lock().unwrap();
+        let (headers, _) = doc.table_mut("set_header")?;
+        let header = headers.get_mut(c as usize).ok_or_else(|| format!("Column {} is outside the table", c))?;
+        *header = name.to_string();
+        Ok(())
+    });
+    let d = doc.clone();
+    engine.register_fn("add_column", move |name: &str| -> ScriptResult<i64> {
+        let mut doc = d.lock().unwrap();
+        let (headers, rows) = doc.table_mut("add_column")?;
+        headers.push(name.to_string());
+        let width = headers.len();
+        for row in rows.iter_mut() {
+            row.resize(width, String::new());
+        }
+        Ok(width as i64 - 1)
+    });
+    let d = doc.clone();
+    engine.register_fn("delete_row", move |r: i64| -> ScriptResult<()> {
+        let mut doc = d.lock().unwrap();
+        let (r, _) = doc.index(r, 0)?;
+        let (_, rows) = doc.table_mut("delete_row")?;
+        rows.remove(r);
+        Ok(())
+    });
+    let d = doc.clone();
+    engine.register_fn("filter", move |ctx: NativeCallContext, keep: FnPtr| -> ScriptResult<()> {
+        // Evaluate the predicate without holding the lock, since it may call get()
+        let snapshot: Vec<Array> = {
+            let doc = d.lock().unwrap();
+            if matches!(doc.input, ScriptInput::File { .. }) {
+                return Err("filter requires an in-memory grid".into());
+            }
+            (0..doc.rows())
+                .map(|r| doc.row(r).into_iter().map(Dynamic::from).collect())
+                .collect()
+        };
+        let mut flags = Vec::with_capacity(snapshot.len());
+        for row in snapshot {
+            flags.push(keep.call_within_context::<bool>(&ctx, (row,))?);
+        }
+        let mut doc = d.lock().unwrap();
+        let (_, rows) = doc.table_mut("filter")?;
+        let mut flags = flags.into_iter();
+        rows.retain(|_| flags.next().unwrap_or(true));
+        Ok(())
+    });
+
+    let result = engine.run(script);
+    drop(engine);
+    result.map_err(|e| anyhow::anyhow!("{}", e))?;
+
+    let doc = Arc::try_unwrap(doc)
+        .map_err(|_| anyhow::anyhow!("Script state is still in use"))?
+        .into_inner()
+        .unwrap();
+    let output = std::mem::take(&mut *output.lock().unwrap());
+    Ok(ScriptOutcome { changes: doc.into_changes(), output })
+}
+
+/// A Rhai expression evaluated against one row at a time, e.g.
+/// `end_date >= start_date` or `total == price * qty`. Each column is a
+/// variable named after its header (other characters become `_`), and
+/// `row["Header"]` reaches any column by its exact name. Numbers are numbers,
+/// empty cells are `()` and everything else is a string.
+pub struct RowExpression {
+    engine: Engine,
+    ast: AST,
+    names: Vec<String>,
+    headers: Vec<String>,
+}
+
+impl RowExpression {
+    pub fn compile(expression: &str, headers: &[String]) -> Result<Self> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_EXPRESSION_OPERATIONS);
+        let ast = engine.compile_expression(expression).map_err(|e| anyhow::anyhow!("{}", e))?;
+        Ok(Self { engine, ast, names: headers.iter().map(|h| variable_name(h)).collect(), headers: headers.to_vec() })
+    }
+
+    /// Evaluate against a row's values; the expression has to produce true or false
+    pub fn eval_bool(&self, values: &[String]) -> Result<bool, String> {
+        let mut scope = Scope::new();
+        let mut row = Map::new();
+        for (c, header) in self.headers.iter().enumerate() {
+            let value = cell_value(values.get(c).map(String::as_str).unwrap_or(""));
+            row.insert(header.as_str().into(), value.clone());
+            scope.push_dynamic(self.names[c].as_str(), value);
+        }
+        scope.push("row", row);
+        let result = self.engine.eval_ast_with_scope::<Dynamic>(&mut scope, &self.ast).map_err(|e| e.to_string())?;
+        result.as_bool().map_err(|_| format!("Expected true or false, got {}", result.type_name()))
+    }
+}
+
+/// A header as a Rhai variable name
+pub fn variable_name(header: &str) -> String {
+    let name: String = header.trim().chars().map(|c| if c.is_alphanumeric() { c } else { '_' }).collect();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", name)
+    } else {
+        name
+    }
+}
+
+fn cell_value(value: &str) -> Dynamic {
+    let value = value.trim();
+    if value.is_empty() {
+        Dynamic::UNIT
+    } else if let Ok(n) = value.parse::<i64>() {
+        Dynamic::from(n)
+    } else if let Ok(x) = value.parse::<f64>() {
+        Dynamic::from(x)
+    } else {
+        Dynamic::from(value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table() -> ScriptInput {
+        ScriptInput::Table {
+            headers: vec!["name".to_string(), "qty".to_string()],
+            rows: vec![
+                vec!["a".to_string(), "1".to_string()],
+                vec!["b".to_string(), "5".to_string()],
+                vec!["c".to_string(), "3".to_string()],
+            ],
+        }
+    }
+
+    #[test]
+    fn test_table_script_filters_and_adds_column() -> Result<()> {
+        let script = r#"
+            filter(|row| parse_int(row[1]) > 2);
+            let c = add_column("double");
+            for r in 0..rows() {
+                set(r, c, parse_int(get(r, 1)) * 2);
+            }
+            print(rows());
+        "#;
+        let outcome = run_script(script, table())?;
+        assert_eq!(outcome.output, vec!["2"]);
+        let ScriptChanges::Table { headers, rows } = outcome.changes else {
+            panic!("expected a table change");
+        };
+        assert_eq!(headers, vec!["name", "qty", "double"]);
+        assert_eq!(rows, vec![vec!["b", "5", "10"], vec!["c", "3", "6"]]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_script_collects_cell_edits() -> Result<()> {
+        let data = [["x", "1"], ["y", "2"]];
+        let input = ScriptInput::File {
+            total: 2,
+            num_cols: 2,
+            row_at: Box::new(move |r| data[r].iter().map(|v| v.to_string()).collect()),
+        };
+        let outcome = run_script(r#"set(1, 0, "z"); set(0, 1, get(0, 1));"#, input)?;
+        let ScriptChanges::Cells(cells) = outcome.changes else {
+            panic!("expected cell changes");
+        };
+        assert_eq!(cells, vec![(1, 0, "y".to_string(), "z".to_string())]);
+
+        let data = [["x"]];
+        let input = ScriptInput::File {
+            total: 1,
+            num_cols: 1,
+            row_at: Box::new(move |r| data[r].iter().map(|v| v.to_string()).collect()),
+        };
+        assert!(run_script(r#"add_column("n");"#, input).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_row_expression() -> Result<()> {
+        let headers = ["start date", "end_date", "price", "qty", "total"].map(String::from);
+        let row = |values: [&str; 5]| values.map(String::from);
+        let rule = RowExpression::compile("end_date >= start_date && total == price * qty", &headers)?;
+        assert_eq!(rule.eval_bool(&row(["2024-01-01", "2024-02-01", "2.5", "4", "10"])), Ok(true));
+        assert_eq!(rule.eval_bool(&row(["2024-03-01", "2024-02-01", "2.5", "4", "10"])), Ok(false));
+
+        let by_name = RowExpression::compile(r#"row["start date"] == ()"#, &headers)?;
+        assert_eq!(by_name.eval_bool(&row(["", "", "", "", ""])), Ok(true));
+        assert!(RowExpression::compile("price +", &headers).is_err());
+        assert!(RowExpression::compile("price", &headers)?.eval_bool(&row(["", "", "1", "", ""])).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,224 @@
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::analysis::ColumnAnalyzer;
+use super::loader::CsvLoader;
+use super::parser::CsvParser;
+use super::query::Query;
+
+/// Rows returned by /rows when no limit is given
+const DEFAULT_PAGE_ROWS: usize = 100;
+/// Largest page /rows will return
+const MAX_PAGE_ROWS: usize = 10_000;
+/// Most values /profile reads from a column, spread evenly over the file
+const PROFILE_SAMPLE_ROWS: usize = 10_000;
+
+/// Read-only view of a CSV file: the first record is the header row
+pub struct FileApi {
+    path: PathBuf,
+    loader: CsvLoader,
+    headers: Vec<String>,
+    /// Profiles already worked out, by column; the file doesn't change while served
+    profiles: RefCell<HashMap<usize, Value>>,
+}
+
+impl FileApi {
+    pub fn open(path: &Path) -> Result<Self> {
+        let loader = CsvLoader::open_table(path)?;
+        let headers = loader
+            .get_record_line(0)
+            .and_then(|line| CsvParser::parse_line(&String::from_utf8_lossy(&line)).ok())
+            .unwrap_or_default();
+        Ok(Self { path: path.to_path_buf(), loader, headers, profiles: RefCell::default() })
+    }
+
+    /// Data rows, excluding the header
+    fn total(&self) -> usize {
+        self.loader.total_records().saturating_sub(1)
+    }
+
+    fn row(&self, r: usize) -> Vec<String> {
+        self.loader
+            .get_record_line(r + 1)
+            .and_then(|line| CsvParser::parse_line(&String::from_utf8_lossy(&line)).ok())
+            .unwrap_or_default()
+    }
+
+    /// One field of a data row, without parsing the rest
+    fn field(&self, r: usize, col: usize) -> String {
+        self.loader
+            .get_record_line(r + 1)
+            .and_then(|line| CsvParser::parse_line_projected(&String::from_utf8_lossy(&line), &[col]).ok())
+            .and_then(|fields| fields.into_iter().next())
+            .unwrap_or_default()
+    }
+
+    /// Answer a GET request for `url` (path plus query string) with a status and JSON body
+    pub fn handle(&self, url: &str) -> (u16, Value) {
+        let (path, query) = url.split_once('?').unwrap_or((url, ""));
+        let params: Vec<(String, String)> = query
+            .split('&')
+            .filter(|p| !p.is_empty())
+            .map(|p| {
+                let (k, v) = p.split_once('=').unwrap_or((p, ""));
+                (percent_decode(k), percent_decode(v))
+            })
+            .collect();
+        let param = |name: &str| params.iter().find(|(k, _)| k == name).map(|(_, v)| v.as_str());
+
+        let result = match path.trim_end_matches('/') {
+            "" => Ok(json!({
+                "file": self.path.display().to_string(),
+                "rows": self.total(),
+                "headers": self.headers,
+                "endpoints": ["/rows?offset&limit", "/profile/:col", "/query?sql="],
+            })),
+            "/rows" => self.rows(param("offset"), param("limit")),
+            "/query" => match param("sql") {
+                Some(sql) => self.query(sql),
+                None => Err(anyhow::anyhow!("Missing sql parameter")),
+            },
+            p if p.starts_with("/profile/") => self.profile(&percent_decode(&p["/profile/".len()..])),
+            _ => return (404, json!({ "error": "Not found" })),
+        };
+        match result {
+            Ok(body) => (200, body),
+            Err(e) => (400, json!({ "error": format!("{:#}", e) })),
+        }
+    }
+
+    fn rows(&self, offset: Option<&str>, limit: Option<&str>) -> Result<Value> {
+        let offset: usize = offset.map(str::parse).transpose().context("Invalid offset")?.unwrap_or(0);
+        let limit: usize = limit.map(str::parse).transpose().context("Invalid limit")?.unwrap_or(DEFAULT_PAGE_ROWS);
+        let end = offset.saturating_add(limit.min(MAX_PAGE_ROWS)).min(self.total());
+        let rows: Vec<Vec<String>> = (offset.min(end)..end).map(|r| self.row(r)).collect();
+        Ok(json!({ "offset": offset, "total": self.total(), "headers": self.headers, "rows": rows }))
+    }
+
+    /// Profile a column given by index or header name, from at most
+    /// `PROFILE_SAMPLE_ROWS` rows so one request can't hold up the others
+    fn profile(&self, col: &str) -> Result<Value> {
+        let index = match col.parse::<usize>() {
+            Ok(i) if i < self.headers.len() => i,
+            _ => self.headers
+                .iter()
+                .position(|h| h.trim() == col)
+                .with_context(|| format!("Unknown column \"{}\"", col))?,
+        };
+        if let Some(profile) = self.profiles.borrow().get(&index) {
+            return Ok(profile.clone());
+        }
+        let total = self.total();
+        let step = total.div_ceil(PROFILE_SAMPLE_ROWS).max(1);
+        let values: Vec<String> = (0..total).step_by(step).map(|r| self.field(r, index)).collect();
+        let mut profile = serde_json::to_value(ColumnAnalyzer::analyze_column(&self.headers[index], index, &values))?;
+        // total_count is the sample's size; this is the file's
+        profile["total_rows"] = json!(total);
+        self.profiles.borrow_mut().insert(index, profile.clone());
+        Ok(profile)
+    }
+
+    fn query(&self, sql: &str) -> Result<Value> {
+        let result = Query::parse(sql)?.run(&self.headers, self.total(), |r| self.row(r))?;
+        Ok(json!({ "headers": result.headers, "rows": result.rows }))
+    }
+}
+
+/// Serve `path` on localhost until the process is stopped
+pub fn serve(path: &Path, port: u16) -> Result<()> {
+    let api = FileApi::open(path)?;
+    let server = tiny_http::Server::http(("127.0.0.1", port))
+        .map_err(|e| anyhow::anyhow!("Failed to listen on port {}: {}", port, e))?;
+    println!("Serving {} ({} rows) on http://127.0.0.1:{}", path.display(), api.total(), port);
+
+    let content_type = tiny_http::Header::from_bytes("Content-Type", "application/json")
+        .expect("static header is valid");
+    for request in server.incoming_requests() {
+        let (status, body) = if *request.method() == tiny_http::Method::Get {
+            api.handle(request.url())
+        } else {
+            (405, json!({ "error": "Only GET is supported" }))
+        };
+        let response = tiny_http::Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(content_type.clone());
+        if let Err(e) = request.respond(response) {
+            eprintln!("Failed to send response: {}", e);
+        }
+    }
+    Ok(())
+}
+
+/// Decode `%XX` escapes and `+` in a query string component
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                    Some(b) => {
+                        out.push(b);
+                        i += 2;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_rows_profile_and_query_endpoints() -> Result<()> {
+        let mut input = NamedTempFile::new()?;
+        write!(input, "id,name\n1,ann\n2,bob\n3,cy\n")?;
+        let api = FileApi::open(input.path())?;
+
+        let (status, body) = api.handle("/rows?offset=1&limit=1");
+        assert_eq!(status, 200);
+        assert_eq!(body["total"], 3);
+        assert_eq!(body["rows"], json!([["2", "bob"]]));
+
+        let (_, body) = api.handle("/profile/id");
+        assert_eq!(body["max"], 3.0);
+
+        let (_, body) = api.handle("/query?sql=SELECT+name+WHERE+id+%3E%3D+2");
+        assert_eq!(body["rows"], json!([["bob"], ["cy"]]));
+
+        assert_eq!(api.handle("/nope").0, 404);
+        assert_eq!(api.handle("/rows?limit=x").0, 400);
+        Ok(())
+    }
+
+    #[test]
+    fn test_profile_samples_large_files() -> Result<()> {
+        let mut input = NamedTempFile::new()?;
+        writeln!(input, "id,name")?;
+        for i in 0..25_000 {
+            writeln!(input, "{},n{}", i, i)?;
+        }
+        let api = FileApi::open(input.path())?;
+
+        let (status, body) = api.handle("/profile/1");
+        assert_eq!(status, 200);
+        assert_eq!(body["total_rows"], 25_000);
+        assert!(body["total_count"].as_u64().is_some_and(|n| n <= PROFILE_SAMPLE_ROWS as u64));
+        assert_eq!(api.handle("/profile/name").1, body);
+        Ok(())
+    }
+}
@@ -1,7 +1,10 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use directories::ProjectDirs;
 
+use super::export::ExportPreset;
+
 #[derive(PartialEq, Clone, Copy, Serialize, Deserialize, Debug)]
 pub enum Theme {
     System,
@@ -12,6 +15,7 @@ pub enum Theme {
     Nord,
     Dracula,
     Catppuccin,
+    HighContrast,
     Custom(usize), // Index into custom_themes
 }
 
@@ -26,6 +30,7 @@ impl Theme {
             Theme::Nord,
             Theme::Dracula,
             Theme::Catppuccin,
+            Theme::HighContrast,
         ]
     }
 
@@ -39,6 +44,7 @@ impl Theme {
             Theme::Nord => "Nord",
             Theme::Dracula => "Dracula",
             Theme::Catppuccin => "Catppuccin",
+            Theme::HighContrast => "High Contrast",
             Theme::Custom(_) => "Custom",
         }
     }
@@ -93,6 +99,25 @@ impl KeybindingMode {
     }
 }
 
+/// Where the selection goes after Enter commits an in-cell edit
+#[derive(PartialEq, Clone, Copy, Serialize, Deserialize, Debug, Default)]
+pub enum EnterDirection {
+    #[default]
+    Down,
+    Right,
+    Stay,
+}
+
+impl EnterDirection {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EnterDirection::Down => "Down",
+            EnterDirection::Right => "Right",
+            EnterDirection::Stay => "Stay",
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub struct KeyCombo {
     pub key: eframe::egui::Key,
@@ -110,11 +135,13 @@ impl Default for KeyCombo {
 
 impl KeyCombo {
     pub fn matches(&self, input: &eframe::egui::InputState) -> bool {
-        input.key_pressed(self.key) && input.modifiers == self.modifiers
+        // matches_exact treats Ctrl and Cmd as the same "command" modifier
+        input.key_pressed(self.key) && input.modifiers.matches_exact(self.modifiers)
     }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
 pub struct Keymap {
     pub move_up: KeyCombo,
     pub move_down: KeyCombo,
@@ -124,6 +151,23 @@ pub struct Keymap {
     pub redo: KeyCombo,
     pub save: KeyCombo,
     pub toggle_hud: KeyCombo,
+    pub new_file: KeyCombo,
+    pub open_file: KeyCombo,
+    pub print: KeyCombo,
+    pub open_settings: KeyCombo,
+    /// Edit the selected cell (like double-click)
+    pub edit_cell: KeyCombo,
+    /// Find duplicates in the selected cell's column
+    pub find_duplicates: KeyCombo,
+    pub toggle_perf_overlay: KeyCombo,
+    /// Narrow / widen the selected cell's column
+    pub shrink_column: KeyCombo,
+    pub grow_column: KeyCombo,
+    /// Lower / raise the height of every row
+    pub shrink_rows: KeyCombo,
+    pub grow_rows: KeyCombo,
+    /// Sum, mean, count and the like of the selected cells
+    pub quick_calc: KeyCombo,
 }
 
 impl Default for Keymap {
@@ -138,6 +182,59 @@ impl Default for Keymap {
             redo: KeyCombo { key: Key::Y, modifiers: Modifiers::COMMAND },
             save: KeyCombo { key: Key::S, modifiers: Modifiers::COMMAND },
             toggle_hud: KeyCombo { key: Key::B, modifiers: Modifiers::COMMAND },
+            new_file: KeyCombo { key: Key::N, modifiers: Modifiers::COMMAND },
+            open_file: KeyCombo { key: Key::O, modifiers: Modifiers::COMMAND },
+            print: KeyCombo { key: Key::P, modifiers: Modifiers::COMMAND },
+            open_settings: KeyCombo { key: Key::Comma, modifiers: Modifiers::COMMAND },
+            edit_cell: KeyCombo { key: Key::F2, modifiers: Modifiers::NONE },
+            find_duplicates: KeyCombo { key: Key::D, modifiers: Modifiers::COMMAND | Modifiers::SHIFT },
+            toggle_perf_overlay: KeyCombo { key: Key::F12, modifiers: Modifiers::NONE },
+            shrink_column: KeyCombo { key: Key::ArrowLeft, modifiers: Modifiers::ALT | Modifiers::SHIFT },
+            grow_column: KeyCombo { key: Key::ArrowRight, modifiers: Modifiers::ALT | Modifiers::SHIFT },
+            shrink_rows: KeyCombo { key: Key::ArrowUp, modifiers: Modifiers::ALT | Modifiers::SHIFT },
+            grow_rows: KeyCombo { key: Key::ArrowDown, modifiers: Modifiers::ALT | Modifiers::SHIFT },
+            quick_calc: KeyCombo { key: Key::F9, modifiers: Modifiers::NONE },
+        }
+    }
+}
+
+/// An entry in the recent files list
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(from = "RecentFileEntry")]
+pub struct RecentFile {
+    pub path: String,
+    /// Unix timestamp (seconds) of the last time the file was opened
+    pub last_opened: u64,
+    pub rows: Option<usize>,
+    /// File size in bytes when it was last opened
+    pub size: Option<u64>,
+    /// Pinned files are listed first and never trimmed from the list
+    pub pinned: bool,
+}
+
+/// Older configs stored recent files as plain paths
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RecentFileEntry {
+    Path(String),
+    Full {
+        path: String,
+        #[serde(default)]
+        last_opened: u64,
+        #[serde(default)]
+        rows: Option<usize>,
+        #[serde(default)]
+        size: Option<u64>,
+        #[serde(default)]
+        pinned: bool,
+    },
+}
+
+impl From<RecentFileEntry> for RecentFile {
+    fn from(entry: RecentFileEntry) -> Self {
+        match entry {
+            RecentFileEntry::Path(path) => Self { path, last_opened: 0, rows: None, size: None, pinned: false },
+            RecentFileEntry::Full { path, last_opened, rows, size, pinned } => Self { path, last_opened, rows, size, pinned },
         }
     }
 }
@@ -148,15 +245,39 @@ pub struct Settings {
     pub font_size: f32,
     pub row_height: f32,
     pub use_edit_modal: bool,
+    /// Typing on a selected cell starts editing it, replacing its content
+    #[serde(default = "default_true")]
+    pub use_type_to_edit: bool,
+    #[serde(default)]
+    pub enter_direction: EnterDirection,
     #[serde(default)]
     pub auto_beautify_json: bool,
     #[serde(default)]
-    pub recent_files: Vec<String>,
+    pub recent_files: Vec<RecentFile>,
     #[serde(default = "default_max_recent")]
     pub max_recent_files: usize,
+    /// Resized column widths by file path, kept for recent files only
+    #[serde(default)]
+    pub column_widths: HashMap<String, Vec<f32>>,
+    /// Row heights set for a file from the keyboard, kept like `column_widths`
+    #[serde(default)]
+    pub row_heights: HashMap<String, f32>,
     #[serde(default)]
     pub stripe_color: Option<[u8; 3]>,
     #[serde(default)]
+    pub show_grid_lines: bool,
+    /// None uses the theme's separator color
+    #[serde(default)]
+    pub grid_line_color: Option<[u8; 3]>,
+    #[serde(default = "default_grid_line_width")]
+    pub grid_line_width: f32,
+    /// Horizontal space between a cell's edge and its text
+    #[serde(default = "default_cell_padding")]
+    pub cell_padding: f32,
+    /// Cells longer than this many bytes are cut short in the table
+    #[serde(default = "default_max_cell_bytes")]
+    pub max_cell_bytes: usize,
+    #[serde(default)]
     pub custom_themes: Vec<CustomTheme>,
     #[serde(default = "default_font")]
     pub font_family: String,
@@ -166,12 +287,48 @@ pub struct Settings {
     pub show_profile_hud: bool,
     #[serde(default)]
     pub keymap: Keymap,
+    #[serde(default = "default_true")]
+    pub show_type_badges: bool,
+    #[serde(default = "default_true")]
+    pub show_sparklines: bool,
+    #[serde(default)]
+    pub show_null_markers: bool,
+    #[serde(default)]
+    pub show_perf_overlay: bool,
+    /// Operations expected to need more than this many MB ask first
+    #[serde(default = "default_memory_budget")]
+    pub memory_budget_mb: u64,
+    /// Fixed UI scale (pixels per point); None follows the monitor's scale factor
+    #[serde(default)]
+    pub ui_scale: Option<f32>,
+    #[serde(default)]
+    pub export_presets: Vec<ExportPreset>,
 }
 
 fn default_max_recent() -> usize {
     10
 }
 
+fn default_grid_line_width() -> f32 {
+    1.0
+}
+
+fn default_cell_padding() -> f32 {
+    4.0
+}
+
+fn default_max_cell_bytes() -> usize {
+    4096
+}
+
+fn default_memory_budget() -> u64 {
+    2048
+}
+
+fn default_true() -> bool {
+    true
+}
+
 fn default_font() -> String {
     "JetBrains Mono".to_string()
 }
@@ -183,15 +340,31 @@ impl Default for Settings {
             font_size: 14.0,
             row_height: 24.0,
             use_edit_modal: false,
+            use_type_to_edit: true,
+            enter_direction: EnterDirection::Down,
             auto_beautify_json: false,
             recent_files: Vec::new(),
             max_recent_files: 10,
+            column_widths: HashMap::new(),
+            row_heights: HashMap::new(),
             stripe_color: None,
+            show_grid_lines: false,
+            grid_line_color: None,
+            grid_line_width: default_grid_line_width(),
+            cell_padding: default_cell_padding(),
+            max_cell_bytes: default_max_cell_bytes(),
             custom_themes: Vec::new(),
             font_family: default_font(),
             keybinding_mode: KeybindingMode::Standard,
             show_profile_hud: false,
             keymap: Keymap::default(),
+            show_type_badges: true,
+            show_sparklines: true,
+            show_null_markers: false,
+            show_perf_overlay: false,
+            memory_budget_mb: default_memory_budget(),
+            ui_scale: None,
+            export_presets: Vec::new(),
         }
     }
 }
@@ -202,27 +375,109 @@ impl Settings {
             let config_dir = proj_dirs.config_dir();
             let config_path = config_dir.join("config.json");
             
-            if config_path.exists() {
-                if let Ok(content) = fs::read_to_string(&config_path) {
-                    if let Ok(settings) = serde_json::from_str(&content) {
-                        return settings;
-                    }
-                }
+            if config_path.exists()
+                && let Ok(content) = fs::read_to_string(&config_path)
+                && let Ok(settings) = serde_json::from_str(&content)
+            {
+                return settings;
             }
         }
         Self::default()
     }
 
-    pub fn add_recent_file(&mut self, path: &str) {
-        // Remove if already exists
-        self.recent_files.retain(|p| p != path);
+    pub fn add_recent_file(&mut self, path: &str, rows: Option<usize>) {
+        self.push_recent_file(path, rows);
+        self.save();
+    }
+
+    fn push_recent_file(&mut self, path: &str, rows: Option<usize>) {
+        // Remove if already exists, keeping its pin
+        let pinned = self.recent_files.iter().any(|f| f.path == path && f.pinned);
+        self.recent_files.retain(|f| f.path != path);
         // Add to front
-        self.recent_files.insert(0, path.to_string());
-        // Trim to max
-        self.recent_files.truncate(self.max_recent_files);
+        let last_opened = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        self.recent_files.insert(0, RecentFile {
+            path: path.to_string(),
+            last_opened,
+            rows,
+            size: fs::metadata(path).ok().map(|m| m.len()),
+            pinned,
+        });
+        // Trim unpinned entries to max
+        let max = self.max_recent_files;
+        let mut unpinned = 0;
+        self.recent_files.retain(|f| {
+            if !f.pinned {
+                unpinned += 1;
+            }
+            f.pinned || unpinned <= max
+        });
+    }
+
+    /// Recent files with pinned entries first, each group most recent first
+    pub fn recent_files_ordered(&self) -> Vec<RecentFile> {
+        let mut files = self.recent_files.clone();
+        files.sort_by_key(|f| !f.pinned);
+        files
+    }
+
+    pub fn toggle_recent_pin(&mut self, path: &str) {
+        if let Some(file) = self.recent_files.iter_mut().find(|f| f.path == path) {
+            file.pinned = !file.pinned;
+            self.save();
+        }
+    }
+
+    pub fn remove_recent_file(&mut self, path: &str) {
+        self.recent_files.retain(|f| f.path != path);
+        self.save();
+    }
+
+    /// Remember a file's column widths, dropping those of files no longer in the recent list
+    pub fn set_column_widths(&mut self, path: &str, widths: Vec<f32>) {
+        self.column_widths.insert(path.to_string(), widths);
+        self.prune_column_widths(path);
+        self.save();
+    }
+
+    pub fn set_row_height(&mut self, path: &str, height: f32) {
+        self.row_heights.insert(path.to_string(), height);
+        self.prune_column_widths(path);
+        self.save();
+    }
+
+    /// Add a preset, replacing any with the same name
+    pub fn save_export_preset(&mut self, preset: ExportPreset) {
+        self.export_presets.retain(|p| p.name != preset.name);
+        self.export_presets.push(preset);
+        self.save();
+    }
+
+    pub fn remove_export_preset(&mut self, name: &str) {
+        self.export_presets.retain(|p| p.name != name);
         self.save();
     }
 
+    pub fn export_preset(&self, name: &str) -> Option<&ExportPreset> {
+        self.export_presets.iter().find(|p| p.name == name)
+    }
+
+    /// Go back to automatic widths for a file
+    pub fn forget_column_widths(&mut self, path: &str) {
+        if self.column_widths.remove(path).is_some() {
+            self.save();
+        }
+    }
+
+    fn prune_column_widths(&mut self, keep: &str) {
+        let recent = &self.recent_files;
+        self.column_widths.retain(|p, _| p == keep || recent.iter().any(|f| f.path == *p));
+        self.row_heights.retain(|p, _| p == keep || recent.iter().any(|f| f.path == *p));
+    }
+
     pub fn save(&self) {
         if let Some(proj_dirs) = ProjectDirs::from("", "", "csvit") {
             let config_dir = proj_dirs.config_dir();
@@ -251,20 +506,18 @@ impl Settings {
     pub fn load_custom_themes(&mut self) {
         if let Some(proj_dirs) = ProjectDirs::from("", "", "csvit") {
             let themes_dir = proj_dirs.config_dir().join("themes");
-            if themes_dir.exists() {
-                if let Ok(entries) = fs::read_dir(&themes_dir) {
-                    for entry in entries.flatten() {
-                        let path = entry.path();
-                        if path.extension().map(|e| e == "json").unwrap_or(false) {
-                            if let Ok(content) = fs::read_to_string(&path) {
-                                if let Ok(theme) = serde_json::from_str::<CustomTheme>(&content) {
-                                    // Only add if not already present
-                                    if !self.custom_themes.iter().any(|t| t.name == theme.name) {
-                                        self.custom_themes.push(theme);
-                                    }
-                                }
-                            }
-                        }
+            if themes_dir.exists()
+                && let Ok(entries) = fs::read_dir(&themes_dir)
+            {
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    if path.extension().map(|e| e == "json").unwrap_or(false)
+                        && let Ok(content) = fs::read_to_string(&path)
+                        && let Ok(theme) = serde_json::from_str::<CustomTheme>(&content)
+                        // Only add if not already present
+                        && !self.custom_themes.iter().any(|t| t.name == theme.name)
+                    {
+                        self.custom_themes.push(theme);
                     }
                 }
             }
@@ -289,3 +542,31 @@ impl Settings {
         ]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_files_legacy_and_pinning() {
+        let legacy: Vec<RecentFile> = serde_json::from_str(r#"["/a.csv", {"path": "/b.csv", "pinned": true}]"#).unwrap();
+        assert_eq!(legacy[0].path, "/a.csv");
+        assert!(!legacy[0].pinned && legacy[1].pinned);
+
+        let mut settings = Settings { max_recent_files: 2, recent_files: legacy, ..Settings::default() };
+        settings.push_recent_file("/c.csv", Some(10));
+        settings.push_recent_file("/d.csv", None);
+        settings.push_recent_file("/b.csv", None);
+
+        let paths: Vec<String> = settings.recent_files_ordered().into_iter().map(|f| f.path).collect();
+        assert_eq!(paths, ["/b.csv", "/d.csv", "/c.csv"]);
+
+        settings.column_widths.insert("/gone.csv".to_string(), vec![80.0]);
+        settings.column_widths.insert("/c.csv".to_string(), vec![90.0]);
+        settings.column_widths.insert("/new.csv".to_string(), vec![70.0]);
+        settings.prune_column_widths("/new.csv");
+        let mut kept: Vec<&String> = settings.column_widths.keys().collect();
+        kept.sort();
+        assert_eq!(kept, ["/c.csv", "/new.csv"]);
+    }
+}
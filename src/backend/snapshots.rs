@@ -0,0 +1,389 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use super::jobs::Progress;
+use super::rebase::RowMatch;
+
+/// Cell changes listed by a comparison; the rest are only counted
+pub const MAX_LISTED_CHANGES: usize = 500;
+
+/// A saved copy of a document, zipped with a small description beside it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub label: String,
+    /// Seconds since the Unix epoch
+    pub created: u64,
+    /// Data rows, not counting the header
+    pub rows: usize,
+    #[serde(skip)]
+    pub path: PathBuf,
+    #[serde(skip)]
+    pub size: u64,
+}
+
+impl Snapshot {
+    /// Creation time in local time, for lists
+    pub fn created_text(&self) -> String {
+        chrono::DateTime::from_timestamp(self.created as i64, 0)
+            .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default()
+    }
+}
+
+/// Folder holding the snapshots of the document at `document_path`, under
+/// the app's data folder. The name keeps the file's stem for anyone browsing
+/// it, with a hash of the full path so same-named files don't mix.
+pub fn snapshot_dir(document_path: &str) -> Option<PathBuf> {
+    let dirs = directories::ProjectDirs::from("", "", "csvit")?;
+    let hash = Sha256::digest(document_path.as_bytes());
+    let stem = Path::new(document_path).file_stem().map_or_else(|| "untitled".into(), |s| s.to_string_lossy());
+    let hex: String = hash.iter().take(8).map(|b| format!("{:02x}", b)).collect();
+    Some(dirs.data_dir().join("snapshots").join(format!("{}-{}", stem, hex)))
+}
+
+/// Write `records` (header first) as a new snapshot in `dir`. A snapshot
+/// that fails or is cancelled partway is removed rather than left half written.
+pub fn create_snapshot(
+    dir: &Path,
+    label: &str,
+    total: usize,
+    records: impl Iterator<Item = Vec<String>>,
+    progress: &Progress,
+) -> Result<Snapshot> {
+    std::fs::create_dir_all(dir).context("Failed to create the snapshots folder")?;
+    let created = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let mut path = dir.join(format!("{}.zip", created));
+    let mut n = 1;
+    while path.exists() {
+        path = dir.join(format!("{}-{}.zip", created, n));
+        n += 1;
+    }
+
+    let file = File::create(&path).context("Failed to create the snapshot")?;
+    let mut snapshot = Snapshot { label: label.to_string(), created, rows: 0, path: path.clone(), size: 0 };
+    if let Err(e) = write_snapshot(file, &mut snapshot, total, records, progress) {
+        let _ = std::fs::remove_file(&path);
+        return Err(e);
+    }
+    snapshot.size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    Ok(snapshot)
+}
+
+/// Zip `records` and the description into `file`, counting the rows into `snapshot`
+fn write_snapshot(
+    file: File,
+    snapshot: &mut Snapshot,
+    total: usize,
+    records: impl Iterator<Item = Vec<String>>,
+    progress: &Progress,
+) -> Result<()> {
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut zip = ZipWriter::new(file);
+    zip.start_file("data.csv", options)?;
+    {
+        let mut writer = csv::WriterBuilder::new().flexible(true).from_writer(&mut zip);
+        progress.set_total(total);
+        for (i, record) in records.enumerate() {
+            if i % 10_000 == 0 {
+                if progress.is_cancelled() {
+                    anyhow::bail!("Snapshot cancelled");
+                }
+                progress.set_done(i);
+            }
+            writer.write_record(&record)?;
+            // The header is record 0, so the last index is the data row count
+            snapshot.rows = i;
+        }
+        writer.flush()?;
+    }
+    zip.start_file("snapshot.json", options)?;
+    zip.write_all(serde_json::to_string_pretty(snapshot)?.as_bytes())?;
+    zip.finish().context("Failed to finish the snapshot")?;
+    Ok(())
+}
+
+/// Snapshots in `dir`, newest first. Files that aren't snapshots are skipped.
+pub fn list_snapshots(dir: &Path) -> Vec<Snapshot> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut snapshots: Vec<Snapshot> = entries.flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|e| e == "zip"))
+        .filter_map(|path| {
+            let mut archive = ZipArchive::new(File::open(&path).ok()?).ok()?;
+            let mut text = String::new();
+            archive.by_name("snapshot.json").ok()?.read_to_string(&mut text).ok()?;
+            let mut snapshot: Snapshot = serde_json::from_str(&text).ok()?;
+            snapshot.size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            snapshot.path = path;
+            Some(snapshot)
+        })
+        .collect();
+    snapshots.sort_by(|a, b| b.created.cmp(&a.created).then_with(|| b.path.cmp(&a.path)));
+    snapshots
+}
+
+/// Stream a snapshot's records, header first, to `visit`
+pub fn read_snapshot(path: &Path, progress: &Progress, visit: &mut dyn FnMut(Vec<String>)) -> Result<()> {
+    let mut archive = ZipArchive::new(File::open(path).context("Failed to open the snapshot")?)
+        .context("Not a snapshot archive")?;
+    let data = archive.by_name("data.csv").context("data.csv not found in the snapshot")?;
+    read_records(data, progress, visit)
+}
+
+/// Stream the records of CSV text from `input`, header first, to `visit`,
+/// stopping early if the job is cancelled
+pub fn read_records(input: impl Read, progress: &Progress, visit: &mut dyn FnMut(Vec<String>)) -> Result<()> {
+    let mut reader = csv::ReaderBuilder::new().has_headers(false).flexible(true).from_reader(input);
+    let mut record = csv::StringRecord::new();
+    let mut i = 0;
+    while reader.read_record(&mut record)? {
+        if i % 10_000 == 0 {
+            if progress.is_cancelled() {
+                anyhow::bail!("Reading cancelled");
+            }
+            progress.set_done(i);
+        }
+        visit(record.iter().map(str::to_string).collect());
+        i += 1;
+    }
+    Ok(())
+}
+
+/// Header and data rows of streamed records, all held in memory; for
+/// restoring a snapshot, not for comparing with one
+pub fn collect_table(read: impl FnOnce(&mut dyn FnMut(Vec<String>)) -> Result<()>) -> Result<(Vec<String>, Vec<Vec<String>>)> {
+    let mut rows = Vec::new();
+    read(&mut |record| rows.push(record))?;
+    let headers = if rows.is_empty() { Vec::new() } else { rows.remove(0) };
+    Ok((headers, rows))
+}
+
+/// Hash of a row's values, to tell whether it changed without keeping it
+fn row_hash(values: &[String]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    values.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// How the document differs from a snapshot
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SnapshotDiff {
+    /// Columns whose name changed, as (column, in the snapshot, now)
+    pub renamed: Vec<(usize, String, String)>,
+    /// Changed cells as (data row, column, in the snapshot, now), up to `MAX_LISTED_CHANGES`
+    pub cells: Vec<(usize, usize, String, String)>,
+    pub changed_cells: usize,
+    pub rows_added: usize,
+    pub rows_removed: usize,
+}
+
+impl SnapshotDiff {
+    pub fn is_empty(&self) -> bool {
+        self.renamed.is_empty() && self.changed_cells == 0 && self.rows_added == 0 && self.rows_removed == 0
+    }
+
+    /// List the cells that differ between a row then and now; the list is
+    /// cut to `MAX_LISTED_CHANGES` once every row is compared
+    fn compare_row(&mut self, r: usize, old: &[String], row: &[String]) {
+        let cell = |row: &[String], c: usize| row.get(c).cloned().unwrap_or_default();
+        for c in 0..old.len().max(row.len()) {
+            let (before, after) = (cell(old, c), cell(row, c));
+            if before != after {
+                self.changed_cells += 1;
+                self.cells.push((r, c, before, after));
+            }
+        }
+    }
+}
+
+/// Compare the document's header and data rows with a snapshot's. Rows are
+/// paired up first, as `matching` says, so inserting, deleting or sorting
+/// rows doesn't make every later row look changed. Matching by content, an
+/// edited row matches nothing, so a row left over is then compared with the
+/// old row beside its neighbour's pair. Rows still unpaired were added or removed.
+///
+/// `old` streams the snapshot's records, header first, each time it's
+/// called. Only hashes of the old rows are kept, so the snapshot is read once
+/// to pair rows up and once more, if any paired row differs, to list its changes.
+pub fn diff_snapshot(
+    old: impl Fn(&mut dyn FnMut(Vec<String>)) -> Result<()>,
+    headers: &[String],
+    rows: impl Iterator<Item = Vec<String>>,
+    matching: RowMatch,
+    progress: &Progress,
+) -> Result<SnapshotDiff> {
+    let mut old_headers = None;
+    let mut old_hashes = Vec::new();
+    let mut by_key: HashMap<u64, VecDeque<usize>> = HashMap::new();
+    old(&mut |record| {
+        if old_headers.is_none() {
+            old_headers = Some(record);
+            return;
+        }
+        let i = old_hashes.len();
+        old_hashes.push(row_hash(&record));
+        if let Some(key) = matching.key_of(&record) {
+            by_key.entry(row_hash(&[key])).or_default().push_back(i);
+        }
+    })?;
+    let old_headers = old_headers.unwrap_or_default();
+
+    let mut diff = SnapshotDiff::default();
+    let cell = |row: &[String], c: usize| row.get(c).cloned().unwrap_or_default();
+    for c in 0..old_headers.len().max(headers.len()) {
+        let (before, after) = (cell(&old_headers, c), cell(headers, c));
+        if before != after {
+            diff.renamed.push((c, before, after));
+        }
+    }
+
+    // Whether each old row was paired, and each new row's old row
+    let mut paired = vec![false; old_hashes.len()];
+    let mut pair_of: Vec<Option<usize>> = Vec::new();
+    // Paired rows that differ, by old row, to compare once it's read again
+    let mut changed: HashMap<usize, (usize, Vec<String>)> = HashMap::new();
+    let mut leftover = Vec::new();
+    for (r, row) in rows.enumerate() {
+        if r % 10_000 == 0 {
+            if progress.is_cancelled() {
+                anyhow::bail!("Comparing cancelled");
+            }
+            progress.set_done(r);
+        }
+        let old = matching
+            .key_of(&row)
+            .and_then(|key| by_key.get_mut(&row_hash(&[key])))
+            .and_then(|rows| rows.pop_front());
+        pair_of.push(old);
+        match old {
+            Some(i) => {
+                paired[i] = true;
+                if old_hashes[i] != row_hash(&row) {
+                    changed.insert(i, (r, row));
+                }
+            }
+            None => leftover.push((r, row)),
+        }
+    }
+
+    if matching == RowMatch::Content {
+        // Down from the row above's pair, then up from the row below's
+        for upwards in [false, true] {
+            let mut rest = Vec::new();
+            let rows: Vec<_> = if upwards { leftover.into_iter().rev().collect() } else { leftover };
+            for (r, row) in rows {
+                let beside = match (upwards, r) {
+                    (false, 0) => Some(0),
+                    (false, r) => pair_of[r - 1].map(|p| p + 1),
+                    (true, r) if r + 1 == pair_of.len() => old_hashes.len().checked_sub(1),
+                    (true, r) => pair_of[r + 1].and_then(|p| p.checked_sub(1)),
+                };
+                match beside.filter(|&i| i < old_hashes.len() && !paired[i]) {
+                    Some(i) => {
+                        paired[i] = true;
+                        pair_of[r] = Some(i);
+                        changed.insert(i, (r, row));
+                    }
+                    None => rest.push((r, row)),
+                }
+            }
+            leftover = rest;
+        }
+    }
+
+    if !changed.is_empty() {
+        let mut i = None;
+        old(&mut |record| {
+            // The header comes first, as None
+            if let Some((r, row)) = i.and_then(|i| changed.get(&i)) {
+                diff.compare_row(*r, &record, row);
+            }
+            i = Some(i.map_or(0, |i| i + 1));
+        })?;
+    }
+    diff.rows_added = leftover.len();
+    diff.rows_removed = paired.iter().filter(|&&p| !p).count();
+    diff.cells.sort_by_key(|&(r, c, _, _)| (r, c));
+    diff.cells.truncate(MAX_LISTED_CHANGES);
+    Ok(diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_snapshot_round_trip_and_diff() -> Result<()> {
+        let dir = TempDir::new()?;
+        let records = vec![
+            vec!["id".to_string(), "name".to_string()],
+            vec!["1".to_string(), "Ann".to_string()],
+            vec!["2".to_string(), "Bo, Jr".to_string()],
+        ];
+        let snapshot = create_snapshot(dir.path(), "before cleanup", records.len(), records.clone().into_iter(), &Progress::default())?;
+        assert_eq!(snapshot.rows, 2);
+
+        let listed = list_snapshots(dir.path());
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].label, "before cleanup");
+        let read = |visit: &mut dyn FnMut(Vec<String>)| read_snapshot(&listed[0].path, &Progress::default(), visit);
+        let (headers, rows) = collect_table(read)?;
+        assert_eq!(headers, records[0]);
+        assert_eq!(rows, records[1..]);
+
+        let now = vec![vec!["1".to_string(), "Anne".to_string()]];
+        let diff = diff_snapshot(read, &["id".to_string(), "name".to_string()], now.into_iter(), RowMatch::Content, &Progress::default())?;
+        assert_eq!(diff.cells, vec![(0, 1, "Ann".to_string(), "Anne".to_string())]);
+        assert_eq!((diff.rows_added, diff.rows_removed), (0, 1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_matches_rows_before_comparing() -> Result<()> {
+        let table = |text: &str| -> Vec<Vec<String>> {
+            text.lines().map(|line| line.split(',').map(String::from).collect()).collect()
+        };
+        let headers = vec!["id".to_string(), "name".to_string()];
+        let old = table("id,name\n1,ann\n2,bob\n3,cy");
+        let old = |visit: &mut dyn FnMut(Vec<String>)| {
+            old.iter().cloned().for_each(visit);
+            Ok(())
+        };
+        // A row inserted at the top, and cy renamed
+        let now = table("0,zed\n1,ann\n2,bob\n3,Cy");
+
+        let by_content = diff_snapshot(old, &headers, now.clone().into_iter(), RowMatch::Content, &Progress::default())?;
+        assert_eq!(by_content.cells, vec![(3, 1, "cy".to_string(), "Cy".to_string())]);
+        assert_eq!((by_content.rows_added, by_content.rows_removed), (1, 0));
+
+        let by_key = diff_snapshot(old, &headers, now.into_iter(), RowMatch::Key(0), &Progress::default())?;
+        assert_eq!(by_key.cells, vec![(3, 1, "cy".to_string(), "Cy".to_string())]);
+        assert_eq!((by_key.rows_added, by_key.rows_removed), (1, 0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_snapshot_leaves_no_file() -> Result<()> {
+        let dir = TempDir::new()?;
+        let progress = Progress::default();
+        progress.cancel();
+        let records = vec![vec!["id".to_string()], vec!["1".to_string()]];
+        assert!(create_snapshot(dir.path(), "cancelled", 2, records.into_iter(), &progress).is_err());
+        assert_eq!(std::fs::read_dir(dir.path())?.count(), 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,288 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use super::editor::DeltaBuffer;
+use super::jobs::Progress;
+use super::loader::CsvLoader;
+use super::parser::CsvParser;
+use super::writer::{try_save_loader_as, CsvWriteOptions};
+
+/// Keys buffered per sorted run when the caller has no memory budget to go by
+pub const DEFAULT_RUN_BYTES: usize = 256 * 1024 * 1024;
+
+/// Rough in-memory cost of one (row, key) pair on top of the key's bytes
+const ENTRY_OVERHEAD: usize = 48;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+impl SortOrder {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SortOrder::Ascending => "ascending",
+            SortOrder::Descending => "descending",
+        }
+    }
+
+    /// Flip an ascending comparison for descending order
+    pub fn apply(&self, ordering: Ordering) -> Ordering {
+        match self {
+            SortOrder::Ascending => ordering,
+            SortOrder::Descending => ordering.reverse(),
+        }
+    }
+}
+
+/// Numbers sort numerically and before text; text sorts by its bytes.
+/// This is a total order, so mixed columns still sort consistently.
+pub fn compare_values(a: &str, b: &str) -> Ordering {
+    match (a.trim().parse::<f64>(), b.trim().parse::<f64>()) {
+        (Ok(x), Ok(y)) => x.total_cmp(&y),
+        (Ok(_), Err(_)) => Ordering::Less,
+        (Err(_), Ok(_)) => Ordering::Greater,
+        (Err(_), Err(_)) => a.cmp(b),
+    }
+}
+
+/// Sort row indices by their keys, spilling to disk once the buffered keys
+/// pass `run_bytes`. Each full buffer is sorted and written to a run file in
+/// `temp_dir`; the runs are then k-way merged. Ties keep input order.
+pub fn sort_indices(
+    keys: impl IntoIterator<Item = (usize, String)>,
+    order: SortOrder,
+    run_bytes: usize,
+    temp_dir: &Path,
+    progress: &Progress,
+) -> Result<SortedIndices> {
+    let mut buffer: Vec<(usize, String)> = Vec::new();
+    let mut buffered = 0;
+    let mut runs: Option<RunDir> = None;
+    for (i, (row, key)) in keys.into_iter().enumerate() {
+        if i % 10_000 == 0 {
+            if progress.is_cancelled() {
+                anyhow::bail!("Sort cancelled");
+            }
+            progress.set_done(i);
+        }
+        buffered += key.len() + ENTRY_OVERHEAD;
+        buffer.push((row, key));
+        if buffered >= run_bytes {
+            let dir = match runs {
+                Some(ref mut dir) => dir,
+                None => runs.insert(RunDir::create(temp_dir)?),
+            };
+            dir.write_run(&mut buffer, order)?;
+            buffered = 0;
+        }
+    }
+
+    buffer.sort_by(|a, b| order.apply(compare_values(&a.1, &b.1)));
+    let Some(mut dir) = runs else {
+        return Ok(SortedIndices::Memory(buffer.into_iter()));
+    };
+    if !buffer.is_empty() {
+        dir.write_run(&mut buffer, order)?;
+    }
+    let mut readers = Vec::with_capacity(dir.runs.len());
+    let mut heap = BinaryHeap::with_capacity(dir.runs.len());
+    for (run, path) in dir.runs.iter().enumerate() {
+        let mut reader = BufReader::new(File::open(path).context("Failed to reopen sort run")?);
+        if let Some((row, key)) = read_entry(&mut reader)? {
+            heap.push(HeapEntry { key, row, run, order });
+        }
+        readers.push(reader);
+    }
+    Ok(SortedIndices::Merge { readers, heap, _dir: dir })
+}
+
+/// Row indices in sorted order, from memory or merged from run files
+pub enum SortedIndices {
+    Memory(std::vec::IntoIter<(usize, String)>),
+    Merge {
+        readers: Vec<BufReader<File>>,
+        heap: BinaryHeap<HeapEntry>,
+        // Removes the run files once the merge is dropped
+        _dir: RunDir,
+    },
+}
+
+impl Iterator for SortedIndices {
+    type Item = Result<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            SortedIndices::Memory(rows) => rows.next().map(|(row, _)| Ok(row)),
+            SortedIndices::Merge { readers, heap, .. } => {
+                let entry = heap.pop()?;
+                match read_entry(&mut readers[entry.run]) {
+                    Ok(Some((row, key))) => heap.push(HeapEntry { key, row, run: entry.run, order: entry.order }),
+                    Ok(None) => {}
+                    Err(e) => return Some(Err(e)),
+                }
+                Some(Ok(entry.row))
+            }
+        }
+    }
+}
+
+/// The head of one run during the merge. Ordered so the max-heap pops the
+/// smallest key first, and the earliest run on ties to keep the sort stable.
+pub struct HeapEntry {
+    key: String,
+    row: usize,
+    run: usize,
+    order: SortOrder,
+}
+
+impl Ord for HeapEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.order
+            .apply(compare_values(&self.key, &other.key))
+            .then(self.run.cmp(&other.run))
+            .reverse()
+    }
+}
+
+impl PartialOrd for HeapEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for HeapEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for HeapEntry {}
+
+/// A private directory of run files, deleted on drop
+pub struct RunDir {
+    path: PathBuf,
+    runs: Vec<PathBuf>,
+}
+
+impl RunDir {
+    fn create(parent: &Path) -> Result<Self> {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let path = parent.join(format!(".csvit-sort-{}-{}", std::process::id(), nanos));
+        std::fs::create_dir_all(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+        Ok(Self { path, runs: Vec::new() })
+    }
+
+    /// Sort and write the buffer as the next run, leaving it empty
+    fn write_run(&mut self, buffer: &mut Vec<(usize, String)>, order: SortOrder) -> Result<()> {
+        buffer.sort_by(|a, b| order.apply(compare_values(&a.1, &b.1)));
+        let path = self.path.join(format!("run{:05}", self.runs.len()));
+        let mut out = BufWriter::new(File::create(&path).context("Failed to create sort run")?);
+        for (row, key) in buffer.drain(..) {
+            out.write_all(&(row as u64).to_le_bytes())?;
+            out.write_all(&(key.len() as u64).to_le_bytes())?;
+            out.write_all(key.as_bytes())?;
+        }
+        out.flush().context("Failed to write sort run (is the disk full?)")?;
+        self.runs.push(path);
+        Ok(())
+    }
+}
+
+impl Drop for RunDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Next (row, key) from a run file, or None at its end
+fn read_entry(reader: &mut impl Read) -> Result<Option<(usize, String)>> {
+    let mut word = [0u8; 8];
+    match reader.read_exact(&mut word) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e).context("Failed to read sort run"),
+    }
+    let row = u64::from_le_bytes(word) as usize;
+    reader.read_exact(&mut word).context("Truncated sort run")?;
+    let mut key = vec![0u8; u64::from_le_bytes(word) as usize];
+    reader.read_exact(&mut key).context("Truncated sort run")?;
+    Ok(Some((row, String::from_utf8_lossy(&key).into_owned())))
+}
+
+/// Write a file-backed document to `path` sorted by one column, keeping
+/// the header first. Only the sort keys are held in memory (up to
+/// `run_bytes` at a time); the records themselves stream from the source.
+pub fn sort_loader(
+    loader: &CsvLoader,
+    edits: &DeltaBuffer,
+    column: usize,
+    order: SortOrder,
+    path: &Path,
+    run_bytes: usize,
+    progress: &Progress,
+) -> Result<()> {
+    let total = loader.total_records();
+    progress.set_total(total);
+    let keys = (1..total).map(|row| {
+        let key = match edits.get_edit(row, column) {
+            Some(value) => value.clone(),
+            None => loader
+                .get_record_line(row)
+                .and_then(|line| CsvParser::parse_line(&String::from_utf8_lossy(&line)).ok())
+                .and_then(|mut fields| (column < fields.len()).then(|| fields.swap_remove(column)))
+                .unwrap_or_default(),
+        };
+        (row, key)
+    });
+    let temp_dir = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let sorted = sort_indices(keys, order, run_bytes, temp_dir, progress)?;
+
+    // A merge error aborts the write before the temp file replaces `path`
+    try_save_loader_as(loader, edits, std::iter::once(Ok(0)).chain(sorted), path, &CsvWriteOptions::matching(loader), progress)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_external_sort() -> Result<()> {
+        let dir = TempDir::new()?;
+        let keys = ["10", "b", "9", "a", "-1", "b", "2.5", ""];
+        let input = || keys.iter().enumerate().map(|(i, k)| (i, k.to_string()));
+
+        let in_memory: Vec<usize> = sort_indices(input(), SortOrder::Ascending, usize::MAX, dir.path(), &Progress::default())?
+            .collect::<Result<_>>()?;
+        assert_eq!(in_memory, vec![4, 6, 2, 0, 7, 3, 1, 5]);
+
+        // A tiny run size forces a run file per key, and ties still keep input order
+        let spilled: Vec<usize> = sort_indices(input(), SortOrder::Ascending, 1, dir.path(), &Progress::default())?
+            .collect::<Result<_>>()?;
+        assert_eq!(spilled, in_memory);
+        let descending: Vec<usize> = sort_indices(input(), SortOrder::Descending, 1, dir.path(), &Progress::default())?
+            .collect::<Result<_>>()?;
+        assert_eq!(descending, vec![1, 5, 3, 7, 0, 2, 6, 4]);
+        assert_eq!(std::fs::read_dir(dir.path())?.count(), 0, "run files are cleaned up");
+
+        let source = dir.path().join("data.csv");
+        std::fs::write(&source, "name,qty\npear,10\napple,2\n\"fig, dried\",7\n")?;
+        let loader = CsvLoader::new(&source)?;
+        let mut edits = DeltaBuffer::new();
+        edits.add_edit(1, 1, "10".to_string(), "1".to_string());
+        let sorted = dir.path().join("sorted.csv");
+        sort_loader(&loader, &edits, 1, SortOrder::Ascending, &sorted, 1, &Progress::default())?;
+        assert_eq!(std::fs::read_to_string(&sorted)?, "name,qty\npear,1\napple,2\n\"fig, dried\",7\n");
+        Ok(())
+    }
+}
@@ -0,0 +1,157 @@
+use anyhow::{Context, Result};
+use memmap2::Mmap;
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Filesystems where mapping a file is unreliable: another client can
+/// truncate it underneath us, which turns page faults into SIGBUS
+#[cfg(target_os = "linux")]
+const NETWORK_FILESYSTEMS: &[&str] = &["nfs", "nfs4", "cifs", "smb3", "smbfs", "9p", "fuse.sshfs"];
+
+/// Random-access bytes of an open file
+pub trait CsvSource: Send + Sync {
+    fn len(&self) -> u64;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Bytes in `start..end`, clamped to the end of the file. Borrowed when
+    /// the source can, otherwise read into a fresh buffer.
+    fn read(&self, start: u64, end: u64) -> Cow<'_, [u8]>;
+
+    /// How the file is being read, for File Info
+    fn kind(&self) -> &'static str;
+}
+
+/// The file mapped into memory; reads are free slices
+pub struct MmapSource {
+    mmap: Mmap,
+}
+
+impl CsvSource for MmapSource {
+    fn len(&self) -> u64 {
+        self.mmap.len() as u64
+    }
+
+    fn read(&self, start: u64, end: u64) -> Cow<'_, [u8]> {
+        let end = (end as usize).min(self.mmap.len());
+        let start = (start as usize).min(end);
+        Cow::Borrowed(&self.mmap[start..end])
+    }
+
+    fn kind(&self) -> &'static str {
+        "Memory-mapped"
+    }
+}
+
+/// Seek-and-read access, for files that can't (or shouldn't) be mapped.
+/// The length is fixed at open, like a mapping's.
+pub struct BufferedSource {
+    file: Mutex<File>,
+    len: u64,
+}
+
+impl BufferedSource {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path).with_context(|| format!("Failed to open file: {:?}", path))?;
+        let len = file.metadata().context("Failed to read file size")?.len();
+        Ok(Self { file: Mutex::new(file), len })
+    }
+}
+
+impl CsvSource for BufferedSource {
+    fn len(&self) -> u64 {
+        self.len
+    }
+
+    fn read(&self, start: u64, end: u64) -> Cow<'_, [u8]> {
+        let end = end.min(self.len);
+        let start = start.min(end);
+        let mut buf = Vec::with_capacity((end - start) as usize);
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        // A short read (the file shrank) just returns fewer bytes
+        if file.seek(SeekFrom::Start(start)).is_ok() {
+            let _ = (&mut *file).take(end - start).read_to_end(&mut buf);
+        }
+        Cow::Owned(buf)
+    }
+
+    fn kind(&self) -> &'static str {
+        "Buffered (not memory-mapped)"
+    }
+}
+
+/// Map the file when that's safe, otherwise fall back to buffered reads
+pub fn open_source(path: &Path) -> Result<Arc<dyn CsvSource>> {
+    if !on_network_filesystem(path) {
+        let file = File::open(path).with_context(|| format!("Failed to open file: {:?}", path))?;
+        // Mapping fails on some special and remote files; those still read fine
+        if let Ok(mmap) = unsafe { Mmap::map(&file) } {
+            return Ok(Arc::new(MmapSource { mmap }));
+        }
+    }
+    Ok(Arc::new(BufferedSource::open(path)?))
+}
+
+/// Whether `path` lives on a network share, by its mount in /proc/mounts
+#[cfg(target_os = "linux")]
+fn on_network_filesystem(path: &Path) -> bool {
+    let Ok(path) = path.canonicalize() else {
+        return false;
+    };
+    let Ok(mounts) = std::fs::read_to_string("/proc/mounts") else {
+        return false;
+    };
+    // The longest mount point containing the path is the one it's on
+    let fs_type = mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let mount_point = fields.nth(1)?.replace("\\040", " ");
+            let fs_type = fields.next()?;
+            path.starts_with(&mount_point).then_some((mount_point.len(), fs_type))
+        })
+        .max_by_key(|(len, _)| *len)
+        .map(|(_, fs_type)| fs_type);
+    fs_type.is_some_and(|fs_type| NETWORK_FILESYSTEMS.contains(&fs_type))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn on_network_filesystem(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::loader::CsvLoader;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_buffered_source_matches_mmap() -> Result<()> {
+        // Enough rows to cross several index chunks, each with a quoted newline
+        let file = NamedTempFile::new()?;
+        let rows = 200_000;
+        let text: String = (0..rows).map(|i| format!("{},\"a\nb\"\n", i)).collect();
+        std::fs::write(file.path(), &text)?;
+
+        let mapped = CsvLoader::new(file.path())?;
+        let buffered = CsvLoader::from_source(Arc::new(BufferedSource::open(file.path())?));
+        assert_eq!(mapped.source_kind(), Some("Memory-mapped"));
+        assert_eq!(buffered.total_records(), rows);
+        assert_eq!(mapped.total_records(), rows);
+        for r in [0, 1, rows / 2, rows - 1] {
+            assert_eq!(buffered.get_record_line(r), mapped.get_record_line(r));
+        }
+        assert_eq!(buffered.get_record_line(rows - 1).as_deref(), Some(format!("{},\"a\nb\"\n", rows - 1).as_bytes()));
+
+        let source = BufferedSource::open(file.path())?;
+        assert_eq!(&source.read(2, 5)[..], &text.as_bytes()[2..5]);
+        assert_eq!(source.read(source.len() - 1, source.len() + 10).len(), 1);
+        Ok(())
+    }
+}
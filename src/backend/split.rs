@@ -0,0 +1,314 @@
+use anyhow::{Context, Result};
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use super::editor::DeltaBuffer;
+use super::jobs::Progress;
+use super::loader::CsvLoader;
+use super::writer::{parse_record, CsvWriteOptions, RecordEncoder};
+
+/// Refuse to split into more files than this by column value
+const MAX_SPLIT_GROUPS: usize = 1000;
+
+/// Keep at most this many column-value parts open at once, well under the
+/// usual file descriptor limit; others are reopened for appending as needed
+const MAX_OPEN_PARTS: usize = 64;
+
+/// How a file is divided into parts
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum SplitMode {
+    /// A new part every N data rows
+    EveryRows(usize),
+    /// One part per distinct value of a column
+    ByColumn(usize),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SplitOptions {
+    pub mode: SplitMode,
+    /// Treat the first record as a header and repeat it at the top of every part
+    pub header: bool,
+}
+
+/// Stream a file-backed document into several CSV files in `out_dir`,
+/// named `{stem}_part001.csv` or `{stem}_{value}.csv` (with `_2`, `_3`...
+/// when two values clean up to the same name). Pending edits are merged in.
+/// Returns the files written, in creation order; if the split fails or is
+/// cancelled, the parts written so far are removed again. A part whose name
+/// is already taken in `out_dir` fails the split rather than overwriting it.
+pub fn split_loader(
+    loader: &CsvLoader,
+    edits: &DeltaBuffer,
+    out_dir: &Path,
+    stem: &str,
+    options: &SplitOptions,
+    progress: &Progress,
+) -> Result<Vec<PathBuf>> {
+    let mut parts = Vec::new();
+    match write_parts(loader, edits, out_dir, stem, options, progress, &mut parts) {
+        Ok(()) => Ok(parts),
+        Err(e) => {
+            for path in &parts {
+                let _ = std::fs::remove_file(path);
+            }
+            Err(e)
+        }
+    }
+}
+
+fn write_parts(
+    loader: &CsvLoader,
+    edits: &DeltaBuffer,
+    out_dir: &Path,
+    stem: &str,
+    options: &SplitOptions,
+    progress: &Progress,
+    parts: &mut Vec<PathBuf>,
+) -> Result<()> {
+    let mut encoder = RecordEncoder::new(&CsvWriteOptions::matching(loader));
+    let total = loader.total_records();
+    let first_data_row = usize::from(options.header);
+    let header_line = if options.header {
+        loader.get_record_line(0).map(|line| encode(&mut encoder, &line, edits, 0)).transpose()?
+    } else {
+        None
+    };
+
+    let mut part_paths: HashMap<String, PathBuf> = HashMap::new();
+    let mut open_parts = OpenParts::default();
+    // File names taken so far, lowercased for case-insensitive file systems
+    let mut names: HashSet<String> = HashSet::new();
+    let mut current: Option<BufWriter<File>> = None;
+
+    progress.set_total(total);
+    for row in first_data_row..total {
+        if row % 10_000 == 0 {
+            if progress.is_cancelled() {
+                anyhow::bail!("Split cancelled");
+            }
+            progress.set_done(row);
+        }
+        let Some(line) = loader.get_record_line(row) else {
+            continue;
+        };
+
+        let out = match options.mode {
+            SplitMode::EveryRows(n) => {
+                if (row - first_data_row) % n.max(1) == 0 {
+                    if let Some(mut previous) = current.take() {
+                        previous.flush()?;
+                    }
+                    let path = out_dir.join(format!("{}_part{:03}.csv", stem, parts.len() + 1));
+                    current = Some(create_part(&path, header_line.as_deref())?);
+                    parts.push(path);
+                }
+                current.as_mut().expect("a part is open after the first row")
+            }
+            SplitMode::ByColumn(col) => {
+                let key = match edits.get_edit(row, col) {
+                    Some(value) => value.clone(),
+                    None => parse_record(&line)?
+                        .get(col)
+                        .map(|f| String::from_utf8_lossy(f).to_string())
+                        .unwrap_or_default(),
+                };
+                match part_paths.get(&key) {
+                    Some(path) => open_parts.get_or_open(&key, || append_part(path))?,
+                    None => {
+                        if part_paths.len() >= MAX_SPLIT_GROUPS {
+                            anyhow::bail!("Column has more than {} distinct values", MAX_SPLIT_GROUPS);
+                        }
+                        let path = out_dir.join(unique_name(&mut names, &format!("{}_{}", stem, sanitize_file_part(&key))));
+                        let out = open_parts.get_or_open(&key, || create_part(&path, header_line.as_deref()))?;
+                        parts.push(path.clone());
+                        part_paths.insert(key, path);
+                        out
+                    }
+                }
+            }
+        };
+        encoder.write(out, &line, edits.row_edits(row))?;
+    }
+
+    for mut out in current.into_iter().chain(open_parts.files.into_values().map(|(out, _)| out)) {
+        out.flush().context("Failed to flush output file")?;
+    }
+    Ok(())
+}
+
+fn encode(encoder: &mut RecordEncoder, line: &[u8], edits: &DeltaBuffer, row: usize) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    encoder.write(&mut buf, line, edits.row_edits(row))?;
+    Ok(buf)
+}
+
+/// Column-value parts currently open, closing the least recently written
+/// one once `MAX_OPEN_PARTS` are open
+#[derive(Default)]
+struct OpenParts {
+    files: HashMap<String, (BufWriter<File>, u64)>,
+    clock: u64,
+}
+
+impl OpenParts {
+    fn get_or_open(&mut self, key: &str, open: impl FnOnce() -> Result<BufWriter<File>>) -> Result<&mut BufWriter<File>> {
+        self.clock += 1;
+        if !self.files.contains_key(key) {
+            if self.files.len() >= MAX_OPEN_PARTS {
+                let oldest = self.files.iter().min_by_key(|(_, (_, used))| *used).map(|(k, _)| k.clone());
+                if let Some((mut out, _)) = oldest.and_then(|k| self.files.remove(&k)) {
+                    out.flush().context("Failed to flush output file")?;
+                }
+            }
+            self.files.insert(key.to_string(), (open()?, self.clock));
+        }
+        let (out, used) = self.files.get_mut(key).expect("part was just opened");
+        *used = self.clock;
+        Ok(out)
+    }
+}
+
+fn create_part(path: &Path, header: Option<&[u8]>) -> Result<BufWriter<File>> {
+    let file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut out = BufWriter::new(file);
+    if let Some(header) = header {
+        out.write_all(header)?;
+    }
+    Ok(out)
+}
+
+fn append_part(path: &Path) -> Result<BufWriter<File>> {
+    let file = OpenOptions::new()
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to reopen {}", path.display()))?;
+    Ok(BufWriter::new(file))
+}
+
+/// `{name}.csv`, or `{name}_2.csv` and so on if that's already in `taken`
+fn unique_name(taken: &mut HashSet<String>, name: &str) -> String {
+    let mut file_name = format!("{}.csv", name);
+    let mut n = 1;
+    while !taken.insert(file_name.to_lowercase()) {
+        n += 1;
+        file_name = format!("{}_{}.csv", name, n);
+    }
+    file_name
+}
+
+/// Make a column value safe to use in a file name
+fn sanitize_file_part(value: &str) -> String {
+    let cleaned: String = value
+        .trim()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .take(64)
+        .collect();
+    if cleaned.is_empty() { "empty".to_string() } else { cleaned }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::{NamedTempFile, TempDir};
+
+    fn loader_for(text: &str) -> Result<(NamedTempFile, CsvLoader)> {
+        let mut input = NamedTempFile::new()?;
+        write!(input, "{}", text)?;
+        let loader = CsvLoader::new(input.path())?;
+        Ok((input, loader))
+    }
+
+    #[test]
+    fn test_split_every_rows_repeats_header() -> Result<()> {
+        let (_input, loader) = loader_for("id,v\n1,a\n2,b\n3,c\n")?;
+        let dir = TempDir::new()?;
+        let options = SplitOptions { mode: SplitMode::EveryRows(2), header: true };
+        let parts = split_loader(&loader, &DeltaBuffer::new(), dir.path(), "data", &options, &Progress::default())?;
+
+        assert_eq!(parts.len(), 2);
+        assert_eq!(std::fs::read_to_string(&parts[0])?, "id,v\n1,a\n2,b\n");
+        assert_eq!(std::fs::read_to_string(&parts[1])?, "id,v\n3,c\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_by_column_uses_edits() -> Result<()> {
+        let (_input, loader) = loader_for("k,v\nx,1\ny,2\nx,3\n")?;
+        let mut edits = DeltaBuffer::new();
+        edits.add_edit(2, 0, "y".to_string(), "x".to_string());
+        let dir = TempDir::new()?;
+        let options = SplitOptions { mode: SplitMode::ByColumn(0), header: true };
+        let parts = split_loader(&loader, &edits, dir.path(), "data", &options, &Progress::default())?;
+
+        assert_eq!(parts, vec![dir.path().join("data_x.csv")]);
+        assert_eq!(std::fs::read_to_string(&parts[0])?, "k,v\nx,1\nx,2\nx,3\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_by_column_keeps_names_apart() -> Result<()> {
+        let (_input, loader) = loader_for("k,v\na/b,1\na?b,2\nA_b,3\n")?;
+        let dir = TempDir::new()?;
+        let options = SplitOptions { mode: SplitMode::ByColumn(0), header: true };
+        let parts = split_loader(&loader, &DeltaBuffer::new(), dir.path(), "data", &options, &Progress::default())?;
+
+        let names: Vec<_> = parts.iter().map(|p| p.file_name().unwrap().to_string_lossy().to_string()).collect();
+        assert_eq!(names, ["data_a_b.csv", "data_a_b_2.csv", "data_A_b_3.csv"]);
+        assert_eq!(std::fs::read_to_string(&parts[1])?, "k,v\na?b,2\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_too_many_groups_leaves_nothing() -> Result<()> {
+        let mut text = String::from("k\n");
+        for i in 0..=MAX_SPLIT_GROUPS {
+            text.push_str(&format!("{}\n", i));
+        }
+        let (_input, loader) = loader_for(&text)?;
+        let dir = TempDir::new()?;
+        let options = SplitOptions { mode: SplitMode::ByColumn(0), header: true };
+        assert!(split_loader(&loader, &DeltaBuffer::new(), dir.path(), "data", &options, &Progress::default()).is_err());
+        assert_eq!(std::fs::read_dir(dir.path())?.count(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_by_column_reopens_closed_parts() -> Result<()> {
+        let groups = MAX_OPEN_PARTS + 6;
+        let mut text = String::from("k,pass\n");
+        for pass in 1..=2 {
+            for i in 0..groups {
+                text.push_str(&format!("{},{}\n", i, pass));
+            }
+        }
+        let (_input, loader) = loader_for(&text)?;
+        let dir = TempDir::new()?;
+        let options = SplitOptions { mode: SplitMode::ByColumn(0), header: true };
+        let parts = split_loader(&loader, &DeltaBuffer::new(), dir.path(), "data", &options, &Progress::default())?;
+
+        assert_eq!(parts.len(), groups);
+        assert_eq!(std::fs::read_to_string(&parts[0])?, "k,pass\n0,1\n0,2\n");
+        assert_eq!(std::fs::read_to_string(&parts[groups - 1])?, format!("k,pass\n{0},1\n{0},2\n", groups - 1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_keeps_existing_files() -> Result<()> {
+        let (_input, loader) = loader_for("k,v\nx,1\ny,2\n")?;
+        let dir = TempDir::new()?;
+        std::fs::write(dir.path().join("data_y.csv"), "mine\n")?;
+        let options = SplitOptions { mode: SplitMode::ByColumn(0), header: true };
+        assert!(split_loader(&loader, &DeltaBuffer::new(), dir.path(), "data", &options, &Progress::default()).is_err());
+
+        assert_eq!(std::fs::read_to_string(dir.path().join("data_y.csv"))?, "mine\n");
+        assert!(!dir.path().join("data_x.csv").exists());
+        Ok(())
+    }
+}
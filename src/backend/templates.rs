@@ -0,0 +1,164 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use super::analysis::InferredType;
+use super::grid::EditableGrid;
+use super::schema::{Constraints, SchemaField, TableSchema};
+
+/// A starting layout for a new document: named, typed columns with their
+/// validation rules as a Table Schema, and how many empty rows to begin with.
+/// Saved templates are JSON files in the config folder's `templates`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Template {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    pub schema: TableSchema,
+    #[serde(default = "default_rows")]
+    pub rows: usize,
+}
+
+fn default_rows() -> usize {
+    10
+}
+
+fn field(name: &str, field_type: &str, constraints: Constraints) -> SchemaField {
+    SchemaField { name: name.to_string(), field_type: field_type.to_string(), constraints }
+}
+
+fn required() -> Constraints {
+    Constraints { required: true, ..Default::default() }
+}
+
+impl Template {
+    /// The templates every install starts with
+    pub fn builtin() -> Vec<Template> {
+        let email = Constraints { pattern: Some(r"[^@\s]+@[^@\s]+\.[^@\s]+".to_string()), ..Default::default() };
+        let contacts = TableSchema {
+            fields: vec![
+                field("First Name", "string", required()),
+                field("Last Name", "string", required()),
+                field("Email", "string", email),
+                field("Phone", "string", Constraints::default()),
+                field("Company", "string", Constraints::default()),
+                field("Notes", "string", Constraints::default()),
+            ],
+            ..Default::default()
+        };
+        let transactions = TableSchema {
+            fields: vec![
+                field("Date", "date", required()),
+                field("Description", "string", required()),
+                field("Category", "string", Constraints::default()),
+                field("Amount", "number", required()),
+                field("Account", "string", Constraints::default()),
+            ],
+            ..Default::default()
+        };
+        let metrics = TableSchema {
+            fields: vec![
+                field("Timestamp", "datetime", required()),
+                field("Metric", "string", required()),
+                field("Value", "number", required()),
+                field("Unit", "string", Constraints::default()),
+                field("Source", "string", Constraints::default()),
+            ],
+            ..Default::default()
+        };
+        vec![
+            Template { name: "Contacts".to_string(), description: "People with email and phone".to_string(), schema: contacts, rows: 10 },
+            Template { name: "Transactions".to_string(), description: "Dated amounts by category".to_string(), schema: transactions, rows: 10 },
+            Template { name: "Metrics Log".to_string(), description: "Timestamped measurements".to_string(), schema: metrics, rows: 10 },
+        ]
+    }
+
+    /// A template of an open document's columns. Its schema is kept when it
+    /// has one; otherwise columns are typed from their profiles where known.
+    pub fn from_document(name: String, headers: &[String], types: &[Option<InferredType>], schema: Option<TableSchema>) -> Self {
+        let schema = schema.unwrap_or_else(|| TableSchema {
+            fields: headers.iter()
+                .enumerate()
+                .map(|(c, header)| {
+                    let field_type = match types.get(c).cloned().flatten() {
+                        Some(InferredType::Integer) => "integer",
+                        Some(InferredType::Float) => "number",
+                        Some(InferredType::Boolean) => "boolean",
+                        Some(InferredType::Date) => "date",
+                        Some(InferredType::Text) => "string",
+                        _ => "any",
+                    };
+                    field(header, field_type, Constraints::default())
+                })
+                .collect(),
+            ..Default::default()
+        });
+        Self { name, description: String::new(), schema, rows: default_rows() }
+    }
+
+    pub fn headers(&self) -> Vec<String> {
+        self.schema.fields.iter().map(|f| f.name.clone()).collect()
+    }
+
+    /// An empty grid with this template's columns
+    pub fn instantiate(&self) -> EditableGrid {
+        let headers = self.headers();
+        let rows = vec![vec![String::new(); headers.len()]; self.rows];
+        EditableGrid::from_rows(headers, rows)
+    }
+
+    /// Write to `dir`, named after the template, replacing one of the same name
+    pub fn save(&self, dir: &Path) -> Result<PathBuf> {
+        std::fs::create_dir_all(dir).context("Failed to create the templates folder")?;
+        let stem: String = self.name.chars()
+            .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+        let path = dir.join(format!("{}.json", stem));
+        std::fs::write(&path, serde_json::to_string_pretty(self)?).context("Failed to write the template")?;
+        Ok(path)
+    }
+}
+
+/// Where saved templates live
+pub fn templates_dir() -> Option<PathBuf> {
+    directories::ProjectDirs::from("", "", "csvit").map(|dirs| dirs.config_dir().join("templates"))
+}
+
+/// Every template saved in `dir`, by name. Files that aren't templates are skipped.
+pub fn load_saved(dir: &Path) -> Vec<Template> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut templates: Vec<Template> = entries.flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|e| e == "json"))
+        .filter_map(|entry| std::fs::read_to_string(entry.path()).ok())
+        .filter_map(|text| serde_json::from_str(&text).ok())
+        .collect();
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    templates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_saved_template_round_trip() -> Result<()> {
+        let dir = TempDir::new()?;
+        let headers = vec!["id".to_string(), "price".to_string()];
+        let mut template = Template::from_document("Price list".to_string(), &headers, &[Some(InferredType::Integer), None], None);
+        template.rows = 3;
+        template.save(dir.path())?;
+        std::fs::write(dir.path().join("notes.json"), "{}")?;
+
+        let saved = load_saved(dir.path());
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].name, "Price list");
+        assert_eq!(saved[0].schema.fields[0].field_type, "integer");
+        let grid = saved[0].instantiate();
+        assert_eq!(grid.headers, headers);
+        assert_eq!(grid.num_rows(), 3);
+        Ok(())
+    }
+}
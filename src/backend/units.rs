@@ -0,0 +1,149 @@
+use std::ops::Range;
+
+use super::editor::CellEdit;
+use super::jobs::Progress;
+
+/// A unit that converts to others of its kind by a fixed factor
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Unit {
+    Bytes,
+    Kilobytes,
+    Megabytes,
+    Gigabytes,
+    Terabytes,
+    Milliseconds,
+    Seconds,
+    Minutes,
+    Hours,
+}
+
+impl Unit {
+    pub const SIZES: [Unit; 5] = [Unit::Bytes, Unit::Kilobytes, Unit::Megabytes, Unit::Gigabytes, Unit::Terabytes];
+    pub const DURATIONS: [Unit; 4] = [Unit::Milliseconds, Unit::Seconds, Unit::Minutes, Unit::Hours];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Unit::Bytes => "B",
+            Unit::Kilobytes => "KB",
+            Unit::Megabytes => "MB",
+            Unit::Gigabytes => "GB",
+            Unit::Terabytes => "TB",
+            Unit::Milliseconds => "ms",
+            Unit::Seconds => "s",
+            Unit::Minutes => "min",
+            Unit::Hours => "h",
+        }
+    }
+
+    /// Size of the unit in bytes or milliseconds. Sizes are binary (1 KB = 1024 B).
+    fn factor(&self) -> f64 {
+        match self {
+            Unit::Bytes => 1.0,
+            Unit::Kilobytes => 1024.0,
+            Unit::Megabytes => 1024.0 * 1024.0,
+            Unit::Gigabytes => 1024.0 * 1024.0 * 1024.0,
+            Unit::Terabytes => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+            Unit::Milliseconds => 1.0,
+            Unit::Seconds => 1_000.0,
+            Unit::Minutes => 60_000.0,
+            Unit::Hours => 3_600_000.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UnitConversion {
+    /// Between two sizes or two durations
+    Scale { from: Unit, to: Unit },
+    FahrenheitToCelsius,
+    CelsiusToFahrenheit,
+    /// Multiply by a fixed rate, e.g. an exchange rate
+    Rate(f64),
+}
+
+impl UnitConversion {
+    pub fn convert(&self, x: f64) -> f64 {
+        match *self {
+            UnitConversion::Scale { from, to } => x * from.factor() / to.factor(),
+            UnitConversion::FahrenheitToCelsius => (x - 32.0) * 5.0 / 9.0,
+            UnitConversion::CelsiusToFahrenheit => x * 9.0 / 5.0 + 32.0,
+            UnitConversion::Rate(rate) => x * rate,
+        }
+    }
+}
+
+/// A conversion and how many decimals to write the result with
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UnitTransform {
+    pub conversion: UnitConversion,
+    pub decimals: usize,
+}
+
+impl UnitTransform {
+    /// The converted value, or None when the cell isn't a number
+    pub fn apply(&self, value: &str) -> Option<String> {
+        let x: f64 = value.trim().parse().ok().filter(|x: &f64| x.is_finite())?;
+        let y = self.conversion.convert(x);
+        let text = format!("{:.*}", self.decimals, y);
+        // "-0.00" reads oddly for a value that rounded to zero
+        Some(match text.strip_prefix('-') {
+            Some(rest) if rest.chars().all(|c| c == '0' || c == '.') => rest.to_string(),
+            _ => text,
+        })
+    }
+}
+
+/// Edits converting every numeric value of a column; other values are left alone
+pub fn convert_edits(
+    col: usize,
+    rows: Range<usize>,
+    value_at: impl Fn(usize) -> String,
+    transform: &UnitTransform,
+    progress: &Progress,
+) -> Vec<CellEdit> {
+    progress.set_total(rows.len());
+    let start = rows.start;
+    let mut edits = Vec::new();
+    for row in rows {
+        if (row - start).is_multiple_of(10_000) {
+            if progress.is_cancelled() {
+                return Vec::new();
+            }
+            progress.set_done(row - start);
+        }
+        let value = value_at(row);
+        if let Some(converted) = transform.apply(&value)
+            && converted != value
+        {
+            edits.push((row, col, value, converted));
+        }
+    }
+    edits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unit_conversions() {
+        let mb = UnitTransform { conversion: UnitConversion::Scale { from: Unit::Kilobytes, to: Unit::Megabytes }, decimals: 2 };
+        assert_eq!(mb.apply("1536").as_deref(), Some("1.50"));
+        assert_eq!(mb.apply("n/a"), None);
+
+        let secs = UnitTransform { conversion: UnitConversion::Scale { from: Unit::Milliseconds, to: Unit::Seconds }, decimals: 3 };
+        assert_eq!(secs.apply(" 1250 ").as_deref(), Some("1.250"));
+
+        let celsius = UnitTransform { conversion: UnitConversion::FahrenheitToCelsius, decimals: 1 };
+        assert_eq!(celsius.apply("212").as_deref(), Some("100.0"));
+        assert_eq!(celsius.apply("32").as_deref(), Some("0.0"));
+        let fahrenheit = UnitTransform { conversion: UnitConversion::CelsiusToFahrenheit, decimals: 0 };
+        assert_eq!(fahrenheit.apply("-40").as_deref(), Some("-40"));
+
+        let euros = UnitTransform { conversion: UnitConversion::Rate(0.5), decimals: 2 };
+        let column = ["price", "10", "", "free", "3.5"];
+        let edits = convert_edits(0, 1..column.len(), |r| column[r].to_string(), &euros, &Progress::default());
+        let outputs: Vec<_> = edits.iter().map(|e| (e.0, e.3.as_str())).collect();
+        assert_eq!(outputs, [(1, "5.00"), (4, "1.75")]);
+    }
+}
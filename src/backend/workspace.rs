@@ -0,0 +1,289 @@
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use super::csvi::{is_csvi_file, load_csvi};
+use super::jobs::Progress;
+
+/// A CSV or CSVI file found under the workspace root
+#[derive(Clone, Debug)]
+pub struct WorkspaceFile {
+    pub path: PathBuf,
+    /// Path relative to the workspace root, for display
+    pub relative: String,
+    pub size: u64,
+    /// Data rows (excluding the header line); None if the file couldn't be read
+    pub rows: Option<usize>,
+}
+
+/// A folder opened as a workspace
+#[derive(Clone, Debug)]
+pub struct Workspace {
+    pub root: PathBuf,
+    pub files: Vec<WorkspaceFile>,
+}
+
+/// Find every CSV/CSVI file under `root` and count its rows
+pub fn scan_workspace(root: &Path, progress: &Progress) -> Result<Workspace> {
+    let mut paths = Vec::new();
+    collect_files(root, &mut paths).with_context(|| format!("Failed to read {}", root.display()))?;
+    paths.sort();
+
+    progress.set_total(paths.len());
+    let mut files = Vec::with_capacity(paths.len());
+    for path in paths {
+        if progress.is_cancelled() {
+            anyhow::bail!("Scan cancelled");
+        }
+        let size = std::fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        let rows = count_rows(&path).ok().map(|n| n.saturating_sub(1));
+        let relative = path.strip_prefix(root).unwrap_or(&path).to_string_lossy().into_owned();
+        files.push(WorkspaceFile { path, relative, size, rows });
+        progress.inc(1);
+    }
+    Ok(Workspace { root: root.to_path_buf(), files })
+}
+
+fn collect_files(dir: &Path, out: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+        let Ok(file_type) = entry.file_type() else { continue };
+        // Skip hidden folders like .git; symlinks are not followed to avoid cycles
+        if file_type.is_dir() && !entry.file_name().to_string_lossy().starts_with('.') {
+            // Unreadable subfolders are skipped rather than failing the whole scan
+            let _ = collect_files(&path, out);
+        } else if file_type.is_file() && is_table_file(&path) {
+            out.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn is_table_file(path: &Path) -> bool {
+    path.extension()
+        .map(|e| e.eq_ignore_ascii_case("csv") || e.eq_ignore_ascii_case("csvi"))
+        .unwrap_or(false)
+}
+
+/// Count records the way the loader indexes them: newlines outside quotes
+fn count_rows(path: &Path) -> Result<usize> {
+    if is_csvi_file(path) {
+        let (csv, _) = load_csvi(path)?;
+        return Ok(count_records(csv.as_bytes(), &mut false, &mut None));
+    }
+    let mut file = File::open(path)?;
+    let mut buf = vec![0u8; 256 * 1024];
+    let mut in_quote = false;
+    let mut last = None;
+    let mut count = 0;
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        count += count_records(&buf[..n], &mut in_quote, &mut last);
+    }
+    Ok(count)
+}
+
+/// Records started in `data`; `last` carries the previous byte across chunks
+fn count_records(data: &[u8], in_quote: &mut bool, last: &mut Option<u8>) -> usize {
+    let mut count = 0;
+    for &b in data {
+        if last.is_none() || (*last == Some(b'\n') && !*in_quote) {
+            count += 1;
+        }
+        if b == b'"' {
+            *in_quote = !*in_quote;
+        }
+        *last = Some(b);
+    }
+    count
+}
+
+/// Matches kept per file; the rest are counted but not listed
+pub const MAX_HITS_PER_FILE: usize = 200;
+
+/// Where a search looks
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchScope {
+    /// The document that's open, including unsaved edits
+    OpenFile,
+    /// Every file in the workspace, as saved on disk
+    Folder,
+}
+
+impl SearchScope {
+    pub const ALL: [SearchScope; 2] = [SearchScope::OpenFile, SearchScope::Folder];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SearchScope::OpenFile => "Open file",
+            SearchScope::Folder => "Folder",
+        }
+    }
+}
+
+/// A cell containing the search text; `row` counts data rows from 0
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchHit {
+    pub row: usize,
+    pub col: usize,
+    pub value: String,
+}
+
+/// Matches found in one file
+#[derive(Clone, Debug)]
+pub struct FileHits {
+    pub path: PathBuf,
+    pub relative: String,
+    pub hits: Vec<SearchHit>,
+    /// Matches beyond `MAX_HITS_PER_FILE`
+    pub more: usize,
+    /// Why the file couldn't be searched to the end, e.g. it isn't valid UTF-8
+    pub error: Option<String>,
+}
+
+impl FileHits {
+    /// Whether there's anything to list: a match, or a reason there might be missing ones
+    pub fn is_empty(&self) -> bool {
+        self.hits.is_empty() && self.error.is_none()
+    }
+}
+
+/// Case-insensitive search of data rows, skipping the header. A row that
+/// can't be read ends the search, keeping the matches before it; so does
+/// cancelling.
+pub fn search_rows(
+    path: PathBuf,
+    relative: String,
+    rows: impl Iterator<Item = Result<Vec<String>>>,
+    query: &str,
+    progress: &Progress,
+) -> FileHits {
+    let needle = query.to_lowercase();
+    let mut found = FileHits { path, relative, hits: Vec::new(), more: 0, error: None };
+    for (row, fields) in rows.enumerate() {
+        if row % 10_000 == 0 && progress.is_cancelled() {
+            found.error = Some("Search cancelled".to_string());
+            break;
+        }
+        let fields = match fields {
+            Ok(fields) => fields,
+            Err(e) => {
+                found.error = Some(format!("Stopped at row {}: {:#}", row + 1, e));
+                break;
+            }
+        };
+        for (col, value) in fields.into_iter().enumerate() {
+            if !value.to_lowercase().contains(&needle) {
+                continue;
+            }
+            if found.hits.len() < MAX_HITS_PER_FILE {
+                found.hits.push(SearchHit { row, col, value });
+            } else {
+                found.more += 1;
+            }
+        }
+    }
+    found
+}
+
+/// Search every file of the workspace, listing in order those with a match
+/// and those that couldn't be read
+pub fn search_workspace(workspace: &Workspace, query: &str, progress: &Progress) -> Result<Vec<FileHits>> {
+    progress.set_total(workspace.files.len());
+    let mut found = Vec::new();
+    for file in &workspace.files {
+        let hits = match data_rows(&file.path) {
+            Ok(rows) => search_rows(file.path.clone(), file.relative.clone(), rows, query, progress),
+            Err(e) => FileHits {
+                path: file.path.clone(),
+                relative: file.relative.clone(),
+                hits: Vec::new(),
+                more: 0,
+                error: Some(format!("{:#}", e)),
+            },
+        };
+        if progress.is_cancelled() {
+            anyhow::bail!("Search cancelled");
+        }
+        if !hits.is_empty() {
+            found.push(hits);
+        }
+        progress.inc(1);
+    }
+    Ok(found)
+}
+
+/// The records of a CSV or CSVI file after the header, read as they're asked for
+fn data_rows(path: &Path) -> Result<impl Iterator<Item = Result<Vec<String>>>> {
+    let input: Box<dyn Read> = if is_csvi_file(path) {
+        let (csv, _) = load_csvi(path)?;
+        Box::new(std::io::Cursor::new(csv.into_bytes()))
+    } else {
+        Box::new(File::open(path).with_context(|| format!("Failed to open {}", path.display()))?)
+    };
+    let reader = csv::ReaderBuilder::new().flexible(true).from_reader(input);
+    Ok(reader.into_records().map(|record| Ok(record?.iter().map(str::to_string).collect())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_scan_workspace() -> Result<()> {
+        let dir = TempDir::new()?;
+        std::fs::create_dir_all(dir.path().join("2024/q1"))?;
+        std::fs::create_dir_all(dir.path().join(".git"))?;
+        std::fs::write(dir.path().join("a.csv"), "id,note\n1,\"two\nlines\"\n2,x\n")?;
+        std::fs::write(dir.path().join("2024/q1/b.CSV"), "id\n1")?;
+        std::fs::write(dir.path().join("2024/readme.txt"), "not a table")?;
+        std::fs::write(dir.path().join(".git/c.csv"), "id\n")?;
+
+        let workspace = scan_workspace(dir.path(), &Progress::default())?;
+        let found: Vec<(String, Option<usize>)> = workspace.files.iter()
+            .map(|f| (f.relative.replace('\\', "/"), f.rows))
+            .collect();
+        assert_eq!(found, [("2024/q1/b.CSV".to_string(), Some(1)), ("a.csv".to_string(), Some(2))]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_workspace() -> Result<()> {
+        let dir = TempDir::new()?;
+        std::fs::write(dir.path().join("a.csv"), "city,country\nParis,France\nLyon,FRANCE\n")?;
+        std::fs::write(dir.path().join("b.csv"), "name\nBerlin\n")?;
+        std::fs::write(dir.path().join("c.csv"), "france\nRome\n")?;
+
+        let workspace = scan_workspace(dir.path(), &Progress::default())?;
+        let found = search_workspace(&workspace, "france", &Progress::default())?;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].relative, "a.csv");
+        let cells: Vec<(usize, usize)> = found[0].hits.iter().map(|hit| (hit.row, hit.col)).collect();
+        assert_eq!(cells, [(0, 1), (1, 1)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_reports_files_it_cannot_read() -> Result<()> {
+        let dir = TempDir::new()?;
+        std::fs::write(dir.path().join("a.csv"), b"name\nParis\nbad \xff\nParis again\n")?;
+        std::fs::write(dir.path().join("b.csv"), "name\nRome\n")?;
+
+        let workspace = scan_workspace(dir.path(), &Progress::default())?;
+        let found = search_workspace(&workspace, "paris", &Progress::default())?;
+        assert_eq!(found.len(), 1);
+        // The match before the bad row is kept, and the file says why it stopped
+        assert_eq!(found[0].hits.len(), 1);
+        assert!(found[0].error.as_deref().is_some_and(|e| e.starts_with("Stopped at row 2")));
+
+        let cancelled = Progress::default();
+        cancelled.cancel();
+        assert!(search_workspace(&workspace, "paris", &cancelled).is_err());
+        Ok(())
+    }
+}
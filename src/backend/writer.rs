@@ -0,0 +1,592 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use super::editor::DeltaBuffer;
+use super::jobs::Progress;
+use super::loader::CsvLoader;
+
+/// How fields are quoted when writing CSV
+#[derive(PartialEq, Clone, Copy, Serialize, Deserialize, Debug, Default)]
+pub enum QuoteStyle {
+    /// Quote only fields containing the delimiter, quotes or newlines
+    #[default]
+    Necessary,
+    Always,
+    NonNumeric,
+    Never,
+}
+
+impl QuoteStyle {
+    pub fn all() -> &'static [QuoteStyle] {
+        &[QuoteStyle::Necessary, QuoteStyle::Always, QuoteStyle::NonNumeric, QuoteStyle::Never]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            QuoteStyle::Necessary => "Minimal",
+            QuoteStyle::Always => "Always",
+            QuoteStyle::NonNumeric => "Non-numeric",
+            QuoteStyle::Never => "Never",
+        }
+    }
+
+    fn to_csv(self) -> csv::QuoteStyle {
+        match self {
+            QuoteStyle::Necessary => csv::QuoteStyle::Necessary,
+            QuoteStyle::Always => csv::QuoteStyle::Always,
+            QuoteStyle::NonNumeric => csv::QuoteStyle::NonNumeric,
+            QuoteStyle::Never => csv::QuoteStyle::Never,
+        }
+    }
+}
+
+/// How a quote inside a quoted field is escaped
+#[derive(PartialEq, Clone, Copy, Serialize, Deserialize, Debug, Default)]
+pub enum EscapeStyle {
+    /// `""`, as RFC 4180 has it
+    #[default]
+    Doubled,
+    /// `\"`, for parsers that expect C-style escapes
+    Backslash,
+}
+
+impl EscapeStyle {
+    pub fn all() -> &'static [EscapeStyle] {
+        &[EscapeStyle::Doubled, EscapeStyle::Backslash]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EscapeStyle::Doubled => "Doubled (\"\")",
+            EscapeStyle::Backslash => "Backslash (\\\")",
+        }
+    }
+
+    /// `field` ready for the writer. With backslash escapes a literal
+    /// backslash is doubled, so `\"` in the output always means a quote; the
+    /// writer then quotes the field for the backslash.
+    fn escape_field(self, field: &[u8]) -> Cow<'_, [u8]> {
+        if self == EscapeStyle::Doubled || !field.contains(&b'\\') {
+            return Cow::Borrowed(field);
+        }
+        let mut escaped = Vec::with_capacity(field.len() + 1);
+        for &b in field {
+            if b == b'\\' {
+                escaped.push(b'\\');
+            }
+            escaped.push(b);
+        }
+        Cow::Owned(escaped)
+    }
+}
+
+/// Output dialect for Save As
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CsvWriteOptions {
+    pub delimiter: u8,
+    /// Delimiter the source records are split on, for converting between dialects
+    #[serde(default = "default_delimiter")]
+    pub source_delimiter: u8,
+    pub quote_style: QuoteStyle,
+    #[serde(default)]
+    pub escape: EscapeStyle,
+    /// End the last record with a newline like every other one
+    #[serde(default = "default_true")]
+    pub trailing_newline: bool,
+    /// End records with `\r\n` instead of `\n`
+    #[serde(default)]
+    pub crlf: bool,
+}
+
+fn default_delimiter() -> u8 {
+    b','
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for CsvWriteOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            source_delimiter: b',',
+            quote_style: QuoteStyle::Necessary,
+            escape: EscapeStyle::Doubled,
+            trailing_newline: true,
+            crlf: false,
+        }
+    }
+}
+
+impl CsvWriteOptions {
+    /// The default dialect with the line endings `loader`'s file already
+    /// uses, so a save doesn't mix `\r\n` and `\n` or add a last newline
+    pub fn matching(loader: &CsvLoader) -> Self {
+        Self {
+            crlf: loader.uses_crlf(),
+            trailing_newline: loader.ends_with_newline(),
+            ..Self::default()
+        }
+    }
+
+    fn newline(&self) -> &'static [u8] {
+        if self.crlf { b"\r\n" } else { b"\n" }
+    }
+
+    /// Whether unedited records can be copied byte-for-byte from the source
+    fn preserves_source(&self) -> bool {
+        self.delimiter == self.source_delimiter
+            && self.quote_style == QuoteStyle::Necessary
+            && self.escape == EscapeStyle::Doubled
+    }
+
+    fn builder(&self) -> csv::WriterBuilder {
+        let mut builder = csv::WriterBuilder::new();
+        builder
+            .delimiter(self.delimiter)
+            .quote_style(self.quote_style.to_csv())
+            .double_quote(self.escape == EscapeStyle::Doubled)
+            .escape(b'\\')
+            .terminator(if self.crlf { csv::Terminator::CRLF } else { csv::Terminator::Any(b'\n') })
+            .flexible(true);
+        builder
+    }
+
+    /// A CSV writer in this dialect; wrap `out` in a `DeferredNewline` to
+    /// honor `trailing_newline`, and write through `write_record`
+    pub fn writer<W: Write>(&self, out: W) -> csv::Writer<W> {
+        self.builder().from_writer(out)
+    }
+
+    /// Write one record with `writer`, escaping its fields for this dialect
+    pub fn write_record<W: Write, T: AsRef<[u8]>>(&self, writer: &mut csv::Writer<W>, record: impl IntoIterator<Item = T>) -> csv::Result<()> {
+        let fields: Vec<T> = record.into_iter().collect();
+        writer.write_record(fields.iter().map(|field| self.escape.escape_field(field.as_ref())))
+    }
+
+    /// Encode in-memory rows as one CSV text
+    pub fn encode_rows<'a>(&self, rows: impl IntoIterator<Item = &'a [String]>) -> Result<String> {
+        self.encode_rows_between(b"", rows, b"")
+    }
+
+    /// Encode in-memory rows between the raw lines a file kept above and
+    /// below its table, which are re-encoded in this dialect too
+    pub fn encode_rows_between<'a>(
+        &self,
+        preamble: &[u8],
+        rows: impl IntoIterator<Item = &'a [String]>,
+        footer: &[u8],
+    ) -> Result<String> {
+        let mut out = DeferredNewline::new(Vec::new());
+        let mut encoder = RecordEncoder::new(self);
+        for line in split_records(preamble) {
+            encoder.write(&mut out, line, std::iter::empty())?;
+        }
+        {
+            let mut writer = self.writer(&mut out);
+            for row in rows {
+                self.write_record(&mut writer, row)?;
+            }
+            writer.flush()?;
+        }
+        for line in split_records(footer) {
+            encoder.write(&mut out, line, std::iter::empty())?;
+        }
+        let bytes = out.finish(self.trailing_newline)?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
+
+/// Holds back the newline at the end of each write until more data
+/// follows, so the last record's terminator can be dropped on request
+pub struct DeferredNewline<W: Write> {
+    inner: W,
+    held: &'static [u8],
+}
+
+impl<W: Write> DeferredNewline<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner, held: b"" }
+    }
+
+    /// Write the held newline if `trailing_newline`, and hand back the writer
+    pub fn finish(mut self, trailing_newline: bool) -> std::io::Result<W> {
+        if trailing_newline {
+            self.inner.write_all(self.held)?;
+        }
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for DeferredNewline<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        self.inner.write_all(self.held)?;
+        let (body, held): (&[u8], &'static [u8]) = if let Some(body) = buf.strip_suffix(b"\r\n") {
+            (body, b"\r\n")
+        } else if let Some(body) = buf.strip_suffix(b"\n") {
+            (body, b"\n")
+        } else {
+            (buf, b"")
+        };
+        self.inner.write_all(body)?;
+        self.held = held;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Stream a file-backed document to `path` without materializing it.
+///
+/// `rows` yields source record indices in output order, so callers can apply
+/// any row ordering or filtering on top. Pending cell edits from `edits` are
+/// merged in; untouched records are copied verbatim when the dialect allows.
+/// The output is written to a temporary file and renamed into place, which
+/// keeps saving over the currently mapped source file safe.
+pub fn save_loader_as(
+    loader: &CsvLoader,
+    edits: &DeltaBuffer,
+    rows: impl IntoIterator<Item = usize>,
+    path: &Path,
+    options: &CsvWriteOptions,
+    progress: &Progress,
+) -> Result<()> {
+    try_save_loader_as(loader, edits, rows.into_iter().map(Ok), path, options, progress)
+}
+
+/// Like [`save_loader_as`] for a row source that can fail partway (such as an
+/// external merge); the first error aborts the save before `path` is replaced
+pub fn try_save_loader_as(
+    loader: &CsvLoader,
+    edits: &DeltaBuffer,
+    rows: impl IntoIterator<Item = Result<usize>>,
+    path: &Path,
+    options: &CsvWriteOptions,
+    progress: &Progress,
+) -> Result<()> {
+    write_via_temp(path, |tmp_path| write_records(loader, edits, rows, tmp_path, options, progress))
+}
+
+/// Run `write` on a temporary file next to `path` and rename it into place
+/// once it succeeds, so a failed or cancelled write leaves `path` untouched
+pub fn write_via_temp<T>(path: &Path, write: impl FnOnce(&Path) -> Result<T>) -> Result<T> {
+    let tmp_path = temp_path_for(path);
+    match write(&tmp_path) {
+        Ok(value) => {
+            std::fs::rename(&tmp_path, path).context("Failed to move saved file into place")?;
+            Ok(value)
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(&tmp_path);
+            Err(e)
+        }
+    }
+}
+
+fn write_records(
+    loader: &CsvLoader,
+    edits: &DeltaBuffer,
+    rows: impl IntoIterator<Item = Result<usize>>,
+    tmp_path: &Path,
+    options: &CsvWriteOptions,
+    progress: &Progress,
+) -> Result<()> {
+    let file = File::create(tmp_path).context("Failed to create output file")?;
+    let mut out = DeferredNewline::new(BufWriter::new(file));
+    let mut encoder = RecordEncoder::new(options);
+    // Notes above the header go back out, in the output dialect like the rest
+    if let Some(preamble) = loader.preamble() {
+        for line in split_records(&preamble) {
+            encoder.write(&mut out, line, std::iter::empty())?;
+        }
+    }
+
+    progress.set_total(loader.total_records());
+    for (written, index) in rows.into_iter().enumerate() {
+        if written % 10_000 == 0 {
+            if progress.is_cancelled() {
+                anyhow::bail!("Save cancelled");
+            }
+            progress.set_done(written);
+        }
+
+        let index = index?;
+        if let Some(line) = loader.get_record_line(index) {
+            encoder.write(&mut out, &line, edits.row_edits(index))?;
+        }
+    }
+    // So do summary rows below them
+    if let Some(footer) = loader.footer() {
+        for line in split_records(&footer) {
+            encoder.write(&mut out, line, std::iter::empty())?;
+        }
+    }
+
+    out.finish(options.trailing_newline).context("Failed to flush output file")?;
+    Ok(())
+}
+
+/// Writes source records in the output dialect, merging in cell edits
+pub struct RecordEncoder {
+    builder: csv::WriterBuilder,
+    // Records that have to be re-encoded are formatted into a scratch buffer
+    scratch: Vec<u8>,
+    raw_copy: bool,
+    source_delimiter: u8,
+    escape: EscapeStyle,
+    newline: &'static [u8],
+}
+
+impl RecordEncoder {
+    pub fn new(options: &CsvWriteOptions) -> Self {
+        let mut builder = options.builder();
+        builder.buffer_capacity(1024);
+        Self {
+            builder,
+            scratch: Vec::new(),
+            raw_copy: options.preserves_source(),
+            source_delimiter: options.source_delimiter,
+            escape: options.escape,
+            newline: options.newline(),
+        }
+    }
+
+    /// Write one raw source record. Untouched records are copied verbatim
+    /// when the dialect allows; anything else is parsed and re-encoded.
+    pub fn write<'a>(
+        &mut self,
+        out: &mut impl Write,
+        line: &[u8],
+        row_edits: impl Iterator<Item = (usize, &'a String)>,
+    ) -> Result<()> {
+        let mut row_edits = row_edits.peekable();
+        // Blank lines (between a preamble and its table, say) stay blank
+        if !self.raw_copy && row_edits.peek().is_none() && line.iter().all(|b| matches!(b, b'\r' | b'\n')) {
+            out.write_all(self.newline)?;
+            return Ok(());
+        }
+        if self.raw_copy && row_edits.peek().is_none() {
+            out.write_all(line)?;
+            if !line.ends_with(b"\n") {
+                out.write_all(self.newline)?;
+            }
+            return Ok(());
+        }
+
+        let mut fields: Vec<Vec<u8>> = parse_record_with(line, self.source_delimiter)?;
+        for (col, value) in row_edits {
+            if col >= fields.len() {
+                fields.resize(col + 1, Vec::new());
+            }
+            fields[col] = value.as_bytes().to_vec();
+        }
+        {
+            let mut formatter = self.builder.from_writer(&mut self.scratch);
+            formatter.write_record(fields.iter().map(|field| self.escape.escape_field(field)))?;
+            formatter.flush()?;
+        }
+        out.write_all(&self.scratch)?;
+        self.scratch.clear();
+        Ok(())
+    }
+}
+
+/// Raw records in `bytes`, each with its line ending, split only on
+/// newlines outside quotes
+fn split_records(bytes: &[u8]) -> Vec<&[u8]> {
+    let mut records = Vec::new();
+    let mut start = 0;
+    let mut in_quote = false;
+    for (i, &b) in bytes.iter().enumerate() {
+        match b {
+            b'"' => in_quote = !in_quote,
+            b'\n' if !in_quote => {
+                records.push(&bytes[start..=i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if start < bytes.len() {
+        records.push(&bytes[start..]);
+    }
+    records
+}
+
+/// Split a raw comma-separated record into its unescaped fields
+pub fn parse_record(line: &[u8]) -> Result<Vec<Vec<u8>>> {
+    parse_record_with(line, b',')
+}
+
+/// Split a raw record on `delimiter` into its unescaped fields
+pub fn parse_record_with(line: &[u8], delimiter: u8) -> Result<Vec<Vec<u8>>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_reader(line);
+    let mut record = csv::ByteRecord::new();
+    reader.read_byte_record(&mut record)?;
+    Ok(record.iter().map(|f| f.to_vec()).collect())
+}
+
+pub fn temp_path_for(path: &Path) -> PathBuf {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| "output".to_string());
+    path.with_file_name(format!(".{}.csvit-tmp", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_save_with_edits_copies_untouched_rows() -> Result<()> {
+        let mut input = NamedTempFile::new()?;
+        write!(input, "a,b\r\n\"x, y\",2\r\n3,4")?;
+        let loader = CsvLoader::new(input.path())?;
+
+        let mut edits = DeltaBuffer::new();
+        edits.add_edit(2, 1, "4".to_string(), "four".to_string());
+
+        // The edited row ends in \r\n like the rest, and no newline is added at the end
+        let output = NamedTempFile::new()?;
+        let progress = Progress::default();
+        let options = CsvWriteOptions::matching(&loader);
+        assert!(options.crlf && !options.trailing_newline);
+        save_loader_as(&loader, &edits, 0..loader.total_records(), output.path(), &options, &progress)?;
+
+        let saved = std::fs::read_to_string(output.path())?;
+        assert_eq!(saved, "a,b\r\n\"x, y\",2\r\n3,four");
+        let mut edits = DeltaBuffer::new();
+        edits.add_edit(1, 1, "2".to_string(), "two".to_string());
+        save_loader_as(&loader, &edits, 0..loader.total_records(), output.path(), &options, &progress)?;
+        assert_eq!(std::fs::read_to_string(output.path())?, "a,b\r\n\"x, y\",two\r\n3,4");
+
+        // A preamble set aside is written back ahead of the rows
+        let loader = CsvLoader::new(input.path())?.with_preamble(1);
+        save_loader_as(&loader, &DeltaBuffer::new(), 0..loader.total_records(), output.path(), &options, &progress)?;
+        assert_eq!(std::fs::read_to_string(output.path())?, "a,b\r\n\"x, y\",2\r\n3,4");
+
+        // And a footer after them, whatever order the rows go out in, ending
+        // the way the source did
+        let loader = CsvLoader::new(input.path())?.with_footer(1);
+        save_loader_as(&loader, &DeltaBuffer::new(), [1, 0], output.path(), &options, &progress)?;
+        assert_eq!(std::fs::read_to_string(output.path())?, "\"x, y\",2\r\na,b\r\n3,4");
+        let newline = CsvWriteOptions { trailing_newline: true, ..options };
+        save_loader_as(&loader, &DeltaBuffer::new(), [1, 0], output.path(), &newline, &progress)?;
+        assert_eq!(std::fs::read_to_string(output.path())?, "\"x, y\",2\r\na,b\r\n3,4\r\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_row_source_leaves_target_untouched() -> Result<()> {
+        let mut input = NamedTempFile::new()?;
+        write!(input, "a,b\n1,2\n")?;
+        let loader = CsvLoader::new(input.path())?;
+
+        let output = NamedTempFile::new()?;
+        std::fs::write(output.path(), "keep me\n")?;
+        let rows = [Ok(0), Err(anyhow::anyhow!("merge failed"))];
+        let result = try_save_loader_as(&loader, &DeltaBuffer::new(), rows, output.path(), &CsvWriteOptions::default(), &Progress::default());
+        assert!(result.is_err());
+        assert_eq!(std::fs::read_to_string(output.path())?, "keep me\n");
+        assert!(!temp_path_for(output.path()).exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_save_with_other_delimiter() -> Result<()> {
+        let mut input = NamedTempFile::new()?;
+        write!(input, "a,b\n\"x; y\",2\n")?;
+        let loader = CsvLoader::new(input.path())?;
+
+        let output = NamedTempFile::new()?;
+        let options = CsvWriteOptions { delimiter: b';', ..Default::default() };
+        save_loader_as(&loader, &DeltaBuffer::new(), [1, 0], output.path(), &options, &Progress::default())?;
+
+        let saved = std::fs::read_to_string(output.path())?;
+        assert_eq!(saved, "\"x; y\";2\na;b\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_quoting_escape_and_trailing_newline() -> Result<()> {
+        let mut input = NamedTempFile::new()?;
+        write!(input, "id,note\n1,\"say \"\"hi\"\"\"\n2,plain\n")?;
+        let loader = CsvLoader::new(input.path())?;
+
+        let output = NamedTempFile::new()?;
+        let options = CsvWriteOptions {
+            quote_style: QuoteStyle::NonNumeric,
+            escape: EscapeStyle::Backslash,
+            trailing_newline: false,
+            ..Default::default()
+        };
+        save_loader_as(&loader, &DeltaBuffer::new(), 0..3, output.path(), &options, &Progress::default())?;
+        let saved = std::fs::read_to_string(output.path())?;
+        assert_eq!(saved, "\"id\",\"note\"\n1,\"say \\\"hi\\\"\"\n2,\"plain\"");
+
+        // Unedited rows are copied as-is, but the last newline still goes
+        let raw = CsvWriteOptions { trailing_newline: false, ..Default::default() };
+        save_loader_as(&loader, &DeltaBuffer::new(), 0..3, output.path(), &raw, &Progress::default())?;
+        assert_eq!(std::fs::read_to_string(output.path())?, "id,note\n1,\"say \"\"hi\"\"\"\n2,plain");
+
+        let rows = [vec!["a".to_string(), "b c".to_string()], vec!["1".to_string(), "x,y".to_string()]];
+        let always = CsvWriteOptions { quote_style: QuoteStyle::Always, ..Default::default() };
+        assert_eq!(always.encode_rows(rows.iter().map(Vec::as_slice))?, "\"a\",\"b c\"\n\"1\",\"x,y\"\n");
+
+        // A backslash of its own is doubled, so it can't be read as escaping the quote after it
+        let rows = [vec![r"C:\dir\".to_string(), r#"a\"b"#.to_string(), "plain".to_string()]];
+        let backslash = CsvWriteOptions { escape: EscapeStyle::Backslash, ..Default::default() };
+        assert_eq!(backslash.encode_rows(rows.iter().map(Vec::as_slice))?, r#""C:\\dir\\","a\\\"b",plain"#.to_string() + "\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_convert_semicolon_to_comma() -> Result<()> {
+        let mut input = NamedTempFile::new()?;
+        write!(input, "name;price\n\"Smith, J\";1,50\nplain;2\n")?;
+        let loader = CsvLoader::new(input.path())?;
+
+        let output = NamedTempFile::new()?;
+        let options = CsvWriteOptions { source_delimiter: b';', ..Default::default() };
+        save_loader_as(&loader, &DeltaBuffer::new(), 0..3, output.path(), &options, &Progress::default())?;
+        let saved = std::fs::read_to_string(output.path())?;
+        assert_eq!(saved, "name,price\n\"Smith, J\",\"1,50\"\nplain,2\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_preamble_and_footer_follow_the_output_dialect() -> Result<()> {
+        let mut input = NamedTempFile::new()?;
+        write!(input, "Run 1; ok\n\nname;price\nx;1,5\nTOTAL;1,5")?;
+        let loader = CsvLoader::new(input.path())?.with_preamble(2).with_footer(1);
+
+        let output = NamedTempFile::new()?;
+        let options = CsvWriteOptions { source_delimiter: b';', ..Default::default() };
+        save_loader_as(&loader, &DeltaBuffer::new(), 0..2, output.path(), &options, &Progress::default())?;
+        let converted = "Run 1, ok\n\nname,price\nx,\"1,5\"\nTOTAL,\"1,5\"\n";
+        assert_eq!(std::fs::read_to_string(output.path())?, converted);
+
+        let rows = [vec!["name".to_string(), "price".to_string()], vec!["x".to_string(), "1,5".to_string()]];
+        let preamble = loader.preamble().unwrap_or_default();
+        let footer = loader.footer().unwrap_or_default();
+        assert_eq!(options.encode_rows_between(&preamble, rows.iter().map(Vec::as_slice), &footer)?, converted);
+        Ok(())
+    }
+}
@@ -0,0 +1,304 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use super::analysis::InferredType;
+use super::export::json_value;
+use super::formatting::CellFormat;
+
+/// Excel's sheet limits
+const MAX_ROWS: usize = 1_048_576;
+const MAX_COLUMNS: usize = 16_384;
+
+const MAIN_NS: &str = "http://schemas.openxmlformats.org/spreadsheetml/2006/main";
+const REL_NS: &str = "http://schemas.openxmlformats.org/package/2006/relationships";
+const DOC_REL_NS: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+
+/// Font and fill of a styled cell; one `<xf>` is written per distinct key
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct StyleKey {
+    bold: bool,
+    italic: bool,
+    bg: Option<[u8; 3]>,
+    fg: Option<[u8; 3]>,
+}
+
+impl StyleKey {
+    fn from_format(format: &CellFormat) -> Self {
+        let rgb = |c: [u8; 4]| [c[0], c[1], c[2]];
+        Self {
+            bold: format.bold,
+            italic: format.italic,
+            bg: format.bg_color.map(rgb),
+            fg: format.text_color.map(rgb),
+        }
+    }
+}
+
+/// Streams one worksheet into an .xlsx file. Numbers and booleans are
+/// written as typed cells, and `CellFormat` colors and bold/italic become
+/// cell styles. Strings are stored inline, so nothing is buffered per row.
+pub struct XlsxWriter {
+    zip: ZipWriter<BufWriter<File>>,
+    options: SimpleFileOptions,
+    styles: Vec<StyleKey>,
+    style_ids: HashMap<StyleKey, usize>,
+    rows: usize,
+}
+
+impl XlsxWriter {
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let mut zip = ZipWriter::new(BufWriter::new(file));
+        let options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .large_file(true);
+        zip.start_file("xl/worksheets/sheet1.xml", options)?;
+        write!(
+            zip,
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><worksheet xmlns="{}"><sheetViews><sheetView workbookViewId="0"><pane ySplit="1" topLeftCell="A2" activePane="bottomLeft" state="frozen"/></sheetView></sheetViews><sheetData>"#,
+            MAIN_NS
+        )?;
+        Ok(Self { zip, options, styles: Vec::new(), style_ids: HashMap::new(), rows: 0 })
+    }
+
+    /// The header row, in bold and always as text
+    pub fn write_header(&mut self, headers: &[String]) -> Result<()> {
+        let bold = self.style_id(StyleKey::from_format(&CellFormat::bold()));
+        self.write_cells(headers, |_, value| Value::String(value.to_string()), |_| Some(bold))
+    }
+
+    /// One row of values; `types` decides which become numbers, `format_of(col)` styles a cell
+    pub fn write_row<'a>(
+        &mut self,
+        values: &[String],
+        types: &[Option<InferredType>],
+        format_of: impl Fn(usize) -> Option<&'a CellFormat>,
+    ) -> Result<()> {
+        let styles: Vec<Option<usize>> = (0..values.len())
+            .map(|col| format_of(col).map(|format| self.style_id(StyleKey::from_format(format))))
+            .collect();
+        self.write_cells(
+            values,
+            |col, value| json_value(value, types.get(col).and_then(Option::as_ref)),
+            |col| styles[col],
+        )
+    }
+
+    fn write_cells(
+        &mut self,
+        values: &[String],
+        cell_value: impl Fn(usize, &str) -> Value,
+        style_of: impl Fn(usize) -> Option<usize>,
+    ) -> Result<()> {
+        if self.rows >= MAX_ROWS {
+            anyhow::bail!("Excel sheets hold at most {} rows", MAX_ROWS);
+        }
+        if values.len() > MAX_COLUMNS {
+            anyhow::bail!("Excel sheets hold at most {} columns", MAX_COLUMNS);
+        }
+        self.rows += 1;
+        let row = self.rows;
+        write!(self.zip, r#"<row r="{}">"#, row)?;
+        for (col, value) in values.iter().enumerate() {
+            let style = style_of(col);
+            let style_attr = style.map(|s| format!(r#" s="{}""#, s)).unwrap_or_default();
+            let cell = format!("{}{}", column_name(col), row);
+            match cell_value(col, value) {
+                // Null-like text such as "NA" is still data; only empty cells are left out
+                Value::Null if value.trim().is_empty() && style.is_none() => {}
+                Value::Null if value.trim().is_empty() => write!(self.zip, r#"<c r="{}"{}/>"#, cell, style_attr)?,
+                Value::Number(n) => write!(self.zip, r#"<c r="{}"{}><v>{}</v></c>"#, cell, style_attr, n)?,
+                Value::Bool(b) => write!(self.zip, r#"<c r="{}"{} t="b"><v>{}</v></c>"#, cell, style_attr, u8::from(b))?,
+                _ => write!(
+                    self.zip,
+                    r#"<c r="{}"{} t="inlineStr"><is><t xml:space="preserve">{}</t></is></c>"#,
+                    cell,
+                    style_attr,
+                    escape_xml(value)
+                )?,
+            }
+        }
+        self.zip.write_all(b"</row>")?;
+        Ok(())
+    }
+
+    /// Close the sheet and write the workbook parts around it
+    pub fn finish(mut self) -> Result<()> {
+        self.zip.write_all(b"</sheetData></worksheet>")?;
+        let styles = self.styles_xml();
+        let parts = [
+            ("[Content_Types].xml", content_types_xml()),
+            ("_rels/.rels", format!(
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><Relationships xmlns="{}"><Relationship Id="rId1" Type="{}/officeDocument" Target="xl/workbook.xml"/></Relationships>"#,
+                REL_NS, DOC_REL_NS
+            )),
+            ("xl/workbook.xml", format!(
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><workbook xmlns="{}" xmlns:r="{}"><sheets><sheet name="Sheet1" sheetId="1" r:id="rId1"/></sheets></workbook>"#,
+                MAIN_NS, DOC_REL_NS
+            )),
+            ("xl/_rels/workbook.xml.rels", format!(
+                r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><Relationships xmlns="{}"><Relationship Id="rId1" Type="{}/worksheet" Target="worksheets/sheet1.xml"/><Relationship Id="rId2" Type="{}/styles" Target="styles.xml"/></Relationships>"#,
+                REL_NS, DOC_REL_NS, DOC_REL_NS
+            )),
+            ("xl/styles.xml", styles),
+        ];
+        for (name, xml) in parts {
+            self.zip.start_file(name, self.options)?;
+            self.zip.write_all(xml.as_bytes())?;
+        }
+        self.zip.finish()?.flush().context("Failed to write .xlsx file")?;
+        Ok(())
+    }
+
+    /// Index into cellXfs; 0 is the unstyled default
+    fn style_id(&mut self, key: StyleKey) -> usize {
+        *self.style_ids.entry(key).or_insert_with(|| {
+            self.styles.push(key);
+            self.styles.len()
+        })
+    }
+
+    /// One font, fill and xf per style, after Excel's required defaults
+    fn styles_xml(&self) -> String {
+        let argb = |c: [u8; 3]| format!("FF{:02X}{:02X}{:02X}", c[0], c[1], c[2]);
+        let mut fonts = String::from(r#"<font><sz val="11"/><name val="Calibri"/></font>"#);
+        // Fills 0 and 1 are reserved by Excel
+        let mut fills = String::from(r#"<fill><patternFill patternType="none"/></fill><fill><patternFill patternType="gray125"/></fill>"#);
+        let mut fill_count = 2;
+        let mut xfs = String::from(r#"<xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0"/>"#);
+        for (i, style) in self.styles.iter().enumerate() {
+            fonts.push_str("<font>");
+            if style.bold {
+                fonts.push_str("<b/>");
+            }
+            if style.italic {
+                fonts.push_str("<i/>");
+            }
+            fonts.push_str(r#"<sz val="11"/>"#);
+            if let Some(fg) = style.fg {
+                fonts.push_str(&format!(r#"<color rgb="{}"/>"#, argb(fg)));
+            }
+            fonts.push_str(r#"<name val="Calibri"/></font>"#);
+            let fill_id = match style.bg {
+                Some(bg) => {
+                    fills.push_str(&format!(r#"<fill><patternFill patternType="solid"><fgColor rgb="{}"/></patternFill></fill>"#, argb(bg)));
+                    fill_count += 1;
+                    fill_count - 1
+                }
+                None => 0,
+            };
+            xfs.push_str(&format!(
+                r#"<xf numFmtId="0" fontId="{}" fillId="{}" borderId="0" xfId="0" applyFont="1" applyFill="1"/>"#,
+                i + 1,
+                fill_id
+            ));
+        }
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><styleSheet xmlns="{}"><fonts count="{}">{}</fonts><fills count="{}">{}</fills><borders count="1"><border/></borders><cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs><cellXfs count="{}">{}</cellXfs></styleSheet>"#,
+            MAIN_NS,
+            self.styles.len() + 1,
+            fonts,
+            fill_count,
+            fills,
+            self.styles.len() + 1,
+            xfs
+        )
+    }
+}
+
+fn content_types_xml() -> String {
+    let sheetml = "application/vnd.openxmlformats-officedocument.spreadsheetml";
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?><Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="{0}.sheet.main+xml"/><Override PartName="/xl/worksheets/sheet1.xml" ContentType="{0}.worksheet+xml"/><Override PartName="/xl/styles.xml" ContentType="{0}.styles+xml"/></Types>"#,
+        sheetml
+    )
+}
+
+/// Spreadsheet column letters: 0 is A, 25 is Z, 26 is AA
+fn column_name(mut col: usize) -> String {
+    let mut name = Vec::new();
+    loop {
+        name.push(b'A' + (col % 26) as u8);
+        if col < 26 {
+            break;
+        }
+        col = col / 26 - 1;
+    }
+    name.reverse();
+    String::from_utf8(name).expect("ASCII letters")
+}
+
+/// Escape text for an XML element, dropping control characters XML can't carry
+fn escape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '\t' | '\n' | '\r' => out.push(c),
+            c if (c as u32) < 0x20 => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_xlsx_typed_and_styled_cells() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("out.xlsx");
+        let highlight = CellFormat::with_bg([255, 235, 59, 255]);
+
+        let mut writer = XlsxWriter::create(&path)?;
+        writer.write_header(&["id".to_string(), "note".to_string()])?;
+        let types = [Some(InferredType::Integer), None];
+        writer.write_row(&["7".to_string(), "a < b & c".to_string()], &types, |col| (col == 1).then_some(&highlight))?;
+        writer.finish()?;
+
+        let mut archive = zip::ZipArchive::new(File::open(&path)?)?;
+        let mut sheet = String::new();
+        archive.by_name("xl/worksheets/sheet1.xml")?.read_to_string(&mut sheet)?;
+        assert!(sheet.contains(r#"<c r="A1" s="1" t="inlineStr"><is><t xml:space="preserve">id</t></is></c>"#));
+        assert!(sheet.contains(r#"<c r="A2"><v>7</v></c>"#));
+        assert!(sheet.contains(r#"<c r="B2" s="2" t="inlineStr"><is><t xml:space="preserve">a &lt; b &amp; c</t></is></c>"#));
+        let mut styles = String::new();
+        archive.by_name("xl/styles.xml")?.read_to_string(&mut styles)?;
+        assert!(styles.contains(r#"<fgColor rgb="FFFFEB3B"/>"#));
+        assert!(styles.contains(r#"<cellXfs count="3">"#));
+
+        assert_eq!((column_name(0), column_name(25), column_name(26), column_name(701)), ("A".into(), "Z".into(), "AA".into(), "ZZ".into()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_xlsx_keeps_null_like_text() -> Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("out.xlsx");
+
+        let mut writer = XlsxWriter::create(&path)?;
+        writer.write_header(&["country".to_string(), "note".to_string()])?;
+        let types = [Some(InferredType::Text), Some(InferredType::Text)];
+        writer.write_row(&["NA".to_string(), String::new()], &types, |_| None)?;
+        writer.finish()?;
+
+        let mut archive = zip::ZipArchive::new(File::open(&path)?)?;
+        let mut sheet = String::new();
+        archive.by_name("xl/worksheets/sheet1.xml")?.read_to_string(&mut sheet)?;
+        assert!(sheet.contains(r#"<c r="A2" t="inlineStr"><is><t xml:space="preserve">NA</t></is></c>"#));
+        assert!(!sheet.contains(r#"r="B2""#));
+        Ok(())
+    }
+}
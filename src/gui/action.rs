@@ -0,0 +1,125 @@
+use crate::backend::analysis::SemanticType;
+use crate::backend::export::ExportPreset;
+use crate::backend::filter::FilterCondition;
+use crate::backend::sort::SortOrder;
+
+/// Something the user asked for from a menu, a button or a shortcut.
+/// Views queue these instead of changing state while they draw; the app
+/// applies them in order once the frame's UI is built (see `GuiApp::dispatch`).
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    NewFile,
+    OpenFile,
+    /// Open a file as its own document, e.g. a result written to disk
+    OpenPath(std::path::PathBuf),
+    OpenFolder,
+    /// Import an XML record file or a table from an HTML page
+    Import,
+    ImportFixedWidth,
+    OpenSettings,
+    /// Open rows as a new in-memory document named `filename`
+    OpenTable { filename: String, headers: Vec<String>, rows: Vec<Vec<String>> },
+    /// Keep the document's columns, types and rules as a template for File → New CSV
+    SaveAsTemplate(String),
+    ToggleProfileHud,
+    /// Show one of the document's dialogs, loaded for what it works on
+    Open(Dialog),
+    SaveAs,
+    Print,
+    FileInfo,
+    RunExportPreset(ExportPreset),
+    /// Compare the document with the file's last committed version
+    DiffAgainstHead,
+    /// Index the file again after it changed on disk
+    Reload,
+    AttachSchema,
+    ValidateSchema,
+    SaveSchema,
+    GenerateSchema,
+    /// Copy the visible columns to the clipboard: every row, or the first this many
+    CopyTable(Option<usize>),
+    OpenInPandas,
+    EditCell { row: usize, col: usize, value: String },
+    /// Open the editor on a cell, starting from `text`
+    StartEdit { row: usize, col: usize, text: String },
+    InspectCell { row: usize, col: usize },
+    /// Clipboard text to paste at the selection, or into Paste Special
+    Paste { text: String, special: bool },
+    /// Paste Special at a cell, from the system clipboard
+    PasteSpecialAt { row: usize, col: usize },
+    /// Add a row or column after the selection, or at the end
+    AddRow,
+    DeleteRow,
+    AddColumn,
+    DeleteColumn,
+    Transpose,
+    /// Add the rows of another CSV, matching its columns by name
+    AppendFile,
+    ConvertToGrid,
+    FollowFile(bool),
+    /// Set aside this many lines above the header
+    SetPreamble(usize),
+    Sort { col: usize, order: SortOrder },
+    Filter(FilterCondition),
+    ClearFilter,
+    ToggleBookmark(usize),
+    /// Keep the last this many rows out of the table as a footer
+    SetFooter(usize),
+    /// Keep a row in view above the others to compare against, or stop
+    PinBaseline(Option<usize>),
+    /// Figures for the selected block of cells, in a small overlay
+    QuickCalc,
+    FindDuplicates(usize),
+    FindKeyCandidates,
+    ListInvalidValues { col: usize, semantic: SemanticType },
+    /// Profile every row of a column rather than a sample
+    ProfileAllRows(usize),
+    ScanProblems,
+    /// Tint a column's numbers from its minimum to its maximum
+    ColorScale { col: usize, on: bool },
+    Histogram(usize),
+    /// Show a row as a JSON object, or rows from it as JSON Lines
+    ViewJson { row: usize, lines: bool },
+    GoToByteOffset,
+    ExportSelectedLines,
+    /// Bytes of a text-view line, in a hex dump
+    HexDump(usize),
+    ExportPlottedData,
+    FilterToBrushed,
+    /// Widen (or with a negative delta, narrow) a column by some points
+    ResizeColumn { col: usize, delta: f32 },
+    /// Back to automatic widths for every column
+    ResetWidths,
+    /// Make every row taller or shorter, for this file only
+    ResizeRows(f32),
+    Undo,
+    Redo,
+}
+
+/// The document's dialogs and panels that `Action::Open` can show
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Dialog {
+    RowColors,
+    SchemaIssues,
+    RowRules,
+    ColumnPicker,
+    Split,
+    Script,
+    Export,
+    Snapshots,
+    FileTables,
+    GroupBy,
+    ScatterMatrix,
+    GeoCheck,
+    Pipeline,
+    Distinct(usize),
+    Normalize(usize),
+    Anonymize(usize),
+    DateFormat(usize),
+    Units(usize),
+    RegexExtract(usize),
+    Rolling(usize),
+    /// Difference or ratio with another column
+    Combine(usize),
+    NumberFormat(usize),
+}
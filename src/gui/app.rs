@@ -1,14 +1,149 @@
 use std::sync::Arc;
 use eframe::egui;
 use egui_extras::{Column, TableBuilder};
-use crate::backend::loader::CsvLoader;
-use crate::backend::paged_reader::PagedReader;
-use crate::backend::editor::EditBuffer;
+use crate::backend::loader::{Append, CsvLoader, Reindex, TableSpan};
+use crate::backend::document::{ColumnReader, CsvDocument, IndexedFileDocument, RowReader};
+use crate::backend::preview::{self, FilePreview};
+use crate::backend::editor::{CellEdit, EditBuffer};
+use crate::backend::file_info::{self, FileStamp};
+use crate::backend::formatting::{ColumnFormat, FormatMap, RowColorRule, RowColors};
 use crate::backend::parser::CsvParser;
-use crate::backend::analysis::{ColumnAnalyzer, ColumnProfile};
-use crate::backend::settings::{Settings, Theme, KeybindingMode};
+use crate::backend::perf::FrameStats;
+use crate::backend::analysis::{ColumnAnalyzer, ColumnProfile, ColumnSample, DistinctValues, DuplicateReport, Histogram, InferredType, InvalidValues, KeyCandidates, SemanticType};
+use crate::backend::anonymize::{self, MaskTransform};
+use crate::backend::normalize::{self, ValueCluster};
+use crate::backend::profile_cache::{self, ProfileCache};
+use crate::backend::rebase::{rebase_edits, EditConflict, Rebase, RowMatch};
+use crate::backend::schema::{SchemaIssue, TableSchema};
+use crate::backend::workspace::{self, FileHits, SearchHit, SearchScope, Workspace};
+use crate::backend::scripting::{ScriptChanges, ScriptInput, ScriptOutcome};
+use crate::backend::git::{self, GitStatus};
+use crate::backend::snapshots::{self, Snapshot, SnapshotDiff};
+use crate::backend::sort::{self, SortOrder};
+use crate::backend::templates::{self, Template};
+use crate::backend::settings::{EnterDirection, Settings, Theme, KeybindingMode};
+use crate::backend::jobs::{Job, Progress};
+use crate::backend::writer::CsvWriteOptions;
+use crate::backend::memory::{self, MemoryWarning};
+use crate::backend::clipboard::{self, PasteMode};
+use crate::gui::action::{Action, Dialog};
+use crate::gui::windows::export::ExportRequest;
+use crate::gui::windows::pipeline::PipelineRequest;
+use crate::gui::windows::geo::GeoRequest;
+use crate::gui::windows::distinct::DistinctRequest;
+use crate::gui::windows::group_by::GroupByRequest;
+use crate::gui::windows::rolling::RollingRequest;
+use crate::gui::windows::combine::CombineRequest;
+use crate::gui::windows::snapshots::SnapshotRequest;
+use crate::gui::windows::scatter_matrix::MATRIX_SAMPLE_ROWS;
+use crate::backend::aggregate::{self, Aggregate, GroupBySpec, GroupSummary, SelectionCounter, SelectionStats, TimeBucket};
+use crate::backend::dates;
+use crate::backend::problems::{Problem, ProblemCategory, Severity};
+use crate::backend::filter::{FilterCondition, FilterTest, RowFilter};
+use crate::backend::audit::AuditAction;
+use crate::gui::windows::normalize::NormalizeAction;
+use crate::gui::vim::{self, RegisterContent, VimAction, VimMode, VimState, UNNAMED_REGISTER};
 use directories::ProjectDirs;
 
+/// Where a streaming save went, and a mapping of it to switch the document
+/// to; None when it was written in a dialect the editor can't read back
+type SaveOutcome = anyhow::Result<(std::path::PathBuf, Option<CsvLoader>)>;
+/// A header sparkline with the column generation and row count it was drawn from
+type Sparkline = (u64, usize, Vec<Option<f64>>);
+/// X and Y columns with their edit generations, the row count, the filter
+/// generation, the time buckets points are resampled into and the
+/// histogram's bin count
+type GraphSource = ((usize, u64), (usize, u64), usize, u64, Option<(TimeBucket, Aggregate)>, Option<usize>);
+/// A rolling column worked out in the background: the columns it read with
+/// their edit generations, then the new column's header and values
+type RollingColumn = (Vec<(usize, u64)>, String, Vec<String>);
+/// The document's headers and how it differs from the last commit
+type HeadDiff = (Vec<String>, SnapshotDiff);
+/// What a Follow File scan found on disk
+enum FollowUpdate {
+    /// Rows were appended; only the new bytes were scanned
+    Appended(Append),
+    /// The file was rewritten and indexed again
+    Rewritten(CsvLoader),
+    /// The file was rewritten while there are unsaved edits, so it wasn't read
+    Conflict,
+}
+
+/// What the graph view read from its columns in the background
+struct GraphRead {
+    /// The first `GRAPH_MAX_ROWS` points, with their rows
+    data: Vec<[f64; 2]>,
+    rows: Vec<usize>,
+    x_time: bool,
+    /// Every point of the column summarized by time bucket, when resampling
+    resampled: Option<Vec<[f64; 2]>>,
+    /// Every Y number of the column binned, when drawing the histogram, and how many there were
+    bars: Option<(Histogram, usize)>,
+}
+
+/// A snapshot read back for restoring, with its header and data rows
+type RestoredSnapshot = (Snapshot, Vec<String>, Vec<Vec<String>>);
+
+/// Rows read for the graph view
+const GRAPH_MAX_ROWS: usize = 5_000;
+
+/// Upper bound on rows gathered by "Copy Column"
+const COPY_COLUMN_LIMIT: usize = 1_000_000;
+
+/// Upper bound on data rows placed on the clipboard by "Copy Table"
+const COPY_TABLE_LIMIT: usize = 100_000;
+
+/// Rows shown by default in the JSON Lines view, and the most it builds at once
+const JSON_LINES_ROWS: usize = 100;
+const JSON_LINES_LIMIT: usize = 10_000;
+
+/// Rows sampled to preview a column transform (and detect date formats)
+const TRANSFORM_SAMPLE_ROWS: usize = 1_000;
+
+/// Tallest an in-cell edit grows its row, in lines
+const MAX_EDIT_LINES: usize = 10;
+
+/// Files wider than this open with the column picker
+const PROJECTION_PROMPT_COLUMNS: usize = 50;
+
+/// Auto-sized columns stay within these widths; longer values end in "…"
+const MIN_AUTO_COLUMN_WIDTH: f32 = 50.0;
+const MAX_AUTO_COLUMN_WIDTH: f32 = 400.0;
+/// Cells Quick Calc reads from a selection before it stops counting
+const QUICK_CALC_MAX_CELLS: usize = 1_000_000;
+
+/// Selections up to this many cells are counted straight away; larger ones in the background
+const QUICK_CALC_INLINE_CELLS: usize = 10_000;
+
+/// Preamble text shown in its banner before the rest is cut off
+const PREAMBLE_DISPLAY_BYTES: usize = 4096;
+
+/// Width of a column before its profile arrives
+const DEFAULT_COLUMN_WIDTH: f32 = 100.0;
+
+/// Height of the column headers, not counting a pinned baseline row below them
+const HEADER_HEIGHT: f32 = 30.0;
+
+/// Keyboard resizing steps, and the limits it stays within; rows match the Settings slider
+const COLUMN_RESIZE_STEP: f32 = 10.0;
+const ROW_RESIZE_STEP: f32 = 2.0;
+const MIN_COLUMN_WIDTH: f32 = 20.0;
+const MIN_ROW_HEIGHT: f32 = 20.0;
+const MAX_ROW_HEIGHT: f32 = 60.0;
+
+/// Seconds between asking git for the open file's status
+const GIT_STATUS_INTERVAL: f64 = 5.0;
+
+/// Edited rows remembered as they were, for carrying edits over a reload
+const EDIT_BASE_ROWS: usize = 100_000;
+
+/// Minimap tick colors, also listed in its tooltip
+const MINIMAP_COLORS: [(&str, egui::Color32); 3] = [
+    ("Edited", egui::Color32::from_rgb(255, 180, 80)),
+    ("Validation issue", egui::Color32::from_rgb(220, 80, 60)),
+    ("Bookmark", egui::Color32::from_rgb(100, 160, 255)),
+];
+
 #[derive(PartialEq)]
 pub enum ViewMode {
     Table,
@@ -16,1020 +151,7138 @@ pub enum ViewMode {
     Graph,
 }
 
-/// Vim-like editor modes (only active when keybinding_mode is Vim)
-#[derive(PartialEq, Clone, Copy, Default)]
-pub enum VimMode {
-    #[default]
-    Normal,
-    Insert,
-    Visual,
-    Command,
+/// A line's hex dump, built once when its window opens rather than every frame
+struct HexDump {
+    line: usize,
+    offset: Option<u64>,
+    /// Where the line starts in the file, and its length
+    start: u64,
+    len: usize,
+    /// How far the byte asked for is into the line, and its value
+    byte: Option<(u64, u8)>,
+    /// Bytes of the line in the dump
+    shown: usize,
+    text: String,
+}
+
+/// Row(s) shown in the JSON view: one object, or `count` rows as JSON Lines
+struct JsonView {
+    first: usize,
+    count: usize,
+    lines: bool,
+    text: String,
+}
+
+/// Who gets the keyboard this frame. Table shortcuts only run in `Grid`, and
+/// app-wide ones are held back while an editor or dialog owns Enter/Escape.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum InputScope {
+    Grid,
+    /// A search box or other text field has focus
+    TextField,
+    /// A cell is being edited in place
+    CellEditor,
+    /// The edit modal or a dialog waiting on the user
+    Modal,
+}
+
+impl InputScope {
+    fn allows_global_shortcuts(self) -> bool {
+        matches!(self, InputScope::Grid | InputScope::TextField)
+    }
+}
+
+/// Work that loads a whole file or column into memory, checked against the budget first
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum MemoryHeavy {
+    ConvertToGrid,
+    ProfileAllRows(usize),
+}
+
+/// The popup cell editor: the value when it opened, and the text being edited
+struct EditModal {
+    row: usize,
+    col: usize,
+    original: String,
+    text: String,
+}
+
+impl EditModal {
+    fn new(row: usize, col: usize, value: String) -> Self {
+        Self { row, col, original: value.clone(), text: value }
+    }
+}
+
+/// Figures for a block of selected cells, shown until dismissed
+struct QuickCalc {
+    rows: usize,
+    cols: usize,
+    stats: SelectionStats,
+    /// Only the first `QUICK_CALC_MAX_CELLS` were counted
+    truncated: bool,
 }
 
 pub struct EditorState {
-    loader: Arc<CsvLoader>,
-    reader: PagedReader,
-    editor: EditBuffer,
+    // The file as indexed on disk, with its pending edits; backs the document until it's loaded into a grid
+    file: IndexedFileDocument,
     view_mode: ViewMode,
     input_buffer: String,
     editing_cell: Option<(usize, usize)>,
     filename: String,
     word_wrap: bool,
-    json_modal: Option<(usize, String)>,
+    json_view: Option<JsonView>,
     num_columns: usize,
     column_widths: Vec<f32>,
+    // Columns the user sized (or whose saved widths were restored); the rest follow their profile
+    manual_widths: std::collections::HashSet<usize>,
+    // Profile generation each auto-sized column was last sized from
+    auto_width_generations: std::collections::HashMap<usize, u64>,
+    // Set when a resize changed column_widths or row_height, and saved once the pointer is released
+    widths_dirty: bool,
+    // Row height set for this file from the keyboard, over the one in settings
+    row_height: Option<f32>,
+    // Makes the table drop its own width state and start from column_widths again
+    reset_widths: bool,
     selected_cell: Option<(usize, usize)>,
-    edit_modal: Option<(usize, usize, String)>,
+    // Opposite corner of a block selection, set by Shift+click or Shift+arrows
+    selection_anchor: Option<(usize, usize)>,
+    quick_calc: Option<QuickCalc>,
+    quick_calc_job: Option<Job<Option<QuickCalc>>>,
+    // Row pinned under the headers while scrolling, and whether numeric
+    // cells show how far they are from it
+    baseline_row: Option<usize>,
+    baseline_deltas: bool,
+    edit_modal: Option<EditModal>,
     // Graph state
     graph_x_col: usize,
     graph_y_col: usize,
     graph_data: Vec<[f64; 2]>,
+    // Columns, their edit generations and the row count graph_data was read for
+    graph_source: Option<GraphSource>,
+    graph_job: Option<(GraphSource, Job<Option<GraphRead>>)>,
+    // Row of each point in graph_data
+    graph_rows: Vec<usize>,
+    // X holds timestamps, plotted as Unix seconds
+    graph_x_time: bool,
+    // Time buckets to summarize a timestamp X by, and how
+    graph_resample: Option<TimeBucket>,
+    graph_resample_aggregate: Aggregate,
+    // What's drawn: graph_data, or its resampled summary
+    graph_plot: Vec<[f64; 2]>,
+    // Overlay the Y column's profile statistics
+    graph_stats: bool,
+    // Plot the Y column's distribution instead of Y against X
+    graph_histogram: bool,
+    graph_bins: usize,
+    // How many numbers the Y column holds, whether or not X parsed, binned into graph_bars
+    graph_value_count: usize,
+    graph_bars: Histogram,
+    // Dragging picks points instead of panning
+    graph_brush: bool,
+    // Corners of the box being dragged, in plot coordinates
+    graph_brush_box: Option<([f64; 2], [f64; 2])>,
+    // Indices into graph_data of the points inside the last brushed box
+    graph_brushed: Vec<usize>,
     // In-memory grid for new/edited files
     grid: Option<crate::backend::grid::EditableGrid>,
     // Column profile for HUD
-    column_profile: Option<ColumnProfile>,
+    profile_column: Option<usize>,
+    // Whole column selected by clicking its header
+    selected_column: Option<usize>,
+    profile_cache: ProfileCache,
+    // Text view: "go to byte offset" input, a line to scroll to, and the
+    // line shown in the hex dump with the byte asked for, if any
+    byte_offset_draft: String,
+    text_scroll_to: Option<usize>,
+    // Lines picked in the text view: the one clicked first, and the one shift-clicked
+    text_selection: Option<(usize, usize)>,
+    hex_line: Option<(usize, Option<u64>)>,
+    hex_dump: Option<HexDump>,
+    // Most common line ending among the first lines, for the text view's badges
+    text_line_ending: Option<file_info::LineEnding>,
+    // Trend lines in numeric column headers
+    sparklines: std::collections::HashMap<usize, Sparkline>,
+    sparkline_job: Option<Job<Vec<(usize, Sparkline)>>>,
     // Vim mode state
-    vim_mode: VimMode,
-    command_buffer: String,
+    vim: VimState,
+    // Transient feedback shown in the status bar
+    status_message: Option<String>,
+    // Streaming Save As for file-backed documents
+    save_as_window: crate::gui::windows::save_as::SaveAsWindow,
+    show_save_as: bool,
+    save_job: Option<Job<SaveOutcome>>,
+    // Paste Special dialog
+    paste_window: crate::gui::windows::paste_special::PasteSpecialWindow,
+    // Saved copies of the document under the app's data folder
+    snapshots_window: crate::gui::windows::snapshots::SnapshotsWindow,
+    show_snapshots: bool,
+    snapshot_job: Option<Job<anyhow::Result<Snapshot>>>,
+    snapshot_diff_job: Option<Job<anyhow::Result<(String, SnapshotDiff)>>>,
+    snapshot_restore_job: Option<Job<anyhow::Result<RestoredSnapshot>>>,
+    // The file's state in its git repository, if it's in one, and changes since HEAD
+    git_status: Option<GitStatus>,
+    git_status_job: Option<Job<Option<GitStatus>>>,
+    git_checked_at: f64,
+    git_diff_job: Option<Job<anyhow::Result<HeadDiff>>>,
+    git_diff: Option<HeadDiff>,
+    show_git_diff: bool,
+    // Lines up the columns of appended or pasted rows with the document's
+    column_mapping_window: crate::gui::windows::column_mapping::ColumnMappingWindow,
+    show_column_mapping: bool,
+    show_paste_special: bool,
+    // Data → Transpose option
+    transpose_first_column_headers: bool,
+    // Columns rendered with a min→max color scale
+    heatmap_columns: std::collections::HashSet<usize>,
+    // Duplicate-value analysis for one column
+    duplicates: Option<DuplicateReport>,
+    duplicate_job: Option<Job<DuplicateReport>>,
+    // Primary-key candidates (single columns or column pairs), with every
+    // column's edit generation when they were found
+    key_candidates: Option<(Vec<u64>, KeyCandidates)>,
+    // Values that don't fit a column's semantic type (email, URL, ...)
+    invalid_values: Option<InvalidValues>,
+    invalid_job: Option<Job<InvalidValues>>,
+    key_job: Option<Job<(Vec<u64>, KeyCandidates)>>,
+    // Split into several files
+    split_window: crate::gui::windows::split::SplitWindow,
+    show_split: bool,
+    split_job: Option<Job<anyhow::Result<Vec<std::path::PathBuf>>>>,
+    // Column subset to display (source indices), None for all columns
+    projection: Option<Vec<usize>>,
+    column_picker: crate::gui::windows::columns::ColumnPickerWindow,
+    show_column_picker: bool,
+    // Row to bring into view on the next frame (e.g. jumping to an issue)
+    scroll_to_row: Option<usize>,
+    // Attached table schema and its validation results
+    schema: Option<TableSchema>,
+    schema_issues: Option<Vec<SchemaIssue>>,
+    schema_window: crate::gui::windows::schema::SchemaIssuesWindow,
+    schema_job: Option<Job<anyhow::Result<Vec<SchemaIssue>>>>,
+    schema_generate_job: Option<Job<TableSchema>>,
+    show_schema_issues: bool,
+    row_rules_window: crate::gui::windows::row_rules::RowRulesWindow,
+    show_row_rules: bool,
+    // Rhai script console
+    script_window: crate::gui::windows::script::ScriptConsoleWindow,
+    show_script: bool,
+    script_job: Option<Job<anyhow::Result<ScriptOutcome>>>,
+    // "Open in pandas" handoff
+    pandas_window: crate::gui::windows::pandas::PandasWindow,
+    show_pandas: bool,
+    handoff_job: Option<Job<anyhow::Result<std::path::PathBuf>>>,
+    export_window: crate::gui::windows::export::ExportWindow,
+    show_export: bool,
+    export_job: Option<Job<anyhow::Result<Vec<std::path::PathBuf>>>>,
+    pipeline_window: crate::gui::windows::pipeline::PipelineWindow,
+    show_pipeline: bool,
+    show_audit_log: bool,
+    // Problems panel: diagnostics from the last scan plus schema validation
+    show_problems: bool,
+    // Quick filter: its conditions, and the rows passing them once computed
+    row_filter: RowFilter,
+    filtered_rows: Option<Vec<usize>>,
+    // Row count the filter last ran over, so structural changes re-run it
+    filtered_total: usize,
+    // Bumped whenever filtered_rows changes, so the graph knows to re-read
+    filter_generation: u64,
+    // Grid row order that row numbers held across frames (brushed rows,
+    // filter results, the baseline) were taken against
+    row_generation: u64,
+    filter_job: Option<Job<Vec<usize>>>,
+    // Cell and text being typed into the "containing" filter
+    filter_draft: Option<((usize, usize), String)>,
+    distinct_window: crate::gui::windows::distinct::DistinctValuesWindow,
+    show_distinct: bool,
+    distinct_job: Option<Job<DistinctValues>>,
+    group_by_window: crate::gui::windows::group_by::GroupByWindow,
+    show_group_by: bool,
+    group_by_job: Option<Job<GroupSummary>>,
+    scatter_window: crate::gui::windows::scatter_matrix::ScatterMatrixWindow,
+    show_scatter: bool,
+    scatter_job: Option<Job<ColumnSample>>,
+    problems: Option<crate::backend::problems::Problems>,
+    problems_job: Option<Job<crate::backend::problems::Problems>>,
+    problem_severities: [bool; 3],
+    problem_category: Option<ProblemCategory>,
+    /// Cell colors and emphasis carried in a .csvi file, keyed by data row
+    cell_formats: FormatMap,
+    /// Preamble and footer of a grid opened from a .csvi archive, which has
+    /// no source file to keep them in
+    kept_lines: Option<(String, String)>,
+    // Copy Table: row count for "First N rows", and the job building the clipboard text
+    copy_table_rows: usize,
+    copy_job: Option<Job<(String, String, usize)>>,
+    print_job: Option<Job<anyhow::Result<usize>>>,
+    // Display-only number/date formats per column, saved in .csvi metadata
+    column_formats: std::collections::BTreeMap<usize, ColumnFormat>,
+    column_format_window: crate::gui::windows::column_format::ColumnFormatWindow,
+    show_column_format: bool,
+    // Whole-row colors by rule, also saved in .csvi metadata
+    row_color_rules: Vec<RowColorRule>,
+    row_colors: RowColors,
+    row_colors_window: crate::gui::windows::row_colors::RowColorsWindow,
+    show_row_colors: bool,
+    // Categorical value normalization
+    normalize_window: crate::gui::windows::normalize::NormalizeWindow,
+    show_normalize: bool,
+    normalize_job: Option<Job<Vec<ValueCluster>>>,
+    // Anonymization / masking transforms
+    anonymize_window: crate::gui::windows::anonymize::AnonymizeWindow,
+    show_anonymize: bool,
+    date_format_window: crate::gui::windows::date_format::DateFormatWindow,
+    show_date_format: bool,
+    unit_window: crate::gui::windows::units::UnitConvertWindow,
+    show_units: bool,
+    regex_window: crate::gui::windows::regex_extract::RegexExtractWindow,
+    show_regex_extract: bool,
+    rolling_window: crate::gui::windows::rolling::RollingWindow,
+    show_rolling: bool,
+    combine_window: crate::gui::windows::combine::CombineWindow,
+    show_combine: bool,
+    geo_window: crate::gui::windows::geo::GeoWindow,
+    show_geo: bool,
+    geo_job: Option<Job<crate::backend::geo::GeoReport>>,
+    // Column-wide rewrite (normalization, masking), applied as one undo step
+    rewrite_job: Option<Job<Vec<CellEdit>>>,
+    // Rolling calculation to insert after the column it's over
+    rolling_job: Option<Job<Option<RollingColumn>>>,
+    // Loading a file-backed document into an editable grid
+    convert_job: Option<Job<Option<crate::backend::grid::EditableGrid>>>,
+    // Rows marked from the row number's context menu, shown on the minimap
+    bookmarks: std::collections::BTreeSet<usize>,
+    // First and last table rows drawn last frame, for the minimap's viewport box
+    visible_rows: (usize, usize),
+    // Size and mtime of the file when it was indexed; a mismatch freezes the view
+    disk_stamp: Option<FileStamp>,
+    file_changed: bool,
+    // Edited rows as they were before editing, so edits can be re-applied
+    // when the file is rewritten on disk; row 0 is always kept for its headers
+    edit_bases: std::collections::HashMap<usize, Vec<String>>,
+    reload_match: RowMatch,
+    // Edits being re-applied after a reload, with the loader they're matched against
+    rebase_job: Option<(std::sync::Weak<CsvLoader>, Job<Rebase>)>,
+    reload_conflicts: Vec<EditConflict>,
+    show_reload_conflicts: bool,
+    // "Skip first N lines" in the Data menu, starting from the detected preamble
+    preamble_draft: usize,
+    // "Footer rows" in the Data menu, for totals at the end of the file
+    footer_draft: usize,
+    // Tables separated by blank lines, when the file holds more than one
+    file_tables: Vec<TableSpan>,
+    file_tables_job: Option<Job<Vec<TableSpan>>>,
+    file_tables_window: crate::gui::windows::file_tables::FileTablesWindow,
+    show_file_tables: bool,
+    // Re-index new rows as the file grows on disk, like `tail -f`
+    follow_file: bool,
+    follow_checked_at: f64,
+    follow_job: Option<Job<anyhow::Result<FollowUpdate>>>,
+    // The loader the running scan started from; a different one by the end makes it stale
+    follow_base: std::sync::Weak<CsvLoader>,
+    // Writing a sorted copy of a file-backed document
+    sort_job: Option<Job<anyhow::Result<std::path::PathBuf>>>,
+    // Actions queued while the UI draws, for the app to apply once this frame is done
+    actions: Vec<Action>,
+    // An operation waiting on the user because it's over the memory budget
+    memory_prompt: Option<(MemoryHeavy, MemoryWarning)>,
+    // File Info dialog and its on-demand checksum
+    file_info_window: crate::gui::windows::file_info::FileInfoWindow,
+    show_file_info: bool,
+    // Full view of a cell too long to show in the table
+    cell_inspector: crate::gui::windows::cell_inspector::CellInspectorWindow,
+    show_cell_inspector: bool,
+    checksum_job: Option<Job<anyhow::Result<String>>>,
 }
 
-pub enum AppState {
-    Welcome,
-    Editor(EditorState),
-    Loading(String), // Show loading spinner
-    Error(String),
-}
+impl EditorState {
+    fn new(loader: Arc<CsvLoader>, filename: String) -> Self {
+        let mut column_picker = crate::gui::windows::columns::ColumnPickerWindow::new();
+        column_picker.load(loader.num_columns(), None);
+        let disk_stamp = FileStamp::read(std::path::Path::new(&filename));
+        Self {
+            view_mode: ViewMode::Table,
+            input_buffer: String::new(),
+            editing_cell: None,
+            word_wrap: false,
+            json_view: None,
+            num_columns: loader.num_columns(),
+            column_widths: vec![DEFAULT_COLUMN_WIDTH; loader.num_columns()],
+            manual_widths: std::collections::HashSet::new(),
+            auto_width_generations: std::collections::HashMap::new(),
+            widths_dirty: false,
+            row_height: None,
+            reset_widths: false,
+            selected_cell: None,
+            selection_anchor: None,
+            quick_calc: None,
+            quick_calc_job: None,
+            baseline_row: None,
+            baseline_deltas: false,
+            edit_modal: None,
+            graph_x_col: 0,
+            graph_y_col: 1,
+            graph_data: Vec::new(),
+            graph_source: None,
+            graph_job: None,
+            graph_rows: Vec::new(),
+            graph_x_time: false,
+            graph_resample: None,
+            graph_resample_aggregate: Aggregate::Mean,
+            graph_plot: Vec::new(),
+            graph_stats: false,
+            graph_histogram: false,
+            graph_bins: 20,
+            graph_value_count: 0,
+            graph_bars: Histogram::default(),
+            graph_brush: false,
+            graph_brush_box: None,
+            graph_brushed: Vec::new(),
+            grid: None,
+            profile_column: None,
+            selected_column: None,
+            profile_cache: ProfileCache::new(profile_cache::file_key(&filename)),
+            byte_offset_draft: String::new(),
+            text_scroll_to: None,
+            text_selection: None,
+            hex_line: None,
+            hex_dump: None,
+            text_line_ending: None,
+            sparklines: std::collections::HashMap::new(),
+            sparkline_job: None,
+            filename,
+            vim: VimState::new(),
+            status_message: None,
+            save_as_window: crate::gui::windows::save_as::SaveAsWindow::new(),
+            show_save_as: false,
+            save_job: None,
+            paste_window: crate::gui::windows::paste_special::PasteSpecialWindow::new(),
+            column_mapping_window: crate::gui::windows::column_mapping::ColumnMappingWindow::new(),
+            snapshots_window: crate::gui::windows::snapshots::SnapshotsWindow::new(),
+            show_snapshots: false,
+            snapshot_job: None,
+            snapshot_diff_job: None,
+            snapshot_restore_job: None,
+            git_status: None,
+            git_status_job: None,
+            git_checked_at: f64::NEG_INFINITY,
+            git_diff_job: None,
+            git_diff: None,
+            show_git_diff: false,
+            show_column_mapping: false,
+            show_paste_special: false,
+            transpose_first_column_headers: false,
+            heatmap_columns: std::collections::HashSet::new(),
+            duplicates: None,
+            duplicate_job: None,
+            key_candidates: None,
+            invalid_values: None,
+            invalid_job: None,
+            key_job: None,
+            split_window: crate::gui::windows::split::SplitWindow::new(),
+            show_split: false,
+            split_job: None,
+            projection: None,
+            column_picker,
+            show_column_picker: loader.num_columns() > PROJECTION_PROMPT_COLUMNS,
+            scroll_to_row: None,
+            schema: None,
+            schema_issues: None,
+            schema_window: crate::gui::windows::schema::SchemaIssuesWindow::new(),
+            schema_job: None,
+            schema_generate_job: None,
+            show_schema_issues: false,
+            row_rules_window: crate::gui::windows::row_rules::RowRulesWindow::new(),
+            show_row_rules: false,
+            script_window: crate::gui::windows::script::ScriptConsoleWindow::new(),
+            show_script: false,
+            script_job: None,
+            pandas_window: crate::gui::windows::pandas::PandasWindow::new(),
+            show_pandas: false,
+            handoff_job: None,
+            export_window: crate::gui::windows::export::ExportWindow::new(),
+            show_export: false,
+            export_job: None,
+            pipeline_window: crate::gui::windows::pipeline::PipelineWindow::new(),
+            show_pipeline: false,
+            show_audit_log: false,
+            show_problems: false,
+            row_filter: RowFilter::default(),
+            filtered_rows: None,
+            filtered_total: 0,
+            filter_generation: 0,
+            row_generation: 0,
+            filter_job: None,
+            filter_draft: None,
+            distinct_window: crate::gui::windows::distinct::DistinctValuesWindow::new(),
+            show_distinct: false,
+            distinct_job: None,
+            group_by_window: crate::gui::windows::group_by::GroupByWindow::new(),
+            show_group_by: false,
+            group_by_job: None,
+            scatter_window: crate::gui::windows::scatter_matrix::ScatterMatrixWindow::new(),
+            show_scatter: false,
+            scatter_job: None,
+            problems: None,
+            problems_job: None,
+            problem_severities: [true; 3],
+            problem_category: None,
+            cell_formats: FormatMap::new(),
+            kept_lines: None,
+            copy_table_rows: 100,
+            copy_job: None,
+            print_job: None,
+            column_formats: std::collections::BTreeMap::new(),
+            column_format_window: crate::gui::windows::column_format::ColumnFormatWindow::new(),
+            show_column_format: false,
+            row_color_rules: Vec::new(),
+            row_colors: RowColors::default(),
+            row_colors_window: crate::gui::windows::row_colors::RowColorsWindow::new(),
+            show_row_colors: false,
+            normalize_window: crate::gui::windows::normalize::NormalizeWindow::new(),
+            show_normalize: false,
+            normalize_job: None,
+            anonymize_window: crate::gui::windows::anonymize::AnonymizeWindow::new(),
+            show_anonymize: false,
+            date_format_window: crate::gui::windows::date_format::DateFormatWindow::new(),
+            show_date_format: false,
+            unit_window: crate::gui::windows::units::UnitConvertWindow::new(),
+            show_units: false,
+            regex_window: crate::gui::windows::regex_extract::RegexExtractWindow::new(),
+            show_regex_extract: false,
+            rolling_window: crate::gui::windows::rolling::RollingWindow::new(),
+            show_rolling: false,
+            combine_window: crate::gui::windows::combine::CombineWindow::new(),
+            show_combine: false,
+            geo_window: crate::gui::windows::geo::GeoWindow::new(),
+            show_geo: false,
+            geo_job: None,
+            rewrite_job: None,
+            rolling_job: None,
+            convert_job: None,
+            bookmarks: std::collections::BTreeSet::new(),
+            visible_rows: (0, 0),
+            disk_stamp,
+            file_changed: false,
+            edit_bases: std::collections::HashMap::new(),
+            reload_match: RowMatch::Content,
+            rebase_job: None,
+            reload_conflicts: Vec::new(),
+            show_reload_conflicts: false,
+            preamble_draft: loader.preamble_lines(),
+            footer_draft: loader.footer_rows(),
+            file_tables: Vec::new(),
+            file_tables_job: None,
+            file_tables_window: crate::gui::windows::file_tables::FileTablesWindow::new(),
+            show_file_tables: false,
+            follow_file: false,
+            follow_checked_at: 0.0,
+            follow_job: None,
+            follow_base: std::sync::Weak::new(),
+            sort_job: None,
+            actions: Vec::new(),
+            memory_prompt: None,
+            file_info_window: crate::gui::windows::file_info::FileInfoWindow::new(),
+            show_file_info: false,
+            cell_inspector: crate::gui::windows::cell_inspector::CellInspectorWindow::new(),
+            show_cell_inspector: false,
+            checksum_job: None,
+            file: IndexedFileDocument::new(loader),
+        }
+    }
 
-pub struct GuiApp {
-    state: AppState,
-    settings: Settings,
-    show_settings: bool,
-    show_new_csv_dialog: bool,
-    new_csv_columns: usize,
-    new_csv_rows: usize,
-    settings_window: crate::gui::windows::settings::SettingsWindow,
-}
+    /// Create an editor backed by an in-memory grid
+    fn with_grid(grid: crate::backend::grid::EditableGrid, filename: String) -> Self {
+        let cols = grid.num_cols();
+        let rows = grid.num_rows();
+        Self {
+            column_widths: vec![DEFAULT_COLUMN_WIDTH; cols],
+            graph_y_col: 1.min(cols.saturating_sub(1)),
+            grid: Some(grid),
+            show_column_picker: false,
+            ..Self::new(Arc::new(CsvLoader::empty(cols, rows)), filename)
+        }
+    }
 
-impl GuiApp {
-    pub fn new(_cc: &eframe::CreationContext<'_>, loader: Option<Arc<CsvLoader>>, filename: Option<String>) -> Self {
-        let mut settings = Settings::load();
-        
-        // Load custom themes if any
-        if let Some(config_dir) = ProjectDirs::from("com", "tigokraft", "csvit") {
-            let theme_dir = config_dir.config_dir().join("themes");
-            if theme_dir.exists() {
-                if let Ok(entries) = std::fs::read_dir(theme_dir) {
-                    for entry in entries.flatten() {
-                         if let Ok(content) = std::fs::read_to_string(entry.path()) {
-                             if let Ok(theme) = serde_json::from_str::<crate::backend::settings::CustomTheme>(&content) {
-                                 settings.custom_themes.push(theme);
-                             }
-                         }
-                    }
-                }
-            }
+    /// Widths (and row height) saved for this file the last time it was resized
+    fn restore_column_widths(&mut self, settings: &Settings) {
+        self.row_height = settings.row_heights.get(&self.filename).copied();
+        if let Some(widths) = settings.column_widths.get(&self.filename)
+            && widths.len() == self.num_columns
+        {
+            self.column_widths = widths.clone();
+            self.manual_widths = (0..self.num_columns).collect();
         }
-        
-        if let Some(ref path) = filename {
-            settings.add_recent_file(path);
+    }
+
+    /// Height of the table's rows: this file's own, or the one in settings
+    fn row_height(&self, settings: &Settings) -> f32 {
+        self.row_height.unwrap_or(settings.row_height)
+    }
+
+    /// Size columns that the user hasn't sized to fit their header and their
+    /// profile's typical (95th percentile) value, again whenever the column changes
+    fn apply_profile_widths(&mut self, columns: &[usize]) {
+        for &col in columns {
+            if self.manual_widths.contains(&col) {
+                continue;
+            }
+            let generation = self.column_generation(col);
+            if self.auto_width_generations.get(&col) == Some(&generation) {
+                continue;
+            }
+            self.request_profile(col);
+            let Some(profile) = self.profile_cache.get(col, generation) else {
+                continue;
+            };
+            let width = profile_width(profile);
+            self.auto_width_generations.insert(col, generation);
+            if let Some(known) = self.column_widths.get_mut(col)
+                && (*known - width).abs() > 0.5
+            {
+                *known = width;
+                self.reset_widths = true;
+            }
         }
-        
-        let state = if let Some(loader) = loader {
-             AppState::Editor(EditorState {
-                loader: loader.clone(),
-                reader: PagedReader::new(loader.clone()),
-                editor: EditBuffer::new(),
-                view_mode: ViewMode::Table,
-                input_buffer: String::new(),
-                editing_cell: None,
-                filename: filename.unwrap_or_else(|| "Unknown.csv".to_string()),
-                word_wrap: false,
-                json_modal: None,
-                num_columns: loader.num_columns(),
-                column_widths: loader.estimate_column_widths(),
-                selected_cell: Some((0, 0)),
-                edit_modal: None,
-                graph_x_col: 0,
-                graph_y_col: 1,
-                graph_data: Vec::new(),
-                grid: None,
-                column_profile: None,
-                vim_mode: VimMode::Normal,
-                command_buffer: String::new(),
+    }
+
+    /// Open Save As, starting from the delimiter and line endings the file appears to use
+    fn save_as_dialog(&mut self) {
+        let source = (!self.document().in_memory()).then(|| {
+            let sample: String = (0..self.file.loader.total_records().min(20))
+                .filter_map(|r| self.file.loader.get_record_line(r))
+                .map(|line| String::from_utf8_lossy(&line).into_owned())
+                .collect();
+            CsvWriteOptions { source_delimiter: preview::sniff_delimiter(&sample), ..CsvWriteOptions::matching(&self.file.loader) }
+        });
+        self.save_as_window.load(source);
+        self.show_save_as = true;
+    }
+
+    /// Write the in-memory grid to a file the user picks; `.csvi` keeps formats and widths alongside
+    fn save_grid_as(&mut self, options: &CsvWriteOptions) {
+        let Some(ref mut grid) = self.grid else {
+            return;
+        };
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv", "tsv", "txt"])
+            .add_filter("CSVit", &["csvi"])
+            .save_file()
+        else {
+            return;
+        };
+        let csvi = path.extension().and_then(|e| e.to_str()) == Some("csvi");
+        // A grid converted from a file with a preamble or footer keeps them
+        let (preamble, footer) = match self.kept_lines {
+            Some((ref preamble, ref footer)) => (preamble.as_bytes().to_vec(), footer.as_bytes().to_vec()),
+            None => (
+                self.file.loader.preamble().unwrap_or_default().into_owned(),
+                self.file.loader.footer().unwrap_or_default().into_owned(),
+            ),
+        };
+        let result = if csvi {
+            grid.to_csv(options).and_then(|csv_text| {
+                let mut metadata = crate::backend::csvi::CsviMetadata::new();
+                metadata.column_formats = self.column_formats.clone();
+                metadata.row_colors = self.row_color_rules.clone();
+                metadata.column_widths = self.column_widths.clone();
+                metadata.formatting = self.cell_formats.clone();
+                metadata.preamble = String::from_utf8_lossy(&preamble).into_owned();
+                metadata.footer = String::from_utf8_lossy(&footer).into_owned();
+                crate::backend::csvi::save_csvi(&path, &csv_text, &metadata)
             })
         } else {
-            AppState::Welcome
+            grid.to_csv_between(options, &preamble, &footer)
+                .and_then(|text| std::fs::write(&path, text).map_err(anyhow::Error::from))
         };
-        
-        Self { 
-            state,
-            settings,
-            show_settings: false,
-            show_new_csv_dialog: false,
-            new_csv_columns: 5,
-            new_csv_rows: 10,
-            settings_window: crate::gui::windows::settings::SettingsWindow::new(),
+        match result {
+            Ok(()) => {
+                grid.mark_saved();
+                self.filename = path.to_string_lossy().to_string();
+                self.profile_cache.set_file_key(profile_cache::file_key(&self.filename));
+                self.status_message = Some(format!("Saved {}", path.display()));
+            }
+            Err(e) => self.status_message = Some(format!("Save failed: {}", e)),
         }
     }
 
-    fn load_file(&mut self, path: &str) {
-        self.state = AppState::Loading(path.to_string());
-        match CsvLoader::new(std::path::Path::new(path)) {
-            Ok(loader) => {
-                let arc_loader = Arc::new(loader);
-                self.settings.add_recent_file(path);
-                self.state = AppState::Editor(EditorState {
-                    loader: arc_loader.clone(),
-                    reader: PagedReader::new(arc_loader.clone()),
-                    editor: EditBuffer::new(),
-                    view_mode: ViewMode::Table,
-                    input_buffer: String::new(),
-                    editing_cell: None,
-                    filename: path.to_string(),
-                    word_wrap: false,
-                    json_modal: None,
-                    num_columns: arc_loader.num_columns(),
-                    column_widths: arc_loader.estimate_column_widths(),
-                    selected_cell: None,
-                    edit_modal: None,
-                    graph_x_col: 0,
-                    graph_y_col: 1,
-                    graph_data: Vec::new(),
-                    grid: None,
-                    column_profile: None,
-                    vim_mode: VimMode::Normal,
-                    command_buffer: String::new(),
+    /// Back to automatic widths for every column
+    fn reset_column_widths(&mut self) {
+        self.column_widths = vec![DEFAULT_COLUMN_WIDTH; self.num_columns];
+        self.manual_widths.clear();
+        self.auto_width_generations.clear();
+        self.reset_widths = true;
+    }
+
+    /// Queue an action for the app to apply once this frame is drawn
+    fn dispatch(&mut self, action: Action) {
+        self.actions.push(action);
+    }
+
+    /// Apply an action aimed at this document
+    fn apply(&mut self, action: Action, settings: &mut Settings) {
+        match action {
+            Action::Open(dialog) => self.open_dialog(dialog),
+            Action::ToggleProfileHud => settings.show_profile_hud = !settings.show_profile_hud,
+            Action::SaveAs if self.save_job.is_none() => self.save_as_dialog(),
+            Action::SaveAsTemplate(name) => self.save_as_template(name),
+            Action::RunExportPreset(preset) if self.export_job.is_none() => self.run_export_preset(&preset),
+            Action::Reload => self.reload_from_disk(),
+            Action::AttachSchema => {
+                if let Some(path) = rfd::FileDialog::new().add_filter("Table Schema", &["json"]).pick_file() {
+                    match TableSchema::load(&path) {
+                        Ok(schema) => {
+                            self.schema = Some(schema);
+                            self.validate_schema();
+                        }
+                        Err(e) => self.status_message = Some(format!("{:#}", e)),
+                    }
+                }
+            }
+            Action::ValidateSchema => self.validate_schema(),
+            Action::SaveSchema => {
+                if let Some(schema) = self.schema.as_ref()
+                    && let Some(path) = rfd::FileDialog::new().add_filter("Table Schema", &["json"]).set_file_name("schema.json").save_file()
+                {
+                    self.status_message = Some(match schema.save(&path) {
+                        Ok(()) => format!("Schema saved to {}", path.display()),
+                        Err(e) => format!("{:#}", e),
+                    });
+                }
+            }
+            Action::GenerateSchema => self.generate_schema(),
+            Action::CopyTable(rows) if self.copy_job.is_none() => self.copy_table(rows),
+            Action::OpenInPandas if self.handoff_job.is_none() => self.open_in_pandas(),
+            Action::Print if self.print_job.is_none() => self.print_dialog(),
+            Action::FileInfo => self.file_info_dialog(),
+            Action::DiffAgainstHead if self.git_diff_job.is_none() => self.diff_against_head(),
+            Action::EditCell { row, col, value } => self.set_cell_value(row, col, value),
+            Action::StartEdit { row, col, text } if settings.use_edit_modal => {
+                let mut modal = EditModal::new(row, col, self.cell_value(row, col));
+                modal.text = text;
+                self.edit_modal = Some(modal);
+            }
+            Action::StartEdit { row, col, text } => {
+                if settings.keybinding_mode == KeybindingMode::Vim {
+                    self.vim.mode = VimMode::Insert;
+                }
+                self.editing_cell = Some((row, col));
+                self.input_buffer = text;
+            }
+            Action::InspectCell { row, col } => self.inspect_cell(row, col),
+            Action::Paste { text, special: true } => self.open_paste_special(&text),
+            Action::Paste { text, special: false } if self.selected_cell.is_some() => {
+                self.paste_rows(PasteMode::Values, clipboard::parse_tsv(&text));
+            }
+            Action::PasteSpecialAt { row, col } => {
+                self.selected_cell = Some((row, col));
+                let text = clipboard::read_system_clipboard().unwrap_or_default();
+                self.open_paste_special(&text);
+            }
+            Action::AddRow => {
+                let at = self.selected_cell.map_or(self.document().row_count(), |(r, _)| r + 1);
+                self.restructure(|document| document.insert_rows(at, vec![Vec::new()]));
+            }
+            Action::DeleteRow => {
+                if let Some((r, _)) = self.selected_cell
+                    && self.restructure(|document| document.delete_row(r)).is_some()
+                {
+                    self.selected_cell = None;
+                }
+            }
+            Action::AddColumn => {
+                let at = self.selected_cell.map_or(self.num_columns, |(_, c)| c + 1);
+                let header = format!("Column {}", self.num_columns + 1);
+                self.restructure(|document| document.insert_columns(at, vec![(header, Vec::new())]));
+            }
+            Action::DeleteColumn => {
+                if let Some((_, c)) = self.selected_cell
+                    && self.restructure(|document| document.delete_column(c)).is_some()
+                {
+                    self.selected_cell = None;
+                }
+            }
+            Action::Transpose => {
+                let first_column_headers = self.transpose_first_column_headers;
+                if self.restructure(|document| document.transpose(first_column_headers)).is_some() {
+                    self.status_message = Some("Table transposed".to_string());
+                }
+            }
+            Action::AppendFile => self.append_file_dialog(),
+            Action::ConvertToGrid if self.convert_job.is_none() => {
+                self.run_memory_heavy(MemoryHeavy::ConvertToGrid, settings.memory_budget_mb, false);
+            }
+            Action::FollowFile(on) => {
+                self.follow_file = on;
+                self.follow_checked_at = 0.0;
+            }
+            Action::SetPreamble(lines) => self.set_preamble(lines),
+            Action::Sort { col, order } => self.sort_by_column(col, order, settings.memory_budget_mb),
+            Action::Filter(condition) => self.add_filter(condition),
+            Action::ClearFilter => {
+                self.row_filter = RowFilter::default();
+                self.apply_filter();
+            }
+            // Removing an existing bookmark is the other half of the toggle
+            Action::ToggleBookmark(row) if !self.bookmarks.remove(&row) => {
+                self.bookmarks.insert(row);
+            }
+            Action::SetFooter(rows) => self.set_footer(rows),
+            Action::PinBaseline(row) => {
+                self.baseline_row = row;
+                self.status_message = Some(match row {
+                    Some(row) => format!("Row {} pinned as the baseline", row),
+                    None => "Baseline unpinned".to_string(),
                 });
             }
-            Err(e) => {
-                self.state = AppState::Error(format!("Failed to load file: {}", e));
+            Action::ResizeColumn { col, delta } => {
+                if let Some(width) = self.column_widths.get_mut(col) {
+                    *width = (*width + delta).max(MIN_COLUMN_WIDTH);
+                    self.manual_widths.insert(col);
+                    self.reset_widths = true;
+                    self.widths_dirty = true;
+                }
+            }
+            Action::ResizeRows(delta) => {
+                let height = self.row_height(settings) + delta;
+                self.row_height = Some(height.clamp(MIN_ROW_HEIGHT, MAX_ROW_HEIGHT));
+                self.widths_dirty = true;
+            }
+            Action::QuickCalc => self.quick_calc(),
+            Action::FindDuplicates(col) => self.find_duplicates(col),
+            Action::FindKeyCandidates if self.key_job.is_none() => self.find_key_candidates(),
+            Action::ListInvalidValues { col, semantic } if self.invalid_job.is_none() => {
+                self.find_invalid_values(col, semantic);
+            }
+            Action::ProfileAllRows(col) => {
+                self.run_memory_heavy(MemoryHeavy::ProfileAllRows(col), settings.memory_budget_mb, false);
+            }
+            Action::ScanProblems if self.problems_job.is_none() => self.scan_problems(),
+            Action::ColorScale { col, on: true } => {
+                self.heatmap_columns.insert(col);
             }
+            Action::ColorScale { col, on: false } => {
+                self.heatmap_columns.remove(&col);
+            }
+            Action::Histogram(col) => {
+                self.graph_y_col = col;
+                self.graph_histogram = true;
+                self.view_mode = ViewMode::Graph;
+            }
+            Action::ViewJson { row, lines } => {
+                let text = self.json_view_text(row, if lines { JSON_LINES_ROWS } else { 1 }, lines);
+                self.json_view = Some(JsonView { first: row, count: JSON_LINES_ROWS, lines, text });
+            }
+            Action::GoToByteOffset => self.go_to_byte_offset(),
+            Action::ExportSelectedLines => self.export_selected_lines(),
+            Action::HexDump(line) => self.hex_line = Some((line, None)),
+            Action::ExportPlottedData => self.export_plotted_data(),
+            Action::FilterToBrushed => self.filter_to_brushed(),
+            Action::ResetWidths => {
+                self.reset_column_widths();
+                settings.forget_column_widths(&self.filename);
+            }
+            Action::Undo | Action::Redo => {
+                let document = self.document_mut();
+                let changed = if action == Action::Undo { document.undo() } else { document.redo() };
+                // It may have changed the table's shape, or its header
+                if changed {
+                    self.sync_columns();
+                }
+            }
+            _ => {}
         }
     }
 
-    fn open_file_dialog(&mut self) {
-        if let Some(path) = rfd::FileDialog::new().add_filter("CSV", &["csv"]).pick_file() {
-            let path_str = path.to_string_lossy().to_string();
-            self.load_file(&path_str);
+    /// Load a dialog with what it works on and show it
+    fn open_dialog(&mut self, dialog: Dialog) {
+        match dialog {
+            Dialog::RowColors => {
+                self.row_colors_window.load(self.row_color_rules.clone(), self.source_headers());
+                self.show_row_colors = true;
+            }
+            Dialog::SchemaIssues => self.show_schema_issues = true,
+            Dialog::RowRules => {
+                let rules = self.schema.as_ref().map(|schema| schema.row_rules.clone()).unwrap_or_default();
+                self.row_rules_window.load(rules, self.source_headers());
+                self.show_row_rules = true;
+            }
+            Dialog::ColumnPicker => {
+                self.column_picker.load(self.num_columns, self.projection.as_deref());
+                self.show_column_picker = true;
+            }
+            Dialog::Split => self.show_split = true,
+            Dialog::Script => self.show_script = true,
+            Dialog::Export => {
+                self.export_window.load(&self.source_headers(), &self.display_columns());
+                self.show_export = true;
+            }
+            Dialog::Snapshots => self.open_snapshots(),
+            Dialog::FileTables => self.open_file_tables(),
+            Dialog::GroupBy => {
+                self.group_by_window.load(self.source_headers());
+                self.show_group_by = true;
+            }
+            Dialog::ScatterMatrix => {
+                let numeric: Vec<usize> = self.column_types().iter()
+                    .enumerate()
+                    .filter(|(_, t)| matches!(t, Some(InferredType::Integer | InferredType::Float)))
+                    .map(|(c, _)| c)
+                    .collect();
+                self.scatter_window.load(self.source_headers(), &numeric);
+                self.show_scatter = true;
+            }
+            Dialog::GeoCheck => self.geo_dialog(),
+            Dialog::Pipeline => {
+                self.pipeline_window.load(self.source_headers());
+                self.show_pipeline = true;
+            }
+            Dialog::Distinct(col) => self.browse_distinct(col),
+            Dialog::Normalize(col) => {
+                self.normalize_window.load(col, self.column_header(col), Vec::new());
+                self.cluster_column(col, self.normalize_window.max_distance());
+                self.show_normalize = true;
+            }
+            Dialog::Anonymize(col) => {
+                let sample = self.cell_value(self.first_data_row(), col);
+                self.anonymize_window.load(col, self.column_header(col), sample);
+                self.show_anonymize = true;
+            }
+            Dialog::DateFormat(col) => {
+                let first = self.first_data_row();
+                let samples = self.column_values(col, first + TRANSFORM_SAMPLE_ROWS).split_off(first);
+                self.date_format_window.load(col, self.column_header(col), samples);
+                self.show_date_format = true;
+            }
+            Dialog::Units(col) => {
+                let first = self.first_data_row();
+                let mut samples = self.column_values(col, first + TRANSFORM_SAMPLE_ROWS).split_off(first);
+                samples.retain(|v| !v.trim().is_empty());
+                self.unit_window.load(col, self.column_header(col), samples);
+                self.show_units = true;
+            }
+            // The rest insert columns, which only a grid can do
+            Dialog::RegexExtract(col) if self.document().in_memory() => {
+                let samples = self.column_values(col, TRANSFORM_SAMPLE_ROWS);
+                self.regex_window.load(col, self.column_header(col), samples);
+                self.show_regex_extract = true;
+            }
+            Dialog::Rolling(col) if self.document().in_memory() => {
+                self.rolling_window.load(col, self.source_headers());
+                self.show_rolling = true;
+            }
+            Dialog::Combine(col) if self.document().in_memory() => self.combine_dialog(col),
+            Dialog::NumberFormat(col) => {
+                let sample = self.cell_value(self.first_data_row(), col);
+                let format = self.column_formats.get(&col).cloned().unwrap_or_default();
+                self.column_format_window.load(col, self.column_header(col), sample, format);
+                self.show_column_format = true;
+            }
+            Dialog::RegexExtract(_) | Dialog::Rolling(_) | Dialog::Combine(_) => {}
         }
     }
-}
 
-impl eframe::App for GuiApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        apply_style(ctx, &self.settings); 
+    /// Current value of a cell, including any pending edit
+    fn cell_value(&self, r: usize, c: usize) -> String {
+        self.document().cell(r, c)
+    }
 
-        egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
-             ui.horizontal(|ui| {
-                 ui.menu_button("File", |ui| {
-                     if ui.button("📄 New CSV").clicked() {
-                         self.show_new_csv_dialog = true;
-                         ui.close();
-                     }
-                     if ui.button("📂 Open").clicked() {
-                         self.open_file_dialog();
-                         ui.close();
-                     }
-                     ui.separator();
-                     ui.menu_button("Recent Files", |ui| {
-                         if self.settings.recent_files.is_empty() {
-                             ui.label("No recent files");
-                         } else {
-                             for path in self.settings.recent_files.clone() {
-                                 let display_name = std::path::Path::new(&path)
-                                     .file_name()
-                                     .map(|n| n.to_string_lossy().to_string())
-                                     .unwrap_or_else(|| path.clone());
-                                 if ui.button(&display_name).on_hover_text(&path).clicked() {
-                                     self.load_file(&path);
-                                     ui.close();
-                                 }
-                             }
-                         }
-                     });
-                 });
-                 if ui.button("⚙ Settings").clicked() {
-                     self.show_settings = true;
-                 }
-             });
-        });
+    /// Whichever of the grid or the indexed file backs this document
+    fn document(&self) -> &dyn CsvDocument {
+        match self.grid {
+            Some(ref grid) => grid,
+            None => &self.file,
+        }
+    }
 
-        // Settings Window
-        if self.show_settings {
-             self.settings_window.show(ctx, &mut self.show_settings, &mut self.settings);
+    fn document_mut(&mut self) -> &mut dyn CsvDocument {
+        match self.grid {
+            Some(ref mut grid) => grid,
+            None => &mut self.file,
         }
-        // New CSV Dialog
-        if self.show_new_csv_dialog {
-            let mut open = true;
-            egui::Window::new("Create New CSV")
-                .open(&mut open)
-                .resizable(false)
-                .show(ctx, |ui| {
-                    ui.horizontal(|ui| {
-                        ui.label("Columns:");
-                        ui.add(egui::DragValue::new(&mut self.new_csv_columns).range(1..=100));
-                    });
-                    ui.horizontal(|ui| {
-                        ui.label("Rows:");
-                        ui.add(egui::DragValue::new(&mut self.new_csv_rows).range(1..=1000));
-                    });
-                    ui.add_space(10.0);
-                    ui.horizontal(|ui| {
-                        if ui.button("Create").clicked() {
-                            // Create an in-memory CSV structure
-                            let cols = self.new_csv_columns;
-                            let rows = self.new_csv_rows;
-                            let default_widths: Vec<f32> = (0..cols).map(|_| 100.0).collect();
-                            let grid = crate::backend::grid::EditableGrid::new(cols, rows);
-                            self.state = AppState::Editor(EditorState {
-                                loader: Arc::new(CsvLoader::empty(cols, rows)),
-                                reader: PagedReader::empty(),
-                                editor: EditBuffer::new(),
-                                view_mode: ViewMode::Table,
-                                input_buffer: String::new(),
-                                editing_cell: None,
-                                filename: "Untitled.csv".to_string(),
-                                word_wrap: false,
-                                json_modal: None,
-                                num_columns: cols,
-                                column_widths: default_widths,
-                                selected_cell: None,
-                                edit_modal: None,
-                                graph_x_col: 0,
-                                graph_y_col: 1.min(cols.saturating_sub(1)),
-                                graph_data: Vec::new(),
-                                grid: Some(grid),
-                                column_profile: None,
-                                vim_mode: VimMode::Normal,
-                                command_buffer: String::new(),
-                            });
-                            self.show_new_csv_dialog = false;
-                        }
-                        if ui.button("Cancel").clicked() {
-                            self.show_new_csv_dialog = false;
-                        }
-                    });
-                });
-            if !open {
-                self.show_new_csv_dialog = false;
+    }
+
+    /// Set a cell through the grid or the delta buffer, whichever backs this document
+    fn set_cell_value(&mut self, r: usize, c: usize, value: String) {
+        let in_memory = self.document().in_memory();
+        if !in_memory {
+            self.record_edit_bases([r]);
+        }
+        self.document_mut().set_cell(r, c, value);
+        if !in_memory && r == 0 {
+            self.recompile_row_colors();
+        }
+    }
+
+    /// Remember rows as they are in the file before they're first edited
+    fn record_edit_bases(&mut self, rows: impl IntoIterator<Item = usize>) {
+        for r in std::iter::once(0).chain(rows) {
+            if self.edit_bases.len() >= EDIT_BASE_ROWS {
+                return;
+            }
+            if !self.edit_bases.contains_key(&r) {
+                self.edit_bases.insert(r, self.file.file_row(r));
             }
         }
+    }
 
-        // Handle Drag & Drop
-        if !ctx.input(|i| i.raw.dropped_files.is_empty()) {
-            let dropped_files = ctx.input(|i| i.raw.dropped_files.clone());
-            if let Some(file) = dropped_files.first() {
-                if let Some(path) = &file.path {
-                    let path_str = path.to_string_lossy().to_string();
-                    self.load_file(&path_str);
+    /// Problem with `value` as a new value for column `c`. Breaking the attached
+    /// schema blocks saving (true); not matching the profiled type is a warning.
+    fn validate_cell_value(&self, c: usize, value: &str) -> Option<(String, bool)> {
+        if let Some(field) = self.schema.as_ref().and_then(|schema| schema.fields.get(c)) {
+            return field.check_value(value).map(|message| (message, true));
+        }
+        let expected = self.profile_cache.get(c, self.column_generation(c))?.data_type.clone()?;
+        let value = value.trim();
+        if value.is_empty() || !matches!(expected, InferredType::Integer | InferredType::Float | InferredType::Boolean | InferredType::Date) {
+            return None;
+        }
+        let actual = ColumnAnalyzer::infer_value_type(value);
+        let fits = actual == expected || (expected == InferredType::Float && actual == InferredType::Integer);
+        (!fits).then(|| (format!("Column looks like {}, but this value is {}", expected.name(), actual.name()), false))
+    }
+
+    /// Display name of a column
+    fn column_header(&self, col: usize) -> String {
+        self.document().headers().get(col).cloned()
+            .unwrap_or_else(|| format!("Column {}", col + 1))
+    }
+
+    /// Which part of the document window keyboard input goes to this frame
+    fn input_scope(&self, ctx: &egui::Context) -> InputScope {
+        if self.edit_modal.is_some() || self.show_paste_special || self.memory_prompt.is_some() {
+            InputScope::Modal
+        } else if self.editing_cell.is_some() {
+            InputScope::CellEditor
+        } else if ctx.wants_keyboard_input() {
+            InputScope::TextField
+        } else {
+            InputScope::Grid
+        }
+    }
+
+    /// Edit generation of a column in whichever buffer backs this document
+    fn column_generation(&self, col: usize) -> u64 {
+        self.document().column_generation(col)
+    }
+
+    /// Row count and a reader for one column's current values (edits applied)
+    /// that can be moved to a background thread
+    fn column_reader(&self, col: usize) -> (usize, ColumnReader) {
+        self.document().column_reader(col)
+    }
+
+    /// Row count and a reader for whole rows (edits applied) that can be
+    /// moved to a background thread
+    fn row_reader(&self) -> (usize, RowReader) {
+        self.document().row_reader()
+    }
+
+    /// Run a memory-heavy operation, or ask first if it's over budget (unless `force`)
+    fn run_memory_heavy(&mut self, action: MemoryHeavy, budget_mb: u64, force: bool) {
+        let (rows, cols, bytes) = (self.file.loader.total_records(), self.num_columns, self.file.loader.byte_len());
+        let needed = match action {
+            MemoryHeavy::ConvertToGrid => memory::table_estimate(bytes, rows, cols),
+            MemoryHeavy::ProfileAllRows(_) => memory::column_estimate(bytes, rows, cols),
+        };
+        if !force && let Err(warning) = memory::check(needed, budget_mb) {
+            self.memory_prompt = Some((action, warning));
+            return;
+        }
+        match action {
+            MemoryHeavy::ConvertToGrid => self.convert_to_grid(),
+            MemoryHeavy::ProfileAllRows(col) => self.profile_all_rows(col),
+        }
+    }
+
+    /// Load every row into an editable grid so rows and columns can be inserted and removed
+    fn convert_to_grid(&mut self) {
+        let loader = self.file.loader.clone();
+        self.convert_job = Some(Job::spawn("Loading into an editable grid", move |progress| {
+            let total = loader.total_records();
+            progress.set_total(total);
+            let parse = |r: usize| loader.get_record_line(r)
+                .and_then(|line| CsvParser::parse_line(&String::from_utf8_lossy(&line)).ok())
+                .unwrap_or_default();
+            let headers = parse(0);
+            let mut rows = Vec::with_capacity(total.saturating_sub(1));
+            for r in 1..total {
+                if r.is_multiple_of(10_000) {
+                    if progress.is_cancelled() {
+                        return None;
+                    }
+                    progress.set_done(r);
                 }
+                rows.push(parse(r));
+            }
+            Some(crate::backend::grid::EditableGrid::from_rows(headers, rows))
+        }));
+    }
+
+    /// Swap in the converted grid. Pending edits are replayed onto it as one undo
+    /// step, and row positions shift up by one since the header leaves the rows.
+    fn finish_grid_conversion(&mut self, mut grid: crate::backend::grid::EditableGrid) {
+        let mut cells = Vec::new();
+        for (row, col, value) in self.file.edits.edits() {
+            match row {
+                0 => grid.set_header(col, value.clone()),
+                _ => cells.push((row - 1, col, value.clone())),
             }
         }
+        grid.set_cells(cells);
+        // The replayed edits are already in the log, under their original times
+        *grid.audit_mut() = std::mem::take(self.file.edits.audit_mut());
+        self.grid = Some(grid);
+        self.file.edits = EditBuffer::new();
+        self.edit_bases.clear();
+        self.selected_cell = self.selected_cell.map(|(r, c)| (r.saturating_sub(1), c));
+        // The header row is gone, so every data row moves up one
+        if let Some(rows) = self.row_filter.rows.take() {
+            self.row_filter.rows = Some(rows.into_iter().filter_map(|r| r.checked_sub(1)).collect());
+        }
+        self.baseline_row = self.baseline_row.and_then(|r| r.checked_sub(1));
+        self.row_generation = self.grid.as_ref().map_or(0, |grid| grid.row_generation());
+        self.editing_cell = None;
+        self.duplicates = None;
+        self.invalid_values = None;
+        self.problems = None;
+        self.filtered_rows = None;
+        self.schema_issues = None;
+        self.profile_cache = ProfileCache::new(profile_cache::file_key(&self.filename));
+        self.sparklines.clear();
+        self.graph_source = None;
+        self.text_line_ending = None;
+        self.sync_columns();
+    }
 
-        let mut next_state = None;
+    /// Profile a column over every row instead of the usual sample
+    fn profile_all_rows(&mut self, col: usize) {
+        let header = self.column_header(col);
+        let (total, value_at) = self.column_reader(col);
+        let first = self.first_data_row();
+        self.profile_cache.invalidate(col);
+        self.profile_cache.request(col, self.column_generation(col), move || {
+            let values: Vec<String> = (first..total).map(value_at).collect();
+            ColumnAnalyzer::analyze_column(&header, col, &values)
+        });
+    }
+
+    /// Switch to a fresh mapping of the file. Pending edits are kept: rows
+    /// only move when the file was rewritten, and then the caller asks first.
+    fn swap_loader(&mut self, loader: CsvLoader) {
+        self.file.set_loader(Arc::new(loader));
+        self.num_columns = self.num_columns.max(self.file.num_columns);
+        self.column_widths.resize(self.num_columns, DEFAULT_COLUMN_WIDTH);
+        self.profile_cache.set_file_key(profile_cache::file_key(&self.filename));
+        self.sparklines.clear();
+        self.graph_source = None;
+        self.text_line_ending = None;
+        // Lines and byte offsets picked in the text view may point elsewhere now
+        self.text_selection = None;
+        self.hex_line = None;
+        self.hex_dump = None;
+        self.disk_stamp = FileStamp::read(std::path::Path::new(&self.filename));
+        self.file_changed = false;
+        self.recompile_row_colors();
+    }
+
+    /// Compare the file's size and mtime with when it was indexed, before rows
+    /// are read from it. An append is handed to Follow File when that's on;
+    /// any other change freezes the view until the user reloads, since reading
+    /// a mapping that was rewritten or truncated gives garbage (or crashes).
+    fn check_disk_changes(&mut self) {
+        if self.document().in_memory() || self.file_changed || self.disk_stamp.is_none() {
+            return;
+        }
+        let path = std::path::PathBuf::from(&self.filename);
+        if FileStamp::read(&path) == self.disk_stamp {
+            return;
+        }
+        // Follow File's scan tells an append it can take on from a rewrite
+        if self.follow_file && (self.follow_job.is_some() || self.start_follow_scan()) {
+            return;
+        }
+        self.file_changed = true;
+    }
+
+    /// Index the file again with its first `lines` lines set aside as a preamble
+    fn set_preamble(&mut self, lines: usize) {
+        let footer = self.file.loader.footer_rows();
+        self.set_table_bounds(lines, footer, format!("{} line(s) above the header", lines));
+    }
+
+    /// Index the file again with its last `rows` records set aside as a footer
+    fn set_footer(&mut self, rows: usize) {
+        let preamble = self.file.loader.preamble_lines();
+        self.set_table_bounds(preamble, rows, format!("{} footer row(s) below the table", rows));
+    }
+
+    /// Which of `file_tables` is showing; one past the last for all of them
+    fn current_table(&self) -> Option<usize> {
+        let bounds = (self.file.loader.preamble_lines(), self.file.loader.footer_rows());
+        let whole = (self.file_tables.first()?.preamble, self.file_tables.last()?.footer);
+        if bounds == whole {
+            return Some(self.file_tables.len());
+        }
+        self.file_tables.iter().position(|t| (t.preamble, t.footer) == bounds)
+    }
+
+    /// Look for several tables in the file in the background; the whole file
+    /// stays open and the picker is offered once the scan finds more than one
+    fn scan_file_tables(&mut self) {
+        let loader = self.file.loader.clone();
+        self.file_tables_job = Some(Job::spawn("Looking for tables", move |progress| loader.find_tables(progress)));
+    }
+
+    fn open_file_tables(&mut self) {
+        self.file_tables_window.load(self.file_tables.clone(), self.current_table());
+        self.show_file_tables = true;
+    }
+
+    /// Rows renumber when the table's first or last line moves, so this
+    /// waits until there are no pending edits
+    fn set_table_bounds(&mut self, preamble: usize, footer: usize, message: String) {
+        if self.file.is_modified() {
+            self.status_message = Some("Save or undo your edits before changing where the table starts or ends".to_string());
+            return;
+        }
+        match CsvLoader::new(std::path::Path::new(&self.filename)) {
+            Ok(loader) => {
+                let loader = loader.with_preamble(preamble).with_footer(footer);
+                // Rows renumber by as many lines as the header moved; marks on
+                // rows that left the table go, and results found by row are stale
+                let moved = loader.preamble_lines() as isize - self.file.loader.preamble_lines() as isize;
+                let total = loader.total_records();
+                self.bookmarks = self.bookmarks.iter()
+                    .filter_map(|&r| r.checked_add_signed(-moved))
+                    .filter(|&r| r > 0 && r < total)
+                    .collect();
+                self.edit_bases.clear();
+                self.duplicates = None;
+                self.invalid_values = None;
+                self.problems = None;
+                self.schema_issues = None;
+                self.row_filter.rows = None;
+                self.preamble_draft = loader.preamble_lines();
+                self.footer_draft = loader.footer_rows();
+                self.num_columns = loader.num_columns();
+                self.file.num_columns = self.num_columns;
+                self.reset_column_widths();
+                self.selected_cell = None;
+                self.selection_anchor = None;
+                self.baseline_row = None;
+                self.filtered_rows = None;
+                self.swap_loader(loader);
+                self.status_message = Some(message);
+            }
+            Err(e) => self.status_message = Some(format!("Reading the file failed: {:#}", e)),
+        }
+    }
+
+    /// Re-read the file after it changed on disk. Edits survive an append;
+    /// a rewrite moves rows around, so edits are re-applied to wherever their
+    /// rows went (see `rebase_edits`) and the rest listed as conflicts.
+    fn reload_from_disk(&mut self) {
+        if self.rebase_job.is_some() {
+            self.status_message = Some("Edits from the last reload are still being re-applied".to_string());
+            return;
+        }
+        match self.file.loader.reindex(std::path::Path::new(&self.filename)) {
+            Ok((loader, kind)) => {
+                let mut edits = Vec::new();
+                let mut bases = std::collections::HashMap::new();
+                if kind == Reindex::Full {
+                    edits = self.file.edits.edits().map(|(r, c, value)| (r, c, value.clone())).collect();
+                    bases = std::mem::take(&mut self.edit_bases);
+                    self.file.edits.clear();
+                    self.num_columns = loader.num_columns();
+                    self.file.num_columns = self.num_columns;
+                    self.reset_column_widths();
+                    self.selected_cell = None;
+                }
+                self.swap_loader(loader);
+                if !edits.is_empty() {
+                    // Finding the edited rows again reads the whole file
+                    let count = edits.len();
+                    let loader = self.file.loader.clone();
+                    let matching = self.reload_match;
+                    let job = Job::spawn("Re-applying edits", move |progress| {
+                        let row_at = |r: usize| loader.get_record_line(r)
+                            .and_then(|line| CsvParser::parse_line(&String::from_utf8_lossy(&line)).ok())
+                            .unwrap_or_default();
+                        rebase_edits(edits, &bases, matching, loader.total_records(), row_at, progress)
+                    });
+                    self.rebase_job = Some((Arc::downgrade(&self.file.loader), job));
+                    self.status_message = Some(format!("Reloaded from disk; re-applying {} edit(s)...", count));
+                    return;
+                }
+                self.status_message = Some(match kind {
+                    Reindex::Full => "Reloaded from disk".to_string(),
+                    Reindex::Appended(added) => format!("Reloaded; {} new row(s), edits kept", added),
+                    Reindex::Unchanged => "Reloaded; contents unchanged".to_string(),
+                });
+            }
+            Err(e) => self.status_message = Some(format!("Reload failed: {:#}", e)),
+        }
+    }
+
+    /// Apply the edits a reload found rows for and list the rest. If the file
+    /// was re-read again meanwhile, every edit is listed as a conflict rather
+    /// than landing on the wrong rows.
+    fn finish_rebase(&mut self, base: &std::sync::Weak<CsvLoader>, mut rebase: Rebase) {
+        if !std::ptr::eq(base.as_ptr(), Arc::as_ptr(&self.file.loader)) {
+            let stale = rebase.edits.drain(..).map(|(r, col, disk, mine)| EditConflict {
+                row: r,
+                new_row: None,
+                col,
+                original: None,
+                disk: Some(disk),
+                mine,
+                reason: "The file was re-read again before it could be re-applied",
+            });
+            rebase.conflicts.extend(stale);
+        }
+        let kept = rebase.edits.len();
+        let conflicts = rebase.conflicts.len();
+        if kept > 0 {
+            self.apply_cell_edits(rebase.edits);
+        }
+        self.reload_conflicts = rebase.conflicts;
+        self.show_reload_conflicts = conflicts > 0;
+        self.status_message = Some(format!("Reloaded from disk; {} edit(s) re-applied, {} conflict(s)", kept, conflicts));
+    }
+
+    /// Pick up rows appended to the file since the last check, at most once a second
+    fn follow_file_tick(&mut self, now: f64) {
+        if !self.follow_file || self.document().in_memory() || now - self.follow_checked_at < 1.0 {
+            return;
+        }
+        self.follow_checked_at = now;
+        self.start_follow_scan();
+    }
+
+    /// Scan the followed file in the background if it changed size, only the
+    /// new bytes when it just grew. Returns whether a scan is on its way; none
+    /// starts while one is running or a save is about to replace the loader.
+    fn start_follow_scan(&mut self) -> bool {
+        if self.follow_job.is_some() || self.save_job.is_some() {
+            return true;
+        }
+        let path = std::path::PathBuf::from(&self.filename);
+        // Cheap check first; most ticks see no change
+        if std::fs::metadata(&path).map(|m| m.len()).ok() == Some(self.file.loader.byte_len()) {
+            return false;
+        }
+        let loader = self.file.loader.clone();
+        let dirty = self.file.edits.is_dirty();
+        self.follow_base = Arc::downgrade(&loader);
+        self.follow_job = Some(Job::spawn("Following file", move |_| {
+            if let Some(append) = loader.scan_append(&path)? {
+                return Ok(FollowUpdate::Appended(append));
+            }
+            // A rewrite would lose the edits' rows, so it isn't even read
+            if dirty {
+                return Ok(FollowUpdate::Conflict);
+            }
+            let (preamble, footer) = (loader.preamble_lines(), loader.footer_rows());
+            drop(loader);
+            Ok(FollowUpdate::Rewritten(CsvLoader::new(&path)?.with_preamble(preamble).with_footer(footer)))
+        }));
+        true
+    }
+
+    /// Apply what a Follow File scan found, unless the loader it scanned from
+    /// was replaced meanwhile (the next tick scans again)
+    fn finish_follow_scan(&mut self, update: anyhow::Result<FollowUpdate>) {
+        if !std::ptr::eq(self.follow_base.as_ptr(), Arc::as_ptr(&self.file.loader)) {
+            return;
+        }
+        match update {
+            Ok(FollowUpdate::Appended(append)) => {
+                let was_at_end = self.selected_cell.is_none_or(|(r, _)| r + 1 >= self.file.loader.total_records());
+                let (loader, added) = CsvLoader::with_append(self.file.take_loader(), append);
+                // Bytes before the append stay put, so the text view keeps its picks
+                let picked = (self.text_selection, self.hex_line);
+                self.swap_loader(loader);
+                (self.text_selection, self.hex_line) = picked;
+                if added > 0 {
+                    self.status_message = Some(format!("{} new row(s)", added));
+                    if was_at_end {
+                        self.scroll_to_row = Some(self.file.loader.total_records().saturating_sub(1));
+                    }
+                }
+            }
+            Ok(FollowUpdate::Rewritten(loader)) => {
+                self.swap_loader(loader);
+                self.status_message = Some("The file was rewritten on disk and has been re-read".to_string());
+            }
+            Ok(FollowUpdate::Conflict) => {
+                self.follow_file = false;
+                self.file_changed = true;
+                self.status_message = Some("The file was rewritten on disk; stopped following to keep your edits".to_string());
+            }
+            Err(e) => {
+                self.follow_file = false;
+                self.file_changed = true;
+                self.status_message = Some(format!("Stopped following: {:#}", e));
+            }
+        }
+    }
+
+    /// Sort rows by a column. A grid is sorted in place as one undo step; a
+    /// file-backed document is written to a sorted copy, spilling sort runs
+    /// to disk beyond the memory budget, and the copy is then opened.
+    fn sort_by_column(&mut self, col: usize, order: SortOrder, budget_mb: u64) {
+        if self.document().in_memory() {
+            let headers = self.document().headers();
+            let mut rows: Vec<Vec<String>> = self.document().rows(0..usize::MAX).collect();
+            rows.sort_by(|a, b| {
+                order.apply(sort::compare_values(a.get(col).map_or("", String::as_str), b.get(col).map_or("", String::as_str)))
+            });
+            if self.restructure(|document| document.replace_table(headers, rows)).is_some() {
+                self.status_message = Some(format!("Sorted by {} ({})", self.column_header(col), order.name()));
+            }
+            return;
+        }
+
+        let path = std::path::Path::new(&self.filename);
+        let stem = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_else(|| "data".to_string());
+        let mut dialog = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .set_file_name(format!("{}_sorted.csv", stem));
+        if let Some(dir) = path.parent().filter(|d| d.is_dir()) {
+            dialog = dialog.set_directory(dir);
+        }
+        let Some(output) = dialog.save_file() else {
+            return;
+        };
+        if output == path {
+            self.status_message = Some("Choose a different file for the sorted copy".to_string());
+            return;
+        }
+        let loader = self.file.loader.clone();
+        let edits = self.file.edits.clone();
+        // Keep half the budget for the sort runs; the rest covers the merge and the UI
+        let run_bytes = (budget_mb as usize * 1024 * 1024 / 2).max(16 * 1024 * 1024);
+        self.sort_job = Some(Job::spawn(format!("Sorting by {}", self.column_header(col)), move |progress| {
+            sort::sort_loader(&loader, &edits, col, order, &output, run_bytes, progress)?;
+            Ok(output)
+        }));
+    }
+
+    /// Queue background profiling of a column unless a fresh profile is cached
+    fn request_profile(&mut self, col: usize) {
+        let generation = self.column_generation(col);
+        if self.profile_cache.is_current(col, generation) {
+            return;
+        }
+        let header = self.column_header(col);
+        let (total, value_at) = self.column_reader(col);
+        // For mmap files, sample up to 1000 rows
+        let sample_size = if self.document().in_memory() { total } else { total.min(1000) };
+        self.profile_cache.request(col, generation, move || {
+            let values: Vec<String> = (0..sample_size).map(value_at).collect();
+            ColumnAnalyzer::analyze_column(&header, col, &values)
+        });
+    }
+
+    /// Label and progress of the background job to show in the status bar
+    fn active_job(&self) -> Option<(&str, &Progress)> {
+        self.active_jobs().into_iter().next()
+    }
+
+    /// Every running background job, for the performance overlay
+    fn active_jobs(&self) -> Vec<(&str, &Progress)> {
+        [
+            self.save_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.split_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.schema_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.schema_generate_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.duplicate_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.script_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.handoff_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.export_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.copy_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.print_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.normalize_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.rewrite_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.rolling_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.rebase_job.as_ref().map(|(_, job)| (job.label.as_str(), job.progress())),
+            self.convert_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.sort_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.checksum_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.key_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.geo_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.invalid_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.problems_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.filter_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.distinct_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.group_by_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.sparkline_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.scatter_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.snapshot_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.snapshot_diff_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.snapshot_restore_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.git_diff_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.file_tables_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.quick_calc_job.as_ref().map(|job| (job.label.as_str(), job.progress())),
+            self.graph_job.as_ref().map(|(_, job)| (job.label.as_str(), job.progress())),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    /// Start scanning a column for repeated values
+    fn find_duplicates(&mut self, col: usize) {
+        let (total, value_at) = self.column_reader(col);
+        self.duplicate_job = Some(Job::spawn("Finding duplicates", move |progress| {
+            ColumnAnalyzer::find_duplicates(col, total, value_at, progress)
+        }));
+        self.duplicates = None;
+    }
+
+    /// Start grouping a column's near-duplicate values for the normalization wizard
+    fn cluster_column(&mut self, col: usize, max_distance: usize) {
+        let (total, value_at) = self.column_reader(col);
+        let rows = self.first_data_row()..total;
+        self.normalize_job = Some(Job::spawn("Clustering values", move |progress| {
+            let counts = normalize::count_values(rows, value_at, progress);
+            normalize::cluster_values(&counts, max_distance)
+        }));
+    }
+
+    /// Rewrite mapped values across the whole column; applied as one undo step
+    fn apply_normalization(&mut self, col: usize, mapping: std::collections::HashMap<String, String>) {
+        let (total, value_at) = self.column_reader(col);
+        let rows = self.first_data_row()..total;
+        self.rewrite_job = Some(Job::spawn("Normalizing values", move |progress| {
+            normalize::normalization_edits(col, rows, value_at, &mapping, progress)
+        }));
+    }
+
+    /// Mask every value of a column; applied as one undo step
+    fn anonymize_column(&mut self, col: usize, transform: MaskTransform) {
+        let (total, value_at) = self.column_reader(col);
+        let rows = self.first_data_row()..total;
+        self.rewrite_job = Some(Job::spawn(format!("Applying {}", transform.name()), move |progress| {
+            anonymize::mask_edits(col, rows, value_at, &transform, progress)
+        }));
+    }
+
+    /// Rewrite a column's dates into another format; applied as one undo step
+    fn reformat_dates(&mut self, col: usize, reformat: crate::backend::dates::DateReformat) {
+        let (total, value_at) = self.column_reader(col);
+        let rows = self.first_data_row()..total;
+        self.rewrite_job = Some(Job::spawn("Reformatting dates", move |progress| {
+            crate::backend::dates::reformat_edits(col, rows, value_at, &reformat, progress)
+        }));
+    }
+
+    /// Convert a numeric column to another unit; applied as one undo step
+    fn convert_units(&mut self, col: usize, transform: crate::backend::units::UnitTransform) {
+        let (total, value_at) = self.column_reader(col);
+        let rows = self.first_data_row()..total;
+        self.rewrite_job = Some(Job::spawn("Converting units", move |progress| {
+            crate::backend::units::convert_edits(col, rows, value_at, &transform, progress)
+        }));
+    }
+
+    /// Insert a column per capture group right after `col`, as one undo step
+    fn extract_with_regex(&mut self, col: usize, regex: &regex::Regex) {
+        let header = self.column_header(col);
+        let (total, value_at) = self.document().column_reader(col);
+        let values: Vec<String> = (0..total).map(value_at).collect();
+        let columns = crate::backend::extract::extract_columns(regex, values.iter().map(String::as_str));
+        let headers = crate::backend::extract::group_headers(regex, &header);
+        let count = columns.len();
+        if self.restructure(|document| document.insert_columns(col + 1, headers.into_iter().zip(columns).collect())).is_some() {
+            self.status_message = Some(format!("Extracted {} column(s) from {}", count, header));
+        }
+    }
+
+    /// Work out a rolling calculation over a column in the background, to be
+    /// inserted right after it by `finish_rolling_column`
+    fn add_rolling_column(&mut self, request: RollingRequest) {
+        if !self.document().in_memory() {
+            self.status_message = Some("Inserting columns requires an editable grid".to_string());
+            return;
+        }
+        let header = format!("{} {}", self.column_header(request.column), request.function.name());
+        let (total, value_at) = self.document().column_reader(request.column);
+        let order = request.order_by.map(|c| self.document().column_reader(c).1);
+        let sources = std::iter::once(request.column).chain(request.order_by)
+            .map(|c| (c, self.column_generation(c)))
+            .collect();
+        self.rolling_job = Some(Job::spawn(format!("Calculating {}", header), move |progress| {
+            let values: Vec<String> = (0..total).map(&value_at).collect();
+            let order: Option<Vec<String>> = order.map(|order_at| (0..total).map(order_at).collect());
+            let result = crate::backend::aggregate::rolling_column(&values, order.as_deref(), request.function, request.window, progress);
+            (!progress.is_cancelled()).then_some((sources, header, result))
+        }));
+    }
+
+    fn finish_rolling_column(&mut self, (sources, header, result): RollingColumn) {
+        let unchanged = sources.iter().all(|&(c, generation)| c < self.num_columns && self.column_generation(c) == generation);
+        if !unchanged || self.document().row_count() != result.len() {
+            self.status_message = Some(format!("The table changed while working out {}; run it again", header));
+            return;
+        }
+        let at = sources[0].0 + 1;
+        if self.restructure(|document| document.insert_columns(at, vec![(header.clone(), result)])).is_some() {
+            self.status_message = Some(format!("Added {}", header));
+        }
+    }
+
+    /// Compare `col` with the other column of a two-column selection, or
+    /// else the column displayed after it
+    fn combine_dialog(&mut self, col: usize) {
+        let columns = self.display_columns();
+        let block = self.selection_block().map(|(_, positions)| columns[positions].to_vec());
+        let other = match block.as_deref() {
+            Some(&[a, b]) if a == col => b,
+            Some(&[a, b]) if b == col => a,
+            _ => {
+                let position = columns.iter().position(|&c| c == col).unwrap_or(0);
+                columns.get(position + 1).or(position.checked_sub(1).and_then(|p| columns.get(p))).copied().unwrap_or(col)
+            }
+        };
+        self.combine_window.load(col, other, self.source_headers());
+        self.show_combine = true;
+    }
+
+    fn add_combined_column(&mut self, request: CombineRequest) {
+        let header = request.combine.header(&self.column_header(request.a), &self.column_header(request.b));
+        let column = |c: usize| -> Vec<String> {
+            let (total, value_at) = self.document().column_reader(c);
+            (0..total).map(value_at).collect()
+        };
+        let result = aggregate::combine_columns(&column(request.a), &column(request.b), request.combine);
+        let at = request.a.max(request.b) + 1;
+        if self.restructure(|document| document.insert_columns(at, vec![(header.clone(), result)])).is_some() {
+            self.status_message = Some(format!("Added {}", header));
+        }
+    }
+
+    /// Open the geo check on the detected latitude/longitude pair, checking it straight away
+    fn geo_dialog(&mut self) {
+        let headers = self.source_headers();
+        let first = self.first_data_row();
+        let (total, row_at) = self.row_reader();
+        let samples: Vec<Vec<String>> = (first..total.min(first + TRANSFORM_SAMPLE_ROWS)).map(row_at).collect();
+        let pair = crate::backend::geo::detect_lat_lon(&headers, &samples);
+        self.geo_window.load(headers, pair);
+        self.show_geo = true;
+        if let Some((lat, lon)) = pair {
+            self.check_coordinates(lat, lon);
+        }
+    }
+
+    /// Validate a latitude/longitude pair over every row in the background
+    fn check_coordinates(&mut self, lat: usize, lon: usize) {
+        let (total, row_at) = self.row_reader();
+        let rows = self.first_data_row()..total;
+        self.geo_job = Some(Job::spawn("Checking coordinates", move |progress| {
+            let pair_at = |r| {
+                let row = row_at(r);
+                let cell = |c: usize| row.get(c).cloned().unwrap_or_default();
+                (cell(lat), cell(lon))
+            };
+            crate::backend::geo::check_coordinates(rows, pair_at, progress)
+        }));
+    }
+
+    fn inspect_cell(&mut self, row: usize, col: usize) {
+        self.cell_inspector.load(row, col, self.column_header(col), self.cell_value(row, col));
+        self.show_cell_inspector = true;
+    }
+
+    fn file_info_dialog(&mut self) {
+        let rows = self.document().row_count().saturating_sub(self.first_data_row());
+        let access = if self.document().in_memory() {
+            Some("Loaded into memory")
+        } else {
+            self.file.loader.source_kind()
+        };
+        self.file_info_window.load(std::path::PathBuf::from(&self.filename), rows, self.num_columns, access);
+        self.show_file_info = true;
+    }
+
+    /// Apply edits computed by a column-wide rewrite as a single undo step
+    fn apply_cell_edits(&mut self, edits: Vec<CellEdit>) -> usize {
+        let count = edits.len();
+        let in_memory = self.document().in_memory();
+        let headers = !in_memory && edits.iter().any(|&(r, ..)| r == 0);
+        if !in_memory {
+            self.record_edit_bases(edits.iter().map(|&(r, ..)| r));
+        }
+        self.document_mut().set_cells(edits);
+        if headers {
+            self.recompile_row_colors();
+        }
+        count
+    }
+
+    /// Start scanning every column (and pairs of columns) for unique, non-null keys
+    fn find_key_candidates(&mut self) {
+        let num_cols = self.num_columns;
+        let generations: Vec<u64> = (0..num_cols).map(|c| self.column_generation(c)).collect();
+        let first = self.first_data_row();
+        let (total, row_at) = self.row_reader();
+        self.key_job = Some(Job::spawn("Finding key candidates", move |progress| {
+            (generations, ColumnAnalyzer::find_key_candidates(num_cols, first..total, row_at, progress))
+        }));
+    }
+
+    /// Key candidates found before any column was edited since; edits can
+    /// make a key column repeat, so older results aren't shown
+    fn current_key_candidates(&self) -> Option<&KeyCandidates> {
+        let (generations, keys) = self.key_candidates.as_ref()?;
+        let current = generations.len() == self.num_columns
+            && generations.iter().enumerate().all(|(c, &generation)| self.column_generation(c) == generation);
+        current.then_some(keys)
+    }
+
+    /// Start listing a column's values that aren't a valid `semantic_type`
+    fn find_invalid_values(&mut self, col: usize, semantic_type: SemanticType) {
+        let (total, value_at) = self.column_reader(col);
+        let rows = self.first_data_row()..total;
+        self.invalid_job = Some(Job::spawn(format!("Checking {} values", semantic_type.name()), move |progress| {
+            ColumnAnalyzer::find_invalid(col, semantic_type, rows, value_at, progress)
+        }));
+        self.invalid_values = None;
+    }
+
+    /// Scan every row for parse, encoding, ragged-row and type problems in the background
+    fn scan_problems(&mut self) {
+        let num_cols = self.num_columns;
+        let types = self.column_types();
+        let (total, row_at) = self.document().scan_reader();
+        let rows = self.first_data_row()..total;
+        self.problems_job = Some(Job::spawn("Scanning for problems", move |progress| {
+            crate::backend::problems::scan(rows, num_cols, &types, row_at, progress)
+        }));
+    }
+
+    /// Open the distinct-values browser on a column and start counting its values
+    fn browse_distinct(&mut self, col: usize) {
+        let (total, value_at) = self.column_reader(col);
+        let rows = self.first_data_row()..total;
+        self.distinct_window.load(col, self.column_header(col));
+        self.show_distinct = true;
+        self.distinct_job = Some(Job::spawn("Counting distinct values", move |progress| {
+            ColumnAnalyzer::distinct_values(col, rows, value_at, progress)
+        }));
+    }
+
+    /// Draw sparklines for those of `columns` profiled as numeric whose line
+    /// is missing or out of date, sampling rows in the background
+    fn request_sparklines(&mut self, columns: &[usize]) {
+        if self.sparkline_job.is_some() {
+            return;
+        }
+        let total = self.document().row_count();
+        let types = self.column_types();
+        let stale: Vec<(usize, u64)> = columns.iter()
+            .filter(|&&c| matches!(types.get(c), Some(Some(InferredType::Integer | InferredType::Float))))
+            .map(|&c| (c, self.column_generation(c)))
+            .filter(|&(c, generation)| !self.sparklines.get(&c).is_some_and(|line| line.0 == generation && line.1 == total))
+            .collect();
+        if stale.is_empty() {
+            return;
+        }
+        let rows = self.first_data_row()..total;
+        let (_, row_at) = self.row_reader();
+        self.sparkline_job = Some(Job::spawn("Drawing sparklines", move |progress| {
+            let cols: Vec<usize> = stale.iter().map(|&(c, _)| c).collect();
+            let lines = ColumnAnalyzer::sparklines(&cols, rows, row_at, progress);
+            stale.into_iter().zip(lines).map(|((c, generation), line)| (c, (generation, total, line))).collect()
+        }));
+    }
+
+    /// What the graph would be read from now; the plot is stale when this changes
+    fn current_graph_source(&self) -> GraphSource {
+        let total = self.document().row_count();
+        (
+            (self.graph_x_col, self.column_generation(self.graph_x_col)),
+            (self.graph_y_col, self.column_generation(self.graph_y_col)),
+            total,
+            self.filter_generation,
+            self.graph_resample.map(|bucket| (bucket, self.graph_resample_aggregate)),
+            self.graph_histogram.then_some(self.graph_bins),
+        )
+    }
+
+    /// Re-read the plotted points in the background, edits included, if the
+    /// columns, their cells, the filter or the resampling changed since the
+    /// last read. Only rows passing the filter are plotted. X is read as
+    /// timestamps when its first value is one rather than a number.
+    fn refresh_graph(&mut self) {
+        let source = self.current_graph_source();
+        if self.graph_source == Some(source) || self.graph_job.as_ref().is_some_and(|(pending, _)| *pending == source) {
+            return;
+        }
+        if let Some((_, job)) = self.graph_job.take() {
+            job.progress().cancel();
+        }
+        let first = self.first_data_row();
+        let (total, x_at) = self.column_reader(self.graph_x_col);
+        let (_, y_at) = self.column_reader(self.graph_y_col);
+        let rows: Box<dyn Iterator<Item = usize> + Send> = match self.filtered_rows {
+            Some(ref rows) => Box::new(rows.clone().into_iter().filter(move |&r| r >= first)),
+            None => Box::new(first..total),
+        };
+        let (resample, bins) = (source.4, source.5);
+        let job = Job::spawn("Reading graph data", move |progress| {
+            // Points are drawn from the first rows only; resampling and the histogram cover them all
+            let limit = if resample.is_some() || bins.is_some() { usize::MAX } else { GRAPH_MAX_ROWS };
+            progress.set_total(total.min(limit));
+            let mut read = GraphRead { data: Vec::new(), rows: Vec::new(), x_time: false, resampled: None, bars: None };
+            let mut values = Vec::new();
+            let mut resampler = resample.map(|(bucket, _)| aggregate::Resampler::new(bucket));
+            let mut time = None;
+            for (i, r) in rows.take(limit).enumerate() {
+                if i % 10_000 == 0 {
+                    if progress.is_cancelled() {
+                        return None;
+                    }
+                    progress.set_done(i);
+                }
+                let x_value = x_at(r);
+                if time.is_none() && !x_value.trim().is_empty() {
+                    time = Some(x_value.trim().parse::<f64>().is_err() && dates::parse_datetime(&x_value).is_some());
+                }
+                let x = match time {
+                    Some(true) => dates::parse_datetime(&x_value).map(|t| t.and_utc().timestamp() as f64),
+                    _ => x_value.trim().parse::<f64>().ok(),
+                };
+                let y = y_at(r).trim().parse::<f64>().ok().filter(|y| y.is_finite());
+                let shown = i < GRAPH_MAX_ROWS;
+                if let (Some(y), Some(_)) = (y, bins) {
+                    values.push(y);
+                }
+                if let (Some(x), Some(y)) = (x, y) {
+                    if shown {
+                        read.data.push([x, y]);
+                        read.rows.push(r);
+                    }
+                    if let (Some(resampler), Some(true)) = (resampler.as_mut(), time) {
+                        resampler.add(x, y);
+                    }
+                }
+            }
+            read.x_time = time == Some(true);
+            if let (Some(resampler), Some((_, aggregate)), true) = (resampler, resample, read.x_time) {
+                read.resampled = Some(resampler.finish(aggregate));
+            }
+            read.bars = bins.map(|bins| (ColumnAnalyzer::histogram(&values, bins), values.len()));
+            Some(read)
+        });
+        self.graph_job = Some((source, job));
+    }
+
+    /// Take on what a finished `graph_job` read
+    fn finish_graph_read(&mut self, source: GraphSource, read: GraphRead) {
+        self.graph_plot = read.resampled.unwrap_or_else(|| read.data.clone());
+        self.graph_data = read.data;
+        self.graph_rows = read.rows;
+        self.graph_x_time = read.x_time;
+        if let Some((bars, count)) = read.bars {
+            self.graph_bars = bars;
+            self.graph_value_count = count;
+        }
+        self.graph_brushed.clear();
+        self.graph_source = Some(source);
+    }
+
+    /// Notice rows inserted, deleted or reordered in the grid since the last
+    /// frame: brushed rows and the pinned baseline can't follow them, so
+    /// they're dropped, and the rest of the filter runs again
+    fn check_row_order(&mut self) {
+        let generation = self.document().row_generation();
+        if generation == self.row_generation {
+            return;
+        }
+        self.row_generation = generation;
+        if self.baseline_row.take().is_some() {
+            self.status_message = Some("Rows changed, so the baseline was unpinned".to_string());
+        }
+        let brushed = self.row_filter.rows.take().is_some();
+        if brushed {
+            self.status_message = Some("Rows changed, so the rows brushed in the graph were dropped from the filter".to_string());
+        }
+        if brushed || !self.row_filter.is_empty() {
+            self.apply_filter();
+        }
+    }
+
+    /// Show only the rows of the brushed points in the table, selecting the first
+    fn filter_to_brushed(&mut self) {
+        let rows: std::collections::HashSet<usize> = self.graph_brushed.iter().map(|&i| self.graph_rows[i]).collect();
+        let Some(&first) = rows.iter().min() else {
+            return;
+        };
+        self.row_filter.rows = Some(rows);
+        self.apply_filter();
+        self.selected_cell = Some((first, self.graph_y_col));
+        self.scroll_to_row = Some(first);
+        self.view_mode = ViewMode::Table;
+    }
+
+    /// The series the graph view is drawing, as headers and rows: histogram
+    /// bins, or the (possibly resampled) points with timestamps written out
+    fn plotted_table(&self) -> (Vec<String>, Vec<Vec<String>>) {
+        use crate::backend::aggregate::format_number;
+        let headers = self.source_headers();
+        let header = |c: usize| headers.get(c).cloned().unwrap_or_else(|| self.column_header(c));
+        if self.graph_histogram {
+            let bars = &self.graph_bars;
+            let rows = bars.counts.iter()
+                .enumerate()
+                .map(|(i, count)| {
+                    let start = bars.start + bars.width * i as f64;
+                    vec![format_number(start), format_number(start + bars.width), count.to_string()]
+                })
+                .collect();
+            let name = header(self.graph_y_col);
+            return (vec![format!("{} from", name), format!("{} to", name), "rows".to_string()], rows);
+        }
+        let resample = self.graph_resample.filter(|_| self.graph_x_time);
+        let x_header = match resample {
+            Some(bucket) => format!("{} ({})", header(self.graph_x_col), bucket.name()),
+            None => header(self.graph_x_col),
+        };
+        let y_header = match resample {
+            Some(_) if self.graph_resample_aggregate == Aggregate::Count => "count".to_string(),
+            Some(_) => format!("{} {}", self.graph_resample_aggregate.name(), header(self.graph_y_col)),
+            None => header(self.graph_y_col),
+        };
+        let rows = self.graph_plot.iter()
+            .map(|&[x, y]| {
+                let x = if self.graph_x_time {
+                    chrono::DateTime::from_timestamp(x as i64, 0).map(|t| t.format("%Y-%m-%dT%H:%M:%S").to_string()).unwrap_or_default()
+                } else {
+                    format_number(x)
+                };
+                vec![x, format_number(y)]
+            })
+            .collect();
+        (vec![x_header, y_header], rows)
+    }
+
+    /// Save the plotted series as CSV or JSON
+    fn export_plotted_data(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .add_filter("JSON", &["json"])
+            .set_file_name("plotted-data.csv")
+            .save_file()
+        else {
+            return;
+        };
+        let (headers, rows) = self.plotted_table();
+        self.status_message = Some(match crate::backend::export::write_table(&headers, &rows, &path) {
+            Ok(()) => format!("Saved {} plotted point(s) to {}", rows.len(), path.display()),
+            Err(e) => format!("Failed to save plotted data: {:#}", e),
+        });
+    }
+
+    /// Scroll the text view to the record holding a byte offset, given in
+    /// decimal or as 0x hex, and show that record's bytes
+    fn go_to_byte_offset(&mut self) {
+        let text = self.byte_offset_draft.trim();
+        let offset = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+            Some(hex) => u64::from_str_radix(hex, 16).ok(),
+            None => text.replace(['_', ','], "").parse::<u64>().ok(),
+        };
+        let Some(offset) = offset else {
+            self.status_message = Some(format!("\"{}\" isn't a byte offset", text));
+            return;
+        };
+        match self.file.loader.record_at_offset(offset) {
+            Some(line) => {
+                self.text_scroll_to = Some(line);
+                self.hex_line = Some((line, Some(offset)));
+            }
+            None => {
+                self.status_message = Some(format!("Byte {} is past the end of the file ({} bytes)", offset, self.file.loader.byte_len()));
+            }
+        }
+    }
+
+    /// The file's usual line ending, judged from its first 64 KB of records
+    fn dominant_line_ending(&mut self) -> file_info::LineEnding {
+        if let Some(ending) = self.text_line_ending {
+            return ending;
+        }
+        let mut sample = Vec::new();
+        let mut line = 0;
+        while sample.len() < 64 * 1024
+            && let Some(bytes) = self.file.loader.get_record_line(line)
+        {
+            sample.extend_from_slice(&bytes);
+            line += 1;
+        }
+        let ending = file_info::LineEnding::dominant(&sample);
+        self.text_line_ending = Some(ending);
+        ending
+    }
+
+    /// Open the Problems panel on its encoding problems
+    fn show_encoding_problems(&mut self) {
+        self.show_problems = true;
+        self.problem_category = Some(ProblemCategory::Encoding);
+        if self.problems.is_none() && self.problems_job.is_none() {
+            self.scan_problems();
+        }
+    }
+
+    /// The lines picked in the text view, first to last
+    fn selected_lines(&self) -> Option<std::ops::RangeInclusive<usize>> {
+        self.text_selection.map(|(a, b)| a.min(b)..=a.max(b))
+    }
+
+    /// Write the picked lines to a file byte for byte
+    fn export_selected_lines(&mut self) {
+        let Some(lines) = self.selected_lines() else {
+            return;
+        };
+        let Some(bytes) = self.file.loader.get_record_range(*lines.start()..lines.end() + 1) else {
+            return;
+        };
+        let Some(path) = rfd::FileDialog::new()
+            .set_file_name(format!("lines-{}-{}.csv", lines.start(), lines.end()))
+            .save_file()
+        else {
+            return;
+        };
+        self.status_message = Some(match std::fs::write(&path, &bytes) {
+            Ok(()) => format!("Saved lines {}–{} ({}) to {}", lines.start(), lines.end(), file_info::format_size(bytes.len() as u64), path.display()),
+            Err(e) => format!("Failed to save lines: {}", e),
+        });
+    }
+
+    /// Sample rows of `columns` for the scatter matrix in the background
+    fn sample_scatter_matrix(&mut self, columns: Vec<usize>) {
+        let first = self.first_data_row();
+        let (total, row_at) = self.row_reader();
+        self.scatter_job = Some(Job::spawn("Sampling rows", move |progress| {
+            ColumnAnalyzer::sample_columns(&columns, first..total, MATRIX_SAMPLE_ROWS, row_at, progress)
+        }));
+    }
+
+    /// Summarize every row by group in the background
+    fn group_by(&mut self, spec: GroupBySpec) {
+        let headers = self.source_headers();
+        let first = self.first_data_row();
+        let (total, row_at) = self.row_reader();
+        self.group_by_job = Some(Job::spawn("Grouping rows", move |progress| {
+            crate::backend::aggregate::group_by(&spec, &headers, first..total, row_at, progress)
+        }));
+    }
+
+    /// Save the group-by summary as CSV, or write it to a temporary file and open that
+    fn save_group_summary(&mut self, open: bool) {
+        let Some(summary) = self.group_by_window.summary() else {
+            return;
+        };
+        let path = if open {
+            let stamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+            std::env::temp_dir().join(format!("csvit_group_by_{}.csv", stamp))
+        } else {
+            match rfd::FileDialog::new().add_filter("CSV", &["csv"]).set_file_name("group-by.csv").save_file() {
+                Some(path) => path,
+                None => return,
+            }
+        };
+        match summary.write_csv(&path) {
+            Ok(()) if open => self.dispatch(Action::OpenPath(path)),
+            Ok(()) => self.status_message = Some(format!("Saved {} groups to {}", summary.rows.len(), path.display())),
+            Err(e) => self.status_message = Some(format!("Failed to save the summary: {:#}", e)),
+        }
+    }
+
+    /// Save the distinct-values browser's list as CSV
+    fn export_distinct(&mut self) {
+        let Some(result) = self.distinct_window.result() else {
+            return;
+        };
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .set_file_name("distinct-values.csv")
+            .save_file()
+        else {
+            return;
+        };
+        self.status_message = Some(match result.write_csv(self.distinct_window.header(), &path) {
+            Ok(()) => format!("Saved {} distinct values to {}", result.values.len(), path.display()),
+            Err(e) => format!("Failed to save distinct values: {:#}", e),
+        });
+    }
+
+    /// Narrow the table by another condition
+    fn add_filter(&mut self, condition: FilterCondition) {
+        self.row_filter.add(condition);
+        self.apply_filter();
+    }
+
+    /// Work out which rows pass the filter in the background; the table shows
+    /// every row until it's done. File-backed documents keep their header row.
+    fn apply_filter(&mut self) {
+        self.filtered_rows = None;
+        self.filter_generation += 1;
+        self.filter_job = None;
+        if self.row_filter.is_empty() {
+            return;
+        }
+        let filter = self.row_filter.clone();
+        let first = self.first_data_row();
+        let (total, row_at) = self.row_reader();
+        self.filtered_total = total;
+        self.filter_job = Some(Job::spawn("Filtering rows", move |progress| {
+            let mut rows: Vec<usize> = (0..first).collect();
+            rows.extend(filter.matching_rows(first..total, row_at, progress));
+            rows
+        }));
+    }
+
+    /// Where `row` sits among the rows on screen (or would, when filtered out)
+    fn display_row(&self, row: usize) -> usize {
+        match self.filtered_rows {
+            Some(ref rows) => rows.binary_search(&row).unwrap_or_else(|i| i),
+            None => row,
+        }
+    }
+
+    /// Displayed rows and column positions spanned by the selection: the
+    /// block out to the anchor, or just the selected cell
+    fn selection_block(&self) -> Option<(std::ops::RangeInclusive<usize>, std::ops::RangeInclusive<usize>)> {
+        let cell = self.selected_cell?;
+        let anchor = self.selection_anchor.unwrap_or(cell);
+        let cols = self.display_columns();
+        let position = |c: usize| cols.iter().position(|&x| x == c);
+        let (a, b) = (position(anchor.1)?, position(cell.1)?);
+        let (r1, r2) = (self.display_row(anchor.0), self.display_row(cell.0));
+        Some((r1.min(r2)..=r1.max(r2), a.min(b)..=a.max(b)))
+    }
+
+    /// Sum, mean, count and the like of the selected cells, straight from the
+    /// rows on screen; a header row inside the block isn't counted
+    fn quick_calc(&mut self) {
+        let Some((rows, positions)) = self.selection_block() else {
+            self.status_message = Some("Select some cells first".to_string());
+            return;
+        };
+        let columns: Vec<usize> = self.display_columns()[positions].to_vec();
+        let first = self.first_data_row();
+        let cells = rows.clone().count().saturating_mul(columns.len());
+        // Only the filtered rows inside the block are copied out, never the whole filter
+        let rows: Box<dyn Iterator<Item = usize> + Send> = match self.filtered_rows {
+            Some(ref shown) => Box::new(shown.get(rows).unwrap_or_default().to_vec().into_iter()),
+            None => Box::new(rows),
+        };
+        if cells <= QUICK_CALC_INLINE_CELLS {
+            let document = self.document();
+            let rows = rows.filter(|&r| r >= first && r < document.row_count());
+            self.quick_calc = count_selection(rows, columns.len(), |r| document.row_projected(r, &columns), &Progress::default());
+            return;
+        }
+        let (total, row_at) = self.row_reader();
+        self.quick_calc = None;
+        self.quick_calc_job = Some(Job::spawn("Calculating selection", move |progress| {
+            progress.set_total(cells.min(QUICK_CALC_MAX_CELLS));
+            let rows = rows.filter(|&r| r >= first && r < total);
+            let project = |r: usize| {
+                let values = row_at(r);
+                columns.iter().map(|&c| values.get(c).cloned().unwrap_or_default()).collect()
+            };
+            count_selection(rows, columns.len(), project, progress)
+        }));
+    }
+
+    /// The row `delta` rows away from `row` on screen, skipping filtered-out rows
+    fn step_row(&self, row: usize, delta: isize, total: usize) -> usize {
+        match self.filtered_rows {
+            Some(ref rows) if !rows.is_empty() => {
+                let at = match rows.binary_search(&row) {
+                    Ok(i) => i as isize + delta,
+                    // Between two shown rows: step onto the neighbour in that direction
+                    Err(i) => i as isize + delta.min(0),
+                };
+                rows[at.clamp(0, rows.len() as isize - 1) as usize]
+            }
+            _ => row.saturating_add_signed(delta).min(total.saturating_sub(1)),
+        }
+    }
+
+    /// First row holding data: file-backed documents keep their header in row 0
+    fn first_data_row(&self) -> usize {
+        self.document().first_data_row()
+    }
+
+    /// Validate the document against the attached schema in the background
+    fn validate_schema(&mut self) {
+        let Some(schema) = self.schema.clone() else {
+            return;
+        };
+        let headers = self.source_headers();
+        let offset = self.first_data_row();
+        let (total, row_at) = self.row_reader();
+        self.schema_issues = None;
+        self.schema_job = Some(Job::spawn("Validating schema", move |progress| {
+            let mut issues = schema.validate(&headers, total.saturating_sub(offset), |r| row_at(r + offset), progress)?;
+            for issue in &mut issues {
+                issue.row = issue.row.map(|r| r + offset);
+            }
+            Ok(issues)
+        }));
+    }
+
+    /// Infer a schema from a sample of every column in the background
+    fn generate_schema(&mut self) {
+        let headers = self.source_headers();
+        let offset = self.first_data_row();
+        let (total, row_at) = self.row_reader();
+        self.schema_generate_job = Some(Job::spawn("Inferring schema", move |_| {
+            // Same 1000-row sample the profile HUD uses for file-backed documents
+            let end = total.min(offset + 1000);
+            let rows: Vec<Vec<String>> = (offset..end).map(row_at).collect();
+            let profiles: Vec<ColumnProfile> = headers.iter().enumerate()
+                .map(|(c, header)| {
+                    let values: Vec<String> = rows.iter().map(|r| r.get(c).cloned().unwrap_or_default()).collect();
+                    ColumnAnalyzer::analyze_column(header, c, &values)
+                })
+                .collect();
+            TableSchema::from_profiles(&profiles)
+        }));
+    }
+
+    /// Run a script in the background against a snapshot of the document
+    fn run_script(&mut self, code: String) {
+        let input = if self.document().in_memory() {
+            ScriptInput::Table { headers: self.document().headers(), rows: self.document().rows(0..usize::MAX).collect() }
+        } else {
+            let num_cols = self.num_columns;
+            let (total, row_at) = self.row_reader();
+            ScriptInput::File { total, num_cols, row_at }
+        };
+        self.script_job = Some(Job::spawn("Running script", move |_| {
+            crate::backend::scripting::run_script(&code, input)
+        }));
+    }
+
+    /// Apply what a finished script changed as a single undo step
+    fn apply_script_changes(&mut self, changes: ScriptChanges) -> usize {
+        match changes {
+            ScriptChanges::None => 0,
+            ScriptChanges::Table { headers, rows } => {
+                let count = rows.len();
+                self.restructure(|document| document.replace_table(headers, rows));
+                count
+            }
+            ScriptChanges::Cells(cells) => {
+                self.apply_cell_edits(cells)
+            }
+        }
+    }
+
+    /// Write the visible columns (with pending edits) to a temp CSV for pandas
+    fn open_in_pandas(&mut self) {
+        let header = Some(self.source_headers());
+        let columns = self.projection.clone();
+        let (total, row_at) = self.row_reader();
+        let first = self.first_data_row().min(total);
+        let (total, row_at) = (total - first, move |r: usize| row_at(r + first));
+        let stem = std::path::Path::new(&self.filename)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "data".to_string());
+        let stamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let path = crate::backend::handoff::handoff_dir().join(format!("{}_{}.csv", stem, stamp));
+        self.handoff_job = Some(Job::spawn("Preparing pandas handoff", move |progress| {
+            crate::backend::handoff::write_handoff_csv(&path, header, total, row_at, columns.as_deref(), progress)?;
+            Ok(path)
+        }));
+    }
+
+    /// Write the data rows to `path` in the background, as the Export dialog set up
+    fn export(&mut self, options: crate::backend::export::ExportOptions, path: std::path::PathBuf) {
+        let types = self.column_types();
+        let formats = self.cell_formats.clone();
+        let (total, row_at) = self.row_reader();
+        let data_start = self.first_data_row().min(total);
+        self.export_job = Some(Job::spawn(format!("Exporting {}", options.format.name()), move |progress| {
+            crate::backend::export::export_rows(&path, &options, data_start..total, row_at, &types, &formats, progress)
+        }));
+    }
+
+    /// Export with a saved preset to its destination, without asking
+    fn run_export_preset(&mut self, preset: &crate::backend::export::ExportPreset) {
+        let options = match preset.resolve(&self.source_headers()) {
+            Ok(options) => options,
+            Err(e) => {
+                self.status_message = Some(format!("Preset \"{}\": {}", preset.name, e));
+                return;
+            }
+        };
+        let path = preset.destination_for(std::path::Path::new(&self.filename), chrono::Local::now().date_naive());
+        if let Err(e) = crate::backend::export::ensure_not_input(std::path::Path::new(&self.filename), &path) {
+            self.status_message = Some(format!("Preset \"{}\": {}", preset.name, e));
+            return;
+        }
+        if let Some(dir) = path.parent().filter(|p| !p.as_os_str().is_empty())
+            && let Err(e) = std::fs::create_dir_all(dir)
+        {
+            self.status_message = Some(format!("Failed to create {}: {}", dir.display(), e));
+            return;
+        }
+        self.export(options, path);
+    }
+
+    /// Run the pipeline's steps on the first rows, for the Pipeline window
+    fn preview_pipeline(&self, steps: &[crate::backend::pipeline::PipelineStep]) -> anyhow::Result<Vec<Vec<String>>> {
+        let (total, row_at) = self.row_reader();
+        let data_start = self.first_data_row().min(total);
+        crate::backend::pipeline::preview_pipeline(steps, &self.source_headers(), data_start..total, row_at)
+    }
+
+    /// Run a pipeline on the document in the background, exporting to its destination
+    fn run_pipeline(&mut self, pipeline: crate::backend::pipeline::Pipeline) {
+        let path = pipeline.export.destination_for(std::path::Path::new(&self.filename), chrono::Local::now().date_naive());
+        if let Err(e) = crate::backend::export::ensure_not_input(std::path::Path::new(&self.filename), &path) {
+            self.status_message = Some(format!("Pipeline: {}", e));
+            return;
+        }
+        if let Some(dir) = path.parent().filter(|p| !p.as_os_str().is_empty())
+            && let Err(e) = std::fs::create_dir_all(dir)
+        {
+            self.status_message = Some(format!("Failed to create {}: {}", dir.display(), e));
+            return;
+        }
+        let headers = self.source_headers();
+        let (total, row_at) = self.row_reader();
+        let data_start = self.first_data_row().min(total);
+        self.export_job = Some(Job::spawn("Running pipeline", move |progress| {
+            crate::backend::pipeline::run_pipeline(&pipeline, &headers, data_start..total, row_at, &path, progress)
+        }));
+    }
+
+    /// Build TSV and HTML for the visible columns in the background.
+    /// `limit` counts data rows; the header row is always included.
+    fn copy_table(&mut self, limit: Option<usize>) {
+        let columns = self.display_columns();
+        let header = self.source_headers();
+        let (total, row_at) = self.row_reader();
+        let data_start = self.first_data_row().min(total);
+        let end = total.min(data_start + limit.unwrap_or(usize::MAX).min(COPY_TABLE_LIMIT));
+        self.copy_job = Some(Job::spawn("Copying table", move |progress| {
+            let project = |values: Vec<String>| -> Vec<String> {
+                columns.iter().map(|&c| values.get(c).cloned().unwrap_or_default()).collect()
+            };
+            let mut rows = Vec::with_capacity(end - data_start + 1);
+            rows.push(project(header));
+            progress.set_total(end);
+            for r in data_start..end {
+                if r % 10_000 == 0 {
+                    progress.set_done(r);
+                }
+                rows.push(project(row_at(r)));
+            }
+            let count = rows.len().saturating_sub(1);
+            (clipboard::to_tsv(&rows), clipboard::to_html_table(&rows), count)
+        }));
+    }
+
+    /// Ask for a destination and print the visible columns to a PDF in the background
+    fn print_dialog(&mut self) {
+        let Some(path) = rfd::FileDialog::new().add_filter("PDF", &["pdf"]).save_file() else {
+            return;
+        };
+        let columns = self.display_columns();
+        let headers = self.source_headers();
+        let layout = crate::backend::print::PrintLayout {
+            title: std::path::Path::new(&self.filename)
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| self.filename.clone()),
+            headers: columns.iter().map(|&c| headers.get(c).cloned().unwrap_or_default()).collect(),
+            widths: columns.iter().map(|&c| self.column_widths.get(c).copied().unwrap_or(DEFAULT_COLUMN_WIDTH)).collect(),
+        };
+        let offset = self.first_data_row();
+        let (total, row_at) = self.row_reader();
+        self.print_job = Some(Job::spawn("Printing", move |progress| {
+            let row = |r: usize| {
+                let values = row_at(r + offset);
+                columns.iter().map(|&c| values.get(c).cloned().unwrap_or_default()).collect()
+            };
+            crate::backend::print::print_pdf(&path, &layout, total.saturating_sub(offset), row, progress)
+        }));
+    }
+
+    /// Keep the first occurrence of each duplicated value and blank the rest
+    fn blank_duplicate_repeats(&mut self, report: &DuplicateReport) {
+        let col = report.column_index;
+        let count = report.repeat_rows.len();
+        let edits = report.repeat_rows.iter()
+            .map(|&r| (r, col, self.cell_value(r, col), String::new()))
+            .collect();
+        self.apply_cell_edits(edits);
+        self.status_message = Some(format!("Blanked {} repeated value(s)", count));
+    }
+
+    /// Open the Paste Special dialog for clipboard text
+    fn open_paste_special(&mut self, text: &str) {
+        if self.paste_window.load(text) {
+            self.show_paste_special = true;
+        } else {
+            self.status_message = Some("Clipboard is empty".to_string());
+        }
+    }
+
+    /// Color rows by `rules` from the next frame on
+    fn set_row_color_rules(&mut self, rules: Vec<RowColorRule>) {
+        self.row_colors = match RowColors::compile(&rules, &self.source_headers()) {
+            Ok(colors) => colors,
+            Err(e) => {
+                self.status_message = Some(format!("{:#}", e));
+                RowColors::default()
+            }
+        };
+        self.row_color_rules = rules;
+    }
+
+    /// Compile the row color rules again after the headers may have changed;
+    /// rules name their columns, which can now be elsewhere or gone
+    fn recompile_row_colors(&mut self) {
+        if !self.row_color_rules.is_empty() {
+            self.set_row_color_rules(self.row_color_rules.clone());
+        }
+    }
+
+    /// Open the snapshot browser on this file's snapshots
+    fn open_snapshots(&mut self) {
+        let list = snapshots::snapshot_dir(&self.filename).map(|dir| snapshots::list_snapshots(&dir)).unwrap_or_default();
+        self.snapshots_window.load(list, self.source_headers());
+        self.show_snapshots = true;
+    }
+
+    /// The document's header and data rows (edits applied), readable from a background thread
+    fn records(&self) -> (usize, impl Iterator<Item = Vec<String>> + Send + 'static) {
+        let headers = self.source_headers();
+        let first = self.first_data_row();
+        let (total, row_at) = self.row_reader();
+        (total.saturating_sub(first) + 1, std::iter::once(headers).chain((first..total).map(row_at)))
+    }
+
+    /// Ask git where the file stands, at most every few seconds. The status
+    /// job isn't listed in `active_jobs`; it's too routine to show a spinner for.
+    fn refresh_git_status(&mut self, now: f64) {
+        if self.git_status_job.is_some() || now - self.git_checked_at < GIT_STATUS_INTERVAL {
+            return;
+        }
+        self.git_checked_at = now;
+        let path = std::path::PathBuf::from(&self.filename);
+        if !path.is_file() {
+            self.git_status = None;
+            return;
+        }
+        self.git_status_job = Some(Job::spawn("Checking git status", move |_| git::file_status(&path)));
+    }
+
+    /// Whether there are changes that closing the document would lose
+    fn has_unsaved_edits(&self) -> bool {
+        self.document().is_modified()
+    }
+
+    /// Bring back a snapshot read by `snapshot_restore_job`. A grid takes it in
+    /// place, as one undo step; a file on disk is never overwritten, so the
+    /// copy opens as a new, untitled document instead.
+    fn restore_snapshot(&mut self, snapshot: Snapshot, headers: Vec<String>, rows: Vec<Vec<String>>) {
+        if self.document().in_memory() {
+            self.restructure(|document| document.replace_table(headers, rows));
+            self.status_message = Some(format!("Restored the snapshot from {} (undo to go back)", snapshot.created_text()));
+        } else {
+            self.dispatch(Action::OpenTable { filename: "Untitled.csv".to_string(), headers, rows });
+        }
+        self.show_snapshots = false;
+    }
+
+    /// Compare the document, unsaved edits included, with the file's last committed version
+    fn diff_against_head(&mut self) {
+        let path = std::path::PathBuf::from(&self.filename);
+        let (total, mut records) = self.records();
+        let matching = self.reload_match;
+        self.git_diff_job = Some(Job::spawn("Comparing with HEAD", move |progress| {
+            progress.set_total(total);
+            let current = records.next().unwrap_or_default();
+            let head = |visit: &mut dyn FnMut(Vec<String>)| git::read_head(&path, progress, visit);
+            let diff = snapshots::diff_snapshot(head, &current, records, matching, progress)?;
+            Ok((current, diff))
+        }));
+    }
+
+    fn snapshot_request(&mut self, request: SnapshotRequest) {
+        let Some(dir) = snapshots::snapshot_dir(&self.filename) else {
+            self.status_message = Some("No data folder to keep snapshots in".to_string());
+            return;
+        };
+        match request {
+            SnapshotRequest::Create(label) => {
+                let (total, records) = self.records();
+                self.snapshot_job = Some(Job::spawn("Creating snapshot", move |progress| {
+                    snapshots::create_snapshot(&dir, &label, total, records, progress)
+                }));
+            }
+            SnapshotRequest::Compare(snapshot) => {
+                let (total, mut records) = self.records();
+                let matching = self.reload_match;
+                self.snapshot_diff_job = Some(Job::spawn("Comparing with snapshot", move |progress| {
+                    progress.set_total(total.max(snapshot.rows + 1));
+                    let current = records.next().unwrap_or_default();
+                    let old = |visit: &mut dyn FnMut(Vec<String>)| snapshots::read_snapshot(&snapshot.path, progress, visit);
+                    let diff = snapshots::diff_snapshot(old, &current, records, matching, progress)?;
+                    Ok((snapshot.label, diff))
+                }));
+            }
+            SnapshotRequest::Restore(snapshot) => {
+                self.snapshot_restore_job = Some(Job::spawn("Reading snapshot", move |progress| {
+                    progress.set_total(snapshot.rows + 1);
+                    let (headers, rows) = snapshots::collect_table(|visit| snapshots::read_snapshot(&snapshot.path, progress, visit))?;
+                    Ok((snapshot, headers, rows))
+                }));
+            }
+            SnapshotRequest::Delete(snapshot) => {
+                if let Err(e) = std::fs::remove_file(&snapshot.path) {
+                    self.status_message = Some(format!("Deleting the snapshot failed: {}", e));
+                }
+                self.open_snapshots();
+            }
+        }
+    }
+
+    /// Save this document's columns as a template for File → New CSV
+    fn save_as_template(&mut self, name: String) {
+        let Some(dir) = templates::templates_dir() else {
+            self.status_message = Some("No config folder to save templates in".to_string());
+            return;
+        };
+        let template = Template::from_document(name, &self.source_headers(), &self.column_types(), self.schema.clone());
+        self.status_message = Some(match template.save(&dir) {
+            Ok(_) => format!("Saved template \"{}\"", template.name),
+            Err(e) => format!("Saving the template failed: {:#}", e),
+        });
+    }
+
+    /// Ask how incoming columns line up with this document's before inserting their rows at `at`
+    fn map_columns(&mut self, source_name: String, headers: Vec<String>, rows: Vec<Vec<String>>, at: usize) {
+        if !self.document().in_memory() {
+            self.status_message = Some("Inserting rows requires an editable grid".to_string());
+            return;
+        }
+        self.column_mapping_window.load(source_name, self.source_headers(), headers, rows, at);
+        self.show_column_mapping = true;
+    }
+
+    /// Pick a CSV and append its rows, matching its columns to this document's
+    fn append_file_dialog(&mut self) {
+        let Some(path) = rfd::FileDialog::new().add_filter("CSV", &["csv"]).pick_file() else {
+            return;
+        };
+        match crate::backend::import::read_csv_table(&path) {
+            Ok((headers, rows)) => {
+                let name = path.file_name().map_or_else(|| path.display().to_string(), |n| n.to_string_lossy().into_owned());
+                let at = self.document().row_count();
+                self.map_columns(name, headers, rows, at);
+            }
+            Err(e) => self.status_message = Some(format!("Append failed: {:#}", e)),
+        }
+    }
+
+    /// Insert rows already in this document's column order as one undo step
+    fn insert_mapped_rows(&mut self, at: usize, rows: Vec<Vec<String>>) {
+        let count = rows.len();
+        match self.document_mut().insert_rows(at, rows) {
+            Ok(()) => {
+                self.selected_cell = Some((at, 0));
+                self.scroll_to_row = Some(at);
+                self.status_message = Some(format!("Inserted {} row(s)", count));
+            }
+            Err(message) => self.status_message = Some(message),
+        }
+    }
+
+    /// Place clipboard rows at the selection (or the top-left) as a single undoable edit
+    fn paste_rows(&mut self, mode: PasteMode, rows: Vec<Vec<String>>) {
+        let (r, c) = self.selected_cell.unwrap_or((0, 0));
+        let rows = if mode == PasteMode::Transposed { clipboard::transpose(&rows) } else { rows };
+        let height = rows.len();
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+
+        match mode {
+            PasteMode::MatchedRows => {
+                let mut rows = rows;
+                if !rows.is_empty() {
+                    let headers = rows.remove(0);
+                    self.map_columns("the clipboard".to_string(), headers, rows, r + 1);
+                }
+                return;
+            }
+            PasteMode::Values | PasteMode::Transposed => {
+                let cells = rows.into_iter().enumerate().flat_map(|(i, row)| {
+                    row.into_iter().enumerate().map(move |(j, value)| (r + i, c + j, value))
+                });
+                // File-backed documents can't grow, so the paste is clipped
+                let total = if self.document().in_memory() { usize::MAX } else { self.document().row_count() };
+                let edits: Vec<_> = cells
+                    .filter(|(row, col, _)| *row < total && *col < self.num_columns)
+                    .map(|(row, col, value)| (row, col, self.cell_value(row, col), value))
+                    .collect();
+                self.apply_cell_edits(edits);
+            }
+            PasteMode::NewRows | PasteMode::NewColumns => {
+                let inserted = if mode == PasteMode::NewRows {
+                    self.document_mut().insert_rows(r + 1, rows)
+                } else {
+                    let first = self.document().column_count() + 1;
+                    let columns = clipboard::transpose(&rows)
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, values)| (format!("Column {}", first + i), values))
+                        .collect();
+                    self.document_mut().insert_columns(c + 1, columns)
+                };
+                if let Err(message) = inserted {
+                    self.status_message = Some(message);
+                    return;
+                }
+                self.sync_columns();
+            }
+        }
+        self.status_message = Some(format!("Pasted {} × {} ({})", height, width, mode.name()));
+    }
+
+    /// Value range of each color-scaled column, from its cached profile
+    fn heatmap_ranges(&mut self) -> Vec<Option<(f64, f64)>> {
+        let mut cols: Vec<usize> = self.heatmap_columns.iter().copied().collect();
+        cols.retain(|&c| c < self.num_columns);
+        for &col in &cols {
+            self.request_profile(col);
+        }
+        (0..self.num_columns)
+            .map(|c| {
+                if !self.heatmap_columns.contains(&c) {
+                    return None;
+                }
+                let profile = self.profile_cache.get(c, self.column_generation(c))?;
+                Some((profile.min?, profile.max?))
+            })
+            .collect()
+    }
+
+    /// Source column indices in display order
+    fn display_columns(&self) -> Vec<usize> {
+        match self.projection {
+            Some(ref cols) => cols.iter().copied().filter(|&c| c < self.num_columns).collect(),
+            None => (0..self.num_columns).collect(),
+        }
+    }
+
+    /// The displayed column `delta` steps away from `col`, clamped to the ends
+    fn step_column(&self, col: usize, delta: isize) -> usize {
+        let cols = self.display_columns();
+        let Some(last) = cols.len().checked_sub(1) else {
+            return col;
+        };
+        let pos = cols.iter().position(|&c| c == col).unwrap_or(0);
+        cols[pos.saturating_add_signed(delta).min(last)]
+    }
+
+    /// Names from the header row, for pickers that run before the table is shown
+    fn source_headers(&self) -> Vec<String> {
+        self.document().headers()
+    }
+
+    /// Match the column count and widths to the document after structural edits
+    fn sync_columns(&mut self) {
+        let (rows, cols) = (self.document().row_count(), self.document().column_count());
+        self.num_columns = cols;
+        self.column_widths.resize(cols, DEFAULT_COLUMN_WIDTH);
+        if let Some((r, c)) = self.selected_cell
+            && (r >= rows || c >= cols)
+        {
+            self.selected_cell = None;
+        }
+        self.recompile_row_colors();
+    }
+
+    /// Change the table's shape, reporting why when the document can't
+    fn restructure<T>(&mut self, change: impl FnOnce(&mut dyn CsvDocument) -> Result<T, String>) -> Option<T> {
+        match change(self.document_mut()) {
+            Ok(done) => {
+                self.sync_columns();
+                Some(done)
+            }
+            Err(message) => {
+                self.status_message = Some(message);
+                None
+            }
+        }
+    }
+
+    /// Values of a whole row, padded to the column count
+    fn row_values(&self, r: usize) -> Vec<String> {
+        self.document().row(r)
+    }
+
+    /// Every edit made to this document this session
+    fn audit_log(&self) -> &crate::backend::audit::AuditLog {
+        self.document().audit()
+    }
+
+    /// Values a cell has held this session, oldest first, from the audit log
+    fn cell_history(&self, r: usize, c: usize) -> Vec<crate::backend::audit::CellVersion> {
+        // The log numbers rows from 1 as shown, counting the header line as row 0
+        let row = r + 1 - self.first_data_row();
+        self.audit_log().cell_history(row, c + 1)
+    }
+
+    fn audit_log_mut(&mut self) -> &mut crate::backend::audit::AuditLog {
+        self.document_mut().audit_mut()
+    }
+
+    /// Save the audit log as CSV or JSON, by the extension picked; true once saved
+    fn export_audit_log(&mut self) -> bool {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .add_filter("JSON", &["json"])
+            .set_file_name("audit-log.csv")
+            .save_file()
+        else {
+            return false;
+        };
+        let json = path.extension().is_some_and(|e| e.eq_ignore_ascii_case("json"));
+        let log = self.audit_log();
+        let result = if json { log.write_json(&path) } else { log.write_csv(&path) };
+        let saved = result.is_ok();
+        self.status_message = Some(match result {
+            Ok(()) => format!("Saved audit log to {}", path.display()),
+            Err(e) => format!("Failed to save audit log: {:#}", e),
+        });
+        saved
+    }
+
+    /// Each column's profiled type, where a profile is ready and the type is a single one
+    fn column_types(&self) -> Vec<Option<InferredType>> {
+        (0..self.num_columns)
+            .map(|c| {
+                self.profile_cache.get(c, self.column_generation(c))
+                    .and_then(|profile| profile.data_type.clone())
+                    .filter(|t| !matches!(t, InferredType::Mixed | InferredType::Empty))
+            })
+            .collect()
+    }
+
+    /// A row as a JSON object keyed by the real headers, typed by each column's profile
+    fn row_as_json(&self, r: usize) -> serde_json::Map<String, serde_json::Value> {
+        let types = self.column_types();
+        crate::backend::export::row_to_json(&self.source_headers(), &self.row_values(r), &types)
+    }
+
+    /// Text for the JSON view: one pretty object, or one compact object per line
+    fn json_view_text(&self, first: usize, count: usize, lines: bool) -> String {
+        if !lines {
+            return serde_json::to_string_pretty(&self.row_as_json(first)).unwrap_or_default();
+        }
+        let total = self.document().row_count();
+        (first..(first + count).min(total))
+            .map(|r| serde_json::to_string(&self.row_as_json(r)).unwrap_or_default())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Values of a column, capped at `limit` rows
+    fn column_values(&self, c: usize, limit: usize) -> Vec<String> {
+        let total = self.document().row_count();
+        (0..total.min(limit)).map(|r| self.cell_value(r, c)).collect()
+    }
+}
+
+pub enum AppState {
+    Welcome,
+    Editor(Box<EditorState>),
+    Loading(String), // Show loading spinner
+    Error(String),
+}
+
+pub struct GuiApp {
+    state: AppState,
+    settings: Settings,
+    show_settings: bool,
+    show_new_csv_dialog: bool,
+    new_csv_columns: usize,
+    new_csv_rows: usize,
+    // Built-in and saved templates for New CSV, read when the dialog first shows; None picks a blank grid
+    templates: Vec<Template>,
+    new_csv_template: Option<usize>,
+    // Name typed for File → Save as Template
+    template_name: String,
+    settings_window: crate::gui::windows::settings::SettingsWindow,
+    // HTML import with several tables to choose from
+    html_picker: crate::gui::windows::html_tables::HtmlTablePickerWindow,
+    show_html_picker: bool,
+    import_filename: String,
+    // Fixed-width import: the whole file is kept until the layout is chosen
+    fixed_width_window: crate::gui::windows::fixed_width::FixedWidthWindow,
+    show_fixed_width: bool,
+    fixed_width_text: String,
+    // Welcome screen previews, fetched the first time a recent file is hovered
+    previews: std::collections::HashMap<String, Result<FilePreview, String>>,
+    preview_jobs: std::collections::HashMap<String, Job<anyhow::Result<FilePreview>>>,
+    // Folder opened as a workspace, listed in a left panel
+    workspace: Option<Workspace>,
+    workspace_job: Option<Job<anyhow::Result<Workspace>>>,
+    // Search of the open file or the whole workspace, listed per file in the workspace panel
+    search_query: String,
+    search_scope: SearchScope,
+    search_job: Option<Job<anyhow::Result<Vec<FileHits>>>>,
+    search_results: Option<Result<Vec<FileHits>, String>>,
+    frame_stats: FrameStats,
+}
+
+impl GuiApp {
+    fn input_scope(&self, ctx: &egui::Context) -> InputScope {
+        if self.show_new_csv_dialog || self.show_html_picker || self.show_fixed_width || self.settings_window.is_capturing_key() {
+            return InputScope::Modal;
+        }
+        match self.state {
+            AppState::Editor(ref state) => state.input_scope(ctx),
+            _ if ctx.wants_keyboard_input() => InputScope::TextField,
+            _ => InputScope::Grid,
+        }
+    }
+
+    pub fn new(_cc: &eframe::CreationContext<'_>, loader: Option<Arc<CsvLoader>>, filename: Option<String>) -> Self {
+        let mut settings = Settings::load();
+        
+        // Load custom themes if any
+        if let Some(config_dir) = ProjectDirs::from("com", "tigokraft", "csvit") {
+            let theme_dir = config_dir.config_dir().join("themes");
+            if theme_dir.exists()
+                && let Ok(entries) = std::fs::read_dir(theme_dir)
+            {
+                for entry in entries.flatten() {
+                     if let Ok(content) = std::fs::read_to_string(entry.path())
+                         && let Ok(theme) = serde_json::from_str::<crate::backend::settings::CustomTheme>(&content)
+                     {
+                         settings.custom_themes.push(theme);
+                     }
+                }
+            }
+        }
+        
+        if let Some(ref path) = filename {
+            let rows = loader.as_ref().map(|l| l.total_records().saturating_sub(1));
+            settings.add_recent_file(path, rows);
+        }
+        
+        let state = if let Some(loader) = loader {
+             let mut editor = EditorState::new(loader, filename.unwrap_or_else(|| "Unknown.csv".to_string()));
+             editor.selected_cell = Some((0, 0));
+             editor.restore_column_widths(&settings);
+             AppState::Editor(Box::new(editor))
+        } else {
+            AppState::Welcome
+        };
+        
+        Self { 
+            state,
+            settings,
+            show_settings: false,
+            show_new_csv_dialog: false,
+            new_csv_columns: 5,
+            new_csv_rows: 10,
+            templates: Vec::new(),
+            new_csv_template: None,
+            template_name: String::new(),
+            settings_window: crate::gui::windows::settings::SettingsWindow::new(),
+            html_picker: crate::gui::windows::html_tables::HtmlTablePickerWindow::new(),
+            show_html_picker: false,
+            import_filename: String::new(),
+            fixed_width_window: crate::gui::windows::fixed_width::FixedWidthWindow::new(),
+            show_fixed_width: false,
+            fixed_width_text: String::new(),
+            previews: std::collections::HashMap::new(),
+            preview_jobs: std::collections::HashMap::new(),
+            workspace: None,
+            workspace_job: None,
+            search_query: String::new(),
+            search_scope: SearchScope::Folder,
+            search_job: None,
+            search_results: None,
+            frame_stats: FrameStats::default(),
+        }
+    }
+
+    fn load_file(&mut self, path: &str) {
+        self.state = AppState::Loading(path.to_string());
+        // Report headers and instrument notes above the table are set aside
+        match CsvLoader::open_table(std::path::Path::new(path)) {
+            Ok(loader) => {
+                let arc_loader = Arc::new(loader);
+                self.settings.add_recent_file(path, Some(arc_loader.total_records().saturating_sub(1)));
+                let mut state = EditorState::new(arc_loader, path.to_string());
+                state.restore_column_widths(&self.settings);
+                state.scan_file_tables();
+                self.state = AppState::Editor(Box::new(state));
+            }
+            Err(e) => {
+                self.state = AppState::Error(format!("Failed to load file: {}", e));
+            }
+        }
+    }
+
+    /// Import an XML record file or a table from an HTML page into a new grid
+    fn import_dialog(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("XML / HTML", &["xml", "html", "htm"])
+            .pick_file()
+        else {
+            return;
+        };
+        // Saving goes to a CSV next to the source, never over it
+        self.import_filename = path.with_extension("csv").to_string_lossy().to_string();
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) => {
+                self.state = AppState::Error(format!("Failed to read file: {}", e));
+                return;
+            }
+        };
+
+        let is_xml = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("xml"));
+        if is_xml {
+            match crate::backend::import::import_xml_records(&text) {
+                Ok(grid) => self.open_grid(grid, self.import_filename.clone()),
+                Err(e) => self.state = AppState::Error(format!("Failed to import XML: {:#}", e)),
+            }
+            return;
+        }
+        let mut tables = crate::backend::import::find_html_tables(&text);
+        match tables.len() {
+            0 => self.state = AppState::Error("No tables found in the HTML file".to_string()),
+            1 => self.open_grid(tables.remove(0).into_grid(), self.import_filename.clone()),
+            _ => {
+                self.html_picker.load(tables);
+                self.show_html_picker = true;
+            }
+        }
+    }
+
+    /// Pick a fixed-width text file and open the column ruler for it
+    fn import_fixed_width_dialog(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Fixed-width text", &["txt", "dat", "prn", "fwf"])
+            .pick_file()
+        else {
+            return;
+        };
+        match std::fs::read_to_string(&path) {
+            Ok(text) => {
+                self.import_filename = path.with_extension("csv").to_string_lossy().to_string();
+                self.fixed_width_window.load(&text);
+                self.fixed_width_text = text;
+                self.show_fixed_width = true;
+            }
+            Err(e) => self.state = AppState::Error(format!("Failed to read file: {}", e)),
+        }
+    }
+
+    fn open_grid(&mut self, grid: crate::backend::grid::EditableGrid, filename: String) {
+        self.state = AppState::Editor(Box::new(EditorState::with_grid(grid, filename)));
+    }
+
+    fn open_folder_dialog(&mut self) {
+        if let Some(root) = rfd::FileDialog::new().pick_folder() {
+            self.scan_workspace(root);
+        }
+    }
+
+    fn scan_workspace(&mut self, root: std::path::PathBuf) {
+        self.workspace_job = Some(Job::spawn("Scanning folder", move |progress| workspace::scan_workspace(&root, progress)));
+    }
+
+    /// Search the open document (with its edits) or every workspace file in the background
+    fn start_search(&mut self) {
+        let query = self.search_query.clone();
+        self.search_results = None;
+        self.search_job = match self.search_scope {
+            SearchScope::Folder => {
+                let Some(workspace) = self.workspace.clone() else { return };
+                Some(Job::spawn("Searching folder", move |progress| workspace::search_workspace(&workspace, &query, progress)))
+            }
+            SearchScope::OpenFile => {
+                let AppState::Editor(ref state) = self.state else { return };
+                let path = std::path::PathBuf::from(&state.filename);
+                let relative = path.file_name().map_or_else(|| state.filename.clone(), |n| n.to_string_lossy().into_owned());
+                let first = state.first_data_row();
+                let (total, row_at) = state.row_reader();
+                Some(Job::spawn("Searching open file", move |progress| {
+                    progress.set_total(total.saturating_sub(first));
+                    let rows = (first..total).map(|r| {
+                        if (r - first) % 10_000 == 0 {
+                            progress.set_done(r - first);
+                        }
+                        Ok(row_at(r))
+                    });
+                    let found = workspace::search_rows(path, relative, rows, &query, progress);
+                    if progress.is_cancelled() {
+                        anyhow::bail!("Search cancelled");
+                    }
+                    Ok(if found.is_empty() { Vec::new() } else { vec![found] })
+                }))
+            }
+        };
+    }
+
+    /// Select a search result, opening its file first unless it's the open one
+    fn open_search_hit(&mut self, path: &std::path::Path, hit: &SearchHit) {
+        let is_open = matches!(self.state, AppState::Editor(ref state) if std::path::Path::new(&state.filename) == path);
+        if !is_open {
+            self.open_path(path);
+        }
+        if let AppState::Editor(ref mut state) = self.state {
+            let row = state.first_data_row() + hit.row;
+            state.selected_cell = Some((row, hit.col));
+            state.scroll_to_row = Some(row);
+        }
+    }
+
+    /// Open a CSV, or a CSVI archive as a grid that saves to a CSV beside it
+    fn open_path(&mut self, path: &std::path::Path) {
+        if !crate::backend::csvi::is_csvi_file(path) {
+            self.load_file(&path.to_string_lossy());
+            return;
+        }
+        match crate::backend::csvi::load_csvi(path) {
+            Ok((csv, metadata)) => {
+                let filename = path.with_extension("csv").to_string_lossy().to_string();
+                self.open_grid(crate::backend::grid::EditableGrid::from_csv(&csv), filename);
+                if let AppState::Editor(ref mut state) = self.state {
+                    state.column_formats = metadata.column_formats;
+                    state.cell_formats = metadata.formatting;
+                    if !metadata.preamble.is_empty() || !metadata.footer.is_empty() {
+                        state.kept_lines = Some((metadata.preamble, metadata.footer));
+                    }
+                    state.set_row_color_rules(metadata.row_colors);
+                    if metadata.column_widths.len() == state.num_columns {
+                        state.column_widths = metadata.column_widths;
+                        state.manual_widths = (0..state.num_columns).collect();
+                    }
+                }
+            }
+            Err(e) => self.state = AppState::Error(format!("Failed to load file: {:#}", e)),
+        }
+    }
+
+    /// Frame times, rows read, profile cache use, memory and running jobs,
+    /// for diagnosing slowdowns on large files
+    fn perf_overlay(&mut self, ctx: &egui::Context) {
+        // Taken every frame so the count covers just the last one
+        let rows_read = match self.state {
+            AppState::Editor(ref state) => state.file.reader.take_rows_read(),
+            _ => 0,
+        };
+        if !self.settings.show_perf_overlay {
+            return;
+        }
+        let ms = |d: std::time::Duration| d.as_secs_f64() * 1000.0;
+        let stats = &self.frame_stats;
+        egui::Area::new(egui::Id::new("perf_overlay"))
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-12.0, -36.0))
+            .interactable(false)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.style_mut().override_text_style = Some(egui::TextStyle::Monospace);
+                    ui.label(format!("frame  {:6.2} ms (avg {:.2}, max {:.2})", ms(stats.last()), ms(stats.average()), ms(stats.max())));
+                    ui.label(format!("rate   {:6.0} fps", 1.0 / ctx.input(|i| i.stable_dt).max(1e-3)));
+                    ui.label(format!("rows   {:6} read last frame", rows_read));
+                    ui.label(format!("memory {:>9}", memory::resident_memory().map(file_info::format_size).unwrap_or_else(|| "n/a".to_string())));
+                    if let AppState::Editor(ref state) = self.state {
+                        let (hits, misses) = state.profile_cache.hit_stats();
+                        let rate = if hits + misses > 0 { hits as f64 * 100.0 / (hits + misses) as f64 } else { 0.0 };
+                        ui.label(format!("cache  {:5.1}% profile hits ({} / {}), {} pending", rate, hits, hits + misses, state.profile_cache.pending_count()));
+                        let jobs = state.active_jobs();
+                        ui.label(format!("jobs   {}", jobs.len()));
+                        for (label, progress) in jobs {
+                            match progress.fraction() {
+                                Some(f) => ui.label(format!("  {} {:.0}%", label, f * 100.0)),
+                                None => ui.label(format!("  {}", label)),
+                            };
+                        }
+                    }
+                });
+            });
+        // Keep memory and job figures moving while idle, without spinning every frame
+        ctx.request_repaint_after(std::time::Duration::from_millis(500));
+    }
+
+    /// Left panel listing the workspace's files with size and row badges
+    fn workspace_panel(&mut self, ctx: &egui::Context) {
+        if let Some(job) = &self.workspace_job {
+            match job.poll() {
+                Some(Ok(Ok(workspace))) => {
+                    self.workspace = Some(workspace);
+                    self.workspace_job = None;
+                }
+                Some(Ok(Err(e))) => {
+                    self.state = AppState::Error(format!("Failed to open folder: {:#}", e));
+                    self.workspace_job = None;
+                }
+                Some(Err(e)) => {
+                    self.state = AppState::Error(e);
+                    self.workspace_job = None;
+                }
+                None => ctx.request_repaint(),
+            }
+        }
+        if let Some(result) = self.search_job.as_ref().and_then(|job| job.poll()) {
+            let cancelled = self.search_job.take().is_some_and(|job| job.progress().is_cancelled());
+            self.search_results = (!cancelled).then(|| match result {
+                Ok(Ok(found)) => Ok(found),
+                Ok(Err(e)) => Err(format!("Search failed: {:#}", e)),
+                Err(e) => Err(e),
+            });
+        } else if self.search_job.is_some() {
+            ctx.request_repaint();
+        }
+        if self.workspace.is_none() && self.workspace_job.is_none() {
+            return;
+        }
+
+        let current = match &self.state {
+            AppState::Editor(state) => Some(state.filename.clone()),
+            _ => None,
+        };
+        let mut open = None;
+        let mut close = false;
+        let mut rescan = None;
+        let mut search = false;
+        let mut go_to = None;
+        egui::SidePanel::left("workspace_panel").resizable(true).default_width(240.0).show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let title = self.workspace.as_ref()
+                    .and_then(|w| w.root.file_name())
+                    .map(|n| n.to_string_lossy().to_string())
+                    .unwrap_or_else(|| "Workspace".to_string());
+                ui.strong(format!("📁 {}", title));
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.small_button("✕").on_hover_text("Close folder").clicked() {
+                        close = true;
+                    }
+                    if let Some(workspace) = &self.workspace
+                        && ui.add_enabled(self.workspace_job.is_none(), egui::Button::new("⟳").small())
+                            .on_hover_text("Rescan").clicked()
+                    {
+                        rescan = Some(workspace.root.clone());
+                    }
+                });
+            });
+            if let Some(job) = &self.workspace_job {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(&job.label);
+                });
+            }
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                let response = ui.add(egui::TextEdit::singleline(&mut self.search_query).hint_text("Search").desired_width(120.0));
+                let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                egui::ComboBox::from_id_salt("search_scope")
+                    .selected_text(self.search_scope.name())
+                    .show_ui(ui, |ui| {
+                        for scope in SearchScope::ALL {
+                            ui.selectable_value(&mut self.search_scope, scope, scope.name());
+                        }
+                    });
+                let ready = !self.search_query.is_empty() && self.search_job.is_none();
+                if (ui.add_enabled(ready, egui::Button::new("🔍")).clicked() || submitted) && ready {
+                    search = true;
+                }
+            });
+            if let Some(job) = &self.search_job {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    match job.progress().fraction() {
+                        Some(fraction) => ui.label(format!("Searching... {:.0}%", fraction * 100.0)),
+                        None => ui.label("Searching..."),
+                    };
+                    if ui.small_button("Cancel").clicked() {
+                        job.progress().cancel();
+                    }
+                });
+            }
+            if let Some(Err(ref e)) = self.search_results {
+                ui.colored_label(ui.visuals().error_fg_color, e);
+                ui.separator();
+            }
+            if let Some(Ok(ref results)) = self.search_results {
+                if results.is_empty() {
+                    ui.label(egui::RichText::new("No matches").weak());
+                }
+                egui::ScrollArea::vertical().id_salt("search_results").max_height(280.0).show(ui, |ui| {
+                    for file in results {
+                        let count = file.hits.len() + file.more;
+                        egui::CollapsingHeader::new(format!("{} ({})", file.relative, count))
+                            .id_salt(&file.path)
+                            .default_open(true)
+                            .show(ui, |ui| {
+                                for hit in &file.hits {
+                                    let text = format!("Row {}, {}: {}", hit.row + 1, hit.col + 1, truncate_display(&hit.value, 60).map_or(hit.value.as_str(), |(prefix, _)| prefix));
+                                    if ui.selectable_label(false, text).clicked() {
+                                        go_to = Some((file.path.clone(), hit.clone()));
+                                    }
+                                }
+                                if file.more > 0 {
+                                    ui.label(egui::RichText::new(format!("and {} more", file.more)).weak());
+                                }
+                                if let Some(ref error) = file.error {
+                                    ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}", error));
+                                }
+                            });
+                    }
+                });
+                ui.separator();
+            }
+
+            let Some(workspace) = &self.workspace else { return };
+            if workspace.files.is_empty() {
+                ui.label(egui::RichText::new("No CSV files in this folder").weak());
+            }
+            egui::ScrollArea::vertical().id_salt("workspace_files").show(ui, |ui| {
+                for file in &workspace.files {
+                    let selected = current.as_deref() == Some(&*file.path.to_string_lossy());
+                    let response = ui.horizontal(|ui| {
+                        let response = ui.selectable_label(selected, &file.relative);
+                        let mut badge = file_info::format_size(file.size);
+                        if let Some(rows) = file.rows {
+                            badge = format!("{} rows · {}", rows, badge);
+                        }
+                        ui.label(egui::RichText::new(badge).weak().small());
+                        response
+                    }).inner;
+                    if response.on_hover_text(file.path.to_string_lossy()).clicked() {
+                        open = Some(file.path.clone());
+                    }
+                }
+            });
+        });
+
+        if close {
+            self.workspace = None;
+            self.workspace_job = None;
+        }
+        if let Some(root) = rescan {
+            self.scan_workspace(root);
+        }
+        if let Some(path) = open {
+            self.open_path(&path);
+        }
+        if search {
+            self.start_search();
+        }
+        if let Some((path, hit)) = go_to {
+            self.open_search_hit(&path, &hit);
+        }
+    }
+
+    /// Apply an action from the menus, the shortcuts or the open document's views
+    fn dispatch(&mut self, action: Action) {
+        match action {
+            Action::NewFile => self.show_new_csv_dialog = true,
+            Action::OpenFile => self.open_file_dialog(),
+            Action::OpenPath(path) => self.open_path(&path),
+            Action::OpenFolder => self.open_folder_dialog(),
+            Action::Import => self.import_dialog(),
+            Action::ImportFixedWidth => self.import_fixed_width_dialog(),
+            Action::OpenSettings => self.show_settings = true,
+            Action::OpenTable { filename, headers, rows } => {
+                self.open_grid(crate::backend::grid::EditableGrid::from_rows(headers, rows), filename);
+            }
+            action => {
+                if let AppState::Editor(ref mut state) = self.state {
+                    state.apply(action, &mut self.settings);
+                }
+            }
+        }
+    }
+
+    fn open_file_dialog(&mut self) {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("CSV", &["csv"])
+            .add_filter("CSVit", &["csvi"])
+            .pick_file()
+        {
+            self.open_path(&path);
+        }
+    }
+}
+
+impl eframe::App for GuiApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let frame_start = std::time::Instant::now();
+        apply_style(ctx, &self.settings); 
+
+        // Keyboard paths for the File menu and Settings
+        // Held back while a dialog or cell editor has the keyboard
+        if self.input_scope(ctx).allows_global_shortcuts() {
+            let keymap = &self.settings.keymap;
+            let shortcuts = [
+                (&keymap.new_file, Action::NewFile),
+                (&keymap.open_file, Action::OpenFile),
+                (&keymap.open_settings, Action::OpenSettings),
+                (&keymap.print, Action::Print),
+            ];
+            let pressed: Vec<Action> = shortcuts.into_iter()
+                .filter(|(binding, _)| ctx.input(|i| binding.matches(i)))
+                .map(|(_, action)| action)
+                .collect();
+            for action in pressed {
+                self.dispatch(action);
+            }
+        }
+
+        egui::TopBottomPanel::top("menu_bar").show(ctx, |ui| {
+             ui.horizontal(|ui| {
+                 ui.menu_button("File", |ui| {
+                     if ui.button("📄 New CSV").clicked() {
+                         self.dispatch(Action::NewFile);
+                         ui.close();
+                     }
+                     if ui.button("📂 Open").clicked() {
+                         self.dispatch(Action::OpenFile);
+                         ui.close();
+                     }
+                     if ui.button("📁 Open Folder...").clicked() {
+                         self.dispatch(Action::OpenFolder);
+                         ui.close();
+                     }
+                     if ui.button("📥 Import XML / HTML...").clicked() {
+                         self.dispatch(Action::Import);
+                         ui.close();
+                     }
+                     if ui.button("📏 Import Fixed-Width...").clicked() {
+                         self.dispatch(Action::ImportFixedWidth);
+                         ui.close();
+                     }
+                     if let AppState::Editor(ref mut state) = self.state {
+                         ui.separator();
+                         let printing = state.print_job.is_some();
+                         let saving = state.save_job.is_some();
+                         if ui.add_enabled(!saving, egui::Button::new("💾 Save As...")).clicked() {
+                             state.dispatch(Action::SaveAs);
+                             ui.close();
+                         }
+                         if ui.add_enabled(!printing, egui::Button::new("🖨 Print to PDF...")).clicked() {
+                             state.dispatch(Action::Print);
+                             ui.close();
+                         }
+                         if ui.button("ℹ File Info...").clicked() {
+                             state.dispatch(Action::FileInfo);
+                             ui.close();
+                         }
+                         if ui.button("📸 Snapshots...")
+                             .on_hover_text("Keep compressed copies of this file to compare against or restore")
+                             .clicked()
+                         {
+                             state.dispatch(Action::Open(Dialog::Snapshots));
+                             ui.close();
+                         }
+                         let in_head = state.git_status.as_ref().is_some_and(|git| git.state.in_head());
+                         if ui.add_enabled(in_head && state.git_diff_job.is_none(), egui::Button::new("🔀 Diff against HEAD"))
+                             .on_hover_text("Review changes to this file since its last git commit")
+                             .clicked()
+                         {
+                             state.dispatch(Action::DiffAgainstHead);
+                             ui.close();
+                         }
+                         let exporting = state.export_job.is_some();
+                         ui.add_enabled_ui(!exporting && !self.settings.export_presets.is_empty(), |ui| {
+                             ui.menu_button("📤 Export Presets", |ui| {
+                                 for preset in &self.settings.export_presets {
+                                     if ui.button(&preset.name).clicked() {
+                                         state.dispatch(Action::RunExportPreset(preset.clone()));
+                                         ui.close();
+                                     }
+                                 }
+                             });
+                         });
+                         ui.menu_button("📐 Save as Template", |ui| {
+                             ui.label("Columns, types and schema rules, for File → New CSV");
+                             ui.add(egui::TextEdit::singleline(&mut self.template_name).hint_text("Template name"));
+                             let name = self.template_name.trim().to_string();
+                             if ui.add_enabled(!name.is_empty(), egui::Button::new("Save")).clicked() {
+                                 state.dispatch(Action::SaveAsTemplate(name));
+                                 self.template_name.clear();
+                                 self.templates.clear();
+                                 ui.close();
+                             }
+                         });
+                     }
+                     ui.separator();
+                     ui.menu_button("Recent Files", |ui| {
+                         if self.settings.recent_files.is_empty() {
+                             ui.label("No recent files");
+                         } else {
+                             for file in self.settings.recent_files_ordered() {
+                                 let mut display_name = std::path::Path::new(&file.path)
+                                     .file_name()
+                                     .map(|n| n.to_string_lossy().to_string())
+                                     .unwrap_or_else(|| file.path.clone());
+                                 if file.pinned {
+                                     display_name = format!("📌 {}", display_name);
+                                 }
+                                 if ui.button(&display_name).on_hover_text(&file.path).clicked() {
+                                     self.dispatch(Action::OpenPath(file.path.into()));
+                                     ui.close();
+                                 }
+                             }
+                         }
+                     });
+                 });
+                 if ui.button("⚙ Settings").clicked() {
+                     self.dispatch(Action::OpenSettings);
+                 }
+             });
+        });
+
+        self.workspace_panel(ctx);
+
+        // Settings Window
+        if self.show_settings {
+             self.settings_window.show(ctx, &mut self.show_settings, &mut self.settings);
+        }
+        if self.show_html_picker
+            && let Some(table) = self.html_picker.show(ctx, &mut self.show_html_picker)
+        {
+            self.open_grid(table.into_grid(), self.import_filename.clone());
+        }
+        if self.show_fixed_width {
+            if let Some(layout) = self.fixed_width_window.show(ctx, &mut self.show_fixed_width) {
+                let text = std::mem::take(&mut self.fixed_width_text);
+                let grid = crate::backend::import::import_fixed_width(&text, &layout.boundaries, &layout.names, layout.header);
+                self.open_grid(grid, self.import_filename.clone());
+            }
+            if !self.show_fixed_width {
+                self.fixed_width_text.clear();
+            }
+        }
+        // New CSV Dialog
+        if self.show_new_csv_dialog {
+            if self.templates.is_empty() {
+                self.templates = Template::builtin();
+                self.templates.extend(templates::templates_dir().map(|dir| templates::load_saved(&dir)).unwrap_or_default());
+            }
+            let mut open = true;
+            let mut create = None;
+            egui::Window::new("Create New CSV")
+                .open(&mut open)
+                .resizable(false)
+                .show(ctx, |ui| {
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label("Start from:");
+                        ui.selectable_value(&mut self.new_csv_template, None, "Blank");
+                        for (i, template) in self.templates.iter().enumerate() {
+                            ui.selectable_value(&mut self.new_csv_template, Some(i), &template.name)
+                                .on_hover_text(&template.description);
+                        }
+                    });
+                    ui.separator();
+                    match self.new_csv_template.and_then(|i| self.templates.get_mut(i)) {
+                        Some(template) => {
+                            if !template.description.is_empty() {
+                                ui.label(egui::RichText::new(&template.description).weak());
+                            }
+                            egui::Grid::new("new_csv_template_fields").striped(true).show(ui, |ui| {
+                                for field in &template.schema.fields {
+                                    ui.label(&field.name);
+                                    ui.label(egui::RichText::new(&field.field_type).weak());
+                                    ui.label(if field.constraints.required { "required" } else { "" });
+                                    ui.end_row();
+                                }
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("Rows:");
+                                ui.add(egui::DragValue::new(&mut template.rows).range(1..=1000));
+                            });
+                        }
+                        None => {
+                            ui.horizontal(|ui| {
+                                ui.label("Columns:");
+                                ui.add(egui::DragValue::new(&mut self.new_csv_columns).range(1..=100));
+                            });
+                            ui.horizontal(|ui| {
+                                ui.label("Rows:");
+                                ui.add(egui::DragValue::new(&mut self.new_csv_rows).range(1..=1000));
+                            });
+                        }
+                    }
+                    ui.add_space(10.0);
+                    ui.horizontal(|ui| {
+                        if ui.button("Create").clicked() {
+                            create = Some(self.new_csv_template);
+                        }
+                        if ui.button("Cancel").clicked() {
+                            self.show_new_csv_dialog = false;
+                        }
+                    });
+                });
+            if let Some(choice) = create {
+                match choice.and_then(|i| self.templates.get(i)) {
+                    // The template's types and rules come along as the document's schema
+                    Some(template) => {
+                        let schema = template.schema.clone();
+                        self.open_grid(template.instantiate(), "Untitled.csv".to_string());
+                        if let AppState::Editor(ref mut state) = self.state {
+                            state.schema = Some(schema);
+                        }
+                    }
+                    None => {
+                        let grid = crate::backend::grid::EditableGrid::new(self.new_csv_columns, self.new_csv_rows);
+                        self.open_grid(grid, "Untitled.csv".to_string());
+                    }
+                }
+                self.show_new_csv_dialog = false;
+            }
+            if !open {
+                self.show_new_csv_dialog = false;
+            }
+        }
+
+        // Handle Drag & Drop
+        if !ctx.input(|i| i.raw.dropped_files.is_empty()) {
+            let dropped_files = ctx.input(|i| i.raw.dropped_files.clone());
+            if let Some(file) = dropped_files.first()
+                && let Some(path) = &file.path
+            {
+                let path_str = path.to_string_lossy().to_string();
+                self.load_file(&path_str);
+            }
+        }
+
+        let mut next_state = None;
+
+        match &mut self.state {
+            AppState::Welcome => {
+                self.preview_jobs.retain(|path, job| match job.poll() {
+                    Some(result) => {
+                        let result = result.and_then(|r| r.map_err(|e| e.to_string()));
+                        self.previews.insert(path.clone(), result);
+                        false
+                    }
+                    None => true,
+                });
+                if !self.preview_jobs.is_empty() {
+                    ctx.request_repaint();
+                }
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    ui.vertical_centered(|ui| {
+                        ui.add_space(60.0);
+                        ui.heading(egui::RichText::new("CSVit").size(48.0).strong());
+                        ui.label(egui::RichText::new("High performance editor for large CSV files").size(16.0).color(egui::Color32::from_gray(150)));
+                        ui.add_space(30.0);
+                        
+                        ui.horizontal(|ui| {
+                            ui.add_space(ui.available_width() / 2.0 - 220.0);
+                            if ui.add(egui::Button::new(egui::RichText::new("📄 New CSV").size(16.0))
+                                .min_size(egui::vec2(140.0, 45.0))
+                                .corner_radius(6.0)
+                            ).clicked() {
+                                self.show_new_csv_dialog = true;
+                            }
+                            ui.add_space(20.0);
+                            if ui.add(egui::Button::new(egui::RichText::new("📂 Open File").size(16.0))
+                                .min_size(egui::vec2(140.0, 45.0))
+                                .corner_radius(6.0)
+                            ).clicked() {
+                                self.open_file_dialog();
+                            }
+                        });
+                        
+                        // Recent Files Section
+                        if !self.settings.recent_files.is_empty() {
+                            ui.add_space(40.0);
+                            ui.heading(egui::RichText::new("Recent Files").size(18.0));
+                            ui.add_space(10.0);
+                            
+                            egui::Frame::default()
+                                .inner_margin(12.0)
+                                .corner_radius(8.0)
+                                .fill(ui.visuals().extreme_bg_color)
+                                .show(ui, |ui| {
+                                    let mut open = None;
+                                    let mut toggle_pin = None;
+                                    let mut remove = None;
+                                    // Pinned files are always shown, on top of the five most recent
+                                    let files = self.settings.recent_files_ordered();
+                                    let pinned = files.iter().filter(|f| f.pinned).count();
+                                    for file in files.iter().take(pinned + 5) {
+                                        let mut display_name = std::path::Path::new(&file.path)
+                                            .file_name()
+                                            .map(|n| n.to_string_lossy().to_string())
+                                            .unwrap_or_else(|| file.path.clone());
+                                        if file.pinned {
+                                            display_name = format!("📌 {}", display_name);
+                                        }
+                                        let response = ui.add(egui::Button::new(display_name)
+                                            .right_text(egui::RichText::new(recent_file_details(file)).weak().small())
+                                            .min_size(egui::vec2(420.0, 30.0))
+                                        );
+                                        if response.hovered()
+                                            && !self.previews.contains_key(&file.path)
+                                            && !self.preview_jobs.contains_key(&file.path)
+                                        {
+                                            let path = std::path::PathBuf::from(&file.path);
+                                            self.preview_jobs.insert(file.path.clone(), Job::spawn("Preview", move |_| preview::preview_file(&path)));
+                                        }
+                                        let response = response.on_hover_ui(|ui| {
+                                            ui.label(egui::RichText::new(&file.path).weak());
+                                            show_file_preview(ui, self.previews.get(&file.path));
+                                        });
+                                        if response.clicked() {
+                                            open = Some(file.path.clone());
+                                        }
+                                        response.context_menu(|ui| {
+                                            if ui.button(if file.pinned { "Unpin" } else { "📌 Pin" }).clicked() {
+                                                toggle_pin = Some(file.path.clone());
+                                                ui.close();
+                                            }
+                                            if ui.button("Copy Path").clicked() {
+                                                ui.ctx().copy_text(file.path.clone());
+                                                ui.close();
+                                            }
+                                            if ui.button("Remove from List").clicked() {
+                                                remove = Some(file.path.clone());
+                                                ui.close();
+                                            }
+                                        });
+                                    }
+                                    if let Some(path) = toggle_pin {
+                                        self.settings.toggle_recent_pin(&path);
+                                    }
+                                    if let Some(path) = remove {
+                                        self.settings.remove_recent_file(&path);
+                                    }
+                                    if let Some(path) = open {
+                                        self.load_file(&path);
+                                    }
+                                });
+                        }
+                    });
+                });
+            }
+            AppState::Error(msg) => {
+                let mut back_clicked = false;
+                egui::CentralPanel::default().show(ctx, |ui| {
+                    ui.vertical_centered(|ui| {
+                        ui.heading("Error");
+                        ui.label(msg.as_str());
+                        if ui.button("Back").clicked() {
+                            back_clicked = true;
+                        }
+                    });
+                });
+                if back_clicked {
+                    next_state = Some(AppState::Welcome);
+                }
+            }
+            AppState::Loading(name) => {
+                 egui::CentralPanel::default().show(ctx, |ui| {
+                    ui.vertical_centered(|ui| {
+                        ui.heading(format!("Loading {}...", name));
+                        ui.spinner();
+                    });
+                });
+            }
+            AppState::Editor(state) => {
+                render_editor(state, ctx, &mut self.settings);
+            }
+        }
+        if let AppState::Editor(ref mut state) = self.state
+            && !state.actions.is_empty()
+        {
+            for action in std::mem::take(&mut state.actions) {
+                self.dispatch(action);
+            }
+            ctx.request_repaint();
+        }
+
+        if let Some(s) = next_state {
+            self.state = s;
+        }
+
+        if self.input_scope(ctx).allows_global_shortcuts() && ctx.input(|i| self.settings.keymap.toggle_perf_overlay.matches(i)) {
+            self.settings.show_perf_overlay = !self.settings.show_perf_overlay;
+        }
+        self.perf_overlay(ctx);
+        self.frame_stats.record(frame_start.elapsed());
+    }
+}
+
+/// "12345 rows · 4.2 MB · 3 days ago" for the recent files list
+fn recent_file_details(file: &crate::backend::settings::RecentFile) -> String {
+    let mut parts = Vec::new();
+    if let Some(rows) = file.rows {
+        parts.push(format!("{} rows", rows));
+    }
+    if let Some(size) = file.size {
+        parts.push(file_info::format_size(size));
+    }
+    if file.last_opened > 0 {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let ago = now.saturating_sub(file.last_opened);
+        parts.push(match ago {
+            0..60 => "just now".to_string(),
+            60..3600 => format!("{} min ago", ago / 60),
+            3600..86400 => format!("{} h ago", ago / 3600),
+            _ => format!("{} days ago", ago / 86400),
+        });
+    }
+    parts.join(" · ")
+}
+
+/// Hover card for a recent file: size, delimiter, column count and the first rows
+fn show_file_preview(ui: &mut egui::Ui, preview: Option<&Result<FilePreview, String>>) {
+    match preview {
+        None => {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Loading preview...");
+            });
+        }
+        Some(Err(e)) => {
+            ui.colored_label(egui::Color32::from_rgb(220, 80, 60), e);
+        }
+        Some(Ok(preview)) => {
+            let delimiter = crate::gui::windows::save_as::DELIMITERS
+                .iter()
+                .find(|(d, _)| *d == preview.delimiter)
+                .map(|(_, name)| *name)
+                .unwrap_or("Comma (,)");
+            ui.label(format!("{} · {} · {} columns", file_info::format_size(preview.size), delimiter, preview.columns));
+            ui.separator();
+            egui::Grid::new("recent_file_preview").striped(true).show(ui, |ui| {
+                for (i, row) in preview.rows.iter().enumerate() {
+                    for cell in row.iter().take(8) {
+                        let text: String = cell.chars().take(24).collect();
+                        let text = egui::RichText::new(text).monospace().small();
+                        ui.label(if i == 0 { text.strong() } else { text });
+                    }
+                    if row.len() > 8 {
+                        ui.label("…");
+                    }
+                    ui.end_row();
+                }
+            });
+        }
+    }
+}
+
+/// Printable text typed this frame, ignoring shortcuts held with Ctrl/Cmd
+fn typed_text(input: &egui::InputState) -> Option<String> {
+    if input.modifiers.command || input.modifiers.ctrl {
+        return None;
+    }
+    let text: String = input.events.iter()
+        .filter_map(|e| match e {
+            egui::Event::Text(text) => Some(text.as_str()),
+            _ => None,
+        })
+        .collect();
+    (!text.is_empty() && !text.chars().any(char::is_control)).then_some(text)
+}
+
+/// Lines shown by the in-cell editor; longer values scroll inside it
+fn edit_lines(text: &str) -> usize {
+    text.split('\n').count().clamp(1, MAX_EDIT_LINES)
+}
+
+/// Before/after text with the changed middle highlighted (common prefix and suffix kept plain)
+fn diff_layouts(before: &str, after: &str, ui: &egui::Ui) -> (egui::text::LayoutJob, egui::text::LayoutJob) {
+    let prefix: usize = before.chars().zip(after.chars())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum();
+    let suffix: usize = before[prefix..].chars().rev().zip(after[prefix..].chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(a, _)| a.len_utf8())
+        .sum();
+
+    let font = egui::TextStyle::Monospace.resolve(ui.style());
+    let plain = egui::TextFormat::simple(font.clone(), ui.visuals().text_color());
+    let layout = |text: &str, color: egui::Color32, strike: bool| {
+        let mut job = egui::text::LayoutJob::default();
+        job.append(&text[..prefix], 0.0, plain.clone());
+        let mut changed = egui::TextFormat::simple(font.clone(), ui.visuals().strong_text_color());
+        changed.background = color;
+        if strike {
+            changed.strikethrough = egui::Stroke::new(1.0, ui.visuals().strong_text_color());
+        }
+        job.append(&text[prefix..text.len() - suffix], 0.0, changed);
+        job.append(&text[text.len() - suffix..], 0.0, plain.clone());
+        job
+    };
+    (
+        layout(before, egui::Color32::from_rgba_unmultiplied(220, 80, 60, 90), true),
+        layout(after, egui::Color32::from_rgba_unmultiplied(80, 200, 120, 90), false),
+    )
+}
+
+fn render_editor(state: &mut EditorState, ctx: &egui::Context, settings: &mut Settings) {
+    let now = ctx.input(|i| i.time);
+    state.check_disk_changes();
+    state.refresh_git_status(now);
+    ctx.request_repaint_after(std::time::Duration::from_secs_f64(GIT_STATUS_INTERVAL));
+    poll_jobs(state, ctx);
+    if state.follow_file {
+        state.follow_file_tick(now);
+        ctx.request_repaint_after(std::time::Duration::from_secs(1));
+    }
+    // Override font size
+    let mut style = (*ctx.style()).clone();
+    style.text_styles.iter_mut().for_each(|(_, font_id)| {
+        font_id.size = settings.font_size;
+    });
+    // This is a bit heavy to do every frame, but fine for now. 
+    // Ideally we'd set this once or in apply_style if it wasn't varying per-frame potentially.
+    // Actually apply_style is better, but here we can scope it to the editor panel if we wanted.
+    // Let's execute it on the ui scope.
+
+    top_bar(ctx, state, &style);
+    if state.document().in_memory() {
+        edit_toolbar(ctx, state);
+    }
+
+    // Ctrl+B toggle for Profile HUD
+    // Toggle Profile HUD
+    if state.input_scope(ctx).allows_global_shortcuts() && ctx.input(|i| settings.keymap.toggle_hud.matches(i)) {
+        state.dispatch(Action::ToggleProfileHud);
+    }
+
+    // Resized widths are saved once the drag ends, not on every frame of it
+    if state.widths_dirty && !ctx.input(|i| i.pointer.any_down()) {
+        state.widths_dirty = false;
+        if std::path::Path::new(&state.filename).exists() {
+            settings.set_column_widths(&state.filename, state.column_widths.clone());
+            if let Some(height) = state.row_height {
+                settings.set_row_height(&state.filename, height);
+            }
+        }
+    }
+
+    if settings.show_profile_hud {
+        profile_hud(ctx, state);
+    }
+    document_windows(ctx, state, settings);
+    status_bar(ctx, state, settings);
+    if state.quick_calc.is_some() {
+        quick_calc_overlay(ctx, state);
+    }
+
+    if state.show_audit_log {
+        audit_log_panel(ctx, state);
+    }
+    if state.show_problems {
+        problems_panel(ctx, state);
+    }
+    if state.show_reload_conflicts {
+        reload_conflicts_window(ctx, state);
+    }
+
+    // Overview of the whole table beside the scrollbar, with click-to-jump
+    if state.view_mode == ViewMode::Table && !state.file_changed {
+        egui::SidePanel::right("row_minimap")
+            .exact_width(14.0)
+            .resizable(false)
+            .frame(egui::Frame::NONE)
+            .show(ctx, |ui| {
+                if let Some(row) = row_minimap(ui, state) {
+                    let col = state.selected_cell.map_or_else(|| state.step_column(0, 0), |(_, c)| c);
+                    state.selected_cell = Some((row, col));
+                    state.scroll_to_row = Some(row);
+                }
+            });
+    }
+
+    egui::CentralPanel::default().show(ctx, |ui| central_panel(ui, ctx, state, settings, &style));
+
+    edit_modal_window(ctx, state);
+    tool_windows(ctx, state, settings, &style);
+}
+
+/// Pick up the results of background jobs that have finished since the last frame
+fn poll_jobs(state: &mut EditorState, ctx: &egui::Context) {
+    if let Some(result) = state.git_status_job.as_ref().and_then(|job| job.poll()) {
+        state.git_status_job = None;
+        state.git_status = result.ok().flatten();
+    }
+    if let Some((base, result)) = state.rebase_job.as_ref().and_then(|(base, job)| Some((base.clone(), job.poll()?))) {
+        state.rebase_job = None;
+        match result {
+            Ok(rebase) => state.finish_rebase(&base, rebase),
+            Err(e) => state.status_message = Some(e),
+        }
+    }
+    if let Some(result) = state.follow_job.as_ref().and_then(|job| job.poll()) {
+        state.follow_job = None;
+        state.finish_follow_scan(result.unwrap_or_else(|e| Err(anyhow::anyhow!(e))));
+    }
+    if let Some(result) = state.file_tables_job.as_ref().and_then(|job| job.poll()) {
+        state.file_tables_job = None;
+        if let Ok(tables) = result
+            && tables.len() > 1
+        {
+            state.status_message = Some(format!("{} tables separated by blank lines; pick one or keep the whole file", tables.len()));
+            state.file_tables = tables;
+            state.open_file_tables();
+        }
+    }
+    if let Some(result) = state.snapshot_job.as_ref().and_then(|job| job.poll()) {
+        state.snapshot_job = None;
+        match result {
+            Ok(Ok(snapshot)) => {
+                state.status_message = Some(format!("Snapshot saved ({})", file_info::format_size(snapshot.size)));
+                state.open_snapshots();
+            }
+            Ok(Err(e)) => state.status_message = Some(format!("Snapshot failed: {:#}", e)),
+            Err(e) => state.status_message = Some(e),
+        }
+    }
+    if let Some(result) = state.snapshot_restore_job.as_ref().and_then(|job| job.poll()) {
+        state.snapshot_restore_job = None;
+        match result {
+            Ok(Ok((snapshot, headers, rows))) => state.restore_snapshot(snapshot, headers, rows),
+            Ok(Err(e)) => state.status_message = Some(format!("Restoring the snapshot failed: {:#}", e)),
+            Err(e) => state.status_message = Some(e),
+        }
+    }
+    if let Some(result) = state.snapshot_diff_job.as_ref().and_then(|job| job.poll()) {
+        state.snapshot_diff_job = None;
+        match result {
+            Ok(Ok((label, diff))) => state.snapshots_window.set_diff(label, diff),
+            Ok(Err(e)) => state.status_message = Some(format!("Comparing failed: {:#}", e)),
+            Err(e) => state.status_message = Some(e),
+        }
+    }
+    if let Some(result) = state.git_diff_job.as_ref().and_then(|job| job.poll()) {
+        state.git_diff_job = None;
+        match result {
+            Ok(Ok(diff)) => {
+                state.git_diff = Some(diff);
+                state.show_git_diff = true;
+            }
+            Ok(Err(e)) => state.status_message = Some(format!("Comparing with HEAD failed: {:#}", e)),
+            Err(e) => state.status_message = Some(e),
+        }
+    }
+    if let Some(result) = state.schema_job.as_ref().and_then(|job| job.poll()) {
+        state.schema_job = None;
+        match result {
+            Ok(Ok(issues)) => {
+                state.status_message = Some(format!("Schema validation: {} issue(s)", issues.len()));
+                state.schema_issues = Some(issues);
+                state.show_schema_issues = true;
+            }
+            Ok(Err(e)) => state.status_message = Some(format!("{:#}", e)),
+            Err(e) => state.status_message = Some(e),
+        }
+    }
+    if let Some(result) = state.schema_generate_job.as_ref().and_then(|job| job.poll()) {
+        state.schema_generate_job = None;
+        match result {
+            Ok(mut schema) => {
+                // Row rules are written by hand, so a regenerated schema keeps them
+                if let Some(ref attached) = state.schema {
+                    schema.row_rules = attached.row_rules.clone();
+                }
+                if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("Table Schema", &["json"])
+                    .set_file_name("schema.json")
+                    .save_file()
+                {
+                    state.status_message = Some(match schema.save(&path) {
+                        Ok(()) => format!("Schema saved to {}", path.display()),
+                        Err(e) => format!("{:#}", e),
+                    });
+                }
+                state.schema = Some(schema);
+            }
+            Err(e) => state.status_message = Some(e),
+        }
+    }
+    if let Some(result) = state.split_job.as_ref().and_then(|job| job.poll()) {
+        state.split_job = None;
+        state.status_message = Some(match result {
+            Ok(Ok(parts)) => format!("Split into {} file(s)", parts.len()),
+            Ok(Err(e)) => format!("Split failed: {}", e),
+            Err(e) => e,
+        });
+    }
+    if let Some(result) = state.script_job.as_ref().and_then(|job| job.poll()) {
+        state.script_job = None;
+        match result {
+            Ok(Ok(outcome)) => {
+                state.script_window.log(outcome.output);
+                let message = match state.apply_script_changes(outcome.changes) {
+                    0 => "Script finished with no changes".to_string(),
+                    n if state.document().in_memory() => format!("Script finished: table now has {} row(s)", n),
+                    n => format!("Script finished: {} cell(s) changed", n),
+                };
+                state.script_window.log([message.clone()]);
+                state.status_message = Some(message);
+            }
+            Ok(Err(e)) => {
+                state.script_window.log([format!("Error: {}", e)]);
+                state.status_message = Some("Script failed".to_string());
+            }
+            Err(e) => state.status_message = Some(e),
+        }
+    }
+    if let Some(result) = state.print_job.as_ref().and_then(|job| job.poll()) {
+        state.print_job = None;
+        state.status_message = Some(match result {
+            Ok(Ok(pages)) => format!("Printed {} page(s)", pages),
+            Ok(Err(e)) => format!("Print failed: {}", e),
+            Err(e) => e,
+        });
+    }
+    if let Some(result) = state.copy_job.as_ref().and_then(|job| job.poll()) {
+        state.copy_job = None;
+        state.status_message = Some(match result {
+            Ok((tsv, html, count)) => match clipboard::write_system_clipboard_html(&html, &tsv) {
+                Ok(()) => format!("Copied {} row(s) to the clipboard", count),
+                Err(_) => {
+                    // No rich clipboard available: fall back to plain TSV
+                    ctx.copy_text(tsv);
+                    format!("Copied {} row(s) to the clipboard as text", count)
+                }
+            },
+            Err(e) => e,
+        });
+    }
+    if let Some(result) = state.handoff_job.as_ref().and_then(|job| job.poll()) {
+        state.handoff_job = None;
+        match result {
+            Ok(Ok(path)) => {
+                state.pandas_window.load(path);
+                state.show_pandas = true;
+            }
+            Ok(Err(e)) => state.status_message = Some(format!("Handoff failed: {}", e)),
+            Err(e) => state.status_message = Some(e),
+        }
+    }
+    if let Some(result) = state.export_job.as_ref().and_then(|job| job.poll()) {
+        state.export_job = None;
+        state.status_message = Some(match result {
+            Ok(Ok(paths)) => match paths.as_slice() {
+                [path] => format!("Exported to {}", path.display()),
+                _ => format!("Exported {} parts to {}", paths.len(), paths[0].parent().unwrap_or(std::path::Path::new("")).display()),
+            },
+            Ok(Err(e)) => format!("Export failed: {}", e),
+            Err(e) => e,
+        });
+    }
+    if let Some(result) = state.save_job.as_ref().and_then(|job| job.poll()) {
+        state.save_job = None;
+        match result {
+            Ok(Ok((path, remapped))) => {
+                match remapped {
+                    // The new file holds the edits, so it's what the document shows from now on
+                    Some(loader) => {
+                        state.file.edits.clear();
+                        state.edit_bases.clear();
+                        state.filename = path.to_string_lossy().to_string();
+                        state.swap_loader(loader);
+                        state.status_message = Some(format!("Saved {}", state.filename));
+                    }
+                    None => {
+                        state.status_message = Some(format!(
+                            "Saved a copy as {}; only comma-separated files open here, so this document stays on the original",
+                            path.display()
+                        ));
+                    }
+                }
+            }
+            Ok(Err(e)) => state.status_message = Some(format!("Save failed: {}", e)),
+            Err(e) => state.status_message = Some(e),
+        }
+    }
+    if let Some(result) = state.quick_calc_job.as_ref().and_then(|job| job.poll()) {
+        state.quick_calc_job = None;
+        match result {
+            Ok(calc) => state.quick_calc = calc,
+            Err(e) => state.status_message = Some(e),
+        }
+    }
+    if let Some(result) = state.problems_job.as_ref().and_then(|job| job.poll()) {
+        state.problems_job = None;
+        match result {
+            Ok(problems) => state.problems = Some(problems),
+            Err(e) => state.status_message = Some(e),
+        }
+    }
+    if let Some(result) = state.key_job.as_ref().and_then(|job| job.poll()) {
+        state.key_job = None;
+        match result {
+            Ok((generations, keys)) => {
+                state.status_message = Some(format!("{} key candidate(s) found", keys.keys.len()));
+                state.key_candidates = Some((generations, keys));
+            }
+            Err(e) => state.status_message = Some(e),
+        }
+    }
+    if let Some(result) = state.normalize_job.as_ref().and_then(|job| job.poll()) {
+        state.normalize_job = None;
+        match result {
+            Ok(clusters) => {
+                let col = state.normalize_window.column();
+                state.normalize_window.load(col, state.column_header(col), clusters);
+            }
+            Err(e) => state.status_message = Some(e),
+        }
+    }
+    if let Some(result) = state.checksum_job.as_ref().and_then(|job| job.poll()) {
+        state.checksum_job = None;
+        match result {
+            Ok(Ok(checksum)) => state.file_info_window.set_checksum(checksum),
+            Ok(Err(e)) => state.status_message = Some(format!("{:#}", e)),
+            Err(e) => state.status_message = Some(e),
+        }
+    }
+    if let Some(result) = state.geo_job.as_ref().and_then(|job| job.poll()) {
+        state.geo_job = None;
+        match result {
+            Ok(report) => state.geo_window.set_report(report),
+            Err(e) => state.status_message = Some(e),
+        }
+    }
+    if let Some(result) = state.rolling_job.as_ref().and_then(|job| job.poll()) {
+        state.rolling_job = None;
+        match result {
+            Ok(Some(column)) => state.finish_rolling_column(column),
+            Ok(None) => {}
+            Err(e) => state.status_message = Some(e),
+        }
+    }
+    if let Some(result) = state.sort_job.as_ref().and_then(|job| job.poll()) {
+        state.sort_job = None;
+        match result {
+            Ok(Ok(path)) => state.dispatch(Action::OpenPath(path)),
+            Ok(Err(e)) => state.status_message = Some(format!("Sort failed: {:#}", e)),
+            Err(e) => state.status_message = Some(e),
+        }
+    }
+    if let Some(result) = state.convert_job.as_ref().and_then(|job| job.poll()) {
+        state.convert_job = None;
+        match result {
+            Ok(Some(grid)) => {
+                state.finish_grid_conversion(grid);
+                state.status_message = Some("Loaded into an editable grid".to_string());
+            }
+            Ok(None) => state.status_message = Some("Conversion cancelled".to_string()),
+            Err(e) => state.status_message = Some(e),
+        }
+    }
+    if let Some(result) = state.rewrite_job.as_ref().and_then(|job| job.poll()) {
+        let label = state.rewrite_job.take().map(|job| job.label).unwrap_or_default();
+        match result {
+            Ok(edits) => {
+                let count = state.apply_cell_edits(edits);
+                state.status_message = Some(format!("{}: {} cell(s) changed", label, count));
+            }
+            Err(e) => state.status_message = Some(e),
+        }
+    }
+    if let Some(result) = state.duplicate_job.as_ref().and_then(|job| job.poll()) {
+        state.duplicate_job = None;
+        match result {
+            Ok(report) => state.duplicates = Some(report),
+            Err(e) => state.status_message = Some(e),
+        }
+    }
+    if let Some(result) = state.scatter_job.as_ref().and_then(|job| job.poll()) {
+        state.scatter_job = None;
+        match result {
+            Ok(sample) => state.scatter_window.set_sample(sample),
+            Err(e) => state.status_message = Some(e),
+        }
+    }
+    if let Some(result) = state.group_by_job.as_ref().and_then(|job| job.poll()) {
+        state.group_by_job = None;
+        match result {
+            Ok(summary) => state.group_by_window.set_summary(summary),
+            Err(e) => state.status_message = Some(e),
+        }
+    }
+    if let Some(result) = state.distinct_job.as_ref().and_then(|job| job.poll()) {
+        state.distinct_job = None;
+        match result {
+            Ok(distinct) => state.distinct_window.set_result(distinct),
+            Err(e) => state.status_message = Some(e),
+        }
+    }
+    if let Some(result) = state.invalid_job.as_ref().and_then(|job| job.poll()) {
+        state.invalid_job = None;
+        match result {
+            Ok(report) => state.invalid_values = Some(report),
+            Err(e) => state.status_message = Some(e),
+        }
+    }
+}
+
+/// File name, view switcher and the document-wide tools above the table
+fn top_bar(ctx: &egui::Context, state: &mut EditorState, style: &egui::Style) {
+    egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
+        ui.style_mut().text_styles = style.text_styles.clone(); // Apply font
+        ui.add_space(4.0);
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new("CSVit").strong());
+            ui.label(egui::RichText::new(&state.filename).color(egui::Color32::from_gray(150)));
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                 ui.selectable_value(&mut state.view_mode, ViewMode::Table, "Table");
+                 ui.selectable_value(&mut state.view_mode, ViewMode::Text, "Text");
+                 ui.selectable_value(&mut state.view_mode, ViewMode::Graph, "Graph");
+                 ui.separator();
+                 ui.checkbox(&mut state.word_wrap, "Word Wrap");
+                 if ui.button("🎨 Row Colors").on_hover_text("Color whole rows that meet a rule").clicked() {
+                     state.dispatch(Action::Open(Dialog::RowColors));
+                 }
+                 let columns_label = match state.projection {
+                     Some(ref cols) => format!("Columns ({}/{})", cols.len(), state.num_columns),
+                     None => "Columns".to_string(),
+                 };
+                 ui.menu_button("Schema", |ui| {
+                     if ui.button("Attach Schema...").clicked() {
+                         state.dispatch(Action::AttachSchema);
+                         ui.close();
+                     }
+                     if ui.add_enabled(state.schema.is_some(), egui::Button::new("Validate")).clicked() {
+                         state.dispatch(Action::ValidateSchema);
+                         ui.close();
+                     }
+                     if ui.add_enabled(state.schema_issues.is_some(), egui::Button::new("Show Issues")).clicked() {
+                         state.dispatch(Action::Open(Dialog::SchemaIssues));
+                         ui.close();
+                     }
+                     if ui.button("Row Rules...").on_hover_text("Checks that compare columns, e.g. end_date >= start_date").clicked() {
+                         state.dispatch(Action::Open(Dialog::RowRules));
+                         ui.close();
+                     }
+                     ui.separator();
+                     if ui.add_enabled(state.schema.is_some(), egui::Button::new("Save Schema...")).clicked() {
+                         state.dispatch(Action::SaveSchema);
+                         ui.close();
+                     }
+                     if ui.button("Generate Schema...").clicked() {
+                         state.dispatch(Action::GenerateSchema);
+                         ui.close();
+                     }
+                 });
+                 let copying = state.copy_job.is_some();
+                 ui.add_enabled_ui(!copying, |ui| {
+                     ui.menu_button("📋 Copy Table", |ui| {
+                         if ui.button("All rows").clicked() {
+                             state.dispatch(Action::CopyTable(None));
+                             ui.close();
+                         }
+                         ui.horizontal(|ui| {
+                             if ui.button("First").clicked() {
+                                 state.dispatch(Action::CopyTable(Some(state.copy_table_rows)));
+                                 ui.close();
+                             }
+                             ui.add(egui::DragValue::new(&mut state.copy_table_rows).range(1..=COPY_TABLE_LIMIT));
+                             ui.label("rows");
+                         });
+                         ui.label(egui::RichText::new("Visible columns, as TSV and HTML for spreadsheets").weak().small());
+                     });
+                 });
+                 if ui.button(columns_label).clicked() {
+                     state.dispatch(Action::Open(Dialog::ColumnPicker));
+                 }
+                 ui.separator();
+                 if !state.document().in_memory() {
+                     let saving = state.save_job.is_some();
+                     if ui.add_enabled(!saving, egui::Button::new("💾 Save As")).clicked() {
+                         state.dispatch(Action::SaveAs);
+                     }
+                     let splitting = state.split_job.is_some();
+                     if ui.add_enabled(!splitting, egui::Button::new("✂ Split")).clicked() {
+                         state.dispatch(Action::Open(Dialog::Split));
+                     }
+                 }
+                 if ui.button("📜 Script").clicked() {
+                     state.dispatch(Action::Open(Dialog::Script));
+                 }
+                 let handing_off = state.handoff_job.is_some();
+                 if ui.add_enabled(!handing_off, egui::Button::new("🐼 Open in pandas"))
+                     .on_hover_text("Write the visible columns to a temp CSV and show a Python snippet")
+                     .clicked()
+                 {
+                     state.dispatch(Action::OpenInPandas);
+                 }
+                 let exporting = state.export_job.is_some();
+                 if ui.add_enabled(!exporting, egui::Button::new("📤 Export")).clicked() {
+                     state.dispatch(Action::Open(Dialog::Export));
+                 }
+            });
+        });
+        ui.add_space(4.0);
+    });
+}
+
+/// Row and column editing, undo/redo and the Data menu
+fn edit_toolbar(ctx: &egui::Context, state: &mut EditorState) {
+    egui::TopBottomPanel::top("edit_toolbar").show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            ui.label("Edit:");
+            if ui.button("➕ Row").clicked() {
+                state.dispatch(Action::AddRow);
+            }
+            if ui.button("➖ Row").clicked() {
+                state.dispatch(Action::DeleteRow);
+            }
+            ui.separator();
+            if ui.button("➕ Col").clicked() {
+                state.dispatch(Action::AddColumn);
+            }
+            if ui.button("➖ Col").clicked() {
+                state.dispatch(Action::DeleteColumn);
+            }
+            ui.separator();
+            // Undo/Redo buttons
+            let (undo_count, redo_count) = state.document().history_len();
+            ui.add_enabled_ui(undo_count > 0, |ui| {
+                if ui.button(format!("↩ Undo ({})", undo_count)).clicked() {
+                    state.dispatch(Action::Undo);
+                }
+            });
+            ui.add_enabled_ui(redo_count > 0, |ui| {
+                if ui.button(format!("↪ Redo ({})", redo_count)).clicked() {
+                    state.dispatch(Action::Redo);
+                }
+            });
+            ui.separator();
+            ui.menu_button("Data", |ui| {
+                if !state.document().in_memory() {
+                    if ui.add_enabled(state.convert_job.is_none(), egui::Button::new("Convert to Editable Grid"))
+                        .on_hover_text("Load the whole file into memory so rows and columns can be inserted, deleted and transposed")
+                        .clicked()
+                    {
+                        state.dispatch(Action::ConvertToGrid);
+                        ui.close();
+                    }
+                    let mut follow = state.follow_file;
+                    if ui.checkbox(&mut follow, "Follow File")
+                        .on_hover_text("Show rows appended to the file as it grows")
+                        .changed()
+                    {
+                        state.dispatch(Action::FollowFile(follow));
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Skip first");
+                        ui.add(egui::DragValue::new(&mut state.preamble_draft).range(0..=10_000));
+                        ui.label("lines");
+                        if ui.button("Apply")
+                            .on_hover_text("Treat the lines above the real header as notes: hidden from the table, kept when saving")
+                            .clicked()
+                        {
+                            state.dispatch(Action::SetPreamble(state.preamble_draft));
+                            ui.close();
+                        }
+                    });
+                    if state.file_tables.len() > 1 && ui.button("Tables in File…").clicked() {
+                        state.dispatch(Action::Open(Dialog::FileTables));
+                        ui.close();
+                    }
+                    ui.horizontal(|ui| {
+                        ui.label("Footer");
+                        ui.add(egui::DragValue::new(&mut state.footer_draft).range(0..=10_000));
+                        ui.label("rows");
+                        if ui.button("Apply")
+                            .on_hover_text("Keep summary rows such as TOTAL out of sorting, profiles and charts; kept when saving")
+                            .clicked()
+                        {
+                            state.dispatch(Action::SetFooter(state.footer_draft));
+                            ui.close();
+                        }
+                    });
+                    ui.separator();
+                }
+                ui.checkbox(&mut state.transpose_first_column_headers, "First column becomes headers");
+                if ui.add_enabled(state.document().in_memory(), egui::Button::new("⇄ Transpose")).clicked() {
+                    state.dispatch(Action::Transpose);
+                    ui.close();
+                }
+                if ui.add_enabled(state.document().in_memory(), egui::Button::new("📎 Append File..."))
+                    .on_hover_text("Add the rows of another CSV, matching its columns to these by name")
+                    .on_disabled_hover_text("Inserting rows requires an editable grid (Data → Convert to Editable Grid)")
+                    .clicked()
+                {
+                    state.dispatch(Action::AppendFile);
+                    ui.close();
+                }
+                ui.separator();
+                ui.checkbox(&mut state.show_audit_log, "📜 Audit Log")
+                    .on_hover_text("Every cell and structural edit made this session, with timestamps");
+                if ui.checkbox(&mut state.show_problems, "⚠ Problems")
+                    .on_hover_text("Parse, encoding, ragged-row, validation and type problems in one list")
+                    .changed()
+                    && state.show_problems
+                    && state.problems.is_none()
+                {
+                    state.dispatch(Action::ScanProblems);
+                }
+                if ui.button("Σ Group By...")
+                    .on_hover_text("One row per group with counts, sums, means and more")
+                    .clicked()
+                {
+                    state.dispatch(Action::Open(Dialog::GroupBy));
+                    ui.close();
+                }
+                if ui.button("▦ Scatter Matrix...")
+                    .on_hover_text("Plot every pair of a few numeric columns to check how they correlate")
+                    .clicked()
+                {
+                    state.dispatch(Action::Open(Dialog::ScatterMatrix));
+                    ui.close();
+                }
+                if ui.button("🌍 Geo Check...")
+                    .on_hover_text("Validate latitude/longitude columns and plot them")
+                    .clicked()
+                {
+                    state.dispatch(Action::Open(Dialog::GeoCheck));
+                    ui.close();
+                }
+                if ui.button("🔁 Pipeline...")
+                    .on_hover_text("Filter, transform and sort, then export; save the steps to re-run on another file")
+                    .clicked()
+                {
+                    state.dispatch(Action::Open(Dialog::Pipeline));
+                    ui.close();
+                }
+            });
+            ui.separator();
+            if ui.button("💾 Save As").clicked() {
+                state.dispatch(Action::SaveAs);
+            }
+        });
+    });
+}
+
+/// Profile of the selected column and the table's key candidates, in a side panel
+fn profile_hud(ctx: &egui::Context, state: &mut EditorState) {
+    egui::SidePanel::right("profile_hud")
+        .resizable(true)
+        .default_width(280.0)
+        .min_width(200.0)
+        .show(ctx, |ui| {
+            let mut refresh = false;
+            ui.horizontal(|ui| {
+                ui.heading("📊 Column Profile");
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.add_enabled(state.profile_column.is_some(), egui::Button::new("⟳"))
+                        .on_hover_text("Profile the column again")
+                        .clicked()
+                    {
+                        refresh = true;
+                    }
+                    if state.profile_cache.pending_count() > 0 {
+                        ui.spinner().on_hover_text(format!("Profiling {} column(s)", state.profile_cache.pending_count()));
+                    }
+                });
+            });
+            ui.separator();
+
+            if let Some(col) = state.profile_column {
+                if refresh {
+                    state.profile_cache.invalidate(col);
+                }
+                state.request_profile(col);
+            }
+            if state.profile_cache.poll() {
+                ctx.request_repaint();
+            }
+            let mut full_profile = None;
+            let mut list_invalid = None;
+            let profile = state.profile_column
+                .and_then(|col| state.profile_cache.get(col, state.column_generation(col)));
+
+            if let Some(profile) = profile {
+                ui.label(format!("Column: {}", profile.header));
+                ui.label(format!("Type: {}", profile.data_type.as_ref().map_or("Unknown", |t| t.name())));
+                if let Some(semantic) = profile.semantic_type {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("Looks like: {}", semantic.name()));
+                        if profile.semantic_invalid > 0 {
+                            ui.label(egui::RichText::new(format!("({} invalid in sample)", profile.semantic_invalid))
+                                .color(ui.visuals().warn_fg_color));
+                        }
+                    });
+                    let running = state.invalid_job.is_some();
+                    if ui.add_enabled(!running, egui::Button::new("List Invalid Values")).clicked() {
+                        list_invalid = Some(Action::ListInvalidValues { col: profile.column_index, semantic });
+                    }
+                }
+                if state.current_key_candidates().is_some_and(|keys| keys.keys.contains(&vec![profile.column_index])) {
+                    ui.label(egui::RichText::new("🔑 Key candidate").color(egui::Color32::from_rgb(230, 190, 80)));
+                }
+                ui.separator();
+
+                // Data health
+                ui.collapsing("📋 Data Health", |ui| {
+                    ui.label(format!("Total Rows: {}", profile.total_count));
+                    // File-backed documents are profiled from a sample
+                    let rows = state.document().row_count().saturating_sub(state.first_data_row());
+                    if !state.document().in_memory() && profile.total_count < rows {
+                        ui.horizontal(|ui| {
+                            ui.label(egui::RichText::new(format!("Sampled from {} rows", rows)).weak());
+                            if ui.small_button("Profile All Rows").clicked() {
+                                full_profile = Some(Action::ProfileAllRows(profile.column_index));
+                            }
+                        });
+                    }
+                    ui.label(format!("Null/Empty: {} ({:.1}%)", profile.null_count, profile.null_percentage()));
+                    ui.label(format!("Unique Values: {}", profile.unique_count));
+                });
+
+                // Numeric stats (if applicable)
+                if profile.min.is_some() {
+                    ui.separator();
+                    ui.collapsing("📈 Numeric Stats", |ui| {
+                        if let Some(min) = profile.min {
+                            ui.label(format!("Min: {:.4}", min));
+                        }
+                        if let Some(max) = profile.max {
+                            ui.label(format!("Max: {:.4}", max));
+                        }
+                        if let Some(mean) = profile.mean {
+                            ui.label(format!("Mean: {:.4}", mean));
+                        }
+                        if let Some(median) = profile.median {
+                            ui.label(format!("Median: {:.4}", median));
+                        }
+                        if let Some(std) = profile.std_dev {
+                            ui.label(format!("Std Dev: {:.4}", std));
+                        }
+                        if let Some(sum) = profile.sum {
+                            ui.label(format!("Sum: {:.4}", sum));
+                        }
+                    });
+                }
+
+                // Top values
+                if !profile.top_values.is_empty() {
+                    ui.separator();
+                    ui.collapsing("🏆 Top Values", |ui| {
+                        for (i, (val, count)) in profile.top_values.iter().enumerate() {
+                            let display_val = if val.len() > 25 {
+                                format!("{}...", &val[..22])
+                            } else {
+                                val.clone()
+                            };
+                            ui.label(format!("{}. {} ({})", i + 1, display_val, count));
+                        }
+                    });
+                }
+            } else if let Some(col) = state.profile_column {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(format!("Profiling {}...", state.column_header(col)));
+                });
+            } else {
+                ui.label("Select a column to view its profile.");
+                ui.label("");
+                ui.label("Click on a column header or select a cell to analyze that column.");
+            }
+
+            // Primary-key candidates across the whole table
+            ui.separator();
+            ui.collapsing("🔑 Key Candidates", |ui| {
+                match state.current_key_candidates() {
+                    Some(found) => {
+                        if found.keys.is_empty() {
+                            ui.label("No unique, non-null column or column pair.");
+                        }
+                        for key in &found.keys {
+                            let names: Vec<String> = key.iter().map(|&c| state.column_header(c)).collect();
+                            ui.label(names.join(" + "));
+                        }
+                        let rows = state.document().row_count().saturating_sub(state.first_data_row());
+                        if found.rows_checked < rows {
+                            ui.label(egui::RichText::new(format!(
+                                "Too many values to hold; unique in the first {} of {} rows",
+                                found.rows_checked, rows
+                            )).weak());
+                        } else if found.pairs_skipped {
+                            ui.label(egui::RichText::new("Too many values to also check column pairs").weak());
+                        }
+                    }
+                    None if state.key_candidates.is_some() => {
+                        ui.label(egui::RichText::new("Columns were edited since; find them again").weak());
+                    }
+                    None => {
+                        ui.label(egui::RichText::new("Not analyzed yet").weak());
+                    }
+                }
+                let running = state.key_job.is_some();
+                if ui.add_enabled(!running, egui::Button::new("Find Key Candidates")).clicked() {
+                    state.dispatch(Action::FindKeyCandidates);
+                }
+            });
+            for action in list_invalid.into_iter().chain(full_profile) {
+                state.dispatch(action);
+            }
+        });
+}
+
+/// Dialogs for saving, schemas, snapshots and other whole-document tools
+fn document_windows(ctx: &egui::Context, state: &mut EditorState, settings: &mut Settings) {
+    // Save As: grids are written in one go, file-backed documents are streamed
+    if state.show_save_as
+        && let Some(options) = state.save_as_window.show(ctx, &mut state.show_save_as)
+    {
+        if state.document().in_memory() {
+            state.save_grid_as(&options);
+        } else if state.rebase_job.is_some() {
+            // Its edits are out of the buffer until they're matched to rows again
+            state.status_message = Some("Wait for the reloaded edits to be re-applied before saving".to_string());
+        } else if let Some(path) = rfd::FileDialog::new().add_filter("CSV", &["csv", "tsv", "txt"]).save_file() {
+            let loader = state.file.loader.clone();
+            let edits = state.file.edits.clone();
+            let total = loader.total_records();
+            state.save_job = Some(Job::spawn("Saving", move |progress| {
+                crate::backend::writer::save_loader_as(&loader, &edits, 0..total, &path, &options, progress)?;
+                if options.delimiter != b',' {
+                    return Ok((path, None));
+                }
+                // Reuse the index when the saved bytes start with it (e.g. an unedited save)
+                let remapped = match loader.extend(&path).ok().flatten() {
+                    Some((remapped, _)) => remapped,
+                    None => CsvLoader::new(&path)?.with_preamble(loader.preamble_lines()).with_footer(loader.footer_rows()),
+                };
+                Ok((path, Some(remapped)))
+            }));
+        }
+    }
+    if state.show_column_format
+        && let Some((col, format)) = state.column_format_window.show(ctx, &mut state.show_column_format)
+    {
+        if format.is_plain() {
+            state.column_formats.remove(&col);
+        } else {
+            state.column_formats.insert(col, format);
+        }
+    }
+    if state.show_file_tables
+        && let Some(choice) = state.file_tables_window.show(ctx, &mut state.show_file_tables)
+    {
+        let count = state.file_tables.len();
+        if let Some(table) = state.file_tables.get(choice) {
+            let message = format!("Table {} of {}: {} row(s)", choice + 1, count, table.rows);
+            state.set_table_bounds(table.preamble, table.footer, message);
+        } else if let (Some(first), Some(last)) = (state.file_tables.first(), state.file_tables.last()) {
+            state.set_table_bounds(first.preamble, last.footer, format!("All {} tables as one", count));
+        }
+    }
+    if state.show_row_colors
+        && let Some(rules) = state.row_colors_window.show(ctx, &mut state.show_row_colors)
+    {
+        state.set_row_color_rules(rules);
+    }
+    if state.show_paste_special
+        && let Some((mode, rows)) = state.paste_window.show(ctx, &mut state.show_paste_special)
+    {
+        state.paste_rows(mode, rows);
+    }
+    if state.show_snapshots {
+        let busy = state.snapshot_job.is_some() || state.snapshot_diff_job.is_some() || state.snapshot_restore_job.is_some();
+        // Only a file's restore replaces the document; a grid's can be undone
+        let unsaved = !state.document().in_memory() && state.has_unsaved_edits();
+        if let Some(request) = state.snapshots_window.show(ctx, &mut state.show_snapshots, busy, unsaved) {
+            state.snapshot_request(request);
+        }
+    }
+    if state.show_git_diff
+        && let Some((ref headers, ref diff)) = state.git_diff
+    {
+        egui::Window::new("Changes since HEAD")
+            .open(&mut state.show_git_diff)
+            .resizable(true)
+            .default_width(480.0)
+            .show(ctx, |ui| crate::gui::windows::snapshots::diff_list(ui, headers, diff));
+    }
+    if state.show_column_mapping
+        && let Some((at, rows)) = state.column_mapping_window.show(ctx, &mut state.show_column_mapping)
+    {
+        state.insert_mapped_rows(at, rows);
+    }
+    if state.show_row_rules
+        && let Some(rules) = state.row_rules_window.show(ctx, &mut state.show_row_rules)
+    {
+        state.schema.get_or_insert_default().row_rules = rules;
+        state.validate_schema();
+    }
+    if state.show_schema_issues
+        && let Some(ref issues) = state.schema_issues
+    {
+        let headers = state.source_headers();
+        if let Some((row, col)) = state.schema_window.show(ctx, &mut state.show_schema_issues, issues, &headers) {
+            state.selected_cell = Some((row, col));
+            state.scroll_to_row = Some(row);
+        }
+    }
+    // Column subset picker (shown on open for very wide files)
+    if state.show_column_picker {
+        let headers = state.source_headers();
+        if let Some(projection) = state.column_picker.show(ctx, &mut state.show_column_picker, &headers) {
+            state.projection = projection;
+            if let Some((r, c)) = state.selected_cell
+                && !state.display_columns().contains(&c)
+            {
+                state.selected_cell = Some((r, state.step_column(c, 0)));
+            }
+        }
+    }
+    // Split a file-backed document into several files
+    if state.show_split {
+        let columns: Vec<String> = (0..state.num_columns).map(|c| state.column_header(c)).collect();
+        if let Some(options) = state.split_window.show(ctx, &mut state.show_split, &columns)
+            && let Some(dir) = rfd::FileDialog::new().pick_folder()
+        {
+            let loader = state.file.loader.clone();
+            let edits = state.file.edits.clone();
+            let stem = std::path::Path::new(&state.filename)
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_else(|| "split".to_string());
+            state.split_job = Some(Job::spawn("Splitting", move |progress| {
+                crate::backend::split::split_loader(&loader, &edits, &dir, &stem, &options, progress)
+            }));
+        }
+    }
+    // Script console
+    if state.show_script {
+        let running = state.script_job.is_some();
+        if let Some(code) = state.script_window.show(ctx, &mut state.show_script, running) {
+            state.run_script(code);
+        }
+    }
+    if state.show_pandas
+        && let Some(message) = state.pandas_window.show(ctx, &mut state.show_pandas)
+    {
+        state.status_message = Some(message);
+    }
+    // Export dialog and its background job
+    if state.show_export
+        && let Some(request) = state.export_window.show(ctx, &mut state.show_export, &settings.export_presets)
+    {
+        match request {
+            ExportRequest::Export(options) => {
+                let extension = options.format.extension();
+                if let Some(path) = rfd::FileDialog::new().add_filter(options.format.name(), &[extension]).save_file() {
+                    state.export(options, path);
+                }
+            }
+            ExportRequest::SavePreset(preset) => {
+                state.status_message = Some(format!("Saved export preset \"{}\"", preset.name));
+                settings.save_export_preset(preset);
+            }
+            ExportRequest::DeletePreset(name) => settings.remove_export_preset(&name),
+        }
+    }
+    if state.show_pipeline
+        && let Some(request) = state.pipeline_window.show(ctx, &mut state.show_pipeline)
+    {
+        match request {
+            PipelineRequest::Preview(steps) => {
+                let preview = state.preview_pipeline(&steps).map_err(|e| format!("{:#}", e));
+                state.pipeline_window.set_preview(preview);
+            }
+            PipelineRequest::Run(_) if state.export_job.is_some() => {
+                state.status_message = Some("Wait for the running export to finish".to_string());
+            }
+            PipelineRequest::Run(pipeline) => state.run_pipeline(pipeline),
+        }
+    }
+}
+
+/// Vim mode, position, job progress or the last message, and git status
+fn status_bar(ctx: &egui::Context, state: &mut EditorState, settings: &Settings) {
+    let vim_enabled = settings.keybinding_mode == KeybindingMode::Vim;
+    let mut diff_against_head = false;
+    egui::TopBottomPanel::bottom("status_bar")
+        .exact_height(24.0)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                // Mode indicator
+                if vim_enabled {
+                    let (mode_text, mode_color) = match state.vim.mode {
+                        VimMode::Normal => ("-- NORMAL --", egui::Color32::from_rgb(100, 200, 100)),
+                        VimMode::Insert => ("-- INSERT --", egui::Color32::from_rgb(100, 150, 255)),
+                        VimMode::Visual => ("-- VISUAL --", egui::Color32::from_rgb(255, 150, 100)),
+                        VimMode::Command => (":", egui::Color32::from_rgb(200, 200, 100)),
+                    };
+                    ui.label(egui::RichText::new(mode_text).color(mode_color).strong().monospace());
+                    ui.separator();
+                }
+                
+                // Position indicator
+                if let Some((r, c)) = state.selected_cell {
+                    ui.label(egui::RichText::new(format!("{}:{}", r + 1, c + 1)).monospace());
+                }
+
+                // Partially typed count / sequence
+                let pending = state.vim.pending_keys();
+                if vim_enabled && !pending.is_empty() {
+                    ui.label(egui::RichText::new(pending).monospace());
+                }
+
+                if let Some((label, progress)) = state.active_job() {
+                    ui.separator();
+                    ui.spinner();
+                    match progress.fraction() {
+                        Some(f) => ui.label(format!("{} {:.0}%", label, f * 100.0)),
+                        None => ui.label(label),
+                    };
+                    ctx.request_repaint();
+                } else if let Some(ref message) = state.status_message {
+                    ui.separator();
+                    ui.label(egui::RichText::new(message).weak());
+                }
+
+                if let Some(ref status) = state.git_status {
+                    ui.separator();
+                    ui.label(egui::RichText::new(format!("🔀 {} · {}", status.branch, status.state.name())).weak());
+                    if status.state.in_head()
+                        && ui.add_enabled(state.git_diff_job.is_none(), egui::Button::new("Diff against HEAD").small()).clicked()
+                    {
+                        diff_against_head = true;
+                    }
+                }
+                
+                if vim_enabled {
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        ui.label(egui::RichText::new("[N]hjkl:move  i:insert  gg/G:top/bottom  {}:blocks  ^D/^U:half page  yy/dd/p/P:rows  \"x:register  Esc:normal").weak().small());
+                    });
+                }
+            });
+        });
+    if diff_against_head {
+        state.dispatch(Action::DiffAgainstHead);
+    }
+}
+
+/// The document itself, as a table, raw text or a graph
+fn central_panel(ui: &mut egui::Ui, ctx: &egui::Context, state: &mut EditorState, settings: &Settings, style: &egui::Style) {
+    ui.style_mut().text_styles = style.text_styles.clone(); // Apply font
+
+    // Rows aren't read from a file that changed underneath us
+    if state.file_changed {
+        file_changed_notice(ui, state);
+        return;
+    }
+
+    // Use grid if available, otherwise use loader
+    let total_rows = state.document().row_count();
+    let mut scroll_target = state.scroll_to_row.take();
+    if let Some(result) = state.filter_job.as_ref().and_then(|job| job.poll()) {
+        state.filter_job = None;
+        match result {
+            Ok(rows) => {
+                state.filtered_rows = Some(rows);
+                state.filter_generation += 1;
+            }
+            Err(e) => state.status_message = Some(e),
+        }
+    }
+    state.check_row_order();
+    // Rows were added or removed since the filter ran, so its row numbers are stale
+    if !state.row_filter.is_empty() && state.filter_job.is_none() && state.filtered_total != total_rows {
+        state.apply_filter();
+    }
+    if let Some(target) = grid_shortcuts(ui, ctx, state, settings, total_rows) {
+        scroll_target = Some(target);
+    }
+
+    let row_height = state.row_height(settings);
+
+    match state.view_mode {
+       ViewMode::Table => {
+           table_view(ui, ctx, state, settings, total_rows, scroll_target, row_height);
+       }
+       ViewMode::Text => {
+           text_view(ui, ctx, state, settings, total_rows, row_height);
+       }
+       ViewMode::Graph => {
+           graph_view(ctx, state);
+       }
+    }
+}
+
+/// In place of the table while the file on disk differs from the one indexed
+fn file_changed_notice(ui: &mut egui::Ui, state: &mut EditorState) {
+    ui.vertical_centered(|ui| {
+        ui.add_space(60.0);
+        ui.heading("⚠ File changed on disk");
+        ui.label(format!("{} was modified by another program.", state.filename));
+        ui.label("The view is paused until the file is reloaded.");
+        if state.file.edits.is_dirty() {
+            ui.label(egui::RichText::new("Unsaved edits are re-applied to their rows; edits to cells that also changed on disk are listed as conflicts.").weak());
+            ui.horizontal(|ui| {
+                ui.label("Find edited rows by:");
+                let headers = state.edit_bases.get(&0).cloned().unwrap_or_default();
+                let name = |m: RowMatch| match m {
+                    RowMatch::Content => "Whole row".to_string(),
+                    RowMatch::Key(c) => headers.get(c).cloned().unwrap_or_else(|| format!("Column {}", c + 1)),
+                };
+                egui::ComboBox::from_id_salt("reload_match")
+                    .selected_text(name(state.reload_match))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut state.reload_match, RowMatch::Content, name(RowMatch::Content))
+                            .on_hover_text("The row with exactly the values it had before editing");
+                        for c in 0..headers.len() {
+                            ui.selectable_value(&mut state.reload_match, RowMatch::Key(c), name(RowMatch::Key(c)));
+                        }
+                    });
+            });
+        }
+        ui.add_space(8.0);
+        if ui.button("⟳ Reload").clicked() {
+            state.dispatch(Action::Reload);
+        }
+    });
+}
+
+/// Keyboard navigation, editing, paste and undo for the table; returns the row to scroll to
+fn grid_shortcuts(ui: &mut egui::Ui, ctx: &egui::Context, state: &mut EditorState, settings: &Settings, total_rows: usize) -> Option<usize> {
+    let mut scroll_target = None;
+    let scroll_target = &mut scroll_target;
+    let scope = state.input_scope(ctx);
+
+    // Keyboard Navigation
+    if scope == InputScope::Grid {
+        // Vim mode: hjkl navigation (only in Normal mode)
+        let vim_mode_active = settings.keybinding_mode == KeybindingMode::Vim && state.vim.mode == VimMode::Normal;
+        let vim_actions = if vim_mode_active {
+            ui.input(|i| state.vim.handle_input(i))
+        } else {
+            Vec::new()
+        };
+
+        if let Some((r, c)) = state.selected_cell {
+            // Arrow keys always work, hjkl only in Vim mode
+            let move_down = ui.input(|i| settings.keymap.move_down.matches(i));
+            let move_up = ui.input(|i| settings.keymap.move_up.matches(i));
+            let move_right = ui.input(|i| settings.keymap.move_right.matches(i));
+            let move_left = ui.input(|i| settings.keymap.move_left.matches(i));
+            // Spreadsheet jumps; consumed so the scroll area doesn't also act on them
+            let page = ((ui.available_height() / state.row_height(settings)) as usize).max(1);
+            let last_row = total_rows.saturating_sub(1);
+            let columns = state.display_columns();
+            let (first_col, last_col) = (columns.first().copied().unwrap_or(c), columns.last().copied().unwrap_or(c));
+            // Shift+arrows grow a block selection out from where it started
+            let extend = ui.input_mut(|i| {
+                if i.consume_key(egui::Modifiers::SHIFT, egui::Key::ArrowDown) {
+                    Some((1, 0))
+                } else if i.consume_key(egui::Modifiers::SHIFT, egui::Key::ArrowUp) {
+                    Some((-1, 0))
+                } else if i.consume_key(egui::Modifiers::SHIFT, egui::Key::ArrowRight) {
+                    Some((0, 1))
+                } else if i.consume_key(egui::Modifiers::SHIFT, egui::Key::ArrowLeft) {
+                    Some((0, -1))
+                } else {
+                    None
+                }
+            });
+            let jump = ui.input_mut(|i| {
+                if i.consume_key(egui::Modifiers::COMMAND, egui::Key::Home) {
+                    Some((0, first_col))
+                } else if i.consume_key(egui::Modifiers::COMMAND, egui::Key::End) {
+                    Some((last_row, last_col))
+                } else if i.consume_key(egui::Modifiers::NONE, egui::Key::Home) {
+                    Some((r, first_col))
+                } else if i.consume_key(egui::Modifiers::NONE, egui::Key::End) {
+                    Some((r, last_col))
+                } else if i.consume_key(egui::Modifiers::NONE, egui::Key::PageDown) {
+                    Some(((r + page).min(last_row), c))
+                } else if i.consume_key(egui::Modifiers::NONE, egui::Key::PageUp) {
+                    Some((r.saturating_sub(page), c))
+                } else if i.consume_key(egui::Modifiers::SHIFT, egui::Key::Tab) {
+                    Some((r, state.step_column(c, -1)))
+                } else if i.consume_key(egui::Modifiers::NONE, egui::Key::Tab) {
+                    Some((r, state.step_column(c, 1)))
+                } else {
+                    None
+                }
+            });
+
+            if jump.is_some() || move_down || move_up || move_right || move_left {
+                state.selection_anchor = None;
+            }
+            if let Some((dr, dc)) = extend {
+                state.selection_anchor.get_or_insert((r, c));
+                let next = (state.step_row(r, dr, total_rows), state.step_column(c, dc));
+                state.selected_cell = Some(next);
+                *scroll_target = Some(next.0);
+            } else if let Some((row, col)) = jump {
+                state.selected_cell = Some((row, col));
+                *scroll_target = Some(row);
+            } else if move_down {
+                let next_row = state.step_row(r, 1, total_rows);
+                state.selected_cell = Some((next_row, c));
+                *scroll_target = Some(next_row);
+            } else if move_up {
+                 let prev_row = state.step_row(r, -1, total_rows);
+                 state.selected_cell = Some((prev_row, c));
+                 *scroll_target = Some(prev_row);
+            } else if move_right {
+                 state.selected_cell = Some((r, state.step_column(c, 1)));
+                 *scroll_target = Some(r);
+            } else if move_left {
+                 state.selected_cell = Some((r, state.step_column(c, -1)));
+                 *scroll_target = Some(r);
+            } else if !vim_actions.is_empty() {
+                 // Half a viewport worth of rows for Ctrl+D / Ctrl+U
+                 let half_page = ((ui.available_height() / state.row_height(settings)) as usize / 2).max(1);
+                 for action in vim_actions {
+                     if let Some(target) = apply_vim_action(state, ctx, action, total_rows, half_page) {
+                         *scroll_target = Some(target);
+                     }
+                 }
+            } else if ui.input(|i| settings.keymap.find_duplicates.matches(i)) {
+                 state.dispatch(Action::FindDuplicates(c));
+            } else if ui.input(|i| settings.keymap.quick_calc.matches(i)) {
+                 state.dispatch(Action::QuickCalc);
+            } else if ui.input(|i| settings.keymap.grow_column.matches(i)) {
+                 state.dispatch(Action::ResizeColumn { col: c, delta: COLUMN_RESIZE_STEP });
+            } else if ui.input(|i| settings.keymap.shrink_column.matches(i)) {
+                 state.dispatch(Action::ResizeColumn { col: c, delta: -COLUMN_RESIZE_STEP });
+            } else if ui.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::Enter))
+                || ui.input(|i| settings.keymap.edit_cell.matches(i))
+            {
+                 // Enter is consumed so the editor opened here doesn't commit on it
+                 state.dispatch(Action::StartEdit { row: r, col: c, text: state.cell_value(r, c) });
+            } else if settings.use_type_to_edit
+                && settings.keybinding_mode != KeybindingMode::Vim
+                && let Some(typed) = ui.input(typed_text)
+            {
+                 // Like a spreadsheet, the typed text replaces what was there
+                 state.dispatch(Action::StartEdit { row: r, col: c, text: typed });
+            }
+        } else {
+            // Initial selection on arrow key or hjkl
+             let any_nav = ui.input(|i| {
+                 settings.keymap.move_down.matches(i) || settings.keymap.move_up.matches(i) || 
+                 settings.keymap.move_right.matches(i) || settings.keymap.move_left.matches(i)
+             }) || vim_actions.iter().any(|a| matches!(a,
+                 VimAction::MoveDown(_) | VimAction::MoveUp(_) | VimAction::MoveLeft(_) | VimAction::MoveRight(_)));
+             if any_nav {
+                 let first_col = state.display_columns().first().copied().unwrap_or(0);
+                 state.selected_cell = Some((0, first_col));
+                 *scroll_target = Some(0);
+             }
+        }
+    }
+
+    // Ctrl+V pastes values at the selection, Ctrl+Shift+V opens Paste Special
+    if scope == InputScope::Grid {
+        let pasted = ui.input(|i| i.events.iter().find_map(|e| match e {
+            egui::Event::Paste(text) => Some((text.clone(), i.modifiers.shift)),
+            _ => None,
+        }));
+        let copied = ui.input(|i| i.events.iter().any(|e| matches!(e, egui::Event::Copy)));
+        if copied
+            && let Some((r, c)) = state.selected_cell
+        {
+            ctx.copy_text(state.cell_value(r, c));
+        }
+        if let Some((text, special)) = pasted {
+            state.dispatch(Action::Paste { text, special });
+        }
+    }
+
+    // Exit insert mode with Escape (Vim mode)
+    if settings.keybinding_mode == KeybindingMode::Vim
+        && state.vim.mode == VimMode::Insert
+        && ui.input(|i| i.key_pressed(egui::Key::Escape))
+    {
+        state.vim.mode = VimMode::Normal;
+    }
+
+    // Undo/Redo keyboard shortcuts
+    // Text fields and editors undo their own typing
+    if scope == InputScope::Grid && ui.input(|i| settings.keymap.undo.matches(i)) {
+        state.dispatch(Action::Undo);
+    }
+    if scope == InputScope::Grid && ui.input(|i| settings.keymap.redo.matches(i)) {
+        state.dispatch(Action::Redo);
+    }
+    if scope == InputScope::Grid && ui.input(|i| settings.keymap.grow_rows.matches(i)) {
+        state.dispatch(Action::ResizeRows(ROW_RESIZE_STEP));
+    }
+    if scope == InputScope::Grid && ui.input(|i| settings.keymap.shrink_rows.matches(i)) {
+        state.dispatch(Action::ResizeRows(-ROW_RESIZE_STEP));
+    }
+    *scroll_target
+}
+
+fn table_view(
+    ui: &mut egui::Ui,
+    ctx: &egui::Context,
+    state: &mut EditorState,
+    settings: &Settings,
+    total_rows: usize,
+    scroll_target: Option<usize>,
+    row_height: f32,
+) {
+    if state.file.loader.preamble_lines() > 0 {
+        preamble_banner(ui, state);
+    }
+    if state.file.loader.footer_rows() > 0 {
+        footer_banner(ui, state);
+    }
+    if !state.row_filter.is_empty() {
+        filter_bar(ui, state);
+    }
+    let shown_rows = state.filtered_rows.as_ref().map_or(total_rows, Vec::len);
+    let heat_ranges = state.heatmap_ranges();
+    let line_height = ui.text_style_height(&egui::TextStyle::Body);
+    // A multi-line edit taller than its row
+    let tall_edit = state.editing_cell.is_some()
+        && edit_lines(&state.input_buffer) as f32 * line_height + 8.0 > row_height;
+    egui::ScrollArea::horizontal().show(ui, |ui| {
+        // Striping paints with faint_bg_color; the configured stripe only
+        // overrides it for the table, not for other faint backgrounds
+        if let Some([r, g, b]) = settings.stripe_color {
+            ui.visuals_mut().faint_bg_color = egui::Color32::from_rgb(r, g, b);
+        }
+        let mut builder = TableBuilder::new(ui)
+            .striped(true)
+            .resizable(true)
+            .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
+            .column(Column::auto()); // Index
+
+        let display_columns = state.display_columns();
+        for &col in &display_columns {
+            let width = state.column_widths.get(col).copied().unwrap_or(DEFAULT_COLUMN_WIDTH);
+            builder = builder.column(Column::initial(width).resizable(true));
+        }
+
+        if let Some(target_row) = scroll_target {
+            builder = builder.scroll_to_row(state.display_row(target_row), Some(egui::Align::Center));
+        }
+        // Widths are kept per file, so key the table's state by it
+        builder = builder.id_salt(&state.filename);
+        if std::mem::take(&mut state.reset_widths) {
+            builder.reset();
+        }
+
+        // The pinned row rides in the sticky header, under the column names
+        let baseline = state.baseline_row
+            .filter(|&r| r < total_rows)
+            .map(|r| (r, state.document().row_projected(r, &display_columns)));
+        let baseline_numbers: Vec<Option<f64>> = baseline.as_ref()
+            .filter(|_| state.baseline_deltas)
+            .map(|(_, fields)| fields.iter().map(|f| f.trim().parse::<f64>().ok()).collect())
+            .unwrap_or_default();
+        let pinned_height = if baseline.is_some() { row_height } else { 0.0 };
+
+        let mut visible_columns = Vec::new();
+        builder
+            .header(HEADER_HEIGHT + pinned_height, |mut header| {
+                header.col(|cell| {
+                    let (mut top, band) = split_header_cell(cell);
+                    top.strong("Row");
+                    cell.expand_to_include_rect(top.min_rect());
+                    if let Some((row, _)) = baseline {
+                        cell.painter().rect_filled(band, 0.0, baseline_fill(cell));
+                        let mut band_ui = cell.new_child(egui::UiBuilder::new().max_rect(band).layout(egui::Layout::left_to_right(egui::Align::Center)));
+                        band_ui.label(format!("📌{}", row));
+                        band_ui.toggle_value(&mut state.baseline_deltas, "Δ").on_hover_text("Show each number's difference from this row");
+                        if band_ui.small_button("✖").on_hover_text("Unpin").clicked() {
+                            state.dispatch(Action::PinBaseline(None));
+                        }
+                        cell.expand_to_include_rect(band_ui.min_rect());
+                    }
+                });
+                for (position, &i) in display_columns.iter().enumerate() {
+                    header.col(|cell| {
+                        let (mut top, band) = split_header_cell(cell);
+                        if let Some((_, ref fields)) = baseline {
+                            let value = fields.get(position).map_or("", String::as_str);
+                            cell.painter().rect_filled(band, 0.0, baseline_fill(cell));
+                            let mut job = egui::text::LayoutJob::single_section(
+                                value.to_string(),
+                                egui::TextFormat::simple(egui::FontId::proportional(settings.font_size), cell.visuals().strong_text_color()),
+                            );
+                            job.wrap = egui::text::TextWrapping::truncate_at_width(band.width() - 2.0 * settings.cell_padding);
+                            let galley = cell.painter().layout_job(job);
+                            let pos = band.min + egui::vec2(settings.cell_padding, (band.height() - galley.size().y) / 2.0);
+                            cell.painter().galley(pos, galley, cell.visuals().strong_text_color());
+                        }
+                        let ui = &mut top;
+                        if ui.is_rect_visible(ui.max_rect()) {
+                            visible_columns.push(i);
+                        }
+                        // The table owns the live widths; mirror them so they can be saved
+                        let width = ui.max_rect().width();
+                        if let Some(known) = state.column_widths.get_mut(i)
+                            && (*known - width).abs() > 0.5
+                        {
+                            *known = width;
+                            // Dragging a column edge takes it out of automatic sizing
+                            if ui.input(|input| input.pointer.any_down()) {
+                                state.manual_widths.insert(i);
+                                state.widths_dirty = true;
+                            }
+                        }
+                        let header_response = ui.selectable_label(
+                            state.selected_column == Some(i),
+                            egui::RichText::new(format!("Col {}", i)).strong(),
+                        );
+                        if header_response.clicked() {
+                            state.selected_column = Some(i);
+                            state.profile_column = Some(i);
+                            // Queued even with the HUD closed, so it's ready when opened
+                            state.request_profile(i);
+                        }
+                        let header_name = state.column_header(i);
+                        header_response.widget_info(|| {
+                            egui::WidgetInfo::labeled(egui::WidgetType::Label, true, format!("Column header {}", header_name))
+                        });
+                        header_response.context_menu(|ui| column_menu(ui, state, i));
+                        if settings.show_type_badges
+                            && let Some(profile) = state.profile_cache.get(i, state.column_generation(i))
+                        {
+                            type_badge(ui, profile);
+                        }
+                        if settings.show_sparklines
+                            && let Some((generation, _, line)) = state.sparklines.get(&i)
+                            && *generation == state.column_generation(i)
+                        {
+                            sparkline(ui, line);
+                        }
+                    });
+                }
+            })
+            .body(|body| {
+                let mut rendered = (usize::MAX, 0);
+                // Block selections are tinted; a lone selected cell just gets its outline
+                let selection_block = state.selection_anchor.and_then(|_| state.selection_block());
+                let render_row = |mut row: egui_extras::TableRow<'_, '_>| {
+                    let row_index = state.filtered_rows.as_ref()
+                        .and_then(|rows| rows.get(row.index()).copied())
+                        .unwrap_or(row.index());
+                    rendered = (rendered.0.min(row_index), rendered.1.max(row_index));
+
+                    // Only displayed columns are materialized
+                    let fields = state.document().row_projected(row_index, &display_columns);
+                    // Row rules see every column, not just the displayed ones
+                    let row_color = (!state.row_colors.is_empty() && row_index >= state.first_data_row())
+                        .then(|| state.row_colors.color(&state.document().row(row_index)))
+                        .flatten()
+                        .map(|[r, g, b, a]| egui::Color32::from_rgba_unmultiplied(r, g, b, a));
+
+                    row.col(|ui| {
+                        if let Some(color) = row_color {
+                            ui.painter().rect_filled(ui.max_rect(), 0.0, color);
+                        }
+                        let bookmarked = state.bookmarks.contains(&row_index);
+                        let label = if bookmarked { format!("🔖{}", row_index) } else { row_index.to_string() };
+                        ui.label(egui::RichText::new(label).color(egui::Color32::from_gray(100)))
+                            .context_menu(|ui| {
+                                if ui.button(if bookmarked { "Remove Bookmark" } else { "Bookmark Row" }).clicked() {
+                                    state.dispatch(Action::ToggleBookmark(row_index));
+                                    ui.close();
+                                }
+                                let pinned = state.baseline_row == Some(row_index);
+                                if row_index >= state.first_data_row()
+                                    && ui.button(if pinned { "Unpin Baseline" } else { "📌 Pin as Baseline" })
+                                        .on_hover_text("Keep this row under the headers while scrolling, to compare against")
+                                        .clicked()
+                                {
+                                    state.dispatch(Action::PinBaseline((!pinned).then_some(row_index)));
+                                    ui.close();
+                                }
+                                if !state.document().in_memory()
+                                    && row_index >= state.first_data_row()
+                                    && ui.button("Mark as Footer from Here")
+                                        .on_hover_text("Set this row and every row below it aside, e.g. totals")
+                                        .clicked()
+                                {
+                                    let rows = state.file.loader.total_records() - row_index;
+                                    state.dispatch(Action::SetFooter(state.file.loader.footer_rows() + rows));
+                                    ui.close();
+                                }
+                            });
+                    });
+                    let display_index = row.index();
+                    let in_block = |position: usize| selection_block.as_ref()
+                        .is_some_and(|(rows, positions)| rows.contains(&display_index) && positions.contains(&position));
+                    for (position, (&col_index, field)) in display_columns.iter().zip(fields.iter()).enumerate() {
+                        let in_block = in_block(position);
+                        row.col(|ui| {
+                            let is_editing = state.editing_cell == Some((row_index, col_index));
+                            let is_selected = state.selected_cell == Some((row_index, col_index));
+
+                            if is_editing {
+                                // Enter commits like a spreadsheet; Alt+Enter adds a line break
+                                // Tab / Shift+Tab commit and move sideways; taken before the
+                                // text box would use them to move focus
+                                let tab = ui.input_mut(|i| {
+                                    if i.consume_key(egui::Modifiers::SHIFT, egui::Key::Tab) {
+                                        Some(-1)
+                                    } else if i.consume_key(egui::Modifiers::NONE, egui::Key::Tab) {
+                                        Some(1)
+                                    } else {
+                                        None
+                                    }
+                                });
+                                let lines = edit_lines(&state.input_buffer);
+                                // One id wherever it's drawn, so focus and the cursor
+                                // survive the edit growing past its row
+                                let editor = egui::TextEdit::multiline(&mut state.input_buffer)
+                                    .id(egui::Id::new("cell_edit"))
+                                    .desired_rows(lines)
+                                    .desired_width(f32::INFINITY)
+                                    .return_key(egui::KeyboardShortcut::new(egui::Modifiers::ALT, egui::Key::Enter));
+                                let response = if tall_edit {
+                                    // Drawn over the rows below, so every row keeps the same
+                                    // height and the table needn't measure them all
+                                    let cell = ui.available_rect_before_wrap();
+                                    ui.allocate_rect(cell, egui::Sense::hover());
+                                    egui::Area::new(egui::Id::new("tall_cell_edit"))
+                                        .fixed_pos(cell.min)
+                                        .order(egui::Order::Foreground)
+                                        .show(ui.ctx(), |ui| {
+                                            ui.set_width(cell.width());
+                                            ui.add(editor)
+                                        })
+                                        .inner
+                                } else {
+                                    ui.add(editor)
+                                };
+                                let enter = ui.input(|i| i.key_pressed(egui::Key::Enter) && !i.modifiers.alt);
+                                if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                                    state.editing_cell = None;
+                                } else if enter || tab.is_some() || response.lost_focus() {
+                                    let value = std::mem::take(&mut state.input_buffer);
+                                    if value != state.cell_value(row_index, col_index) {
+                                        state.dispatch(Action::EditCell { row: row_index, col: col_index, value });
+                                    }
+                                    state.editing_cell = None;
+                                    if let Some(delta) = tab {
+                                        state.selected_cell = Some((row_index, state.step_column(col_index, delta)));
+                                    } else if enter {
+                                        // Advance for the next entry, keeping it in view
+                                        let next = match settings.enter_direction {
+                                            EnterDirection::Down => ((row_index + 1).min(total_rows.saturating_sub(1)), col_index),
+                                            EnterDirection::Right => (row_index, state.step_column(col_index, 1)),
+                                            EnterDirection::Stay => (row_index, col_index),
+                                        };
+                                        state.selected_cell = Some(next);
+                                        state.scroll_to_row = Some(next.0);
+                                    }
+                                } else {
+                                    response.request_focus();
+                                }
+                            } else {
+                                 let text = field.clone();
+
+                                // Use placeholder for empty cells to make them clickable
+                                let formatted = state.column_formats.get(&col_index)
+                                    .filter(|_| row_index >= state.first_data_row())
+                                    .map(|format| format.apply(&text));
+                                let display_text = formatted.as_deref().unwrap_or(text.as_str());
+                                let display_text = if display_text.is_empty() { " " } else { display_text };
+                                // A megabyte-long field would cost a whole frame to lay out
+                                let truncated = truncate_display(display_text, settings.max_cell_bytes);
+                                let display_text = truncated.map_or(display_text, |(prefix, _)| prefix);
+
+                                // Fill entire available cell space for easy clicking
+                                let available = ui.available_size();
+                                let cell_size = egui::vec2(available.x.max(80.0), row_height - 2.0);
+                                let (rect, response) = ui.allocate_exact_size(cell_size, egui::Sense::click());
+
+                                if let Some(color) = row_color {
+                                    ui.painter().rect_filled(rect, 0.0, color);
+                                }
+                                // Color scale from the column's profiled min/max
+                                if let Some(Some((min, max))) = heat_ranges.get(col_index)
+                                    && let Ok(value) = text.trim().parse::<f64>()
+                                {
+                                    let t = if max > min { ((value - min) / (max - min)) as f32 } else { 0.5 };
+                                    ui.painter().rect_filled(rect, 0.0, heat_color(t.clamp(0.0, 1.0)));
+                                }
+
+                                // Repeated values in the column under duplicate analysis
+                                if let Some(ref report) = state.duplicates
+                                    && report.column_index == col_index
+                                    && report.is_duplicate(&text)
+                                {
+                                    ui.painter().rect_filled(rect, 0.0, egui::Color32::from_rgba_unmultiplied(255, 170, 60, 60));
+                                }
+
+                                if state.selected_column == Some(col_index) || in_block {
+                                    ui.painter().rect_filled(rect, 0.0, ui.visuals().selection.bg_fill.gamma_multiply(0.35));
+                                }
+
+                                // Hatch missing values so gaps in the data stand out
+                                let is_null = settings.show_null_markers && ColumnAnalyzer::is_null_like(&text);
+                                if is_null {
+                                    paint_null_marker(ui, rect, text.trim().is_empty());
+                                }
+
+                                // Draw text within the allocated area, cut off with "…" at the column edge
+                                let text_pos = rect.min + egui::vec2(settings.cell_padding, (rect.height() - settings.font_size) / 2.0);
+                                let mut job = egui::text::LayoutJob::single_section(
+                                    display_text.to_string(),
+                                    egui::TextFormat::simple(egui::FontId::proportional(settings.font_size), ui.visuals().text_color()),
+                                );
+                                job.wrap = egui::text::TextWrapping::truncate_at_width(rect.width() - 2.0 * settings.cell_padding);
+                                let galley = ui.painter().layout_job(job);
+                                ui.painter().galley(text_pos, galley, ui.visuals().text_color());
+                                // How far the value is from the pinned baseline's, in the cell's corner
+                                if let Some(Some(base)) = baseline_numbers.get(position)
+                                    && baseline.as_ref().is_some_and(|&(r, _)| r != row_index)
+                                    && row_index >= state.first_data_row()
+                                    && let Ok(value) = text.trim().parse::<f64>()
+                                {
+                                    let delta = value - base;
+                                    let color = if delta > 0.0 {
+                                        egui::Color32::from_rgb(100, 200, 100)
+                                    } else if delta < 0.0 {
+                                        egui::Color32::from_rgb(220, 80, 60)
+                                    } else {
+                                        ui.visuals().weak_text_color()
+                                    };
+                                    let galley = ui.painter().layout_no_wrap(format_delta(delta), egui::FontId::proportional(settings.font_size * 0.85), color);
+                                    let pos = egui::pos2(rect.right() - galley.size().x - settings.cell_padding, rect.center().y - galley.size().y / 2.0);
+                                    ui.painter().rect_filled(egui::Rect::from_min_size(pos, galley.size()).expand(2.0), 2.0, ui.visuals().extreme_bg_color);
+                                    ui.painter().galley(pos, galley, color);
+                                }
+                                let more_rect = truncated.map(|(_, hidden)| {
+                                    let galley = ui.painter().layout_no_wrap(
+                                        format!("…(+{}, click to view)", file_info::format_size(hidden as u64)),
+                                        egui::FontId::proportional(settings.font_size * 0.85),
+                                        ui.visuals().hyperlink_color,
+                                    );
+                                    let pos = egui::pos2(rect.right() - galley.size().x - settings.cell_padding, rect.center().y - galley.size().y / 2.0);
+                                    let more_rect = egui::Rect::from_min_size(pos, galley.size());
+                                    ui.painter().rect_filled(more_rect.expand(2.0), 2.0, ui.visuals().extreme_bg_color);
+                                    ui.painter().galley(pos, galley, ui.visuals().hyperlink_color);
+                                    more_rect
+                                });
+
+                                // Screen readers announce the position along with the value
+                                response.widget_info(|| {
+                                    let value = if text.trim().is_empty() { "empty".to_string() } else { text.clone() };
+                                    let label = format!("Row {}, {}: {}", row_index, state.column_header(col_index), value);
+                                    egui::WidgetInfo::selected(egui::WidgetType::SelectableLabel, true, is_selected, label)
+                                });
+
+                                // Full value and type details on hover
+                                let response = if text.is_empty() {
+                                    response
+                                } else {
+                                    let edited = state.document().is_edited(row_index, col_index);
+                                    response.on_hover_ui(|ui| cell_tooltip(ui, &text, edited, settings.max_cell_bytes))
+                                };
+
+                                // Right and bottom edges, centred in the gap to the next cell
+                                if settings.show_grid_lines {
+                                    let color = settings.grid_line_color
+                                        .map(|[r, g, b]| egui::Color32::from_rgb(r, g, b))
+                                        .unwrap_or(ui.visuals().widgets.noninteractive.bg_stroke.color);
+                                    let stroke = egui::Stroke::new(settings.grid_line_width, color);
+                                    let cell = ui.max_rect();
+                                    let gap = ui.spacing().item_spacing / 2.0;
+                                    let (right, bottom) = (cell.right() + gap.x, cell.bottom() + gap.y);
+                                    let painter = ui.painter().with_clip_rect(ui.clip_rect().expand2(gap));
+                                    painter.vline(right, (cell.top() - gap.y)..=bottom, stroke);
+                                    painter.hline((cell.left() - gap.x)..=right, bottom, stroke);
+                                }
+
+                                // Selection Highlight
+                                if is_selected {
+                                    ui.painter().rect_stroke(
+                                        response.rect,
+                                        0.0,
+                                        egui::Stroke::new(2.0, egui::Color32::from_rgb(100, 200, 255)),
+                                        egui::StrokeKind::Middle
+                                    );
+                                }
+
+                                if response.clicked()
+                                    && let Some(more_rect) = more_rect
+                                    && response.interact_pointer_pos().is_some_and(|pos| more_rect.contains(pos))
+                                {
+                                    state.dispatch(Action::InspectCell { row: row_index, col: col_index });
+                                }
+                                if response.clicked() {
+                                    // Shift+click spans a block from the cell selected before
+                                    match state.selected_cell {
+                                        Some(previous) if ui.input(|i| i.modifiers.shift) => {
+                                            state.selection_anchor.get_or_insert(previous);
+                                        }
+                                        _ => state.selection_anchor = None,
+                                    }
+                                    state.selected_cell = Some((row_index, col_index));
+                                    state.selected_column = None;
+
+                                    // The HUD picks this up from the profile cache
+                                    state.profile_column = Some(col_index);
+                                }
+
+                                if response.double_clicked() {
+                                    if settings.use_edit_modal {
+                                        // Load full content for modal
+                                        // We need to re-read essentially, or copy logic.
+                                        // Since we are inside the closure, we can't easily call the `cell_value` helper 
+                                        // if it borrows key parts. But we have `text` here!
+                                        state.edit_modal = Some(EditModal::new(row_index, col_index, text.clone()));
+                                    } else {
+                                        state.editing_cell = Some((row_index, col_index));
+                                        state.input_buffer = text.clone();
+                                    }
+                                }
+
+                                response.context_menu(|ui| cell_menu(ui, state, row_index, col_index, &text));
+                            }
+                        });
+                    }
+                };
+                body.rows(row_height, shown_rows, render_row);
+                if rendered.0 <= rendered.1 {
+                    state.visible_rows = rendered;
+                }
+            });
+
+        // Profile on-screen columns in the background for the type
+        // badges and sparklines, and so the HUD updates instantly
+        if settings.show_profile_hud || settings.show_type_badges || settings.show_sparklines {
+            for &col in &visible_columns {
+                state.request_profile(col);
+            }
+        }
+        state.apply_profile_widths(&visible_columns);
+        if state.profile_cache.poll() {
+            ctx.request_repaint();
+        }
+        if let Some(result) = state.sparkline_job.as_ref().and_then(|job| job.poll()) {
+            state.sparkline_job = None;
+            if let Ok(lines) = result {
+                state.sparklines.extend(lines);
+            }
+            ctx.request_repaint();
+        }
+        if settings.show_sparklines {
+            state.request_sparklines(&visible_columns);
+        }
+    });
+}
+
+/// A column header's right-click menu
+fn column_menu(ui: &mut egui::Ui, state: &mut EditorState, i: usize) {
+    let mut scaled = state.heatmap_columns.contains(&i);
+    if ui.checkbox(&mut scaled, "Color Scale").changed() {
+        state.dispatch(Action::ColorScale { col: i, on: scaled });
+        ui.close();
+    }
+    let sorting = state.sort_job.is_some();
+    let ellipsis = if state.document().in_memory() { "" } else { "..." };
+    for order in [SortOrder::Ascending, SortOrder::Descending] {
+        let label = match order {
+            SortOrder::Ascending => format!("Sort Ascending{}", ellipsis),
+            SortOrder::Descending => format!("Sort Descending{}", ellipsis),
+        };
+        if ui.add_enabled(!sorting, egui::Button::new(label)).clicked() {
+            state.dispatch(Action::Sort { col: i, order });
+            ui.close();
+        }
+    }
+    if ui.button("Find Duplicates").clicked() {
+        state.dispatch(Action::FindDuplicates(i));
+        ui.close();
+    }
+    if ui.button("Distinct Values...").clicked() {
+        state.dispatch(Action::Open(Dialog::Distinct(i)));
+        ui.close();
+    }
+    if ui.button("📊 Histogram").clicked() {
+        state.dispatch(Action::Histogram(i));
+        ui.close();
+    }
+    if ui.button("Normalize Values...").clicked() {
+        state.dispatch(Action::Open(Dialog::Normalize(i)));
+        ui.close();
+    }
+    if ui.button("Anonymize...").clicked() {
+        state.dispatch(Action::Open(Dialog::Anonymize(i)));
+        ui.close();
+    }
+    if ui.button("Reformat Dates...").clicked() {
+        state.dispatch(Action::Open(Dialog::DateFormat(i)));
+        ui.close();
+    }
+    if ui.button("Convert Units...").clicked() {
+        state.dispatch(Action::Open(Dialog::Units(i)));
+        ui.close();
+    }
+    if ui.add_enabled(state.document().in_memory(), egui::Button::new("Extract with Regex..."))
+        .on_disabled_hover_text("Inserting columns requires an editable grid (Data → Convert to Editable Grid)")
+        .clicked()
+    {
+        state.dispatch(Action::Open(Dialog::RegexExtract(i)));
+        ui.close();
+    }
+    if ui.add_enabled(state.document().in_memory(), egui::Button::new("Rolling Calculation..."))
+        .on_disabled_hover_text("Inserting columns requires an editable grid (Data → Convert to Editable Grid)")
+        .clicked()
+    {
+        state.dispatch(Action::Open(Dialog::Rolling(i)));
+        ui.close();
+    }
+    if ui.add_enabled(state.document().in_memory(), egui::Button::new("Difference / Ratio..."))
+        .on_disabled_hover_text("Inserting columns requires an editable grid (Data → Convert to Editable Grid)")
+        .on_hover_text("Compare with another numeric column; select a cell in both first to pick it")
+        .clicked()
+    {
+        state.dispatch(Action::Open(Dialog::Combine(i)));
+        ui.close();
+    }
+    if ui.button("Reset Widths").clicked() {
+        state.dispatch(Action::ResetWidths);
+        ui.close();
+    }
+    if ui.button("Number Format...").clicked() {
+        state.dispatch(Action::Open(Dialog::NumberFormat(i)));
+        ui.close();
+    }
+}
+
+/// A cell's right-click menu
+fn cell_menu(ui: &mut egui::Ui, state: &mut EditorState, row_index: usize, col_index: usize, text: &str) {
+     if ui.button("Edit Cell").clicked() {
+         // Always allow explicit edit via menu
+         state.dispatch(Action::StartEdit { row: row_index, col: col_index, text: text.to_string() });
+         ui.close();
+     }
+    if ui.button("Inspect Cell").clicked() {
+        state.dispatch(Action::InspectCell { row: row_index, col: col_index });
+        ui.close();
+    }
+    let history = state.cell_history(row_index, col_index);
+    if !history.is_empty() {
+        ui.menu_button(format!("History ({})", history.len() - 1), |ui| {
+            let last = history.len() - 1;
+            for (i, version) in history.iter().enumerate().rev() {
+                let when = version.time.as_deref()
+                    .and_then(|t| t.get(11..19))
+                    .unwrap_or("original");
+                let value = truncate_display(&version.value, 40).map_or(version.value.as_str(), |(s, _)| s);
+                ui.horizontal(|ui| {
+                    ui.label(egui::RichText::new(when).monospace().weak());
+                    if i == last {
+                        ui.label(egui::RichText::new(value).strong())
+                            .on_hover_text("Current value");
+                    } else if ui.button(value)
+                        .on_hover_text("Restore this version")
+                        .clicked()
+                    {
+                        state.dispatch(Action::EditCell { row: row_index, col: col_index, value: version.value.clone() });
+                        state.status_message = Some("Restored an earlier version of the cell".to_string());
+                        ui.close();
+                    }
+                });
+            }
+        });
+    }
+    ui.separator();
+    if ui.button("Copy Value").clicked() {
+        ui.ctx().copy_text(text.to_string());
+        ui.close();
+    }
+    ui.menu_button("Copy Row", |ui| {
+        let values = state.row_values(row_index);
+        if ui.button("As CSV").clicked() {
+            ui.ctx().copy_text(clipboard::to_csv_row(&values));
+            ui.close();
+        }
+        if ui.button("As TSV").clicked() {
+            ui.ctx().copy_text(clipboard::to_tsv(&[values]));
+            ui.close();
+        }
+        if ui.button("As JSON").clicked() {
+            let map = state.row_as_json(row_index);
+            ui.ctx().copy_text(serde_json::to_string_pretty(&map).unwrap_or_default());
+            ui.close();
+        }
+    });
+    if ui.button("Copy Column").clicked() {
+        let values = state.column_values(col_index, COPY_COLUMN_LIMIT);
+        if values.len() == COPY_COLUMN_LIMIT {
+            state.status_message = Some(format!("Copied the first {} values of the column", COPY_COLUMN_LIMIT));
+        }
+        ui.ctx().copy_text(values.join("\n"));
+        ui.close();
+    }
+    if ui.button("Copy Header").clicked() {
+        ui.ctx().copy_text(state.column_header(col_index));
+        ui.close();
+    }
+    if ui.button("Paste Special...").clicked() {
+        state.dispatch(Action::PasteSpecialAt { row: row_index, col: col_index });
+        ui.close();
+    }
+    ui.separator();
+    ui.add_enabled_ui(row_index >= state.first_data_row(), |ui| {
+        if ui.button("Filter Rows Equal to This Cell").clicked() {
+            state.dispatch(Action::Filter(FilterCondition { column: col_index, test: FilterTest::Equals(text.to_string()) }));
+            ui.close();
+        }
+        ui.menu_button("Filter Rows Containing", |ui| {
+            // Starts as the cell's text; trim it down to the part to look for
+            let draft = match state.filter_draft {
+                Some((cell, ref mut draft)) if cell == (row_index, col_index) => draft,
+                _ => &mut state.filter_draft.insert(((row_index, col_index), text.to_string())).1,
+            };
+            let response = ui.add(egui::TextEdit::singleline(draft).desired_width(180.0));
+            let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if (ui.add_enabled(!draft.is_empty(), egui::Button::new("Filter")).clicked() || submitted)
+                && !draft.is_empty()
+            {
+                let value = std::mem::take(draft);
+                state.filter_draft = None;
+                state.dispatch(Action::Filter(FilterCondition { column: col_index, test: FilterTest::Contains(value) }));
+                ui.close();
+            }
+        });
+        if ui.button("Exclude This Value").clicked() {
+            state.dispatch(Action::Filter(FilterCondition { column: col_index, test: FilterTest::NotEquals(text.to_string()) }));
+            ui.close();
+        }
+    });
+    ui.separator();
+    if ui.button("View Row as JSON").clicked() {
+        state.dispatch(Action::ViewJson { row: row_index, lines: false });
+        ui.close();
+    }
+    if ui.button("View Rows as JSON Lines").clicked() {
+        state.dispatch(Action::ViewJson { row: row_index, lines: true });
+        ui.close();
+    }
+}
+
+/// Raw lines with their numbers and byte offsets
+fn text_view(ui: &mut egui::Ui, ctx: &egui::Context, state: &mut EditorState, settings: &Settings, total_rows: usize, row_height: f32) {
+     // Byte offsets only exist for a file on disk
+     let on_disk = !state.document().in_memory() && state.file.loader.byte_len() > 0;
+     if on_disk {
+         ui.horizontal(|ui| {
+             ui.label("Go to byte offset:");
+             let response = ui.add(egui::TextEdit::singleline(&mut state.byte_offset_draft).hint_text("e.g. 1048576 or 0x100000").desired_width(160.0));
+             if ui.button("Go").clicked() || (response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter))) {
+                 state.dispatch(Action::GoToByteOffset);
+             }
+             ui.separator();
+             match state.selected_lines() {
+                 Some(lines) => {
+                     ui.label(format!("Lines {}–{}", lines.start(), lines.end()));
+                     if ui.button("📋 Copy")
+                         .on_hover_text("Copy the lines as text; bytes that aren't valid UTF-8 become �")
+                         .clicked()
+                         && let Some(bytes) = state.file.loader.get_record_range(*lines.start()..lines.end() + 1)
+                     {
+                         ctx.copy_text(String::from_utf8_lossy(&bytes).into_owned());
+                     }
+                     if ui.button("💾 Export Lines...").on_hover_text("Save the lines with their original bytes, unchanged").clicked() {
+                         state.dispatch(Action::ExportSelectedLines);
+                     }
+                     if ui.button("Hex").clicked() {
+                         state.dispatch(Action::HexDump(*lines.start()));
+                     }
+                     if ui.small_button("✖").clicked() {
+                         state.text_selection = None;
+                     }
+                 }
+                 None => {
+                     ui.label(egui::RichText::new("Click a line number to pick it, Shift+click to pick a range").weak());
+                 }
+             }
+         });
+     }
+     let mut scroll = egui::ScrollArea::vertical();
+     if let Some(line) = state.text_scroll_to.take() {
+         scroll = scroll.vertical_scroll_offset(line as f32 * (row_height + ui.spacing().item_spacing.y));
+     }
+     let usual_ending = if on_disk { state.dominant_line_ending() } else { file_info::LineEnding::None };
+     scroll.show_rows(ui, row_height, total_rows, |ui, row_range| {
+        let len = row_range.end - row_range.start;
+        let rows = state.file.reader.get_rows(row_range.start, len).unwrap_or_default();
 
-        match &mut self.state {
-            AppState::Welcome => {
-                egui::CentralPanel::default().show(ctx, |ui| {
-                    ui.vertical_centered(|ui| {
-                        ui.add_space(60.0);
-                        ui.heading(egui::RichText::new("CSVit").size(48.0).strong());
-                        ui.label(egui::RichText::new("High performance editor for large CSV files").size(16.0).color(egui::Color32::from_gray(150)));
-                        ui.add_space(30.0);
-                        
+        for (i, line) in rows.iter().enumerate() {
+            let idx = row_range.start + i;
+            ui.horizontal(|ui| {
+               let gutter = match state.file.loader.record_offset(idx).filter(|_| on_disk) {
+                   Some(offset) => format!("{: >6} {: >10} |", idx, offset),
+                   None => format!("{: >6} |", idx),
+               };
+               let picked = state.selected_lines().is_some_and(|lines| lines.contains(&idx))
+                   || state.hex_line.is_some_and(|(l, _)| l == idx);
+               let color = if picked { ui.visuals().selection.stroke.color } else { egui::Color32::from_gray(100) };
+               let gutter = ui.add(egui::Label::new(egui::RichText::new(gutter).color(color).monospace()).sense(egui::Sense::click()));
+               if on_disk && gutter.on_hover_text("Line number and starting byte offset; click to pick, Shift+click for a range").clicked() {
+                   state.text_selection = match state.text_selection {
+                       Some((anchor, _)) if ui.input(|i| i.modifiers.shift) => Some((anchor, idx)),
+                       _ => Some((idx, idx)),
+                   };
+               }
+               if on_disk {
+                   let warn = ui.visuals().warn_fg_color;
+                   let ending = state.file.loader.get_record_line(idx).map_or(file_info::LineEnding::None, |bytes| file_info::LineEnding::of_line(&bytes));
+                   if ending != file_info::LineEnding::None
+                       && ending != usual_ending
+                       && ui.add(egui::Button::new(egui::RichText::new(ending.short_name()).small().color(warn)).small())
+                           .on_hover_text(format!("Ends with {}, unlike most lines ({}); click for File Info", ending.name(), usual_ending.name()))
+                           .clicked()
+                   {
+                       state.dispatch(Action::FileInfo);
+                   }
+                   if line.contains('\u{FFFD}')
+                       && ui.add(egui::Button::new(egui::RichText::new("�").small().color(warn)).small())
+                           .on_hover_text("Holds bytes that aren't valid UTF-8, shown as �; click for the Problems panel")
+                           .clicked()
+                   {
+                       state.show_encoding_problems();
+                   }
+               }
+               match truncate_display(line.trim_end(), settings.max_cell_bytes) {
+                   Some((prefix, hidden)) => {
+                       ui.monospace(prefix);
+                       ui.label(egui::RichText::new(format!("…(+{})", file_info::format_size(hidden as u64))).weak());
+                   }
+                   None => {
+                       ui.monospace(line.trim_end());
+                   }
+               }
+            });
+        }
+    });
+}
+
+fn graph_view(ctx: &egui::Context, state: &mut EditorState) {
+    egui::CentralPanel::default().show(ctx, |ui| {
+        ui.horizontal(|ui| {
+           ui.selectable_value(&mut state.graph_histogram, false, "📉 Line");
+           ui.selectable_value(&mut state.graph_histogram, true, "📊 Histogram");
+           ui.separator();
+           if !state.graph_histogram {
+               ui.label("X Axis:");
+               egui::ComboBox::from_id_salt("x_axis")
+                   .selected_text(format!("Col {}", state.graph_x_col))
+                   .show_ui(ui, |ui| {
+                       for i in 0..state.num_columns {
+                           ui.selectable_value(&mut state.graph_x_col, i, format!("Col {}", i));
+                       }
+                   });
+           }
+
+           ui.label(if state.graph_histogram { "Column:" } else { "Y Axis:" });
+            egui::ComboBox::from_id_salt("y_axis")
+               .selected_text(format!("Col {}", state.graph_y_col))
+               .show_ui(ui, |ui| {
+                   for i in 0..state.num_columns {
+                       ui.selectable_value(&mut state.graph_y_col, i, format!("Col {}", i));
+                   }
+               });
+
+           if ui.button("Regenerate Graph").clicked() {
+               state.graph_source = None;
+           }
+           if ui.button("💾 Export Data...")
+               .on_hover_text("Save the series as drawn, after filtering, resampling or binning, as CSV or JSON")
+               .clicked()
+           {
+               state.dispatch(Action::ExportPlottedData);
+           }
+           if state.graph_histogram {
+               ui.separator();
+               ui.label("Bins:");
+               ui.add(egui::DragValue::new(&mut state.graph_bins).range(1..=200));
+           } else {
+               if state.graph_x_time {
+                   ui.separator();
+                   ui.label("Resample:");
+                   let mut changed = false;
+                   egui::ComboBox::from_id_salt("graph_resample")
+                       .selected_text(state.graph_resample.map_or("Off", |b| b.name()))
+                       .show_ui(ui, |ui| {
+                           changed |= ui.selectable_value(&mut state.graph_resample, None, "Off").changed();
+                           for bucket in TimeBucket::ALL {
+                               changed |= ui.selectable_value(&mut state.graph_resample, Some(bucket), bucket.name()).changed();
+                           }
+                       });
+                   if state.graph_resample.is_some() {
+                       egui::ComboBox::from_id_salt("graph_resample_aggregate")
+                           .selected_text(state.graph_resample_aggregate.name())
+                           .show_ui(ui, |ui| {
+                               for aggregate in [Aggregate::Mean, Aggregate::Sum, Aggregate::Count] {
+                                   changed |= ui.selectable_value(&mut state.graph_resample_aggregate, aggregate, aggregate.name()).changed();
+                               }
+                           });
+                   }
+                   if changed {
+                       state.graph_brush = false;
+                       state.graph_brushed.clear();
+                   }
+               }
+               let resampled = state.graph_x_time && state.graph_resample.is_some();
+               ui.separator();
+               ui.toggle_value(&mut state.graph_stats, "📈 Statistics")
+                   .on_hover_text("Mark the Y column's mean, median, standard deviation, min and max");
+               ui.add_enabled_ui(!resampled, |ui| {
+                   ui.toggle_value(&mut state.graph_brush, "⬚ Brush")
+                       .on_hover_text("Drag a box around points to pick their rows")
+                       .on_disabled_hover_text("Brushing picks rows, so it needs resampling off");
+               });
+               if !state.graph_brushed.is_empty() {
+                   ui.label(format!("{} point(s) brushed", state.graph_brushed.len()));
+                   if ui.button("Filter Table to These Rows").clicked() {
+                       state.dispatch(Action::FilterToBrushed);
+                   }
+                   if ui.small_button("Clear").clicked() {
+                       state.graph_brushed.clear();
+                   }
+               }
+           }
+           if !state.row_filter.is_empty() {
+               ui.label(egui::RichText::new(format!("Plotting filtered rows ({} points)", if state.graph_histogram { state.graph_value_count } else { state.graph_data.len() })).weak());
+           }
+        });
+        // Follows the grid or the file with its pending edits, and
+        // re-reads whenever a plotted column is edited
+        state.refresh_graph();
+        if let Some(result) = state.graph_job.as_ref().and_then(|(_, job)| job.poll()) {
+            let (source, _) = state.graph_job.take().expect("just polled");
+            match result {
+                Ok(Some(read)) => state.finish_graph_read(source, read),
+                Ok(None) => {}
+                Err(e) => {
+                    // Not read again until something changes
+                    state.graph_source = Some(source);
+                    state.status_message = Some(e);
+                }
+            }
+        }
+
+        if state.graph_histogram {
+            let bars: Vec<egui_plot::Bar> = state.graph_bars.counts.iter()
+                .enumerate()
+                .map(|(i, &count)| egui_plot::Bar::new(state.graph_bars.center(i), count as f64).width(state.graph_bars.width))
+                .collect();
+            egui_plot::Plot::new("csv_histogram")
+                .y_axis_label("Rows")
+                .show(ui, |plot_ui| {
+                    plot_ui.bar_chart(egui_plot::BarChart::new(state.column_header(state.graph_y_col), bars));
+                });
+        } else {
+            if state.graph_stats {
+                state.request_profile(state.graph_y_col);
+                if state.profile_cache.poll() {
+                    ctx.request_repaint();
+                }
+            }
+            let stats = state.graph_stats
+                .then(|| state.profile_cache.get(state.graph_y_col, state.column_generation(state.graph_y_col)).cloned())
+                .flatten();
+            let mut plot = egui_plot::Plot::new("csv_plot")
+               .allow_drag(!state.graph_brush)
+               .allow_boxed_zoom(!state.graph_brush);
+            if stats.is_some() {
+                // Clicking an entry hides or shows it
+                plot = plot.legend(egui_plot::Legend::default());
+            }
+            if state.graph_x_time {
+                plot = plot.x_axis_formatter(|mark, range| {
+                    let format = if range.end() - range.start() > 3.0 * 86_400.0 { "%Y-%m-%d" } else { "%Y-%m-%d %H:%M" };
+                    chrono::DateTime::from_timestamp(mark.value as i64, 0)
+                        .map(|t| t.format(format).to_string())
+                        .unwrap_or_default()
+                });
+            }
+            plot.show(ui, |plot_ui| {
+                   plot_ui.line(egui_plot::Line::new("Data", egui_plot::PlotPoints::new(state.graph_plot.clone())));
+                   plot_ui.points(egui_plot::Points::new("Data Points", egui_plot::PlotPoints::new(state.graph_plot.clone())).radius(3.0));
+                   if let Some(ref profile) = stats {
+                       plot_stats(plot_ui, profile, &state.graph_plot);
+                   }
+                   if !state.graph_brushed.is_empty() {
+                       let brushed: Vec<[f64; 2]> = state.graph_brushed.iter().map(|&i| state.graph_data[i]).collect();
+                       plot_ui.points(egui_plot::Points::new("Brushed", egui_plot::PlotPoints::new(brushed))
+                           .radius(5.0)
+                           .color(egui::Color32::from_rgb(255, 140, 0)));
+                   }
+                   if !state.graph_brush {
+                       return;
+                   }
+                   let response = plot_ui.response();
+                   let (started, dragged, stopped) = (
+                       response.drag_started_by(egui::PointerButton::Primary),
+                       response.dragged_by(egui::PointerButton::Primary),
+                       response.drag_stopped_by(egui::PointerButton::Primary),
+                   );
+                   if let Some(pointer) = plot_ui.pointer_coordinate() {
+                       let at = [pointer.x, pointer.y];
+                       if started {
+                           state.graph_brush_box = Some((at, at));
+                       } else if dragged && let Some((_, ref mut corner)) = state.graph_brush_box {
+                           *corner = at;
+                       }
+                   }
+                   let Some((a, b)) = state.graph_brush_box else {
+                       return;
+                   };
+                   let outline = vec![[a[0], a[1]], [b[0], a[1]], [b[0], b[1]], [a[0], b[1]]];
+                   plot_ui.polygon(egui_plot::Polygon::new("Brush", egui_plot::PlotPoints::new(outline)));
+                   if stopped {
+                       let (x0, x1) = (a[0].min(b[0]), a[0].max(b[0]));
+                       let (y0, y1) = (a[1].min(b[1]), a[1].max(b[1]));
+                       state.graph_brushed = state.graph_data.iter()
+                           .enumerate()
+                           .filter(|(_, [x, y])| (x0..=x1).contains(x) && (y0..=y1).contains(y))
+                           .map(|(i, _)| i)
+                           .collect();
+                       state.graph_brush_box = None;
+                   }
+               });
+        }
+    });
+}
+
+/// The full-size cell editor, when Settings asks for it instead of in-place editing
+fn edit_modal_window(ctx: &egui::Context, state: &mut EditorState) {
+    if let Some(mut modal) = state.edit_modal.take() {
+        let mut open = true;
+        let mut save = false;
+        let mut cancel = false;
+        let problem = state.validate_cell_value(modal.col, &modal.text);
+        let blocked = problem.as_ref().is_some_and(|(_, blocking)| *blocking);
+        egui::Window::new(format!("Edit {} · Row {}", state.column_header(modal.col), modal.row))
+            .id(egui::Id::new("edit_modal"))
+            .open(&mut open)
+            .default_size(egui::vec2(420.0, 320.0))
+            .show(ctx, |ui| {
+                if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
+                    cancel = true;
+                }
+                // Taken before the text box sees it, so it doesn't insert a newline
+                if ui.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::Enter)) && !blocked {
+                    save = true;
+                }
+                ui.add(egui::TextEdit::multiline(&mut modal.text).desired_width(f32::INFINITY).desired_rows(8));
+
+                if modal.text != modal.original {
+                    ui.separator();
+                    let (before, after) = diff_layouts(&modal.original, &modal.text, ui);
+                    egui::Grid::new("edit_modal_diff").num_columns(2).show(ui, |ui| {
+                        ui.label(egui::RichText::new("Before").weak());
+                        ui.label(before);
+                        ui.end_row();
+                        ui.label(egui::RichText::new("After").weak());
+                        ui.label(after);
+                        ui.end_row();
+                    });
+                }
+                if let Some((ref message, blocking)) = problem {
+                    let color = if blocking { egui::Color32::from_rgb(220, 80, 60) } else { egui::Color32::from_rgb(230, 160, 60) };
+                    ui.colored_label(color, format!("⚠ {}", message));
+                }
+
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(!blocked, egui::Button::new("Save")).on_hover_text("Ctrl+Enter").clicked() {
+                        save = true;
+                    }
+                    if blocked && ui.button("Save Anyway").clicked() {
+                        save = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancel = true;
+                    }
+                    if ui.button("Beautify JSON").clicked()
+                        && let Ok(value) = serde_json::from_str::<serde_json::Value>(&modal.text)
+                        && let Ok(pretty) = serde_json::to_string_pretty(&value)
+                    {
+                        modal.text = pretty;
+                    }
+                });
+            });
+
+        if save {
+            if modal.text != modal.original {
+                state.dispatch(Action::EditCell { row: modal.row, col: modal.col, value: modal.text });
+            }
+        } else if open && !cancel {
+            state.edit_modal = Some(modal);
+        }
+    }
+
+}
+
+/// Column tools and the reports they produce
+fn tool_windows(ctx: &egui::Context, state: &mut EditorState, settings: &Settings, style: &egui::Style) {
+    if state.show_normalize {
+        let busy = state.normalize_job.is_some() || state.rewrite_job.is_some();
+        match state.normalize_window.show(ctx, &mut state.show_normalize, busy) {
+            Some(NormalizeAction::Recluster(max_distance)) => {
+                state.cluster_column(state.normalize_window.column(), max_distance);
+            }
+            Some(NormalizeAction::Apply(mapping)) => {
+                state.apply_normalization(state.normalize_window.column(), mapping);
+            }
+            None => {}
+        }
+    }
+    if state.show_cell_inspector
+        && let Some((row, col)) = state.cell_inspector.show(ctx, &mut state.show_cell_inspector)
+    {
+        let value = state.cell_value(row, col);
+        if settings.use_edit_modal {
+            state.edit_modal = Some(EditModal::new(row, col, value));
+        } else {
+            state.selected_cell = Some((row, col));
+            state.editing_cell = Some((row, col));
+            state.input_buffer = value;
+            state.scroll_to_row = Some(row);
+        }
+        state.show_cell_inspector = false;
+    }
+    if state.show_file_info {
+        let hashing = state.checksum_job.is_some();
+        if let Some(path) = state.file_info_window.show(ctx, &mut state.show_file_info, hashing) {
+            state.checksum_job = Some(Job::spawn("Computing SHA-256", move |progress| file_info::sha256_file(&path, progress)));
+        }
+    }
+    if state.show_anonymize
+        && let Some((col, transform)) = state.anonymize_window.show(ctx, &mut state.show_anonymize)
+    {
+        state.anonymize_column(col, transform);
+    }
+    if state.show_date_format
+        && let Some((col, reformat)) = state.date_format_window.show(ctx, &mut state.show_date_format)
+    {
+        state.reformat_dates(col, reformat);
+    }
+    if state.show_units
+        && let Some((col, transform)) = state.unit_window.show(ctx, &mut state.show_units)
+    {
+        state.convert_units(col, transform);
+    }
+    if state.show_geo
+        && let Some(request) = state.geo_window.show(ctx, &mut state.show_geo, state.geo_job.is_some())
+    {
+        match request {
+            GeoRequest::Check(lat, lon) => state.check_coordinates(lat, lon),
+            GeoRequest::Jump(row) => {
+                let (lat, _) = state.geo_window.columns();
+                state.selected_cell = Some((row, lat));
+                state.scroll_to_row = Some(row);
+            }
+        }
+    }
+    if state.show_rolling
+        && let Some(request) = state.rolling_window.show(ctx, &mut state.show_rolling)
+    {
+        state.add_rolling_column(request);
+    }
+    if state.show_combine
+        && let Some(request) = state.combine_window.show(ctx, &mut state.show_combine)
+    {
+        state.add_combined_column(request);
+    }
+    if state.show_regex_extract
+        && let Some((col, regex)) = state.regex_window.show(ctx, &mut state.show_regex_extract)
+    {
+        state.extract_with_regex(col, &regex);
+    }
+    // Over-budget operations wait here for the user to choose
+    if let Some((action, ref warning)) = state.memory_prompt {
+        let mut choice = None;
+        let alternative = match action {
+            MemoryHeavy::ConvertToGrid => "Keep Streaming View",
+            MemoryHeavy::ProfileAllRows(_) => "Keep Sampled Profile",
+        };
+        egui::Window::new("Large Operation")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+            .show(ctx, |ui| {
+                ui.label(warning.message());
+                ui.label(egui::RichText::new(match action {
+                    MemoryHeavy::ConvertToGrid => "The streaming view reads rows from disk as they're shown, and cell edits still work there.",
+                    MemoryHeavy::ProfileAllRows(_) => "The sampled profile reads only the first rows of the column.",
+                }).weak());
+                ui.add_space(6.0);
+                ui.horizontal(|ui| {
+                    if ui.button(alternative).clicked() {
+                        choice = Some(false);
+                    }
+                    if ui.button("Continue Anyway").clicked() {
+                        choice = Some(true);
+                    }
+                });
+            });
+        if let Some(proceed) = choice {
+            state.memory_prompt = None;
+            if proceed {
+                state.run_memory_heavy(action, settings.memory_budget_mb, true);
+            }
+        }
+    }
+    if let Some(report) = state.duplicates.take() {
+        let mut open = true;
+        let mut blank = false;
+        egui::Window::new(format!("Duplicates in {}", state.column_header(report.column_index)))
+            .open(&mut open)
+            .resizable(true)
+            .default_width(280.0)
+            .show(ctx, |ui| {
+                if report.values.is_empty() {
+                    ui.label("Every value in this column is unique.");
+                    return;
+                }
+                ui.label(format!(
+                    "{} value(s) repeated, {} extra occurrence(s)",
+                    report.values.len(),
+                    report.repeat_rows.len()
+                ));
+                ui.separator();
+                egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    egui::Grid::new("duplicate_values").striped(true).show(ui, |ui| {
+                        for (value, count) in report.values.iter().take(500) {
+                            ui.label(value);
+                            ui.label(format!("×{}", count));
+                            ui.end_row();
+                        }
+                    });
+                });
+                ui.separator();
+                if ui.button("Keep First / Blank Others").clicked() {
+                    blank = true;
+                }
+            });
+        if blank {
+            state.blank_duplicate_repeats(&report);
+        } else if open {
+            state.duplicates = Some(report);
+        }
+    }
+    // Hex dump of a line from the text view
+    if let Some((line, offset)) = state.hex_line {
+        if state.hex_dump.as_ref().is_none_or(|dump| (dump.line, dump.offset) != (line, offset)) {
+            state.hex_dump = Some(hex_dump(&state.file.loader, line, offset));
+        }
+        let mut open = true;
+        if let Some(ref dump) = state.hex_dump {
+            egui::Window::new(format!("Bytes of Line {}", line))
+                .id(egui::Id::new("hex_dump"))
+                .open(&mut open)
+                .resizable(true)
+                .default_size([620.0, 300.0])
+                .show(ctx, |ui| {
+                    ui.label(format!("Bytes {}–{} ({})", dump.start, dump.start + dump.len as u64, file_info::format_size(dump.len as u64)));
+                    if let (Some(offset), Some((into, byte))) = (offset, dump.byte) {
+                        ui.label(format!("Byte {} is 0x{:02x}, {} byte(s) into the line", offset, byte, into));
+                    }
+                    ui.separator();
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        ui.monospace(&dump.text);
+                    });
+                    if dump.shown < dump.len {
+                        ui.label(egui::RichText::new(format!("Showing {} of the line", file_info::format_size(dump.shown as u64))).weak());
+                    }
+                });
+        }
+        if !open {
+            state.hex_line = None;
+            state.hex_dump = None;
+        }
+    }
+    if state.show_scatter
+        && let Some(columns) = state.scatter_window.show(ctx, &mut state.show_scatter, state.scatter_job.is_some())
+    {
+        state.sample_scatter_matrix(columns);
+    }
+    let unsaved = state.has_unsaved_edits();
+    if state.show_group_by
+        && let Some(request) = state.group_by_window.show(ctx, &mut state.show_group_by, state.group_by_job.is_some(), unsaved)
+    {
+        match request {
+            GroupByRequest::Run(spec) => state.group_by(spec),
+            GroupByRequest::Export => state.save_group_summary(false),
+            GroupByRequest::Open => state.save_group_summary(true),
+        }
+    }
+    if state.show_distinct
+        && let Some(request) = state.distinct_window.show(ctx, &mut state.show_distinct)
+    {
+        match request {
+            DistinctRequest::Filter(column, values) => {
+                state.dispatch(Action::Filter(FilterCondition { column, test: FilterTest::OneOf(values) }));
+                state.view_mode = ViewMode::Table;
+            }
+            DistinctRequest::Export => state.export_distinct(),
+        }
+    }
+    if !state.show_distinct
+        && let Some(job) = state.distinct_job.take()
+    {
+        job.progress().cancel();
+    }
+    if let Some(report) = state.invalid_values.take() {
+        let mut open = true;
+        let mut jump = None;
+        egui::Window::new(format!("Invalid {} in {}", report.semantic_type.name(), state.column_header(report.column_index)))
+            .open(&mut open)
+            .resizable(true)
+            .default_width(320.0)
+            .show(ctx, |ui| {
+                if report.count == 0 {
+                    ui.label(format!("Every non-empty value is a valid {}.", report.semantic_type.name()));
+                    return;
+                }
+                ui.label(format!("{} invalid value(s)", report.count));
+                ui.separator();
+                egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    egui::Grid::new("invalid_values").striped(true).show(ui, |ui| {
+                        for (row, value) in &report.values {
+                            if ui.link(format!("Row {}", row)).clicked() {
+                                jump = Some(*row);
+                            }
+                            ui.label(value);
+                            ui.end_row();
+                        }
+                    });
+                    if report.count > report.values.len() {
+                        ui.label(egui::RichText::new(format!("… and {} more", report.count - report.values.len())).weak());
+                    }
+                });
+            });
+        if let Some(row) = jump {
+            state.selected_cell = Some((row, report.column_index));
+            state.scroll_to_row = Some(row);
+        }
+        if open {
+            state.invalid_values = Some(report);
+        }
+    }
+    // Row JSON / JSON Lines view
+    if let Some(mut view) = state.json_view.take() {
+        let mut open = true;
+        let mut regenerate = false;
+        egui::Window::new("Row JSON")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.style_mut().text_styles = style.text_styles.clone();
+                ui.horizontal(|ui| {
+                    regenerate |= ui.radio_value(&mut view.lines, false, "Object").changed();
+                    regenerate |= ui.radio_value(&mut view.lines, true, "JSON Lines").changed();
+                    ui.separator();
+                    ui.label(if view.lines { "From row" } else { "Row" });
+                    regenerate |= ui.add(egui::DragValue::new(&mut view.first)).changed();
+                    if view.lines {
+                        ui.label("rows:");
+                        regenerate |= ui.add(egui::DragValue::new(&mut view.count).range(1..=JSON_LINES_LIMIT)).changed();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("📋 Copy").clicked() {
+                        ui.ctx().copy_text(view.text.clone());
+                    }
+                    let (filter, ext) = if view.lines { ("JSON Lines", "jsonl") } else { ("JSON", "json") };
+                    if ui.button("💾 Save...").clicked()
+                        && let Some(path) = rfd::FileDialog::new().add_filter(filter, &[ext]).save_file()
+                    {
+                        state.status_message = Some(match std::fs::write(&path, &view.text) {
+                            Ok(()) => format!("Saved {}", path.display()),
+                            Err(e) => format!("Failed to save {}: {}", path.display(), e),
+                        });
+                    }
+                });
+                ui.separator();
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    ui.add(egui::TextEdit::multiline(&mut view.text.as_str()).code_editor());
+                });
+            });
+        if regenerate {
+            view.text = state.json_view_text(view.first, view.count, view.lines);
+        }
+        if open {
+            state.json_view = Some(view);
+        }
+    }
+}
+
+/// Lines set aside above the header, folded into a banner over the table
+fn preamble_banner(ui: &mut egui::Ui, state: &mut EditorState) {
+    let lines = state.file.loader.preamble_lines();
+    // Read a byte past what's shown, so a cut shows as one
+    let text = state.file.loader.preamble_head(PREAMBLE_DISPLAY_BYTES + 1)
+        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+        .unwrap_or_default();
+    egui::CollapsingHeader::new(egui::RichText::new(format!("📝 {} line(s) above the header, kept when saving", lines)).weak())
+        .id_salt("preamble_banner")
+        .show(ui, |ui| {
+            egui::ScrollArea::vertical().max_height(120.0).show(ui, |ui| {
+                ui.monospace(truncate_display(text.trim_end(), PREAMBLE_DISPLAY_BYTES).map_or(text.trim_end(), |(prefix, _)| prefix));
+            });
+            if !state.document().in_memory()
+                && ui.small_button("Show as Rows").on_hover_text("Put these lines back into the table").clicked()
+            {
+                state.dispatch(Action::SetPreamble(0));
+            }
+        });
+}
+
+/// Summary rows set aside at the end of the file, folded like the preamble
+fn footer_banner(ui: &mut egui::Ui, state: &mut EditorState) {
+    let rows = state.file.loader.footer_rows();
+    let text = state.file.loader.footer_head(PREAMBLE_DISPLAY_BYTES + 1)
+        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+        .unwrap_or_default();
+    egui::CollapsingHeader::new(egui::RichText::new(format!("Σ {} footer row(s) below the table, kept when saving", rows)).weak())
+        .id_salt("footer_banner")
+        .show(ui, |ui| {
+            egui::ScrollArea::vertical().id_salt("footer_text").max_height(120.0).show(ui, |ui| {
+                ui.monospace(truncate_display(text.trim_end(), PREAMBLE_DISPLAY_BYTES).map_or(text.trim_end(), |(prefix, _)| prefix));
+            });
+            if !state.document().in_memory()
+                && ui.small_button("Show as Rows").on_hover_text("Put these rows back into the table").clicked()
+            {
+                state.dispatch(Action::SetFooter(0));
+            }
+        });
+}
+
+/// The selection's figures in a corner of the window, until Esc or ✖
+/// Figures for the cells of `rows` in `cols` columns, fed to the counter as
+/// they're read; None if cancelled
+fn count_selection(
+    rows: impl Iterator<Item = usize>,
+    cols: usize,
+    row_at: impl Fn(usize) -> Vec<String>,
+    progress: &Progress,
+) -> Option<QuickCalc> {
+    let mut counter = SelectionCounter::default();
+    let mut shown = 0;
+    let mut truncated = false;
+    for row in rows {
+        if shown % 10_000 == 0 {
+            if progress.is_cancelled() {
+                return None;
+            }
+            progress.set_done(shown * cols);
+        }
+        if (shown + 1) * cols > QUICK_CALC_MAX_CELLS {
+            truncated = true;
+            break;
+        }
+        for value in row_at(row) {
+            counter.add(&value);
+        }
+        shown += 1;
+    }
+    Some(QuickCalc { rows: shown, cols, stats: counter.finish(), truncated })
+}
+
+fn quick_calc_overlay(ctx: &egui::Context, state: &mut EditorState) {
+    let Some(ref calc) = state.quick_calc else {
+        return;
+    };
+    let mut close = ctx.input(|i| i.key_pressed(egui::Key::Escape));
+    egui::Area::new(egui::Id::new("quick_calc"))
+        .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-30.0, -36.0))
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.strong(format!("{} × {} cells", calc.rows, calc.cols));
+                    if ui.small_button("✖").clicked() {
+                        close = true;
+                    }
+                });
+                if calc.truncated {
+                    ui.label(egui::RichText::new(format!("First {} cells only", QUICK_CALC_MAX_CELLS)).weak().small());
+                }
+                let totals = &calc.stats.totals;
+                let figure = |aggregate| totals.value(aggregate).map(aggregate::format_number).unwrap_or_else(|| "—".to_string());
+                egui::Grid::new("quick_calc_figures").num_columns(2).show(ui, |ui| {
+                    for (name, value) in [
+                        ("Sum", figure(Aggregate::Sum)),
+                        ("Mean", figure(Aggregate::Mean)),
+                        ("Count", figure(Aggregate::CountNonEmpty)),
+                        ("Min", figure(Aggregate::Min)),
+                        ("Max", figure(Aggregate::Max)),
+                        ("Distinct", calc.stats.distinct.to_string()),
+                    ] {
+                        ui.label(name);
+                        ui.monospace(value);
+                        ui.end_row();
+                    }
+                });
+            });
+        });
+    if close {
+        state.quick_calc = None;
+    }
+}
+
+/// Edits a reload couldn't carry over, each kept or dropped by hand
+fn reload_conflicts_window(ctx: &egui::Context, state: &mut EditorState) {
+    let headers = state.source_headers();
+    let mut resolved = None;
+    let mut apply = None;
+    let mut open = state.show_reload_conflicts;
+    egui::Window::new("Reload Conflicts")
+        .open(&mut open)
+        .resizable(true)
+        .default_width(620.0)
+        .show(ctx, |ui| {
+            ui.label(format!("{} edit(s) couldn't be re-applied after the file changed on disk", state.reload_conflicts.len()));
+            ui.separator();
+            egui::ScrollArea::vertical().max_height(360.0).show(ui, |ui| {
+                egui::Grid::new("reload_conflicts").num_columns(7).striped(true).show(ui, |ui| {
+                    for title in ["Row", "Column", "Original", "On disk", "Mine", "", ""] {
+                        ui.strong(title);
+                    }
+                    ui.end_row();
+                    let shown = |value: &Option<String>| value.clone().unwrap_or_else(|| "—".to_string());
+                    for (i, conflict) in state.reload_conflicts.iter().enumerate() {
+                        ui.label(match conflict.new_row {
+                            Some(r) if r != conflict.row => format!("{} → {}", conflict.row, r),
+                            _ => conflict.row.to_string(),
+                        });
+                        ui.label(headers.get(conflict.col).cloned().unwrap_or_else(|| format!("Column {}", conflict.col + 1)));
+                        ui.label(egui::RichText::new(shown(&conflict.original)).weak());
+                        ui.label(shown(&conflict.disk));
+                        ui.label(egui::RichText::new(&conflict.mine).strong());
+                        ui.label(egui::RichText::new(conflict.reason).weak().small());
                         ui.horizontal(|ui| {
-                            ui.add_space(ui.available_width() / 2.0 - 220.0);
-                            if ui.add(egui::Button::new(egui::RichText::new("📄 New CSV").size(16.0))
-                                .min_size(egui::vec2(140.0, 45.0))
-                                .corner_radius(6.0)
-                            ).clicked() {
-                                self.show_new_csv_dialog = true;
+                            if let Some(r) = conflict.new_row
+                                && ui.small_button("Use Mine").clicked()
+                            {
+                                apply = Some((r, conflict.col, conflict.mine.clone()));
+                                resolved = Some(i);
                             }
-                            ui.add_space(20.0);
-                            if ui.add(egui::Button::new(egui::RichText::new("📂 Open File").size(16.0))
-                                .min_size(egui::vec2(140.0, 45.0))
-                                .corner_radius(6.0)
-                            ).clicked() {
-                                self.open_file_dialog();
+                            if ui.small_button("Keep Disk").clicked() {
+                                resolved = Some(i);
                             }
                         });
-                        
-                        // Recent Files Section
-                        if !self.settings.recent_files.is_empty() {
-                            ui.add_space(40.0);
-                            ui.heading(egui::RichText::new("Recent Files").size(18.0));
-                            ui.add_space(10.0);
-                            
-                            egui::Frame::default()
-                                .inner_margin(12.0)
-                                .corner_radius(8.0)
-                                .fill(ui.visuals().extreme_bg_color)
-                                .show(ui, |ui| {
-                                    for path in self.settings.recent_files.clone().iter().take(5) {
-                                        let display_name = std::path::Path::new(path)
-                                            .file_name()
-                                            .map(|n| n.to_string_lossy().to_string())
-                                            .unwrap_or_else(|| path.clone());
-                                        if ui.add(egui::Button::new(&display_name)
-                                            .min_size(egui::vec2(300.0, 30.0))
-                                        ).on_hover_text(path).clicked() {
-                                            self.load_file(path);
-                                        }
-                                    }
-                                });
-                        }
-                    });
+                        ui.end_row();
+                    }
                 });
+            });
+            ui.separator();
+            if ui.button("Keep Disk for All").clicked() {
+                state.reload_conflicts.clear();
             }
-            AppState::Error(msg) => {
-                let mut back_clicked = false;
-                egui::CentralPanel::default().show(ctx, |ui| {
-                    ui.vertical_centered(|ui| {
-                        ui.heading("Error");
-                        ui.label(msg.as_str());
-                        if ui.button("Back").clicked() {
-                            back_clicked = true;
+        });
+    if let Some((r, c, value)) = apply {
+        state.set_cell_value(r, c, value);
+    }
+    if let Some(i) = resolved {
+        state.reload_conflicts.remove(i);
+    }
+    state.show_reload_conflicts = open && !state.reload_conflicts.is_empty();
+}
+
+/// Bottom panel listing the audit log, newest last
+fn audit_log_panel(ctx: &egui::Context, state: &mut EditorState) {
+    let mut export = false;
+    let mut clear = false;
+    let mut close = false;
+    egui::TopBottomPanel::bottom("audit_log")
+        .resizable(true)
+        .default_height(180.0)
+        .show(ctx, |ui| {
+            let entries = state.audit_log().entries();
+            let dropped = state.audit_log().dropped();
+            ui.horizontal(|ui| {
+                ui.strong("📜 Audit Log");
+                ui.label(egui::RichText::new(format!("{} changes", entries.len())).weak());
+                if dropped > 0 {
+                    ui.label(egui::RichText::new(format!("({} oldest dropped)", dropped)).weak());
+                }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.small_button("✕").clicked() {
+                        close = true;
+                    }
+                    if ui.add_enabled(!entries.is_empty(), egui::Button::new("Export and Clear...").small())
+                        .on_hover_text("Save the log, then start a fresh one")
+                        .clicked()
+                    {
+                        clear = true;
+                    }
+                    if ui.add_enabled(!entries.is_empty(), egui::Button::new("Export...").small())
+                        .on_hover_text("Save as CSV or JSON")
+                        .clicked()
+                    {
+                        export = true;
+                    }
+                });
+            });
+            ui.separator();
+            let row_height = ui.text_style_height(&egui::TextStyle::Body);
+            egui::ScrollArea::vertical()
+                .auto_shrink([false, false])
+                .stick_to_bottom(true)
+                .show_rows(ui, row_height, entries.len(), |ui, range| {
+                    egui::Grid::new("audit_log_grid").num_columns(6).striped(true).show(ui, |ui| {
+                        for entry in &entries[range] {
+                            ui.label(egui::RichText::new(entry.time.get(11..23).unwrap_or(&entry.time)).monospace())
+                                .on_hover_text(&entry.time);
+                            ui.label(if entry.action == AuditAction::Edit {
+                                entry.change.to_string()
+                            } else {
+                                format!("{} {}", entry.action.name(), entry.change.to_lowercase())
+                            });
+                            ui.label(entry.row.map(|r| format!("row {}", r)).unwrap_or_default());
+                            ui.label(entry.column.map(|c| format!("col {}", c)).unwrap_or_default());
+                            ui.label(egui::RichText::new(truncate_display(&entry.old_value, 60).map_or(entry.old_value.as_str(), |(s, _)| s)).weak());
+                            ui.label(truncate_display(&entry.new_value, 60).map_or(entry.new_value.as_str(), |(s, _)| s));
+                            ui.end_row();
                         }
                     });
                 });
-                if back_clicked {
-                    next_state = Some(AppState::Welcome);
-                }
+        });
+    if close {
+        state.show_audit_log = false;
+    }
+    if export {
+        state.export_audit_log();
+    }
+    if clear && state.export_audit_log() {
+        state.audit_log_mut().clear();
+    }
+}
+
+/// The quick filter's conditions, each removable, and how many rows pass
+fn filter_bar(ui: &mut egui::Ui, state: &mut EditorState) {
+    let mut remove = None;
+    let mut clear = false;
+    let mut reapply = false;
+    let mut clear_rows = false;
+    ui.horizontal_wrapped(|ui| {
+        ui.label("🔍 Filter:");
+        for (i, condition) in state.row_filter.conditions.iter().enumerate() {
+            let label = format!("{} {}  ✖", state.column_header(condition.column), condition.describe());
+            if ui.button(label).on_hover_text("Remove this condition").clicked() {
+                remove = Some(i);
             }
-            AppState::Loading(name) => {
-                 egui::CentralPanel::default().show(ctx, |ui| {
-                    ui.vertical_centered(|ui| {
-                        ui.heading(format!("Loading {}...", name));
-                        ui.spinner();
-                    });
-                });
+        }
+        if let Some(ref rows) = state.row_filter.rows
+            && ui.button(format!("{} row(s) brushed in the graph  ✖", rows.len())).on_hover_text("Remove this condition").clicked()
+        {
+            clear_rows = true;
+        }
+        ui.separator();
+        match state.filtered_rows {
+            Some(ref rows) => {
+                let shown = rows.len() - state.first_data_row().min(rows.len());
+                let total = state.filtered_total.saturating_sub(state.first_data_row());
+                ui.label(egui::RichText::new(format!("{} of {} rows", shown, total)).weak());
             }
-            AppState::Editor(state) => {
-                render_editor(state, ctx, &mut self.settings);
+            None => {
+                ui.spinner();
             }
         }
-
-        if let Some(s) = next_state {
-            self.state = s;
+        if ui.small_button("⟳").on_hover_text("Filter again, e.g. after edits").clicked() {
+            reapply = true;
+        }
+        if ui.small_button("Clear").clicked() {
+            clear = true;
         }
+    });
+    if let Some(i) = remove {
+        state.row_filter.conditions.remove(i);
+        reapply = true;
+    }
+    if clear_rows {
+        state.row_filter.rows = None;
+        reapply = true;
+    }
+    if clear {
+        state.dispatch(Action::ClearFilter);
+    } else if reapply {
+        state.apply_filter();
     }
 }
 
-fn render_editor(state: &mut EditorState, ctx: &egui::Context, settings: &mut Settings) {
-    // Override font size
-    let mut style = (*ctx.style()).clone();
-    style.text_styles.iter_mut().for_each(|(_, font_id)| {
-        font_id.size = settings.font_size;
-    });
-    // This is a bit heavy to do every frame, but fine for now. 
-    // Ideally we'd set this once or in apply_style if it wasn't varying per-frame potentially.
-    // Actually apply_style is better, but here we can scope it to the editor panel if we wanted.
-    // Let's execute it on the ui scope.
-
-    egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
-        ui.style_mut().text_styles = style.text_styles.clone(); // Apply font
-        ui.add_space(4.0);
-        ui.horizontal(|ui| {
-            ui.label(egui::RichText::new("CSVit").strong());
-            ui.label(egui::RichText::new(&state.filename).color(egui::Color32::from_gray(150)));
-            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                 ui.selectable_value(&mut state.view_mode, ViewMode::Table, "Table");
-                 ui.selectable_value(&mut state.view_mode, ViewMode::Text, "Text");
-                 ui.selectable_value(&mut state.view_mode, ViewMode::Graph, "Graph");
-                 ui.separator();
-                 ui.checkbox(&mut state.word_wrap, "Word Wrap");
-                 ui.separator();
-                 if ui.button("Export JSON").clicked() {
-                     if let Some(path) = rfd::FileDialog::new().add_filter("JSON", &["json"]).save_file() {
-                         let input = state.filename.clone();
-                         let output = path.to_string_lossy().to_string();
-                         std::thread::spawn(move || {
-                             let _ = crate::backend::export::export_to_json(&input, &output);
-                         });
-                     }
-                 }
-            });
-        });
-        ui.add_space(4.0);
-    });
-
-    // Edit toolbar (only shown when grid mode is active)
-    if state.grid.is_some() {
-        egui::TopBottomPanel::top("edit_toolbar").show(ctx, |ui| {
+/// Scan results and schema validation issues in one list, filtered by
+/// severity and category; clicking a location selects it
+fn problems_panel(ctx: &egui::Context, state: &mut EditorState) {
+    let mut rescan = false;
+    let mut close = false;
+    let mut jump = None;
+    egui::TopBottomPanel::bottom("problems")
+        .resizable(true)
+        .default_height(180.0)
+        .show(ctx, |ui| {
+            let validation: Vec<Problem> = state.schema_issues.iter().flatten().map(Problem::from).collect();
+            let scanned = state.problems.as_ref();
+            let count = |category: ProblemCategory| match category {
+                ProblemCategory::Validation => validation.len(),
+                _ => scanned.map_or(0, |p| p.count(category)),
+            };
             ui.horizontal(|ui| {
-                ui.label("Edit:");
-                if ui.button("➕ Row").clicked() {
-                    if let Some(ref mut grid) = state.grid {
-                        let after = state.selected_cell.map(|(r, _)| r);
-                        grid.add_row(after);
-                    }
-                }
-                if ui.button("➖ Row").clicked() {
-                    if let Some(ref mut grid) = state.grid {
-                        if let Some((r, _)) = state.selected_cell {
-                            grid.delete_row(r);
-                            state.selected_cell = None;
-                        }
-                    }
+                ui.strong("⚠ Problems");
+                for (i, severity) in Severity::ALL.iter().enumerate() {
+                    let n: usize = ProblemCategory::ALL.iter().filter(|c| c.severity() == *severity).map(|c| count(*c)).sum();
+                    ui.toggle_value(&mut state.problem_severities[i], format!("{} {} {}", severity.icon(), n, severity.name()));
                 }
                 ui.separator();
-                if ui.button("➕ Col").clicked() {
-                    if let Some(ref mut grid) = state.grid {
-                        let after = state.selected_cell.map(|(_, c)| c);
-                        grid.add_column(after);
-                        state.num_columns = grid.num_cols();
-                        state.column_widths.push(100.0);
-                    }
+                if ui.selectable_label(state.problem_category.is_none(), "All").clicked() {
+                    state.problem_category = None;
                 }
-                if ui.button("➖ Col").clicked() {
-                    if let Some(ref mut grid) = state.grid {
-                        if let Some((_, c)) = state.selected_cell {
-                            grid.delete_column(c);
-                            state.num_columns = grid.num_cols();
-                            if !state.column_widths.is_empty() {
-                                state.column_widths.pop();
-                            }
-                            state.selected_cell = None;
-                        }
+                for category in ProblemCategory::ALL {
+                    let selected = state.problem_category == Some(category);
+                    if ui.selectable_label(selected, format!("{} ({})", category.name(), count(category))).clicked() {
+                        state.problem_category = (!selected).then_some(category);
                     }
                 }
-                ui.separator();
-                // Undo/Redo buttons
-                let can_undo = state.grid.as_ref().map(|g| g.can_undo()).unwrap_or(false);
-                let can_redo = state.grid.as_ref().map(|g| g.can_redo()).unwrap_or(false);
-                let undo_count = state.grid.as_ref().map(|g| g.undo_count()).unwrap_or(0);
-                let redo_count = state.grid.as_ref().map(|g| g.redo_count()).unwrap_or(0);
-                
-                ui.add_enabled_ui(can_undo, |ui| {
-                    if ui.button(format!("↩ Undo ({})", undo_count)).clicked() {
-                        if let Some(ref mut grid) = state.grid {
-                            grid.undo();
-                        }
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.small_button("✕").clicked() {
+                        close = true;
                     }
-                });
-                ui.add_enabled_ui(can_redo, |ui| {
-                    if ui.button(format!("↪ Redo ({})", redo_count)).clicked() {
-                        if let Some(ref mut grid) = state.grid {
-                            grid.redo();
-                        }
+                    if state.problems_job.is_some() {
+                        ui.spinner();
+                    } else if ui.small_button("⟳ Rescan").clicked() {
+                        rescan = true;
                     }
                 });
-                ui.separator();
-                if ui.button("💾 Save As").clicked() {
-                    if let Some(path) = rfd::FileDialog::new()
-                        .add_filter("CSV", &["csv"])
-                        .add_filter("CSVit", &["csvi"])
-                        .save_file()
-                    {
-                        if let Some(ref grid) = state.grid {
-                            let csv_text = grid.to_csv();
-                            let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("csv");
-                            if ext == "csvi" {
-                                let metadata = crate::backend::csvi::CsviMetadata::new();
-                                let _ = crate::backend::csvi::save_csvi(&path, &csv_text, &metadata);
+            });
+            ui.separator();
+            if scanned.is_none() && validation.is_empty() {
+                ui.label(egui::RichText::new("Not scanned yet").weak());
+                return;
+            }
+
+            let shown: Vec<&Problem> = scanned
+                .into_iter()
+                .flat_map(|p| p.list.iter())
+                .chain(&validation)
+                .filter(|p| state.problem_severities[p.severity() as usize])
+                .filter(|p| state.problem_category.is_none_or(|c| c == p.category))
+                .collect();
+            if shown.is_empty() {
+                ui.label(egui::RichText::new("✔ No problems").color(egui::Color32::from_rgb(100, 200, 100)));
+                return;
+            }
+            let row_height = ui.text_style_height(&egui::TextStyle::Body);
+            egui::ScrollArea::vertical()
+                .auto_shrink([false, false])
+                .show_rows(ui, row_height, shown.len(), |ui, range| {
+                    egui::Grid::new("problems_grid").num_columns(4).striped(true).show(ui, |ui| {
+                        for problem in &shown[range] {
+                            ui.label(problem.severity().icon()).on_hover_text(problem.severity().name());
+                            ui.label(egui::RichText::new(problem.category.name()).weak());
+                            let location = match (problem.row, problem.column) {
+                                (Some(row), Some(col)) => format!("Row {}, {}", row, state.column_header(col)),
+                                (Some(row), None) => format!("Row {}", row),
+                                (None, Some(col)) => state.column_header(col),
+                                (None, None) => String::new(),
+                            };
+                            if let Some(row) = problem.row {
+                                if ui.link(location).clicked() {
+                                    jump = Some((row, problem.column.unwrap_or(0)));
+                                }
                             } else {
-                                let _ = std::fs::write(&path, csv_text);
+                                ui.label(location);
                             }
-                            state.filename = path.to_string_lossy().to_string();
+                            ui.label(&problem.message);
+                            ui.end_row();
                         }
-                    }
-                }
-            });
+                    });
+                });
+            let listed = scanned.map_or(0, |p| p.list.len()) + validation.len();
+            let total = scanned.map_or(0, |p| p.total()) + validation.len();
+            if total > listed {
+                ui.label(egui::RichText::new(format!("Showing the first {} of {} problems", listed, total)).weak().small());
+            }
         });
+    if close {
+        state.show_problems = false;
+    }
+    if rescan {
+        state.dispatch(Action::ScanProblems);
     }
+    if let Some((row, col)) = jump {
+        state.selected_cell = Some((row, col));
+        state.scroll_to_row = Some(row);
+    }
+}
 
-    // Ctrl+B toggle for Profile HUD
-    // Toggle Profile HUD
-    if ctx.input(|i| settings.keymap.toggle_hud.matches(i)) {
-        settings.show_profile_hud = !settings.show_profile_hud;
+/// Read `line` and dump its bytes. Long records are cut to 64 KB around the
+/// byte asked for; the dump is for spotting stray bytes, not reading.
+fn hex_dump(loader: &CsvLoader, line: usize, offset: Option<u64>) -> HexDump {
+    const SHOWN: usize = 64 * 1024;
+    let start = loader.record_offset(line).unwrap_or(0);
+    let bytes = loader.get_record_line(line).unwrap_or_default();
+    // The offset came from an earlier read, so it may not land in the line any more
+    let into = offset.and_then(|o| o.checked_sub(start)).filter(|&into| into < bytes.len() as u64);
+    let from = into.map_or(0, |into| (into as usize).saturating_sub(SHOWN / 2) / 16 * 16);
+    let shown = &bytes[from.min(bytes.len())..(from + SHOWN).min(bytes.len())];
+    HexDump {
+        line,
+        offset,
+        start,
+        len: bytes.len(),
+        byte: into.map(|into| (into, bytes[into as usize])),
+        shown: shown.len(),
+        text: file_info::hex_dump(shown, start + from as u64),
     }
+}
 
-    // Profile HUD Side Panel (right side)
-    if settings.show_profile_hud {
-        egui::SidePanel::right("profile_hud")
-            .resizable(true)
-            .default_width(280.0)
-            .min_width(200.0)
-            .show(ctx, |ui| {
-                ui.heading("📊 Column Profile");
-                ui.separator();
-                
-                if let Some(ref profile) = state.column_profile {
-                    ui.label(format!("Column: {}", profile.header));
-                    ui.label(format!("Type: {}", profile.data_type.as_ref().map_or("Unknown", |t| t.name())));
-                    ui.separator();
-                    
-                    // Data health
-                    ui.collapsing("📋 Data Health", |ui| {
-                        ui.label(format!("Total Rows: {}", profile.total_count));
-                        ui.label(format!("Null/Empty: {} ({:.1}%)", profile.null_count, profile.null_percentage()));
-                        ui.label(format!("Unique Values: {}", profile.unique_count));
-                    });
-                    
-                    // Numeric stats (if applicable)
-                    if profile.min.is_some() {
-                        ui.separator();
-                        ui.collapsing("📈 Numeric Stats", |ui| {
-                            if let Some(min) = profile.min {
-                                ui.label(format!("Min: {:.4}", min));
-                            }
-                            if let Some(max) = profile.max {
-                                ui.label(format!("Max: {:.4}", max));
-                            }
-                            if let Some(mean) = profile.mean {
-                                ui.label(format!("Mean: {:.4}", mean));
-                            }
-                            if let Some(std) = profile.std_dev {
-                                ui.label(format!("Std Dev: {:.4}", std));
-                            }
-                            if let Some(sum) = profile.sum {
-                                ui.label(format!("Sum: {:.4}", sum));
-                            }
-                        });
-                    }
-                    
-                    // Top values
-                    if !profile.top_values.is_empty() {
-                        ui.separator();
-                        ui.collapsing("🏆 Top Values", |ui| {
-                            for (i, (val, count)) in profile.top_values.iter().enumerate() {
-                                let display_val = if val.len() > 25 {
-                                    format!("{}...", &val[..22])
-                                } else {
-                                    val.clone()
-                                };
-                                ui.label(format!("{}. {} ({})", i + 1, display_val, count));
-                            }
-                        });
-                    }
-                } else {
-                    ui.label("Select a column to view its profile.");
-                    ui.label("");
-                    ui.label("Click on a column header or select a cell to analyze that column.");
-                }
-            });
+/// A strip spanning the whole table: edited rows, validation issues and
+/// bookmarks as ticks, the on-screen rows as a box. Returns the row clicked
+/// or dragged to.
+fn row_minimap(ui: &mut egui::Ui, state: &EditorState) -> Option<usize> {
+    let total = state.document().row_count();
+    let (rect, response) = ui.allocate_exact_size(ui.available_size(), egui::Sense::click_and_drag());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+    if total == 0 {
+        return None;
     }
+    let y_of = |row: usize| rect.top() + (row as f32 + 0.5) / total as f32 * rect.height();
 
-    // Vim mode status bar (bottom panel)
-    if settings.keybinding_mode == KeybindingMode::Vim {
-        egui::TopBottomPanel::bottom("vim_status_bar")
-            .exact_height(24.0)
-            .show(ctx, |ui| {
-                ui.horizontal(|ui| {
-                    // Mode indicator
-                    let (mode_text, mode_color) = match state.vim_mode {
-                        VimMode::Normal => ("-- NORMAL --", egui::Color32::from_rgb(100, 200, 100)),
-                        VimMode::Insert => ("-- INSERT --", egui::Color32::from_rgb(100, 150, 255)),
-                        VimMode::Visual => ("-- VISUAL --", egui::Color32::from_rgb(255, 150, 100)),
-                        VimMode::Command => (":", egui::Color32::from_rgb(200, 200, 100)),
-                    };
-                    ui.label(egui::RichText::new(mode_text).color(mode_color).strong().monospace());
-                    
-                    ui.separator();
-                    
-                    // Position indicator
-                    if let Some((r, c)) = state.selected_cell {
-                        ui.label(egui::RichText::new(format!("{}:{}", r + 1, c + 1)).monospace());
-                    }
-                    
-                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
-                        ui.label(egui::RichText::new("hjkl:move  i:insert  gg:top  G:bottom  0:start  $:end  Esc:normal").weak().small());
-                    });
-                });
-            });
+    let (first, last) = state.visible_rows;
+    let viewport = egui::Rect::from_x_y_ranges(rect.x_range(), y_of(first) - 1.0..=y_of(last) + 1.0);
+    painter.rect_filled(viewport, 0.0, ui.visuals().widgets.inactive.bg_fill);
+
+    let edited = state.document().edited_rows();
+    let issues: Vec<usize> = state.schema_issues.iter().flatten().filter_map(|issue| issue.row).collect();
+    let bookmarks: Vec<usize> = state.bookmarks.iter().copied().collect();
+    // Ticks per pixel row, so millions of marks cost one line each
+    let mut drawn = std::collections::HashSet::new();
+    for (color, rows) in [(MINIMAP_COLORS[0].1, edited), (MINIMAP_COLORS[1].1, issues), (MINIMAP_COLORS[2].1, bookmarks)] {
+        drawn.clear();
+        for row in rows {
+            let y = y_of(row).round();
+            if drawn.insert(y as i32) {
+                painter.hline(rect.x_range(), y, egui::Stroke::new(2.0, color));
+            }
+        }
+    }
+    if let Some((row, _)) = state.selected_cell {
+        painter.hline(rect.x_range(), y_of(row), egui::Stroke::new(1.0, ui.visuals().strong_text_color()));
     }
 
-    egui::CentralPanel::default().show(ctx, |ui| {
-         ui.style_mut().text_styles = style.text_styles.clone(); // Apply font
-         
-         // Use grid if available, otherwise use loader
-         let total_rows = if let Some(ref grid) = state.grid {
-             grid.num_rows()
-         } else {
-             state.loader.total_records()
-         };
-         let num_cols = state.num_columns;
-         let mut scroll_target = None;
-         
-         // Helper to load content - uses grid if available
-         let load_content = |state: &mut EditorState, r: usize, c: usize| -> String {
-              if let Some(ref grid) = state.grid {
-                  grid.get_cell(r, c).cloned().unwrap_or_default()
-              } else {
-                  let line_content = match state.reader.get_rows(r, 1) {
-                        Ok(v) => v.get(0).cloned().unwrap_or_default(),
-                        Err(_) => String::new(),
-                  };
-                  let fields = CsvParser::parse_line(&line_content).unwrap_or_default();
-                  if let Some(edit) = state.editor.get_edit(r, c) {
-                      edit.clone()
-                  } else {
-                      fields.get(c).cloned().unwrap_or_default()
-                  }
-              }
-         };
-
-         // Keyboard Navigation
-         if state.editing_cell.is_none() && state.edit_modal.is_none() {
-             // Vim mode: hjkl navigation (only in Normal mode)
-             let vim_mode_active = settings.keybinding_mode == KeybindingMode::Vim && state.vim_mode == VimMode::Normal;
-             
-             if let Some((r, c)) = state.selected_cell {
-                 // Arrow keys always work, hjkl only in Vim mode
-                 let move_down = ui.input(|i| settings.keymap.move_down.matches(i)) 
-                     || (vim_mode_active && ui.input(|i| i.key_pressed(egui::Key::J)));
-                 let move_up = ui.input(|i| settings.keymap.move_up.matches(i))
-                     || (vim_mode_active && ui.input(|i| i.key_pressed(egui::Key::K)));
-                 let move_right = ui.input(|i| settings.keymap.move_right.matches(i))
-                     || (vim_mode_active && ui.input(|i| i.key_pressed(egui::Key::L)));
-                 let move_left = ui.input(|i| settings.keymap.move_left.matches(i))
-                     || (vim_mode_active && ui.input(|i| i.key_pressed(egui::Key::H)));
-                 
-                 // Vim shortcuts
-                 let go_top = vim_mode_active && ui.input(|i| i.key_pressed(egui::Key::G) && !i.modifiers.shift);
-                 let go_bottom = vim_mode_active && ui.input(|i| i.key_pressed(egui::Key::G) && i.modifiers.shift);
-                 let go_line_start = vim_mode_active && ui.input(|i| i.key_pressed(egui::Key::Num0) || i.key_pressed(egui::Key::Home));
-                 let go_line_end = vim_mode_active && ui.input(|i| i.key_pressed(egui::Key::Num4) && i.modifiers.shift); // $
-                 
-                 // Enter insert mode with 'i'
-                 let enter_insert = vim_mode_active && ui.input(|i| i.key_pressed(egui::Key::I));
-                 
-                 if move_down {
-                     let next_row = (r.min(total_rows - 1) + 1).min(total_rows - 1);
-                     state.selected_cell = Some((next_row, c));
-                     scroll_target = Some(next_row);
-                 } else if move_up {
-                      let prev_row = r.saturating_sub(1);
-                      state.selected_cell = Some((prev_row, c));
-                      scroll_target = Some(prev_row);
-                 } else if move_right {
-                      state.selected_cell = Some((r, (c + 1).min(num_cols - 1)));
-                      scroll_target = Some(r);
-                 } else if move_left {
-                      state.selected_cell = Some((r, c.saturating_sub(1)));
-                      scroll_target = Some(r);
-                 } else if go_top {
-                      state.selected_cell = Some((0, c));
-                      scroll_target = Some(0);
-                 } else if go_bottom {
-                      state.selected_cell = Some((total_rows.saturating_sub(1), c));
-                      scroll_target = Some(total_rows.saturating_sub(1));
-                 } else if go_line_start {
-                      state.selected_cell = Some((r, 0));
-                 } else if go_line_end {
-                      state.selected_cell = Some((r, num_cols.saturating_sub(1)));
-                 } else if enter_insert {
-                      state.vim_mode = VimMode::Insert;
-                      state.editing_cell = Some((r, c));
-                      state.input_buffer = load_content(state, r, c);
-                 } else if ui.input(|i| i.key_pressed(egui::Key::Enter)) {
-                      if settings.use_edit_modal {
-                          let text = load_content(state, r, c);
-                          state.edit_modal = Some((r, c, text));
-                      } else {
-                          if vim_mode_active {
-                              state.vim_mode = VimMode::Insert;
-                          }
-                          state.editing_cell = Some((r, c));
-                          state.input_buffer = load_content(state, r, c);
-                      }
-                 }
-             } else {
-                 // Initial selection on arrow key or hjkl
-                  let any_nav = ui.input(|i| {
-                      settings.keymap.move_down.matches(i) || settings.keymap.move_up.matches(i) || 
-                      settings.keymap.move_right.matches(i) || settings.keymap.move_left.matches(i) ||
-                      (vim_mode_active && (i.key_pressed(egui::Key::H) || i.key_pressed(egui::Key::J) || 
-                                           i.key_pressed(egui::Key::K) || i.key_pressed(egui::Key::L)))
-                  });
-                  if any_nav {
-                      state.selected_cell = Some((0, 0));
-                      scroll_target = Some(0);
-                  }
-             }
-         }
-         
-         // Exit insert mode with Escape (Vim mode)
-         if settings.keybinding_mode == KeybindingMode::Vim && state.vim_mode == VimMode::Insert {
-             if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
-                 state.vim_mode = VimMode::Normal;
-             }
-         }
-         
-         // Undo/Redo keyboard shortcuts
-         if ui.input(|i| settings.keymap.undo.matches(i)) {
-             if let Some(ref mut grid) = state.grid {
-                 grid.undo();
-             }
-         }
-         if ui.input(|i| settings.keymap.redo.matches(i)) {
-             if let Some(ref mut grid) = state.grid {
-                 grid.redo();
-             }
-         }
-
-         let row_height = settings.row_height;
-
-         match state.view_mode {
-            ViewMode::Table => {
-                egui::ScrollArea::horizontal().show(ui, |ui| {
-                    let mut builder = TableBuilder::new(ui)
-                        .striped(true)
-                        .resizable(true)
-                        .cell_layout(egui::Layout::left_to_right(egui::Align::Center))
-                        .column(Column::auto()); // Index
-                    
-                    for width in &state.column_widths {
-                        builder = builder.column(Column::initial(*width).resizable(true));
-                    }
+    let row_at = |y: f32| ((((y - rect.top()) / rect.height()) * total as f32) as usize).min(total - 1);
+    let jump = response.interact_pointer_pos().filter(|_| response.clicked() || response.dragged()).map(|pos| row_at(pos.y));
+    if let Some(pos) = response.hover_pos() {
+        response.on_hover_ui(|ui| {
+            ui.label(format!("Row {}", row_at(pos.y)));
+            for (name, color) in MINIMAP_COLORS {
+                ui.colored_label(color, format!("■ {}", name));
+            }
+        });
+    }
+    jump
+}
 
-                    if let Some(target_row) = scroll_target {
-                        builder = builder.scroll_to_row(target_row, Some(egui::Align::Center));
-                    }
-                    
-                    builder
-                        .header(30.0, |mut header| {
-                            header.col(|ui| { ui.strong("Row"); });
-                            for i in 0..state.num_columns {
-                                header.col(|ui| { ui.strong(format!("Col {}", i)); });
-                            }
-                        })
-                        .body(|body| {
-                            body.rows(row_height, total_rows, |mut row| {
-                                let row_index = row.index();
-                                
-                                // Get fields from grid if available, otherwise from reader
-                                let fields: Vec<String> = if let Some(ref grid) = state.grid {
-                                    (0..state.num_columns)
-                                        .map(|c| grid.get_cell(row_index, c).cloned().unwrap_or_default())
-                                        .collect()
-                                } else {
-                                    let line_content = match state.reader.get_rows(row_index, 1) {
-                                        Ok(v) => v.get(0).cloned().unwrap_or_default(),
-                                        Err(_) => String::new(),
-                                    };
-                                    let mut fields = CsvParser::parse_line(&line_content).unwrap_or_default();
-                                    while fields.len() < state.num_columns { fields.push(String::new()); }
-                                    fields
-                                };
+/// Width that fits a column's header and its typical value, capped so
+/// outliers don't blow out the layout
+fn profile_width(profile: &ColumnProfile) -> f32 {
+    // Average character width at the default font size, plus padding; the
+    // header also has its button frame and the type badge beside it
+    const CHAR_WIDTH: f32 = 8.0;
+    let header = profile.header.chars().count() as f32 * CHAR_WIDTH + 40.0;
+    let values = profile.length_p95 as f32 * CHAR_WIDTH + 16.0;
+    header.max(values).clamp(MIN_AUTO_COLUMN_WIDTH, MAX_AUTO_COLUMN_WIDTH)
+}
 
-                                row.col(|ui| { ui.label(egui::RichText::new(row_index.to_string()).color(egui::Color32::from_gray(100))); });
-                                for (col_index, field) in fields.iter().enumerate().take(state.num_columns) {
-                                    row.col(|ui| {
-                                        let is_editing = state.editing_cell == Some((row_index, col_index));
-                                        let is_selected = state.selected_cell == Some((row_index, col_index));
-                                        
-                                        if is_editing {
-                                            let response = ui.text_edit_singleline(&mut state.input_buffer);
-                                            if response.lost_focus() || ui.input(|i| i.key_pressed(egui::Key::Enter)) {
-                                                if let Some(ref mut grid) = state.grid {
-                                                    grid.set_cell(row_index, col_index, state.input_buffer.clone());
-                                                } else {
-                                                    let old_value = field.clone();
-                                                    state.editor.add_edit(row_index, col_index, old_value, state.input_buffer.clone());
-                                                }
-                                                state.editing_cell = None;
-                                            } else if ui.input(|i| i.key_pressed(egui::Key::Escape)) {
-                                                state.editing_cell = None;
-                                            }
-                                            response.request_focus();
-                                        } else {
-                                             let text = if let Some(edit) = state.editor.get_edit(row_index, col_index) {
-                                                edit
-                                            } else {
-                                                field
-                                            };
-                                            
-                                            // Use placeholder for empty cells to make them clickable
-                                            let display_text = if text.is_empty() { " " } else { text };
-                                            
-                                            // Fill entire available cell space for easy clicking
-                                            let available = ui.available_size();
-                                            let cell_size = egui::vec2(available.x.max(80.0), row_height - 2.0);
-                                            let (rect, response) = ui.allocate_exact_size(cell_size, egui::Sense::click());
-                                            
-                                            // Draw text within the allocated area
-                                            let text_pos = rect.min + egui::vec2(4.0, (rect.height() - settings.font_size) / 2.0);
-                                            ui.painter().text(
-                                                text_pos,
-                                                egui::Align2::LEFT_TOP,
-                                                display_text,
-                                                egui::FontId::proportional(settings.font_size),
-                                                ui.visuals().text_color(),
-                                            );
-                                            
-                                            // Selection Highlight
-                                            if is_selected {
-                                                ui.painter().rect_stroke(
-                                                    response.rect,
-                                                    0.0,
-                                                    egui::Stroke::new(2.0, egui::Color32::from_rgb(100, 200, 255)),
-                                                    egui::StrokeKind::Middle
-                                                );
-                                            }
+/// The first `limit` bytes of `text` (on a character boundary) and how many
+/// bytes were cut, or None when it fits
+fn truncate_display(text: &str, limit: usize) -> Option<(&str, usize)> {
+    if text.len() <= limit {
+        return None;
+    }
+    let mut end = limit;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    Some((&text[..end], text.len() - end))
+}
 
-                                            if response.clicked() {
-                                                state.selected_cell = Some((row_index, col_index));
-                                                
-                                                // Update column profile if HUD is enabled
-                                                if settings.show_profile_hud {
-                                                    // Collect column values for analysis
-                                                    let header = if let Some(ref grid) = state.grid {
-                                                        grid.get_header(col_index).cloned().unwrap_or_else(|| format!("Column {}", col_index + 1))
-                                                    } else {
-                                                        format!("Column {}", col_index + 1)
-                                                    };
-                                                    
-                                                    let values: Vec<String> = if let Some(ref grid) = state.grid {
-                                                        (0..grid.num_rows())
-                                                            .filter_map(|r| grid.get_cell(r, col_index).cloned())
-                                                            .collect()
-                                                    } else {
-                                                        // For mmap files, sample up to 1000 rows
-                                                        let sample_size = total_rows.min(1000);
-                                                        (0..sample_size)
-                                                            .filter_map(|r| {
-                                                                state.reader.get_rows(r, 1).ok()
-                                                                    .and_then(|rows| rows.get(0).cloned())
-                                                                    .and_then(|line| CsvParser::parse_line(&line).ok())
-                                                                    .and_then(|fields| fields.get(col_index).cloned())
-                                                            })
-                                                            .collect()
-                                                    };
-                                                    
-                                                    state.column_profile = Some(ColumnAnalyzer::analyze_column(&header, col_index, &values));
-                                                }
-                                            }
-                                            
-                                            if response.double_clicked() {
-                                                if settings.use_edit_modal {
-                                                    // Load full content for modal
-                                                    // We need to re-read essentially, or copy logic.
-                                                    // Since we are inside the closure, we can't easily call `load_content` helper 
-                                                    // if it borrows key parts. But we have `text` here!
-                                                    state.edit_modal = Some((row_index, col_index, text.clone()));
-                                                } else {
-                                                    state.editing_cell = Some((row_index, col_index));
-                                                    state.input_buffer = text.clone();
-                                                }
-                                            }
-                                            
-                                            response.context_menu(|ui| {
-                                                 if ui.button("Edit Cell").clicked() {
-                                                     // Always allow explicit edit via menu
-                                                     if settings.use_edit_modal {
-                                                          state.edit_modal = Some((row_index, col_index, text.clone()));
-                                                     } else {
-                                                          state.editing_cell = Some((row_index, col_index));
-                                                          state.input_buffer = text.clone();
-                                                     }
-                                                     ui.close();
-                                                 }
-                                                if ui.button("View Row as JSON").clicked() {
-                                                    // Collect all fields for this row
-                                                    let mut map = serde_json::Map::new();
-                                                    for (i, val) in fields.iter().enumerate() {
-                                                        // Ideally fetch headers. For now use Col {i}
-                                                        map.insert(format!("Col {}", i), serde_json::Value::String(val.clone()));
-                                                    }
-                                                    let json = serde_json::to_string_pretty(&map).unwrap_or_default();
-                                                    state.json_modal = Some((row_index, json));
-                                                    ui.close();
-                                                }
-                                            });
-                                        }
-                                    });
-                                }
-                            });
-                        });
-                });
-            }
-            ViewMode::Text => {
-                 egui::ScrollArea::vertical().show_rows(ui, row_height, total_rows, |ui, row_range| {
-                    let len = row_range.end - row_range.start;
-                    let rows = state.reader.get_rows(row_range.start, len).unwrap_or_default();
-                    
-                    for (i, line) in rows.iter().enumerate() {
-                        let idx = row_range.start + i;
-                        ui.horizontal(|ui| {
-                           ui.label(egui::RichText::new(format!("{: >6} |", idx)).color(egui::Color32::from_gray(100)).monospace());
-                           ui.monospace(line.trim_end());
-                        });
-                    }
-                });
-            }
-            ViewMode::Graph => {
-                 egui::CentralPanel::default().show(ctx, |ui| {
-                     ui.horizontal(|ui| {
-                        ui.label("X Axis:");
-                        egui::ComboBox::from_id_salt("x_axis")
-                            .selected_text(format!("Col {}", state.graph_x_col))
-                            .show_ui(ui, |ui| {
-                                for i in 0..state.num_columns {
-                                    ui.selectable_value(&mut state.graph_x_col, i, format!("Col {}", i));
-                                }
-                            });
-                        
-                        ui.label("Y Axis:");
-                         egui::ComboBox::from_id_salt("y_axis")
-                            .selected_text(format!("Col {}", state.graph_y_col))
-                            .show_ui(ui, |ui| {
-                                for i in 0..state.num_columns {
-                                    ui.selectable_value(&mut state.graph_y_col, i, format!("Col {}", i));
-                                }
-                            });
-                        
-                        if ui.button("Regenerate Graph").clicked() {
-                            // Fetch data
-                            let records = std::cmp::min(state.loader.total_records(), 5000); // Limit to 5000 for perfo
-                            let mut data = Vec::with_capacity(records);
-                            for i in 0..records {
-                                if let Some(line) = state.loader.get_record_line(i) {
-                                     // Need to parse quickly without `csv` reader if possible or use helper
-                                     // Using CsvParser would be safer
-                                    let line_str = String::from_utf8_lossy(line);
-                                    let fields = CsvParser::parse_line(&line_str).unwrap_or_default();
-                                    
-                                    let x_str = fields.get(state.graph_x_col).cloned().unwrap_or_default();
-                                    let y_str = fields.get(state.graph_y_col).cloned().unwrap_or_default();
-                                    
-                                    if let (Ok(x), Ok(y)) = (x_str.parse::<f64>(), y_str.parse::<f64>()) {
-                                        data.push([x, y]);
-                                    }
-                                }
-                            }
-                            state.graph_data = data;
-                        }
-                     });
-                     
-                     egui_plot::Plot::new("csv_plot")
-                        .show(ui, |plot_ui| {
-                            plot_ui.line(egui_plot::Line::new("Data", egui_plot::PlotPoints::new(state.graph_data.clone())));
-                            plot_ui.points(egui_plot::Points::new("Data Points", egui_plot::PlotPoints::new(state.graph_data.clone())).radius(3.0));
-                        });
-                 });
-            }
-         }
-    });
+/// Tooltip body for a hovered cell: the value, cut at `max_bytes`, plus type and size details
+fn cell_tooltip(ui: &mut egui::Ui, value: &str, edited: bool, max_bytes: usize) {
+    ui.set_max_width(480.0);
+    match truncate_display(value, max_bytes) {
+        Some((prefix, _)) => ui.label(format!("{}…", prefix)),
+        None => ui.label(value),
+    };
+    ui.separator();
+    let inferred = ColumnAnalyzer::infer_value_type(value.trim());
+    ui.label(egui::RichText::new(format!(
+        "{} · {} bytes · {} chars",
+        inferred.name(),
+        value.len(),
+        value.chars().count()
+    )).weak().small());
+    if edited {
+        ui.label(egui::RichText::new("✏ Edited").color(egui::Color32::from_rgb(255, 180, 80)).small());
+    }
+}
 
-    // Render Edit Modal
-    if let Some((r, c, mut text)) = state.edit_modal.clone() {
-        let mut open = true;
-        egui::Window::new(format!("Edit Cell ({}, {})", r, c))
-            .open(&mut open)
-            .resize(|r| r.fixed_size(egui::vec2(400.0, 300.0))) 
-            .show(ctx, |ui| {
-                ui.add(egui::TextEdit::multiline(&mut text).desired_width(f32::INFINITY).desired_rows(10));
-                ui.horizontal(|ui| {
-                    if ui.button("Save").clicked() {
-                        // Old value is empty since we don't track it in edit modal
-                        state.editor.add_edit(r, c, String::new(), text.clone());
-                        state.edit_modal = None;
-                    }
-                    if ui.button("Cancel").clicked() {
-                        state.edit_modal = None;
-                    }
-                    if ui.button("Beautify JSON").clicked() {
-                        if let Ok(value) = serde_json::from_str::<serde_json::Value>(&text) {
-                            if let Ok(pretty) = serde_json::to_string_pretty(&value) {
-                                text = pretty;
-                            }
-                        }
-                    }
-                });
-            });
-        
-        if open {
-             // Update the state text if changed (so typing works)
-             // But wait, `text` is a local clone. We need to write back to `state.edit_modal`.
-             if let Some((_, _, ref mut stored_text)) = state.edit_modal {
-                 *stored_text = text;
-             }
-        } else {
-            state.edit_modal = None;
+/// Split a header cell into the header proper and, below it, the band a
+/// pinned baseline row is drawn in (empty when nothing is pinned)
+fn split_header_cell(cell: &mut egui::Ui) -> (egui::Ui, egui::Rect) {
+    let rect = cell.max_rect();
+    let (top, band) = rect.split_top_bottom_at_y((rect.top() + HEADER_HEIGHT).min(rect.bottom()));
+    let top = cell.new_child(egui::UiBuilder::new().max_rect(top).layout(egui::Layout::left_to_right(egui::Align::Center)));
+    (top, band)
+}
+
+fn baseline_fill(ui: &egui::Ui) -> egui::Color32 {
+    ui.visuals().selection.bg_fill.gamma_multiply(0.25)
+}
+
+/// A difference from the baseline with its sign, e.g. "+1.5" or "-0.003"
+fn format_delta(delta: f64) -> String {
+    let text = format!("{:+.3}", delta);
+    let text = if text.contains('.') { text.trim_end_matches('0').trim_end_matches('.') } else { &text };
+    if text == "-0" { "+0".to_string() } else { text.to_string() }
+}
+
+/// Cool-to-warm gradient for a value at `t` in [0, 1] of its column's range
+fn heat_color(t: f32) -> egui::Color32 {
+    let low = [70.0, 130.0, 220.0];
+    let high = [230.0, 80.0, 60.0];
+    let channel = |i: usize| egui::lerp(low[i]..=high[i], t) as u8;
+    egui::Color32::from_rgba_unmultiplied(channel(0), channel(1), channel(2), 90)
+}
+
+/// Hatched background for an empty or null-like cell, with "∅" when it has no text at all
+fn paint_null_marker(ui: &egui::Ui, rect: egui::Rect, empty: bool) {
+    let painter = ui.painter_at(rect);
+    let color = ui.visuals().weak_text_color().gamma_multiply(0.25);
+    let stroke = egui::Stroke::new(1.0, color);
+    let spacing = 8.0;
+    let mut x = rect.left() - rect.height();
+    while x < rect.right() {
+        painter.line_segment(
+            [egui::pos2(x, rect.bottom()), egui::pos2(x + rect.height(), rect.top())],
+            stroke,
+        );
+        x += spacing;
+    }
+    if empty {
+        painter.text(
+            rect.right_center() - egui::vec2(6.0, 0.0),
+            egui::Align2::RIGHT_CENTER,
+            "∅",
+            egui::FontId::proportional(12.0),
+            ui.visuals().weak_text_color(),
+        );
+    }
+}
+
+/// Small inferred-type marker for a column header, with the confidence on hover
+fn type_badge(ui: &mut egui::Ui, profile: &ColumnProfile) {
+    let Some(ref data_type) = profile.data_type else {
+        return;
+    };
+    let badge = egui::RichText::new(data_type.badge())
+        .small()
+        .monospace()
+        .color(ui.visuals().weak_text_color());
+    let tooltip = match data_type {
+        InferredType::Mixed => "Mixed types".to_string(),
+        _ => format!("{} ({:.0}% of non-empty values)", data_type.name(), profile.type_confidence * 100.0),
+    };
+    let tooltip = match profile.semantic_type {
+        Some(semantic) => format!("{}, looks like {}", tooltip, semantic.name()),
+        None => tooltip,
+    };
+    ui.label(badge).on_hover_text(tooltip);
+}
+
+/// Mean and median lines, a one-standard-deviation band around the mean, and
+/// min/max lines from a column's profile, over the plotted points' X range
+fn plot_stats(plot_ui: &mut egui_plot::PlotUi<'_>, profile: &ColumnProfile, points: &[[f64; 2]]) {
+    let (x0, x1) = points.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), p| (lo.min(p[0]), hi.max(p[0])));
+    if let (Some(mean), Some(std_dev)) = (profile.mean, profile.std_dev)
+        && x0 <= x1
+        && std_dev > 0.0
+    {
+        let band = vec![[x0, mean - std_dev], [x1, mean - std_dev], [x1, mean + std_dev], [x0, mean + std_dev]];
+        plot_ui.polygon(egui_plot::Polygon::new("±1 Std Dev", egui_plot::PlotPoints::new(band))
+            .fill_color(egui::Color32::from_rgba_unmultiplied(100, 150, 255, 30))
+            .stroke(egui::Stroke::NONE));
+    }
+    let lines = [
+        ("Mean", profile.mean, egui_plot::LineStyle::Solid),
+        ("Median", profile.median, egui_plot::LineStyle::dashed_loose()),
+        ("Min", profile.min, egui_plot::LineStyle::dotted_dense()),
+        ("Max", profile.max, egui_plot::LineStyle::dotted_dense()),
+    ];
+    for (name, value, style) in lines {
+        if let Some(y) = value {
+            let label = format!("{} ({})", name, crate::backend::aggregate::format_number((y * 1e4).round() / 1e4));
+            plot_ui.hline(egui_plot::HLine::new(label, y).style(style));
         }
     }
+}
 
-    // Render JSON Modal
-    if let Some((idx, json)) = &state.json_modal {
-        let mut open = true;
-        egui::Window::new(format!("Row {} JSON", idx))
-            .open(&mut open)
-            .collapsible(false)
-            .resizable(true)
-            .show(ctx, |ui| {
-                ui.style_mut().text_styles = style.text_styles.clone();
-                egui::ScrollArea::vertical().show(ui, |ui| {
-                    ui.add(egui::TextEdit::multiline(&mut json.as_str()).code_editor());
-                });
-            });
-        if !open {
-            state.json_modal = None;
+/// A small trend line of a column's values over row order, for its header
+fn sparkline(ui: &mut egui::Ui, points: &[Option<f64>]) {
+    let numbers = points.iter().flatten();
+    let (min, max) = numbers.clone().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &x| (lo.min(x), hi.max(x)));
+    if points.len() < 2 || min > max {
+        return;
+    }
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(36.0, 12.0), egui::Sense::hover());
+    let span = if max > min { max - min } else { 1.0 };
+    let position = |i: usize, x: f64| {
+        egui::pos2(
+            rect.left() + rect.width() * i as f32 / (points.len() - 1) as f32,
+            rect.bottom() - rect.height() * ((x - min) / span) as f32,
+        )
+    };
+    let stroke = egui::Stroke::new(1.0, ui.visuals().weak_text_color());
+    // Gaps where a stretch of rows had no numbers
+    let mut segment = Vec::new();
+    for (i, point) in points.iter().enumerate() {
+        match point {
+            Some(x) => segment.push(position(i, *x)),
+            None => {
+                ui.painter().add(egui::Shape::line(std::mem::take(&mut segment), stroke));
+            }
         }
     }
+    ui.painter().add(egui::Shape::line(segment, stroke));
+    response.on_hover_text(format!(
+        "Trend over row order, sampled\nmin {}, max {}",
+        crate::backend::aggregate::format_number(min),
+        crate::backend::aggregate::format_number(max),
+    ));
+}
+
+/// Apply a resolved Vim action to the selection, returning the row to scroll to
+fn apply_vim_action(state: &mut EditorState, ctx: &egui::Context, action: VimAction, total_rows: usize, half_page: usize) -> Option<usize> {
+    let (r, c) = state.selected_cell?;
+    let last_row = total_rows.saturating_sub(1);
+
+    let (row, col) = match action {
+        VimAction::MoveDown(n) => ((r + n).min(last_row), c),
+        VimAction::MoveUp(n) => (r.saturating_sub(n), c),
+        VimAction::MoveRight(n) => (r, state.step_column(c, n as isize)),
+        VimAction::MoveLeft(n) => (r, state.step_column(c, -(n as isize))),
+        VimAction::HalfPageDown(n) => ((r + half_page * n).min(last_row), c),
+        VimAction::HalfPageUp(n) => (r.saturating_sub(half_page * n), c),
+        VimAction::GoToRow(row) => (row.min(last_row), c),
+        VimAction::GoToBottom => (last_row, c),
+        VimAction::LineStart => (r, state.step_column(c, isize::MIN)),
+        VimAction::LineEnd => (r, state.step_column(c, isize::MAX)),
+        VimAction::NextBoundary(n) => {
+            let mut row = r;
+            for _ in 0..n {
+                row = vim::next_boundary(row, total_rows, |i| state.cell_value(i, c).is_empty());
+            }
+            (row, c)
+        }
+        VimAction::PrevBoundary(n) => {
+            let mut row = r;
+            for _ in 0..n {
+                row = vim::prev_boundary(row, |i| state.cell_value(i, c).is_empty());
+            }
+            (row, c)
+        }
+        VimAction::EnterInsert => {
+            state.vim.mode = VimMode::Insert;
+            state.editing_cell = Some((r, c));
+            state.input_buffer = state.cell_value(r, c);
+            return None;
+        }
+        VimAction::YankRows { register, count } => {
+            let end = (r + count).min(total_rows);
+            let rows: Vec<Vec<String>> = (r..end).map(|i| state.row_values(i)).collect();
+            state.status_message = Some(format!("{} row(s) yanked", rows.len()));
+            ctx.copy_text(state.vim.registers.store(register, RegisterContent::Rows(rows)));
+            return None;
+        }
+        VimAction::DeleteRows { register, count } => {
+            let rows = state.restructure(|document| document.delete_rows(r, count))?;
+            state.status_message = Some(format!("{} row(s) deleted", rows.len()));
+            ctx.copy_text(state.vim.registers.store(register, RegisterContent::Rows(rows)));
+            let remaining = state.document().row_count();
+            (r.min(remaining.saturating_sub(1)), c)
+        }
+        VimAction::YankCell { register } => {
+            let value = state.cell_value(r, c);
+            ctx.copy_text(state.vim.registers.store(register, RegisterContent::Cell(value)));
+            state.status_message = Some("Cell yanked".to_string());
+            return None;
+        }
+        VimAction::DeleteCell { register } => {
+            let value = state.cell_value(r, c);
+            ctx.copy_text(state.vim.registers.store(register, RegisterContent::Cell(value)));
+            state.set_cell_value(r, c, String::new());
+            return None;
+        }
+        VimAction::Put { register, count, before } => {
+            let clipboard_text = if register == UNNAMED_REGISTER {
+                crate::backend::clipboard::read_system_clipboard()
+            } else {
+                None
+            };
+            match state.vim.registers.get(register, clipboard_text) {
+                Some(RegisterContent::Cell(value)) => {
+                    state.set_cell_value(r, c, value);
+                    return None;
+                }
+                Some(RegisterContent::Rows(rows)) => {
+                    let at = if before { r } else { (r + 1).min(state.document().row_count()) };
+                    let put = std::iter::repeat_n(rows.iter(), count).flatten().cloned().collect();
+                    state.restructure(|document| document.insert_rows(at, put))?;
+                    state.status_message = Some(format!("{} row(s) put", rows.len() * count));
+                    (at, c)
+                }
+                None => {
+                    state.status_message = Some(format!("Register \"{} is empty", register));
+                    return None;
+                }
+            }
+        }
+    };
+
+    state.selected_cell = Some((row, col));
+    Some(row)
 }
 
 fn apply_style(ctx: &egui::Context, settings: &Settings) {
+    if let Some(scale) = settings.ui_scale
+        && (ctx.pixels_per_point() - scale).abs() > f32::EPSILON
+    {
+        ctx.set_pixels_per_point(scale);
+    }
     match settings.theme {
         Theme::System => {
             ctx.set_visuals(egui::Visuals::default()); 
@@ -1126,6 +7379,36 @@ fn apply_style(ctx: &egui::Context, settings: &Settings) {
             visuals.override_text_color = Some(egui::Color32::from_rgb(205, 214, 244));
             ctx.set_visuals(visuals);
         }
+        Theme::HighContrast => {
+            // Pure black and white with thick outlines and a bright focus color
+            let mut visuals = egui::Visuals::dark();
+            let focus = egui::Color32::from_rgb(255, 220, 0);
+            visuals.panel_fill = egui::Color32::BLACK;
+            visuals.window_fill = egui::Color32::BLACK;
+            visuals.extreme_bg_color = egui::Color32::BLACK;
+            visuals.faint_bg_color = egui::Color32::from_gray(28);
+            visuals.override_text_color = Some(egui::Color32::WHITE);
+            visuals.window_stroke = egui::Stroke::new(2.0, egui::Color32::WHITE);
+            for widget in [
+                &mut visuals.widgets.noninteractive,
+                &mut visuals.widgets.inactive,
+                &mut visuals.widgets.hovered,
+                &mut visuals.widgets.active,
+                &mut visuals.widgets.open,
+            ] {
+                widget.bg_fill = egui::Color32::BLACK;
+                widget.weak_bg_fill = egui::Color32::BLACK;
+                widget.bg_stroke = egui::Stroke::new(1.5, egui::Color32::WHITE);
+                widget.fg_stroke = egui::Stroke::new(1.5, egui::Color32::WHITE);
+            }
+            visuals.widgets.hovered.bg_stroke = egui::Stroke::new(2.5, focus);
+            visuals.widgets.active.bg_fill = focus;
+            visuals.widgets.active.fg_stroke = egui::Stroke::new(2.0, egui::Color32::BLACK);
+            visuals.selection.bg_fill = egui::Color32::from_rgb(0, 90, 200);
+            visuals.selection.stroke = egui::Stroke::new(2.0, focus);
+            visuals.hyperlink_color = focus;
+            ctx.set_visuals(visuals);
+        }
         Theme::Custom(idx) => {
             if let Some(custom) = settings.custom_themes.get(idx) {
                 let mut visuals = egui::Visuals::dark();
@@ -1,3 +1,5 @@
+pub mod action;
 pub mod app;
 pub mod windows;
+pub mod vim;
 
@@ -0,0 +1,347 @@
+use eframe::egui;
+use std::collections::HashMap;
+use crate::backend::clipboard;
+
+/// The unnamed register, shared with the system clipboard
+pub const UNNAMED_REGISTER: char = '"';
+
+/// Vim-like editor modes (only active when keybinding_mode is Vim)
+#[derive(PartialEq, Clone, Copy, Default, Debug)]
+pub enum VimMode {
+    #[default]
+    Normal,
+    Insert,
+    Visual,
+    Command,
+}
+
+/// A command resolved from Normal-mode keystrokes.
+/// Counts are already folded in (a bare motion has a count of 1).
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum VimAction {
+    MoveUp(usize),
+    MoveDown(usize),
+    MoveLeft(usize),
+    MoveRight(usize),
+    /// Ctrl+D / Ctrl+U, repeated `count` times
+    HalfPageDown(usize),
+    HalfPageUp(usize),
+    /// `gg` (or `Ngg` / `NG`) - zero-based row
+    GoToRow(usize),
+    /// `G` without a count
+    GoToBottom,
+    LineStart,
+    LineEnd,
+    /// `}` / `{` - next/previous empty-cell boundary in the column
+    NextBoundary(usize),
+    PrevBoundary(usize),
+    EnterInsert,
+    /// `yy` - yank `count` rows starting at the cursor
+    YankRows { register: char, count: usize },
+    /// `dd` - delete `count` rows into the register
+    DeleteRows { register: char, count: usize },
+    /// `yl` - yank the current cell
+    YankCell { register: char },
+    /// `x` - clear the current cell into the register
+    DeleteCell { register: char },
+    /// `p` / `P` - put the register after/below or before/above the cursor
+    Put { register: char, count: usize, before: bool },
+}
+
+/// What a register holds: a single cell value or whole rows
+#[derive(Clone, Debug, PartialEq)]
+pub enum RegisterContent {
+    Cell(String),
+    Rows(Vec<Vec<String>>),
+}
+
+impl RegisterContent {
+    pub fn to_tsv(&self) -> String {
+        match self {
+            RegisterContent::Cell(value) => value.clone(),
+            RegisterContent::Rows(rows) => clipboard::to_tsv(rows),
+        }
+    }
+
+    /// Interpret clipboard text: a lone value becomes a cell, anything else rows
+    pub fn from_tsv(text: &str) -> Self {
+        let mut rows = clipboard::parse_tsv(text);
+        if rows.len() == 1 && rows[0].len() == 1 && !text.ends_with('\n') {
+            RegisterContent::Cell(rows.remove(0).remove(0))
+        } else {
+            RegisterContent::Rows(rows)
+        }
+    }
+}
+
+/// Named registers (`"a`-`"z`) plus the unnamed register
+#[derive(Default)]
+pub struct Registers {
+    registers: HashMap<char, RegisterContent>,
+    /// Text last pushed to the system clipboard, to detect external copies
+    last_copied: Option<String>,
+}
+
+impl Registers {
+    /// Store into a register. Like Vim, every yank/delete also fills the
+    /// unnamed register; the returned text should go to the system clipboard.
+    pub fn store(&mut self, register: char, content: RegisterContent) -> String {
+        let text = content.to_tsv();
+        if register != UNNAMED_REGISTER {
+            self.registers.insert(register, content.clone());
+        }
+        self.registers.insert(UNNAMED_REGISTER, content);
+        self.last_copied = Some(text.clone());
+        text
+    }
+
+    /// Fetch a register. For the unnamed register, text copied to the
+    /// clipboard by another application takes precedence.
+    pub fn get(&self, register: char, clipboard_text: Option<String>) -> Option<RegisterContent> {
+        if register == UNNAMED_REGISTER
+            && let Some(text) = clipboard_text
+            && !text.is_empty()
+            && self.last_copied.as_ref() != Some(&text)
+        {
+            return Some(RegisterContent::from_tsv(&text));
+        }
+        self.registers.get(&register).cloned()
+    }
+}
+
+/// Normal-mode input state machine: accumulates numeric prefixes and
+/// multi-key sequences (`gg`) until they resolve into a [`VimAction`].
+#[derive(Default)]
+pub struct VimState {
+    pub mode: VimMode,
+    count: Option<usize>,
+    pending: Option<char>,
+    /// Register selected with `"x` for the next operator
+    register: Option<char>,
+    pub registers: Registers,
+}
+
+impl VimState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drain this frame's Normal-mode keystrokes into actions.
+    pub fn handle_input(&mut self, input: &egui::InputState) -> Vec<VimAction> {
+        let mut actions = Vec::new();
+        for event in &input.events {
+            let action = match event {
+                egui::Event::Text(text) => {
+                    let mut last = None;
+                    for c in text.chars() {
+                        last = self.feed_char(c).or(last);
+                    }
+                    last
+                }
+                egui::Event::Key { key, pressed: true, modifiers, .. } => self.feed_key(*key, *modifiers),
+                _ => None,
+            };
+            actions.extend(action);
+        }
+        actions
+    }
+
+    /// Feed a typed character. Digits build up the count prefix, except
+    /// right after `"`, where they name a register (`"1p`).
+    pub fn feed_char(&mut self, c: char) -> Option<VimAction> {
+        if self.pending == Some('"') {
+            self.pending = None;
+            if c.is_ascii_alphanumeric() || c == UNNAMED_REGISTER {
+                self.register = Some(c.to_ascii_lowercase());
+            }
+            return None;
+        }
+
+        if let Some(digit) = c.to_digit(10)
+            && (digit != 0 || self.count.is_some())
+        {
+            let count = self.count.unwrap_or(0);
+            self.count = Some(count.saturating_mul(10).saturating_add(digit as usize));
+            return None;
+        }
+
+        let count = self.count.take();
+        let n = count.unwrap_or(1);
+
+        match self.pending.take() {
+            Some('g') => {
+                self.register = None;
+                return match c {
+                    'g' => Some(VimAction::GoToRow(n.saturating_sub(1))),
+                    _ => None,
+                };
+            }
+            Some(op @ ('y' | 'd')) => {
+                let register = self.take_register();
+                return match (op, c) {
+                    ('y', 'y') => Some(VimAction::YankRows { register, count: n }),
+                    ('y', 'l') => Some(VimAction::YankCell { register }),
+                    ('d', 'd') => Some(VimAction::DeleteRows { register, count: n }),
+                    _ => None,
+                };
+            }
+            _ => {}
+        }
+
+        match c {
+            'j' => Some(VimAction::MoveDown(n)),
+            'k' => Some(VimAction::MoveUp(n)),
+            'l' => Some(VimAction::MoveRight(n)),
+            'h' => Some(VimAction::MoveLeft(n)),
+            '}' => Some(VimAction::NextBoundary(n)),
+            '{' => Some(VimAction::PrevBoundary(n)),
+            '0' => Some(VimAction::LineStart),
+            '$' => Some(VimAction::LineEnd),
+            'i' => Some(VimAction::EnterInsert),
+            'G' => Some(match count {
+                Some(row) => VimAction::GoToRow(row.saturating_sub(1)),
+                None => VimAction::GoToBottom,
+            }),
+            'g' | 'y' | 'd' | '"' => {
+                // Keep the count around for the second key of the sequence
+                self.count = count;
+                self.pending = Some(c);
+                None
+            }
+            'x' => Some(VimAction::DeleteCell { register: self.take_register() }),
+            'p' => Some(VimAction::Put { register: self.take_register(), count: n, before: false }),
+            'P' => Some(VimAction::Put { register: self.take_register(), count: n, before: true }),
+            _ => {
+                self.register = None;
+                None
+            }
+        }
+    }
+
+    fn take_register(&mut self) -> char {
+        self.register.take().unwrap_or(UNNAMED_REGISTER)
+    }
+
+    /// Feed a non-text key press (control chords, Escape, Home/End).
+    pub fn feed_key(&mut self, key: egui::Key, modifiers: egui::Modifiers) -> Option<VimAction> {
+        if modifiers.ctrl {
+            let n = self.count.take().unwrap_or(1);
+            self.pending = None;
+            return match key {
+                egui::Key::D => Some(VimAction::HalfPageDown(n)),
+                egui::Key::U => Some(VimAction::HalfPageUp(n)),
+                _ => None,
+            };
+        }
+        match key {
+            egui::Key::Escape => {
+                self.reset();
+                None
+            }
+            egui::Key::Home => {
+                self.reset();
+                Some(VimAction::LineStart)
+            }
+            egui::Key::End => {
+                self.reset();
+                Some(VimAction::LineEnd)
+            }
+            _ => None,
+        }
+    }
+
+    /// Discard any partially typed count or sequence.
+    pub fn reset(&mut self) {
+        self.count = None;
+        self.pending = None;
+        self.register = None;
+    }
+
+    /// Keys typed so far that haven't resolved yet (shown in the status bar).
+    pub fn pending_keys(&self) -> String {
+        let mut s = self.register.map(|r| format!("\"{}", r)).unwrap_or_default();
+        s.push_str(&self.count.map(|n| n.to_string()).unwrap_or_default());
+        if let Some(c) = self.pending {
+            s.push(c);
+        }
+        s
+    }
+}
+
+/// Row reached by `}` from `start`: skip any empty cells, then the run of
+/// non-empty ones, stopping on the first empty cell after it (or the last row).
+pub fn next_boundary(start: usize, total: usize, is_empty: impl Fn(usize) -> bool) -> usize {
+    let last = total.saturating_sub(1);
+    let mut row = start + 1;
+    while row < last && is_empty(row) {
+        row += 1;
+    }
+    while row < last && !is_empty(row) {
+        row += 1;
+    }
+    row.min(last)
+}
+
+/// Row reached by `{` from `start`, mirroring [`next_boundary`] upwards.
+pub fn prev_boundary(start: usize, is_empty: impl Fn(usize) -> bool) -> usize {
+    let mut row = start.saturating_sub(1);
+    while row > 0 && is_empty(row) {
+        row -= 1;
+    }
+    while row > 0 && !is_empty(row) {
+        row -= 1;
+    }
+    row
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(state: &mut VimState, keys: &str) -> Vec<VimAction> {
+        keys.chars().filter_map(|c| state.feed_char(c)).collect()
+    }
+
+    #[test]
+    fn test_counts_and_sequences() {
+        let mut vim = VimState::new();
+        assert_eq!(feed(&mut vim, "5j"), vec![VimAction::MoveDown(5)]);
+        assert_eq!(feed(&mut vim, "10l"), vec![VimAction::MoveRight(10)]);
+        assert_eq!(feed(&mut vim, "0"), vec![VimAction::LineStart]);
+        assert_eq!(feed(&mut vim, "gg"), vec![VimAction::GoToRow(0)]);
+        assert_eq!(feed(&mut vim, "12gg"), vec![VimAction::GoToRow(11)]);
+        assert_eq!(feed(&mut vim, "G"), vec![VimAction::GoToBottom]);
+        assert_eq!(feed(&mut vim, "3"), vec![]);
+        assert_eq!(vim.pending_keys(), "3");
+        assert_eq!(vim.feed_key(egui::Key::D, egui::Modifiers::CTRL), Some(VimAction::HalfPageDown(3)));
+        assert_eq!(vim.pending_keys(), "");
+    }
+
+    #[test]
+    fn test_registers() {
+        let mut vim = VimState::new();
+        assert_eq!(feed(&mut vim, "\"a3yy"), vec![VimAction::YankRows { register: 'a', count: 3 }]);
+        assert_eq!(feed(&mut vim, "2dd"), vec![VimAction::DeleteRows { register: '"', count: 2 }]);
+        assert_eq!(feed(&mut vim, "\"bP"), vec![VimAction::Put { register: 'b', count: 1, before: true }]);
+        assert_eq!(feed(&mut vim, "\"1p"), vec![VimAction::Put { register: '1', count: 1, before: false }]);
+        assert_eq!(feed(&mut vim, "2\"02p"), vec![VimAction::Put { register: '0', count: 22, before: false }]);
+
+        let rows = RegisterContent::Rows(vec![vec!["1".into(), "2".into()]]);
+        let copied = vim.registers.store('a', rows.clone());
+        assert_eq!(vim.registers.get('a', None), Some(rows.clone()));
+        // Our own clipboard text doesn't override the unnamed register's row shape
+        assert_eq!(vim.registers.get('"', Some(copied)), Some(rows));
+        assert_eq!(vim.registers.get('"', Some("external".into())), Some(RegisterContent::Cell("external".into())));
+    }
+
+    #[test]
+    fn test_boundaries() {
+        // rows: 0 "a", 1 "b", 2 "", 3 "", 4 "c", 5 "d", 6 ""
+        let cells = ["a", "b", "", "", "c", "d", ""];
+        let is_empty = |r: usize| cells[r].is_empty();
+        assert_eq!(next_boundary(0, cells.len(), is_empty), 2);
+        assert_eq!(next_boundary(2, cells.len(), is_empty), 6);
+        assert_eq!(prev_boundary(5, is_empty), 3);
+        assert_eq!(prev_boundary(3, is_empty), 0);
+    }
+}
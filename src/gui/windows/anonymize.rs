@@ -0,0 +1,106 @@
+use eframe::egui;
+use crate::backend::anonymize::MaskTransform;
+
+#[derive(PartialEq, Clone, Copy)]
+enum MaskKind {
+    Hash,
+    Mask,
+    FakeName,
+    FakeEmail,
+}
+
+pub struct AnonymizeWindow {
+    column: usize,
+    header: String,
+    sample: String,
+    kind: MaskKind,
+    salt: String,
+    keep: usize,
+}
+
+impl Default for AnonymizeWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AnonymizeWindow {
+    pub fn new() -> Self {
+        Self {
+            column: 0,
+            header: String::new(),
+            sample: String::new(),
+            kind: MaskKind::Hash,
+            salt: String::new(),
+            keep: 4,
+        }
+    }
+
+    /// Anonymize `column`, previewing the transform on `sample`
+    pub fn load(&mut self, column: usize, header: String, sample: String) {
+        self.column = column;
+        self.header = header;
+        self.sample = sample;
+    }
+
+    fn transform(&self) -> MaskTransform {
+        match self.kind {
+            MaskKind::Hash => MaskTransform::Hash { salt: self.salt.clone() },
+            MaskKind::Mask => MaskTransform::MaskKeepLast(self.keep),
+            MaskKind::FakeName => MaskTransform::FakeName,
+            MaskKind::FakeEmail => MaskTransform::FakeEmail,
+        }
+    }
+
+    /// Returns the column and transform once the user applies it
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool) -> Option<(usize, MaskTransform)> {
+        let mut confirmed = None;
+        egui::Window::new(format!("Anonymize: {}", self.header))
+            .id(egui::Id::new("anonymize_window"))
+            .open(open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.radio_value(&mut self.kind, MaskKind::Hash, "SHA-256 hash with salt");
+                if self.kind == MaskKind::Hash {
+                    ui.horizontal(|ui| {
+                        ui.label("Salt:");
+                        ui.add(egui::TextEdit::singleline(&mut self.salt).password(true).desired_width(180.0));
+                    });
+                    if self.salt.is_empty() {
+                        ui.label(egui::RichText::new("Without a salt, common values can be recovered by hashing guesses.")
+                            .small()
+                            .color(egui::Color32::from_rgb(230, 160, 60)));
+                    }
+                }
+                ui.radio_value(&mut self.kind, MaskKind::Mask, "Partial mask");
+                if self.kind == MaskKind::Mask {
+                    ui.horizontal(|ui| {
+                        ui.label("Keep last");
+                        ui.add(egui::DragValue::new(&mut self.keep).range(0..=16));
+                        ui.label("characters");
+                    });
+                }
+                ui.radio_value(&mut self.kind, MaskKind::FakeName, "Fake names");
+                ui.radio_value(&mut self.kind, MaskKind::FakeEmail, "Fake emails");
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Preview:");
+                    ui.monospace(&self.sample);
+                    ui.label("→");
+                    ui.monospace(self.transform().apply(&self.sample));
+                });
+                ui.label(egui::RichText::new("Every value in the column is replaced; Undo reverts it.").weak().small());
+
+                ui.add_space(6.0);
+                if ui.button("Apply").clicked() {
+                    confirmed = Some((self.column, self.transform()));
+                }
+            });
+        if confirmed.is_some() {
+            *open = false;
+        }
+        confirmed
+    }
+}
@@ -0,0 +1,96 @@
+use eframe::egui;
+use crate::backend::file_info::format_size;
+
+/// Characters per displayed line; longer lines are wrapped at this width
+const CHUNK_CHARS: usize = 160;
+
+/// Read-only view of one cell's full value, however large. The text is
+/// split into short lines up front so only the visible ones are laid out.
+pub struct CellInspectorWindow {
+    row: usize,
+    col: usize,
+    header: String,
+    value: String,
+    chunks: Vec<(usize, usize)>,
+}
+
+impl Default for CellInspectorWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CellInspectorWindow {
+    pub fn new() -> Self {
+        Self {
+            row: 0,
+            col: 0,
+            header: String::new(),
+            value: String::new(),
+            chunks: Vec::new(),
+        }
+    }
+
+    pub fn load(&mut self, row: usize, col: usize, header: String, value: String) {
+        self.chunks = chunk_lines(&value);
+        self.row = row;
+        self.col = col;
+        self.header = header;
+        self.value = value;
+    }
+
+    /// Returns the cell to open in the editor when "Edit" is clicked
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool) -> Option<(usize, usize)> {
+        let mut edit = None;
+        egui::Window::new("Cell Inspector")
+            .open(open)
+            .default_size([560.0, 420.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.strong(format!("Row {}, {}", self.row, self.header));
+                    ui.label(egui::RichText::new(format!(
+                        "{} · {} lines",
+                        format_size(self.value.len() as u64),
+                        self.value.lines().count()
+                    )).weak());
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("📋 Copy").clicked() {
+                        ui.ctx().copy_text(self.value.clone());
+                    }
+                    if ui.button("✏ Edit").clicked() {
+                        edit = Some((self.row, self.col));
+                    }
+                });
+                ui.separator();
+                let row_height = ui.text_style_height(&egui::TextStyle::Monospace);
+                egui::ScrollArea::both()
+                    .auto_shrink([false, false])
+                    .show_rows(ui, row_height, self.chunks.len(), |ui, range| {
+                        for &(start, end) in &self.chunks[range] {
+                            ui.monospace(&self.value[start..end]);
+                        }
+                    });
+            });
+        edit
+    }
+}
+
+/// Byte ranges of each displayed line: the value's own lines, cut every `CHUNK_CHARS`
+fn chunk_lines(value: &str) -> Vec<(usize, usize)> {
+    let mut chunks = Vec::new();
+    let mut offset = 0;
+    for raw in value.split('\n') {
+        let line = raw.strip_suffix('\r').unwrap_or(raw);
+        let mut start = 0;
+        for (n, (i, _)) in line.char_indices().enumerate() {
+            if n > 0 && n.is_multiple_of(CHUNK_CHARS) {
+                chunks.push((offset + start, offset + i));
+                start = i;
+            }
+        }
+        chunks.push((offset + start, offset + line.len()));
+        offset += raw.len() + 1;
+    }
+    chunks
+}
@@ -0,0 +1,117 @@
+use eframe::egui;
+use crate::backend::formatting::ColumnFormat;
+
+/// Date patterns offered in the dropdown; any strftime pattern can be typed
+const DATE_PATTERNS: &[&str] = &["%Y-%m-%d", "%d/%m/%Y", "%m/%d/%Y", "%d %b %Y", "%B %-d, %Y", "%Y-%m-%d %H:%M"];
+
+pub struct ColumnFormatWindow {
+    column: usize,
+    header: String,
+    sample: String,
+    format: ColumnFormat,
+}
+
+impl Default for ColumnFormatWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ColumnFormatWindow {
+    pub fn new() -> Self {
+        Self {
+            column: 0,
+            header: String::new(),
+            sample: String::new(),
+            format: ColumnFormat::default(),
+        }
+    }
+
+    /// Edit the format of `column`, previewing it on `sample`
+    pub fn load(&mut self, column: usize, header: String, sample: String, format: ColumnFormat) {
+        self.column = column;
+        self.header = header;
+        self.sample = sample;
+        self.format = format;
+    }
+
+    /// Returns the column and its new format once the user applies it
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool) -> Option<(usize, ColumnFormat)> {
+        let mut confirmed = None;
+        egui::Window::new(format!("Format: {}", self.header))
+            .id(egui::Id::new("column_format_window"))
+            .open(open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                let format = &mut self.format;
+                egui::Grid::new("column_format_grid").num_columns(2).show(ui, |ui| {
+                    let mut fixed = format.decimals.is_some();
+                    ui.checkbox(&mut fixed, "Decimal places");
+                    if fixed {
+                        let mut places = format.decimals.unwrap_or(2);
+                        ui.add(egui::DragValue::new(&mut places).range(0..=10));
+                        format.decimals = Some(places);
+                    } else {
+                        format.decimals = None;
+                    }
+                    ui.end_row();
+
+                    ui.checkbox(&mut format.thousands_separator, "Thousands separator");
+                    ui.end_row();
+                    ui.checkbox(&mut format.percent, "Percentage");
+                    ui.end_row();
+
+                    let mut currency = format.currency.is_some();
+                    ui.checkbox(&mut currency, "Currency symbol");
+                    if currency {
+                        let symbol = format.currency.get_or_insert_with(|| "$".to_string());
+                        ui.add(egui::TextEdit::singleline(symbol).desired_width(40.0));
+                    } else {
+                        format.currency = None;
+                    }
+                    ui.end_row();
+
+                    let mut dated = format.date_format.is_some();
+                    ui.checkbox(&mut dated, "Date format");
+                    if dated {
+                        let pattern = format.date_format.get_or_insert_with(|| DATE_PATTERNS[0].to_string());
+                        ui.horizontal(|ui| {
+                            ui.add(egui::TextEdit::singleline(pattern).desired_width(120.0));
+                            egui::ComboBox::from_id_salt("date_pattern").selected_text("").width(20.0).show_ui(ui, |ui| {
+                                for p in DATE_PATTERNS {
+                                    ui.selectable_value(pattern, p.to_string(), *p);
+                                }
+                            });
+                        });
+                    } else {
+                        format.date_format = None;
+                    }
+                    ui.end_row();
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label("Preview:");
+                    ui.monospace(&self.sample);
+                    ui.label("→");
+                    ui.monospace(self.format.apply(&self.sample));
+                });
+                ui.label(egui::RichText::new("Only the display changes; cell values stay as they are.").weak().small());
+
+                ui.add_space(6.0);
+                ui.horizontal(|ui| {
+                    if ui.button("Apply").clicked() {
+                        confirmed = Some((self.column, self.format.clone()));
+                    }
+                    if ui.button("Clear").clicked() {
+                        confirmed = Some((self.column, ColumnFormat::default()));
+                    }
+                });
+            });
+        if confirmed.is_some() {
+            *open = false;
+        }
+        confirmed
+    }
+}
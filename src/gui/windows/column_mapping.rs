@@ -0,0 +1,97 @@
+use eframe::egui;
+use crate::backend::import::{map_rows, suggest_column_mapping};
+
+/// Pairs the columns of incoming rows with the open document's columns
+/// before they're inserted, so a differently ordered or named export lines up
+pub struct ColumnMappingWindow {
+    source_name: String,
+    target: Vec<String>,
+    source: Vec<String>,
+    rows: Vec<Vec<String>>,
+    mapping: Vec<Option<usize>>,
+    /// Row the mapped rows are inserted at
+    at: usize,
+}
+
+impl Default for ColumnMappingWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ColumnMappingWindow {
+    pub fn new() -> Self {
+        Self {
+            source_name: String::new(),
+            target: Vec::new(),
+            source: Vec::new(),
+            rows: Vec::new(),
+            mapping: Vec::new(),
+            at: 0,
+        }
+    }
+
+    /// Map `rows` headed by `source` onto the `target` columns, to be inserted at row `at`
+    pub fn load(&mut self, source_name: String, target: Vec<String>, source: Vec<String>, rows: Vec<Vec<String>>, at: usize) {
+        self.mapping = suggest_column_mapping(&target, &source);
+        self.source_name = source_name;
+        self.target = target;
+        self.source = source;
+        self.rows = rows;
+        self.at = at;
+    }
+
+    /// Returns the insert position and the rows in the document's column order once confirmed
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool) -> Option<(usize, Vec<Vec<String>>)> {
+        let mut confirmed = None;
+        egui::Window::new("Match Columns")
+            .open(open)
+            .resizable(true)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.label(format!("{} row(s) from {}", self.rows.len(), self.source_name));
+                ui.separator();
+                egui::ScrollArea::vertical().max_height(360.0).show(ui, |ui| {
+                    egui::Grid::new("column_mapping").num_columns(3).striped(true).show(ui, |ui| {
+                        ui.strong("Column");
+                        ui.strong("Filled from");
+                        ui.strong("First value");
+                        ui.end_row();
+                        for (t, name) in self.target.iter().enumerate() {
+                            ui.label(name);
+                            let selected = self.mapping[t].and_then(|s| self.source.get(s)).map_or("(leave empty)", String::as_str);
+                            egui::ComboBox::from_id_salt(("column_mapping_source", t))
+                                .selected_text(selected)
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut self.mapping[t], None, "(leave empty)");
+                                    for (s, source) in self.source.iter().enumerate() {
+                                        ui.selectable_value(&mut self.mapping[t], Some(s), source);
+                                    }
+                                });
+                            let sample = self.mapping[t].and_then(|s| self.rows.first().and_then(|row| row.get(s)));
+                            ui.label(egui::RichText::new(sample.map_or("", String::as_str)).weak());
+                            ui.end_row();
+                        }
+                    });
+                });
+
+                let unused: Vec<&str> = self.source.iter()
+                    .enumerate()
+                    .filter(|(s, _)| !self.mapping.contains(&Some(*s)))
+                    .map(|(_, name)| name.as_str())
+                    .collect();
+                if !unused.is_empty() {
+                    ui.label(egui::RichText::new(format!("Not imported: {}", unused.join(", "))).weak());
+                }
+                ui.add_space(6.0);
+                if ui.add_enabled(self.mapping.iter().any(Option::is_some), egui::Button::new("Insert Rows")).clicked() {
+                    confirmed = Some((self.at, map_rows(&self.mapping, &self.rows)));
+                    self.rows.clear();
+                }
+            });
+        if confirmed.is_some() {
+            *open = false;
+        }
+        confirmed
+    }
+}
@@ -0,0 +1,85 @@
+use eframe::egui;
+
+/// Picks which columns of a wide file are loaded and displayed
+pub struct ColumnPickerWindow {
+    selected: Vec<bool>,
+    filter: String,
+}
+
+impl Default for ColumnPickerWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ColumnPickerWindow {
+    pub fn new() -> Self {
+        Self {
+            selected: Vec::new(),
+            filter: String::new(),
+        }
+    }
+
+    /// Start from the current projection (None shows every column)
+    pub fn load(&mut self, num_columns: usize, projection: Option<&[usize]>) {
+        self.selected = match projection {
+            Some(cols) => (0..num_columns).map(|c| cols.contains(&c)).collect(),
+            None => vec![true; num_columns],
+        };
+        self.filter.clear();
+    }
+
+    /// Returns the chosen projection once applied: None means all columns
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool, headers: &[String]) -> Option<Option<Vec<usize>>> {
+        let mut confirmed = None;
+        egui::Window::new("Choose Columns")
+            .open(open)
+            .resizable(true)
+            .default_height(420.0)
+            .show(ctx, |ui| {
+                let count = self.selected.iter().filter(|s| **s).count();
+                ui.label(format!("{} of {} columns selected", count, self.selected.len()));
+                ui.horizontal(|ui| {
+                    ui.label("Filter:");
+                    ui.text_edit_singleline(&mut self.filter);
+                });
+                let filter = self.filter.to_lowercase();
+                let matches = |c: usize| filter.is_empty()
+                    || headers.get(c).is_some_and(|h| h.to_lowercase().contains(&filter));
+                ui.horizontal(|ui| {
+                    if ui.button("Select All").clicked() {
+                        (0..self.selected.len()).filter(|&c| matches(c)).for_each(|c| self.selected[c] = true);
+                    }
+                    if ui.button("Select None").clicked() {
+                        (0..self.selected.len()).filter(|&c| matches(c)).for_each(|c| self.selected[c] = false);
+                    }
+                });
+                ui.separator();
+
+                egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    for (c, selected) in self.selected.iter_mut().enumerate() {
+                        if !matches(c) {
+                            continue;
+                        }
+                        let name = headers.get(c).map(String::as_str).unwrap_or("");
+                        ui.checkbox(selected, format!("{}  {}", c + 1, name));
+                    }
+                });
+
+                ui.separator();
+                ui.add_enabled_ui(count > 0, |ui| {
+                    if ui.button("Apply").clicked() {
+                        confirmed = Some(if count == self.selected.len() {
+                            None
+                        } else {
+                            Some((0..self.selected.len()).filter(|&c| self.selected[c]).collect())
+                        });
+                    }
+                });
+            });
+        if confirmed.is_some() {
+            *open = false;
+        }
+        confirmed
+    }
+}
@@ -0,0 +1,86 @@
+use eframe::egui;
+use crate::backend::aggregate::Combine;
+
+/// Two columns to compare in a new column
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CombineRequest {
+    pub a: usize,
+    pub b: usize,
+    pub combine: Combine,
+}
+
+/// Sets up a difference, ratio or percent change between two numeric columns
+pub struct CombineWindow {
+    headers: Vec<String>,
+    request: CombineRequest,
+}
+
+impl Default for CombineWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CombineWindow {
+    pub fn new() -> Self {
+        Self {
+            headers: Vec::new(),
+            request: CombineRequest { a: 0, b: 0, combine: Combine::Difference },
+        }
+    }
+
+    /// Compare `a` with `b`; the calculation is kept
+    pub fn load(&mut self, a: usize, b: usize, headers: Vec<String>) {
+        self.request.a = a;
+        self.request.b = b;
+        self.headers = headers;
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool) -> Option<CombineRequest> {
+        let mut confirmed = None;
+        egui::Window::new("Difference / Ratio Column")
+            .open(open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                let header = |c: usize| self.headers.get(c).cloned().unwrap_or_default();
+                egui::Grid::new("combine_options").num_columns(2).show(ui, |ui| {
+                    for (label, column) in [("a:", &mut self.request.a), ("b:", &mut self.request.b)] {
+                        ui.label(label);
+                        egui::ComboBox::from_id_salt(("combine_column", label))
+                            .selected_text(header(*column))
+                            .show_ui(ui, |ui| {
+                                for (c, name) in self.headers.iter().enumerate() {
+                                    ui.selectable_value(column, c, name);
+                                }
+                            });
+                        ui.end_row();
+                    }
+
+                    ui.label("Calculate:");
+                    egui::ComboBox::from_id_salt("combine_function")
+                        .selected_text(self.request.combine.name())
+                        .show_ui(ui, |ui| {
+                            for combine in Combine::ALL {
+                                ui.selectable_value(&mut self.request.combine, combine, combine.name());
+                            }
+                        });
+                    ui.end_row();
+                });
+                if ui.small_button("⇄ Swap a and b").clicked() {
+                    std::mem::swap(&mut self.request.a, &mut self.request.b);
+                }
+                let new_header = self.request.combine.header(&header(self.request.a), &header(self.request.b));
+                ui.label(egui::RichText::new(format!("New column \"{}\". Rows where either value isn't a number, or b is zero for a ratio, stay empty.", new_header)).weak().small());
+
+                ui.add_space(6.0);
+                if ui.add_enabled(self.request.a != self.request.b, egui::Button::new("Add Column")).clicked() {
+                    confirmed = Some(self.request);
+                }
+            });
+        if confirmed.is_some() {
+            *open = false;
+        }
+        confirmed
+    }
+}
@@ -0,0 +1,170 @@
+use eframe::egui;
+use std::collections::HashMap;
+use crate::backend::dates::{detect_formats, format_date, interpretations, parse_date, DateReformat, DATE_FORMATS, ISO_DATE, TARGET_FORMATS};
+
+/// Sample values shown in the preview
+const PREVIEW_VALUES: usize = 12;
+/// Ambiguous values listed for settling by hand
+const MAX_AMBIGUOUS: usize = 50;
+
+/// Rewrites a column's dates from a detected (or picked) format into another,
+/// letting ambiguous values like 03/04/05 be settled one by one
+pub struct DateFormatWindow {
+    column: usize,
+    header: String,
+    samples: Vec<String>,
+    detected: Vec<(&'static str, usize)>,
+    source: String,
+    target: String,
+    overrides: HashMap<String, String>,
+}
+
+impl Default for DateFormatWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DateFormatWindow {
+    pub fn new() -> Self {
+        Self {
+            column: 0,
+            header: String::new(),
+            samples: Vec::new(),
+            detected: Vec::new(),
+            source: ISO_DATE.to_string(),
+            target: ISO_DATE.to_string(),
+            overrides: HashMap::new(),
+        }
+    }
+
+    /// Reformat `column`, detecting its format from `samples` (its first values)
+    pub fn load(&mut self, column: usize, header: String, mut samples: Vec<String>) {
+        samples.retain(|v| !v.trim().is_empty());
+        let mut seen = std::collections::HashSet::new();
+        samples.retain(|v| seen.insert(v.clone()));
+        self.detected = detect_formats(&samples);
+        self.source = self.detected.first().map_or(ISO_DATE, |&(format, _)| format).to_string();
+        self.column = column;
+        self.header = header;
+        self.samples = samples;
+        self.overrides.clear();
+    }
+
+    fn reformat(&self) -> DateReformat {
+        DateReformat { source: self.source.clone(), target: self.target.clone(), overrides: self.overrides.clone() }
+    }
+
+    /// Returns the column and rewrite once the user applies it
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool) -> Option<(usize, DateReformat)> {
+        let mut confirmed = None;
+        egui::Window::new(format!("Reformat Dates: {}", self.header))
+            .id(egui::Id::new("date_format_window"))
+            .open(open)
+            .resizable(true)
+            .default_width(460.0)
+            .show(ctx, |ui| {
+                egui::Grid::new("date_format_grid").num_columns(2).show(ui, |ui| {
+                    ui.label("Source format:");
+                    ui.horizontal(|ui| {
+                        egui::ComboBox::from_id_salt("date_source")
+                            .selected_text(format_label(DATE_FORMATS, &self.source))
+                            .show_ui(ui, |ui| {
+                                for &(format, label) in DATE_FORMATS {
+                                    let parsed = self.detected.iter().find(|&&(f, _)| f == format).map_or(0, |&(_, n)| n);
+                                    let text = format!("{}  ({} of {})", label, parsed, self.samples.len());
+                                    ui.selectable_value(&mut self.source, format.to_string(), text);
+                                }
+                            });
+                        ui.add(egui::TextEdit::singleline(&mut self.source).desired_width(90.0).font(egui::TextStyle::Monospace))
+                            .on_hover_text("strftime pattern, e.g. %d/%m/%Y");
+                    });
+                    ui.end_row();
+
+                    ui.label("Target format:");
+                    ui.horizontal(|ui| {
+                        egui::ComboBox::from_id_salt("date_target")
+                            .selected_text(format_label(TARGET_FORMATS, &self.target))
+                            .show_ui(ui, |ui| {
+                                for &(format, label) in TARGET_FORMATS {
+                                    ui.selectable_value(&mut self.target, format.to_string(), label);
+                                }
+                            });
+                        ui.add(egui::TextEdit::singleline(&mut self.target).desired_width(90.0).font(egui::TextStyle::Monospace));
+                    });
+                    ui.end_row();
+                });
+                ui.separator();
+
+                let reformat = self.reformat();
+                let unparsed = self.samples.iter().filter(|v| reformat.apply(v).is_none()).count();
+                ui.label(format!("Preview ({} of {} sampled values don't parse and are left as they are)", unparsed, self.samples.len()));
+                egui::Grid::new("date_preview").num_columns(3).striped(true).show(ui, |ui| {
+                    for value in self.samples.iter().take(PREVIEW_VALUES) {
+                        ui.monospace(value);
+                        ui.label("→");
+                        match reformat.apply(value) {
+                            Some(output) => ui.monospace(output),
+                            None => ui.label(egui::RichText::new("not a date").weak()),
+                        };
+                        ui.end_row();
+                    }
+                });
+
+                // Values the source format reads one way but other formats read differently
+                let ambiguous: Vec<(String, Vec<String>)> = self.samples
+                    .iter()
+                    .filter(|v| parse_date(v, &self.source).is_some())
+                    .filter_map(|v| {
+                        let readings = interpretations(v);
+                        (readings.len() > 1).then(|| {
+                            (v.clone(), readings.iter().filter_map(|&(_, date)| format_date(date, &self.target)).collect())
+                        })
+                    })
+                    .take(MAX_AMBIGUOUS)
+                    .collect();
+                if !ambiguous.is_empty() {
+                    ui.separator();
+                    ui.label(egui::RichText::new(format!("{} ambiguous value(s): pick what each means", ambiguous.len()))
+                        .color(egui::Color32::from_rgb(230, 160, 60)));
+                    egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                        egui::Grid::new("date_ambiguous").num_columns(2).striped(true).show(ui, |ui| {
+                            for (value, readings) in ambiguous {
+                                ui.monospace(&value);
+                                ui.horizontal_wrapped(|ui| {
+                                    let chosen = self.overrides.get(&value).cloned();
+                                    if ui.radio(chosen.is_none(), "As source format").clicked() {
+                                        self.overrides.remove(&value);
+                                    }
+                                    for reading in readings {
+                                        if ui.radio(chosen.as_ref() == Some(&reading), &reading).clicked() {
+                                            self.overrides.insert(value.clone(), reading);
+                                        }
+                                    }
+                                });
+                                ui.end_row();
+                            }
+                        });
+                    });
+                }
+
+                ui.add_space(6.0);
+                ui.label(egui::RichText::new("Only the first rows are sampled here; the whole column is rewritten, and Undo reverts it.").weak().small());
+                let valid = !self.source.is_empty() && format_date(chrono::NaiveDate::default(), &self.target).is_some();
+                if !valid {
+                    ui.colored_label(ui.visuals().error_fg_color, "The target format isn't a valid pattern");
+                }
+                if ui.add_enabled(valid, egui::Button::new("Apply")).clicked() {
+                    confirmed = Some((self.column, self.reformat()));
+                }
+            });
+        if confirmed.is_some() {
+            *open = false;
+        }
+        confirmed
+    }
+}
+
+fn format_label(formats: &[(&'static str, &'static str)], format: &str) -> String {
+    formats.iter().find(|&&(f, _)| f == format).map_or_else(|| "Custom".to_string(), |&(_, label)| label.to_string())
+}
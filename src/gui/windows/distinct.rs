@@ -0,0 +1,133 @@
+use std::collections::HashSet;
+
+use eframe::egui;
+use crate::backend::analysis::{DistinctValues, MAX_DISTINCT_VALUES};
+
+/// What the distinct-values browser asks the editor to do
+pub enum DistinctRequest {
+    /// Show only rows whose value in the column is one of these
+    Filter(usize, HashSet<String>),
+    /// Save the value/count list
+    Export,
+}
+
+/// Lists a column's distinct values with their counts; picked values become
+/// a filter
+pub struct DistinctValuesWindow {
+    column: usize,
+    header: String,
+    result: Option<DistinctValues>,
+    selected: HashSet<String>,
+    search: String,
+}
+
+impl Default for DistinctValuesWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DistinctValuesWindow {
+    pub fn new() -> Self {
+        Self { column: 0, header: String::new(), result: None, selected: HashSet::new(), search: String::new() }
+    }
+
+    /// Browse `column`; its values arrive later through `set_result`
+    pub fn load(&mut self, column: usize, header: String) {
+        self.column = column;
+        self.header = header;
+        self.result = None;
+        self.selected.clear();
+        self.search.clear();
+    }
+
+    pub fn set_result(&mut self, result: DistinctValues) {
+        self.result = Some(result);
+    }
+
+    pub fn result(&self) -> Option<&DistinctValues> {
+        self.result.as_ref()
+    }
+
+    pub fn header(&self) -> &str {
+        &self.header
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool) -> Option<DistinctRequest> {
+        let mut request = None;
+        egui::Window::new(format!("Distinct Values: {}", self.header))
+            .id(egui::Id::new("distinct_values_window"))
+            .open(open)
+            .resizable(true)
+            .default_size([340.0, 420.0])
+            .show(ctx, |ui| {
+                let Some(ref result) = self.result else {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Counting values...");
+                    });
+                    return;
+                };
+                ui.label(format!("{} distinct value(s) in {} rows", result.values.len(), result.rows_scanned));
+                if result.too_many {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        format!("Too many distinct values: only the first {} seen are counted", MAX_DISTINCT_VALUES),
+                    );
+                }
+                ui.horizontal(|ui| {
+                    ui.label("🔍");
+                    ui.add(egui::TextEdit::singleline(&mut self.search).hint_text("Search values").desired_width(180.0));
+                });
+                let needle = self.search.to_lowercase();
+                let shown: Vec<&(String, usize)> = result.values
+                    .iter()
+                    .filter(|(value, _)| needle.is_empty() || value.to_lowercase().contains(&needle))
+                    .collect();
+                ui.horizontal(|ui| {
+                    if ui.small_button("Select Shown").clicked() {
+                        self.selected.extend(shown.iter().map(|(value, _)| value.clone()));
+                    }
+                    if ui.small_button("Select None").clicked() {
+                        self.selected.clear();
+                    }
+                });
+                ui.separator();
+
+                let row_height = ui.text_style_height(&egui::TextStyle::Body) + 4.0;
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .auto_shrink([false, true])
+                    .show_rows(ui, row_height, shown.len(), |ui, range| {
+                        for (value, count) in &shown[range] {
+                            ui.horizontal(|ui| {
+                                let mut checked = self.selected.contains(value);
+                                let label = if value.is_empty() { "(empty)" } else { value.as_str() };
+                                if ui.checkbox(&mut checked, label).changed() {
+                                    if checked {
+                                        self.selected.insert(value.clone());
+                                    } else {
+                                        self.selected.remove(value);
+                                    }
+                                }
+                                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                                    ui.label(egui::RichText::new(count.to_string()).weak());
+                                });
+                            });
+                        }
+                    });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    let label = format!("Filter to {} Selected", self.selected.len());
+                    if ui.add_enabled(!self.selected.is_empty(), egui::Button::new(label)).clicked() {
+                        request = Some(DistinctRequest::Filter(self.column, self.selected.clone()));
+                    }
+                    if ui.button("Export...").on_hover_text("Save the values and counts as CSV").clicked() {
+                        request = Some(DistinctRequest::Export);
+                    }
+                });
+            });
+        request
+    }
+}
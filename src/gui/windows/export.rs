@@ -0,0 +1,240 @@
+use eframe::egui;
+use crate::backend::export::{ExportColumn, ExportFormat, ExportOptions, ExportPreset, DEFAULT_DESTINATION};
+use crate::backend::writer::QuoteStyle;
+use super::save_as::delimiter_combo;
+
+const MB: u64 = 1024 * 1024;
+/// Part size offered when splitting is first turned on
+const DEFAULT_PART_MB: u64 = 512;
+
+/// One row of the column list: whether it's exported, where it comes from, and its output name
+struct ColumnRow {
+    include: bool,
+    source: usize,
+    header: String,
+    name: String,
+}
+
+/// What the Export dialog asks the editor to do
+pub enum ExportRequest {
+    Export(ExportOptions),
+    SavePreset(ExportPreset),
+    DeletePreset(String),
+}
+
+/// The dialog every exporter goes through: format, then which columns, in
+/// what order and under which headers
+pub struct ExportWindow {
+    options: ExportOptions,
+    rows: Vec<ColumnRow>,
+    preset_name: String,
+    destination: String,
+}
+
+impl Default for ExportWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExportWindow {
+    pub fn new() -> Self {
+        Self {
+            options: ExportOptions::default(),
+            rows: Vec::new(),
+            preset_name: String::new(),
+            destination: DEFAULT_DESTINATION.to_string(),
+        }
+    }
+
+    /// Start from the displayed columns, in order, under their own headers.
+    /// The format and dialect picked last time are kept.
+    pub fn load(&mut self, headers: &[String], columns: &[usize]) {
+        self.rows = columns
+            .iter()
+            .map(|&c| {
+                let header = headers.get(c).cloned().unwrap_or_default();
+                ColumnRow { include: true, source: c, name: header.clone(), header }
+            })
+            .collect();
+    }
+
+    /// Take a preset's format and dialect, and include just its columns, first and in its order
+    fn apply_preset(&mut self, preset: &ExportPreset) {
+        self.options = preset.options.clone();
+        self.preset_name = preset.name.clone();
+        self.destination = preset.destination.clone();
+        let mut ordered = Vec::with_capacity(self.rows.len());
+        for (column, header) in preset.options.columns.iter().zip(&preset.source_headers) {
+            if let Some(i) = self.rows.iter().position(|r| &r.header == header) {
+                let mut row = self.rows.remove(i);
+                row.include = true;
+                row.name = column.name.clone();
+                ordered.push(row);
+            }
+        }
+        for mut row in self.rows.drain(..) {
+            row.include = false;
+            ordered.push(row);
+        }
+        self.rows = ordered;
+    }
+
+    /// The included columns, in order, under their output names
+    fn columns(&self) -> Vec<ExportColumn> {
+        self.rows
+            .iter()
+            .filter(|r| r.include)
+            .map(|r| ExportColumn { source: r.source, name: r.name.clone() })
+            .collect()
+    }
+
+    /// Returns what to do once the user exports or changes a preset
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool, presets: &[ExportPreset]) -> Option<ExportRequest> {
+        let mut confirmed = None;
+        egui::Window::new("Export")
+            .open(open)
+            .resizable(true)
+            .default_height(460.0)
+            .show(ctx, |ui| {
+                egui::Grid::new("export_grid").num_columns(2).show(ui, |ui| {
+                    if !presets.is_empty() {
+                        ui.label("Preset:");
+                        ui.horizontal(|ui| {
+                            let mut chosen = None;
+                            egui::ComboBox::from_id_salt("export_preset")
+                                .selected_text(if self.preset_name.is_empty() { "None" } else { self.preset_name.as_str() })
+                                .show_ui(ui, |ui| {
+                                    for preset in presets {
+                                        if ui.selectable_label(preset.name == self.preset_name, &preset.name).clicked() {
+                                            chosen = Some(preset);
+                                        }
+                                    }
+                                });
+                            if let Some(preset) = chosen {
+                                self.apply_preset(preset);
+                            }
+                            if presets.iter().any(|p| p.name == self.preset_name)
+                                && ui.small_button("🗑").on_hover_text("Delete this preset").clicked()
+                            {
+                                confirmed = Some(ExportRequest::DeletePreset(std::mem::take(&mut self.preset_name)));
+                            }
+                        });
+                        ui.end_row();
+                    }
+
+                    ui.label("Format:");
+                    egui::ComboBox::from_id_salt("export_format")
+                        .selected_text(self.options.format.name())
+                        .show_ui(ui, |ui| {
+                            for format in ExportFormat::all() {
+                                ui.selectable_value(&mut self.options.format, *format, format.name());
+                            }
+                        });
+                    ui.end_row();
+
+                    if matches!(self.options.format, ExportFormat::Json | ExportFormat::JsonLines) {
+                        ui.label("Split:");
+                        ui.horizontal(|ui| {
+                            let mut split = self.options.max_part_bytes.is_some();
+                            if ui.checkbox(&mut split, "into parts of at most").changed() {
+                                self.options.max_part_bytes = split.then_some(DEFAULT_PART_MB * MB);
+                            }
+                            if let Some(ref mut bytes) = self.options.max_part_bytes {
+                                let mut mb = *bytes / MB;
+                                ui.add(egui::DragValue::new(&mut mb).range(1..=u64::MAX / MB).suffix(" MB"));
+                                *bytes = mb * MB;
+                            }
+                        });
+                        ui.end_row();
+                    }
+
+                    if self.options.format == ExportFormat::Csv {
+                        ui.label("Delimiter:");
+                        delimiter_combo(ui, "export_delimiter", &mut self.options.csv.delimiter);
+                        ui.end_row();
+
+                        ui.label("Quoting:");
+                        egui::ComboBox::from_id_salt("export_quoting")
+                            .selected_text(self.options.csv.quote_style.name())
+                            .show_ui(ui, |ui| {
+                                for style in QuoteStyle::all() {
+                                    ui.selectable_value(&mut self.options.csv.quote_style, *style, style.name());
+                                }
+                            });
+                        ui.end_row();
+                    }
+                });
+                ui.separator();
+
+                let count = self.rows.iter().filter(|r| r.include).count();
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} of {} columns", count, self.rows.len()));
+                    if ui.small_button("All").clicked() {
+                        self.rows.iter_mut().for_each(|r| r.include = true);
+                    }
+                    if ui.small_button("None").clicked() {
+                        self.rows.iter_mut().for_each(|r| r.include = false);
+                    }
+                });
+                let mut swap = None;
+                egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    egui::Grid::new("export_columns").num_columns(3).striped(true).show(ui, |ui| {
+                        let last = self.rows.len().saturating_sub(1);
+                        for (i, row) in self.rows.iter_mut().enumerate() {
+                            ui.checkbox(&mut row.include, &row.header);
+                            ui.add_enabled(row.include, egui::TextEdit::singleline(&mut row.name).desired_width(160.0))
+                                .on_hover_text("Header in the exported file");
+                            ui.horizontal(|ui| {
+                                if ui.add_enabled(i > 0, egui::Button::new("⬆").small()).clicked() {
+                                    swap = Some((i - 1, i));
+                                }
+                                if ui.add_enabled(i < last, egui::Button::new("⬇").small()).clicked() {
+                                    swap = Some((i, i + 1));
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+                });
+                if let Some((a, b)) = swap {
+                    self.rows.swap(a, b);
+                }
+
+                ui.separator();
+                if ui.add_enabled(count > 0, egui::Button::new("Choose File & Export")).clicked() {
+                    let mut options = self.options.clone();
+                    options.columns = self.columns();
+                    confirmed = Some(ExportRequest::Export(options));
+                }
+
+                ui.collapsing("Save as Preset", |ui| {
+                    egui::Grid::new("export_preset_grid").num_columns(2).show(ui, |ui| {
+                        ui.label("Name:");
+                        ui.text_edit_singleline(&mut self.preset_name);
+                        ui.end_row();
+                        ui.label("Destination:");
+                        ui.text_edit_singleline(&mut self.destination)
+                            .on_hover_text("{dir}, {stem}, {date} and {ext} are filled in from the file being exported");
+                        ui.end_row();
+                    });
+                    let name = self.preset_name.trim();
+                    if ui.add_enabled(count > 0 && !name.is_empty(), egui::Button::new("Save Preset")).clicked() {
+                        let mut options = self.options.clone();
+                        options.columns = self.columns();
+                        confirmed = Some(ExportRequest::SavePreset(ExportPreset {
+                            name: name.to_string(),
+                            source_headers: self.rows.iter().filter(|r| r.include).map(|r| r.header.clone()).collect(),
+                            options,
+                            destination: self.destination.clone(),
+                        }));
+                    }
+                    ui.label(egui::RichText::new("Run it from File → Export Presets or with `csvit export --preset <name> <file>`").weak().small());
+                });
+            });
+        if matches!(confirmed, Some(ExportRequest::Export(_))) {
+            *open = false;
+        }
+        confirmed
+    }
+}
@@ -0,0 +1,136 @@
+use eframe::egui;
+use std::path::PathBuf;
+use crate::backend::file_info::{format_size, FileInfo};
+
+pub struct FileInfoWindow {
+    path: PathBuf,
+    rows: usize,
+    columns: usize,
+    /// How the open document reads the file (mapped, buffered, in memory)
+    access: Option<&'static str>,
+    /// None when the document hasn't been saved to disk yet
+    info: Option<Result<FileInfo, String>>,
+    checksum: Option<String>,
+    expected: String,
+}
+
+impl Default for FileInfoWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileInfoWindow {
+    pub fn new() -> Self {
+        Self {
+            path: PathBuf::new(),
+            rows: 0,
+            columns: 0,
+            access: None,
+            info: None,
+            checksum: None,
+            expected: String::new(),
+        }
+    }
+
+    pub fn load(&mut self, path: PathBuf, rows: usize, columns: usize, access: Option<&'static str>) {
+        self.info = path.exists().then(|| FileInfo::read(&path).map_err(|e| format!("{:#}", e)));
+        self.path = path;
+        self.rows = rows;
+        self.columns = columns;
+        self.access = access;
+        self.checksum = None;
+    }
+
+    pub fn set_checksum(&mut self, checksum: String) {
+        self.checksum = Some(checksum);
+    }
+
+    /// Returns the path to hash when the user asks for a checksum
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool, hashing: bool) -> Option<PathBuf> {
+        let mut requested = None;
+        egui::Window::new("File Info")
+            .open(open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("file_info_grid").num_columns(2).striped(true).show(ui, |ui| {
+                    ui.label("Path");
+                    ui.label(egui::RichText::new(self.path.to_string_lossy()).monospace());
+                    ui.end_row();
+                    ui.label("Rows");
+                    ui.label(self.rows.to_string());
+                    ui.end_row();
+                    ui.label("Columns");
+                    ui.label(self.columns.to_string());
+                    ui.end_row();
+                    match self.info {
+                        Some(Ok(ref info)) => {
+                            ui.label("Size");
+                            ui.label(format!("{} ({} bytes)", format_size(info.size), info.size));
+                            ui.end_row();
+                            if let Some(modified) = info.modified {
+                                ui.label("Modified");
+                                ui.label(chrono::DateTime::<chrono::Local>::from(modified).format("%Y-%m-%d %H:%M:%S").to_string());
+                                ui.end_row();
+                            }
+                            ui.label("Encoding");
+                            ui.label(info.encoding);
+                            ui.end_row();
+                            ui.label("Line endings");
+                            ui.label(info.line_ending.name());
+                            ui.end_row();
+                            if let Some(access) = self.access {
+                                ui.label("Access");
+                                ui.label(access);
+                                ui.end_row();
+                            }
+                        }
+                        Some(Err(ref e)) => {
+                            ui.label("Error");
+                            ui.colored_label(egui::Color32::from_rgb(220, 80, 60), e);
+                            ui.end_row();
+                        }
+                        None => {
+                            ui.label("File");
+                            ui.label(egui::RichText::new("Not saved to disk yet").weak());
+                            ui.end_row();
+                        }
+                    }
+                });
+
+                if !matches!(self.info, Some(Ok(_))) {
+                    return;
+                }
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.strong("SHA-256");
+                    if hashing {
+                        ui.spinner();
+                    } else if ui.button(if self.checksum.is_some() { "Recompute" } else { "Compute" }).clicked() {
+                        requested = Some(self.path.clone());
+                    }
+                });
+                if let Some(ref checksum) = self.checksum {
+                    ui.horizontal(|ui| {
+                        ui.label(egui::RichText::new(checksum).monospace().small());
+                        if ui.small_button("📋").on_hover_text("Copy").clicked() {
+                            ui.ctx().copy_text(checksum.clone());
+                        }
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Expected:");
+                        ui.add(egui::TextEdit::singleline(&mut self.expected).hint_text("paste a checksum to compare").desired_width(240.0));
+                        let expected = self.expected.trim();
+                        if !expected.is_empty() {
+                            if expected.eq_ignore_ascii_case(checksum) {
+                                ui.colored_label(egui::Color32::from_rgb(80, 200, 120), "✔ Match");
+                            } else {
+                                ui.colored_label(egui::Color32::from_rgb(220, 80, 60), "✘ Mismatch");
+                            }
+                        }
+                    });
+                }
+            });
+        requested
+    }
+}
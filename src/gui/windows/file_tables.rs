@@ -0,0 +1,55 @@
+use eframe::egui;
+use crate::backend::loader::TableSpan;
+
+/// The tables found in one file, separated by blank lines; the chosen one
+/// is shown while the others wait above and below it, untouched on save
+pub struct FileTablesWindow {
+    tables: Vec<TableSpan>,
+    selected: usize,
+}
+
+impl Default for FileTablesWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileTablesWindow {
+    pub fn new() -> Self {
+        Self { tables: Vec::new(), selected: 0 }
+    }
+
+    /// `current` is the table being shown, if any; see `show`
+    pub fn load(&mut self, tables: Vec<TableSpan>, current: Option<usize>) {
+        self.tables = tables;
+        self.selected = current.unwrap_or(0);
+    }
+
+    /// Returns the chosen table's index once the user opens it. One past the
+    /// last table stands for the whole file, which is what opens first.
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool) -> Option<usize> {
+        let mut confirmed = None;
+        egui::Window::new("Tables in File")
+            .open(open)
+            .resizable(true)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                ui.label(format!("{} tables separated by blank lines", self.tables.len()));
+                egui::ScrollArea::vertical().id_salt("file_table_list").max_height(240.0).show(ui, |ui| {
+                    for (i, table) in self.tables.iter().enumerate() {
+                        ui.radio_value(&mut self.selected, i, format!("Table {} — {} row(s), from line {}", i + 1, table.rows, table.preamble + 1));
+                        ui.add(egui::Label::new(egui::RichText::new(&table.header).monospace().weak()).truncate());
+                    }
+                    ui.radio_value(&mut self.selected, self.tables.len(), "Whole file as one table");
+                });
+                ui.add_space(6.0);
+                if ui.button("Open Table").clicked() {
+                    confirmed = Some(self.selected);
+                }
+            });
+        if confirmed.is_some() {
+            *open = false;
+        }
+        confirmed
+    }
+}
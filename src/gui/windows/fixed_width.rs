@@ -0,0 +1,147 @@
+use eframe::egui;
+use std::collections::BTreeSet;
+use crate::backend::import::{guess_fixed_width_boundaries, split_fixed_width};
+
+/// Lines shown under the ruler
+const PREVIEW_LINES: usize = 20;
+
+/// What the user chose in the fixed-width import dialog
+pub struct FixedWidthLayout {
+    pub boundaries: Vec<usize>,
+    pub names: Vec<String>,
+    pub header: bool,
+}
+
+pub struct FixedWidthWindow {
+    preview: Vec<String>,
+    boundaries: BTreeSet<usize>,
+    names: Vec<String>,
+    header: bool,
+}
+
+impl Default for FixedWidthWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FixedWidthWindow {
+    pub fn new() -> Self {
+        Self {
+            preview: Vec::new(),
+            boundaries: BTreeSet::new(),
+            names: Vec::new(),
+            header: true,
+        }
+    }
+
+    /// Load the start of a file and suggest boundaries from it
+    pub fn load(&mut self, text: &str) {
+        self.preview = text.lines().take(PREVIEW_LINES).map(|l| l.replace('\t', " ")).collect();
+        let lines: Vec<&str> = self.preview.iter().map(String::as_str).collect();
+        self.boundaries = guess_fixed_width_boundaries(&lines).into_iter().collect();
+        self.names.clear();
+    }
+
+    /// Returns the layout once the user confirms
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool) -> Option<FixedWidthLayout> {
+        let mut confirmed = None;
+        egui::Window::new("Import Fixed-Width")
+            .open(open)
+            .default_width(700.0)
+            .show(ctx, |ui| {
+                ui.label("Click on the ruler or the preview to add or remove a column boundary.");
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.header, "First line is a header");
+                    if ui.button("Guess").clicked() {
+                        let lines: Vec<&str> = self.preview.iter().map(String::as_str).collect();
+                        self.boundaries = guess_fixed_width_boundaries(&lines).into_iter().collect();
+                    }
+                    if ui.button("Clear").clicked() {
+                        self.boundaries.clear();
+                    }
+                });
+                ui.separator();
+
+                egui::ScrollArea::both().id_salt("fixed_width_preview").max_height(320.0).show(ui, |ui| {
+                    self.ruler(ui);
+                });
+
+                ui.separator();
+                let boundaries: Vec<usize> = self.boundaries.iter().copied().collect();
+                self.names.resize(boundaries.len() + 1, String::new());
+                let defaults = self.preview.first()
+                    .filter(|_| self.header)
+                    .map(|l| split_fixed_width(l, &boundaries))
+                    .unwrap_or_default();
+                ui.label("Column names:");
+                ui.horizontal_wrapped(|ui| {
+                    for (i, name) in self.names.iter_mut().enumerate() {
+                        let hint = defaults.get(i).filter(|d| !d.is_empty()).cloned()
+                            .unwrap_or_else(|| format!("Column {}", i + 1));
+                        ui.add(egui::TextEdit::singleline(name).hint_text(hint).desired_width(90.0));
+                    }
+                });
+
+                ui.add_space(6.0);
+                if ui.button("Import").clicked() {
+                    confirmed = Some(FixedWidthLayout {
+                        boundaries,
+                        names: self.names.clone(),
+                        header: self.header,
+                    });
+                }
+            });
+        if confirmed.is_some() {
+            *open = false;
+        }
+        confirmed
+    }
+
+    /// Character ruler and preview lines, with boundaries drawn as vertical lines
+    fn ruler(&mut self, ui: &mut egui::Ui) {
+        let font = egui::TextStyle::Monospace.resolve(ui.style());
+        let char_width = ui.fonts_mut(|f| f.glyph_width(&font, '0'));
+        let row_height = ui.text_style_height(&egui::TextStyle::Monospace) + 2.0;
+        let width = self.preview.iter().map(|l| l.chars().count()).max().unwrap_or(0) + 1;
+
+        let size = egui::vec2(width as f32 * char_width, (self.preview.len() + 1) as f32 * row_height);
+        let (rect, response) = ui.allocate_exact_size(size, egui::Sense::click());
+        let painter = ui.painter_at(rect);
+        let visuals = ui.visuals();
+
+        for pos in 0..width {
+            let x = rect.left() + pos as f32 * char_width;
+            let tick = if pos % 10 == 0 { row_height * 0.6 } else if pos % 5 == 0 { row_height * 0.4 } else { row_height * 0.2 };
+            painter.line_segment(
+                [egui::pos2(x, rect.top() + row_height - tick), egui::pos2(x, rect.top() + row_height)],
+                egui::Stroke::new(1.0, visuals.weak_text_color()),
+            );
+            if pos % 10 == 0 && pos > 0 {
+                painter.text(egui::pos2(x + 2.0, rect.top()), egui::Align2::LEFT_TOP, pos.to_string(),
+                    egui::FontId::monospace(font.size * 0.7), visuals.weak_text_color());
+            }
+        }
+        for (i, line) in self.preview.iter().enumerate() {
+            let y = rect.top() + (i + 1) as f32 * row_height;
+            let color = if i == 0 && self.header { visuals.strong_text_color() } else { visuals.text_color() };
+            painter.text(egui::pos2(rect.left(), y), egui::Align2::LEFT_TOP, line, font.clone(), color);
+        }
+        for &b in &self.boundaries {
+            let x = rect.left() + b as f32 * char_width;
+            painter.line_segment(
+                [egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())],
+                egui::Stroke::new(1.5, egui::Color32::from_rgb(220, 80, 60)),
+            );
+        }
+
+        if response.clicked()
+            && let Some(pos) = response.interact_pointer_pos()
+        {
+            let col = ((pos.x - rect.left()) / char_width).round() as usize;
+            if col > 0 && col < width && !self.boundaries.remove(&col) {
+                self.boundaries.insert(col);
+            }
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use eframe::egui;
+use crate::backend::geo::GeoReport;
+
+/// What the geo window asks the editor to do
+pub enum GeoRequest {
+    /// Validate this (latitude, longitude) column pair
+    Check(usize, usize),
+    /// Select this row in the table
+    Jump(usize),
+}
+
+/// Validates a latitude/longitude column pair and plots the valid points on
+/// a plain longitude/latitude canvas
+pub struct GeoWindow {
+    headers: Vec<String>,
+    lat: usize,
+    lon: usize,
+    report: Option<GeoReport>,
+}
+
+impl Default for GeoWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GeoWindow {
+    pub fn new() -> Self {
+        Self { headers: Vec::new(), lat: 0, lon: 1, report: None }
+    }
+
+    pub fn load(&mut self, headers: Vec<String>, pair: Option<(usize, usize)>) {
+        if let Some((lat, lon)) = pair {
+            self.lat = lat;
+            self.lon = lon;
+        }
+        self.headers = headers;
+        self.report = None;
+    }
+
+    /// The (latitude, longitude) columns picked
+    pub fn columns(&self) -> (usize, usize) {
+        (self.lat, self.lon)
+    }
+
+    pub fn set_report(&mut self, report: GeoReport) {
+        self.report = Some(report);
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool, checking: bool) -> Option<GeoRequest> {
+        let mut request = None;
+        egui::Window::new("Geo Check")
+            .open(open)
+            .resizable(true)
+            .default_size([640.0, 560.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    for (label, id, col) in [("Latitude:", "geo_lat", &mut self.lat), ("Longitude:", "geo_lon", &mut self.lon)] {
+                        ui.label(label);
+                        egui::ComboBox::from_id_salt(id)
+                            .selected_text(self.headers.get(*col).map_or("", String::as_str))
+                            .show_ui(ui, |ui| {
+                                for (i, header) in self.headers.iter().enumerate() {
+                                    ui.selectable_value(col, i, header);
+                                }
+                            });
+                    }
+                    if ui.add_enabled(!checking && self.lat != self.lon, egui::Button::new("Check")).clicked() {
+                        request = Some(GeoRequest::Check(self.lat, self.lon));
+                    }
+                    if checking {
+                        ui.spinner();
+                    }
+                });
+                let Some(ref report) = self.report else {
+                    return;
+                };
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} valid", report.valid));
+                    ui.separator();
+                    ui.label(format!("{} empty", report.empty));
+                    ui.separator();
+                    let color = if report.issue_count > 0 { ui.visuals().warn_fg_color } else { ui.visuals().text_color() };
+                    ui.colored_label(color, format!("{} with problems", report.issue_count));
+                });
+                if !report.issues.is_empty() {
+                    egui::CollapsingHeader::new(format!("Problem rows ({})", report.issue_count))
+                        .default_open(report.valid == 0)
+                        .show(ui, |ui| {
+                            egui::ScrollArea::vertical().id_salt("geo_issues").max_height(140.0).show(ui, |ui| {
+                                for &(row, issue) in &report.issues {
+                                    ui.horizontal(|ui| {
+                                        if ui.link(format!("Row {}", row)).clicked() {
+                                            request = Some(GeoRequest::Jump(row));
+                                        }
+                                        ui.label(issue.name());
+                                    });
+                                }
+                                if report.issue_count > report.issues.len() {
+                                    ui.label(egui::RichText::new(format!("… and {} more", report.issue_count - report.issues.len())).weak());
+                                }
+                            });
+                        });
+                }
+                if report.points.len() < report.valid {
+                    ui.label(egui::RichText::new(format!("Map shows the first {} points", report.points.len())).weak().small());
+                }
+                egui_plot::Plot::new("geo_map")
+                    .data_aspect(1.0)
+                    .include_x(-180.0)
+                    .include_x(180.0)
+                    .include_y(-90.0)
+                    .include_y(90.0)
+                    .x_axis_label("Longitude")
+                    .y_axis_label("Latitude")
+                    .show(ui, |plot_ui| {
+                        // The edges of the world, so stray points stand out
+                        let frame = vec![[-180.0, -90.0], [180.0, -90.0], [180.0, 90.0], [-180.0, 90.0], [-180.0, -90.0]];
+                        plot_ui.line(egui_plot::Line::new("Bounds", egui_plot::PlotPoints::new(frame)).color(egui::Color32::GRAY));
+                        plot_ui.line(egui_plot::Line::new("Equator", egui_plot::PlotPoints::new(vec![[-180.0, 0.0], [180.0, 0.0]])).color(egui::Color32::DARK_GRAY));
+                        plot_ui.points(egui_plot::Points::new("Points", egui_plot::PlotPoints::new(report.points.clone())).radius(2.0));
+                    });
+            });
+        request
+    }
+}
@@ -0,0 +1,185 @@
+use eframe::egui;
+use crate::backend::aggregate::{Aggregate, GroupBySpec, GroupSummary, MAX_GROUPS};
+
+/// Summary rows shown in the window; the rest are in the export
+const PREVIEW_GROUPS: usize = 1_000;
+
+/// What the Group By window asks the editor to do
+pub enum GroupByRequest {
+    Run(GroupBySpec),
+    /// Save the summary as CSV
+    Export,
+    /// Open the summary as a document of its own
+    Open,
+}
+
+/// Builds a group-by summary: key columns, then aggregates over other columns
+pub struct GroupByWindow {
+    headers: Vec<String>,
+    spec: GroupBySpec,
+    summary: Option<GroupSummary>,
+    /// Open as Document was clicked while the document has unsaved edits
+    confirm_open: bool,
+}
+
+impl Default for GroupByWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GroupByWindow {
+    pub fn new() -> Self {
+        Self { headers: Vec::new(), spec: GroupBySpec::default(), summary: None, confirm_open: false }
+    }
+
+    /// Start over for a document with these headers; the last setup is kept
+    /// if the columns are the same
+    pub fn load(&mut self, headers: Vec<String>) {
+        if headers != self.headers {
+            self.spec = GroupBySpec { keys: Vec::new(), aggregates: vec![(0, Aggregate::Count)] };
+            self.headers = headers;
+        }
+        self.summary = None;
+        self.confirm_open = false;
+    }
+
+    pub fn set_summary(&mut self, summary: GroupSummary) {
+        self.summary = Some(summary);
+        self.confirm_open = false;
+    }
+
+    pub fn summary(&self) -> Option<&GroupSummary> {
+        self.summary.as_ref()
+    }
+
+    /// `unsaved` is whether the open document has edits that opening the
+    /// summary in its place would lose
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool, running: bool, unsaved: bool) -> Option<GroupByRequest> {
+        let mut request = None;
+        egui::Window::new("Group By")
+            .open(open)
+            .resizable(true)
+            .default_size([560.0, 480.0])
+            .show(ctx, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Group by:");
+                    let mut remove = None;
+                    for (i, &key) in self.spec.keys.iter().enumerate() {
+                        if ui.button(format!("{}  ✖", self.headers.get(key).map_or("", String::as_str))).clicked() {
+                            remove = Some(i);
+                        }
+                    }
+                    if let Some(i) = remove {
+                        self.spec.keys.remove(i);
+                    }
+                    egui::ComboBox::from_id_salt("group_by_add_key")
+                        .selected_text("＋ Column")
+                        .show_ui(ui, |ui| {
+                            for (c, header) in self.headers.iter().enumerate() {
+                                if !self.spec.keys.contains(&c) && ui.selectable_label(false, header).clicked() {
+                                    self.spec.keys.push(c);
+                                }
+                            }
+                        });
+                });
+
+                ui.label("Aggregates:");
+                let mut remove = None;
+                for (i, (column, aggregate)) in self.spec.aggregates.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        egui::ComboBox::from_id_salt(("group_by_agg", i))
+                            .selected_text(aggregate.name())
+                            .show_ui(ui, |ui| {
+                                for a in Aggregate::ALL {
+                                    ui.selectable_value(aggregate, a, a.name());
+                                }
+                            });
+                        // A plain count covers the group, whatever the column
+                        if *aggregate != Aggregate::Count {
+                            ui.label("of");
+                            egui::ComboBox::from_id_salt(("group_by_col", i))
+                                .selected_text(self.headers.get(*column).map_or("", String::as_str))
+                                .show_ui(ui, |ui| {
+                                    for (c, header) in self.headers.iter().enumerate() {
+                                        ui.selectable_value(column, c, header);
+                                    }
+                                });
+                        }
+                        if ui.small_button("✖").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                }
+                if let Some(i) = remove {
+                    self.spec.aggregates.remove(i);
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("＋ Aggregate").clicked() {
+                        self.spec.aggregates.push((0, Aggregate::Sum));
+                    }
+                    let ready = !self.spec.keys.is_empty() && !self.spec.aggregates.is_empty();
+                    if ui.add_enabled(ready && !running, egui::Button::new("Summarize")).clicked() {
+                        request = Some(GroupByRequest::Run(self.spec.clone()));
+                    }
+                    if running {
+                        ui.spinner();
+                    }
+                });
+
+                let Some(ref summary) = self.summary else {
+                    return;
+                };
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label(format!("{} group(s)", summary.rows.len()));
+                    if ui.button("Export...").clicked() {
+                        request = Some(GroupByRequest::Export);
+                    }
+                    if ui.button("Open as Document").on_hover_text("Replaces the open file, like a sorted copy").clicked() {
+                        if unsaved {
+                            self.confirm_open = true;
+                        } else {
+                            request = Some(GroupByRequest::Open);
+                        }
+                    }
+                });
+                if summary.skipped_rows > 0 {
+                    ui.colored_label(ui.visuals().warn_fg_color, format!(
+                        "Stopped at {} groups; {} row(s) with other keys were left out",
+                        MAX_GROUPS, summary.skipped_rows
+                    ));
+                }
+                if self.confirm_open {
+                    ui.colored_label(ui.visuals().warn_fg_color, "Opening the summary closes this document and its unsaved edits are lost.");
+                    ui.horizontal(|ui| {
+                        if ui.button("Open Anyway").clicked() {
+                            request = Some(GroupByRequest::Open);
+                            self.confirm_open = false;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            self.confirm_open = false;
+                        }
+                    });
+                }
+                egui::ScrollArea::both().auto_shrink([false, false]).show(ui, |ui| {
+                    egui::Grid::new("group_by_summary").striped(true).show(ui, |ui| {
+                        for header in &summary.headers {
+                            ui.strong(header);
+                        }
+                        ui.end_row();
+                        for row in summary.rows.iter().take(PREVIEW_GROUPS) {
+                            for value in row {
+                                ui.label(value);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                    if summary.rows.len() > PREVIEW_GROUPS {
+                        ui.label(egui::RichText::new(format!("Showing the first {} groups; export for all", PREVIEW_GROUPS)).weak());
+                    }
+                });
+            });
+        request
+    }
+}
@@ -0,0 +1,81 @@
+use eframe::egui;
+use crate::backend::import::HtmlTable;
+
+/// Rows shown in the preview of the selected table
+const PREVIEW_ROWS: usize = 5;
+
+pub struct HtmlTablePickerWindow {
+    tables: Vec<HtmlTable>,
+    selected: usize,
+}
+
+impl Default for HtmlTablePickerWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HtmlTablePickerWindow {
+    pub fn new() -> Self {
+        Self {
+            tables: Vec::new(),
+            selected: 0,
+        }
+    }
+
+    pub fn load(&mut self, tables: Vec<HtmlTable>) {
+        self.tables = tables;
+        self.selected = 0;
+    }
+
+    /// Returns the chosen table once the user confirms
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool) -> Option<HtmlTable> {
+        let mut confirmed = None;
+        egui::Window::new("Import HTML Table")
+            .open(open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.label(format!("{} tables found", self.tables.len()));
+                egui::ScrollArea::vertical().id_salt("html_table_list").max_height(200.0).show(ui, |ui| {
+                    for (i, table) in self.tables.iter().enumerate() {
+                        let label = format!(
+                            "Table {}{} — {} × {}",
+                            i + 1,
+                            table.caption.as_ref().map(|c| format!(": {}", c)).unwrap_or_default(),
+                            table.rows.len(),
+                            table.width()
+                        );
+                        ui.radio_value(&mut self.selected, i, label);
+                    }
+                });
+
+                if let Some(table) = self.tables.get(self.selected) {
+                    ui.separator();
+                    egui::Grid::new("html_table_preview").striped(true).show(ui, |ui| {
+                        for header in &table.headers {
+                            ui.label(egui::RichText::new(header).strong());
+                        }
+                        if !table.headers.is_empty() {
+                            ui.end_row();
+                        }
+                        for row in table.rows.iter().take(PREVIEW_ROWS) {
+                            for cell in row {
+                                ui.label(egui::RichText::new(cell).monospace());
+                            }
+                            ui.end_row();
+                        }
+                    });
+                }
+
+                ui.add_space(6.0);
+                if ui.button("Import").clicked() && self.selected < self.tables.len() {
+                    confirmed = Some(self.tables.swap_remove(self.selected));
+                }
+            });
+        if confirmed.is_some() {
+            *open = false;
+        }
+        confirmed
+    }
+}
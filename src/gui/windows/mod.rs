@@ -1,4 +1,34 @@
 pub mod settings;
+pub mod save_as;
+pub mod paste_special;
+pub mod column_mapping;
+pub mod split;
+pub mod columns;
+pub mod schema;
+pub mod script;
+pub mod pandas;
+pub mod html_tables;
+pub mod file_tables;
+pub mod fixed_width;
+pub mod column_format;
+pub mod normalize;
+pub mod anonymize;
+pub mod date_format;
+pub mod units;
+pub mod regex_extract;
+pub mod geo;
+pub mod distinct;
+pub mod group_by;
+pub mod rolling;
+pub mod combine;
+pub mod scatter_matrix;
+pub mod row_rules;
+pub mod row_colors;
+pub mod file_info;
+pub mod snapshots;
+pub mod cell_inspector;
+pub mod export;
+pub mod pipeline;
 
 pub trait Window {
     fn name(&self) -> &'static str;
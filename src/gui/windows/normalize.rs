@@ -0,0 +1,117 @@
+use eframe::egui;
+use std::collections::HashMap;
+use crate::backend::normalize::ValueCluster;
+
+/// What the user asked for in the normalization wizard
+pub enum NormalizeAction {
+    /// Cluster again with a different edit distance
+    Recluster(usize),
+    /// Replace each mapped value with its canonical value
+    Apply(HashMap<String, String>),
+}
+
+pub struct NormalizeWindow {
+    column: usize,
+    header: String,
+    clusters: Vec<ValueCluster>,
+    /// Whether each cluster is included in the mapping
+    merge: Vec<bool>,
+    max_distance: usize,
+}
+
+impl Default for NormalizeWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NormalizeWindow {
+    pub fn new() -> Self {
+        Self {
+            column: 0,
+            header: String::new(),
+            clusters: Vec::new(),
+            merge: Vec::new(),
+            max_distance: 2,
+        }
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    pub fn max_distance(&self) -> usize {
+        self.max_distance
+    }
+
+    pub fn load(&mut self, column: usize, header: String, clusters: Vec<ValueCluster>) {
+        self.column = column;
+        self.header = header;
+        self.merge = vec![true; clusters.len()];
+        self.clusters = clusters;
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool, busy: bool) -> Option<NormalizeAction> {
+        let mut action = None;
+        egui::Window::new(format!("Normalize Values: {}", self.header))
+            .id(egui::Id::new("normalize_window"))
+            .open(open)
+            .default_width(460.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Max typo distance:");
+                    ui.add(egui::DragValue::new(&mut self.max_distance).range(0..=5));
+                    if ui.add_enabled(!busy, egui::Button::new("Re-cluster")).clicked() {
+                        action = Some(NormalizeAction::Recluster(self.max_distance));
+                    }
+                    if busy {
+                        ui.spinner();
+                    }
+                });
+                ui.separator();
+
+                if self.clusters.is_empty() {
+                    ui.label(if busy { "Scanning values..." } else { "No near-duplicate values found." });
+                    return;
+                }
+                ui.label(format!("{} cluster(s). Click a spelling to make it the canonical value, or type one.", self.clusters.len()));
+                egui::ScrollArea::vertical().id_salt("normalize_clusters").max_height(360.0).show(ui, |ui| {
+                    for (i, cluster) in self.clusters.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut self.merge[i], "");
+                            ui.label("→");
+                            ui.add(egui::TextEdit::singleline(&mut cluster.canonical).desired_width(160.0));
+                            ui.label(egui::RichText::new(format!("{} rows", cluster.total())).weak());
+                        });
+                        ui.indent(("normalize_cluster", i), |ui| {
+                            ui.horizontal_wrapped(|ui| {
+                                for (value, count) in &cluster.values {
+                                    let selected = *value == cluster.canonical;
+                                    if ui.selectable_label(selected, format!("{} ({})", value, count)).clicked() {
+                                        cluster.canonical = value.clone();
+                                    }
+                                }
+                            });
+                        });
+                        ui.separator();
+                    }
+                });
+
+                let merging = self.merge.iter().filter(|m| **m).count();
+                if ui.add_enabled(!busy && merging > 0, egui::Button::new(format!("Apply {} cluster(s)", merging))).clicked() {
+                    let mapping = self.clusters.iter()
+                        .zip(&self.merge)
+                        .filter(|(_, merge)| **merge)
+                        .flat_map(|(cluster, _)| {
+                            cluster.values.iter().map(move |(value, _)| (value.clone(), cluster.canonical.clone()))
+                        })
+                        .collect();
+                    action = Some(NormalizeAction::Apply(mapping));
+                }
+            });
+        if matches!(action, Some(NormalizeAction::Apply(_))) {
+            *open = false;
+        }
+        action
+    }
+}
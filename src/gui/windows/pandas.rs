@@ -0,0 +1,66 @@
+use eframe::egui;
+use std::path::PathBuf;
+use crate::backend::handoff;
+
+pub struct PandasWindow {
+    csv_path: Option<PathBuf>,
+    snippet: String,
+}
+
+impl Default for PandasWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PandasWindow {
+    pub fn new() -> Self {
+        Self {
+            csv_path: None,
+            snippet: String::new(),
+        }
+    }
+
+    /// Show the snippet for a freshly written handoff file
+    pub fn load(&mut self, csv_path: PathBuf) {
+        self.snippet = handoff::pandas_snippet(&csv_path);
+        self.csv_path = Some(csv_path);
+    }
+
+    /// Returns a status message after copying or launching jupyter
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool) -> Option<String> {
+        let mut message = None;
+        egui::Window::new("Open in pandas")
+            .open(open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                let Some(ref csv_path) = self.csv_path else {
+                    return;
+                };
+                ui.label(format!("Data written to {}", csv_path.display()));
+                ui.add_space(4.0);
+                let mut snippet = self.snippet.as_str();
+                ui.add(egui::TextEdit::multiline(&mut snippet).code_editor().desired_width(440.0));
+
+                ui.horizontal(|ui| {
+                    if ui.button("📋 Copy Snippet").clicked() {
+                        ui.ctx().copy_text(self.snippet.clone());
+                        message = Some("Python snippet copied".to_string());
+                    }
+                    if ui.button("Open in Jupyter").clicked() {
+                        let notebook = csv_path.with_extension("ipynb");
+                        message = Some(
+                            match handoff::write_notebook(&notebook, &self.snippet)
+                                .and_then(|_| handoff::launch_jupyter(&notebook))
+                            {
+                                Ok(()) => format!("Launched jupyter with {}", notebook.display()),
+                                Err(e) => format!("{:#}", e),
+                            },
+                        );
+                    }
+                });
+            });
+        message
+    }
+}
@@ -0,0 +1,70 @@
+use eframe::egui;
+use crate::backend::clipboard::{self, PasteMode};
+
+/// Rows shown in the clipboard preview
+const PREVIEW_ROWS: usize = 5;
+
+pub struct PasteSpecialWindow {
+    mode: PasteMode,
+    rows: Vec<Vec<String>>,
+}
+
+impl Default for PasteSpecialWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PasteSpecialWindow {
+    pub fn new() -> Self {
+        Self {
+            mode: PasteMode::Values,
+            rows: Vec::new(),
+        }
+    }
+
+    /// Load clipboard text to paste. Returns false if there is nothing to paste.
+    pub fn load(&mut self, text: &str) -> bool {
+        self.rows = clipboard::parse_tsv(text);
+        !self.rows.is_empty()
+    }
+
+    /// Returns the chosen mode and the parsed rows once the user confirms
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool) -> Option<(PasteMode, Vec<Vec<String>>)> {
+        let mut confirmed = None;
+        egui::Window::new("Paste Special")
+            .open(open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                let width = self.rows.iter().map(|r| r.len()).max().unwrap_or(0);
+                ui.label(format!("Clipboard: {} row(s) × {} column(s)", self.rows.len(), width));
+
+                egui::Grid::new("paste_special_preview").striped(true).show(ui, |ui| {
+                    for row in self.rows.iter().take(PREVIEW_ROWS) {
+                        for cell in row {
+                            ui.label(egui::RichText::new(cell).monospace());
+                        }
+                        ui.end_row();
+                    }
+                });
+                if self.rows.len() > PREVIEW_ROWS {
+                    ui.label(egui::RichText::new(format!("… {} more", self.rows.len() - PREVIEW_ROWS)).weak().small());
+                }
+
+                ui.separator();
+                for mode in PasteMode::all() {
+                    ui.radio_value(&mut self.mode, *mode, mode.name());
+                }
+
+                ui.add_space(6.0);
+                if ui.button("Paste").clicked() {
+                    confirmed = Some((self.mode, std::mem::take(&mut self.rows)));
+                }
+            });
+        if confirmed.is_some() {
+            *open = false;
+        }
+        confirmed
+    }
+}
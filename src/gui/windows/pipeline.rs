@@ -0,0 +1,227 @@
+use eframe::egui;
+use crate::backend::export::{ExportFormat, ExportOptions, ExportPreset};
+use crate::backend::pipeline::{ColumnTransform, Pipeline, PipelineStep, DEFAULT_DESTINATION};
+use crate::backend::sort::SortOrder;
+
+/// What the pipeline builder asks the editor to do
+pub enum PipelineRequest {
+    /// Run these steps on the first rows and hand back the result with `set_preview`
+    Preview(Vec<PipelineStep>),
+    /// Run the whole pipeline on the open document
+    Run(Pipeline),
+}
+
+/// Composes filter, transform and sort steps ahead of an export, with a
+/// preview of the result; pipelines are saved to and opened from JSON files
+pub struct PipelineWindow {
+    pipeline: Pipeline,
+    headers: Vec<String>,
+    preview: Option<Result<Vec<Vec<String>>, String>>,
+}
+
+impl Default for PipelineWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PipelineWindow {
+    pub fn new() -> Self {
+        Self {
+            pipeline: Pipeline {
+                steps: Vec::new(),
+                export: ExportPreset {
+                    name: String::new(),
+                    options: ExportOptions { format: ExportFormat::Csv, ..Default::default() },
+                    source_headers: Vec::new(),
+                    destination: DEFAULT_DESTINATION.to_string(),
+                },
+            },
+            headers: Vec::new(),
+            preview: None,
+        }
+    }
+
+    /// Steps are kept between openings; only the columns to offer change
+    pub fn load(&mut self, headers: Vec<String>) {
+        self.headers = headers;
+        self.preview = None;
+    }
+
+    pub fn set_preview(&mut self, preview: Result<Vec<Vec<String>>, String>) {
+        self.preview = Some(preview);
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool) -> Option<PipelineRequest> {
+        let mut request = None;
+        egui::Window::new("Pipeline")
+            .open(open)
+            .resizable(true)
+            .default_size([620.0, 520.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if ui.button("📂 Open...").clicked()
+                        && let Some(path) = rfd::FileDialog::new().add_filter("Pipeline", &["json"]).pick_file()
+                    {
+                        match Pipeline::load(&path) {
+                            Ok(pipeline) => {
+                                self.pipeline = pipeline;
+                                self.preview = None;
+                            }
+                            Err(e) => self.preview = Some(Err(format!("{:#}", e))),
+                        }
+                    }
+                    if ui.button("💾 Save...").clicked()
+                        && let Some(path) = rfd::FileDialog::new().add_filter("Pipeline", &["json"]).save_file()
+                        && let Err(e) = self.pipeline.save(&path)
+                    {
+                        self.preview = Some(Err(format!("{:#}", e)));
+                    }
+                });
+                ui.separator();
+
+                self.steps_ui(ui);
+                ui.horizontal(|ui| {
+                    let first = self.headers.first().cloned().unwrap_or_default();
+                    if ui.button("➕ Filter").clicked() {
+                        self.pipeline.steps.push(PipelineStep::Filter { condition: String::new() });
+                    }
+                    if ui.button("➕ Transform").clicked() {
+                        self.pipeline.steps.push(PipelineStep::Transform { column: first.clone(), transform: ColumnTransform::Trim });
+                    }
+                    if ui.button("➕ Sort").clicked() {
+                        self.pipeline.steps.push(PipelineStep::Sort { column: first, order: SortOrder::Ascending });
+                    }
+                });
+                ui.separator();
+
+                let export = &mut self.pipeline.export;
+                egui::Grid::new("pipeline_export").num_columns(2).show(ui, |ui| {
+                    ui.label("Export as:");
+                    egui::ComboBox::from_id_salt("pipeline_format")
+                        .selected_text(export.options.format.name())
+                        .show_ui(ui, |ui| {
+                            for format in ExportFormat::all() {
+                                ui.selectable_value(&mut export.options.format, *format, format.name());
+                            }
+                        });
+                    ui.end_row();
+                    ui.label("Destination:");
+                    ui.text_edit_singleline(&mut export.destination)
+                        .on_hover_text("{dir}, {stem}, {date} and {ext} are filled in from the input file");
+                    ui.end_row();
+                });
+                ui.horizontal(|ui| {
+                    if ui.button("👁 Preview").clicked() {
+                        request = Some(PipelineRequest::Preview(self.pipeline.steps.clone()));
+                    }
+                    if ui.button("▶ Run").clicked() {
+                        request = Some(PipelineRequest::Run(self.pipeline.clone()));
+                    }
+                });
+                ui.label(egui::RichText::new("Re-run a saved pipeline with `csvit pipeline <pipeline.json> <file>`").weak().small());
+
+                match self.preview {
+                    Some(Ok(ref rows)) => {
+                        ui.separator();
+                        ui.label(format!("First {} rows of the result", rows.len()));
+                        egui::ScrollArea::both().max_height(220.0).show(ui, |ui| {
+                            egui::Grid::new("pipeline_preview").striped(true).show(ui, |ui| {
+                                for header in &self.headers {
+                                    ui.strong(header);
+                                }
+                                ui.end_row();
+                                for row in rows {
+                                    for value in row {
+                                        ui.label(value);
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                        });
+                    }
+                    Some(Err(ref e)) => {
+                        ui.separator();
+                        ui.colored_label(ui.visuals().error_fg_color, e);
+                    }
+                    None => {}
+                }
+            });
+        request
+    }
+
+    fn steps_ui(&mut self, ui: &mut egui::Ui) {
+        if self.pipeline.steps.is_empty() {
+            ui.label(egui::RichText::new("No steps yet: every row is exported as-is").weak());
+        }
+        let headers = &self.headers;
+        let last = self.pipeline.steps.len().saturating_sub(1);
+        let mut swap = None;
+        let mut remove = None;
+        egui::Grid::new("pipeline_steps").num_columns(3).striped(true).show(ui, |ui| {
+            for (i, step) in self.pipeline.steps.iter_mut().enumerate() {
+                ui.label(format!("{}. {}", i + 1, step.name()));
+                ui.horizontal(|ui| match step {
+                    PipelineStep::Filter { condition } => {
+                        ui.add(egui::TextEdit::singleline(condition).hint_text("status = 'open' AND total > 10").desired_width(300.0));
+                    }
+                    PipelineStep::Transform { column, transform } => {
+                        column_combo(ui, ("pipeline_column", i), headers, column);
+                        egui::ComboBox::from_id_salt(("pipeline_transform", i))
+                            .selected_text(transform.name())
+                            .show_ui(ui, |ui| {
+                                for option in [
+                                    ColumnTransform::Trim,
+                                    ColumnTransform::Uppercase,
+                                    ColumnTransform::Lowercase,
+                                    ColumnTransform::Replace { find: String::new(), replace: String::new() },
+                                ] {
+                                    let selected = option.name() == transform.name();
+                                    if ui.selectable_label(selected, option.name()).clicked() && !selected {
+                                        *transform = option;
+                                    }
+                                }
+                            });
+                        if let ColumnTransform::Replace { find, replace } = transform {
+                            ui.add(egui::TextEdit::singleline(find).hint_text("find").desired_width(80.0));
+                            ui.add(egui::TextEdit::singleline(replace).hint_text("replace with").desired_width(80.0));
+                        }
+                    }
+                    PipelineStep::Sort { column, order } => {
+                        column_combo(ui, ("pipeline_column", i), headers, column);
+                        ui.selectable_value(order, SortOrder::Ascending, "Ascending");
+                        ui.selectable_value(order, SortOrder::Descending, "Descending");
+                    }
+                });
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(i > 0, egui::Button::new("⬆").small()).clicked() {
+                        swap = Some((i - 1, i));
+                    }
+                    if ui.add_enabled(i < last, egui::Button::new("⬇").small()).clicked() {
+                        swap = Some((i, i + 1));
+                    }
+                    if ui.small_button("🗑").clicked() {
+                        remove = Some(i);
+                    }
+                });
+                ui.end_row();
+            }
+        });
+        if let Some((a, b)) = swap {
+            self.pipeline.steps.swap(a, b);
+        }
+        if let Some(i) = remove {
+            self.pipeline.steps.remove(i);
+        }
+    }
+}
+
+fn column_combo(ui: &mut egui::Ui, id: impl std::hash::Hash, headers: &[String], column: &mut String) {
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(column.as_str())
+        .show_ui(ui, |ui| {
+            for header in headers {
+                ui.selectable_value(column, header.clone(), header);
+            }
+        });
+}
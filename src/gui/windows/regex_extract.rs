@@ -0,0 +1,106 @@
+use eframe::egui;
+use regex::Regex;
+use crate::backend::extract::{extract_groups, group_headers};
+
+/// Sample values shown in the preview
+const PREVIEW_VALUES: usize = 10;
+
+/// Pulls new columns out of a column with a regex's capture groups,
+/// previewed on the first values
+pub struct RegexExtractWindow {
+    column: usize,
+    header: String,
+    samples: Vec<String>,
+    pattern: String,
+}
+
+impl Default for RegexExtractWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RegexExtractWindow {
+    pub fn new() -> Self {
+        Self { column: 0, header: String::new(), samples: Vec::new(), pattern: String::new() }
+    }
+
+    /// Extract from `column`, previewing on `samples` (its first values).
+    /// The last pattern is kept.
+    pub fn load(&mut self, column: usize, header: String, samples: Vec<String>) {
+        self.column = column;
+        self.header = header;
+        self.samples = samples;
+    }
+
+    /// Returns the column and compiled pattern once the user applies it
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool) -> Option<(usize, Regex)> {
+        let mut confirmed = None;
+        egui::Window::new(format!("Extract with Regex: {}", self.header))
+            .id(egui::Id::new("regex_extract_window"))
+            .open(open)
+            .resizable(true)
+            .default_width(480.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Pattern:");
+                    ui.add(egui::TextEdit::singleline(&mut self.pattern)
+                        .hint_text(r"@(?P<domain>.+)$")
+                        .font(egui::TextStyle::Monospace)
+                        .desired_width(f32::INFINITY));
+                });
+                ui.label(egui::RichText::new("Each capture group becomes a new column; named groups (?P<name>…) name theirs.").weak().small());
+                ui.separator();
+
+                let regex = match Regex::new(&self.pattern) {
+                    Ok(_) if self.pattern.is_empty() => {
+                        ui.label(egui::RichText::new("Enter a pattern with at least one capture group").weak());
+                        return;
+                    }
+                    Ok(regex) if regex.captures_len() < 2 => {
+                        ui.label(egui::RichText::new("The pattern has no capture groups; wrap a part in ( )").weak());
+                        return;
+                    }
+                    Ok(regex) => regex,
+                    Err(e) => {
+                        ui.colored_label(ui.visuals().error_fg_color, e.to_string());
+                        return;
+                    }
+                };
+
+                let headers = group_headers(&regex, &self.header);
+                let matched = self.samples.iter().filter(|v| regex.is_match(v)).count();
+                ui.label(format!("{} of {} sampled values match", matched, self.samples.len()));
+                egui::ScrollArea::both().max_height(240.0).show(ui, |ui| {
+                    egui::Grid::new("regex_preview").striped(true).show(ui, |ui| {
+                        ui.strong(&self.header);
+                        for header in &headers {
+                            ui.strong(header);
+                        }
+                        ui.end_row();
+                        for value in self.samples.iter().take(PREVIEW_VALUES) {
+                            ui.monospace(value);
+                            match extract_groups(&regex, value) {
+                                Some(groups) => groups.iter().for_each(|g| {
+                                    ui.monospace(g);
+                                }),
+                                None => {
+                                    ui.label(egui::RichText::new("no match").weak());
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+
+                ui.add_space(6.0);
+                if ui.button(format!("Insert {} Column(s)", headers.len())).clicked() {
+                    confirmed = Some((self.column, regex));
+                }
+            });
+        if confirmed.is_some() {
+            *open = false;
+        }
+        confirmed
+    }
+}
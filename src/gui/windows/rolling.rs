@@ -0,0 +1,93 @@
+use eframe::egui;
+use crate::backend::aggregate::Rolling;
+
+/// A rolling calculation to add as a new column
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RollingRequest {
+    pub column: usize,
+    pub function: Rolling,
+    pub window: usize,
+    /// Column giving the order, e.g. a timestamp; None for row order
+    pub order_by: Option<usize>,
+}
+
+/// Sets up a rolling mean/sum/min/max or cumulative sum over a numeric column
+pub struct RollingWindow {
+    headers: Vec<String>,
+    request: RollingRequest,
+}
+
+impl Default for RollingWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RollingWindow {
+    pub fn new() -> Self {
+        Self {
+            headers: Vec::new(),
+            request: RollingRequest { column: 0, function: Rolling::Mean, window: 7, order_by: None },
+        }
+    }
+
+    /// Calculate over `column`; the function, window and order are kept
+    pub fn load(&mut self, column: usize, headers: Vec<String>) {
+        if self.request.order_by.is_some_and(|c| c >= headers.len()) {
+            self.request.order_by = None;
+        }
+        self.request.column = column;
+        self.headers = headers;
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool) -> Option<RollingRequest> {
+        let mut confirmed = None;
+        let header = |c: usize| self.headers.get(c).map_or("", String::as_str);
+        egui::Window::new(format!("Rolling Calculation: {}", header(self.request.column)))
+            .id(egui::Id::new("rolling_window"))
+            .open(open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("rolling_options").num_columns(2).show(ui, |ui| {
+                    ui.label("Function:");
+                    egui::ComboBox::from_id_salt("rolling_function")
+                        .selected_text(self.request.function.name())
+                        .show_ui(ui, |ui| {
+                            for function in Rolling::ALL {
+                                ui.selectable_value(&mut self.request.function, function, function.name());
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Window:");
+                    ui.add_enabled(
+                        self.request.function != Rolling::CumulativeSum,
+                        egui::DragValue::new(&mut self.request.window).range(1..=100_000).suffix(" rows"),
+                    );
+                    ui.end_row();
+
+                    ui.label("Order by:");
+                    egui::ComboBox::from_id_salt("rolling_order")
+                        .selected_text(self.request.order_by.map_or("Row order", header))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.request.order_by, None, "Row order");
+                            for (c, name) in self.headers.iter().enumerate() {
+                                ui.selectable_value(&mut self.request.order_by, Some(c), name);
+                            }
+                        });
+                    ui.end_row();
+                });
+                ui.label(egui::RichText::new("Rows before the first full window stay empty, as do non-numeric values.").weak().small());
+
+                ui.add_space(6.0);
+                if ui.button("Add Column").clicked() {
+                    confirmed = Some(self.request);
+                }
+            });
+        if confirmed.is_some() {
+            *open = false;
+        }
+        confirmed
+    }
+}
@@ -0,0 +1,95 @@
+use eframe::egui;
+use crate::backend::formatting::RowColorRule;
+use crate::backend::scripting::{variable_name, RowExpression};
+
+/// Edits the rules that color whole rows, e.g. failed jobs in red
+pub struct RowColorsWindow {
+    rules: Vec<RowColorRule>,
+    headers: Vec<String>,
+}
+
+impl Default for RowColorsWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RowColorsWindow {
+    pub fn new() -> Self {
+        Self { rules: Vec::new(), headers: Vec::new() }
+    }
+
+    pub fn load(&mut self, rules: Vec<RowColorRule>, headers: Vec<String>) {
+        self.rules = rules;
+        self.headers = headers;
+    }
+
+    /// Returns the rules once the user applies them
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool) -> Option<Vec<RowColorRule>> {
+        let mut confirmed = None;
+        egui::Window::new("Row Colors")
+            .open(open)
+            .resizable(true)
+            .default_width(480.0)
+            .show(ctx, |ui| {
+                ui.label(egui::RichText::new("Rows are colored by the first rule they meet, e.g. status == \"failed\" or amount > 1000.").weak().small());
+                let names: Vec<String> = self.headers.iter().map(|h| variable_name(h)).collect();
+                ui.label(egui::RichText::new(format!("Columns: {}", names.join(", "))).weak().small());
+                ui.separator();
+
+                let mut remove = None;
+                let mut swap = None;
+                let mut valid = true;
+                let count = self.rules.len();
+                egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                    for (i, rule) in self.rules.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut rule.enabled, "");
+                            let [r, g, b, a] = rule.color;
+                            let mut color = egui::Color32::from_rgba_unmultiplied(r, g, b, a);
+                            if egui::color_picker::color_edit_button_srgba(ui, &mut color, egui::color_picker::Alpha::OnlyBlend).changed() {
+                                rule.color = color.to_srgba_unmultiplied();
+                            }
+                            ui.add(egui::TextEdit::singleline(&mut rule.expression)
+                                .hint_text("status == \"failed\"")
+                                .font(egui::TextStyle::Monospace)
+                                .desired_width(280.0));
+                            if ui.add_enabled(i > 0, egui::Button::new("⬆").small()).on_hover_text("Check this rule earlier").clicked() {
+                                swap = Some(i - 1);
+                            }
+                            if ui.add_enabled(i + 1 < count, egui::Button::new("⬇").small()).on_hover_text("Check this rule later").clicked() {
+                                swap = Some(i);
+                            }
+                            if ui.small_button("✖").on_hover_text("Remove rule").clicked() {
+                                remove = Some(i);
+                            }
+                        });
+                        if rule.enabled
+                            && let Err(e) = RowExpression::compile(&rule.expression, &self.headers)
+                        {
+                            valid = false;
+                            ui.colored_label(ui.visuals().error_fg_color, e.to_string());
+                        }
+                    }
+                });
+                if let Some(i) = swap {
+                    self.rules.swap(i, i + 1);
+                }
+                if let Some(i) = remove {
+                    self.rules.remove(i);
+                }
+                if ui.button("＋ Add Rule").clicked() {
+                    self.rules.push(RowColorRule::default());
+                }
+
+                ui.separator();
+                if ui.add_enabled(valid, egui::Button::new("Apply")).clicked() {
+                    confirmed = Some(self.rules.clone());
+                }
+            });
+        if confirmed.is_some() {
+            *open = false;
+        }
+        confirmed
+    }
+}
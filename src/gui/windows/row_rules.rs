@@ -0,0 +1,77 @@
+use eframe::egui;
+use crate::backend::schema::RowRule;
+use crate::backend::scripting::{variable_name, RowExpression};
+
+/// Edits the schema's row rules: expressions every row has to satisfy
+pub struct RowRulesWindow {
+    rules: Vec<RowRule>,
+    headers: Vec<String>,
+}
+
+impl Default for RowRulesWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RowRulesWindow {
+    pub fn new() -> Self {
+        Self { rules: Vec::new(), headers: Vec::new() }
+    }
+
+    pub fn load(&mut self, rules: Vec<RowRule>, headers: Vec<String>) {
+        self.rules = rules;
+        self.headers = headers;
+    }
+
+    /// Returns the rules once the user applies them
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool) -> Option<Vec<RowRule>> {
+        let mut confirmed = None;
+        egui::Window::new("Row Rules")
+            .open(open)
+            .resizable(true)
+            .default_width(520.0)
+            .show(ctx, |ui| {
+                ui.label(egui::RichText::new("Each rule is an expression every row must make true, e.g. end_date >= start_date or total == price * qty.").weak().small());
+                let names: Vec<String> = self.headers.iter().map(|h| variable_name(h)).collect();
+                ui.label(egui::RichText::new(format!("Columns: {}", names.join(", "))).weak().small());
+                ui.separator();
+
+                let mut remove = None;
+                let mut valid = true;
+                egui::ScrollArea::vertical().max_height(320.0).show(ui, |ui| {
+                    for (i, rule) in self.rules.iter_mut().enumerate() {
+                        ui.horizontal(|ui| {
+                            ui.add(egui::TextEdit::singleline(&mut rule.name).hint_text("Name").desired_width(120.0));
+                            ui.add(egui::TextEdit::singleline(&mut rule.expression)
+                                .hint_text("end_date >= start_date")
+                                .font(egui::TextStyle::Monospace)
+                                .desired_width(300.0));
+                            if ui.small_button("✖").on_hover_text("Remove rule").clicked() {
+                                remove = Some(i);
+                            }
+                        });
+                        if let Err(e) = RowExpression::compile(&rule.expression, &self.headers) {
+                            valid = false;
+                            ui.colored_label(ui.visuals().error_fg_color, e.to_string());
+                        }
+                    }
+                });
+                if let Some(i) = remove {
+                    self.rules.remove(i);
+                }
+                if ui.button("＋ Add Rule").clicked() {
+                    self.rules.push(RowRule::default());
+                }
+
+                ui.separator();
+                if ui.add_enabled(valid, egui::Button::new("Apply & Validate")).clicked() {
+                    confirmed = Some(self.rules.clone());
+                }
+            });
+        if confirmed.is_some() {
+            *open = false;
+        }
+        confirmed
+    }
+}
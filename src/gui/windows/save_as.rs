@@ -0,0 +1,116 @@
+use eframe::egui;
+use crate::backend::writer::{CsvWriteOptions, EscapeStyle, QuoteStyle};
+
+/// Delimiters offered in the Save As dialog
+pub const DELIMITERS: &[(u8, &str)] = &[
+    (b',', "Comma (,)"),
+    (b';', "Semicolon (;)"),
+    (b'\t', "Tab"),
+    (b'|', "Pipe (|)"),
+];
+
+pub struct SaveAsWindow {
+    options: CsvWriteOptions,
+    /// Whether the source is a file whose records are re-split on `source_delimiter`
+    converting: bool,
+}
+
+impl Default for SaveAsWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SaveAsWindow {
+    pub fn new() -> Self {
+        Self {
+            options: CsvWriteOptions::default(),
+            converting: false,
+        }
+    }
+
+    /// `source` is how a file-backed document is written (its detected
+    /// delimiter and line endings), None for a grid
+    pub fn load(&mut self, source: Option<CsvWriteOptions>) {
+        self.converting = source.is_some();
+        self.options.source_delimiter = source.map_or(b',', |s| s.source_delimiter);
+        if let Some(source) = source {
+            self.options.crlf = source.crlf;
+            self.options.trailing_newline = source.trailing_newline;
+        }
+    }
+
+    /// Returns the chosen options once the user confirms
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool) -> Option<CsvWriteOptions> {
+        let mut confirmed = None;
+        egui::Window::new("Save As CSV")
+            .open(open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("save_as_grid").num_columns(2).show(ui, |ui| {
+                    if self.converting {
+                        ui.label("Source delimiter:");
+                        delimiter_combo(ui, "save_as_source_delimiter", &mut self.options.source_delimiter);
+                        ui.end_row();
+                    }
+
+                    ui.label("Delimiter:");
+                    delimiter_combo(ui, "save_as_delimiter", &mut self.options.delimiter);
+                    ui.end_row();
+
+                    ui.label("Quoting:");
+                    egui::ComboBox::from_id_salt("save_as_quoting")
+                        .selected_text(self.options.quote_style.name())
+                        .show_ui(ui, |ui| {
+                            for style in QuoteStyle::all() {
+                                ui.selectable_value(&mut self.options.quote_style, *style, style.name());
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Escape quotes:");
+                    egui::ComboBox::from_id_salt("save_as_escape")
+                        .selected_text(self.options.escape.name())
+                        .show_ui(ui, |ui| {
+                            for style in EscapeStyle::all() {
+                                ui.selectable_value(&mut self.options.escape, *style, style.name());
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("");
+                    ui.checkbox(&mut self.options.trailing_newline, "Newline after the last row");
+                    ui.end_row();
+
+                    ui.label("");
+                    ui.checkbox(&mut self.options.crlf, "Windows line endings (CRLF)");
+                    ui.end_row();
+                });
+
+                ui.label(egui::RichText::new("Large files are streamed to disk; untouched rows are copied as-is when the dialect is unchanged.").weak().small());
+                ui.add_space(6.0);
+                if ui.button("Choose File & Save").clicked() {
+                    confirmed = Some(self.options);
+                }
+            });
+        if confirmed.is_some() {
+            *open = false;
+        }
+        confirmed
+    }
+}
+
+pub fn delimiter_combo(ui: &mut egui::Ui, id: &str, delimiter: &mut u8) {
+    let current = DELIMITERS.iter()
+        .find(|(d, _)| d == delimiter)
+        .map(|(_, name)| *name)
+        .unwrap_or("Custom");
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(current)
+        .show_ui(ui, |ui| {
+            for (value, name) in DELIMITERS {
+                ui.selectable_value(delimiter, *value, *name);
+            }
+        });
+}
@@ -0,0 +1,139 @@
+use eframe::egui;
+use crate::backend::analysis::{ColumnAnalyzer, ColumnSample};
+
+/// Columns a matrix can hold before the cells get too small to read
+pub const MAX_MATRIX_COLUMNS: usize = 6;
+
+/// Rows sampled for the matrix, spread over the whole document
+pub const MATRIX_SAMPLE_ROWS: usize = 2_000;
+
+const CELL_SIZE: f32 = 130.0;
+const DIAGONAL_BINS: usize = 15;
+
+/// Small multiples of every pair of the picked numeric columns, with a
+/// histogram of each column on the diagonal
+pub struct ScatterMatrixWindow {
+    headers: Vec<String>,
+    selected: Vec<usize>,
+    sample: Option<ColumnSample>,
+}
+
+impl Default for ScatterMatrixWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScatterMatrixWindow {
+    pub fn new() -> Self {
+        Self { headers: Vec::new(), selected: Vec::new(), sample: None }
+    }
+
+    /// Pick from `headers`, starting with the first few of `numeric`; the
+    /// last pick is kept if the columns are the same
+    pub fn load(&mut self, headers: Vec<String>, numeric: &[usize]) {
+        if headers != self.headers {
+            self.selected = numeric.iter().copied().take(MAX_MATRIX_COLUMNS).collect();
+            self.headers = headers;
+        }
+        self.sample = None;
+    }
+
+    pub fn set_sample(&mut self, sample: ColumnSample) {
+        self.sample = Some(sample);
+    }
+
+    /// Returns the columns to sample when Plot is clicked
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool, running: bool) -> Option<Vec<usize>> {
+        let mut request = None;
+        egui::Window::new("Scatter Matrix")
+            .open(open)
+            .resizable(true)
+            .default_size([640.0, 560.0])
+            .show(ctx, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label(format!("Columns (up to {}):", MAX_MATRIX_COLUMNS));
+                    for (c, header) in self.headers.iter().enumerate() {
+                        let mut checked = self.selected.contains(&c);
+                        let full = self.selected.len() >= MAX_MATRIX_COLUMNS;
+                        if ui.add_enabled(checked || !full, egui::Checkbox::new(&mut checked, header)).changed() {
+                            if checked {
+                                self.selected.push(c);
+                            } else {
+                                self.selected.retain(|&s| s != c);
+                            }
+                        }
+                    }
+                });
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(self.selected.len() >= 2 && !running, egui::Button::new("Plot")).clicked() {
+                        let mut columns = self.selected.clone();
+                        columns.sort_unstable();
+                        request = Some(columns);
+                    }
+                    if running {
+                        ui.spinner();
+                    }
+                    ui.label(egui::RichText::new(format!("Samples up to {} rows spread over the file", MATRIX_SAMPLE_ROWS)).weak());
+                });
+
+                let Some(ref sample) = self.sample else {
+                    return;
+                };
+                ui.separator();
+                let header = |c: usize| self.headers.get(c).map_or("", String::as_str);
+                egui::ScrollArea::both().auto_shrink([false, false]).show(ui, |ui| {
+                    egui::Grid::new("scatter_matrix").spacing([4.0, 4.0]).show(ui, |ui| {
+                        ui.label("");
+                        for &col in &sample.columns {
+                            ui.strong(header(col));
+                        }
+                        ui.end_row();
+                        for (row, &y_col) in sample.columns.iter().enumerate() {
+                            ui.strong(header(y_col));
+                            for col in 0..sample.columns.len() {
+                                matrix_cell(ui, sample, row, col);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+        request
+    }
+}
+
+/// One plot of the matrix: column `col` against column `row`, or the
+/// column's histogram on the diagonal
+fn matrix_cell(ui: &mut egui::Ui, sample: &ColumnSample, row: usize, col: usize) {
+    let plot = egui_plot::Plot::new(("scatter_matrix_cell", row, col))
+        .width(CELL_SIZE)
+        .height(CELL_SIZE)
+        .show_axes(false)
+        .allow_drag(false)
+        .allow_zoom(false)
+        .allow_scroll(false)
+        .allow_boxed_zoom(false);
+    let response = if row == col {
+        let values: Vec<f64> = sample.values[col].iter().flatten().copied().collect();
+        let histogram = ColumnAnalyzer::histogram(&values, DIAGONAL_BINS);
+        let bars = histogram.counts.iter()
+            .enumerate()
+            .map(|(i, &count)| egui_plot::Bar::new(histogram.center(i), count as f64).width(histogram.width))
+            .collect();
+        plot.show(ui, |plot_ui| plot_ui.bar_chart(egui_plot::BarChart::new("Values", bars))).response
+    } else {
+        let points = sample.pairs(col, row);
+        plot.show(ui, |plot_ui| {
+            plot_ui.points(egui_plot::Points::new("Rows", egui_plot::PlotPoints::new(points)).radius(1.5));
+        })
+        .response
+    };
+    if row != col {
+        let text = match sample.correlation(col, row) {
+            Some(r) => format!("r = {:.2}", r),
+            None => "No correlation (too few numbers, or a constant column)".to_string(),
+        };
+        response.on_hover_text(text);
+    }
+}
@@ -0,0 +1,60 @@
+use eframe::egui;
+use crate::backend::schema::SchemaIssue;
+
+/// Lists schema validation problems; clicking one jumps to the cell
+#[derive(Default)]
+pub struct SchemaIssuesWindow;
+
+impl SchemaIssuesWindow {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Returns the (row, column) of an issue the user clicked
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        open: &mut bool,
+        issues: &[SchemaIssue],
+        headers: &[String],
+    ) -> Option<(usize, usize)> {
+        let mut jump = None;
+        egui::Window::new("Schema Issues")
+            .open(open)
+            .resizable(true)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                if issues.is_empty() {
+                    ui.label(egui::RichText::new("✔ The file matches the schema.").color(egui::Color32::from_rgb(100, 200, 100)));
+                    return;
+                }
+                ui.label(format!("{} issue(s)", issues.len()));
+                ui.separator();
+                egui::ScrollArea::vertical().max_height(360.0).show(ui, |ui| {
+                    egui::Grid::new("schema_issues").striped(true).num_columns(3).show(ui, |ui| {
+                        for issue in issues {
+                            let location = match issue.row {
+                                Some(row) => format!("Row {}", row),
+                                None => "Header".to_string(),
+                            };
+                            let column = issue.column
+                                .map(|c| headers.get(c).cloned().unwrap_or_else(|| format!("Column {}", c + 1)))
+                                .unwrap_or_default();
+                            // Row rules span the row, so they jump to its first column
+                            if let Some(row) = issue.row {
+                                if ui.link(location).clicked() {
+                                    jump = Some((row, issue.column.unwrap_or(0)));
+                                }
+                            } else {
+                                ui.label(location);
+                            }
+                            ui.label(column);
+                            ui.label(&issue.message);
+                            ui.end_row();
+                        }
+                    });
+                });
+            });
+        jump
+    }
+}
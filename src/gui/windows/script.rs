@@ -0,0 +1,86 @@
+use eframe::egui;
+use crate::backend::scripting::SCRIPT_HELP;
+
+/// Keep the output log from growing without bound
+const MAX_OUTPUT_LINES: usize = 500;
+
+pub struct ScriptConsoleWindow {
+    code: String,
+    output: Vec<String>,
+}
+
+impl Default for ScriptConsoleWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScriptConsoleWindow {
+    pub fn new() -> Self {
+        Self {
+            code: "for r in 0..rows() {\n    let v = get(r, 0);\n    set(r, 0, v.to_upper());\n}\n".to_string(),
+            output: Vec::new(),
+        }
+    }
+
+    /// Append lines to the output log
+    pub fn log(&mut self, lines: impl IntoIterator<Item = String>) {
+        self.output.extend(lines);
+        if self.output.len() > MAX_OUTPUT_LINES {
+            self.output.drain(..self.output.len() - MAX_OUTPUT_LINES);
+        }
+    }
+
+    /// Returns the script to run when the user presses Run (or Ctrl+Enter)
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool, running: bool) -> Option<String> {
+        let mut run = None;
+        egui::Window::new("Script Console")
+            .open(open)
+            .default_width(520.0)
+            .show(ctx, |ui| {
+                ui.label(egui::RichText::new(SCRIPT_HELP).weak().small());
+                ui.separator();
+
+                let editor = egui::ScrollArea::vertical()
+                    .id_salt("script_code")
+                    .max_height(220.0)
+                    .show(ui, |ui| {
+                        ui.add(
+                            egui::TextEdit::multiline(&mut self.code)
+                                .code_editor()
+                                .desired_rows(10)
+                                .desired_width(f32::INFINITY),
+                        )
+                    })
+                    .inner;
+                let shortcut = editor.has_focus()
+                    && ui.input(|i| i.modifiers.command && i.key_pressed(egui::Key::Enter));
+
+                ui.horizontal(|ui| {
+                    let clicked = ui.add_enabled(!running, egui::Button::new("▶ Run")).clicked();
+                    if (clicked || shortcut) && !running {
+                        run = Some(self.code.clone());
+                    }
+                    if running {
+                        ui.spinner();
+                    }
+                    if ui.button("Clear Output").clicked() {
+                        self.output.clear();
+                    }
+                    ui.label(egui::RichText::new("Changes are applied as one undo step").weak().small());
+                });
+
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .id_salt("script_output")
+                    .max_height(160.0)
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for line in &self.output {
+                            ui.label(egui::RichText::new(line).monospace());
+                        }
+                    });
+            });
+        run
+    }
+}
@@ -1,10 +1,12 @@
 use eframe::egui;
-use crate::backend::settings::{Settings, Theme, KeybindingMode, KeyCombo};
+use crate::backend::settings::{EnterDirection, Settings, Theme, KeybindingMode, KeyCombo};
 
 
 pub struct SettingsWindow {
     selected_tab: SettingsTab,
     key_capture: Option<&'static str>, 
+    // UI scale being dragged; applied on release so the slider doesn't move under the pointer
+    ui_scale_draft: Option<f32>,
 }
 
 #[derive(PartialEq, Clone, Copy, Debug)]
@@ -14,14 +16,26 @@ enum SettingsTab {
     Theme,
 }
 
+impl Default for SettingsWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SettingsWindow {
     pub fn new() -> Self {
         Self {
             selected_tab: SettingsTab::General,
             key_capture: None,
+            ui_scale_draft: None,
         }
     }
 
+    /// True while waiting for the user to press a new key for a binding
+    pub fn is_capturing_key(&self) -> bool {
+        self.key_capture.is_some()
+    }
+
     pub fn show(&mut self, ctx: &egui::Context, open: &mut bool, settings: &mut Settings) {
         egui::Window::new("Settings")
             .open(open)
@@ -73,12 +87,49 @@ impl SettingsWindow {
         ui.heading("Appearance");
         ui.add(egui::Slider::new(&mut settings.font_size, 10.0..=24.0).text("Font Size"));
         ui.add(egui::Slider::new(&mut settings.row_height, 20.0..=60.0).text("Row Height"));
+
+        let mut fixed_scale = settings.ui_scale.is_some();
+        if ui.checkbox(&mut fixed_scale, "Fixed UI Scale (overrides monitor scaling)").changed() {
+            if fixed_scale {
+                settings.ui_scale = Some(ui.ctx().pixels_per_point());
+            } else {
+                settings.ui_scale = None;
+                ui.ctx().set_zoom_factor(1.0);
+            }
+        }
+        if let Some(scale) = settings.ui_scale {
+            let draft = self.ui_scale_draft.get_or_insert(scale);
+            let response = ui.add(egui::Slider::new(draft, 0.5..=3.0).step_by(0.05).text("UI Scale"));
+            if response.drag_stopped() || (response.changed() && !response.dragged()) {
+                settings.ui_scale = Some(*draft);
+            }
+            if !response.dragged() && !response.changed() {
+                self.ui_scale_draft = None;
+            }
+        }
         
         ui.separator();
         ui.heading("Behavior");
         ui.checkbox(&mut settings.use_edit_modal, "Use Popup for Editing");
+        ui.checkbox(&mut settings.use_type_to_edit, "Start Editing When Typing on a Cell");
+        ui.horizontal(|ui| {
+            ui.label("After Enter, move:");
+            for direction in [EnterDirection::Down, EnterDirection::Right, EnterDirection::Stay] {
+                ui.selectable_value(&mut settings.enter_direction, direction, direction.name());
+            }
+        });
         ui.checkbox(&mut settings.auto_beautify_json, "Auto-beautify JSON in Popup");
         ui.checkbox(&mut settings.show_profile_hud, "Show Column Profile HUD (Ctrl+B)");
+        ui.checkbox(&mut settings.show_type_badges, "Show Column Type Badges");
+        ui.checkbox(&mut settings.show_sparklines, "Show Sparklines in Numeric Column Headers");
+        ui.checkbox(&mut settings.show_null_markers, "Highlight Empty/Null Cells (∅)");
+        ui.checkbox(&mut settings.show_perf_overlay, "Show Performance Overlay (F12)");
+        ui.horizontal(|ui| {
+            ui.label("Memory Budget:");
+            ui.add(egui::DragValue::new(&mut settings.memory_budget_mb).range(128..=65536).speed(16).suffix(" MB"));
+        })
+        .response
+        .on_hover_text("Loading a whole file into memory asks first when it would need more than this");
 
         ui.separator();
         ui.heading("Recent Files");
@@ -114,6 +165,26 @@ impl SettingsWindow {
             Self::key_binder(ui, ctx, key_capture, "Save", "save", &mut keymap.save);
             Self::key_binder(ui, ctx, key_capture, "Toggle HUD", "toggle_hud", &mut keymap.toggle_hud);
             ui.end_row();
+
+            Self::key_binder(ui, ctx, key_capture, "New File", "new_file", &mut keymap.new_file);
+            Self::key_binder(ui, ctx, key_capture, "Open File", "open_file", &mut keymap.open_file);
+            Self::key_binder(ui, ctx, key_capture, "Print", "print", &mut keymap.print);
+            Self::key_binder(ui, ctx, key_capture, "Settings", "open_settings", &mut keymap.open_settings);
+            ui.end_row();
+
+            Self::key_binder(ui, ctx, key_capture, "Edit Cell", "edit_cell", &mut keymap.edit_cell);
+            Self::key_binder(ui, ctx, key_capture, "Find Duplicates", "find_duplicates", &mut keymap.find_duplicates);
+            Self::key_binder(ui, ctx, key_capture, "Quick Calc", "quick_calc", &mut keymap.quick_calc);
+            ui.end_row();
+
+            Self::key_binder(ui, ctx, key_capture, "Performance Overlay", "toggle_perf_overlay", &mut keymap.toggle_perf_overlay);
+            ui.end_row();
+
+            Self::key_binder(ui, ctx, key_capture, "Narrow Column", "shrink_column", &mut keymap.shrink_column);
+            Self::key_binder(ui, ctx, key_capture, "Widen Column", "grow_column", &mut keymap.grow_column);
+            Self::key_binder(ui, ctx, key_capture, "Shorter Rows", "shrink_rows", &mut keymap.shrink_rows);
+            Self::key_binder(ui, ctx, key_capture, "Taller Rows", "grow_rows", &mut keymap.grow_rows);
+            ui.end_row();
         });
     }
     
@@ -188,6 +259,27 @@ impl SettingsWindow {
         ui.end_row();
     }
 
+    /// A few table rows painted with the stripe color, updated as it's edited
+    fn stripe_preview(ui: &mut egui::Ui, stripe: egui::Color32) {
+        ui.add_space(4.0);
+        egui::Frame::group(ui.style()).show(ui, |ui| {
+            let font = egui::TextStyle::Body.resolve(ui.style());
+            for i in 0..5 {
+                let (rect, _) = ui.allocate_exact_size(egui::vec2(240.0, 20.0), egui::Sense::hover());
+                if i % 2 == 1 {
+                    ui.painter().rect_filled(rect, 0.0, stripe);
+                }
+                ui.painter().text(
+                    rect.left_center() + egui::vec2(6.0, 0.0),
+                    egui::Align2::LEFT_CENTER,
+                    format!("Row {}    {}", i + 1, 1250 * (i + 1)),
+                    font.clone(),
+                    ui.visuals().text_color(),
+                );
+            }
+        });
+    }
+
     fn show_theme(&mut self, ui: &mut egui::Ui, settings: &mut Settings) {
          ui.heading("Built-in Themes");
          egui::ComboBox::from_id_salt("theme_selector")
@@ -214,13 +306,11 @@ impl SettingsWindow {
             
         ui.separator();
         ui.heading("Workspace Colors");
+        let theme_stripe = ui.visuals().faint_bg_color;
         let mut stripe_enabled = settings.stripe_color.is_some();
-        if ui.checkbox(&mut stripe_enabled, "Enable Striped Rows").changed() {
-            if stripe_enabled {
-                settings.stripe_color = Some([40, 40, 50]); // Default dark stripe
-            } else {
-                settings.stripe_color = None;
-            }
+        if ui.checkbox(&mut stripe_enabled, "Custom Stripe Color").changed() {
+            // Start from the theme's own stripe so the change is a tweak, not a jump
+            settings.stripe_color = stripe_enabled.then(|| [theme_stripe.r(), theme_stripe.g(), theme_stripe.b()]);
         }
         
         if let Some(ref mut color) = settings.stripe_color {
@@ -232,5 +322,34 @@ impl SettingsWindow {
                  }
              });
         }
+
+        let stripe = settings.stripe_color
+            .map(|[r, g, b]| egui::Color32::from_rgb(r, g, b))
+            .unwrap_or(theme_stripe);
+        Self::stripe_preview(ui, stripe);
+
+        ui.separator();
+        ui.heading("Grid Lines");
+        ui.checkbox(&mut settings.show_grid_lines, "Show Lines Between Cells");
+        if settings.show_grid_lines {
+            let theme_line = ui.visuals().widgets.noninteractive.bg_stroke.color;
+            let mut custom_color = settings.grid_line_color.is_some();
+            ui.horizontal(|ui| {
+                if ui.checkbox(&mut custom_color, "Custom Color").changed() {
+                    settings.grid_line_color = custom_color.then(|| [theme_line.r(), theme_line.g(), theme_line.b()]);
+                }
+                if let Some(ref mut color) = settings.grid_line_color {
+                    ui.color_edit_button_srgb(color);
+                }
+            });
+            ui.add(egui::Slider::new(&mut settings.grid_line_width, 0.5..=3.0).step_by(0.5).text("Thickness"));
+        }
+        ui.add(egui::Slider::new(&mut settings.cell_padding, 0.0..=16.0).step_by(1.0).text("Cell Padding"));
+        ui.horizontal(|ui| {
+            ui.label("Truncate cells after");
+            ui.add(egui::DragValue::new(&mut settings.max_cell_bytes).range(64..=1_048_576).speed(64).suffix(" bytes"));
+        })
+        .response
+        .on_hover_text("Longer values show a marker that opens the full text in the Cell Inspector");
     }
 }
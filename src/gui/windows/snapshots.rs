@@ -0,0 +1,149 @@
+use eframe::egui;
+use crate::backend::file_info;
+use crate::backend::snapshots::{Snapshot, SnapshotDiff};
+
+/// Changes listed under a comparison before the rest are summarized
+const SHOWN_CHANGES: usize = 100;
+
+pub enum SnapshotRequest {
+    Create(String),
+    Compare(Snapshot),
+    Restore(Snapshot),
+    Delete(Snapshot),
+}
+
+/// Saved copies of the document: take one, compare against one, or bring one back
+pub struct SnapshotsWindow {
+    snapshots: Vec<Snapshot>,
+    headers: Vec<String>,
+    label: String,
+    /// The compared snapshot's label and how the document differs from it
+    diff: Option<(String, SnapshotDiff)>,
+    /// Snapshot waiting for the go-ahead to replace a document with unsaved edits
+    confirm_restore: Option<Snapshot>,
+}
+
+impl Default for SnapshotsWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SnapshotsWindow {
+    pub fn new() -> Self {
+        Self { snapshots: Vec::new(), headers: Vec::new(), label: String::new(), diff: None, confirm_restore: None }
+    }
+
+    pub fn load(&mut self, snapshots: Vec<Snapshot>, headers: Vec<String>) {
+        self.snapshots = snapshots;
+        self.headers = headers;
+        self.diff = None;
+        self.confirm_restore = None;
+    }
+
+    pub fn set_diff(&mut self, label: String, diff: SnapshotDiff) {
+        self.diff = Some((label, diff));
+    }
+
+    /// `unsaved` says restoring would drop unsaved edits, so it asks first
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool, busy: bool, unsaved: bool) -> Option<SnapshotRequest> {
+        let mut request = None;
+        egui::Window::new("Snapshots")
+            .open(open)
+            .resizable(true)
+            .default_width(520.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.add(egui::TextEdit::singleline(&mut self.label).hint_text("Label, e.g. before cleanup"));
+                    if ui.add_enabled(!busy, egui::Button::new("📸 Create Snapshot")).clicked() {
+                        request = Some(SnapshotRequest::Create(std::mem::take(&mut self.label)));
+                    }
+                    if busy {
+                        ui.spinner();
+                    }
+                });
+                ui.separator();
+                if self.snapshots.is_empty() {
+                    ui.label(egui::RichText::new("No snapshots of this file yet").weak());
+                }
+                egui::ScrollArea::vertical().id_salt("snapshot_list").max_height(220.0).show(ui, |ui| {
+                    egui::Grid::new("snapshots").striped(true).num_columns(4).show(ui, |ui| {
+                        for snapshot in &self.snapshots {
+                            ui.label(snapshot.created_text());
+                            ui.label(if snapshot.label.is_empty() { "(no label)" } else { &snapshot.label });
+                            ui.label(egui::RichText::new(format!("{} rows · {}", snapshot.rows, file_info::format_size(snapshot.size))).weak());
+                            ui.horizontal(|ui| {
+                                if ui.add_enabled(!busy, egui::Button::new("Compare").small()).clicked() {
+                                    request = Some(SnapshotRequest::Compare(snapshot.clone()));
+                                }
+                                if ui.add_enabled(!busy, egui::Button::new("Restore").small())
+                                    .on_hover_text("Bring this snapshot back; for a file on disk it opens as a new, untitled document")
+                                    .clicked()
+                                {
+                                    if unsaved {
+                                        self.confirm_restore = Some(snapshot.clone());
+                                    } else {
+                                        request = Some(SnapshotRequest::Restore(snapshot.clone()));
+                                    }
+                                }
+                                if ui.small_button("🗑").on_hover_text("Delete this snapshot").clicked() {
+                                    request = Some(SnapshotRequest::Delete(snapshot.clone()));
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+                });
+
+                if let Some(snapshot) = self.confirm_restore.clone() {
+                    ui.separator();
+                    ui.colored_label(ui.visuals().warn_fg_color, format!(
+                        "Restoring \"{}\" closes this document and its unsaved edits are lost.",
+                        if snapshot.label.is_empty() { snapshot.created_text() } else { snapshot.label.clone() }
+                    ));
+                    ui.horizontal(|ui| {
+                        if ui.button("Restore Anyway").clicked() {
+                            request = Some(SnapshotRequest::Restore(snapshot));
+                            self.confirm_restore = None;
+                        }
+                        if ui.button("Cancel").clicked() {
+                            self.confirm_restore = None;
+                        }
+                    });
+                }
+
+                let Some((ref label, ref diff)) = self.diff else {
+                    return;
+                };
+                ui.separator();
+                ui.strong(format!("Changes since \"{}\"", label));
+                diff_list(ui, &self.headers, diff);
+            });
+        request
+    }
+}
+
+/// Summary and list of the changes in `diff`, naming columns from `headers`
+pub fn diff_list(ui: &mut egui::Ui, headers: &[String], diff: &SnapshotDiff) {
+    if diff.is_empty() {
+        ui.label("No changes");
+        return;
+    }
+    ui.label(format!(
+        "{} cell(s) changed · {} row(s) added · {} row(s) removed",
+        diff.changed_cells, diff.rows_added, diff.rows_removed
+    ));
+    let header = |c: usize| headers.get(c).cloned().unwrap_or_else(|| format!("Column {}", c + 1));
+    egui::ScrollArea::vertical().id_salt("snapshot_diff").show(ui, |ui| {
+        for (c, before, after) in &diff.renamed {
+            ui.label(format!("Column {}: \"{}\" → \"{}\"", c + 1, before, after));
+        }
+        for (row, col, before, after) in diff.cells.iter().take(SHOWN_CHANGES) {
+            ui.label(format!("Row {}, {}: \"{}\" → \"{}\"", row + 1, header(*col), before, after));
+        }
+        let more = diff.changed_cells.saturating_sub(SHOWN_CHANGES.min(diff.cells.len()));
+        if more > 0 {
+            ui.label(egui::RichText::new(format!("and {} more", more)).weak());
+        }
+    });
+}
@@ -0,0 +1,76 @@
+use eframe::egui;
+use crate::backend::split::{SplitMode, SplitOptions};
+
+pub struct SplitWindow {
+    by_column: bool,
+    rows_per_file: usize,
+    column: usize,
+    header: bool,
+}
+
+impl Default for SplitWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SplitWindow {
+    pub fn new() -> Self {
+        Self {
+            by_column: false,
+            rows_per_file: 100_000,
+            column: 0,
+            header: true,
+        }
+    }
+
+    /// Returns the chosen options once the user confirms
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool, columns: &[String]) -> Option<SplitOptions> {
+        let mut confirmed = None;
+        egui::Window::new("Split File")
+            .open(open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.radio_value(&mut self.by_column, false, "Every N rows");
+                ui.add_enabled_ui(!self.by_column, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.add_space(20.0);
+                        ui.add(egui::DragValue::new(&mut self.rows_per_file).range(1..=usize::MAX).speed(100));
+                        ui.label("rows per file");
+                    });
+                });
+
+                ui.radio_value(&mut self.by_column, true, "One file per value of a column");
+                ui.add_enabled_ui(self.by_column, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.add_space(20.0);
+                        let current = columns.get(self.column).map(String::as_str).unwrap_or("");
+                        egui::ComboBox::from_id_salt("split_column")
+                            .selected_text(current)
+                            .show_ui(ui, |ui| {
+                                for (i, name) in columns.iter().enumerate() {
+                                    ui.selectable_value(&mut self.column, i, name);
+                                }
+                            });
+                    });
+                });
+
+                ui.separator();
+                ui.checkbox(&mut self.header, "First row is a header (repeat it in every file)");
+                ui.add_space(6.0);
+                if ui.button("Choose Folder & Split").clicked() {
+                    let mode = if self.by_column {
+                        SplitMode::ByColumn(self.column)
+                    } else {
+                        SplitMode::EveryRows(self.rows_per_file)
+                    };
+                    confirmed = Some(SplitOptions { mode, header: self.header });
+                }
+            });
+        if confirmed.is_some() {
+            *open = false;
+        }
+        confirmed
+    }
+}
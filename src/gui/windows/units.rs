@@ -0,0 +1,145 @@
+use eframe::egui;
+use crate::backend::units::{Unit, UnitConversion, UnitTransform};
+
+/// Sample values shown in the preview
+const PREVIEW_VALUES: usize = 8;
+
+#[derive(PartialEq, Clone, Copy)]
+enum UnitKind {
+    Size,
+    Duration,
+    Temperature,
+    Currency,
+}
+
+/// Converts a numeric column between units, previewed on its first values
+pub struct UnitConvertWindow {
+    column: usize,
+    header: String,
+    samples: Vec<String>,
+    kind: UnitKind,
+    size: (Unit, Unit),
+    duration: (Unit, Unit),
+    to_celsius: bool,
+    rate: f64,
+    decimals: usize,
+}
+
+impl Default for UnitConvertWindow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UnitConvertWindow {
+    pub fn new() -> Self {
+        Self {
+            column: 0,
+            header: String::new(),
+            samples: Vec::new(),
+            kind: UnitKind::Size,
+            size: (Unit::Bytes, Unit::Megabytes),
+            duration: (Unit::Milliseconds, Unit::Seconds),
+            to_celsius: true,
+            rate: 1.0,
+            decimals: 2,
+        }
+    }
+
+    /// Convert `column`, previewing on `samples` (its first non-empty values)
+    pub fn load(&mut self, column: usize, header: String, samples: Vec<String>) {
+        self.column = column;
+        self.header = header;
+        self.samples = samples;
+    }
+
+    fn transform(&self) -> UnitTransform {
+        let conversion = match self.kind {
+            UnitKind::Size => UnitConversion::Scale { from: self.size.0, to: self.size.1 },
+            UnitKind::Duration => UnitConversion::Scale { from: self.duration.0, to: self.duration.1 },
+            UnitKind::Temperature if self.to_celsius => UnitConversion::FahrenheitToCelsius,
+            UnitKind::Temperature => UnitConversion::CelsiusToFahrenheit,
+            UnitKind::Currency => UnitConversion::Rate(self.rate),
+        };
+        UnitTransform { conversion, decimals: self.decimals }
+    }
+
+    /// Returns the column and conversion once the user applies it
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool) -> Option<(usize, UnitTransform)> {
+        let mut confirmed = None;
+        egui::Window::new(format!("Convert Units: {}", self.header))
+            .id(egui::Id::new("unit_convert_window"))
+            .open(open)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.kind, UnitKind::Size, "Data Size");
+                    ui.selectable_value(&mut self.kind, UnitKind::Duration, "Duration");
+                    ui.selectable_value(&mut self.kind, UnitKind::Temperature, "Temperature");
+                    ui.selectable_value(&mut self.kind, UnitKind::Currency, "Currency");
+                });
+                ui.separator();
+                ui.horizontal(|ui| match self.kind {
+                    UnitKind::Size => unit_pair(ui, "size", &Unit::SIZES, &mut self.size),
+                    UnitKind::Duration => unit_pair(ui, "duration", &Unit::DURATIONS, &mut self.duration),
+                    UnitKind::Temperature => {
+                        ui.radio_value(&mut self.to_celsius, true, "°F → °C");
+                        ui.radio_value(&mut self.to_celsius, false, "°C → °F");
+                    }
+                    UnitKind::Currency => {
+                        ui.label("1 unit =");
+                        ui.add(egui::DragValue::new(&mut self.rate).speed(0.01).max_decimals(6).range(0.0..=f64::MAX));
+                        ui.label("in the new currency");
+                    }
+                });
+                if self.kind == UnitKind::Size {
+                    ui.label(egui::RichText::new("Binary units: 1 KB = 1024 B").weak().small());
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Decimals:");
+                    ui.add(egui::DragValue::new(&mut self.decimals).range(0..=10));
+                });
+
+                ui.separator();
+                let transform = self.transform();
+                egui::Grid::new("unit_preview").num_columns(3).striped(true).show(ui, |ui| {
+                    for value in self.samples.iter().take(PREVIEW_VALUES) {
+                        ui.monospace(value);
+                        ui.label("→");
+                        match transform.apply(value) {
+                            Some(converted) => ui.monospace(converted),
+                            None => ui.label(egui::RichText::new("not a number").weak()),
+                        };
+                        ui.end_row();
+                    }
+                });
+                ui.label(egui::RichText::new("Numeric values are converted in one step that Undo reverts; others are left alone.").weak().small());
+
+                ui.add_space(6.0);
+                if ui.button("Apply").clicked() {
+                    confirmed = Some((self.column, transform));
+                }
+            });
+        if confirmed.is_some() {
+            *open = false;
+        }
+        confirmed
+    }
+}
+
+fn unit_pair(ui: &mut egui::Ui, id: &str, units: &[Unit], (from, to): &mut (Unit, Unit)) {
+    for (salt, unit) in [("from", from), ("to", to)] {
+        if salt == "to" {
+            ui.label("→");
+        }
+        egui::ComboBox::from_id_salt((id, salt))
+            .width(60.0)
+            .selected_text(unit.name())
+            .show_ui(ui, |ui| {
+                for u in units {
+                    ui.selectable_value(unit, *u, u.name());
+                }
+            });
+    }
+}
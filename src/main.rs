@@ -3,7 +3,7 @@
 pub mod backend;
 pub mod gui;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use std::path::PathBuf;
 use anyhow::Result;
 use crate::backend::loader::CsvLoader;
@@ -14,15 +14,56 @@ struct Args {
     /// Path to the CSV file to open
     #[arg(short, long)]
     file: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Serve a CSV file as read-only JSON on localhost
+    Serve {
+        file: PathBuf,
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+    },
+    /// Run an export preset saved from the Export dialog
+    Export {
+        file: PathBuf,
+        #[arg(long)]
+        preset: String,
+    },
+    /// Re-run a pipeline saved from the Pipeline window on another file
+    Pipeline {
+        pipeline: PathBuf,
+        file: PathBuf,
+    },
+    /// Time the backend on a generated file, as a baseline for performance work
+    #[command(hide = true)]
+    Bench {
+        #[arg(long, default_value_t = 1_000_000)]
+        rows: usize,
+        /// Times each operation is run; min, median and mean are reported
+        #[arg(long, default_value_t = 5)]
+        samples: usize,
+    },
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
+
+    match args.command {
+        Some(Command::Serve { file, port }) => return crate::backend::server::serve(&file, port),
+        Some(Command::Export { file, preset }) => return run_export(&file, &preset),
+        Some(Command::Pipeline { pipeline, file }) => return run_pipeline(&pipeline, &file),
+        Some(Command::Bench { rows, samples }) => return run_bench(rows, samples),
+        None => {}
+    }
     
     let (loader, filename) = if let Some(path) = args.file {
          let path_str = path.to_string_lossy().to_string();
          println!("Loading file: {:?}", path);
-         let loader = CsvLoader::new(&path)?;
+         let loader = CsvLoader::open_table(&path)?;
          println!("File loaded. {} records found.", loader.total_records());
          (Some(std::sync::Arc::new(loader)), Some(path_str))
     } else {
@@ -45,3 +86,32 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+fn run_export(file: &std::path::Path, name: &str) -> Result<()> {
+    let settings = crate::backend::settings::Settings::load();
+    let Some(preset) = settings.export_preset(name) else {
+        let known: Vec<&str> = settings.export_presets.iter().map(|p| p.name.as_str()).collect();
+        anyhow::bail!("No export preset named \"{}\" (saved presets: {})", name, known.join(", "));
+    };
+    let progress = crate::backend::jobs::Progress::default();
+    for path in crate::backend::export::run_preset(preset, file, &progress)? {
+        println!("{}", path.display());
+    }
+    Ok(())
+}
+
+fn run_pipeline(pipeline: &std::path::Path, file: &std::path::Path) -> Result<()> {
+    let pipeline = crate::backend::pipeline::Pipeline::load(pipeline)?;
+    let progress = crate::backend::jobs::Progress::default();
+    for path in crate::backend::pipeline::run_pipeline_file(&pipeline, file, &progress)? {
+        println!("{}", path.display());
+    }
+    Ok(())
+}
+
+fn run_bench(rows: usize, samples: usize) -> Result<()> {
+    println!("Benchmarking on {} generated rows, {} sample(s) each", rows, samples);
+    for result in crate::backend::bench::run_in_temp_dir(rows, samples)? {
+        println!("{}", result);
+    }
+    Ok(())
+}